@@ -0,0 +1,96 @@
+//! Простой вывод для экранных дикторов (`/settings plain on`): без декоративных эмодзи и
+//! псевдографики, со словами вместо значков — «Напоминание:», «Высокий приоритет».
+//!
+//! Режим действует на всю обработку обновления: обработчик запускается в `scope`, а `t`
+//! и другие места, где собирается текст, проверяют `current`. В запросы к базе режим
+//! передаёт `Db::call`.
+
+use std::future::Future;
+
+use reventor::parse;
+
+use crate::i18n::{t, Lang};
+use crate::settings::Settings;
+
+tokio::task_local! {
+    /// Язык простого вывода в текущей обработке; `None` — обычный вывод.
+    static PLAIN: Option<Lang>;
+}
+
+/// Язык, если в текущей обработке включён простой вывод.
+pub fn current() -> Option<Lang> {
+    PLAIN.try_with(|plain| *plain).ok().flatten()
+}
+
+/// Режим вывода для получателя с этими настройками.
+pub fn of(settings: &Settings) -> Option<Lang> {
+    settings.plain.then_some(settings.lang)
+}
+
+pub async fn scope<F: Future>(plain: Option<Lang>, f: F) -> F::Output {
+    PLAIN.scope(plain, f).await
+}
+
+pub fn with<R>(plain: Option<Lang>, f: impl FnOnce() -> R) -> R {
+    PLAIN.sync_scope(plain, f)
+}
+
+/// Эмодзи, значки и псевдографика, которые диктор зачитывает названиями («колокольчик»).
+fn decorative(c: char) -> bool {
+    matches!(
+        c as u32,
+        0x1F000..=0x1FAFF // эмодзи и пиктограммы
+            | 0x2300..=0x23FF // технические значки: ⏰ ⏭ ⏸
+            | 0x2580..=0x25FF // блоки и геометрические фигуры: █ ■ ▶
+            | 0x2600..=0x27BF // разные символы и дингбаты: ☀ ✅ ⚠
+            | 0x2B00..=0x2BFF // ⭐
+            | 0x200D | 0xFE0F // склейки и вариант эмодзи
+    )
+}
+
+/// Текст без декоративных символов. Строки, где они были, заново собираются через одиночные
+/// пробелы, чтобы от значков не оставались лишние; маркеры списков `•` становятся дефисами.
+pub fn strip(text: &str) -> String {
+    text.split('\n')
+        .map(|line| {
+            if !line.chars().any(|c| decorative(c) || c == '•') {
+                return line.to_string();
+            }
+            let kept = line.chars().filter(|c| !decorative(*c)).map(|c| if c == '•' { '-' } else { c }).collect::<String>();
+            kept.split_whitespace().collect::<Vec<_>>().join(" ")
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Текст события с приоритетом словами: `!!! Сдать отчёт` → `Наивысший приоритет: Сдать отчёт`.
+pub fn spell_priority(lang: Lang, text: &str) -> String {
+    let key = match parse::priority(text) {
+        0 => return text.to_string(),
+        1 => "plain_priority_1",
+        2 => "plain_priority_2",
+        _ => "plain_priority_3",
+    };
+    format!("{}: {}", t(lang, key), text.trim_start().trim_start_matches('!').trim_start())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn strips_decorations_and_spells_priority() {
+        assert_eq!(strip("🔔 Напоминание!\n  отступ\n⭐ Избранное:\n• пункт 🙏"), "Напоминание!\n  отступ\nИзбранное:\n- пункт");
+        assert_eq!(strip("█████▋ 4"), "4");
+        assert_eq!(spell_priority(Lang::En, "!! Pay rent"), "High priority: Pay rent");
+        assert_eq!(spell_priority(Lang::Ru, "Без приоритета!"), "Без приоритета!");
+    }
+
+    #[tokio::test]
+    async fn messages_follow_the_scope() {
+        assert_eq!(t(Lang::En, "reminder"), "🔔 Reminder!\n{text}\nTime: {time}");
+        let plain = scope(Some(Lang::En), async { t(Lang::En, "reminder") }).await;
+        assert_eq!(plain, "Reminder: {text}\nTime: {time}");
+        assert_eq!(with(Some(Lang::En), || t(Lang::En, "checklist_done")), "All items are done");
+    }
+}
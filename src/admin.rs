@@ -0,0 +1,126 @@
+use teloxide::prelude::*;
+use teloxide::types::{InputFile, User};
+
+use crate::backup;
+use crate::blocklist;
+use crate::chunks;
+use crate::humanize;
+use crate::config;
+use crate::i18n::{t, tf, Lang};
+use crate::maintenance;
+use crate::metrics;
+use crate::referrals;
+use crate::render;
+use crate::{DatabaseError, Db};
+
+/// Сколько самых частых функций помещается на диаграмму использования.
+const USAGE_CHART_BARS: usize = 12;
+
+pub fn is_admin(msg: &Message) -> bool {
+    msg.from().is_some_and(is_admin_user)
+}
+
+pub fn is_admin_user(user: &User) -> bool {
+    config::get().admin_ids.contains(&(user.id.0 as i64))
+}
+
+/// `/admin backup now`, `/admin usage [дни]`, `/admin referrals [дни]`, `/admin maintenance on|off`, `/admin block <id>`,
+/// `/admin reload` — служебные команды для администраторов бота.
+pub async fn handle_admin_command(bot: &Bot, msg: &Message, db: &Db, args: &str, lang: Lang) -> ResponseResult<()> {
+    if !is_admin(msg) {
+        bot.send_message(msg.chat.id, t(lang, "admin_only")).await?;
+        return Ok(());
+    }
+
+    // Текст объявления передаётся как есть, поэтому режим обслуживания разбирается отдельно
+    if let Some(rest) = args.strip_prefix("maintenance") {
+        return maintenance::handle_admin(bot, msg, db, rest.trim(), lang).await;
+    }
+    // Причина блокировки тоже произвольный текст
+    let (command, rest) = args.split_once(char::is_whitespace).map_or((args, ""), |(command, rest)| (command, rest.trim()));
+    if matches!(command, "block" | "unblock" | "blocked") {
+        return blocklist::handle_admin(bot, msg, db, command, rest, lang).await;
+    }
+
+    match args.split_whitespace().collect::<Vec<_>>().as_slice() {
+        ["reload"] => {
+            let response = match config::reload() {
+                Ok(_) => t(lang, "admin_reloaded"),
+                Err(e) => {
+                    log::error!("Failed to reload configuration: {}", e);
+                    tf(lang, "admin_reload_failed", &[("error", &e)])
+                }
+            };
+            bot.send_message(msg.chat.id, response).await?;
+        }
+        ["backup", "now"] => {
+            let response = match backup::run(db).await {
+                Ok(path) => tf(lang, "admin_backup_done", &[("path", &path.display())]),
+                Err(e) => {
+                    log::error!("Manual backup failed: {}", e);
+                    tf(lang, "admin_backup_failed", &[("error", &e)])
+                }
+            };
+            chunks::send(bot, msg.chat.id, response).await?;
+        }
+        ["usage", rest @ ..] => {
+            let days = match rest {
+                [] => Some(metrics::DEFAULT_DAYS),
+                [days] => days.parse().ok().filter(|days| *days > 0),
+                _ => None,
+            };
+            let Some(days) = days else {
+                bot.send_message(msg.chat.id, t(lang, "admin_usage")).await?;
+                return Ok(());
+            };
+            let (usage, lag) = db.call(move |conn| Ok((metrics::usage(conn, days)?, metrics::lag(conn, days)?)))
+                .await
+                .map_err(DatabaseError)?;
+            let mut response = if usage.is_empty() {
+                tf(lang, "admin_usage_empty", &[("days", &humanize::days(lang, days.into()))])
+            } else {
+                let lines = usage.iter().map(|(feature, count)| format!("{} — {}", feature, count)).collect::<Vec<_>>();
+                tf(lang, "admin_usage_report", &[("days", &humanize::days(lang, days.into())), ("usage", &lines.join("\n"))])
+            };
+            if let Some((count, average, max)) = lag {
+                response.push_str("\n\n");
+                response.push_str(&tf(lang, "admin_usage_lag", &[("count", &count), ("average", &average), ("max", &max)]));
+            }
+            chunks::send(bot, msg.chat.id, response).await?;
+            let (features, counts): (Vec<_>, Vec<_>) = usage.iter().take(USAGE_CHART_BARS).map(|(feature, count)| (feature.clone(), *count as u64)).unzip();
+            let title = tf(lang, "admin_usage_chart", &[("days", &humanize::days(lang, days.into()))]);
+            if let Some(png) = render::bar_chart(&title, &features, &[("", counts)]) {
+                bot.send_photo(msg.chat.id, InputFile::memory(png).file_name("usage.png")).await?;
+            }
+        }
+        ["referrals", rest @ ..] => {
+            let days = match rest {
+                [] => Some(None),
+                [days] => days.parse().ok().filter(|days| *days > 0).map(Some),
+                _ => None,
+            };
+            let Some(days) = days else {
+                bot.send_message(msg.chat.id, t(lang, "admin_usage")).await?;
+                return Ok(());
+            };
+            let report = db.call(move |conn| referrals::report(conn, days)).await.map_err(DatabaseError)?;
+            let period = days.map_or_else(|| t(lang, "admin_referrals_all_time"), |days| {
+                tf(lang, "admin_referrals_days", &[("days", &humanize::days(lang, days.into()))])
+            });
+            let response = if report.is_empty() {
+                tf(lang, "admin_referrals_empty", &[("period", &period)])
+            } else {
+                let lines = report
+                    .iter()
+                    .map(|(code, signups, active)| tf(lang, "admin_referral_line", &[("code", code), ("signups", signups), ("active", active)]))
+                    .collect::<Vec<_>>();
+                tf(lang, "admin_referrals_report", &[("period", &period), ("codes", &lines.join("\n"))])
+            };
+            chunks::send(bot, msg.chat.id, response).await?;
+        }
+        _ => {
+            bot.send_message(msg.chat.id, t(lang, "admin_usage")).await?;
+        }
+    }
+    Ok(())
+}
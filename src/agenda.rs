@@ -0,0 +1,250 @@
+use teloxide::prelude::*;
+use teloxide::types::InputFile;
+use chrono::{Datelike, Duration, NaiveDate, NaiveDateTime};
+
+use crate::categories;
+use crate::chunks;
+use crate::humanize;
+use crate::i18n::{t, tf};
+use crate::query::Period;
+use crate::render::{self, Card};
+use crate::settings::{Settings, SortOrder};
+use crate::timezone;
+use crate::{DatabaseError, Db, UserEvent, format_event_span, get_user_events, parse_event_time, resolve_date};
+
+/// Сколько событий помещается на карточку дня; остальные сводятся в «и ещё N».
+const CARD_ROWS: usize = 12;
+
+/// Заголовок дня: «Сегодня», «Завтра» или «Пн 17.03».
+fn day_header(settings: &Settings, day: NaiveDate, today: NaiveDate) -> String {
+    let lang = settings.lang;
+    if day == today {
+        return t(lang, "agenda_today");
+    }
+    if day == today + Duration::days(1) {
+        return t(lang, "agenda_tomorrow");
+    }
+
+    let weekdays = t(lang, "calendar_weekdays");
+    let weekday = weekdays
+        .split_whitespace()
+        .nth(day.weekday().num_days_from_monday() as usize)
+        .unwrap_or_default();
+    if day.year() == today.year() {
+        format!("{} {}", weekday, settings.format_short_date(day))
+    } else {
+        format!("{} {}", weekday, settings.format_date(day))
+    }
+}
+
+/// Список событий, сгруппированный по дням; внутри группы показывается только время.
+fn format_agenda(settings: &Settings, events: &[UserEvent], now: NaiveDateTime) -> String {
+    let lang = settings.lang;
+    let today = now.date();
+
+    let mut dated = events
+        .iter()
+        .filter_map(|event| parse_event_time(&event.event_time).map(|time| (time, event)))
+        .collect::<Vec<_>>();
+    dated.sort_by_key(|(time, event)| (*time, event.id));
+
+    let mut sections: Vec<String> = Vec::new();
+    let mut current_day = None;
+    for (time, event) in dated {
+        if current_day != Some(time.date()) {
+            current_day = Some(time.date());
+            sections.push(format!("\n{}", day_header(settings, time.date(), today)));
+        }
+
+        let text = categories::label(event.icon.as_deref(), &event.text);
+        let mut line = format!("#{} {} - {}", event.id, format_event_span(settings, time, event.end_time.as_deref()), text);
+        if time.date() == today && time > now {
+            line.push_str(&format!(" ({})", humanize::until(lang, time - now)));
+        }
+        sections.push(line);
+    }
+
+    sections.join("\n").trim_start().to_string()
+}
+
+/// Плоский список без группировки: по времени, дате создания или приоритету.
+fn format_flat(settings: &Settings, events: &[UserEvent], order: SortOrder) -> String {
+    let mut sorted = events.iter().collect::<Vec<_>>();
+    match order {
+        SortOrder::Priority => sorted.sort_by_key(|event| (-event.priority, parse_event_time(&event.event_time), event.id)),
+        SortOrder::Time => sorted.sort_by_key(|event| (parse_event_time(&event.event_time), event.id)),
+        _ => sorted.sort_by_key(|event| event.id),
+    }
+
+    sorted
+        .iter()
+        .map(|event| {
+            let time = parse_event_time(&event.event_time).map_or_else(
+                || event.event_time.clone(),
+                |time| format!("{} {}", settings.format_date(time.date()), format_event_span(settings, time, event.end_time.as_deref())),
+            );
+            format!("#{} {} - {}", event.id, time, categories::label(event.icon.as_deref(), &event.text))
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// `/events [sort:time|created|priority]` или `/events 💼`; без аргумента используется порядок из настроек.
+/// Значок или название категории оставляет в списке только события этой категории.
+pub async fn handle_events_command(bot: &Bot, msg: &Message, db: &Db, args: &str, settings: &Settings) -> ResponseResult<()> {
+    let lang = settings.lang;
+    let (order, category) = match args.strip_prefix("sort:") {
+        Some(value) => (SortOrder::parse(value), None),
+        None if args.is_empty() => (Some(settings.sort), None),
+        None => match categories::parse(args) {
+            Some(icon) => (Some(settings.sort), Some(icon)),
+            None => (None, None),
+        },
+    };
+    let Some(order) = order else {
+        bot.send_message(msg.chat.id, t(lang, "events_usage")).await?;
+        return Ok(());
+    };
+
+    let telegram_id = msg.from().unwrap().id.0 as i64;
+    let mut events = db.call(move |conn| get_user_events(conn, telegram_id)).await.map_err(DatabaseError)?;
+    if let Some(icon) = category {
+        events.retain(|event| event.icon.as_deref() == Some(icon.as_str()));
+    }
+
+    if events.is_empty() {
+        bot.send_message(msg.chat.id, t(lang, "no_events")).await?;
+        return Ok(());
+    }
+
+    // Избранные события показываются отдельно над остальными
+    let (starred, events): (Vec<_>, Vec<_>) = events.into_iter().partition(|event| event.starred);
+    let mut agenda = match order {
+        SortOrder::Time => format_agenda(settings, &events, timezone::now_in(settings.timezone)),
+        _ => format_flat(settings, &events, order),
+    };
+    if !starred.is_empty() {
+        let starred = tf(lang, "events_starred", &[("events", &format_flat(settings, &starred, SortOrder::Time))]);
+        agenda = format!("{}\n\n{}", starred, agenda).trim_end().to_string();
+    }
+    chunks::send(bot, msg.chat.id, tf(lang, "events_header", &[("events", &agenda)])).await?;
+    Ok(())
+}
+
+/// Ответ на вопрос о расписании («что у меня в пятницу?»): события периода в формате `/events`.
+pub async fn handle_query(bot: &Bot, msg: &Message, db: &Db, period: Period, settings: &Settings) -> ResponseResult<()> {
+    let lang = settings.lang;
+    let Some(user) = msg.from() else {
+        return Ok(());
+    };
+    let telegram_id = user.id.0 as i64;
+    let events = db.call(move |conn| get_user_events(conn, telegram_id)).await.map_err(DatabaseError)?;
+    let events = events
+        .into_iter()
+        .filter(|event| parse_event_time(&event.event_time).is_some_and(|time| period.contains(time.date())))
+        .collect::<Vec<_>>();
+
+    let label = if period.start == period.end {
+        settings.format_date(period.start)
+    } else {
+        format!("{} – {}", settings.format_date(period.start), settings.format_date(period.end))
+    };
+    let response = if events.is_empty() {
+        tf(lang, "query_empty", &[("period", &label)])
+    } else {
+        let agenda = format_agenda(settings, &events, timezone::now_in(settings.timezone));
+        tf(lang, "query_header", &[("period", &label), ("events", &agenda)])
+    };
+    chunks::send(bot, msg.chat.id, response).await?;
+    Ok(())
+}
+
+/// Карточка дня для `/agenda image`: время и текст без значков категорий — шрифт картинки их не рисует.
+fn day_card(settings: &Settings, day: NaiveDate, today: NaiveDate, events: &[(NaiveDateTime, &UserEvent)]) -> Card {
+    let lang = settings.lang;
+    Card {
+        title: day_header(settings, day, today),
+        subtitle: tf(lang, "agenda_card_subtitle", &[
+            ("date", &settings.format_date(day)),
+            ("events", &humanize::count(lang, "unit_events", events.len() as i64)),
+        ]),
+        rows: events
+            .iter()
+            .take(CARD_ROWS)
+            .map(|(time, event)| (format_event_span(settings, *time, event.end_time.as_deref()), event.text.clone()))
+            .collect(),
+        footer: (events.len() > CARD_ROWS).then(|| tf(lang, "agenda_card_more", &[("count", &(events.len() - CARD_ROWS))])),
+    }
+}
+
+/// `/agenda [image] [ДД.ММ]`: события одного дня; с `image` — картинкой, которую удобно переслать в группу.
+pub async fn handle_agenda_command(bot: &Bot, msg: &Message, db: &Db, args: &str, settings: &Settings) -> ResponseResult<()> {
+    let lang = settings.lang;
+    let Some(user) = msg.from() else {
+        return Ok(());
+    };
+    let (image, day) = match args.split_once(char::is_whitespace).unwrap_or((args, "")) {
+        ("image" | "картинка", day) => (true, day.trim()),
+        _ => (false, args),
+    };
+    let now = timezone::now_in(settings.timezone);
+    let today = now.date();
+    let day = match day.to_lowercase().as_str() {
+        "" | "today" | "сегодня" => Some(today),
+        "tomorrow" | "завтра" => Some(today + Duration::days(1)),
+        value => resolve_date(value, today, settings.date_format),
+    };
+    let Some(day) = day else {
+        bot.send_message(msg.chat.id, t(lang, "agenda_usage")).await?;
+        return Ok(());
+    };
+
+    let telegram_id = user.id.0 as i64;
+    let mut events = db.call(move |conn| get_user_events(conn, telegram_id)).await.map_err(DatabaseError)?;
+    events.retain(|event| parse_event_time(&event.event_time).is_some_and(|time| time.date() == day));
+    if events.is_empty() {
+        bot.send_message(msg.chat.id, tf(lang, "agenda_day_empty", &[("date", &settings.format_date(day))])).await?;
+        return Ok(());
+    }
+
+    if image {
+        let mut dated = events
+            .iter()
+            .filter_map(|event| parse_event_time(&event.event_time).map(|time| (time, event)))
+            .collect::<Vec<_>>();
+        dated.sort_by_key(|(time, event)| (*time, event.id));
+        if let Some(png) = render::card(&day_card(settings, day, today, &dated)) {
+            bot.send_photo(msg.chat.id, InputFile::memory(png).file_name(format!("agenda-{}.png", day))).await?;
+            return Ok(());
+        }
+    }
+    chunks::send(bot, msg.chat.id, format_agenda(settings, &events, now)).await?;
+    Ok(())
+}
+
+/// `/next`: ближайшее предстоящее событие и сколько до него осталось.
+pub async fn handle_next_command(bot: &Bot, msg: &Message, db: &Db, settings: &Settings) -> ResponseResult<()> {
+    let lang = settings.lang;
+    let Some(user) = msg.from() else {
+        return Ok(());
+    };
+    let telegram_id = user.id.0 as i64;
+    let events = db.call(move |conn| get_user_events(conn, telegram_id)).await.map_err(DatabaseError)?;
+    let now = timezone::now_in(settings.timezone);
+    let next = events
+        .iter()
+        .filter_map(|event| parse_event_time(&event.event_time).map(|time| (time, event)))
+        .filter(|(time, _)| *time > now)
+        .min_by_key(|(time, event)| (*time, event.id));
+
+    let response = match next {
+        Some((time, event)) => tf(lang, "next_event", &[
+            ("time", &settings.format_datetime(time)),
+            ("text", &categories::label(event.icon.as_deref(), &event.text)),
+            ("until", &humanize::until(lang, time - now)),
+        ]),
+        None => t(lang, "no_events"),
+    };
+    chunks::send(bot, msg.chat.id, response).await?;
+    Ok(())
+}
@@ -0,0 +1,280 @@
+//! Годовщины и памятные даты: раз в год в тот же день приходит напоминание своими словами
+//! с числом прошедших лет — «5 лет с переезда». Обычные события одноразовые, поэтому
+//! годовщины живут отдельно, как привычки, и помнят год последнего напоминания.
+
+use teloxide::prelude::*;
+use chrono::{DateTime, Datelike, NaiveDate, NaiveDateTime, NaiveTime, Utc};
+use rusqlite::{Connection, params};
+
+use reventor::parse;
+
+use crate::accessibility;
+use crate::blocklist;
+use crate::chunks;
+use crate::clock::{self, Clock};
+use crate::groups;
+use crate::humanize;
+use crate::i18n::{t, tf, Lang};
+use crate::outbox::{self, Outgoing};
+use crate::settings::{self, Settings};
+use crate::timezone;
+use crate::{DatabaseError, Db, crypto, ensure_user_exists, in_transaction, parse_time_input, tenants};
+
+const DAY_FORMAT: &str = "%Y-%m-%d";
+/// Время напоминания, если в команде его нет.
+const DEFAULT_TIME: &str = "09:00";
+/// Место для числа лет во фразе.
+const YEARS: &str = "{years}";
+
+#[derive(Debug)]
+struct Anniversary {
+    id: i64,
+    tenant: String,
+    owner_id: i64,
+    chat_id: i64,
+    since: NaiveDate,
+    remind_time: String,
+    phrase: String,
+    last_sent_year: Option<i32>,
+}
+
+pub fn init_tables(conn: &Connection) -> Result<(), rusqlite::Error> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS anniversaries (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            tenant TEXT NOT NULL DEFAULT 'default',
+            user_id INTEGER NOT NULL,
+            chat_id INTEGER NOT NULL,
+            since TEXT NOT NULL,
+            remind_time TEXT NOT NULL,
+            phrase TEXT NOT NULL,
+            last_sent_year INTEGER,
+            created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            FOREIGN KEY(user_id) REFERENCES users(id) ON DELETE CASCADE
+        )",
+        [],
+    )?;
+    Ok(())
+}
+
+fn query_anniversaries<P: rusqlite::Params>(conn: &Connection, condition: &str, params: P) -> Result<Vec<Anniversary>, rusqlite::Error> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT a.id, a.tenant, u.telegram_id, a.chat_id, a.since, a.remind_time, a.phrase, a.last_sent_year
+         FROM anniversaries a
+         JOIN users u ON a.user_id = u.id
+         WHERE {}
+         ORDER BY substr(a.since, 6), a.id",
+        condition
+    ))?;
+    let anniversaries = stmt.query_map(params, |row| {
+        let since: String = row.get(4)?;
+        Ok(Anniversary {
+            id: row.get(0)?,
+            tenant: row.get(1)?,
+            owner_id: row.get(2)?,
+            chat_id: row.get(3)?,
+            since: NaiveDate::parse_from_str(&since, DAY_FORMAT).unwrap_or_default(),
+            remind_time: row.get(5)?,
+            phrase: crypto::open(row.get(6)?),
+            last_sent_year: row.get(7)?,
+        })
+    })?
+    .collect::<Result<Vec<_>, _>>()?;
+    Ok(anniversaries)
+}
+
+/// День годовщины в году `year`; 29 февраля в невисокосный год отмечается 28-го.
+fn day_in(since: NaiveDate, year: i32) -> NaiveDate {
+    NaiveDate::from_ymd_opt(year, since.month(), since.day())
+        .or_else(|| NaiveDate::from_ymd_opt(year, since.month(), since.day() - 1))
+        .unwrap_or(since)
+}
+
+/// Фраза с числом лет вместо `{years}`: «{years} с переезда» → «5 лет с переезда».
+/// Без места для числа оно дописывается в конце.
+fn render(lang: Lang, phrase: &str, years: i64) -> String {
+    let years = humanize::years(lang, years);
+    if phrase.contains(YEARS) {
+        phrase.replace(YEARS, &years)
+    } else {
+        format!("{} — {}", phrase, years)
+    }
+}
+
+/// Разбирает `ДД.ММ.ГГГГ [ЧЧ:ММ] фраза`. Год обязателен: от него считаются годы.
+fn parse_args(args: &str, settings: &Settings, today: NaiveDate) -> Option<(NaiveDate, NaiveTime, String)> {
+    let (date, rest) = args.trim().split_once(char::is_whitespace)?;
+    let with_year = date.contains('-') || date.matches(['.', '/']).count() == 2;
+    let since = parse::date(date, today, settings.locale().date_order).filter(|since| with_year && *since <= today)?;
+    let rest = rest.trim_start();
+    let (time, phrase) = match rest.split_once(char::is_whitespace).and_then(|(time, phrase)| Some((parse_time_input(time)?, phrase))) {
+        Some(found) => found,
+        None => (parse_time_input(DEFAULT_TIME)?, rest),
+    };
+    let phrase = phrase.trim();
+    (!phrase.is_empty()).then(|| (since, time, phrase.to_string()))
+}
+
+/// Сохраняет годовщину из `parse_args`.
+fn save(conn: &Connection, tenant: &str, user_id: i64, chat_id: i64, (since, remind_time, phrase): &(NaiveDate, NaiveTime, String), today: NaiveDate) -> Result<i64, rusqlite::Error> {
+    // Если годовщина сегодня и время уже прошло, первое напоминание — через год, а не сразу
+    conn.execute(
+        "INSERT INTO anniversaries (tenant, user_id, chat_id, since, remind_time, phrase, last_sent_year) VALUES (?, ?, ?, ?, ?, ?, ?)",
+        params![
+            tenant,
+            user_id,
+            chat_id,
+            since.format(DAY_FORMAT).to_string(),
+            remind_time.format("%H:%M").to_string(),
+            crypto::seal(phrase),
+            (day_in(*since, today.year()) <= today).then_some(today.year()),
+        ],
+    )?;
+    Ok(conn.last_insert_rowid())
+}
+
+fn remove(conn: &Connection, telegram_id: i64, id: i64) -> Result<bool, rusqlite::Error> {
+    let deleted = conn.execute(
+        "DELETE FROM anniversaries WHERE id = ? AND user_id = (SELECT id FROM users WHERE telegram_id = ?)",
+        params![id, telegram_id],
+    )?;
+    Ok(deleted > 0)
+}
+
+/// Ставит в очередь напоминания о сегодняшних годовщинах, время которых наступило по часам владельца.
+fn queue_due(conn: &Connection, now: NaiveDateTime, instant: DateTime<Utc>) -> Result<Vec<i64>, rusqlite::Error> {
+    let mut queued = Vec::new();
+    for anniversary in query_anniversaries(conn, blocklist::NOT_BLOCKED, [])? {
+        let Ok(remind_time) = NaiveTime::parse_from_str(&anniversary.remind_time, "%H:%M") else {
+            continue;
+        };
+        let settings = settings::resolve(conn, anniversary.owner_id, anniversary.chat_id).unwrap_or_default();
+        let local = timezone::local_at(instant, settings.timezone);
+        let year = local.year();
+        if local.date() != day_in(anniversary.since, year) || local.time() < remind_time || anniversary.last_sent_year >= Some(year) {
+            continue;
+        }
+        if settings.is_quiet(local.time()) {
+            continue;
+        }
+        let years = i64::from(year - anniversary.since.year());
+        let text = accessibility::with(accessibility::of(&settings), || {
+            tf(settings.lang, "anniversary_reminder", &[("text", &render(settings.lang, &anniversary.phrase, years))])
+        });
+        in_transaction(conn, |tx| {
+            tx.execute("UPDATE anniversaries SET last_sent_year = ? WHERE id = ?", params![year, anniversary.id])?;
+            outbox::push(tx, &Outgoing { tenant: anniversary.tenant.clone(), chat_id: anniversary.chat_id, text, reply_markup: None, reminder: None }, now)
+        })?;
+        queued.push(anniversary.id);
+    }
+    Ok(queued)
+}
+
+pub async fn send_due_anniversaries(db: &Db, clock: &dyn Clock) {
+    let (now, instant) = (clock.now_utc(), clock.now());
+    match db.call(move |conn| queue_due(conn, now, instant)).await {
+        Ok(queued) if !queued.is_empty() => log::info!("Queued anniversary reminders {:?}", queued),
+        Ok(_) => {}
+        Err(e) => log::error!("Failed to queue anniversary reminders: {}", e),
+    }
+}
+
+/// `/anniversary ДД.ММ.ГГГГ [ЧЧ:ММ] фраза` — ежегодное напоминание, `{years}` во фразе
+/// заменяется числом лет; `/anniversary remove #id`; без аргументов — список.
+pub async fn handle_anniversary_command(bot: &Bot, msg: &Message, db: &Db, args: &str, settings: &Settings) -> ResponseResult<()> {
+    let lang = settings.lang;
+    let Some(user) = msg.from() else {
+        return Ok(());
+    };
+    let (telegram_id, username, chat_id, tenant) = (user.id.0 as i64, user.username.clone(), msg.chat.id.0, tenants::name_of(bot));
+    let today = clock::SYSTEM.now_in(settings.timezone).date();
+    let args = args.trim();
+
+    let response = if args.is_empty() {
+        let anniversaries = db.call(move |conn| query_anniversaries(conn, "u.telegram_id = ?", params![telegram_id]))
+            .await
+            .map_err(DatabaseError)?;
+        if anniversaries.is_empty() {
+            t(lang, "anniversaries_empty")
+        } else {
+            let lines = anniversaries
+                .iter()
+                .map(|anniversary| {
+                    let next = day_in(anniversary.since, today.year());
+                    let next = if next < today { day_in(anniversary.since, today.year() + 1) } else { next };
+                    tf(lang, "anniversary_line", &[
+                        ("id", &anniversary.id),
+                        ("date", &settings.format_date(next)),
+                        ("text", &render(lang, &anniversary.phrase, i64::from(next.year() - anniversary.since.year()))),
+                    ])
+                })
+                .collect::<Vec<_>>();
+            tf(lang, "anniversaries_list", &[("anniversaries", &lines.join("\n"))])
+        }
+    } else if let Some(id) = args.strip_prefix("remove").or_else(|| args.strip_prefix("stop")) {
+        match id.trim().trim_start_matches('#').parse::<i64>() {
+            Ok(id) => {
+                let removed = db.call(move |conn| remove(conn, telegram_id, id)).await.map_err(DatabaseError)?;
+                tf(lang, if removed { "anniversary_removed" } else { "anniversary_not_found" }, &[("id", &id)])
+            }
+            Err(_) => t(lang, "anniversary_usage"),
+        }
+    } else if let Some(parsed) = parse_args(args, settings, today) {
+        if !groups::can_manage_events(bot, msg, db).await? {
+            return groups::reply_not_allowed(bot, msg, db).await;
+        }
+        let (since, time, phrase) = parsed.clone();
+        let id = db.call(move |conn| in_transaction(conn, |tx| {
+            let user_id = ensure_user_exists(tx, tenant, telegram_id, username)?;
+            save(tx, tenant, user_id, chat_id, &parsed, today)
+        })).await.map_err(DatabaseError)?;
+        log::info!("Created anniversary {}", id);
+        let next = day_in(since, today.year());
+        let next = if next <= today { day_in(since, today.year() + 1) } else { next };
+        tf(lang, "anniversary_saved", &[
+            ("id", &id),
+            ("date", &settings.format_date(next)),
+            ("time", &settings.format_time(time)),
+            ("text", &render(lang, &phrase, i64::from(next.year() - since.year()))),
+        ])
+    } else {
+        t(lang, "anniversary_usage")
+    };
+    chunks::send(bot, msg.chat.id, response).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+    use crate::storage::tests::memory_db;
+
+    #[test]
+    fn phrases_count_years() {
+        assert_eq!(render(Lang::Ru, "{years} с переезда", 5), "5 лет с переезда");
+        assert_eq!(render(Lang::En, "{years} since we moved", 1), "1 year since we moved");
+        assert_eq!(render(Lang::Ru, "Свадьба", 22), "Свадьба — 22 года");
+        let leap = NaiveDate::from_ymd_opt(2020, 2, 29).unwrap();
+        assert_eq!(day_in(leap, 2026), NaiveDate::from_ymd_opt(2026, 2, 28).unwrap());
+    }
+
+    #[tokio::test]
+    async fn reminds_once_a_year() {
+        let db = memory_db();
+        let instant = |year| Utc.with_ymd_and_hms(year, 6, 15, 10, 0, 0).unwrap();
+        let queued = db.call(move |conn| {
+            let user_id = ensure_user_exists(conn, tenants::DEFAULT, 42, None)?;
+            let today = NaiveDate::from_ymd_opt(2025, 1, 1).unwrap();
+            let parsed = parse_args("15.06.2021 {years} с переезда", &Settings::default(), today).unwrap();
+            save(conn, tenants::DEFAULT, user_id, 42, &parsed, today)?;
+            let first = queue_due(conn, instant(2026).naive_utc(), instant(2026))?;
+            let again = queue_due(conn, instant(2026).naive_utc(), instant(2026))?;
+            let text = conn.query_row("SELECT text FROM outbox", [], |row| row.get(0)).map(crypto::open)?;
+            let next_year = queue_due(conn, instant(2027).naive_utc(), instant(2027))?;
+            Ok((first.len(), again.len(), text, next_year.len()))
+        }).await.unwrap();
+        assert_eq!(queued, (1, 0, "🗓 5 лет с переезда".to_string(), 1));
+    }
+}
@@ -0,0 +1,151 @@
+use teloxide::prelude::*;
+use chrono::NaiveDateTime;
+use rusqlite::{Connection, params};
+
+use crate::chunks;
+use crate::i18n::{t, tf};
+use crate::settings::Settings;
+use crate::{DatabaseError, Db, UTC_FORMAT, crypto, parse_event_time, privacy, timezone};
+
+pub fn init_tables(conn: &Connection) -> Result<(), rusqlite::Error> {
+    // Без внешнего ключа: история должна пережить удаление самого события.
+    // chat_id — чат события, только в нём видна его история.
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS audit_log (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            event_id INTEGER NOT NULL,
+            chat_id INTEGER NOT NULL,
+            actor_id INTEGER,
+            action TEXT NOT NULL,
+            old_time TEXT,
+            old_text TEXT,
+            new_time TEXT,
+            new_text TEXT,
+            created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP
+        )",
+        [],
+    )?;
+    conn.execute("CREATE INDEX IF NOT EXISTS audit_log_event ON audit_log(event_id)", [])?;
+    Ok(())
+}
+
+/// Состояние события до или после изменения: время и текст.
+pub type Snapshot<'a> = Option<(&'a str, &'a str)>;
+
+/// Записывает изменение события. `actor_id` — `users.id` автора, `None` — сам бот.
+/// Вызывается, пока событие ещё есть в базе: из него берётся чат.
+/// Изменения пользователей, включивших `/privacy`, не записываются.
+pub fn record(
+    conn: &Connection,
+    event_id: i64,
+    actor_id: Option<i64>,
+    action: &str,
+    old: Snapshot,
+    new: Snapshot,
+) -> Result<(), rusqlite::Error> {
+    if actor_id.map(|id| privacy::is_private_user(conn, id)).transpose()?.unwrap_or(false) {
+        return Ok(());
+    }
+    conn.execute(
+        "INSERT INTO audit_log (event_id, chat_id, actor_id, action, old_time, old_text, new_time, new_text)
+         SELECT e.id, COALESCE(e.chat_id, u.telegram_id), ?, ?, ?, ?, ?, ?
+         FROM events e
+         JOIN users u ON e.user_id = u.id
+         WHERE e.id = ?",
+        params![
+            actor_id,
+            action,
+            old.map(|(time, _)| time),
+            old.map(|(_, text)| crypto::seal(text)),
+            new.map(|(time, _)| time),
+            new.map(|(_, text)| crypto::seal(text)),
+            event_id,
+        ],
+    )?;
+    Ok(())
+}
+
+struct Entry {
+    actor: Option<String>,
+    action: String,
+    old: Option<(String, String)>,
+    new: Option<(String, String)>,
+    created_at: String,
+}
+
+fn history(conn: &Connection, event_id: i64, chat_id: i64) -> Result<Vec<Entry>, rusqlite::Error> {
+    let mut stmt = conn.prepare(
+        "SELECT COALESCE('@' || u.username, CAST(u.telegram_id AS TEXT)), a.action,
+                a.old_time, a.old_text, a.new_time, a.new_text, a.created_at
+         FROM audit_log a
+         LEFT JOIN users u ON a.actor_id = u.id
+         WHERE a.event_id = ? AND a.chat_id = ?
+         ORDER BY a.id"
+    )?;
+    let entries = stmt.query_map(params![event_id, chat_id], |row| {
+        let snapshot = |time: Option<String>, text: Option<String>| time.zip(text.map(crypto::open));
+        Ok(Entry {
+            actor: row.get(0)?,
+            action: row.get(1)?,
+            old: snapshot(row.get(2)?, row.get(3)?),
+            new: snapshot(row.get(4)?, row.get(5)?),
+            created_at: row.get(6)?,
+        })
+    })?
+    .collect::<Result<Vec<_>, _>>()?;
+    Ok(entries)
+}
+
+fn describe_snapshot(settings: &Settings, snapshot: &Option<(String, String)>) -> String {
+    match snapshot {
+        Some((time, text)) => {
+            let time = parse_event_time(time).map_or_else(|| time.clone(), |time| settings.format_datetime(time));
+            format!("{} {}", time, text)
+        }
+        None => String::new(),
+    }
+}
+
+fn describe_entry(settings: &Settings, entry: &Entry) -> String {
+    let lang = settings.lang;
+    let when = NaiveDateTime::parse_from_str(&entry.created_at, UTC_FORMAT).map_or_else(
+        |_| entry.created_at.clone(),
+        |utc| settings.format_datetime(timezone::from_utc(utc, settings.timezone)),
+    );
+    let who = entry.actor.clone().unwrap_or_else(|| t(lang, "audit_system"));
+    let key = match entry.action.as_str() {
+        "create" => "audit_created",
+        "edit" => "audit_edited",
+        "delete" => "audit_deleted",
+        "snooze" => "audit_snoozed",
+        _ => "audit_changed",
+    };
+    tf(lang, key, &[
+        ("when", &when),
+        ("who", &who),
+        ("action", &entry.action),
+        ("old", &describe_snapshot(settings, &entry.old)),
+        ("new", &describe_snapshot(settings, &entry.new)),
+    ])
+}
+
+/// `/audit #id` — история изменений события. Показывается в чате события всем его участникам.
+pub async fn handle_audit_command(bot: &Bot, msg: &Message, db: &Db, args: &str, settings: &Settings) -> ResponseResult<()> {
+    let lang = settings.lang;
+    let Ok(event_id) = args.trim().trim_start_matches('#').parse::<i64>() else {
+        bot.send_message(msg.chat.id, t(lang, "audit_usage")).await?;
+        return Ok(());
+    };
+
+    let chat_id = msg.chat.id.0;
+    let entries = db.call(move |conn| history(conn, event_id, chat_id)).await.map_err(DatabaseError)?;
+
+    let response = if entries.is_empty() {
+        tf(lang, "audit_empty", &[("id", &event_id)])
+    } else {
+        let entries = entries.iter().map(|entry| describe_entry(settings, entry)).collect::<Vec<_>>().join("\n");
+        tf(lang, "audit_history", &[("id", &event_id), ("entries", &entries)])
+    };
+    chunks::send(bot, msg.chat.id, response).await?;
+    Ok(())
+}
@@ -0,0 +1,123 @@
+use std::env;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::time::Duration;
+
+use rusqlite::DatabaseName;
+
+use crate::{Db, s3, supervisor};
+
+/// Имя файла копии: `reventor-20250403-093000.db`. Сортировка по имени совпадает с порядком по времени.
+const FILE_PREFIX: &str = "reventor-";
+const FILE_SUFFIX: &str = ".db";
+
+#[derive(Debug)]
+pub struct BackupConfig {
+    pub dir: PathBuf,
+    /// `None` — автоматические копии выключены, остаётся только `/admin backup now`.
+    pub interval: Option<Duration>,
+    pub keep: usize,
+}
+
+/// Настройки из окружения: `BACKUP_DIR` (по умолчанию `backups`),
+/// `BACKUP_INTERVAL_HOURS` (24, 0 выключает расписание) и `BACKUP_KEEP` (7).
+pub fn config() -> &'static BackupConfig {
+    static CONFIG: OnceLock<BackupConfig> = OnceLock::new();
+    CONFIG.get_or_init(|| {
+        let hours: u64 = env::var("BACKUP_INTERVAL_HOURS").ok().and_then(|v| v.parse().ok()).unwrap_or(24);
+        BackupConfig {
+            dir: env::var("BACKUP_DIR").map_or_else(|_| PathBuf::from("backups"), PathBuf::from),
+            interval: (hours > 0).then(|| Duration::from_secs(hours * 3600)),
+            keep: env::var("BACKUP_KEEP").ok().and_then(|v| v.parse().ok()).unwrap_or(7).max(1),
+        }
+    })
+}
+
+/// Снимок базы через backup API SQLite: копия согласована, даже если в базу в это время пишут.
+pub async fn run(db: &Db) -> Result<PathBuf, rusqlite::Error> {
+    let config = config();
+    let path = config.dir.join(format!(
+        "{}{}{}",
+        FILE_PREFIX,
+        chrono::Local::now().format("%Y%m%d-%H%M%S"),
+        FILE_SUFFIX
+    ));
+    if let Err(e) = std::fs::create_dir_all(&config.dir) {
+        log::error!("Failed to create backup directory {:?}: {}", config.dir, e);
+    }
+
+    let target = path.clone();
+    db.call(move |conn| conn.backup(DatabaseName::Main, &target, None)).await?;
+    log::info!("Database backup saved to {:?}", path);
+    upload_offsite(&path).await;
+
+    if let Err(e) = prune(&config.dir, config.keep) {
+        log::error!("Failed to prune old backups in {:?}: {}", config.dir, e);
+    }
+    Ok(path)
+}
+
+/// Отправляет копию в S3, если оно настроено. Сбой выгрузки не отменяет локальную копию.
+/// Сроком хранения копий в бакете управляют правила жизненного цикла самого бакета.
+async fn upload_offsite(path: &Path) {
+    let Some(config) = s3::config() else {
+        return;
+    };
+    let Some(name) = path.file_name().and_then(|name| name.to_str()) else {
+        return;
+    };
+    let body = match tokio::fs::read(path).await {
+        Ok(body) => body,
+        Err(e) => {
+            log::error!("Failed to read backup {:?} for upload: {}", path, e);
+            return;
+        }
+    };
+    match s3::upload(config, name, body).await {
+        Ok(()) => log::info!("Backup {} uploaded to S3", name),
+        Err(e) => log::error!("Failed to upload backup {} to S3: {}", name, e),
+    }
+}
+
+/// Оставляет `keep` последних копий.
+fn prune(dir: &Path, keep: usize) -> std::io::Result<()> {
+    let mut backups = std::fs::read_dir(dir)?
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| name.starts_with(FILE_PREFIX) && name.ends_with(FILE_SUFFIX))
+        })
+        .collect::<Vec<_>>();
+    backups.sort();
+
+    let excess = backups.len().saturating_sub(keep);
+    for path in backups.into_iter().take(excess) {
+        println!("Removing old backup: {:?}", path);
+        std::fs::remove_file(path)?;
+    }
+    Ok(())
+}
+
+/// Фоновая задача, делающая копии по расписанию из `BACKUP_INTERVAL_HOURS`.
+pub fn spawn_scheduler(db: Db) {
+    let Some(interval) = config().interval else {
+        log::info!("Scheduled backups are disabled");
+        return;
+    };
+
+    supervisor::spawn("backup", move || {
+        let db = db.clone();
+        async move {
+            // Без копии при старте: частые перезапуски иначе вытеснили бы старые копии
+            let mut timer = tokio::time::interval_at(tokio::time::Instant::now() + interval, interval);
+            loop {
+                timer.tick().await;
+                if let Err(e) = run(&db).await {
+                    log::error!("Scheduled backup failed: {}", e);
+                }
+            }
+        }
+    });
+}
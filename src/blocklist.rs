@@ -0,0 +1,95 @@
+use teloxide::prelude::*;
+use rusqlite::{Connection, params};
+
+use crate::chunks;
+use crate::i18n::{t, tf, Lang};
+use crate::{DatabaseError, Db};
+
+/// Условие для запросов уведомлений: напоминания заблокированных пользователей не отправляются.
+pub const NOT_BLOCKED: &str = "u.telegram_id NOT IN (SELECT telegram_id FROM blocked_users)";
+
+pub fn init_tables(conn: &Connection) -> Result<(), rusqlite::Error> {
+    // Пользователи, чьи сообщения бот игнорирует; их события сохраняются, но не отправляются
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS blocked_users (
+            telegram_id INTEGER PRIMARY KEY,
+            reason TEXT,
+            blocked_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP
+        )",
+        [],
+    )?;
+    Ok(())
+}
+
+fn block(conn: &Connection, telegram_id: i64, reason: Option<&str>) -> Result<(), rusqlite::Error> {
+    conn.execute(
+        "INSERT INTO blocked_users (telegram_id, reason) VALUES (?, ?)
+         ON CONFLICT(telegram_id) DO UPDATE SET reason = excluded.reason",
+        params![telegram_id, reason],
+    )?;
+    Ok(())
+}
+
+fn unblock(conn: &Connection, telegram_id: i64) -> Result<bool, rusqlite::Error> {
+    Ok(conn.execute("DELETE FROM blocked_users WHERE telegram_id = ?", params![telegram_id])? > 0)
+}
+
+fn list(conn: &Connection) -> Result<Vec<(i64, Option<String>, String)>, rusqlite::Error> {
+    let mut stmt = conn.prepare("SELECT telegram_id, reason, blocked_at FROM blocked_users ORDER BY blocked_at, telegram_id")?;
+    let blocked = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(blocked)
+}
+
+/// Заблокирован ли пользователь. При ошибке базы сообщение обрабатывается как обычно.
+pub async fn is_blocked(db: &Db, telegram_id: i64) -> bool {
+    let result = db.call(move |conn| {
+        conn.query_row("SELECT EXISTS(SELECT 1 FROM blocked_users WHERE telegram_id = ?)", params![telegram_id], |row| row.get(0))
+    }).await;
+    match result {
+        Ok(blocked) => blocked,
+        Err(e) => {
+            log::error!("Failed to check blocklist for user {}: {}", telegram_id, e);
+            false
+        }
+    }
+}
+
+/// `/admin block <id> [причина]`, `/admin unblock <id>` и `/admin blocked`.
+pub async fn handle_admin(bot: &Bot, msg: &Message, db: &Db, command: &str, args: &str, lang: Lang) -> ResponseResult<()> {
+    let (id, reason) = args.split_once(char::is_whitespace).map_or((args, ""), |(id, reason)| (id, reason.trim()));
+    let response = match (command, id.parse::<i64>()) {
+        ("block", Ok(telegram_id)) => {
+            let reason = (!reason.is_empty()).then(|| reason.to_string());
+            log::info!("User {} blocked: {}", telegram_id, reason.as_deref().unwrap_or("-"));
+            db.call(move |conn| block(conn, telegram_id, reason.as_deref())).await.map_err(DatabaseError)?;
+            tf(lang, "blocklist_blocked", &[("id", &telegram_id)])
+        }
+        ("unblock", Ok(telegram_id)) if reason.is_empty() => {
+            if db.call(move |conn| unblock(conn, telegram_id)).await.map_err(DatabaseError)? {
+                log::info!("User {} unblocked", telegram_id);
+                tf(lang, "blocklist_unblocked", &[("id", &telegram_id)])
+            } else {
+                tf(lang, "blocklist_not_blocked", &[("id", &telegram_id)])
+            }
+        }
+        ("blocked", _) if args.is_empty() => {
+            let blocked = db.call(list).await.map_err(DatabaseError)?;
+            if blocked.is_empty() {
+                t(lang, "blocklist_empty")
+            } else {
+                let lines = blocked
+                    .iter()
+                    .map(|(id, reason, at)| match reason {
+                        Some(reason) => format!("{} — {} ({})", id, reason, at),
+                        None => format!("{} ({})", id, at),
+                    })
+                    .collect::<Vec<_>>();
+                tf(lang, "blocklist_list", &[("users", &lines.join("\n"))])
+            }
+        }
+        _ => t(lang, "admin_usage"),
+    };
+    chunks::send(bot, msg.chat.id, response).await?;
+    Ok(())
+}
@@ -0,0 +1,104 @@
+use teloxide::prelude::*;
+use chrono::{Duration, NaiveDate, NaiveDateTime, NaiveTime, Timelike};
+use rusqlite::{Connection, params};
+
+use crate::accessibility;
+use crate::chunks;
+use crate::i18n::{t, tf};
+use crate::settings::Settings;
+use crate::timezone;
+use crate::{DatabaseError, Db, crypto, parse_event_time, resolve_date};
+
+/// Сколько занимает событие без указанной длительности.
+pub const DEFAULT_DURATION_MINUTES: i64 = 60;
+/// Часы, которые показываются всегда; ночные — только если в них что-то есть.
+const DAY_START_HOUR: u32 = 8;
+const DAY_END_HOUR: u32 = 21;
+
+/// Событие дня как отрезок времени.
+#[derive(Debug)]
+struct Block {
+    start: NaiveDateTime,
+    end: NaiveDateTime,
+    text: String,
+}
+
+fn day_blocks(conn: &Connection, telegram_id: i64, date: NaiveDate) -> Result<Vec<Block>, rusqlite::Error> {
+    let mut stmt = conn.prepare(
+        "SELECT e.event_time, e.text, e.end_time FROM events e
+         JOIN users u ON e.user_id = u.id
+         WHERE u.telegram_id = ? AND e.status IN ('pending', 'sent') AND e.event_time LIKE ?",
+    )?;
+    let day_prefix = format!("{} %", date.format("%d.%m.%Y"));
+    let mut blocks = stmt
+        .query_map(params![telegram_id, day_prefix], |row| {
+            Ok((row.get::<_, String>(0)?, crypto::open(row.get(1)?), row.get::<_, Option<String>>(2)?))
+        })?
+        .collect::<Result<Vec<_>, _>>()?
+        .into_iter()
+        .filter_map(|(time, text, end)| {
+            let start = parse_event_time(&time)?;
+            let end = end.as_deref().and_then(parse_event_time).filter(|end| *end > start)
+                .unwrap_or(start + Duration::minutes(DEFAULT_DURATION_MINUTES));
+            Some(Block { start, end, text })
+        })
+        .collect::<Vec<_>>();
+    blocks.sort_by_key(|block| block.start);
+    Ok(blocks)
+}
+
+/// Сетка по часам: занятый час показывает первое попавшее в него событие.
+fn render_day(settings: &Settings, date: NaiveDate, blocks: &[Block]) -> String {
+    let occupied = |hour: u32| {
+        let slot_start = date.and_time(NaiveTime::from_hms_opt(hour, 0, 0).unwrap());
+        let slot_end = slot_start + Duration::hours(1);
+        blocks.iter().find(|block| block.start < slot_end && block.end > slot_start)
+    };
+    let first = blocks.iter().map(|block| block.start.hour()).min().unwrap_or(DAY_START_HOUR).min(DAY_START_HOUR);
+    let last = blocks
+        .iter()
+        .map(|block| if block.end.date() > date { 23 } else { block.end.hour() })
+        .max()
+        .unwrap_or(DAY_END_HOUR)
+        .max(DAY_END_HOUR);
+
+    (first..=last.min(23))
+        .map(|hour| {
+            let time = settings.format_time(NaiveTime::from_hms_opt(hour, 0, 0).unwrap());
+            match (occupied(hour), accessibility::current()) {
+                (Some(block), None) => format!("{} ■ {}", time, block.text.lines().next().unwrap_or_default()),
+                (None, None) => format!("{} □", time),
+                (Some(block), Some(_)) => format!("{}: {}", time, block.text.lines().next().unwrap_or_default()),
+                (None, Some(lang)) => format!("{}: {}", time, t(lang, "plain_free")),
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// `/busy [дата]` — занятые и свободные часы дня, по умолчанию сегодня.
+pub async fn handle_busy_command(bot: &Bot, msg: &Message, db: &Db, args: &str, settings: &Settings) -> ResponseResult<()> {
+    let lang = settings.lang;
+    let Some(user) = msg.from() else {
+        return Ok(());
+    };
+    let today = timezone::now_in(settings.timezone).date();
+    let date = match args.to_lowercase().as_str() {
+        "" | "today" | "сегодня" => Some(today),
+        "tomorrow" | "завтра" => Some(today + Duration::days(1)),
+        value => resolve_date(value, today, settings.date_format),
+    };
+    let Some(date) = date else {
+        bot.send_message(msg.chat.id, t(lang, "busy_usage")).await?;
+        return Ok(());
+    };
+
+    let telegram_id = user.id.0 as i64;
+    let blocks = db.call(move |conn| day_blocks(conn, telegram_id, date)).await.map_err(DatabaseError)?;
+    let response = tf(lang, "busy_header", &[
+        ("date", &settings.format_date(date)),
+        ("slots", &render_day(settings, date, &blocks)),
+    ]);
+    chunks::send(bot, msg.chat.id, response).await?;
+    Ok(())
+}
@@ -0,0 +1,163 @@
+use teloxide::prelude::*;
+use teloxide::types::{InlineKeyboardButton, InlineKeyboardMarkup, ParseMode};
+use chrono::{Datelike, Months, NaiveDate, NaiveDateTime};
+use rusqlite::{Connection, params};
+
+use crate::chunks;
+use crate::i18n::{t, tf, Lang};
+use crate::settings::{self, Settings};
+use crate::timezone;
+use crate::{DatabaseError, Db, crypto, parse_event_time};
+
+/// Все события пользователя за месяц, отсортированные по времени.
+fn get_month_events(conn: &Connection, telegram_id: i64, month: NaiveDate) -> Result<Vec<(NaiveDateTime, String)>, rusqlite::Error> {
+    let mut stmt = conn.prepare(
+        "SELECT e.event_time, e.text FROM events e
+         JOIN users u ON e.user_id = u.id
+         WHERE u.telegram_id = ? AND e.event_time LIKE ?"
+    )?;
+    let mut events = stmt.query_map(params![telegram_id, format!("%.{} %", month.format("%m.%Y"))], |row| {
+        Ok((row.get::<_, String>(0)?, crypto::open(row.get(1)?)))
+    })?
+    .collect::<Result<Vec<_>, _>>()?
+    .into_iter()
+    .filter_map(|(time, text)| parse_event_time(&time).map(|time| (time, text)))
+    .collect::<Vec<_>>();
+
+    events.sort_by_key(|(time, _)| *time);
+    Ok(events)
+}
+
+fn days_in_month(month: NaiveDate) -> u32 {
+    let next = month + Months::new(1);
+    (next - month).num_days() as u32
+}
+
+/// Текст с моноширинной сеткой месяца; дни с событиями помечены `*`.
+fn render_grid(lang: Lang, month: NaiveDate, marked: &[u32]) -> String {
+    let month_name = t(lang, "calendar_months")
+        .split(',')
+        .nth(month.month0() as usize)
+        .unwrap_or_default()
+        .to_string();
+
+    let mut grid = t(lang, "calendar_weekdays");
+    let offset = month.weekday().num_days_from_monday();
+    let mut line = "   ".repeat(offset as usize);
+    for day in 1..=days_in_month(month) {
+        let mark = if marked.contains(&day) { '*' } else { ' ' };
+        line.push_str(&format!("{:>2}{}", day, mark));
+        if (offset + day).is_multiple_of(7) {
+            grid.push('\n');
+            grid.push_str(line.trim_end());
+            line.clear();
+        }
+    }
+    if !line.is_empty() {
+        grid.push('\n');
+        grid.push_str(line.trim_end());
+    }
+
+    format!("<b>{} {}</b>\n<pre>{}</pre>", month_name, month.year(), grid)
+}
+
+/// Кнопки дней в той же раскладке, что и сетка, плюс навигация по месяцам.
+fn render_keyboard(month: NaiveDate, marked: &[u32]) -> InlineKeyboardMarkup {
+    let offset = month.weekday().num_days_from_monday() as usize;
+    let mut cells = vec![InlineKeyboardButton::callback(" ", "cal:noop"); offset];
+    for day in 1..=days_in_month(month) {
+        let label = if marked.contains(&day) { format!("{}•", day) } else { day.to_string() };
+        let date = month.with_day(day).unwrap_or(month);
+        cells.push(InlineKeyboardButton::callback(label, format!("cal:day:{}", date.format("%Y-%m-%d"))));
+    }
+    while !cells.len().is_multiple_of(7) {
+        cells.push(InlineKeyboardButton::callback(" ", "cal:noop"));
+    }
+
+    let mut rows = cells.chunks(7).map(|row| row.to_vec()).collect::<Vec<_>>();
+    let prev = month - Months::new(1);
+    let next = month + Months::new(1);
+    rows.push(vec![
+        InlineKeyboardButton::callback("◀", format!("cal:month:{}", prev.format("%Y-%m-%d"))),
+        InlineKeyboardButton::callback("▶", format!("cal:month:{}", next.format("%Y-%m-%d"))),
+    ]);
+    InlineKeyboardMarkup::new(rows)
+}
+
+fn render_month(conn: &Connection, telegram_id: i64, lang: Lang, month: NaiveDate) -> Result<(String, InlineKeyboardMarkup), rusqlite::Error> {
+    let mut marked = get_month_events(conn, telegram_id, month)?
+        .iter()
+        .map(|(time, _)| time.day())
+        .collect::<Vec<_>>();
+    marked.dedup();
+    Ok((render_grid(lang, month, &marked), render_keyboard(month, &marked)))
+}
+
+fn day_events_text(conn: &Connection, telegram_id: i64, settings: &Settings, day: NaiveDate) -> Result<String, rusqlite::Error> {
+    let events = get_month_events(conn, telegram_id, day.with_day(1).unwrap_or(day))?;
+    let lines = events
+        .iter()
+        .filter(|(time, _)| time.date() == day)
+        .map(|(time, text)| format!("{} - {}", settings.format_time(time.time()), text))
+        .collect::<Vec<_>>();
+
+    let date = settings.format_date(day);
+    Ok(if lines.is_empty() {
+        tf(settings.lang, "calendar_day_empty", &[("date", &date)])
+    } else {
+        tf(settings.lang, "calendar_day", &[("date", &date), ("events", &lines.join("\n"))])
+    })
+}
+
+pub async fn handle_calendar_command(bot: &Bot, msg: &Message, db: &Db, settings: &Settings) -> ResponseResult<()> {
+    let (today, lang) = (timezone::now_in(settings.timezone).date(), settings.lang);
+    let month = today.with_day(1).unwrap_or(today);
+
+    let telegram_id = msg.from().unwrap().id.0 as i64;
+    let (text, keyboard) = db.call(move |conn| render_month(conn, telegram_id, lang, month))
+        .await
+        .map_err(DatabaseError)?;
+
+    bot.send_message(msg.chat.id, text)
+        .parse_mode(ParseMode::Html)
+        .reply_markup(keyboard)
+        .await?;
+    Ok(())
+}
+
+pub async fn handle_callback(bot: &Bot, q: &CallbackQuery, db: &Db, args: &str) -> ResponseResult<()> {
+    let (action, value) = args.split_once(':').unwrap_or((args, ""));
+    let date = NaiveDate::parse_from_str(value, "%Y-%m-%d").ok();
+    let (Some(message), Some(date)) = (q.message.as_ref(), date) else {
+        bot.answer_callback_query(q.id.clone()).await?;
+        return Ok(());
+    };
+    let telegram_id = q.from.id.0 as i64;
+
+    let chat_id = message.chat.id.0;
+    let settings = db.call(move |conn| settings::resolve(conn, telegram_id, chat_id)).await.map_err(DatabaseError)?;
+    match action {
+        "month" => {
+            let lang = settings.lang;
+            let (text, keyboard) = db.call(move |conn| render_month(conn, telegram_id, lang, date))
+                .await
+                .map_err(DatabaseError)?;
+            bot.answer_callback_query(q.id.clone()).await?;
+            bot.edit_message_text(message.chat.id, message.id, text)
+                .parse_mode(ParseMode::Html)
+                .reply_markup(keyboard)
+                .await?;
+        }
+        "day" => {
+            let text = db.call(move |conn| day_events_text(conn, telegram_id, &settings, date))
+                .await
+                .map_err(DatabaseError)?;
+            bot.answer_callback_query(q.id.clone()).await?;
+            chunks::send(bot, message.chat.id, text).await?;
+        }
+        _ => {
+            bot.answer_callback_query(q.id.clone()).await?;
+        }
+    }
+    Ok(())
+}
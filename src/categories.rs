@@ -0,0 +1,105 @@
+use teloxide::prelude::*;
+use rusqlite::{Connection, params};
+
+use crate::accessibility;
+use crate::chunks;
+use crate::i18n::{t, tf, Lang};
+use crate::settings::Settings;
+use crate::{DatabaseError, Db, add_column_if_missing, get_event};
+
+/// Категории с именами, по которым их можно указать вместо эмодзи: (значок, по-русски, по-английски).
+const CATEGORIES: &[(&str, &str, &str)] = &[
+    ("💼", "работа", "work"),
+    ("💊", "здоровье", "health"),
+    ("🎂", "праздник", "birthday"),
+    ("🏠", "дом", "home"),
+    ("🛒", "покупки", "shopping"),
+    ("✈️", "поездки", "travel"),
+    ("📚", "учёба", "study"),
+    ("💰", "финансы", "money"),
+    ("⚽", "спорт", "sport"),
+];
+/// Эмодзи с модификаторами и склейками занимает несколько символов, но не больше этого.
+const MAX_ICON_CHARS: usize = 8;
+
+pub fn init_tables(conn: &Connection) -> Result<(), rusqlite::Error> {
+    add_column_if_missing(conn, "events", "icon", "TEXT")?;
+    Ok(())
+}
+
+/// Значок по имени категории или сам эмодзи; текст с буквами и цифрами значком не считается.
+pub fn parse(value: &str) -> Option<String> {
+    let value = value.trim();
+    let lower = value.to_lowercase();
+    if let Some((icon, _, _)) = CATEGORIES.iter().find(|(_, ru, en)| lower == *ru || lower == *en) {
+        return Some(icon.to_string());
+    }
+    let is_emoji = !value.is_empty()
+        && value.chars().count() <= MAX_ICON_CHARS
+        && !value.chars().any(|c| c.is_alphanumeric() || c.is_whitespace() || c.is_ascii_punctuation());
+    is_emoji.then(|| value.to_string())
+}
+
+/// Текст события со значком категории впереди. В простом выводе вместо значка — название
+/// категории, а приоритет записан словами.
+pub fn label(icon: Option<&str>, text: &str) -> String {
+    if let Some(lang) = accessibility::current() {
+        let text = accessibility::spell_priority(lang, text);
+        return match icon.and_then(|icon| CATEGORIES.iter().find(|(known, _, _)| *known == icon)) {
+            Some((_, ru, en)) => format!("{}: {}", if lang == Lang::Ru { ru } else { en }, text),
+            None => text,
+        };
+    }
+    match icon {
+        Some(icon) => format!("{} {}", icon, text),
+        None => text.to_string(),
+    }
+}
+
+fn describe_all(lang: Lang) -> String {
+    CATEGORIES
+        .iter()
+        .map(|(icon, ru, en)| format!("{} {}", icon, if lang == Lang::Ru { ru } else { en }))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn set_icon(conn: &Connection, event_id: i64, icon: Option<&str>) -> Result<(), rusqlite::Error> {
+    conn.execute("UPDATE events SET icon = ? WHERE id = ?", params![icon, event_id])?;
+    Ok(())
+}
+
+/// `/icon #id 💼|work|off` — значок или категория события.
+pub async fn handle_icon_command(bot: &Bot, msg: &Message, db: &Db, args: &str, settings: &Settings) -> ResponseResult<()> {
+    let lang = settings.lang;
+    let Some(user) = msg.from() else {
+        return Ok(());
+    };
+    let parsed = args.split_once(char::is_whitespace).and_then(|(id, value)| {
+        let event_id = id.trim_start_matches('#').parse::<i64>().ok()?;
+        match value.trim() {
+            "off" => Some((event_id, None)),
+            value => parse(value).map(|icon| (event_id, Some(icon))),
+        }
+    });
+    let Some((event_id, icon)) = parsed else {
+        chunks::send(bot, msg.chat.id, tf(lang, "icon_usage", &[("categories", &describe_all(lang))])).await?;
+        return Ok(());
+    };
+
+    let (telegram_id, chat_id, saved_icon) = (user.id.0 as i64, msg.chat.id.0, icon.clone());
+    let updated = db.call(move |conn| {
+        match get_event(conn, event_id)?.filter(|event| event.chat_id == chat_id && event.owner_id == telegram_id) {
+            Some(_) => set_icon(conn, event_id, saved_icon.as_deref()).map(|_| true),
+            None => Ok(false),
+        }
+    }).await.map_err(DatabaseError)?;
+
+    let response = match (updated, icon) {
+        (false, _) => t(lang, "event_not_found"),
+        (true, Some(icon)) => tf(lang, "icon_saved", &[("id", &event_id), ("icon", &icon)]),
+        (true, None) => tf(lang, "icon_removed", &[("id", &event_id)]),
+    };
+    chunks::send(bot, msg.chat.id, response).await?;
+    Ok(())
+}
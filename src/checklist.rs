@@ -0,0 +1,150 @@
+use teloxide::prelude::*;
+use teloxide::types::{InlineKeyboardButton, InlineKeyboardMarkup};
+use rusqlite::{Connection, params, OptionalExtension};
+
+use crate::accessibility;
+use crate::i18n::t;
+use crate::followups::{self, FollowUp};
+use crate::settings;
+use crate::stars;
+use crate::{DatabaseError, Db, crypto, in_transaction};
+
+#[derive(Debug)]
+struct ChecklistItem {
+    id: i64,
+    text: String,
+    checked: bool,
+}
+
+pub fn init_tables(conn: &Connection) -> Result<(), rusqlite::Error> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS checklist_items (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            event_id INTEGER NOT NULL,
+            position INTEGER NOT NULL,
+            text TEXT NOT NULL,
+            checked INTEGER NOT NULL DEFAULT 0,
+            FOREIGN KEY(event_id) REFERENCES events(id) ON DELETE CASCADE
+        )",
+        [],
+    )?;
+    Ok(())
+}
+
+/// Пункты чек-листа — строки текста события, начинающиеся с `- `.
+fn parse_items(text: &str) -> Vec<&str> {
+    text.lines()
+        .filter_map(|line| line.trim().strip_prefix("- "))
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+        .collect()
+}
+
+/// Пересоздаёт пункты чек-листа события по его тексту.
+pub fn save_items(conn: &Connection, event_id: i64, text: &str) -> Result<(), rusqlite::Error> {
+    conn.execute("DELETE FROM checklist_items WHERE event_id = ?", params![event_id])?;
+    for (position, item) in parse_items(text).into_iter().enumerate() {
+        conn.execute(
+            "INSERT INTO checklist_items (event_id, position, text) VALUES (?, ?, ?)",
+            params![event_id, position as i64, crypto::seal(item)],
+        )?;
+    }
+    Ok(())
+}
+
+fn get_items(conn: &Connection, event_id: i64) -> Result<Vec<ChecklistItem>, rusqlite::Error> {
+    let mut stmt = conn.prepare(
+        "SELECT id, text, checked FROM checklist_items WHERE event_id = ? ORDER BY position"
+    )?;
+    let items = stmt.query_map(params![event_id], |row| {
+        Ok(ChecklistItem {
+            id: row.get(0)?,
+            text: crypto::open(row.get(1)?),
+            checked: row.get(2)?,
+        })
+    })?
+    .collect::<Result<Vec<_>, _>>()?;
+    Ok(items)
+}
+
+fn render_keyboard(items: &[ChecklistItem]) -> InlineKeyboardMarkup {
+    InlineKeyboardMarkup::new(items.iter().map(|item| {
+        let label = match accessibility::current() {
+            Some(lang) => format!("{}: {}", t(lang, if item.checked { "plain_checked" } else { "plain_unchecked" }), item.text),
+            None => format!("{} {}", if item.checked { "☑" } else { "☐" }, item.text),
+        };
+        vec![InlineKeyboardButton::callback(label, format!("chk:{}", item.id))]
+    }))
+}
+
+/// Клавиатура с пунктами для напоминания, если у события есть чек-лист.
+pub fn keyboard(conn: &Connection, event_id: i64) -> Result<Option<InlineKeyboardMarkup>, rusqlite::Error> {
+    let items = get_items(conn, event_id)?;
+    Ok((!items.is_empty()).then(|| render_keyboard(&items)))
+}
+
+/// Результат переключения пункта: событие, его пункты и созданные продолжения,
+/// если последним нажатием событие было завершено.
+type Toggled = (i64, Vec<ChecklistItem>, bool, Vec<FollowUp>);
+
+/// Переключает пункт и возвращает событие, его пункты и то, выполнены ли теперь все пункты.
+fn toggle_item(conn: &Connection, item_id: i64) -> Result<Option<Toggled>, rusqlite::Error> {
+    let event_id: Option<i64> = conn.query_row(
+        "SELECT event_id FROM checklist_items WHERE id = ?",
+        params![item_id],
+        |row| row.get(0),
+    ).optional()?;
+    let Some(event_id) = event_id else {
+        return Ok(None);
+    };
+
+    conn.execute("UPDATE checklist_items SET checked = 1 - checked WHERE id = ?", params![item_id])?;
+    let items = get_items(conn, event_id)?;
+    let all_checked = items.iter().all(|item| item.checked);
+
+    // Событие с чек-листом завершается, только когда отмечены все пункты
+    let follow_ups = if all_checked {
+        followups::complete_event(conn, event_id)?.unwrap_or_default()
+    } else {
+        conn.execute(
+            "UPDATE events SET status = 'sent', done_at = NULL WHERE id = ? AND status = 'done'",
+            params![event_id],
+        )?;
+        Vec::new()
+    };
+
+    Ok(Some((event_id, items, all_checked, follow_ups)))
+}
+
+pub async fn handle_callback(bot: &Bot, q: &CallbackQuery, db: &Db, args: &str) -> ResponseResult<()> {
+    let item_id: i64 = args.parse().unwrap_or_default();
+    let Some(message) = q.message.as_ref() else {
+        bot.answer_callback_query(q.id.clone()).await?;
+        return Ok(());
+    };
+
+    let (telegram_id, chat_id) = (q.from.id.0 as i64, message.chat.id.0);
+    let (settings, toggled) = db.call(move |conn| {
+        let settings = settings::resolve(conn, telegram_id, chat_id)?;
+        Ok((settings, in_transaction(conn, |tx| toggle_item(tx, item_id))?))
+    }).await.map_err(DatabaseError)?;
+    let lang = settings.lang;
+
+    let Some((event_id, items, all_checked, follow_ups)) = toggled else {
+        bot.answer_callback_query(q.id.clone()).await?;
+        return Ok(());
+    };
+
+    if all_checked {
+        log::info!("Checklist of event {} completed", event_id);
+        bot.answer_callback_query(q.id.clone()).text(t(lang, "checklist_done")).await?;
+        stars::unpin(bot, db, event_id).await?;
+    } else {
+        bot.answer_callback_query(q.id.clone()).await?;
+    }
+    bot.edit_message_reply_markup(message.chat.id, message.id)
+        .reply_markup(render_keyboard(&items))
+        .await?;
+    followups::announce(bot, db, &settings, &follow_ups).await?;
+    Ok(())
+}
@@ -0,0 +1,65 @@
+use teloxide::payloads::SendMessage;
+use teloxide::prelude::*;
+use teloxide::requests::JsonRequest;
+
+/// Предел длины сообщения в Telegram; считается в UTF-16, как у самого Telegram.
+pub const MAX_MESSAGE_LEN: usize = 4096;
+
+fn utf16_len(text: &str) -> usize {
+    text.chars().map(char::len_utf16).sum()
+}
+
+/// Байтовая граница, до которой текст укладывается в `limit` единиц UTF-16.
+fn fit(text: &str, limit: usize) -> usize {
+    let mut units = 0;
+    for (index, c) in text.char_indices() {
+        units += c.len_utf16();
+        if units > limit {
+            return index;
+        }
+    }
+    text.len()
+}
+
+/// Делит текст на части не длиннее `limit`. Режет по пустой строке, затем по переводу строки,
+/// затем по пробелу, и только слово длиннее предела разрезается посередине.
+/// Граница абзаца или строки из начала части не берётся, чтобы части не получались слишком короткими.
+pub fn split(text: &str, limit: usize) -> Vec<String> {
+    let mut chunks = Vec::new();
+    let mut rest = text;
+    while utf16_len(rest) > limit {
+        let end = fit(rest, limit);
+        let head = &rest[..end];
+        let cut = head
+            .rfind("\n\n")
+            .filter(|&index| index > end / 2)
+            .or_else(|| head.rfind('\n').filter(|&index| index > end / 2))
+            .or_else(|| head.rfind(char::is_whitespace).filter(|&index| index > 0))
+            .unwrap_or(end);
+        let chunk = rest[..cut].trim_end();
+        if !chunk.is_empty() {
+            chunks.push(chunk.to_string());
+        }
+        rest = rest[cut..].trim_start();
+    }
+    if chunks.is_empty() || !rest.is_empty() {
+        chunks.push(rest.to_string());
+    }
+    chunks
+}
+
+/// Отправляет все части длинного текста, кроме последней, и возвращает запрос для последней,
+/// чтобы к ней можно было добавить клавиатуру или ответ на сообщение.
+pub async fn send_last(bot: &Bot, chat_id: ChatId, text: impl Into<String>) -> ResponseResult<JsonRequest<SendMessage>> {
+    let mut chunks = split(&text.into(), MAX_MESSAGE_LEN);
+    let last = chunks.pop().unwrap_or_default();
+    for chunk in chunks {
+        bot.send_message(chat_id, chunk).await?;
+    }
+    Ok(bot.send_message(chat_id, last))
+}
+
+/// Отправляет текст одним или несколькими сообщениями и возвращает последнее из них.
+pub async fn send(bot: &Bot, chat_id: ChatId, text: impl Into<String>) -> ResponseResult<Message> {
+    send_last(bot, chat_id, text).await?.await
+}
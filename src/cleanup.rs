@@ -0,0 +1,87 @@
+use teloxide::prelude::*;
+use teloxide::types::MessageId;
+use chrono::NaiveDateTime;
+use rusqlite::{Connection, params};
+
+use crate::clock::Clock;
+use crate::{Db, UTC_FORMAT, groups, tenants};
+
+/// Сообщение, которое пора удалить.
+#[derive(Debug)]
+struct Deletion {
+    tenant: String,
+    chat_id: i64,
+    message_id: i32,
+}
+
+pub fn init_tables(conn: &Connection) -> Result<(), rusqlite::Error> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS pending_deletions (
+            chat_id INTEGER NOT NULL,
+            message_id INTEGER NOT NULL,
+            tenant TEXT NOT NULL DEFAULT 'default',
+            delete_at TEXT NOT NULL,
+            PRIMARY KEY(chat_id, message_id)
+        )",
+        [],
+    )?;
+    conn.execute("CREATE INDEX IF NOT EXISTS idx_pending_deletions_due ON pending_deletions(delete_at)", [])?;
+    Ok(())
+}
+
+fn enqueue(conn: &Connection, tenant: &str, chat_id: i64, message_id: i32) -> Result<(), rusqlite::Error> {
+    let Some(minutes) = groups::cleanup_minutes(conn, chat_id)? else {
+        return Ok(());
+    };
+    let delete_at = chrono::Utc::now().naive_utc() + chrono::Duration::minutes(minutes as i64);
+    conn.execute(
+        "INSERT OR IGNORE INTO pending_deletions (chat_id, message_id, tenant, delete_at) VALUES (?, ?, ?, ?)",
+        params![chat_id, message_id, tenant, delete_at.format(UTC_FORMAT).to_string()],
+    )?;
+    Ok(())
+}
+
+/// Ставит сообщение в очередь на удаление, если в группе включена уборка (`/groupsettings cleanup`).
+/// В личных чатах ничего не удаляется.
+pub async fn schedule(bot: &Bot, db: &Db, message: &Message) {
+    if message.chat.is_private() {
+        return;
+    }
+    let (tenant, chat_id, message_id) = (tenants::name_of(bot), message.chat.id.0, message.id.0);
+    if let Err(e) = db.call(move |conn| enqueue(conn, tenant, chat_id, message_id)).await {
+        log::error!("Failed to schedule deletion of message {} in chat {}: {}", message_id, chat_id, e);
+    }
+}
+
+fn take_due(conn: &Connection, now: NaiveDateTime) -> Result<Vec<Deletion>, rusqlite::Error> {
+    let now = now.format(UTC_FORMAT).to_string();
+    let mut stmt = conn.prepare("SELECT tenant, chat_id, message_id FROM pending_deletions WHERE delete_at <= ?")?;
+    let due = stmt.query_map(params![now], |row| {
+        Ok(Deletion { tenant: row.get(0)?, chat_id: row.get(1)?, message_id: row.get(2)? })
+    })?
+    .collect::<Result<Vec<_>, _>>()?;
+    conn.execute("DELETE FROM pending_deletions WHERE delete_at <= ?", params![now])?;
+    Ok(due)
+}
+
+/// Удаляет сообщения, срок которых подошёл. Удаление не повторяется: сообщение могли уже удалить
+/// вручную, а у бота могло не быть прав на удаление чужих сообщений.
+pub async fn delete_due_messages(db: &Db, clock: &dyn Clock) {
+    let now = clock.now_utc();
+    let due = match db.call(move |conn| take_due(conn, now)).await {
+        Ok(due) => due,
+        Err(e) => {
+            log::error!("Failed to load pending deletions: {}", e);
+            return;
+        }
+    };
+
+    for deletion in due {
+        if let Err(e) = tenants::bot(&deletion.tenant)
+            .delete_message(ChatId(deletion.chat_id), MessageId(deletion.message_id))
+            .await
+        {
+            log::error!("Failed to delete message {} in chat {}: {}", deletion.message_id, deletion.chat_id, e);
+        }
+    }
+}
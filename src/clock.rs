@@ -0,0 +1,113 @@
+use std::sync::Mutex;
+
+use chrono::Duration;
+use chrono::{DateTime, NaiveDateTime, Utc};
+use chrono_tz::Tz;
+
+use crate::timezone;
+
+/// Источник текущего времени для планировщика и разбора дат. В работе — системные часы,
+/// в тестах и `reventor simulate` — `FixedClock`, чтобы проверять полночь, переводы часов и смену года.
+pub trait Clock: Send + Sync {
+    fn now(&self) -> DateTime<Utc>;
+
+    /// Текущий момент в UTC без пояса, как он хранится в `event_utc`.
+    fn now_utc(&self) -> NaiveDateTime {
+        self.now().naive_utc()
+    }
+
+    /// Текущее время на часах пользователя; без пояса — время сервера.
+    fn now_in(&self, tz: Option<Tz>) -> NaiveDateTime {
+        timezone::local_at(self.now(), tz)
+    }
+}
+
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+}
+
+/// Системные часы для обработчиков команд, которым время не внедряется.
+pub static SYSTEM: SystemClock = SystemClock;
+
+/// Часы, которые стоят, пока их не переведут.
+pub struct FixedClock {
+    now: Mutex<DateTime<Utc>>,
+}
+
+impl FixedClock {
+    pub fn new(now: DateTime<Utc>) -> Self {
+        FixedClock { now: Mutex::new(now) }
+    }
+
+    pub fn advance(&self, by: Duration) {
+        let mut now = self.now.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        *now += by;
+    }
+}
+
+impl Clock for FixedClock {
+    fn now(&self) -> DateTime<Utc> {
+        *self.now.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{NaiveDate, TimeZone};
+
+    use super::*;
+    use crate::settings::Settings;
+    use crate::shards::Shard;
+    use crate::storage::tests::memory_db;
+    use crate::{ensure_user_exists, get_due_events, insert_event, resolve_event_time, tenants};
+
+    fn utc(year: i32, month: u32, day: u32, hour: u32, minute: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(year, month, day, hour, minute, 0).unwrap()
+    }
+
+    fn in_zone(zone: &str) -> Settings {
+        Settings { timezone: timezone::parse_zone(zone), ..Settings::default() }
+    }
+
+    #[test]
+    fn date_without_year_rolls_over_at_local_new_year() {
+        // В UTC ещё 31 декабря, в Москве уже 1 января
+        let clock = FixedClock::new(utc(2025, 12, 31, 22, 30));
+        let time = resolve_event_time(&clock, Some("05.01"), "10:00", &in_zone("Europe/Moscow"));
+        assert_eq!(time, NaiveDate::from_ymd_opt(2026, 1, 5).and_then(|date| date.and_hms_opt(10, 0, 0)));
+    }
+
+    #[test]
+    fn event_without_date_is_today_until_local_midnight() {
+        let clock = FixedClock::new(utc(2025, 6, 10, 20, 59));
+        let settings = in_zone("Europe/Moscow");
+        let today = resolve_event_time(&clock, None, "09:00", &settings).unwrap();
+        assert_eq!(today.date(), NaiveDate::from_ymd_opt(2025, 6, 10).unwrap());
+
+        clock.advance(Duration::minutes(1));
+        let tomorrow = resolve_event_time(&clock, None, "09:00", &settings).unwrap();
+        assert_eq!(tomorrow.date(), NaiveDate::from_ymd_opt(2025, 6, 11).unwrap());
+    }
+
+    #[tokio::test]
+    async fn event_after_spring_forward_is_due_at_shifted_utc() {
+        let db = memory_db();
+        db.call(|conn| {
+            let user_id = ensure_user_exists(conn, tenants::DEFAULT, 42, None)?;
+            timezone::set_timezone(conn, 42, timezone::parse_zone("Europe/Berlin").unwrap())?;
+            // 30 марта 2025 в Берлине часы переводятся на летнее время: 03:30 — это 01:30 UTC
+            insert_event(conn, tenants::DEFAULT, user_id, 42, "После перевода часов", "30.03.2025 03:30")
+        }).await.unwrap();
+
+        let clock = FixedClock::new(utc(2025, 3, 30, 1, 29));
+        let due = |now| db.call(move |conn| get_due_events(conn, now, Shard::ALL));
+        assert!(due(clock.now_utc()).await.unwrap().is_empty());
+
+        clock.advance(Duration::minutes(1));
+        assert_eq!(due(clock.now_utc()).await.unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,119 @@
+use std::collections::HashMap;
+use std::env;
+use std::sync::{Arc, OnceLock, RwLock};
+use std::time::Duration;
+
+use reventor::parse::{self, Pipeline};
+
+use crate::i18n;
+use crate::settings::{self, QuietHours};
+
+const DEFAULT_POLL_INTERVAL: Duration = Duration::from_secs(10);
+const DEFAULT_OUTBOX_INTERVAL: Duration = Duration::from_secs(1);
+const DEFAULT_OUTBOX_WORKERS: usize = 8;
+
+/// Настройки, которые можно поменять без перезапуска: `SIGHUP` или `/admin reload`
+/// перечитывают `.env` и тексты из `MESSAGES_DIR`. Остальные переменные читаются один раз при запуске.
+#[derive(Debug)]
+pub struct Config {
+    /// Пауза между проходами планировщика из `POLL_INTERVAL_SECS`.
+    pub poll_interval: Duration,
+    /// Насколько заранее планировщик ставит напоминание в очередь, из `DELIVERY_TOLERANCE_SECS`.
+    /// Отправитель всё равно ждёт времени события, поэтому с окном, не меньшим паузы между проходами,
+    /// точность доставки определяет `outbox_interval`, а не редкие проходы планировщика.
+    pub delivery_tolerance: Duration,
+    /// Пауза между проходами отправителя очереди из `OUTBOX_INTERVAL_MS`.
+    pub outbox_interval: Duration,
+    /// Сколько чатов отправитель очереди обслуживает одновременно, из `OUTBOX_WORKERS`.
+    pub outbox_workers: usize,
+    /// Тихие часы для тех, кто не настраивал свои, из `DEFAULT_QUIET_HOURS`, например `23:00-08:00`.
+    pub quiet_hours: Option<QuietHours>,
+    /// Telegram id администраторов бота из `ADMIN_IDS` через запятую.
+    pub admin_ids: Vec<i64>,
+    /// Чат для обращений `/feedback` из `FEEDBACK_CHAT_ID`.
+    pub feedback_chat: Option<i64>,
+    /// Синтаксисы времени в событиях по порядку из `PARSERS`, например `syntax,ru`.
+    pub parsers: Pipeline,
+}
+
+impl Config {
+    /// Значения из `overrides` важнее переменных окружения процесса.
+    fn load(overrides: &HashMap<String, String>) -> Config {
+        let var = |key: &str| overrides.get(key).cloned().or_else(|| env::var(key).ok());
+        Config {
+            poll_interval: var("POLL_INTERVAL_SECS")
+                .and_then(|secs| secs.trim().parse().ok())
+                .filter(|secs| *secs > 0)
+                .map_or(DEFAULT_POLL_INTERVAL, Duration::from_secs),
+            delivery_tolerance: var("DELIVERY_TOLERANCE_SECS")
+                .and_then(|secs| secs.trim().parse().ok())
+                .map_or(Duration::ZERO, Duration::from_secs),
+            outbox_interval: var("OUTBOX_INTERVAL_MS")
+                .and_then(|ms| ms.trim().parse().ok())
+                .filter(|ms| *ms > 0)
+                .map_or(DEFAULT_OUTBOX_INTERVAL, Duration::from_millis),
+            outbox_workers: var("OUTBOX_WORKERS")
+                .and_then(|workers| workers.trim().parse().ok())
+                .filter(|workers| *workers > 0)
+                .unwrap_or(DEFAULT_OUTBOX_WORKERS),
+            quiet_hours: var("DEFAULT_QUIET_HOURS").and_then(|value| QuietHours::parse(value.trim())),
+            admin_ids: var("ADMIN_IDS")
+                .unwrap_or_default()
+                .split(',')
+                .filter_map(|id| id.trim().parse().ok())
+                .collect(),
+            feedback_chat: var("FEEDBACK_CHAT_ID").and_then(|id| id.trim().parse().ok()),
+            parsers: var("PARSERS").map_or_else(Pipeline::default, |names| {
+                Pipeline::from_names(&names).unwrap_or_else(|e| {
+                    log::error!("Invalid PARSERS, using {}: {}", parse::DEFAULT_PARSERS, e);
+                    Pipeline::default()
+                })
+            }),
+        }
+    }
+}
+
+fn current() -> &'static RwLock<Arc<Config>> {
+    static CONFIG: OnceLock<RwLock<Arc<Config>>> = OnceLock::new();
+    CONFIG.get_or_init(|| RwLock::new(Arc::new(Config::load(&HashMap::new()))))
+}
+
+pub fn get() -> Arc<Config> {
+    current().read().unwrap_or_else(|poisoned| poisoned.into_inner()).clone()
+}
+
+/// Перечитывает `.env` и тексты оператора. Значения из файла заменяют те, что были в окружении
+/// при запуске; текущие диалоги и сессии не затрагиваются. С ошибкой в текстах не меняется ничего.
+pub fn reload() -> Result<Arc<Config>, String> {
+    let messages = i18n::load_overrides()?;
+    log::info!("Message overrides reloaded: {}", messages);
+    // Файл читается без записи в окружение: менять переменные работающего процесса небезопасно
+    #[allow(deprecated)]
+    let overrides = dotenv::dotenv_iter()
+        .map_err(|e| e.to_string())?
+        .collect::<Result<HashMap<_, _>, _>>()
+        .map_err(|e| e.to_string())?;
+    let config = Arc::new(Config::load(&overrides));
+    log::info!("Configuration reloaded: {:?}", config);
+    *current().write().unwrap_or_else(|poisoned| poisoned.into_inner()) = config.clone();
+    settings::clear_cache();
+    Ok(config)
+}
+
+/// Перечитывает настройки по `SIGHUP`.
+pub fn spawn_reload_on_sighup() {
+    tokio::spawn(async {
+        let mut hangups = match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::hangup()) {
+            Ok(hangups) => hangups,
+            Err(e) => {
+                log::error!("Failed to listen for SIGHUP: {}", e);
+                return;
+            }
+        };
+        while hangups.recv().await.is_some() {
+            if let Err(e) = reload() {
+                log::error!("Failed to reload configuration: {}", e);
+            }
+        }
+    });
+}
@@ -0,0 +1,467 @@
+//! Доверенные контакты: пользователь привязывает другого человека с его согласия и ставит
+//! напоминания, которые придут в его чат: `/for mom @18:00 выпить лекарство`. Такие события
+//! видят и могут отменить обе стороны.
+
+use teloxide::prelude::*;
+use teloxide::types::{InlineKeyboardButton, InlineKeyboardMarkup};
+use rusqlite::{Connection, params, OptionalExtension};
+use reventor::parse;
+
+use crate::audit;
+use crate::chunks;
+use crate::clock::{self, Clock};
+use crate::i18n::{t, tf, Lang};
+use crate::settings::{self, Settings};
+use crate::{DatabaseError, Db, config, crypto, ensure_user_exists, in_transaction, insert_event, parse_event_time, set_event_end, set_event_recurrence, tenants};
+
+/// Имя контакта в `/for <имя>`: одно слово, без `@` и `#`, чтобы не путаться с временем и номерами.
+const MAX_NAME_CHARS: usize = 32;
+
+/// Привязка: `owner_id` ставит напоминания в личный чат `contact_id`.
+#[derive(Debug, PartialEq)]
+struct Link {
+    id: i64,
+    owner_id: i64,
+    contact_id: i64,
+    name: String,
+    active: bool,
+}
+
+/// Событие, поставленное через `/for`: кто поставил, кому и под каким именем.
+struct Shared {
+    id: i64,
+    text: String,
+    event_time: String,
+    owner_id: i64,
+    contact_id: i64,
+    name: String,
+}
+
+pub fn init_tables(conn: &Connection) -> Result<(), rusqlite::Error> {
+    // status: pending — ждёт согласия получателя, active — напоминания разрешены
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS trusted_contacts (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            owner_id INTEGER NOT NULL,
+            contact_id INTEGER NOT NULL,
+            name TEXT NOT NULL,
+            status TEXT NOT NULL DEFAULT 'pending',
+            created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            UNIQUE(owner_id, name),
+            UNIQUE(owner_id, contact_id)
+        )",
+        [],
+    )?;
+    conn.execute("CREATE INDEX IF NOT EXISTS idx_trusted_contacts_contact ON trusted_contacts(contact_id)", [])?;
+    Ok(())
+}
+
+fn valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && name.chars().count() <= MAX_NAME_CHARS
+        && !name.starts_with(['@', '#'])
+        && name.chars().all(|c| c.is_alphanumeric() || c == '_' || c == '-')
+}
+
+fn link_from_row(row: &rusqlite::Row) -> Result<Link, rusqlite::Error> {
+    Ok(Link {
+        id: row.get(0)?,
+        owner_id: row.get(1)?,
+        contact_id: row.get(2)?,
+        name: row.get(3)?,
+        active: row.get::<_, String>(4)? == "active",
+    })
+}
+
+fn get_link(conn: &Connection, link_id: i64) -> Result<Option<Link>, rusqlite::Error> {
+    conn.query_row(
+        "SELECT id, owner_id, contact_id, name, status FROM trusted_contacts WHERE id = ?",
+        params![link_id],
+        link_from_row,
+    ).optional()
+}
+
+fn find_by_name(conn: &Connection, owner_id: i64, name: &str) -> Result<Option<Link>, rusqlite::Error> {
+    conn.query_row(
+        "SELECT id, owner_id, contact_id, name, status FROM trusted_contacts WHERE owner_id = ? AND name = ?",
+        params![owner_id, name.to_lowercase()],
+        link_from_row,
+    ).optional()
+}
+
+/// Привязки, где пользователь с любой стороны.
+fn links_of(conn: &Connection, telegram_id: i64) -> Result<Vec<Link>, rusqlite::Error> {
+    let mut stmt = conn.prepare(
+        "SELECT id, owner_id, contact_id, name, status FROM trusted_contacts
+         WHERE owner_id = ? OR contact_id = ? ORDER BY owner_id = ? DESC, name",
+    )?;
+    let links = stmt.query_map(params![telegram_id, telegram_id, telegram_id], link_from_row)?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(links)
+}
+
+/// Запрос на привязку. Повторный запрос к тому же человеку только меняет имя: данное согласие
+/// остаётся в силе, а напоминания по привязке видны по-прежнему. Привязка к другому человеку
+/// под тем же именем снимается вместе с напоминаниями, как в `remove`.
+/// Возвращает id привязки и нужно ли спрашивать согласие. Вызывается внутри транзакции.
+fn request(conn: &Connection, owner_id: i64, contact_id: i64, name: &str) -> Result<(i64, bool), rusqlite::Error> {
+    let name = name.to_lowercase();
+    if let Some(taken) = find_by_name(conn, owner_id, &name)?.filter(|link| link.contact_id != contact_id) {
+        remove(conn, &taken)?;
+    }
+    let existing = conn.query_row(
+        "SELECT id, owner_id, contact_id, name, status FROM trusted_contacts WHERE owner_id = ? AND contact_id = ?",
+        params![owner_id, contact_id],
+        link_from_row,
+    ).optional()?;
+    if let Some(link) = existing {
+        conn.execute("UPDATE trusted_contacts SET name = ? WHERE id = ?", params![name, link.id])?;
+        return Ok((link.id, !link.active));
+    }
+    conn.execute(
+        "INSERT INTO trusted_contacts (owner_id, contact_id, name) VALUES (?, ?, ?)",
+        params![owner_id, contact_id, name],
+    )?;
+    Ok((conn.last_insert_rowid(), true))
+}
+
+/// Снимает привязку вместе с ещё не наступившими напоминаниями по ней: без согласия получателя
+/// они приходить не должны.
+fn remove(conn: &Connection, link: &Link) -> Result<usize, rusqlite::Error> {
+    let cancelled = conn.execute(
+        "DELETE FROM events WHERE status = 'pending' AND chat_id = ?
+         AND user_id IN (SELECT id FROM users WHERE telegram_id = ?)",
+        params![link.contact_id, link.owner_id],
+    )?;
+    conn.execute("DELETE FROM trusted_contacts WHERE id = ?", params![link.id])?;
+    Ok(cancelled)
+}
+
+/// Предстоящие события по действующим привязкам пользователя, с любой стороны.
+fn shared_events(conn: &Connection, telegram_id: i64) -> Result<Vec<Shared>, rusqlite::Error> {
+    let mut stmt = conn.prepare(
+        "SELECT e.id, e.text, e.event_time, c.owner_id, c.contact_id, c.name
+         FROM events e
+         JOIN users u ON e.user_id = u.id
+         JOIN trusted_contacts c ON c.owner_id = u.telegram_id AND c.contact_id = e.chat_id AND c.status = 'active'
+         WHERE e.status = 'pending' AND (c.owner_id = ?1 OR c.contact_id = ?1)
+         ORDER BY e.event_utc, e.id",
+    )?;
+    let events = stmt.query_map(params![telegram_id], |row| {
+        Ok(Shared {
+            id: row.get(0)?,
+            text: crypto::open(row.get(1)?),
+            event_time: row.get(2)?,
+            owner_id: row.get(3)?,
+            contact_id: row.get(4)?,
+            name: row.get(5)?,
+        })
+    })?
+    .collect::<Result<Vec<_>, _>>()?;
+    Ok(events)
+}
+
+fn username_of(conn: &Connection, telegram_id: i64) -> Result<String, rusqlite::Error> {
+    let username: Option<String> = conn.query_row(
+        "SELECT username FROM users WHERE telegram_id = ?",
+        params![telegram_id],
+        |row| row.get(0),
+    ).optional()?.flatten();
+    Ok(username.map_or_else(|| telegram_id.to_string(), |name| format!("@{}", name)))
+}
+
+fn lang_of(conn: &Connection, telegram_id: i64) -> Result<Lang, rusqlite::Error> {
+    Ok(settings::resolve(conn, telegram_id, telegram_id)?.lang)
+}
+
+fn consent_keyboard(lang: Lang, link_id: i64) -> InlineKeyboardMarkup {
+    InlineKeyboardMarkup::new(vec![vec![
+        InlineKeyboardButton::callback(t(lang, "contact_allow"), format!("contact:ok:{}", link_id)),
+        InlineKeyboardButton::callback(t(lang, "contact_decline"), format!("contact:no:{}", link_id)),
+    ]])
+}
+
+/// Сообщение другой стороне; ошибки только в лог — она могла остановить бота.
+async fn notify(bot: &Bot, telegram_id: i64, text: String) {
+    if let Err(e) = bot.send_message(ChatId(telegram_id), text).await {
+        log::error!("Failed to notify trusted contact {}: {}", telegram_id, e);
+    }
+}
+
+/// `/contact` — контакты и общие события, `/contact add <имя> @username` — попросить согласия,
+/// `/contact remove <имя|@username>` — снять привязку с любой стороны, `/contact cancel #id` — отменить
+/// общее событие. Работает только в личном чате: напоминания по привязке приходят туда же.
+pub async fn handle_contact_command(bot: &Bot, msg: &Message, db: &Db, args: &str, settings: &Settings) -> ResponseResult<()> {
+    let lang = settings.lang;
+    let Some(user) = msg.from() else {
+        return Ok(());
+    };
+    if !msg.chat.is_private() {
+        bot.send_message(msg.chat.id, t(lang, "contact_private_only")).await?;
+        return Ok(());
+    }
+    let (telegram_id, username, tenant) = (user.id.0 as i64, user.username.clone(), tenants::name_of(bot));
+    let parts = args.split_whitespace().map(str::to_string).collect::<Vec<_>>();
+    let settings = *settings;
+
+    match parts.iter().map(String::as_str).collect::<Vec<_>>().as_slice() {
+        [] => {
+            let response = db.call(move |conn| {
+                ensure_user_exists(conn, tenant, telegram_id, username)?;
+                let links = links_of(conn, telegram_id)?;
+                if links.is_empty() {
+                    return Ok(t(lang, "contact_none"));
+                }
+                let mut lines = Vec::new();
+                for link in &links {
+                    let key = match (link.owner_id == telegram_id, link.active) {
+                        (true, true) => "contact_line_mine",
+                        (true, false) => "contact_line_pending",
+                        (false, _) => "contact_line_theirs",
+                    };
+                    let other = username_of(conn, if link.owner_id == telegram_id { link.contact_id } else { link.owner_id })?;
+                    lines.push(tf(lang, key, &[("name", &link.name), ("user", &other)]));
+                }
+                let events = shared_events(conn, telegram_id)?
+                    .into_iter()
+                    .map(|event| {
+                        let time = parse_event_time(&event.event_time).map_or(event.event_time, |time| settings.format_datetime(time));
+                        let key = if event.owner_id == telegram_id { "contact_event_for" } else { "contact_event_from" };
+                        let other = username_of(conn, if event.owner_id == telegram_id { event.contact_id } else { event.owner_id })?;
+                        Ok(tf(lang, key, &[("id", &event.id), ("time", &time), ("text", &event.text), ("name", &event.name), ("user", &other)]))
+                    })
+                    .collect::<Result<Vec<_>, rusqlite::Error>>()?;
+                let events = if events.is_empty() { t(lang, "contact_no_events") } else { events.join("\n") };
+                Ok(tf(lang, "contact_list", &[("contacts", &lines.join("\n")), ("events", &events)]))
+            }).await.map_err(DatabaseError)?;
+            chunks::send(bot, msg.chat.id, response).await?;
+        }
+        ["add", name, target] if valid_name(name) && target.starts_with('@') => {
+            let (name, target) = (name.to_string(), target.trim_start_matches('@').to_lowercase());
+            let requested_name = name.clone();
+            let requested = db.call(move |conn| in_transaction(conn, |conn| {
+                ensure_user_exists(conn, tenant, telegram_id, username)?;
+                let contact: Option<i64> = conn.query_row(
+                    "SELECT telegram_id FROM users WHERE LOWER(username) = ?",
+                    params![target],
+                    |row| row.get(0),
+                ).optional()?;
+                match contact {
+                    Some(contact_id) if contact_id != telegram_id => {
+                        let (link_id, ask) = request(conn, telegram_id, contact_id, &name)?;
+                        Ok(Some((link_id, ask, contact_id, lang_of(conn, contact_id)?, username_of(conn, telegram_id)?)))
+                    }
+                    _ => Ok(None),
+                }
+            })).await.map_err(DatabaseError)?;
+            let Some((link_id, ask, contact_id, contact_lang, owner)) = requested else {
+                bot.send_message(msg.chat.id, t(lang, "contact_unknown_user")).await?;
+                return Ok(());
+            };
+            if !ask {
+                bot.send_message(msg.chat.id, tf(lang, "contact_renamed", &[("name", &requested_name.to_lowercase())])).await?;
+                return Ok(());
+            }
+            let request = tf(contact_lang, "contact_request", &[("user", &owner), ("name", &user.full_name())]);
+            match bot.send_message(ChatId(contact_id), request).reply_markup(consent_keyboard(contact_lang, link_id)).await {
+                Ok(_) => bot.send_message(msg.chat.id, t(lang, "contact_requested")).await?,
+                Err(e) => {
+                    log::error!("Failed to ask {} for trusted contact consent: {}", contact_id, e);
+                    bot.send_message(msg.chat.id, t(lang, "contact_unknown_user")).await?
+                }
+            };
+        }
+        ["remove", who] => {
+            let who = who.to_lowercase();
+            let removed = db.call(move |conn| {
+                let link = match who.strip_prefix('@') {
+                    // Получатель снимает привязку по имени пользователя того, кто её создал
+                    Some(username) => links_of(conn, telegram_id)?.into_iter().find(|link| {
+                        let other = if link.owner_id == telegram_id { link.contact_id } else { link.owner_id };
+                        username_of(conn, other).is_ok_and(|other| other.to_lowercase() == format!("@{}", username))
+                    }),
+                    None => find_by_name(conn, telegram_id, &who)?,
+                };
+                let Some(link) = link else {
+                    return Ok(None);
+                };
+                let cancelled = remove(conn, &link)?;
+                let other = if link.owner_id == telegram_id { link.contact_id } else { link.owner_id };
+                Ok(Some((other, lang_of(conn, other)?, username_of(conn, telegram_id)?, cancelled)))
+            }).await.map_err(DatabaseError)?;
+            match removed {
+                Some((other, other_lang, me, cancelled)) => {
+                    bot.send_message(msg.chat.id, tf(lang, "contact_removed", &[("count", &cancelled)])).await?;
+                    notify(bot, other, tf(other_lang, "contact_removed_by", &[("user", &me)])).await;
+                }
+                None => {
+                    bot.send_message(msg.chat.id, t(lang, "contact_not_found")).await?;
+                }
+            }
+        }
+        ["cancel", id] => {
+            let Ok(event_id) = id.trim_start_matches('#').parse::<i64>() else {
+                bot.send_message(msg.chat.id, t(lang, "contact_usage")).await?;
+                return Ok(());
+            };
+            let cancelled = db.call(move |conn| in_transaction(conn, |tx| {
+                let Some(event) = shared_events(tx, telegram_id)?.into_iter().find(|event| event.id == event_id) else {
+                    return Ok(None);
+                };
+                let actor = ensure_user_exists(tx, tenant, telegram_id, username)?;
+                audit::record(tx, event_id, Some(actor), "delete", Some((&event.event_time, &event.text)), None)?;
+                tx.execute("DELETE FROM events WHERE id = ?", params![event_id])?;
+                let other = if event.owner_id == telegram_id { event.contact_id } else { event.owner_id };
+                Ok(Some((other, lang_of(tx, other)?, username_of(tx, telegram_id)?, event.text)))
+            })).await.map_err(DatabaseError)?;
+            match cancelled {
+                Some((other, other_lang, me, text)) => {
+                    bot.send_message(msg.chat.id, tf(lang, "contact_event_cancelled", &[("id", &event_id)])).await?;
+                    notify(bot, other, tf(other_lang, "contact_event_cancelled_by", &[("user", &me), ("text", &text)])).await;
+                }
+                None => {
+                    bot.send_message(msg.chat.id, t(lang, "event_not_found")).await?;
+                }
+            }
+        }
+        _ => {
+            bot.send_message(msg.chat.id, t(lang, "contact_usage")).await?;
+        }
+    }
+    Ok(())
+}
+
+/// `/for <имя> @время текст` — напоминание в чат контакта. Время понимается по часам того,
+/// кто ставит напоминание, как и у его собственных событий.
+pub async fn handle_for_command(bot: &Bot, msg: &Message, db: &Db, args: &str, settings: &Settings) -> ResponseResult<()> {
+    let lang = settings.lang;
+    let Some(user) = msg.from() else {
+        return Ok(());
+    };
+    let Some((name, text)) = args.trim().split_once(char::is_whitespace) else {
+        bot.send_message(msg.chat.id, t(lang, "contact_for_usage")).await?;
+        return Ok(());
+    };
+    let parsed = match config::get().parsers.parse(text.trim(), settings.locale(), clock::SYSTEM.now(), settings.timezone) {
+        Ok(parsed) => parsed,
+        Err(_) => {
+            bot.send_message(msg.chat.id, t(lang, "contact_for_usage")).await?;
+            return Ok(());
+        }
+    };
+    let (telegram_id, username, tenant) = (user.id.0 as i64, user.username.clone(), tenants::name_of(bot));
+    let (name, event_text, time, end_time) = (name.to_string(), parsed.text.clone(), parse::store(parsed.datetime), parsed.end.map(parse::store));
+    let recurrence = parsed.recurrence.clone();
+
+    let created = db.call(move |conn| in_transaction(conn, |tx| {
+        let user_id = ensure_user_exists(tx, tenant, telegram_id, username)?;
+        let Some(link) = find_by_name(tx, telegram_id, &name)?.filter(|link| link.active) else {
+            return Ok(None);
+        };
+        let event_id = insert_event(tx, tenant, user_id, link.contact_id, &event_text, &time)?;
+        set_event_end(tx, event_id, end_time.as_deref())?;
+        set_event_recurrence(tx, event_id, recurrence.as_ref())?;
+        Ok(Some((event_id, link.contact_id, lang_of(tx, link.contact_id)?, username_of(tx, telegram_id)?)))
+    })).await.map_err(DatabaseError)?;
+
+    let Some((event_id, contact_id, contact_lang, owner)) = created else {
+        bot.send_message(msg.chat.id, t(lang, "contact_not_found")).await?;
+        return Ok(());
+    };
+    let when = settings.format_datetime(parsed.datetime);
+    bot.send_message(msg.chat.id, tf(lang, "contact_for_saved", &[("id", &event_id), ("time", &when), ("text", &parsed.text)])).await?;
+    notify(bot, contact_id, tf(contact_lang, "contact_for_received", &[("user", &owner), ("id", &event_id), ("time", &when), ("text", &parsed.text)])).await;
+    Ok(())
+}
+
+/// Ответ на запрос привязки: `ok:<id>` или `no:<id>`. Ответить может только тот, кого просили.
+pub async fn handle_callback(bot: &Bot, q: &CallbackQuery, db: &Db, args: &str) -> ResponseResult<()> {
+    let Some(message) = q.message.as_ref() else {
+        bot.answer_callback_query(q.id.clone()).await?;
+        return Ok(());
+    };
+    let (answer, link_id) = match args.split_once(':') {
+        Some((answer @ ("ok" | "no"), id)) => (answer == "ok", id.parse::<i64>().unwrap_or_default()),
+        _ => {
+            bot.answer_callback_query(q.id.clone()).await?;
+            return Ok(());
+        }
+    };
+    let telegram_id = q.from.id.0 as i64;
+    let decided = db.call(move |conn| {
+        let lang = lang_of(conn, telegram_id)?;
+        let Some(link) = get_link(conn, link_id)?.filter(|link| link.contact_id == telegram_id && !link.active) else {
+            return Ok((lang, None));
+        };
+        if answer {
+            conn.execute("UPDATE trusted_contacts SET status = 'active' WHERE id = ?", params![link_id])?;
+        } else {
+            remove(conn, &link)?;
+        }
+        Ok((lang, Some((link.owner_id, lang_of(conn, link.owner_id)?, username_of(conn, telegram_id)?, link.name))))
+    }).await.map_err(DatabaseError)?;
+
+    bot.answer_callback_query(q.id.clone()).await?;
+    let (lang, decided) = decided;
+    let Some((owner_id, owner_lang, me, name)) = decided else {
+        bot.edit_message_text(message.chat.id, message.id, t(lang, "contact_request_expired")).await?;
+        return Ok(());
+    };
+    let (mine, theirs) = if answer { ("contact_accepted", "contact_accepted_by") } else { ("contact_declined", "contact_declined_by") };
+    bot.edit_message_text(message.chat.id, message.id, t(lang, mine)).await?;
+    notify(bot, owner_id, tf(owner_lang, theirs, &[("user", &me), ("name", &name)])).await;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::tests::memory_db;
+
+    #[tokio::test]
+    async fn both_sides_see_events_until_the_link_is_removed() {
+        let db = memory_db();
+        let (before, owner_view, contact_view, cancelled, after) = db.call(|conn| {
+            let owner = ensure_user_exists(conn, tenants::DEFAULT, 1, Some("son".to_string()))?;
+            ensure_user_exists(conn, tenants::DEFAULT, 2, Some("mom".to_string()))?;
+            let (link_id, _) = request(conn, 1, 2, "Mom")?;
+            insert_event(conn, tenants::DEFAULT, owner, 2, "Выпить лекарство", "01.01.2030 18:00")?;
+            // Пока получатель не согласился, событие в общий список не попадает
+            let before = shared_events(conn, 2)?.len();
+            conn.execute("UPDATE trusted_contacts SET status = 'active' WHERE id = ?", params![link_id])?;
+            let owner_view = shared_events(conn, 1)?.into_iter().map(|event| (event.name, event.text)).collect::<Vec<_>>();
+            let contact_view = shared_events(conn, 2)?.len();
+            let link = find_by_name(conn, 1, "mom")?.unwrap();
+            let cancelled = remove(conn, &link)?;
+            Ok((before, owner_view, contact_view, cancelled, links_of(conn, 2)?.len()))
+        }).await.unwrap();
+        assert_eq!(before, 0);
+        assert_eq!(owner_view, vec![("mom".to_string(), "Выпить лекарство".to_string())]);
+        assert_eq!((contact_view, cancelled, after), (1, 1, 0));
+        assert!(valid_name("мама") && !valid_name("@mom") && !valid_name("#1"));
+    }
+
+    #[tokio::test]
+    async fn re_requests_rename_in_place_and_replacements_cancel_reminders() {
+        let db = memory_db();
+        let (ask, renamed, replaced, left) = db.call(|conn| {
+            let owner = ensure_user_exists(conn, tenants::DEFAULT, 1, Some("son".to_string()))?;
+            ensure_user_exists(conn, tenants::DEFAULT, 2, Some("mom".to_string()))?;
+            ensure_user_exists(conn, tenants::DEFAULT, 3, Some("dad".to_string()))?;
+            let (link_id, _) = request(conn, 1, 2, "mom")?;
+            conn.execute("UPDATE trusted_contacts SET status = 'active' WHERE id = ?", params![link_id])?;
+            insert_event(conn, tenants::DEFAULT, owner, 2, "Выпить лекарство", "01.01.2030 18:00")?;
+            // Новое имя для того же человека: согласие и общее событие остаются
+            let (same_id, ask) = request(conn, 1, 2, "mama")?;
+            let renamed = (same_id == link_id, shared_events(conn, 2)?.len());
+            // Имя отдано другому человеку: старая привязка снимается вместе с напоминаниями
+            let (_, replaced) = request(conn, 1, 3, "mama")?;
+            let left: i64 = conn.query_row("SELECT COUNT(*) FROM events WHERE chat_id = 2", [], |row| row.get(0))?;
+            Ok((ask, renamed, replaced, left))
+        }).await.unwrap();
+        assert!(!ask);
+        assert_eq!(renamed, (true, 1));
+        assert!(replaced);
+        assert_eq!(left, 0);
+    }
+}
@@ -0,0 +1,110 @@
+use std::env;
+use std::sync::OnceLock;
+
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use rusqlite::{Connection, params};
+
+use crate::in_transaction;
+
+/// Метка зашифрованного значения в БД; строки без неё хранятся открытым текстом.
+const PREFIX: &str = "enc:v1:";
+/// Длина nonce AES-GCM в байтах.
+const NONCE_LEN: usize = 12;
+/// Что увидит пользователь, если значение не удалось расшифровать.
+const UNREADABLE: &str = "🔒";
+
+/// Таблицы и столбцы с текстом пользователя, которые шифруются.
+const COLUMNS: [(&str, &str); 8] = [
+    ("events", "text"),
+    ("checklist_items", "text"),
+    ("event_drafts", "text"),
+    ("import_drafts", "items"),
+    ("mirror_webhooks", "url"),
+    ("mirror_deliveries", "text"),
+    ("audit_log", "old_text"),
+    ("audit_log", "new_text"),
+];
+
+/// Ключ из `EVENT_TEXT_KEY`: 32 байта в hex (`openssl rand -hex 32`). Без ключа текст хранится как есть.
+fn cipher() -> Option<&'static Aes256Gcm> {
+    static CIPHER: OnceLock<Option<Aes256Gcm>> = OnceLock::new();
+    CIPHER.get_or_init(|| {
+        let key = env::var("EVENT_TEXT_KEY").ok()?;
+        match hex::decode(key.trim()) {
+            Ok(key) if key.len() == 32 => Some(Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key))),
+            _ => {
+                log::error!("EVENT_TEXT_KEY must be 32 bytes in hex, encryption is disabled");
+                None
+            }
+        }
+    }).as_ref()
+}
+
+/// Значение для записи в БД: зашифрованное, если задан ключ.
+pub fn seal(text: &str) -> String {
+    let Some(cipher) = cipher() else {
+        return text.to_string();
+    };
+    let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+    let ciphertext = cipher.encrypt(&nonce, text.as_bytes()).expect("AES-GCM encryption does not fail");
+    format!("{}{}{}", PREFIX, hex::encode(nonce), hex::encode(ciphertext))
+}
+
+/// Текст из БД: зашифрованные значения расшифровываются, открытые возвращаются как есть.
+pub fn open(stored: String) -> String {
+    let Some(sealed) = stored.strip_prefix(PREFIX) else {
+        return stored;
+    };
+    let Some(cipher) = cipher() else {
+        log::error!("Found encrypted text, but EVENT_TEXT_KEY is not set");
+        return UNREADABLE.to_string();
+    };
+    let text = hex::decode(sealed).ok().filter(|bytes| bytes.len() > NONCE_LEN).and_then(|bytes| {
+        let (nonce, ciphertext) = bytes.split_at(NONCE_LEN);
+        let plain = cipher.decrypt(Nonce::from_slice(nonce), ciphertext).ok()?;
+        String::from_utf8(plain).ok()
+    });
+    text.unwrap_or_else(|| {
+        log::error!("Failed to decrypt text, EVENT_TEXT_KEY may have changed");
+        UNREADABLE.to_string()
+    })
+}
+
+/// Шифрует записи, сохранённые до появления ключа. Без ключа только сообщает
+/// о зашифрованных строках, которые бот не сможет прочитать.
+pub fn encrypt_existing(conn: &Connection) -> Result<(), rusqlite::Error> {
+    let pattern = format!("{}%", PREFIX);
+    for (table, column) in COLUMNS {
+        if cipher().is_none() {
+            let sealed: i64 = conn.query_row(
+                &format!("SELECT COUNT(*) FROM {} WHERE {} LIKE ?", table, column),
+                params![pattern],
+                |row| row.get(0),
+            )?;
+            if sealed > 0 {
+                log::error!("{} rows in {} are encrypted, but EVENT_TEXT_KEY is not set", sealed, table);
+            }
+            continue;
+        }
+
+        let rows = {
+            let mut stmt = conn.prepare(&format!("SELECT id, {} FROM {} WHERE {} NOT LIKE ?", column, table, column))?;
+            let rows = stmt
+                .query_map(params![pattern], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?)))?
+                .collect::<Result<Vec<_>, _>>()?;
+            rows
+        };
+        if rows.is_empty() {
+            continue;
+        }
+        in_transaction(conn, |tx| {
+            for (id, text) in &rows {
+                tx.execute(&format!("UPDATE {} SET {} = ? WHERE id = ?", table, column), params![seal(text), id])?;
+            }
+            Ok(())
+        })?;
+        log::info!("Encrypted {} existing rows in {}", rows.len(), table);
+    }
+    Ok(())
+}
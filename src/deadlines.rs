@@ -0,0 +1,214 @@
+//! Дедлайны: к событию-дедлайну сами приходят предупреждения за неделю, за три дня, за день
+//! и за три часа, каждое настойчивее предыдущего. Вместо четырёх отдельных событий —
+//! `/deadline @20.06 18:00 Сдать диплом` или `/deadline #id` для уже созданного.
+
+use teloxide::prelude::*;
+use chrono::{Duration, NaiveDateTime};
+use rusqlite::{Connection, params};
+
+use reventor::parse;
+
+use crate::accessibility;
+use crate::blocklist;
+use crate::categories;
+use crate::chunks;
+use crate::clock::{self, Clock};
+use crate::config;
+use crate::groups;
+use crate::humanize;
+use crate::i18n::{t, tf};
+use crate::outbox::{self, Outgoing};
+use crate::settings::{self, Settings};
+use crate::timezone;
+use crate::{DatabaseError, Db, UTC_FORMAT, add_column_if_missing, crypto, ensure_user_exists, get_event, in_transaction, insert_event, parse_event_time, set_event_end, set_event_recurrence, tenants};
+
+/// Предупреждения: за сколько минут до срока и каким текстом, от самого раннего.
+const STAGES: &[(i64, &str)] = &[
+    (7 * 24 * 60, "deadline_week"),
+    (3 * 24 * 60, "deadline_3d"),
+    (24 * 60, "deadline_1d"),
+    (3 * 60, "deadline_3h"),
+];
+
+pub fn init_tables(conn: &Connection) -> Result<(), rusqlite::Error> {
+    // Событие-дедлайн и сколько предупреждений по нему уже пройдено
+    add_column_if_missing(conn, "events", "deadline", "INTEGER NOT NULL DEFAULT 0")?;
+    add_column_if_missing(conn, "events", "deadline_stage", "INTEGER NOT NULL DEFAULT 0")?;
+    Ok(())
+}
+
+/// Сколько предупреждений уже должно было прийти к моменту `now`.
+fn passed_stages(event_utc: NaiveDateTime, now: NaiveDateTime) -> usize {
+    STAGES.iter().filter(|(minutes, _)| event_utc - Duration::minutes(*minutes) <= now).count()
+}
+
+/// Включает или выключает режим дедлайна. Предупреждения, время которых уже прошло,
+/// не отправляются: за два дня до срока «осталась неделя» только запутает.
+pub fn set_deadline(conn: &Connection, event_id: i64, enabled: bool, now: NaiveDateTime) -> Result<(), rusqlite::Error> {
+    let event_utc: Option<String> = conn.query_row("SELECT event_utc FROM events WHERE id = ?", params![event_id], |row| row.get(0))?;
+    let stage = event_utc
+        .and_then(|utc| NaiveDateTime::parse_from_str(&utc, UTC_FORMAT).ok())
+        .map_or(STAGES.len(), |utc| passed_stages(utc, now));
+    conn.execute("UPDATE events SET deadline = ?, deadline_stage = ? WHERE id = ?", params![enabled, stage as i64, event_id])?;
+    Ok(())
+}
+
+/// Ставит в очередь наступившие предупреждения. Если пропущено несколько (бот не работал),
+/// уходит только последнее. Перенос срока позже возвращает счётчик назад, и предупреждения
+/// приходят заново относительно нового времени.
+pub fn queue_due(conn: &Connection, now: NaiveDateTime) -> Result<Vec<i64>, rusqlite::Error> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT e.id, e.tenant, u.telegram_id, COALESCE(e.chat_id, u.telegram_id), e.text, e.icon, e.event_utc, e.event_time, e.deadline_stage
+         FROM events e
+         JOIN users u ON e.user_id = u.id
+         WHERE e.deadline = 1 AND e.status = 'pending' AND e.event_utc > ? AND {}",
+        blocklist::NOT_BLOCKED
+    ))?;
+    let candidates = stmt.query_map(params![now.format(UTC_FORMAT).to_string()], |row| {
+        Ok((
+            row.get::<_, i64>(0)?,
+            row.get::<_, String>(1)?,
+            row.get::<_, i64>(2)?,
+            row.get::<_, i64>(3)?,
+            categories::label(row.get::<_, Option<String>>(5)?.as_deref(), &crypto::open(row.get(4)?)),
+            row.get::<_, String>(6)?,
+            row.get::<_, String>(7)?,
+            row.get::<_, i64>(8)? as usize,
+        ))
+    })?
+    .collect::<Result<Vec<_>, _>>()?;
+
+    let mut queued = Vec::new();
+    for (event_id, tenant, owner_id, chat_id, text, event_utc, event_time, stage) in candidates {
+        let Ok(utc) = NaiveDateTime::parse_from_str(&event_utc, UTC_FORMAT) else {
+            continue;
+        };
+        let passed = passed_stages(utc, now);
+        if passed == stage {
+            continue;
+        }
+        let settings = settings::resolve(conn, owner_id, chat_id).unwrap_or_default();
+        if passed > stage && settings.is_quiet(timezone::from_utc(now, settings.timezone).time()) {
+            continue;
+        }
+        in_transaction(conn, |tx| {
+            tx.execute("UPDATE events SET deadline_stage = ? WHERE id = ?", params![passed as i64, event_id])?;
+            if passed > stage {
+                let time = parse_event_time(&event_time).map_or(event_time.clone(), |time| settings.format_datetime(time));
+                let text = accessibility::with(accessibility::of(&settings), || {
+                    tf(settings.lang, STAGES[passed - 1].1, &[
+                        ("id", &event_id),
+                        ("text", &text),
+                        ("time", &time),
+                        ("left", &humanize::duration(settings.lang, utc - now)),
+                    ])
+                });
+                outbox::push(tx, &Outgoing { tenant: tenant.clone(), chat_id, text, reply_markup: None, reminder: None }, now)?;
+            }
+            Ok(())
+        })?;
+        if passed > stage {
+            queued.push(event_id);
+        }
+    }
+    Ok(queued)
+}
+
+pub async fn send_due_deadlines(db: &Db, clock: &dyn Clock) {
+    let now = clock.now_utc();
+    match db.call(move |conn| queue_due(conn, now)).await {
+        Ok(queued) if !queued.is_empty() => log::info!("Queued deadline warnings for events {:?}", queued),
+        Ok(_) => {}
+        Err(e) => log::error!("Failed to queue deadline warnings: {}", e),
+    }
+}
+
+/// `/deadline @время текст` — новое событие-дедлайн; `/deadline #id` — сделать дедлайном
+/// существующее событие; `/deadline off #id` — обычное событие без предупреждений.
+pub async fn handle_deadline_command(bot: &Bot, msg: &Message, db: &Db, args: &str, settings: &Settings) -> ResponseResult<()> {
+    let lang = settings.lang;
+    let Some(user) = msg.from() else {
+        return Ok(());
+    };
+    if !groups::can_manage_events(bot, msg, db).await? {
+        return groups::reply_not_allowed(bot, msg, db).await;
+    }
+    let (telegram_id, username, chat_id, tenant) = (user.id.0 as i64, user.username.clone(), msg.chat.id.0, tenants::name_of(bot));
+    let now = clock::SYSTEM.now_utc();
+    let args = args.trim();
+
+    let (enabled, id) = match args.strip_prefix("off") {
+        Some(id) => (false, id.trim()),
+        None => (true, args),
+    };
+    if let Some(id) = id.strip_prefix('#') {
+        let Ok(event_id) = id.parse::<i64>() else {
+            bot.send_message(msg.chat.id, t(lang, "deadline_usage")).await?;
+            return Ok(());
+        };
+        let updated = db.call(move |conn| {
+            match get_event(conn, event_id)?.filter(|event| event.chat_id == chat_id && event.owner_id == telegram_id) {
+                Some(_) => set_deadline(conn, event_id, enabled, now).map(|_| true),
+                None => Ok(false),
+            }
+        }).await.map_err(DatabaseError)?;
+        let response = match (updated, enabled) {
+            (false, _) => t(lang, "event_not_found"),
+            (true, true) => tf(lang, "deadline_on", &[("id", &event_id)]),
+            (true, false) => tf(lang, "deadline_off", &[("id", &event_id)]),
+        };
+        chunks::send(bot, msg.chat.id, response).await?;
+        return Ok(());
+    }
+
+    let parsed = match config::get().parsers.parse(args, settings.locale(), clock::SYSTEM.now(), settings.timezone) {
+        Ok(parsed) if enabled => parsed,
+        _ => {
+            bot.send_message(msg.chat.id, t(lang, "deadline_usage")).await?;
+            return Ok(());
+        }
+    };
+    let (text, time, end_time, recurrence) = (parsed.text.clone(), parse::store(parsed.datetime), parsed.end.map(parse::store), parsed.recurrence.clone());
+    let event_id = db.call(move |conn| in_transaction(conn, |tx| {
+        let user_id = ensure_user_exists(tx, tenant, telegram_id, username)?;
+        let event_id = insert_event(tx, tenant, user_id, chat_id, &text, &time)?;
+        set_event_end(tx, event_id, end_time.as_deref())?;
+        set_event_recurrence(tx, event_id, recurrence.as_ref())?;
+        set_deadline(tx, event_id, true, now)?;
+        Ok(event_id)
+    })).await.map_err(DatabaseError)?;
+    log::info!("Created deadline event {}", event_id);
+    let response = tf(lang, "deadline_saved", &[("id", &event_id), ("time", &settings.format_datetime(parsed.datetime)), ("text", &parsed.text)]);
+    chunks::send(bot, msg.chat.id, response).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::tests::memory_db;
+
+    fn at(value: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(value, UTC_FORMAT).unwrap()
+    }
+
+    #[tokio::test]
+    async fn warnings_escalate_and_skip_the_missed_ones() {
+        let db = memory_db();
+        let sent = db.call(|conn| {
+            let user_id = ensure_user_exists(conn, tenants::DEFAULT, 42, None)?;
+            timezone::set_timezone(conn, 42, chrono_tz::UTC)?;
+            let event_id = insert_event(conn, tenants::DEFAULT, user_id, 42, "Сдать диплом", "20.06.2030 18:00")?;
+            // Дедлайн включён за пять дней: предупреждение за неделю уже не нужно
+            set_deadline(conn, event_id, true, at("2030-06-15 18:00:00"))?;
+            let mut sent = Vec::new();
+            for now in ["2030-06-15 19:00:00", "2030-06-17 18:00:00", "2030-06-17 18:05:00", "2030-06-20 16:00:00"] {
+                sent.push(queue_due(conn, at(now))?.len());
+            }
+            let stage: i64 = conn.query_row("SELECT deadline_stage FROM events WHERE id = ?", params![event_id], |row| row.get(0))?;
+            Ok((sent, stage))
+        }).await.unwrap();
+        // За день до срока бот не работал: сразу за три часа уходит только последнее предупреждение
+        assert_eq!(sent, (vec![0, 1, 0, 1], 4));
+    }
+}
@@ -0,0 +1,183 @@
+use teloxide::prelude::*;
+use teloxide::types::InlineKeyboardMarkup;
+use chrono::{NaiveDate, NaiveDateTime};
+use rusqlite::{Connection, params};
+
+use crate::accessibility;
+use crate::blocklist;
+use crate::categories;
+use crate::chunks;
+use crate::clock::Clock;
+use crate::holidays;
+use crate::humanize;
+use crate::i18n::{t, tf};
+use crate::overdue;
+use crate::settings::{self, Settings};
+use crate::stars;
+use crate::tasks;
+use crate::timezone;
+use crate::weather;
+use crate::{Db, add_column_if_missing, crypto, parse_event_time, tenants};
+
+#[derive(Debug)]
+struct DigestUser {
+    telegram_id: i64,
+    tenant: String,
+    digest_time: String,
+    sent_on: Option<String>,
+}
+
+pub fn init_tables(conn: &Connection) -> Result<(), rusqlite::Error> {
+    // Дата последней отправленной сводки, чтобы не слать её дважды за день
+    add_column_if_missing(conn, "users", "digest_sent_on", "TEXT")?;
+    Ok(())
+}
+
+fn get_digest_users(conn: &Connection) -> Result<Vec<DigestUser>, rusqlite::Error> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT u.telegram_id, u.digest_time, u.digest_sent_on, u.tenant FROM users u
+         WHERE u.digest_time IS NOT NULL AND u.digest_time != 'off' AND {}",
+        blocklist::NOT_BLOCKED
+    ))?;
+    let users = stmt.query_map([], |row| {
+        Ok(DigestUser {
+            telegram_id: row.get(0)?,
+            tenant: row.get(3)?,
+            digest_time: row.get(1)?,
+            sent_on: row.get(2)?,
+        })
+    })?
+    .collect::<Result<Vec<_>, _>>()?;
+    Ok(users)
+}
+
+fn mark_digest_sent(conn: &Connection, telegram_id: i64, date: NaiveDate) -> Result<(), rusqlite::Error> {
+    conn.execute(
+        "UPDATE users SET digest_sent_on = ? WHERE telegram_id = ?",
+        params![date.format("%Y-%m-%d").to_string(), telegram_id],
+    )?;
+    Ok(())
+}
+
+fn get_day_events(conn: &Connection, telegram_id: i64, date: NaiveDate) -> Result<Vec<(NaiveDateTime, String)>, rusqlite::Error> {
+    let mut stmt = conn.prepare(
+        "SELECT e.event_time, e.text, e.icon FROM events e
+         JOIN users u ON e.user_id = u.id
+         WHERE u.telegram_id = ? AND e.status = 'pending' AND e.event_time LIKE ?"
+    )?;
+    let mut events = stmt.query_map(params![telegram_id, format!("{} %", date.format("%d.%m.%Y"))], |row| {
+        Ok((row.get::<_, String>(0)?, categories::label(row.get::<_, Option<String>>(2)?.as_deref(), &crypto::open(row.get(1)?))))
+    })?
+    .collect::<Result<Vec<_>, _>>()?
+    .into_iter()
+    .filter_map(|(time, text)| parse_event_time(&time).map(|time| (time, text)))
+    .collect::<Vec<_>>();
+
+    events.sort_by_key(|(time, _)| *time);
+    Ok(events)
+}
+
+/// Текст сводки с кнопками переноса пропущенных или `None`, если на сегодня нечего сообщить.
+fn build_digest(
+    conn: &Connection,
+    telegram_id: i64,
+    settings: &Settings,
+    now: NaiveDateTime,
+) -> Result<Option<(String, Option<InlineKeyboardMarkup>)>, rusqlite::Error> {
+    let lang = settings.lang;
+    let today = now.date();
+    let events = get_day_events(conn, telegram_id, today)?;
+    let open_tasks = tasks::get_open_tasks(conn, telegram_id)?;
+    let holidays = settings.country.and_then(|country| holidays::describe_day(country, today, lang));
+    // Сегодняшние избранные уже есть в списке событий
+    let mut starred = stars::open_starred(conn, telegram_id)?;
+    starred.retain(|(time, _)| time.date() != today);
+
+    let overdue = overdue::get_overdue(conn, telegram_id)?;
+
+    if events.is_empty() && open_tasks.is_empty() && holidays.is_none() && starred.is_empty() && overdue.is_empty() {
+        return Ok(None);
+    }
+
+    let mut sections = vec![t(lang, "digest_header")];
+    sections.extend(holidays);
+    if !events.is_empty() {
+        let list = events
+            .iter()
+            .map(|(time, text)| {
+                let line = format!("{} - {}", settings.format_time(time.time()), text);
+                if *time > now { format!("{} ({})", line, humanize::until(lang, *time - now)) } else { line }
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+        sections.push(tf(lang, "digest_events", &[("events", &list)]));
+    }
+    sections.extend(stars::digest_section(settings, &starred));
+    if !open_tasks.is_empty() {
+        sections.push(tf(lang, "digest_tasks", &[("tasks", &tasks::format_tasks(&open_tasks))]));
+    }
+
+    let keyboard = (!overdue.is_empty()).then(|| {
+        sections.push(tf(lang, "digest_overdue", &[("events", &overdue::format_list(settings, &overdue))]));
+        overdue::keyboard(lang, &overdue)
+    });
+
+    Ok(Some((sections.join("\n\n"), keyboard)))
+}
+
+/// Рассылает ежедневные сводки пользователям, у которых наступило время сводки.
+pub async fn send_due_digests(db: &Db, clock: &dyn Clock) {
+    let instant = clock.now();
+    let digests = db.call(move |conn| {
+        let mut digests = Vec::new();
+        for user in get_digest_users(conn)? {
+            let Ok(digest_time) = chrono::NaiveTime::parse_from_str(&user.digest_time, "%H:%M") else {
+                continue;
+            };
+
+            let settings = settings::resolve(conn, user.telegram_id, user.telegram_id).unwrap_or_default();
+            let now = timezone::local_at(instant, settings.timezone);
+            let today = now.date();
+            if now.time() < digest_time || user.sent_on.as_deref() == Some(today.format("%Y-%m-%d").to_string().as_str()) {
+                continue;
+            }
+
+            let plain = accessibility::of(&settings);
+            match accessibility::with(plain, || build_digest(conn, user.telegram_id, &settings, now)) {
+                Ok(text) => {
+                    let _ = mark_digest_sent(conn, user.telegram_id, today);
+                    if let Some((text, keyboard)) = text {
+                        let place = weather::user_place(conn, user.telegram_id).ok().flatten();
+                        digests.push((user.telegram_id, user.tenant, text, keyboard, place, settings.lang, plain, today));
+                    }
+                }
+                Err(e) => log::error!("Failed to build digest for {}: {}", user.telegram_id, e),
+            }
+        }
+        Ok(digests)
+    }).await;
+    let digests = match digests {
+        Ok(digests) => digests,
+        Err(e) => {
+            log::error!("Failed to load digest users: {}", e);
+            return;
+        }
+    };
+
+    for (telegram_id, tenant, mut text, keyboard, place, lang, plain, today) in digests {
+        if let Some(place) = place {
+            if let Some(forecast) = accessibility::scope(plain, weather::forecast_line(db, place, today, lang)).await {
+                text = format!("{}\n\n{}", forecast, text);
+            }
+        }
+        println!("Sending digest to {}", telegram_id);
+        // Длинная сводка уходит несколькими сообщениями, кнопки — под последним
+        let Ok(mut request) = chunks::send_last(tenants::bot(&tenant), ChatId(telegram_id), text).await else {
+            continue;
+        };
+        if let Some(keyboard) = keyboard {
+            request = request.reply_markup(keyboard);
+        }
+        let _ = request.await;
+    }
+}
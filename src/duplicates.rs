@@ -0,0 +1,245 @@
+use teloxide::prelude::*;
+use teloxide::types::{InlineKeyboardButton, InlineKeyboardMarkup};
+use regex::Regex;
+use rusqlite::{Connection, params, OptionalExtension};
+
+use reventor::parse;
+
+use crate::chunks;
+use crate::clock::{self, Clock};
+use crate::config;
+use crate::i18n::{t, tf};
+use crate::settings::{self, Settings};
+use crate::{
+    DatabaseError, Db, EVENT_TIME_FORMAT, crypto, event_confirmation, get_event, groups, ics, in_transaction, insert_event,
+    link_event_message, parse_event_time, set_event_end, shifted_end, tenants,
+};
+
+#[derive(Debug)]
+struct Draft {
+    user_id: i64,
+    chat_id: i64,
+    source_message_id: i32,
+    text: String,
+    event_time: String,
+}
+
+/// Чем закончилось нажатие на кнопку под предупреждением о дубликате.
+enum DraftOutcome {
+    NotYours,
+    Expired,
+    Discarded,
+    Created(Draft, i64),
+}
+
+pub fn init_tables(conn: &Connection) -> Result<(), rusqlite::Error> {
+    // Событие, отложенное до решения пользователя «создать всё равно»
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS event_drafts (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            user_id INTEGER NOT NULL,
+            chat_id INTEGER NOT NULL,
+            source_message_id INTEGER NOT NULL,
+            text TEXT NOT NULL,
+            event_time DATETIME NOT NULL,
+            created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            FOREIGN KEY(user_id) REFERENCES users(id) ON DELETE CASCADE
+        )",
+        [],
+    )?;
+    Ok(())
+}
+
+/// Есть ли у пользователя в этом чате неотправленное событие с тем же текстом и временем.
+/// Текст сравнивается после расшифровки: зашифрованные копии одного текста в БД различаются.
+pub fn find_duplicate(conn: &Connection, user_id: i64, chat_id: i64, text: &str, event_time: &str) -> Result<bool, rusqlite::Error> {
+    let mut stmt = conn.prepare(
+        "SELECT text FROM events
+         WHERE user_id = ? AND COALESCE(chat_id, ?) = ? AND event_time = ? AND status = 'pending'",
+    )?;
+    let texts = stmt
+        .query_map(params![user_id, chat_id, chat_id, event_time], |row| row.get::<_, String>(0))?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(texts.into_iter().any(|stored| crypto::open(stored) == text))
+}
+
+fn save_draft(conn: &Connection, user_id: i64, chat_id: i64, source_message_id: i32, text: &str, event_time: &str) -> Result<i64, rusqlite::Error> {
+    conn.execute(
+        "INSERT INTO event_drafts (user_id, chat_id, source_message_id, text, event_time) VALUES (?, ?, ?, ?, ?)",
+        params![user_id, chat_id, source_message_id, crypto::seal(text), event_time],
+    )?;
+    Ok(conn.last_insert_rowid())
+}
+
+fn take_draft(conn: &Connection, draft_id: i64) -> Result<Option<Draft>, rusqlite::Error> {
+    let draft = conn.query_row(
+        "SELECT user_id, chat_id, source_message_id, text, event_time FROM event_drafts WHERE id = ?",
+        params![draft_id],
+        |row| {
+            Ok(Draft {
+                user_id: row.get(0)?,
+                chat_id: row.get(1)?,
+                source_message_id: row.get(2)?,
+                text: crypto::open(row.get(3)?),
+                event_time: row.get(4)?,
+            })
+        },
+    ).optional()?;
+
+    conn.execute("DELETE FROM event_drafts WHERE id = ?", params![draft_id])?;
+    Ok(draft)
+}
+
+fn draft_owner(conn: &Connection, draft_id: i64) -> Result<Option<i64>, rusqlite::Error> {
+    conn.query_row(
+        "SELECT u.telegram_id FROM event_drafts d JOIN users u ON d.user_id = u.id WHERE d.id = ?",
+        params![draft_id],
+        |row| row.get(0),
+    ).optional()
+}
+
+pub async fn warn_duplicate(
+    bot: &Bot,
+    msg: &Message,
+    db: &Db,
+    user_id: i64,
+    text: &str,
+    event_time: &str,
+    settings: &Settings,
+) -> ResponseResult<()> {
+    let (chat_id, message_id) = (msg.chat.id.0, msg.id.0);
+    let (draft_text, draft_time) = (text.to_string(), event_time.to_string());
+    let draft_id = db.call(move |conn| save_draft(conn, user_id, chat_id, message_id, &draft_text, &draft_time))
+        .await
+        .map_err(DatabaseError)?;
+
+    let lang = settings.lang;
+    let time = parse_event_time(event_time).map_or_else(|| event_time.to_string(), |time| settings.format_datetime(time));
+    let keyboard = InlineKeyboardMarkup::new(vec![vec![
+        InlineKeyboardButton::callback(t(lang, "duplicate_create"), format!("dup:create:{}", draft_id)),
+        InlineKeyboardButton::callback(t(lang, "duplicate_cancel"), format!("dup:cancel:{}", draft_id)),
+    ]]);
+
+    chunks::send_last(bot, msg.chat.id, tf(lang, "duplicate_warning", &[("time", &time), ("text", &text)])).await?
+        .reply_markup(keyboard)
+        .await?;
+    Ok(())
+}
+
+pub async fn handle_callback(bot: &Bot, q: &CallbackQuery, db: &Db, args: &str) -> ResponseResult<()> {
+    let Some((action, draft_id)) = args.split_once(':') else {
+        bot.answer_callback_query(q.id.clone()).await?;
+        return Ok(());
+    };
+    let draft_id: i64 = draft_id.parse().unwrap_or_default();
+    let Some(message) = q.message.as_ref() else {
+        bot.answer_callback_query(q.id.clone()).await?;
+        return Ok(());
+    };
+
+    let (telegram_id, chat_id, message_id) = (q.from.id.0 as i64, message.chat.id.0, message.id.0);
+    let (create, tenant) = (action == "create", tenants::name_of(bot));
+    let (settings, outcome) = db.call(move |conn| {
+        let settings = settings::resolve(conn, telegram_id, chat_id)?;
+
+        // Нажать на кнопку может только автор события
+        if draft_owner(conn, draft_id)?.is_some_and(|owner| owner != telegram_id) {
+            return Ok((settings, DraftOutcome::NotYours));
+        }
+        let Some(draft) = take_draft(conn, draft_id)? else {
+            return Ok((settings, DraftOutcome::Expired));
+        };
+        if !create {
+            return Ok((settings, DraftOutcome::Discarded));
+        }
+
+        let event_id = in_transaction(conn, |tx| {
+            let event_id = insert_event(tx, tenant, draft.user_id, draft.chat_id, &draft.text, &draft.event_time)?;
+            link_event_message(tx, draft.chat_id, draft.source_message_id, event_id, "source")?;
+            link_event_message(tx, draft.chat_id, message_id, event_id, "confirmation")?;
+            Ok(event_id)
+        })?;
+        Ok((settings, DraftOutcome::Created(draft, event_id)))
+    }).await.map_err(DatabaseError)?;
+    let lang = settings.lang;
+
+    let (draft, event_id) = match outcome {
+        DraftOutcome::NotYours => {
+            bot.answer_callback_query(q.id.clone()).text(t(lang, "event_not_yours")).await?;
+            return Ok(());
+        }
+        DraftOutcome::Expired => {
+            bot.answer_callback_query(q.id.clone()).text(t(lang, "duplicate_expired")).await?;
+            return Ok(());
+        }
+        DraftOutcome::Discarded => {
+            bot.answer_callback_query(q.id.clone()).await?;
+            bot.edit_message_text(message.chat.id, message.id, t(lang, "duplicate_discarded")).await?;
+            return Ok(());
+        }
+        DraftOutcome::Created(draft, event_id) => (draft, event_id),
+    };
+
+    let response = match parse_event_time(&draft.event_time) {
+        Some(time) => event_confirmation(&settings, &draft.text, time),
+        None => draft.text.clone(),
+    };
+    bot.answer_callback_query(q.id.clone()).await?;
+    bot.edit_message_text(message.chat.id, message.id, response)
+        .reply_markup(ics::keyboard(lang, event_id))
+        .await?;
+    Ok(())
+}
+
+/// `/duplicate #id @ДД.ММ ЧЧ:ММ` — копия события на новое время.
+pub async fn handle_duplicate_command(bot: &Bot, msg: &Message, db: &Db, args: &str, settings: &Settings) -> ResponseResult<()> {
+    let lang = settings.lang;
+    let id_re = Regex::new(r"^#?(\d+)\s+(.+)$").unwrap();
+
+    let parsed = id_re.captures(args).and_then(|captures| {
+        let event_id: i64 = captures.get(1)?.as_str().parse().ok()?;
+        let target = config::get().parsers.parse(captures.get(2)?.as_str(), settings.locale(), clock::SYSTEM.now(), settings.timezone);
+        Some((event_id, target))
+    });
+    let (event_id, event_time) = match parsed {
+        Some((event_id, Ok(target))) => (event_id, target.datetime),
+        None | Some((_, Err(parse::ParseError::MissingTime))) => {
+            bot.send_message(msg.chat.id, t(lang, "duplicate_usage")).await?;
+            return Ok(());
+        }
+        Some((_, Err(parse::ParseError::InvalidDate(value) | parse::ParseError::InvalidTime(value) | parse::ParseError::InvalidEnd(value)))) => {
+            chunks::send(bot, msg.chat.id, tf(lang, "event_invalid_time", &[("value", &value)])).await?;
+            return Ok(());
+        }
+    };
+
+    let event = db.call(move |conn| get_event(conn, event_id)).await.map_err(DatabaseError)?;
+
+    let Some(event) = event else {
+        bot.send_message(msg.chat.id, t(lang, "event_not_found")).await?;
+        return Ok(());
+    };
+    if msg.from().map(|u| u.id.0 as i64) != Some(event.owner_id) || !groups::can_manage_events(bot, msg, db).await? {
+        bot.send_message(msg.chat.id, t(lang, "event_not_yours")).await?;
+        return Ok(());
+    }
+
+    let (user_id, chat_id, text, tenant) = (event.user_id, event.chat_id, event.text.clone(), tenants::name_of(bot));
+    // Копия сохраняет длительность оригинала: без окончания она выпала бы из занятости и пересечений
+    let end_time = shifted_end(&event, event_time);
+    let copy_id = db.call(move |conn| in_transaction(conn, |tx| {
+        let copy_id = insert_event(tx, tenant, user_id, chat_id, &text, &event_time.format(EVENT_TIME_FORMAT).to_string())?;
+        set_event_end(tx, copy_id, end_time.as_deref())?;
+        Ok(copy_id)
+    })).await.map_err(DatabaseError)?;
+
+    let confirmation = chunks::send_last(bot, msg.chat.id, event_confirmation(settings, &event.text, event_time)).await?
+        .reply_markup(ics::keyboard(lang, copy_id))
+        .await?;
+
+    let chat_id = msg.chat.id.0;
+    db.call(move |conn| link_event_message(conn, chat_id, confirmation.id.0, copy_id, "confirmation"))
+        .await
+        .map_err(DatabaseError)?;
+    Ok(())
+}
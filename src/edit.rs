@@ -0,0 +1,139 @@
+use teloxide::prelude::*;
+use regex::Regex;
+
+use reventor::parse;
+
+use crate::{blocklist, chunks, cleanup, clock, config, geofence, humanize};
+use crate::clock::Clock;
+use crate::i18n::{t, tf};
+use crate::settings::Settings;
+use crate::timezone;
+use crate::{
+    DATE_PATTERN, DatabaseError, Db, StoredEvent, TIME_PATTERN, find_event_by_message, get_event, groups, link_event_message,
+    in_transaction, parse_event_time, set_event_end, update_event,
+};
+
+enum Amendment {
+    /// `end_time` меняется вместе со временем начала: новое `@время` без диапазона убирает длительность.
+    Changed { text: String, event_time: String, end_time: Option<String> },
+    InvalidTime(String),
+}
+
+/// Новое состояние события по тексту правки. Если в правке только `@время`,
+/// текст события сохраняется, иначе заменяется целиком, как при создании.
+fn amend(event: &StoredEvent, text: &str, settings: &Settings) -> Amendment {
+    let only_time = Regex::new(&format!(r"^@(?:{}\s+)?{}(?:\s*[-–]\s*{})?$", DATE_PATTERN, TIME_PATTERN, TIME_PATTERN)).unwrap();
+
+    match config::get().parsers.parse(text, settings.locale(), clock::SYSTEM.now(), settings.timezone) {
+        Ok(parsed) => Amendment::Changed {
+            text: if only_time.is_match(text.trim()) { event.text.clone() } else { text.to_string() },
+            event_time: parse::store(parsed.datetime),
+            end_time: parsed.end.map(parse::store),
+        },
+        Err(parse::ParseError::MissingTime) => Amendment::Changed {
+            text: text.to_string(),
+            event_time: event.event_time.clone(),
+            end_time: event.end_time.clone(),
+        },
+        Err(parse::ParseError::InvalidDate(value) | parse::ParseError::InvalidTime(value) | parse::ParseError::InvalidEnd(value)) => {
+            Amendment::InvalidTime(value)
+        }
+    }
+}
+
+/// Событие, на подтверждение которого отвечает сообщение.
+pub async fn replied_event(msg: &Message, db: &Db) -> ResponseResult<Option<i64>> {
+    let Some(reply) = msg.reply_to_message() else {
+        return Ok(None);
+    };
+    if !reply.from().is_some_and(|u| u.is_bot) {
+        return Ok(None);
+    }
+
+    let (chat_id, message_id) = (msg.chat.id.0, reply.id.0);
+    let event_id = db.call(move |conn| find_event_by_message(conn, chat_id, message_id, "confirmation"))
+        .await
+        .map_err(DatabaseError)?;
+    Ok(event_id)
+}
+
+pub async fn amend_from_reply(bot: &Bot, msg: &Message, db: &Db, event_id: i64, settings: &Settings) -> ResponseResult<()> {
+    apply_amendment(bot, msg, db, event_id, settings).await
+}
+
+/// Правка исходного сообщения обновляет созданное из него событие.
+pub async fn handle_edited_message(bot: Bot, msg: Message, db: Db) -> ResponseResult<()> {
+    if let Some(user) = msg.from() {
+        if blocklist::is_blocked(&db, user.id.0 as i64).await {
+            return Ok(());
+        }
+    }
+    // Трансляция геопозиции приходит правками исходного сообщения
+    if let Some(location) = msg.location() {
+        return geofence::check_location(&msg, &db, location).await;
+    }
+    if msg.text().is_none() {
+        return Ok(());
+    }
+
+    let (chat_id, message_id) = (msg.chat.id.0, msg.id.0);
+    let event_id = db.call(move |conn| find_event_by_message(conn, chat_id, message_id, "source"))
+        .await
+        .map_err(DatabaseError)?;
+    let settings = crate::settings::for_message(&db, &msg).await.map_err(DatabaseError)?;
+
+    match event_id {
+        Some(event_id) => apply_amendment(&bot, &msg, &db, event_id, &settings).await,
+        None => Ok(()),
+    }
+}
+
+async fn apply_amendment(bot: &Bot, msg: &Message, db: &Db, event_id: i64, settings: &Settings) -> ResponseResult<()> {
+    let lang = settings.lang;
+    let text = msg.text().unwrap_or_default();
+
+    let event = db.call(move |conn| get_event(conn, event_id)).await.map_err(DatabaseError)?;
+
+    let Some(event) = event else {
+        let error = bot.send_message(msg.chat.id, t(lang, "event_not_found")).await?;
+        cleanup::schedule(bot, db, &error).await;
+        return Ok(());
+    };
+
+    let sender = msg.from().map(|u| u.id.0 as i64);
+    if sender != Some(event.owner_id) || !groups::can_manage_events(bot, msg, db).await? {
+        let error = bot.send_message(msg.chat.id, t(lang, "event_not_yours")).await?;
+        cleanup::schedule(bot, db, &error).await;
+        return Ok(());
+    }
+
+    let (new_text, new_time, new_end) = match amend(&event, text, settings) {
+        Amendment::Changed { text, event_time, end_time } => (text, event_time, end_time),
+        Amendment::InvalidTime(value) => {
+            let error = chunks::send(bot, msg.chat.id, tf(lang, "event_invalid_time", &[("value", &value)])).await?;
+            cleanup::schedule(bot, db, &error).await;
+            return Ok(());
+        }
+    };
+
+    let (text, time) = (new_text.clone(), new_time.clone());
+    db.call(move |conn| in_transaction(conn, |tx| {
+        update_event(tx, event.id, &text, &time)?;
+        set_event_end(tx, event.id, new_end.as_deref())
+    })).await.map_err(DatabaseError)?;
+
+    let time = parse_event_time(&new_time).map_or_else(|| new_time.clone(), |time| {
+        let until = humanize::until(lang, time - timezone::now_in(settings.timezone));
+        format!("{} ({})", settings.format_datetime(time), until)
+    });
+    let confirmation = chunks::send_last(bot, msg.chat.id, tf(lang, "event_updated", &[("time", &time), ("text", &new_text)])).await?
+        .reply_to_message_id(msg.id)
+        .await?;
+    cleanup::schedule(bot, db, &confirmation).await;
+
+    let chat_id = msg.chat.id.0;
+    db.call(move |conn| link_event_message(conn, chat_id, confirmation.id.0, event.id, "confirmation"))
+        .await
+        .map_err(DatabaseError)?;
+    Ok(())
+}
@@ -0,0 +1,103 @@
+use axum::extract::{Path, State};
+use axum::http::{StatusCode, header};
+use axum::response::{IntoResponse, Response};
+use chrono::{Duration, NaiveDateTime, Utc};
+use rusqlite::{Connection, params};
+
+use crate::settings::{self, Settings};
+use crate::tokens::{self, Scope, TokenOwner};
+use crate::{Db, UTC_FORMAT, crypto, parse_event_time};
+
+/// Насколько вперёд лента показывает события.
+const FEED_DAYS: i64 = 30;
+
+struct FeedEntry {
+    id: i64,
+    text: String,
+    event_time: String,
+    event_utc: NaiveDateTime,
+}
+
+/// Неотправленные события владельца на ближайшие `FEED_DAYS` дней.
+fn upcoming_events(conn: &Connection, user_id: i64) -> Result<Vec<FeedEntry>, rusqlite::Error> {
+    let now = Utc::now().naive_utc();
+    let until = now + Duration::days(FEED_DAYS);
+    let mut stmt = conn.prepare(
+        "SELECT id, text, event_time, event_utc FROM events
+         WHERE user_id = ? AND status = 'pending' AND event_utc >= ? AND event_utc < ?
+         ORDER BY event_utc"
+    )?;
+    let rows = stmt.query_map(
+        params![user_id, now.format(UTC_FORMAT).to_string(), until.format(UTC_FORMAT).to_string()],
+        |row| Ok((row.get::<_, i64>(0)?, crypto::open(row.get(1)?), row.get::<_, String>(2)?, row.get::<_, String>(3)?)),
+    )?
+    .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(rows
+        .into_iter()
+        .filter_map(|(id, text, event_time, event_utc)| {
+            let event_utc = NaiveDateTime::parse_from_str(&event_utc, UTC_FORMAT).ok()?;
+            Some(FeedEntry { id, text, event_time, event_utc })
+        })
+        .collect())
+}
+
+pub fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn rfc3339(utc: NaiveDateTime) -> String {
+    utc.format("%Y-%m-%dT%H:%M:%SZ").to_string()
+}
+
+/// Лента Atom: заголовок записи — время и первая строка события, содержимое — весь текст.
+fn render(owner: &TokenOwner, settings: &Settings, entries: &[FeedEntry]) -> String {
+    let entries = entries
+        .iter()
+        .map(|entry| {
+            let time = parse_event_time(&entry.event_time)
+                .map_or_else(|| entry.event_time.clone(), |time| settings.format_datetime(time));
+            let title = format!("{} {}", time, entry.text.lines().next().unwrap_or_default());
+            format!(
+                "  <entry>\n    <id>urn:reventor:event:{}</id>\n    <title>{}</title>\n    <updated>{}</updated>\n    <content type=\"text\">{}</content>\n  </entry>\n",
+                entry.id,
+                escape(&title),
+                rfc3339(entry.event_utc),
+                escape(&entry.text),
+            )
+        })
+        .collect::<String>();
+
+    format!(
+        "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n<feed xmlns=\"http://www.w3.org/2005/Atom\">\n  <id>urn:reventor:feed:{}</id>\n  <title>reventor</title>\n  <updated>{}</updated>\n{}</feed>\n",
+        owner.user_id,
+        rfc3339(Utc::now().naive_utc()),
+        entries,
+    )
+}
+
+/// `GET /feed/<token>` — лента Atom с событиями владельца токена. Нужен токен с правом `read`.
+pub async fn handle_feed(State(db): State<Db>, Path(token): Path<String>) -> Response {
+    let result = db.call(move |conn| {
+        let owner = match tokens::authenticate(conn, &token, Scope::Read)? {
+            Ok(owner) => owner,
+            Err(e) => return Ok(Err(e)),
+        };
+        let settings = settings::resolve(conn, owner.telegram_id, owner.telegram_id)?;
+        let entries = upcoming_events(conn, owner.user_id)?;
+        Ok(Ok(render(&owner, &settings, &entries)))
+    }).await;
+
+    match result {
+        Ok(Ok(feed)) => ([(header::CONTENT_TYPE, "application/atom+xml; charset=utf-8")], feed).into_response(),
+        Ok(Err(tokens::AuthError::Unknown)) => (StatusCode::UNAUTHORIZED, "unknown token").into_response(),
+        Ok(Err(tokens::AuthError::MissingScope)) => (StatusCode::FORBIDDEN, "token lacks the read scope").into_response(),
+        Err(e) => {
+            log::error!("Feed failed: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
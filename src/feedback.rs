@@ -0,0 +1,135 @@
+use teloxide::prelude::*;
+use rusqlite::{Connection, params, OptionalExtension};
+
+use crate::chunks;
+use crate::config;
+use crate::i18n::{t, tf, Lang};
+use crate::settings;
+use crate::{DatabaseError, Db, tenants};
+
+const MAX_FEEDBACK_CHARS: usize = 2000;
+
+/// Обращение пользователя, на которое администратор ответил.
+#[derive(Debug)]
+struct Answered {
+    id: i64,
+    tenant: String,
+    telegram_id: i64,
+    chat_id: i64,
+}
+
+/// Чат, куда пересылаются обращения, из `FEEDBACK_CHAT_ID`. Без него обращения только сохраняются.
+fn admin_chat() -> Option<i64> {
+    config::get().feedback_chat
+}
+
+pub fn init_tables(conn: &Connection) -> Result<(), rusqlite::Error> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS feedback (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            tenant TEXT NOT NULL DEFAULT 'default',
+            telegram_id INTEGER NOT NULL,
+            chat_id INTEGER NOT NULL,
+            text TEXT NOT NULL,
+            admin_message_id INTEGER,
+            answered_at DATETIME,
+            created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP
+        )",
+        [],
+    )?;
+    Ok(())
+}
+
+fn save(conn: &Connection, tenant: &str, telegram_id: i64, chat_id: i64, text: &str) -> Result<i64, rusqlite::Error> {
+    conn.execute(
+        "INSERT INTO feedback (tenant, telegram_id, chat_id, text) VALUES (?, ?, ?, ?)",
+        params![tenant, telegram_id, chat_id, text],
+    )?;
+    Ok(conn.last_insert_rowid())
+}
+
+fn link_admin_message(conn: &Connection, feedback_id: i64, message_id: i32) -> Result<(), rusqlite::Error> {
+    conn.execute("UPDATE feedback SET admin_message_id = ? WHERE id = ?", params![message_id, feedback_id])?;
+    Ok(())
+}
+
+fn find_by_admin_message(conn: &Connection, message_id: i32) -> Result<Option<Answered>, rusqlite::Error> {
+    conn.query_row(
+        "SELECT id, tenant, telegram_id, chat_id FROM feedback WHERE admin_message_id = ?",
+        params![message_id],
+        |row| Ok(Answered { id: row.get(0)?, tenant: row.get(1)?, telegram_id: row.get(2)?, chat_id: row.get(3)? }),
+    ).optional()
+}
+
+/// `/feedback текст` — сохраняет обращение и пересылает его в чат администратора
+/// с id пользователя и версией бота.
+pub async fn handle_feedback_command(bot: &Bot, msg: &Message, db: &Db, args: &str, lang: Lang) -> ResponseResult<()> {
+    let Some(user) = msg.from() else {
+        return Ok(());
+    };
+    if args.is_empty() || args.chars().count() > MAX_FEEDBACK_CHARS {
+        bot.send_message(msg.chat.id, t(lang, "feedback_usage")).await?;
+        return Ok(());
+    }
+
+    let (tenant, telegram_id, chat_id, text) = (tenants::name_of(bot), user.id.0 as i64, msg.chat.id.0, args.to_string());
+    let admin = admin_chat();
+    let (feedback_id, admin_lang) = db.call(move |conn| {
+        let feedback_id = save(conn, tenant, telegram_id, chat_id, &text)?;
+        let admin_lang = admin.map(|admin| settings::resolve(conn, admin, admin)).transpose()?.map(|settings| settings.lang);
+        Ok((feedback_id, admin_lang))
+    }).await.map_err(DatabaseError)?;
+    log::info!("Feedback #{} received from user {}", feedback_id, telegram_id);
+
+    if let Some((admin, admin_lang)) = admin.zip(admin_lang) {
+        let username = user.username.as_ref().map_or_else(String::new, |name| format!(" (@{})", name));
+        let forwarded = tf(admin_lang, "feedback_admin", &[
+            ("id", &feedback_id),
+            ("user", &format!("{}{}", telegram_id, username)),
+            ("version", &env!("CARGO_PKG_VERSION")),
+            ("text", &args),
+        ]);
+        match chunks::send(bot, ChatId(admin), forwarded).await {
+            Ok(message) => {
+                let message_id = message.id.0;
+                db.call(move |conn| link_admin_message(conn, feedback_id, message_id)).await.map_err(DatabaseError)?;
+            }
+            Err(e) => log::error!("Failed to forward feedback #{}: {}", feedback_id, e),
+        }
+    }
+
+    bot.send_message(msg.chat.id, t(lang, "feedback_thanks")).await?;
+    Ok(())
+}
+
+/// Ответ администратора на пересланное обращение в чате `FEEDBACK_CHAT_ID`.
+/// Возвращает `false`, если сообщение не является таким ответом.
+pub async fn relay_reply(bot: &Bot, msg: &Message, db: &Db, lang: Lang) -> ResponseResult<bool> {
+    let (Some(reply), Some(text)) = (msg.reply_to_message(), msg.text()) else {
+        return Ok(false);
+    };
+    if admin_chat() != Some(msg.chat.id.0) {
+        return Ok(false);
+    }
+    let message_id = reply.id.0;
+    let Some(feedback) = db.call(move |conn| find_by_admin_message(conn, message_id)).await.map_err(DatabaseError)? else {
+        return Ok(false);
+    };
+
+    let (telegram_id, chat_id, feedback_id) = (feedback.telegram_id, feedback.chat_id, feedback.id);
+    let user_lang = db.call(move |conn| {
+        conn.execute("UPDATE feedback SET answered_at = CURRENT_TIMESTAMP WHERE id = ?", params![feedback_id])?;
+        settings::resolve(conn, telegram_id, chat_id)
+    }).await.map_err(DatabaseError)?.lang;
+
+    let answer = tf(user_lang, "feedback_reply", &[("id", &feedback.id), ("text", &text)]);
+    let response = match chunks::send(tenants::bot(&feedback.tenant), ChatId(feedback.chat_id), answer).await {
+        Ok(_) => tf(lang, "feedback_reply_sent", &[("id", &feedback.id)]),
+        Err(e) => {
+            log::error!("Failed to relay answer to feedback #{}: {}", feedback.id, e);
+            tf(lang, "feedback_reply_failed", &[("id", &feedback.id)])
+        }
+    };
+    bot.send_message(msg.chat.id, response).reply_to_message_id(msg.id).await?;
+    Ok(true)
+}
@@ -0,0 +1,92 @@
+use teloxide::prelude::*;
+use teloxide::types::{InlineKeyboardButton, InlineKeyboardMarkup};
+use chrono::NaiveDateTime;
+use rusqlite::{Connection, params};
+use reventor::parse::{self, Offset};
+
+use crate::chunks;
+use crate::i18n::{t, Lang};
+use crate::settings::{self, Settings};
+use crate::stars;
+use crate::timezone;
+use crate::{DatabaseError, Db, EVENT_TIME_FORMAT, event_confirmation, get_event, ics, in_transaction, insert_event, link_event_message};
+
+/// Событие, созданное по строке `-> ...` после подтверждения исходного.
+#[derive(Debug)]
+pub struct FollowUp {
+    id: i64,
+    chat_id: i64,
+    text: String,
+    event_time: NaiveDateTime,
+}
+
+/// Кнопка «Готово» для напоминания без чек-листа.
+pub fn ack_keyboard(lang: Lang, event_id: i64) -> InlineKeyboardMarkup {
+    InlineKeyboardMarkup::new(vec![vec![
+        InlineKeyboardButton::callback(t(lang, "event_ack"), format!("ack:{}", event_id)),
+    ]])
+}
+
+/// Завершает отправленное событие и создаёт его продолжения.
+/// Возвращает `None`, если событие уже было завершено раньше.
+pub fn complete_event(conn: &Connection, event_id: i64) -> Result<Option<Vec<FollowUp>>, rusqlite::Error> {
+    let updated = conn.execute(
+        "UPDATE events SET status = 'done', done_at = CURRENT_TIMESTAMP WHERE id = ? AND status = 'sent'",
+        params![event_id],
+    )?;
+    if updated == 0 {
+        return Ok(None);
+    }
+    let Some(event) = get_event(conn, event_id)? else {
+        return Ok(Some(Vec::new()));
+    };
+
+    let now = timezone::now_in(timezone::user_zone(conn, event.user_id)?);
+    let mut follow_ups = Vec::new();
+    for Offset { delay, text } in parse::offsets(&event.text) {
+        let event_time = now + delay;
+        let id = insert_event(conn, &event.tenant, event.user_id, event.chat_id, &text, &event_time.format(EVENT_TIME_FORMAT).to_string())?;
+        log::info!("Scheduled follow-up {} for event {}", id, event_id);
+        follow_ups.push(FollowUp { id, chat_id: event.chat_id, text, event_time });
+    }
+    Ok(Some(follow_ups))
+}
+
+/// Сообщает о созданных продолжениях; ответ на подтверждение меняет их, как обычные события.
+pub async fn announce(bot: &Bot, db: &Db, settings: &Settings, follow_ups: &[FollowUp]) -> ResponseResult<()> {
+    for follow_up in follow_ups {
+        let confirmation = chunks::send_last(bot, ChatId(follow_up.chat_id), event_confirmation(settings, &follow_up.text, follow_up.event_time)).await?
+            .reply_markup(ics::keyboard(settings.lang, follow_up.id))
+            .await?;
+
+        let (chat_id, event_id) = (follow_up.chat_id, follow_up.id);
+        db.call(move |conn| link_event_message(conn, chat_id, confirmation.id.0, event_id, "confirmation"))
+            .await
+            .map_err(DatabaseError)?;
+    }
+    Ok(())
+}
+
+pub async fn handle_callback(bot: &Bot, q: &CallbackQuery, db: &Db, args: &str) -> ResponseResult<()> {
+    let event_id: i64 = args.parse().unwrap_or_default();
+    let Some(message) = q.message.as_ref() else {
+        bot.answer_callback_query(q.id.clone()).await?;
+        return Ok(());
+    };
+
+    let (telegram_id, chat_id) = (q.from.id.0 as i64, message.chat.id.0);
+    let (settings, completed) = db.call(move |conn| {
+        let settings = settings::resolve(conn, telegram_id, chat_id)?;
+        Ok((settings, in_transaction(conn, |tx| complete_event(tx, event_id))?))
+    }).await.map_err(DatabaseError)?;
+
+    let lang = settings.lang;
+    bot.answer_callback_query(q.id.clone()).text(t(lang, "event_acknowledged")).await?;
+    bot.edit_message_reply_markup(message.chat.id, message.id).await?;
+
+    if let Some(follow_ups) = completed {
+        stars::unpin(bot, db, event_id).await?;
+        announce(bot, db, &settings, &follow_ups).await?;
+    }
+    Ok(())
+}
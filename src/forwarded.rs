@@ -0,0 +1,192 @@
+//! Пересланные напоминания: сообщения других ботов-напоминалок и уведомления календарей
+//! («Notification: Созвон @ Tue Mar 17, 2026 10am») превращаются в свои события одной кнопкой.
+
+use teloxide::prelude::*;
+use chrono::{Datelike, NaiveDate, NaiveDateTime, NaiveTime};
+use regex::Regex;
+use reventor::parse::{self, DATE_PATTERN, DateOrder, TIME_PATTERN};
+
+use crate::groups;
+use crate::import::{self, ImportReport};
+use crate::settings::Settings;
+use crate::timezone;
+use crate::Db;
+
+/// Начала названий месяцев: английские и русские, с января.
+const MONTHS: [[&str; 2]; 12] = [
+    ["jan", "янв"], ["feb", "фев"], ["mar", "мар"], ["apr", "апр"], ["may", "ма"], ["jun", "июн"],
+    ["jul", "июл"], ["aug", "авг"], ["sep", "сен"], ["oct", "окт"], ["nov", "ноя"], ["dec", "дек"],
+];
+/// Служебные слова в начале сообщений напоминалок, которые не относятся к названию.
+const PREFIX: &str = r"(?i)^[^\p{L}\p{N}]*(?:напоминание|напоминаю|уведомление|reminder|notification)?\s*[:!.\-–—]?\s*";
+/// Связки, которые остаются от строки после вырезания даты и времени.
+const LINKERS: &str = r"(?i)(?:^|\s)(?:в|во|на|до|с|at|on|by|from)\s*$";
+
+/// Дата словами: `15 марта 2026`, `17 мар.`, `Mar 17, 2026`, `March 17th`.
+const WORD_DATE: &str = r"(?i)(?:^|[^\p{L}\d])(?P<date>(\d{1,2})\s+(\p{L}{3,})\.?(?:\s+(\d{4}))?|([a-z]{3,})\.?\s+(\d{1,2})(?:st|nd|rd|th)?(?:,?\s+(\d{4}))?)(?:$|[^\p{L}\d])";
+
+fn month(name: &str) -> Option<u32> {
+    MONTHS
+        .iter()
+        .position(|prefixes| prefixes.iter().any(|prefix| name.starts_with(prefix)))
+        .map(|index| index as u32 + 1)
+}
+
+/// Первая дата в строке и её место. Год, если его нет, — ближайший, при котором дата не в прошлом.
+fn find_date(line: &str, today: NaiveDate, order: DateOrder) -> Option<(NaiveDate, (usize, usize))> {
+    let numeric = Regex::new(&format!(r"(?:^|[^\d.:/])({})(?:$|[^\d:])", DATE_PATTERN)).unwrap();
+    let found = numeric.captures_iter(line).find_map(|captures| {
+        let value = captures.get(1)?;
+        let no_year = value.as_str().matches(['.', '/']).count() < 2 && !value.as_str().contains('-');
+        Some((parse::date(value.as_str(), today, order)?, no_year, (value.start(), value.end())))
+    });
+    let found = found.or_else(|| {
+        Regex::new(WORD_DATE).unwrap().captures_iter(line).find_map(|captures| {
+            let (day, name, year) = match captures.get(2) {
+                Some(day) => (day, captures.get(3)?, captures.get(4)),
+                None => (captures.get(6)?, captures.get(5)?, captures.get(7)),
+            };
+            let month = month(&name.as_str().to_lowercase())?;
+            let date = NaiveDate::from_ymd_opt(year.map_or(Some(today.year()), |year| year.as_str().parse().ok())?, month, day.as_str().parse().ok()?)?;
+            let whole = captures.name("date")?;
+            Some((date, year.is_none(), (whole.start(), whole.end())))
+        })
+    })?;
+
+    let (date, no_year, span) = found;
+    let date = if no_year && date < today { date.with_year(date.year() + 1)? } else { date };
+    Some((date, span))
+}
+
+/// Первое время в строке и его место.
+fn find_time(line: &str) -> Option<(NaiveTime, (usize, usize))> {
+    let re = Regex::new(&format!(r"(?:^|[^\d:.])({})(?:$|[^\d:.])", TIME_PATTERN)).unwrap();
+    let found = re.captures_iter(line).find_map(|captures| {
+        let value = captures.get(1)?;
+        Some((parse::time(value.as_str())?, (value.start(), value.end())))
+    });
+    found
+}
+
+/// Название без служебного начала, вырезанных даты и времени и оставшихся связок.
+fn clean_title(line: &str, spans: &[(usize, usize)]) -> String {
+    let mut spans = spans.to_vec();
+    spans.sort();
+    let mut title = String::new();
+    let mut from = 0;
+    for (start, end) in spans {
+        if start >= from {
+            title.push_str(&line[from..start]);
+            title.push(' ');
+            from = end;
+        }
+    }
+    title.push_str(&line[from..]);
+
+    // В уведомлениях Google Календаря название стоит до ` @ `
+    let title = title.split(" @ ").next().unwrap_or_default();
+    let title = Regex::new(PREFIX).unwrap().replace(title, "");
+    let mut title = title.split_whitespace().collect::<Vec<_>>().join(" ");
+    let linkers = Regex::new(LINKERS).unwrap();
+    loop {
+        let trimmed = title.trim_end_matches(|c: char| !c.is_alphanumeric() && c != ')').to_string();
+        let trimmed = linkers.replace(&trimmed, "").trim().to_string();
+        if trimmed == title {
+            return title;
+        }
+        title = trimmed;
+    }
+}
+
+/// События из текста пересланного сообщения: каждая строка с датой и временем — отдельное событие;
+/// если дата и время в разных строках, событие одно и называется первой строкой без них.
+fn recognize(text: &str, today: NaiveDate, order: DateOrder) -> Vec<(String, NaiveDateTime)> {
+    let lines = text.lines().map(str::trim).filter(|line| !line.is_empty()).collect::<Vec<_>>();
+    let dated = lines
+        .iter()
+        .enumerate()
+        .filter_map(|(index, line)| {
+            let (date, date_span) = find_date(line, today, order)?;
+            let (time, time_span) = find_time(line)?;
+            Some((index, clean_title(line, &[date_span, time_span]), date.and_time(time)))
+        })
+        .collect::<Vec<_>>();
+
+    let untitled_line = |skip: &[usize]| {
+        lines
+            .iter()
+            .enumerate()
+            .filter(|(index, _)| !skip.contains(index))
+            .map(|(_, line)| clean_title(line, &[]))
+            .find(|title| !title.is_empty())
+    };
+
+    match dated.as_slice() {
+        [] => {
+            let date = lines.iter().enumerate().find_map(|(index, line)| Some((index, find_date(line, today, order)?.0)));
+            let time = lines.iter().enumerate().find_map(|(index, line)| Some((index, find_time(line)?.0)));
+            let (Some((date_line, date)), Some((time_line, time))) = (date, time) else {
+                return Vec::new();
+            };
+            untitled_line(&[date_line, time_line]).map(|title| vec![(title, date.and_time(time))]).unwrap_or_default()
+        }
+        [(index, title, datetime)] if title.is_empty() => {
+            untitled_line(&[*index]).map(|title| vec![(title, *datetime)]).unwrap_or_default()
+        }
+        _ => dated.into_iter().filter(|(_, title, _)| !title.is_empty()).map(|(_, title, datetime)| (title, datetime)).collect(),
+    }
+}
+
+/// Предлагает создать события из пересланного сообщения. `false` — в тексте нет узнаваемого
+/// напоминания, и сообщение обрабатывается как обычно.
+pub async fn offer(bot: &Bot, msg: &Message, db: &Db, text: &str, settings: &Settings) -> ResponseResult<bool> {
+    if msg.forward().is_none() {
+        return Ok(false);
+    }
+    let now = timezone::now_in(settings.timezone);
+    let found = recognize(text, now.date(), settings.date_format.order());
+    if found.is_empty() {
+        return Ok(false);
+    }
+    if !groups::can_manage_events(bot, msg, db).await? {
+        groups::reply_not_allowed(bot, msg, db).await?;
+        return Ok(true);
+    }
+
+    let mut report = ImportReport::default();
+    for (title, datetime) in found {
+        report.push(&title, "", Ok(datetime), now);
+    }
+    import::offer(bot, msg, db, report, settings).await?;
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(date: (i32, u32, u32), time: (u32, u32)) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(date.0, date.1, date.2).unwrap().and_hms_opt(time.0, time.1, 0).unwrap()
+    }
+
+    #[test]
+    fn recognizes_common_reminder_formats() {
+        let today = NaiveDate::from_ymd_opt(2026, 3, 1).unwrap();
+        let cases = [
+            ("🔔 Напоминание\nПозвонить маме\n📅 15.03.2026 10:00", vec![("Позвонить маме", at((2026, 3, 15), (10, 0)))]),
+            ("Notification: Team sync @ Tue Mar 17, 2026 10am - 11am (GMT+3) (me@example.com)", vec![("Team sync", at((2026, 3, 17), (10, 0)))]),
+            ("Напоминание: оплатить интернет 20 марта в 9:30", vec![("оплатить интернет", at((2026, 3, 20), (9, 30)))]),
+            ("Стоматолог\nДата: 05.02\nВремя: 18:00", vec![("Стоматолог", at((2027, 2, 5), (18, 0)))]),
+            (
+                "Ваши напоминания:\n12.03 08:00 Зарядка\n14.03 19:30 Кино",
+                vec![("Зарядка", at((2026, 3, 12), (8, 0))), ("Кино", at((2026, 3, 14), (19, 30)))],
+            ),
+            ("Просто сообщение без даты", vec![]),
+            ("Встреча 15.03 без времени", vec![]),
+        ];
+        for (text, expected) in cases {
+            let expected = expected.into_iter().map(|(title, time)| (title.to_string(), time)).collect::<Vec<_>>();
+            assert_eq!(recognize(text, today, DateOrder::DayMonth), expected, "{}", text);
+        }
+    }
+}
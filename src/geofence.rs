@@ -0,0 +1,238 @@
+use teloxide::prelude::*;
+use teloxide::types::{KeyboardButton, KeyboardMarkup, KeyboardRemove, Location};
+use rusqlite::{Connection, params, OptionalExtension};
+
+use crate::chunks;
+use crate::i18n::{t, tf, Lang};
+use crate::timezone;
+use crate::{DatabaseError, Db, crypto, ensure_user_exists, tenants};
+
+/// Радиус по умолчанию: примерно квартал, с запасом на погрешность GPS.
+const DEFAULT_RADIUS: u32 = 200;
+const MAX_RADIUS: u32 = 5000;
+const EARTH_RADIUS_M: f64 = 6_371_000.0;
+
+/// Напоминание, которое срабатывает у места, а не во времени.
+#[derive(Debug)]
+struct PlaceReminder {
+    id: i64,
+    tenant: String,
+    chat_id: i64,
+    text: String,
+    lat: f64,
+    lon: f64,
+    radius: u32,
+}
+
+pub fn init_tables(conn: &Connection) -> Result<(), rusqlite::Error> {
+    // status: awaiting — ждём геопозицию места, pending — ждём приближения, sent — напомнили
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS place_reminders (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            tenant TEXT NOT NULL DEFAULT 'default',
+            user_id INTEGER NOT NULL,
+            chat_id INTEGER NOT NULL,
+            text TEXT NOT NULL,
+            latitude REAL,
+            longitude REAL,
+            radius_m INTEGER NOT NULL,
+            status TEXT NOT NULL DEFAULT 'awaiting',
+            created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            FOREIGN KEY(user_id) REFERENCES users(id) ON DELETE CASCADE
+        )",
+        [],
+    )?;
+    Ok(())
+}
+
+/// Расстояние по поверхности Земли в метрах (формула гаверсинусов).
+fn distance_m((lat1, lon1): (f64, f64), (lat2, lon2): (f64, f64)) -> f64 {
+    let (phi1, phi2) = (lat1.to_radians(), lat2.to_radians());
+    let d_phi = (lat2 - lat1).to_radians();
+    let d_lambda = (lon2 - lon1).to_radians();
+    let a = (d_phi / 2.0).sin().powi(2) + phi1.cos() * phi2.cos() * (d_lambda / 2.0).sin().powi(2);
+    2.0 * EARTH_RADIUS_M * a.sqrt().asin()
+}
+
+/// Разбирает `[радиус] текст`; радиус в метрах.
+fn parse_args(args: &str) -> Option<(u32, String)> {
+    let (first, rest) = args.split_once(char::is_whitespace).unwrap_or((args, ""));
+    let (radius, text) = match first.trim_end_matches('m').trim_end_matches('м').parse::<u32>() {
+        Ok(radius) if !rest.trim().is_empty() => (radius, rest.trim()),
+        _ => (DEFAULT_RADIUS, args.trim()),
+    };
+    (!text.is_empty() && (1..=MAX_RADIUS).contains(&radius)).then(|| (radius, text.to_string()))
+}
+
+fn create_reminder(conn: &Connection, tenant: &str, user_id: i64, chat_id: i64, text: &str, radius: u32) -> Result<i64, rusqlite::Error> {
+    // Незаконченное напоминание без места заменяется новым
+    conn.execute("DELETE FROM place_reminders WHERE user_id = ? AND status = 'awaiting'", params![user_id])?;
+    conn.execute(
+        "INSERT INTO place_reminders (tenant, user_id, chat_id, text, radius_m) VALUES (?, ?, ?, ?, ?)",
+        params![tenant, user_id, chat_id, crypto::seal(text), radius],
+    )?;
+    Ok(conn.last_insert_rowid())
+}
+
+/// Задаёт место ожидающему напоминанию. Возвращает id и радиус.
+fn set_place(conn: &Connection, telegram_id: i64, (lat, lon): (f64, f64)) -> Result<Option<(i64, u32)>, rusqlite::Error> {
+    let awaiting = conn.query_row(
+        "SELECT p.id, p.radius_m FROM place_reminders p JOIN users u ON p.user_id = u.id
+         WHERE u.telegram_id = ? AND p.status = 'awaiting'",
+        params![telegram_id],
+        |row| Ok((row.get::<_, i64>(0)?, row.get::<_, u32>(1)?)),
+    ).optional()?;
+    if let Some((id, _)) = awaiting {
+        conn.execute(
+            "UPDATE place_reminders SET latitude = ?, longitude = ?, status = 'pending' WHERE id = ?",
+            params![lat, lon, id],
+        )?;
+    }
+    timezone::set_awaiting_location(conn, telegram_id, None)?;
+    Ok(awaiting)
+}
+
+fn pending_reminders(conn: &Connection, telegram_id: i64) -> Result<Vec<PlaceReminder>, rusqlite::Error> {
+    let mut stmt = conn.prepare(
+        "SELECT p.id, p.tenant, p.chat_id, p.text, p.latitude, p.longitude, p.radius_m
+         FROM place_reminders p JOIN users u ON p.user_id = u.id
+         WHERE u.telegram_id = ? AND p.status = 'pending'
+         ORDER BY p.id",
+    )?;
+    let reminders = stmt.query_map(params![telegram_id], |row| {
+        Ok(PlaceReminder {
+            id: row.get(0)?,
+            tenant: row.get(1)?,
+            chat_id: row.get(2)?,
+            text: crypto::open(row.get(3)?),
+            lat: row.get(4)?,
+            lon: row.get(5)?,
+            radius: row.get(6)?,
+        })
+    })?
+    .collect::<Result<Vec<_>, _>>()?;
+    Ok(reminders)
+}
+
+/// Помечает напоминание сработавшим; `false`, если его уже отправили из другого обновления.
+fn claim(conn: &Connection, id: i64) -> Result<bool, rusqlite::Error> {
+    let updated = conn.execute("UPDATE place_reminders SET status = 'sent' WHERE id = ? AND status = 'pending'", params![id])?;
+    Ok(updated > 0)
+}
+
+fn cancel(conn: &Connection, telegram_id: i64, id: i64) -> Result<bool, rusqlite::Error> {
+    let deleted = conn.execute(
+        "DELETE FROM place_reminders
+         WHERE id = ? AND status != 'sent' AND user_id = (SELECT id FROM users WHERE telegram_id = ?)",
+        params![id, telegram_id],
+    )?;
+    Ok(deleted > 0)
+}
+
+/// `/place [радиус] текст` — напомнить у места, `/place cancel #id` — отменить.
+/// Место выбирается геопозицией, поэтому команда работает только в личном чате.
+pub async fn handle_place_command(bot: &Bot, msg: &Message, db: &Db, args: &str, lang: Lang) -> ResponseResult<()> {
+    if !msg.chat.is_private() {
+        bot.send_message(msg.chat.id, t(lang, "place_private_only")).await?;
+        return Ok(());
+    }
+    let Some(user) = msg.from() else {
+        return Ok(());
+    };
+    let (telegram_id, username, tenant, chat_id) = (user.id.0 as i64, user.username.clone(), tenants::name_of(bot), msg.chat.id.0);
+
+    if let Some(id) = args.strip_prefix("cancel") {
+        let response = match id.trim().trim_start_matches('#').parse::<i64>() {
+            Ok(id) => {
+                let cancelled = db.call(move |conn| cancel(conn, telegram_id, id)).await.map_err(DatabaseError)?;
+                let key = if cancelled { "place_cancelled" } else { "place_not_found" };
+                tf(lang, key, &[("id", &id)])
+            }
+            Err(_) => t(lang, "place_usage"),
+        };
+        chunks::send(bot, msg.chat.id, response).await?;
+        return Ok(());
+    }
+
+    let Some((radius, text)) = parse_args(args) else {
+        bot.send_message(msg.chat.id, t(lang, "place_usage")).await?;
+        return Ok(());
+    };
+    db.call(move |conn| {
+        let user_id = ensure_user_exists(conn, tenant, telegram_id, username)?;
+        create_reminder(conn, tenant, user_id, chat_id, &text, radius)?;
+        timezone::set_awaiting_location(conn, telegram_id, Some("place"))
+    }).await.map_err(DatabaseError)?;
+
+    let keyboard = KeyboardMarkup::new(vec![vec![KeyboardButton::new(t(lang, "timezone_share")).request(
+        teloxide::types::ButtonRequest::Location,
+    )]])
+    .resize_keyboard(true)
+    .one_time_keyboard(true);
+    bot.send_message(msg.chat.id, t(lang, "place_prompt")).reply_markup(keyboard).await?;
+    Ok(())
+}
+
+/// `/places` — напоминания, которые ждут приближения к месту.
+pub async fn handle_places_list(bot: &Bot, msg: &Message, db: &Db, lang: Lang) -> ResponseResult<()> {
+    let Some(user) = msg.from() else {
+        return Ok(());
+    };
+    let telegram_id = user.id.0 as i64;
+    let reminders = db.call(move |conn| pending_reminders(conn, telegram_id)).await.map_err(DatabaseError)?;
+    let response = if reminders.is_empty() {
+        t(lang, "places_empty")
+    } else {
+        let list = reminders
+            .iter()
+            .map(|reminder| tf(lang, "place_line", &[("id", &reminder.id), ("radius", &reminder.radius), ("text", &reminder.text)]))
+            .collect::<Vec<_>>()
+            .join("\n");
+        tf(lang, "places_list", &[("places", &list)])
+    };
+    chunks::send(bot, msg.chat.id, response).await?;
+    Ok(())
+}
+
+/// Геопозиция, присланная после `/place`: место напоминания.
+pub async fn save_location(bot: &Bot, msg: &Message, db: &Db, location: &Location, lang: Lang) -> ResponseResult<()> {
+    let Some(user) = msg.from() else {
+        return Ok(());
+    };
+    let (telegram_id, place) = (user.id.0 as i64, (location.latitude, location.longitude));
+    let saved = db.call(move |conn| set_place(conn, telegram_id, place)).await.map_err(DatabaseError)?;
+    let response = match saved {
+        Some((id, radius)) => tf(lang, "place_saved", &[("id", &id), ("radius", &radius)]),
+        None => t(lang, "place_usage"),
+    };
+    chunks::send_last(bot, msg.chat.id, response).await?.reply_markup(KeyboardRemove::new()).await?;
+    Ok(())
+}
+
+/// Сверяет геопозицию пользователя (обычно обновление трансляции) с его местами и напоминает о тех,
+/// в радиус которых он попал. Каждое напоминание срабатывает один раз.
+pub async fn check_location(msg: &Message, db: &Db, location: &Location) -> ResponseResult<()> {
+    let Some(user) = msg.from() else {
+        return Ok(());
+    };
+    let (telegram_id, here) = (user.id.0 as i64, (location.latitude, location.longitude));
+
+    let triggered = db.call(move |conn| {
+        let mut triggered = Vec::new();
+        for reminder in pending_reminders(conn, telegram_id)? {
+            let distance = distance_m(here, (reminder.lat, reminder.lon));
+            if distance <= reminder.radius as f64 && claim(conn, reminder.id)? {
+                let settings = crate::settings::resolve(conn, telegram_id, reminder.chat_id)?;
+                triggered.push((reminder, settings.lang));
+            }
+        }
+        Ok(triggered)
+    }).await.map_err(DatabaseError)?;
+
+    for (reminder, lang) in triggered {
+        log::info!("Place reminder {} triggered for user {}", reminder.id, telegram_id);
+        let text = tf(lang, "place_reminder", &[("text", &reminder.text)]);
+        chunks::send(tenants::bot(&reminder.tenant), ChatId(reminder.chat_id), text).await?;
+    }
+    Ok(())
+}
@@ -0,0 +1,325 @@
+use teloxide::prelude::*;
+use rusqlite::{Connection, params, OptionalExtension};
+
+use crate::chunks;
+use crate::humanize;
+use crate::i18n::{t, tf, Lang};
+use crate::cleanup;
+use crate::settings;
+use crate::{DatabaseError, Db, add_column_if_missing};
+
+/// Дольше суток сообщения бота уже никому не мешают.
+const MAX_CLEANUP_MINUTES: u32 = 24 * 60;
+
+/// Кто может создавать и удалять события в группе.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EventPolicy {
+    Everyone,
+    AdminsOnly,
+    Whitelist,
+}
+
+impl EventPolicy {
+    fn as_str(self) -> &'static str {
+        match self {
+            EventPolicy::Everyone => "everyone",
+            EventPolicy::AdminsOnly => "admins",
+            EventPolicy::Whitelist => "whitelist",
+        }
+    }
+
+    fn parse(value: &str) -> Option<Self> {
+        match value {
+            "everyone" => Some(EventPolicy::Everyone),
+            "admins" => Some(EventPolicy::AdminsOnly),
+            "whitelist" => Some(EventPolicy::Whitelist),
+            _ => None,
+        }
+    }
+
+    fn describe(self, lang: Lang) -> String {
+        match self {
+            EventPolicy::Everyone => t(lang, "policy_everyone"),
+            EventPolicy::AdminsOnly => t(lang, "policy_admins"),
+            EventPolicy::Whitelist => t(lang, "policy_whitelist"),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct WhitelistEntry {
+    telegram_id: i64,
+    username: Option<String>,
+}
+
+pub fn init_tables(conn: &Connection) -> Result<(), rusqlite::Error> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS chat_settings (
+            chat_id INTEGER PRIMARY KEY,
+            event_policy TEXT NOT NULL DEFAULT 'everyone'
+        )",
+        [],
+    )?;
+
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS chat_whitelist (
+            chat_id INTEGER NOT NULL,
+            telegram_id INTEGER NOT NULL,
+            username TEXT,
+            created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            PRIMARY KEY(chat_id, telegram_id)
+        )",
+        [],
+    )?;
+
+    // Через сколько минут удалять подтверждения, ошибки и команды; NULL — не удалять
+    add_column_if_missing(conn, "chat_settings", "cleanup_minutes", "INTEGER")?;
+
+    Ok(())
+}
+
+/// Задержка уборки сообщений в группе, если она включена.
+pub fn cleanup_minutes(conn: &Connection, chat_id: i64) -> Result<Option<u32>, rusqlite::Error> {
+    let minutes = conn.query_row(
+        "SELECT cleanup_minutes FROM chat_settings WHERE chat_id = ?",
+        params![chat_id],
+        |row| row.get::<_, Option<u32>>(0),
+    ).optional()?;
+    Ok(minutes.flatten())
+}
+
+fn set_cleanup_minutes(conn: &Connection, chat_id: i64, minutes: Option<u32>) -> Result<(), rusqlite::Error> {
+    conn.execute(
+        "INSERT INTO chat_settings (chat_id, cleanup_minutes) VALUES (?, ?)
+         ON CONFLICT(chat_id) DO UPDATE SET cleanup_minutes = excluded.cleanup_minutes",
+        params![chat_id, minutes],
+    )?;
+    Ok(())
+}
+
+fn get_event_policy(conn: &Connection, chat_id: i64) -> Result<EventPolicy, rusqlite::Error> {
+    let policy: Option<String> = conn.query_row(
+        "SELECT event_policy FROM chat_settings WHERE chat_id = ?",
+        params![chat_id],
+        |row| row.get(0),
+    ).optional()?;
+
+    Ok(policy.as_deref().and_then(EventPolicy::parse).unwrap_or(EventPolicy::Everyone))
+}
+
+fn set_event_policy(conn: &Connection, chat_id: i64, policy: EventPolicy) -> Result<(), rusqlite::Error> {
+    conn.execute(
+        "INSERT INTO chat_settings (chat_id, event_policy) VALUES (?, ?)
+         ON CONFLICT(chat_id) DO UPDATE SET event_policy = excluded.event_policy",
+        params![chat_id, policy.as_str()],
+    )?;
+    Ok(())
+}
+
+fn is_whitelisted(conn: &Connection, chat_id: i64, telegram_id: i64) -> Result<bool, rusqlite::Error> {
+    conn.query_row(
+        "SELECT 1 FROM chat_whitelist WHERE chat_id = ? AND telegram_id = ?",
+        params![chat_id, telegram_id],
+        |_| Ok(()),
+    ).optional().map(|row| row.is_some())
+}
+
+fn add_to_whitelist(conn: &Connection, chat_id: i64, telegram_id: i64, username: Option<String>) -> Result<(), rusqlite::Error> {
+    conn.execute(
+        "INSERT INTO chat_whitelist (chat_id, telegram_id, username) VALUES (?, ?, ?)
+         ON CONFLICT(chat_id, telegram_id) DO UPDATE SET username = excluded.username",
+        params![chat_id, telegram_id, username],
+    )?;
+    Ok(())
+}
+
+fn remove_from_whitelist(conn: &Connection, chat_id: i64, telegram_id: i64) -> Result<bool, rusqlite::Error> {
+    let removed = conn.execute(
+        "DELETE FROM chat_whitelist WHERE chat_id = ? AND telegram_id = ?",
+        params![chat_id, telegram_id],
+    )?;
+    Ok(removed > 0)
+}
+
+fn get_whitelist(conn: &Connection, chat_id: i64) -> Result<Vec<WhitelistEntry>, rusqlite::Error> {
+    let mut stmt = conn.prepare(
+        "SELECT telegram_id, username FROM chat_whitelist WHERE chat_id = ? ORDER BY created_at"
+    )?;
+
+    let entries = stmt.query_map(params![chat_id], |row| {
+        Ok(WhitelistEntry {
+            telegram_id: row.get(0)?,
+            username: row.get(1)?,
+        })
+    })?
+    .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(entries)
+}
+
+async fn is_chat_admin(bot: &Bot, chat_id: ChatId, user_id: UserId) -> ResponseResult<bool> {
+    let member = bot.get_chat_member(chat_id, user_id).await?;
+    Ok(member.is_privileged())
+}
+
+/// Проверяет, может ли автор сообщения создавать и удалять события в этом чате.
+/// В личных чатах ограничений нет.
+pub async fn can_manage_events(bot: &Bot, msg: &Message, db: &Db) -> ResponseResult<bool> {
+    if msg.chat.is_private() {
+        return Ok(true);
+    }
+    let Some(user) = msg.from() else {
+        return Ok(false);
+    };
+
+    let (chat_id, telegram_id) = (msg.chat.id.0, user.id.0 as i64);
+    let (policy, whitelisted) = db.call(move |conn| {
+        let policy = get_event_policy(conn, chat_id)?;
+        let whitelisted = policy == EventPolicy::Whitelist && is_whitelisted(conn, chat_id, telegram_id)?;
+        Ok((policy, whitelisted))
+    }).await.map_err(DatabaseError)?;
+
+    match policy {
+        EventPolicy::Everyone => Ok(true),
+        EventPolicy::Whitelist if whitelisted => Ok(true),
+        EventPolicy::AdminsOnly | EventPolicy::Whitelist => is_chat_admin(bot, msg.chat.id, user.id).await,
+    }
+}
+
+pub async fn reply_not_allowed(bot: &Bot, msg: &Message, db: &Db) -> ResponseResult<()> {
+    let chat_id = msg.chat.id.0;
+    let policy = db.call(move |conn| get_event_policy(conn, chat_id)).await.map_err(DatabaseError)?;
+    let lang = settings::for_message(db, msg).await.map_err(DatabaseError)?.lang;
+
+    let error = chunks::send(bot, msg.chat.id, tf(lang, "group_not_allowed", &[("policy", &policy.describe(lang))])).await?;
+    cleanup::schedule(bot, db, &error).await;
+    Ok(())
+}
+
+pub async fn handle_group_settings(bot: &Bot, msg: &Message, db: &Db, args: &str) -> ResponseResult<()> {
+    let lang = settings::for_message(db, msg).await.map_err(DatabaseError)?.lang;
+
+    if msg.chat.is_private() {
+        bot.send_message(msg.chat.id, t(lang, "group_settings_private_only")).await?;
+        return Ok(());
+    }
+    let Some(user) = msg.from() else {
+        return Ok(());
+    };
+
+    let chat_id = msg.chat.id.0;
+    let mut parts = args.split_whitespace();
+    let subcommand = parts.next();
+
+    if subcommand.is_none() {
+        let (policy, whitelist, overrides, cleanup) = db.call(move |conn| {
+            Ok((
+                get_event_policy(conn, chat_id)?,
+                get_whitelist(conn, chat_id)?,
+                settings::chat_overrides(conn, chat_id)?,
+                cleanup_minutes(conn, chat_id)?,
+            ))
+        }).await.map_err(DatabaseError)?;
+
+        let (chat_lang, clock, quiet) = overrides.describe(lang);
+        let cleanup = match cleanup {
+            Some(minutes) => tf(lang, "group_cleanup_after", &[("duration", &humanize::minutes(lang, minutes.into()))]),
+            None => t(lang, "quiet_off"),
+        };
+        let mut text = tf(lang, "group_settings_current", &[
+            ("policy", &policy.describe(lang)),
+            ("lang", &chat_lang),
+            ("clock", &clock),
+            ("quiet", &quiet),
+            ("cleanup", &cleanup),
+        ]);
+        text.push('\n');
+        if !whitelist.is_empty() {
+            let names = whitelist
+                .iter()
+                .map(|e| e.username.as_ref().map_or_else(|| e.telegram_id.to_string(), |u| format!("@{}", u)))
+                .collect::<Vec<_>>()
+                .join(", ");
+            text.push_str(&tf(lang, "group_whitelist", &[("names", &names)]));
+            text.push('\n');
+        }
+        text.push('\n');
+        text.push_str(&t(lang, "group_settings_help"));
+        chunks::send(bot, msg.chat.id, text).await?;
+        return Ok(());
+    }
+
+    if !is_chat_admin(bot, msg.chat.id, user.id).await? {
+        bot.send_message(msg.chat.id, t(lang, "group_admins_only")).await?;
+        return Ok(());
+    }
+
+    match subcommand.unwrap() {
+        "allow" | "deny" => {
+            let Some(target) = msg.reply_to_message().and_then(|m| m.from()) else {
+                bot.send_message(msg.chat.id, t(lang, "group_reply_to_member")).await?;
+                return Ok(());
+            };
+
+            let name = target.full_name();
+            let (target_id, username) = (target.id.0 as i64, target.username.clone());
+            let key = if subcommand == Some("allow") {
+                db.call(move |conn| add_to_whitelist(conn, chat_id, target_id, username)).await.map_err(DatabaseError)?;
+                "group_whitelist_added"
+            } else if db.call(move |conn| remove_from_whitelist(conn, chat_id, target_id)).await.map_err(DatabaseError)? {
+                "group_whitelist_removed"
+            } else {
+                "group_whitelist_missing"
+            };
+            let response = tf(lang, key, &[("name", &name)]);
+
+            chunks::send(bot, msg.chat.id, response).await?;
+        }
+        name @ ("lang" | "time" | "quiet") => {
+            let value = parts.next().unwrap_or_default();
+            let normalized = if value == "default" {
+                settings::setting_column(name).map(|column| (column, None))
+            } else {
+                settings::normalize_setting(name, value).map(|(column, value)| (column, Some(value)))
+            };
+
+            let Some((column, value)) = normalized else {
+                chunks::send(bot, msg.chat.id, tf(lang, "settings_invalid_value", &[("value", &value)])).await?;
+                return Ok(());
+            };
+
+            db.call(move |conn| settings::set_chat_setting(conn, chat_id, column, value.as_deref()))
+                .await
+                .map_err(DatabaseError)?;
+            let lang = settings::for_message(db, msg).await.map_err(DatabaseError)?.lang;
+
+            bot.send_message(msg.chat.id, t(lang, "settings_saved")).await?;
+        }
+        "cleanup" => {
+            let value = parts.next().unwrap_or_default();
+            let minutes = match value {
+                "off" => Some(None),
+                value => value.parse::<u32>().ok().filter(|minutes| (1..=MAX_CLEANUP_MINUTES).contains(minutes)).map(Some),
+            };
+            let Some(minutes) = minutes else {
+                chunks::send(bot, msg.chat.id, tf(lang, "settings_invalid_value", &[("value", &value)])).await?;
+                return Ok(());
+            };
+
+            db.call(move |conn| set_cleanup_minutes(conn, chat_id, minutes)).await.map_err(DatabaseError)?;
+            bot.send_message(msg.chat.id, t(lang, "settings_saved")).await?;
+        }
+        value => match EventPolicy::parse(value) {
+            Some(policy) => {
+                db.call(move |conn| set_event_policy(conn, chat_id, policy)).await.map_err(DatabaseError)?;
+
+                chunks::send(bot, msg.chat.id, tf(lang, "group_policy_set", &[("policy", &policy.describe(lang))])).await?;
+            }
+            None => {
+                bot.send_message(msg.chat.id, t(lang, "group_unknown_setting")).await?;
+            }
+        },
+    }
+
+    Ok(())
+}
@@ -0,0 +1,269 @@
+//! Гостевые ссылки: страница только для чтения с ближайшими событиями чата, чтобы
+//! родственники без Telegram видели общий семейный календарь. Ссылка живёт ограниченное
+//! время и открывается встроенным HTTP-сервером.
+
+use std::env;
+
+use teloxide::prelude::*;
+use aes_gcm::aead::OsRng;
+use aes_gcm::aead::rand_core::RngCore;
+use axum::extract::{Path, State};
+use axum::http::{StatusCode, header};
+use axum::response::{IntoResponse, Response};
+use chrono::{Duration, NaiveDateTime, Utc};
+use rusqlite::{Connection, params, OptionalExtension};
+
+use crate::chunks;
+use crate::feed::escape;
+use crate::groups;
+use crate::i18n::{t, tf};
+use crate::settings::{self, Settings};
+use crate::timezone;
+use crate::{DatabaseError, Db, UTC_FORMAT, crypto, http, parse_event_time};
+
+/// Срок ссылки по умолчанию и наибольший срок в днях.
+const DEFAULT_DAYS: i64 = 7;
+const MAX_DAYS: i64 = 90;
+/// Насколько вперёд страница показывает события.
+const PAGE_DAYS: i64 = 30;
+
+/// Ссылка, выданная в чате.
+#[derive(Debug)]
+struct GuestLink {
+    id: i64,
+    code: String,
+    expires_utc: NaiveDateTime,
+}
+
+/// Что показать гостю по коду.
+#[derive(Debug, PartialEq)]
+enum Lookup {
+    Unknown,
+    Expired,
+    /// Чат и тот, кто выдал ссылку: страница оформляется по его настройкам.
+    Chat { chat_id: i64, telegram_id: i64 },
+}
+
+pub fn init_tables(conn: &Connection) -> Result<(), rusqlite::Error> {
+    // Код в ссылке случайный, как у /share: чужие календари нельзя перебрать
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS guest_links (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            code TEXT NOT NULL UNIQUE,
+            chat_id INTEGER NOT NULL,
+            telegram_id INTEGER NOT NULL,
+            expires_utc TEXT NOT NULL,
+            created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP
+        )",
+        [],
+    )?;
+    Ok(())
+}
+
+/// Внешний адрес HTTP-сервера: `PUBLIC_URL` или `HTTP_ADDR`, если сервер включён.
+fn base_url() -> Option<String> {
+    let url = env::var("PUBLIC_URL").ok().or_else(|| http::address().map(|addr| format!("http://{}", addr)))?;
+    Some(url.trim_end_matches('/').to_string())
+}
+
+fn create_link(conn: &Connection, chat_id: i64, telegram_id: i64, expires_utc: NaiveDateTime) -> Result<GuestLink, rusqlite::Error> {
+    let mut bytes = [0u8; 16];
+    OsRng.fill_bytes(&mut bytes);
+    let code = hex::encode(bytes);
+    conn.execute(
+        "INSERT INTO guest_links (code, chat_id, telegram_id, expires_utc) VALUES (?, ?, ?, ?)",
+        params![code, chat_id, telegram_id, expires_utc.format(UTC_FORMAT).to_string()],
+    )?;
+    Ok(GuestLink { id: conn.last_insert_rowid(), code, expires_utc })
+}
+
+/// Действующие ссылки чата; истёкшие заодно удаляются.
+fn chat_links(conn: &Connection, chat_id: i64, now: NaiveDateTime) -> Result<Vec<GuestLink>, rusqlite::Error> {
+    conn.execute("DELETE FROM guest_links WHERE expires_utc <= ?", params![now.format(UTC_FORMAT).to_string()])?;
+    let mut stmt = conn.prepare("SELECT id, code, expires_utc FROM guest_links WHERE chat_id = ? ORDER BY id")?;
+    let links = stmt.query_map(params![chat_id], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?, row.get::<_, String>(2)?)))?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(links
+        .into_iter()
+        .filter_map(|(id, code, expires)| {
+            Some(GuestLink { id, code, expires_utc: NaiveDateTime::parse_from_str(&expires, UTC_FORMAT).ok()? })
+        })
+        .collect())
+}
+
+fn revoke_link(conn: &Connection, chat_id: i64, link_id: i64) -> Result<bool, rusqlite::Error> {
+    let deleted = conn.execute("DELETE FROM guest_links WHERE id = ? AND chat_id = ?", params![link_id, chat_id])?;
+    Ok(deleted > 0)
+}
+
+fn lookup(conn: &Connection, code: &str, now: NaiveDateTime) -> Result<Lookup, rusqlite::Error> {
+    let found = conn.query_row(
+        "SELECT chat_id, telegram_id, expires_utc FROM guest_links WHERE code = ?",
+        params![code],
+        |row| Ok((row.get::<_, i64>(0)?, row.get::<_, i64>(1)?, row.get::<_, String>(2)?)),
+    ).optional()?;
+    Ok(match found {
+        None => Lookup::Unknown,
+        Some((_, _, expires)) if expires <= now.format(UTC_FORMAT).to_string() => Lookup::Expired,
+        Some((chat_id, telegram_id, _)) => Lookup::Chat { chat_id, telegram_id },
+    })
+}
+
+/// Неотправленные события чата на ближайшие `PAGE_DAYS` дней: местное время и текст.
+fn upcoming_events(conn: &Connection, chat_id: i64, now: NaiveDateTime) -> Result<Vec<(String, String)>, rusqlite::Error> {
+    let mut stmt = conn.prepare(
+        "SELECT e.event_time, e.text FROM events e
+         JOIN users u ON e.user_id = u.id
+         WHERE COALESCE(e.chat_id, u.telegram_id) = ? AND e.status = 'pending' AND e.event_utc >= ? AND e.event_utc < ?
+         ORDER BY e.event_utc"
+    )?;
+    let rows = stmt.query_map(
+        params![chat_id, now.format(UTC_FORMAT).to_string(), (now + Duration::days(PAGE_DAYS)).format(UTC_FORMAT).to_string()],
+        |row| Ok((row.get::<_, String>(0)?, crypto::open(row.get(1)?))),
+    )?;
+    rows.collect()
+}
+
+/// Страница для гостя: события, сгруппированные по дням, без кнопок и форм.
+fn render(settings: &Settings, events: &[(String, String)]) -> String {
+    let lang = settings.lang;
+    let mut body = String::new();
+    let mut current_day = None;
+    for (event_time, text) in events {
+        let time = parse_event_time(event_time);
+        let day = time.map(|time| time.date());
+        if day != current_day || body.is_empty() {
+            if !body.is_empty() {
+                body.push_str("</ul>\n");
+            }
+            let heading = day.map_or_else(|| event_time.clone(), |day| settings.format_date(day));
+            body.push_str(&format!("<h2>{}</h2>\n<ul>\n", escape(&heading)));
+            current_day = day;
+        }
+        let time = time.map_or_else(String::new, |time| settings.format_time(time.time()));
+        body.push_str(&format!("<li><b>{}</b> {}</li>\n", escape(&time), escape(text).replace('\n', "<br>")));
+    }
+    if body.is_empty() {
+        body = format!("<p>{}</p>\n", escape(&t(lang, "guest_page_empty")));
+    } else {
+        body.push_str("</ul>\n");
+    }
+
+    format!(
+        "<!DOCTYPE html>\n<html lang=\"{lang}\">\n<head>\n<meta charset=\"utf-8\">\n\
+         <meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\n\
+         <meta name=\"robots\" content=\"noindex\">\n<title>{title}</title>\n\
+         <style>body{{font-family:sans-serif;max-width:40em;margin:2em auto;padding:0 1em}}li{{margin:.3em 0}}</style>\n\
+         </head>\n<body>\n<h1>{title}</h1>\n{body}</body>\n</html>\n",
+        lang = lang.code(),
+        title = escape(&t(lang, "guest_page_title")),
+        body = body,
+    )
+}
+
+/// `GET /guest/<код>` — ближайшие события чата только для чтения.
+pub async fn handle_page(State(db): State<Db>, Path(code): Path<String>) -> Response {
+    let result = db.call(move |conn| {
+        let now = Utc::now().naive_utc();
+        let (chat_id, telegram_id) = match lookup(conn, &code, now)? {
+            Lookup::Chat { chat_id, telegram_id } => (chat_id, telegram_id),
+            other => return Ok(Err(other)),
+        };
+        let settings = settings::resolve(conn, telegram_id, chat_id)?;
+        Ok(Ok(render(&settings, &upcoming_events(conn, chat_id, now)?)))
+    }).await;
+
+    match result {
+        Ok(Ok(page)) => ([(header::CONTENT_TYPE, "text/html; charset=utf-8"), (header::CACHE_CONTROL, "no-store")], page).into_response(),
+        Ok(Err(Lookup::Expired)) => (StatusCode::GONE, "link expired").into_response(),
+        Ok(Err(_)) => (StatusCode::NOT_FOUND, "unknown link").into_response(),
+        Err(e) => {
+            log::error!("Guest page failed: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+/// `/guest [дни]`, `/guest list`, `/guest revoke <id>` — ссылки на страницу с событиями чата
+/// для тех, у кого нет Telegram. Выдавать и отзывать их может тот, кто управляет событиями чата.
+pub async fn handle_guest_command(bot: &Bot, msg: &Message, db: &Db, args: &str, settings: &Settings) -> ResponseResult<()> {
+    let lang = settings.lang;
+    let Some(user) = msg.from() else {
+        return Ok(());
+    };
+    let Some(base_url) = base_url() else {
+        bot.send_message(msg.chat.id, t(lang, "guest_disabled")).await?;
+        return Ok(());
+    };
+    if !groups::can_manage_events(bot, msg, db).await? {
+        return groups::reply_not_allowed(bot, msg, db).await;
+    }
+
+    let (chat_id, telegram_id) = (msg.chat.id.0, user.id.0 as i64);
+    let now = Utc::now().naive_utc();
+    let (action, rest) = args.split_once(char::is_whitespace).unwrap_or((args, ""));
+    let response = match action {
+        "list" => {
+            let links = db.call(move |conn| chat_links(conn, chat_id, now)).await.map_err(DatabaseError)?;
+            if links.is_empty() {
+                t(lang, "guest_list_empty")
+            } else {
+                links
+                    .iter()
+                    .map(|link| guest_line(settings, &base_url, link))
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            }
+        }
+        "revoke" => match rest.trim().trim_start_matches('#').parse::<i64>() {
+            Ok(link_id) => {
+                let revoked = db.call(move |conn| revoke_link(conn, chat_id, link_id)).await.map_err(DatabaseError)?;
+                tf(lang, if revoked { "guest_revoked" } else { "guest_not_found" }, &[("id", &link_id)])
+            }
+            Err(_) => t(lang, "guest_usage"),
+        },
+        days => match if days.is_empty() { Some(DEFAULT_DAYS) } else { days.parse().ok() } {
+            Some(days) if (1..=MAX_DAYS).contains(&days) => {
+                let link = db.call(move |conn| create_link(conn, chat_id, telegram_id, now + Duration::days(days)))
+                    .await
+                    .map_err(DatabaseError)?;
+                log::info!("User {} created guest link {} for chat {}", telegram_id, link.id, chat_id);
+                tf(lang, "guest_created", &[("link", &guest_line(settings, &base_url, &link))])
+            }
+            _ => t(lang, "guest_usage"),
+        },
+    };
+    chunks::send(bot, msg.chat.id, response).await?;
+    Ok(())
+}
+
+fn guest_line(settings: &Settings, base_url: &str, link: &GuestLink) -> String {
+    let expires = timezone::from_utc(link.expires_utc, settings.timezone);
+    tf(settings.lang, "guest_line", &[
+        ("id", &link.id),
+        ("url", &format!("{}/guest/{}", base_url, link.code)),
+        ("expires", &settings.format_datetime(expires)),
+    ])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::tests::memory_db;
+
+    #[tokio::test]
+    async fn links_open_until_they_expire() {
+        let db = memory_db();
+        let now = Utc::now().naive_utc();
+        let (fresh, stale, unknown, listed) = db.call(move |conn| {
+            let fresh = create_link(conn, -100, 42, now + Duration::days(1))?;
+            let stale = create_link(conn, -100, 42, now + Duration::minutes(1))?;
+            let later = now + Duration::minutes(2);
+            Ok((lookup(conn, &fresh.code, later)?, lookup(conn, &stale.code, later)?, lookup(conn, "nope", later)?, chat_links(conn, -100, later)?.len()))
+        }).await.unwrap();
+        assert_eq!(fresh, Lookup::Chat { chat_id: -100, telegram_id: 42 });
+        assert_eq!(stale, Lookup::Expired);
+        assert_eq!(unknown, Lookup::Unknown);
+        assert_eq!(listed, 1);
+    }
+}
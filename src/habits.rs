@@ -0,0 +1,346 @@
+use teloxide::prelude::*;
+use teloxide::types::{InlineKeyboardButton, InlineKeyboardMarkup};
+use chrono::{Duration, NaiveDate, NaiveTime};
+use rusqlite::{Connection, params, OptionalExtension};
+
+use crate::blocklist;
+use crate::chunks;
+use crate::humanize;
+use crate::clock::Clock;
+use crate::groups;
+use crate::i18n::{t, tf, Lang};
+use crate::settings::{self, Settings};
+use crate::timezone;
+use crate::{DatabaseError, Db, ensure_user_exists, in_transaction, parse_time_input, tenants};
+
+const DAY_FORMAT: &str = "%Y-%m-%d";
+
+#[derive(Debug)]
+struct Habit {
+    id: i64,
+    tenant: String,
+    owner_id: i64,
+    chat_id: i64,
+    name: String,
+    remind_time: String,
+    created_on: NaiveDate,
+    last_sent_on: Option<String>,
+}
+
+pub fn init_tables(conn: &Connection) -> Result<(), rusqlite::Error> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS habits (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            user_id INTEGER NOT NULL,
+            chat_id INTEGER NOT NULL,
+            name TEXT NOT NULL,
+            remind_time TEXT NOT NULL,
+            active INTEGER NOT NULL DEFAULT 1,
+            last_sent_on TEXT,
+            created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            FOREIGN KEY(user_id) REFERENCES users(id) ON DELETE CASCADE
+        )",
+        [],
+    )?;
+
+    // Одна запись на привычку в день: выполнена или нет
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS habit_log (
+            habit_id INTEGER NOT NULL,
+            day TEXT NOT NULL,
+            done INTEGER NOT NULL,
+            PRIMARY KEY(habit_id, day),
+            FOREIGN KEY(habit_id) REFERENCES habits(id) ON DELETE CASCADE
+        )",
+        [],
+    )?;
+    Ok(())
+}
+
+fn save_habit(conn: &Connection, tenant: &str, user_id: i64, chat_id: i64, name: &str, remind_time: &str) -> Result<i64, rusqlite::Error> {
+    conn.execute(
+        "INSERT INTO habits (tenant, user_id, chat_id, name, remind_time) VALUES (?, ?, ?, ?, ?)",
+        params![tenant, user_id, chat_id, name, remind_time],
+    )?;
+    Ok(conn.last_insert_rowid())
+}
+
+fn query_habits<P: rusqlite::Params>(conn: &Connection, condition: &str, params: P) -> Result<Vec<Habit>, rusqlite::Error> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT h.id, u.telegram_id, h.chat_id, h.name, h.remind_time, date(h.created_at), h.last_sent_on, h.tenant
+         FROM habits h
+         JOIN users u ON h.user_id = u.id
+         WHERE h.active = 1 AND {}
+         ORDER BY h.remind_time, h.id",
+        condition
+    ))?;
+    let habits = stmt.query_map(params, |row| {
+        let created_on: String = row.get(5)?;
+        Ok(Habit {
+            id: row.get(0)?,
+            tenant: row.get(7)?,
+            owner_id: row.get(1)?,
+            chat_id: row.get(2)?,
+            name: row.get(3)?,
+            remind_time: row.get(4)?,
+            created_on: NaiveDate::parse_from_str(&created_on, DAY_FORMAT).unwrap_or_default(),
+            last_sent_on: row.get(6)?,
+        })
+    })?
+    .collect::<Result<Vec<_>, _>>()?;
+    Ok(habits)
+}
+
+fn get_user_habits(conn: &Connection, telegram_id: i64) -> Result<Vec<Habit>, rusqlite::Error> {
+    query_habits(conn, "u.telegram_id = ?", params![telegram_id])
+}
+
+fn get_active_habits(conn: &Connection) -> Result<Vec<Habit>, rusqlite::Error> {
+    query_habits(conn, blocklist::NOT_BLOCKED, [])
+}
+
+fn stop_habit(conn: &Connection, telegram_id: i64, habit_id: i64) -> Result<bool, rusqlite::Error> {
+    let updated = conn.execute(
+        "UPDATE habits SET active = 0
+         WHERE id = ? AND user_id = (SELECT id FROM users WHERE telegram_id = ?)",
+        params![habit_id, telegram_id],
+    )?;
+    Ok(updated > 0)
+}
+
+fn mark_habit_sent(conn: &Connection, habit_id: i64, day: NaiveDate) -> Result<(), rusqlite::Error> {
+    conn.execute(
+        "UPDATE habits SET last_sent_on = ? WHERE id = ?",
+        params![day.format(DAY_FORMAT).to_string(), habit_id],
+    )?;
+    Ok(())
+}
+
+fn habit_owner(conn: &Connection, habit_id: i64) -> Result<Option<(i64, String)>, rusqlite::Error> {
+    conn.query_row(
+        "SELECT u.telegram_id, h.name FROM habits h JOIN users u ON h.user_id = u.id WHERE h.id = ?",
+        params![habit_id],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    ).optional()
+}
+
+fn log_habit(conn: &Connection, habit_id: i64, day: &str, done: bool) -> Result<(), rusqlite::Error> {
+    conn.execute(
+        "INSERT INTO habit_log (habit_id, day, done) VALUES (?, ?, ?)
+         ON CONFLICT(habit_id, day) DO UPDATE SET done = excluded.done",
+        params![habit_id, day, done],
+    )?;
+    Ok(())
+}
+
+fn done_days(conn: &Connection, habit_id: i64) -> Result<Vec<NaiveDate>, rusqlite::Error> {
+    let mut stmt = conn.prepare("SELECT day FROM habit_log WHERE habit_id = ? AND done = 1")?;
+    let days = stmt.query_map(params![habit_id], |row| row.get::<_, String>(0))?
+        .collect::<Result<Vec<_>, _>>()?
+        .into_iter()
+        .filter_map(|day| NaiveDate::parse_from_str(&day, DAY_FORMAT).ok())
+        .collect();
+    Ok(days)
+}
+
+/// Серия — число дней подряд с отметкой «да». Сегодняшний день ещё не прерывает серию,
+/// если на вопрос пока не ответили.
+/// Текущие серии привычек пользователя для `/stats`.
+pub fn streaks(conn: &Connection, telegram_id: i64, today: NaiveDate) -> Result<Vec<(String, i64)>, rusqlite::Error> {
+    get_user_habits(conn, telegram_id)?
+        .into_iter()
+        .map(|habit| Ok((habit.name, streak(&done_days(conn, habit.id)?, today))))
+        .collect()
+}
+
+fn streak(done: &[NaiveDate], today: NaiveDate) -> i64 {
+    let mut day = if done.contains(&today) { today } else { today - Duration::days(1) };
+    let mut streak = 0;
+    while done.contains(&day) {
+        streak += 1;
+        day -= Duration::days(1);
+    }
+    streak
+}
+
+/// Процент выполнения за последние 7 дней (или с момента создания привычки, если она моложе).
+fn weekly_percent(done: &[NaiveDate], created_on: NaiveDate, today: NaiveDate) -> i64 {
+    let week_start = today - Duration::days(6);
+    let start = week_start.max(created_on).min(today);
+    let days = (today - start).num_days() + 1;
+    let done_in_week = done.iter().filter(|day| **day >= start && **day <= today).count() as i64;
+    done_in_week * 100 / days
+}
+
+fn answer_keyboard(lang: Lang, habit_id: i64, day: NaiveDate) -> InlineKeyboardMarkup {
+    let day = day.format(DAY_FORMAT);
+    InlineKeyboardMarkup::new(vec![vec![
+        InlineKeyboardButton::callback(t(lang, "habit_yes"), format!("hab:yes:{}:{}", habit_id, day)),
+        InlineKeyboardButton::callback(t(lang, "habit_no"), format!("hab:no:{}:{}", habit_id, day)),
+    ]])
+}
+
+/// `/habit daily ЧЧ:ММ название` или `/habit stop #id`.
+pub async fn handle_habit_command(bot: &Bot, msg: &Message, db: &Db, args: &str, settings: &Settings) -> ResponseResult<()> {
+    let lang = settings.lang;
+    let user = msg.from().unwrap();
+    let mut parts = args.splitn(3, char::is_whitespace);
+    let action = parts.next().unwrap_or_default();
+
+    match action {
+        "daily" | "ежедневно" => {
+            let time = parts.next().and_then(parse_time_input);
+            let name = parts.next().unwrap_or_default().trim();
+            let Some(time) = time.filter(|_| !name.is_empty()) else {
+                bot.send_message(msg.chat.id, t(lang, "habit_usage")).await?;
+                return Ok(());
+            };
+            if !groups::can_manage_events(bot, msg, db).await? {
+                return groups::reply_not_allowed(bot, msg, db).await;
+            }
+
+            let (telegram_id, username, chat_id) = (user.id.0 as i64, user.username.clone(), msg.chat.id.0);
+            let (habit_name, tenant) = (name.to_string(), tenants::name_of(bot));
+            let habit_id = db.call(move |conn| in_transaction(conn, |tx| {
+                let user_id = ensure_user_exists(tx, tenant, telegram_id, username)?;
+                save_habit(tx, tenant, user_id, chat_id, &habit_name, &time.format("%H:%M").to_string())
+            })).await.map_err(DatabaseError)?;
+
+            log::info!("Created habit {}", habit_id);
+            let time = settings.format_time(time);
+            chunks::send(bot, msg.chat.id, tf(lang, "habit_saved", &[("id", &habit_id), ("name", &name), ("time", &time)])).await?;
+        }
+        "stop" => {
+            let habit_id: Option<i64> = parts.next().and_then(|id| id.trim_start_matches('#').parse().ok());
+            let Some(habit_id) = habit_id else {
+                bot.send_message(msg.chat.id, t(lang, "habit_usage")).await?;
+                return Ok(());
+            };
+
+            let telegram_id = user.id.0 as i64;
+            let stopped = db.call(move |conn| stop_habit(conn, telegram_id, habit_id)).await.map_err(DatabaseError)?;
+
+            let key = if stopped { "habit_stopped" } else { "habit_not_found" };
+            chunks::send(bot, msg.chat.id, tf(lang, key, &[("id", &habit_id)])).await?;
+        }
+        _ => {
+            bot.send_message(msg.chat.id, t(lang, "habit_usage")).await?;
+        }
+    }
+    Ok(())
+}
+
+/// `/habits` — серии и процент выполнения за неделю.
+pub async fn handle_habits_report(bot: &Bot, msg: &Message, db: &Db, settings: &Settings) -> ResponseResult<()> {
+    let lang = settings.lang;
+    let today = timezone::now_in(settings.timezone).date();
+
+    let telegram_id = msg.from().unwrap().id.0 as i64;
+    let habits = db.call(move |conn| {
+        get_user_habits(conn, telegram_id)?
+            .into_iter()
+            .map(|habit| Ok((done_days(conn, habit.id)?, habit)))
+            .collect::<Result<Vec<_>, rusqlite::Error>>()
+    }).await.map_err(DatabaseError)?;
+
+    let mut lines = Vec::new();
+    for (done, habit) in &habits {
+        let time = NaiveTime::parse_from_str(&habit.remind_time, "%H:%M")
+            .map_or_else(|_| habit.remind_time.clone(), |time| settings.format_time(time));
+        lines.push(tf(lang, "habit_report_line", &[
+            ("id", &habit.id),
+            ("name", &habit.name),
+            ("time", &time),
+            ("streak", &humanize::days(lang, streak(done, today))),
+            ("percent", &weekly_percent(done, habit.created_on, today)),
+        ]));
+    }
+
+    if lines.is_empty() {
+        bot.send_message(msg.chat.id, t(lang, "habits_empty")).await?;
+        return Ok(());
+    }
+    chunks::send(bot, msg.chat.id, tf(lang, "habits_report", &[("habits", &lines.join("\n"))])).await?;
+    Ok(())
+}
+
+/// Задаёт вопрос «Сделали?» по привычкам, время которых наступило сегодня.
+pub async fn send_due_habits(db: &Db, clock: &dyn Clock) {
+    let instant = clock.now();
+    let questions = db.call(move |conn| {
+        let mut questions = Vec::new();
+        for habit in get_active_habits(conn)? {
+            let Ok(remind_time) = NaiveTime::parse_from_str(&habit.remind_time, "%H:%M") else {
+                continue;
+            };
+
+            // Время привычки — по часам владельца, поэтому при переводе часов она не сдвигается
+            let settings = settings::resolve(conn, habit.owner_id, habit.chat_id).unwrap_or_default();
+            let now = timezone::local_at(instant, settings.timezone);
+            let today = now.date();
+            if now.time() < remind_time || habit.last_sent_on.as_deref() == Some(today.format(DAY_FORMAT).to_string().as_str()) {
+                continue;
+            }
+            if settings.is_quiet(now.time()) {
+                continue;
+            }
+            let _ = mark_habit_sent(conn, habit.id, today);
+            questions.push((habit, settings.lang, today));
+        }
+        Ok(questions)
+    }).await;
+    let questions = match questions {
+        Ok(questions) => questions,
+        Err(e) => {
+            log::error!("Failed to load habits: {}", e);
+            return;
+        }
+    };
+
+    for (habit, lang, today) in questions {
+        println!("Sending habit check for habit: {:?}", habit);
+        let _ = tenants::bot(&habit.tenant)
+            .send_message(ChatId(habit.chat_id), tf(lang, "habit_question", &[("name", &habit.name)]))
+            .reply_markup(answer_keyboard(lang, habit.id, today))
+            .await;
+    }
+}
+
+pub async fn handle_callback(bot: &Bot, q: &CallbackQuery, db: &Db, args: &str) -> ResponseResult<()> {
+    let mut parts = args.splitn(3, ':');
+    let answer = parts.next().unwrap_or_default();
+    let habit_id: i64 = parts.next().and_then(|id| id.parse().ok()).unwrap_or_default();
+    let day = parts.next().unwrap_or_default();
+    let Some(message) = q.message.as_ref() else {
+        bot.answer_callback_query(q.id.clone()).await?;
+        return Ok(());
+    };
+
+    let (telegram_id, chat_id) = (q.from.id.0 as i64, message.chat.id.0);
+    let (settings, owner) = db.call(move |conn| {
+        Ok((settings::resolve(conn, telegram_id, chat_id)?, habit_owner(conn, habit_id)?))
+    }).await.map_err(DatabaseError)?;
+    let lang = settings.lang;
+    let Some((owner, name)) = owner else {
+        bot.answer_callback_query(q.id.clone()).await?;
+        return Ok(());
+    };
+    if owner != telegram_id {
+        bot.answer_callback_query(q.id.clone()).text(t(lang, "habit_not_yours")).await?;
+        return Ok(());
+    }
+
+    let done = answer == "yes";
+    let day = day.to_string();
+    let today = timezone::now_in(settings.timezone).date();
+    let current_streak = db.call(move |conn| {
+        log_habit(conn, habit_id, &day, done)?;
+        Ok(streak(&done_days(conn, habit_id)?, today))
+    }).await.map_err(DatabaseError)?;
+
+    let key = if done { "habit_logged_yes" } else { "habit_logged_no" };
+    bot.answer_callback_query(q.id.clone()).await?;
+    bot.edit_message_text(message.chat.id, message.id, tf(lang, key, &[("name", &name), ("streak", &humanize::days(lang, current_streak))]))
+        .await?;
+    Ok(())
+}
@@ -0,0 +1,133 @@
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::Json;
+use chrono::{DateTime, Utc};
+use serde_json::{Value, json};
+use teloxide::prelude::*;
+
+use crate::shards::{self, Shard};
+use crate::{Db, supervisor, tenants};
+
+/// Проход планировщика занимает секунды; без проходов дольше этого он считается зависшим.
+const STALL_AFTER: Duration = Duration::from_secs(120);
+/// Сколько проверка ждёт базу и Telegram, чтобы оркестратор не отвалился по своему тайм-ауту.
+const CHECK_TIMEOUT: Duration = Duration::from_secs(5);
+
+struct Tick {
+    at: Instant,
+    time: Option<DateTime<Utc>>,
+}
+
+fn last_tick() -> &'static Mutex<Tick> {
+    static TICK: OnceLock<Mutex<Tick>> = OnceLock::new();
+    TICK.get_or_init(|| Mutex::new(Tick { at: Instant::now(), time: None }))
+}
+
+/// Последние проходы планировщика напоминаний по шардам этого процесса.
+fn shard_ticks() -> &'static Mutex<HashMap<Shard, Tick>> {
+    static TICKS: OnceLock<Mutex<HashMap<Shard, Tick>>> = OnceLock::new();
+    TICKS.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+fn started() -> Instant {
+    static STARTED: OnceLock<Instant> = OnceLock::new();
+    *STARTED.get_or_init(Instant::now)
+}
+
+/// Начинает отсчёт с запуска бота: до первого прохода планировщик не считается зависшим.
+pub fn mark_started() {
+    started();
+    last_tick();
+}
+
+/// Отмечает завершённый проход планировщика уведомлений.
+pub fn record_tick() {
+    let mut tick = last_tick().lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    *tick = Tick { at: Instant::now(), time: Some(Utc::now()) };
+}
+
+/// Отмечает проход планировщика напоминаний по шарду, аренду которого держит этот процесс.
+pub fn record_shard_tick(shard: Shard) {
+    let mut ticks = shard_ticks().lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    ticks.insert(shard, Tick { at: Instant::now(), time: Some(Utc::now()) });
+}
+
+/// Шарды, аренда которых за этим процессом: зависший цикл шарда не продлевает аренду,
+/// но и не отдаёт её, пока другой процесс не перехватит. Без базы список пуст —
+/// недоступная база и так отмечена отдельно.
+async fn shard_status(db: &Db) -> (bool, Vec<Value>) {
+    let held = match tokio::time::timeout(CHECK_TIMEOUT, db.call(shards::held)).await {
+        Ok(Ok(held)) => held,
+        _ => return (false, Vec::new()),
+    };
+    let ticks = shard_ticks().lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    let mut any_stalled = false;
+    let status = held
+        .into_iter()
+        .map(|shard| {
+            let (age, time) = ticks.get(&shard).map_or((started().elapsed(), None), |tick| (tick.at.elapsed(), tick.time));
+            let stalled = age > STALL_AFTER;
+            any_stalled |= stalled;
+            json!({
+                "shard": shard.index,
+                "last_tick": time.map(|time| time.to_rfc3339()),
+                "seconds_since_tick": age.as_secs(),
+                "stalled": stalled,
+            })
+        })
+        .collect();
+    (any_stalled, status)
+}
+
+async fn database_ok(db: &Db) -> bool {
+    let check = db.call(|conn| conn.query_row("SELECT 1", [], |row| row.get::<_, i64>(0)));
+    matches!(tokio::time::timeout(CHECK_TIMEOUT, check).await, Ok(Ok(_)))
+}
+
+async fn telegram_ok(bot: &Bot) -> bool {
+    matches!(tokio::time::timeout(CHECK_TIMEOUT, bot.get_me().send()).await, Ok(Ok(_)))
+}
+
+/// `GET /healthz` для Kubernetes и Docker: база, последние проходы планировщиков,
+/// связь с Telegram и фоновые задачи. 503 — когда недоступна база, завис планировщик
+/// или задача ждёт перезапуска. Недоступный Telegram только показывается:
+/// перезапуск бота его не починит.
+pub async fn handle_healthz(State(db): State<Db>) -> (StatusCode, Json<Value>) {
+    let database = database_ok(&db).await;
+
+    let (age, tick_time) = {
+        let tick = last_tick().lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        (tick.at.elapsed(), tick.time)
+    };
+    let (shards_stalled, per_shard) = shard_status(&db).await;
+    let stalled = age > STALL_AFTER || shards_stalled;
+
+    let mut telegram = serde_json::Map::new();
+    for tenant in tenants::all() {
+        telegram.insert(tenant.name.clone(), json!(telegram_ok(&tenant.bot).await));
+    }
+
+    let (tasks_ok, tasks) = supervisor::health();
+    let ok = database && !stalled && tasks_ok;
+    if !ok {
+        log::error!("Health check failed: database {}, scheduler stalled {}, tasks {}", database, stalled, tasks_ok);
+    }
+    let status = if ok { StatusCode::OK } else { StatusCode::SERVICE_UNAVAILABLE };
+    let body = json!({
+        "ok": ok,
+        "database": database,
+        "scheduler": {
+            "last_tick": tick_time.map(|time| time.to_rfc3339()),
+            "seconds_since_tick": age.as_secs(),
+            "stalled": stalled,
+            "shards": per_shard,
+        },
+        "telegram": telegram,
+        "tasks": tasks,
+    });
+    (status, Json(body))
+}
@@ -0,0 +1,211 @@
+use teloxide::prelude::*;
+use teloxide::types::ParseMode;
+
+use crate::i18n::{t, tf, Lang};
+
+/// Раздел справки `/help <раздел>`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Topic {
+    Creation,
+    Recurrence,
+    Lists,
+    Groups,
+    Settings,
+    Export,
+    Tools,
+}
+
+/// Разделы в порядке показа: код, название по-русски, по-английски.
+const TOPICS: &[(Topic, &str, &str, &str)] = &[
+    (Topic::Creation, "creation", "создание", "creation"),
+    (Topic::Recurrence, "recurrence", "повторы", "recurrence"),
+    (Topic::Lists, "lists", "списки", "lists"),
+    (Topic::Groups, "groups", "группы", "groups"),
+    (Topic::Settings, "settings", "настройки", "settings"),
+    (Topic::Export, "export", "экспорт", "export"),
+    (Topic::Tools, "tools", "инструменты", "tools"),
+];
+
+/// Строка справки: синтаксис, описание и пример, который можно скопировать и отправить.
+/// Тексты парами (по-русски, по-английски), как в `i18n`.
+struct Entry {
+    topic: Topic,
+    usage: (&'static str, &'static str),
+    about: (&'static str, &'static str),
+    example: Option<(&'static str, &'static str)>,
+}
+
+const fn entry(
+    topic: Topic,
+    usage: (&'static str, &'static str),
+    about: (&'static str, &'static str),
+    example: Option<(&'static str, &'static str)>,
+) -> Entry {
+    Entry { topic, usage, about, example }
+}
+
+/// Реестр команд и форматов. Новая команда добавляется сюда, и справка по разделу строится сама.
+const REGISTRY: &[Entry] = &[
+    entry(Topic::Creation, ("@ЧЧ:ММ текст", "@HH:MM text"), ("событие на сегодня", "event for today"),
+        Some(("@18:30 Позвонить маме", "@18:30 Call mom"))),
+    entry(Topic::Creation, ("@ДД.ММ ЧЧ:ММ текст", "@DD.MM HH:MM text"), ("событие на дату", "event for a specific date"),
+        Some(("@25.12 10:00 Купить подарки", "@25.12 10:00 Buy presents"))),
+    entry(Topic::Creation, ("@ДД.ММ.ГГГГ ЧЧ:ММ текст", "@DD.MM.YYYY HH:MM text"), ("событие на дату с годом", "event for a date with year"),
+        Some(("@01.03.2027 09:00 Продлить страховку", "@01.03.2027 09:00 Renew insurance"))),
+    entry(Topic::Creation, ("@ЧЧ:ММ-ЧЧ:ММ текст", "@HH:MM-HH:MM text"), ("событие с временем окончания", "event with an end time"),
+        Some(("@14:00-15:30 Встреча с командой", "@14:00-15:30 Team meeting"))),
+    entry(Topic::Creation, ("@7pm, @7:30 am", "@7pm, @7:30 am"), ("время в 12-часовом формате", "time in 12-hour format"),
+        Some(("@7:30 am Пробежка", "@7:30 am Morning run"))),
+    entry(Topic::Creation, ("- пункт", "- item"), ("строки с «- » станут чек-листом в напоминании", "lines starting with \"- \" become a checklist in the reminder"),
+        Some(("@19:00 Купить\n- хлеб\n- молоко", "@19:00 Shopping\n- bread\n- milk"))),
+    entry(Topic::Creation, ("! или !!", "! or !!"), ("в начале текста повышает приоритет", "at the start of the text raises the priority"),
+        Some(("@12:00 !! Оплатить счёт", "@12:00 !! Pay the bill"))),
+    entry(Topic::Creation, ("✅ 👍 😴", "✅ 👍 😴"),
+        ("реакция на напоминание: ✅ или 👍 — выполнено, 😴 — отложить на час", "reaction to a reminder: ✅ or 👍 marks it done, 😴 snoozes it for an hour"), None),
+    entry(Topic::Creation, ("ответ на подтверждение", "reply to a confirmation"),
+        ("новое @время или текст изменяет событие; можно и отредактировать исходное сообщение", "a new @time or text changes the event; you can also edit the original message"),
+        Some(("@19:30", "@19:30"))),
+    entry(Topic::Creation, ("/duplicate #id @ДД.ММ ЧЧ:ММ", "/duplicate #id @DD.MM HH:MM"), ("копия события на новое время", "copy an event to a new time"),
+        Some(("/duplicate #12 @25.12 10:00", "/duplicate #12 @25.12 10:00"))),
+    entry(Topic::Creation, ("/star #id", "/star #id"), ("избранное: вверху списка и в каждой сводке", "starred: on top of the list and in every digest"),
+        Some(("/star #12", "/star #12"))),
+    entry(Topic::Creation, ("/icon #id 💼|off", "/icon #id 💼|off"), ("значок категории события", "event category icon"),
+        Some(("/icon #12 работа", "/icon #12 work"))),
+    entry(Topic::Recurrence, ("-> 3d текст", "-> 3d text"),
+        ("новое напоминание через 3 дня после «Готово» (m, h, d, w)", "a new reminder 3 days after \"Done\" (m, h, d, w)"),
+        Some(("@10:00 Полить цветы\n-> 3d Полить цветы", "@10:00 Water the plants\n-> 3d Water the plants"))),
+    entry(Topic::Recurrence, ("/repeat #id 10m [6]", "/repeat #id 10m [6]"), ("повторять напоминание, пока не нажато «Готово»", "repeat the reminder until you press \"Done\""),
+        Some(("/repeat #12 10m 6", "/repeat #12 10m 6"))),
+    entry(Topic::Recurrence, ("/habit daily ЧЧ:ММ название, /habits", "/habit daily HH:MM name, /habits"), ("привычки и серии", "habits and streaks"),
+        Some(("/habit daily 07:00 Зарядка", "/habit daily 07:00 Workout"))),
+    entry(Topic::Recurrence, ("/meds ЧЧ:ММ,ЧЧ:ММ на N дней название, /meds", "/meds HH:MM,HH:MM for N days name, /meds"),
+        ("курс лекарств: приёмы, отметки по дням и итог", "a medication course: doses, daily marks and a summary"),
+        Some(("/meds 08:00,14:00,20:00 на 10 дней амоксициллин", "/meds 08:00,14:00,20:00 for 10 days amoxicillin"))),
+    entry(Topic::Recurrence, ("/deadline @ДД.ММ ЧЧ:ММ текст, /deadline #id", "/deadline @DD.MM HH:MM text, /deadline #id"),
+        ("дедлайн: предупреждения за неделю, 3 дня, день и 3 часа", "a deadline: warnings a week, 3 days, a day and 3 hours before"),
+        Some(("/deadline @20.06 18:00 Сдать диплом", "/deadline @20.06 18:00 Submit the thesis"))),
+    entry(Topic::Recurrence, ("/anniversary ДД.ММ.ГГГГ [ЧЧ:ММ] фраза", "/anniversary DD.MM.YYYY [HH:MM] phrase"),
+        ("годовщина каждый год, {years} — сколько лет прошло", "a yearly anniversary, {years} is how many years have passed"),
+        Some(("/anniversary 15.06.2021 {years} с переезда", "/anniversary 15.06.2021 {years} since we moved"))),
+    entry(Topic::Recurrence, ("/template save имя @день ЧЧ:ММ текст, /templates", "/template save name @day HH:MM text, /templates"),
+        ("шаблоны частых напоминаний", "templates for frequent reminders"),
+        Some(("/template save спорт @вт 19:00 Тренировка", "/template save gym @tue 19:00 Workout"))),
+    entry(Topic::Lists, ("/events [sort:time|created|priority|💼]", "/events [sort:time|created|priority|💼]"), ("список событий", "list of events"),
+        Some(("/events sort:priority", "/events sort:priority"))),
+    entry(Topic::Lists, ("/stats", "/stats"), ("статистика: события по неделям, загруженные дни, серии привычек", "stats: events per week, busiest weekdays, habit streaks"), None),
+    entry(Topic::Lists, ("/next", "/next"), ("ближайшее событие и сколько до него осталось", "the next event and how long until it starts"), None),
+    entry(Topic::Lists, ("вопрос словами", "a question in words"), ("события на день или неделю", "events for a day or a week"),
+        Some(("что у меня в пятницу?", "what's on friday?"))),
+    entry(Topic::Lists, ("/calendar", "/calendar"), ("календарь на месяц", "month calendar"), None),
+    entry(Topic::Lists, ("/agenda [image] [ДД.ММ]", "/agenda [image] [DD.MM]"), ("события дня списком или картинкой", "a day's events as a list or an image"),
+        Some(("/agenda image 15.03", "/agenda image 03/15"))),
+    entry(Topic::Lists, ("/busy [ДД.ММ]", "/busy [DD.MM]"), ("занятые и свободные часы дня", "busy and free hours of a day"),
+        Some(("/busy 25.12", "/busy 25.12"))),
+    entry(Topic::Lists, ("/overdue", "/overdue"), ("пропущенные события с кнопками переноса", "overdue events with reschedule buttons"), None),
+    entry(Topic::Lists, ("/audit #id", "/audit #id"), ("история изменений события", "change history of an event"), Some(("/audit #12", "/audit #12"))),
+    entry(Topic::Lists, ("/todo текст, /todos", "/todo text, /todos"), ("задачи без времени", "tasks without a time"),
+        Some(("/todo Разобрать почту", "/todo Clean up the inbox"))),
+    entry(Topic::Groups, ("/groupsettings", "/groupsettings"), ("кто может создавать события, уборка сообщений и другие настройки группы", "who can create events, message cleanup and other group settings"),
+        Some(("/groupsettings cleanup 10", "/groupsettings cleanup 10"))),
+    entry(Topic::Groups, ("/poll 18:00|19:00|20:00 текст", "/poll 18:00|19:00|20:00 text"), ("голосование за время события", "vote for an event time"),
+        Some(("/poll 18:00|19:00|20:00 Созвон", "/poll 18:00|19:00|20:00 Team call"))),
+    entry(Topic::Groups, ("/closepoll", "/closepoll"), ("завершить голосование досрочно", "close the vote early"), None),
+    entry(Topic::Groups, ("/contact add имя @username", "/contact add name @username"),
+        ("с согласия человека ставить напоминания в его чат", "set reminders in someone's chat with their consent"),
+        Some(("/contact add мама @username", "/contact add mom @username"))),
+    entry(Topic::Groups, ("/for имя @ЧЧ:ММ текст", "/for name @HH:MM text"), ("напоминание в чат контакта", "a reminder in a contact's chat"),
+        Some(("/for мама @18:00 Выпить лекарство", "/for mom @18:00 Take medicine"))),
+    entry(Topic::Settings, ("/settings", "/settings"), ("язык, формат времени, тихие часы и сводка", "language, time format, quiet hours and digest"),
+        Some(("/settings quiet 23:00-08:00", "/settings quiet 23:00-08:00"))),
+    entry(Topic::Settings, ("/settings plain on|off", "/settings plain on|off"),
+        ("простой вывод без эмодзи для экранного диктора", "plain output without emoji for screen readers"), None),
+    entry(Topic::Settings, ("/timezone [зона]", "/timezone [zone]"), ("часовой пояс по названию или геопозиции", "timezone by name or by location"),
+        Some(("/timezone Europe/Moscow", "/timezone Europe/London"))),
+    entry(Topic::Settings, ("/weather [off]", "/weather [off]"), ("прогноз погоды в сводке и напоминаниях", "weather forecast in the digest and reminders"), None),
+    entry(Topic::Settings, ("/voice on|off, /voice #id on|off|default", "/voice on|off, /voice #id on|off|default"),
+        ("присылать напоминания ещё и голосовым сообщением", "also send reminders as voice messages"), Some(("/voice #12 on", "/voice #12 on"))),
+    entry(Topic::Settings, ("/privacy [on|off]", "/privacy [on|off]"), ("не учитывать меня в статистике и журнале изменений", "leave me out of usage statistics and the change log"), None),
+    entry(Topic::Export, ("/share #id", "/share #id"), ("QR-код, чтобы другой человек добавил событие себе", "QR code for someone else to add the event"),
+        Some(("/share #12", "/share #12"))),
+    entry(Topic::Export, ("/mirror slack|discord|mqtt <url>", "/mirror slack|discord|mqtt <url>"), ("дублировать напоминания в Slack, Discord или MQTT умного дома", "mirror reminders to Slack, Discord or home automation over MQTT"),
+        Some(("/mirror mqtt lights", "/mirror mqtt lights"))),
+    entry(Topic::Export, ("/webhook <url>|test|off", "/webhook <url>|test|off"), ("напоминания подписанным запросом на свой адрес", "reminders as signed requests to your own endpoint"),
+        Some(("/webhook test", "/webhook test"))),
+    entry(Topic::Export, ("/subscribe <ссылка .ics> [название]|list|remove|conflicts", "/subscribe <.ics link> [name]|list|remove|conflicts"),
+        ("события из чужого календаря с обновлением", "events from someone else's calendar, kept up to date"),
+        Some(("/subscribe https://example.com/timetable.ics Пары", "/subscribe https://example.com/timetable.ics Classes"))),
+    entry(Topic::Export, ("/guest [дни]|list|revoke", "/guest [days]|list|revoke"),
+        ("ссылка на страницу с событиями чата для тех, у кого нет Telegram", "a link to a web page with the chat's events for people without Telegram"),
+        Some(("/guest 14", "/guest 14"))),
+    entry(Topic::Export, ("/token create|list|revoke", "/token create|list|revoke"), ("токены для HTTP API и ленты событий", "tokens for the HTTP API and the event feed"),
+        Some(("/token create read", "/token create read"))),
+    entry(Topic::Export, ("файл .csv или .json", ".csv or .json file"), ("импорт выгрузки Todoist или Google Tasks", "import a Todoist or Google Tasks export"), None),
+    entry(Topic::Export, ("пересланное напоминание", "forwarded reminder"),
+        ("сообщение другого бота или уведомление календаря становится событием", "a message from another bot or a calendar notification becomes an event"), None),
+    entry(Topic::Tools, ("/pomodoro 25 5 4", "/pomodoro 25 5 4"), ("помодоро: работа, перерыв и число циклов", "pomodoro: work, break and number of cycles"),
+        Some(("/pomodoro 25 5 4", "/pomodoro 25 5 4"))),
+    entry(Topic::Tools, ("/timer 15m [подпись]", "/timer 15m [label]"), ("короткий таймер без события в списке", "a short timer that stays out of your event list"),
+        Some(("/timer 10m чай", "/timer 10m tea"))),
+    entry(Topic::Tools, ("/place [метры] текст, /places", "/place [meters] text, /places"), ("напомнить рядом с местом", "remind you near a place"),
+        Some(("/place 300 Купить лекарства", "/place 300 Buy medicine"))),
+    entry(Topic::Tools, ("/leave #id [минуты]", "/leave #id [minutes]"), ("напомнить, когда пора выходить", "remind when it's time to leave"),
+        Some(("/leave #12 40", "/leave #12 40"))),
+    entry(Topic::Tools, ("/feedback текст", "/feedback text"), ("сообщить об ошибке или предложить идею", "report a bug or suggest an idea"),
+        Some(("/feedback Хочу напоминания по выходным", "/feedback I'd like weekend-only reminders"))),
+];
+
+fn pick(lang: Lang, (ru, en): (&'static str, &'static str)) -> &'static str {
+    match lang {
+        Lang::Ru => ru,
+        Lang::En => en,
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
+
+fn parse_topic(value: &str) -> Option<Topic> {
+    let value = value.to_lowercase();
+    TOPICS
+        .iter()
+        .find(|(_, code, ru, en)| value == *code || value == *ru || value == *en)
+        .map(|(topic, ..)| *topic)
+}
+
+/// Общая справка: как создать событие и список разделов.
+fn overview(lang: Lang) -> String {
+    let topics = TOPICS
+        .iter()
+        .map(|(_, code, ru, en)| format!("/help {} — {}", code, pick(lang, (ru, en))))
+        .collect::<Vec<_>>()
+        .join("\n");
+    tf(lang, "help_overview", &[("topics", &topics)])
+}
+
+/// Справка по разделу: строки реестра с примерами в `<code>`, чтобы их копировали нажатием.
+fn topic_help(lang: Lang, topic: Topic) -> String {
+    REGISTRY
+        .iter()
+        .filter(|entry| entry.topic == topic)
+        .map(|entry| {
+            let line = format!("<b>{}</b> — {}", escape(pick(lang, entry.usage)), escape(pick(lang, entry.about)));
+            match entry.example {
+                Some(example) => format!("{}\n<code>{}</code>", line, escape(pick(lang, example))),
+                None => line,
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
+
+/// `/help [раздел]`; без раздела или с неизвестным разделом — общая справка.
+pub async fn handle_help_command(bot: &Bot, msg: &Message, args: &str, lang: Lang) -> ResponseResult<()> {
+    let text = match parse_topic(args) {
+        Some(topic) => topic_help(lang, topic),
+        None if args.is_empty() => overview(lang),
+        None => format!("{}\n\n{}", escape(&t(lang, "help_unknown_topic")), overview(lang)),
+    };
+    bot.send_message(msg.chat.id, text).parse_mode(ParseMode::Html).await?;
+    Ok(())
+}
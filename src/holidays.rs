@@ -0,0 +1,200 @@
+use std::env;
+use std::fs;
+use std::sync::OnceLock;
+
+use chrono::{Datelike, Duration, NaiveDate, Weekday};
+
+use crate::i18n::{t, tf, Lang};
+
+/// Страна для праздников в сводке: двухбуквенный код ISO 3166, например `RU`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Country([u8; 2]);
+
+impl Country {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.to_ascii_uppercase().as_bytes() {
+            &[a, b] if a.is_ascii_uppercase() && b.is_ascii_uppercase() => Some(Country([a, b])),
+            _ => None,
+        }
+    }
+
+    pub fn code(self) -> String {
+        String::from_utf8_lossy(&self.0).into_owned()
+    }
+}
+
+/// Выходной день или памятная дата, когда обычно работают.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Kind {
+    Public,
+    Observance,
+}
+
+impl Kind {
+    fn parse(value: &str) -> Option<Self> {
+        match value.trim() {
+            "public" => Some(Kind::Public),
+            "observance" => Some(Kind::Observance),
+            _ => None,
+        }
+    }
+}
+
+/// Когда наступает праздник.
+#[derive(Debug, Clone, Copy)]
+enum Rule {
+    /// Каждый год в этот день.
+    Fixed(u32, u32),
+    /// N-й день недели месяца; `-1` — последний.
+    NthWeekday(u32, Weekday, i8),
+    /// Только в эту дату (из загруженного файла).
+    Once(NaiveDate),
+}
+
+impl Rule {
+    fn matches(self, date: NaiveDate) -> bool {
+        match self {
+            Rule::Fixed(month, day) => date.month() == month && date.day() == day,
+            Rule::NthWeekday(month, weekday, n) => {
+                if date.month() != month || date.weekday() != weekday {
+                    return false;
+                }
+                if n > 0 {
+                    (date.day() - 1) / 7 + 1 == n as u32
+                } else {
+                    (date + Duration::days(7)).month() != month
+                }
+            }
+            Rule::Once(once) => date == once,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Holiday {
+    country: String,
+    rule: Rule,
+    kind: Kind,
+    name: String,
+}
+
+/// Встроенный набор: праздники с постоянной датой или правилом «N-й день недели».
+/// Переходящие по лунному календарю даты (Пасха и т.п.) можно добавить файлом из `HOLIDAYS_FILE`.
+const EMBEDDED: &[(&str, Rule, Kind, &str)] = &[
+    ("RU", Rule::Fixed(1, 1), Kind::Public, "Новый год"),
+    ("RU", Rule::Fixed(1, 7), Kind::Public, "Рождество Христово"),
+    ("RU", Rule::Fixed(2, 23), Kind::Public, "День защитника Отечества"),
+    ("RU", Rule::Fixed(3, 8), Kind::Public, "Международный женский день"),
+    ("RU", Rule::Fixed(5, 1), Kind::Public, "Праздник Весны и Труда"),
+    ("RU", Rule::Fixed(5, 9), Kind::Public, "День Победы"),
+    ("RU", Rule::Fixed(6, 12), Kind::Public, "День России"),
+    ("RU", Rule::Fixed(11, 4), Kind::Public, "День народного единства"),
+    ("RU", Rule::Fixed(4, 12), Kind::Observance, "День космонавтики"),
+    ("RU", Rule::Fixed(9, 1), Kind::Observance, "День знаний"),
+    ("RU", Rule::Fixed(12, 31), Kind::Observance, "Канун Нового года"),
+    ("US", Rule::Fixed(1, 1), Kind::Public, "New Year's Day"),
+    ("US", Rule::NthWeekday(1, Weekday::Mon, 3), Kind::Public, "Martin Luther King Jr. Day"),
+    ("US", Rule::NthWeekday(2, Weekday::Mon, 3), Kind::Public, "Presidents' Day"),
+    ("US", Rule::NthWeekday(5, Weekday::Mon, -1), Kind::Public, "Memorial Day"),
+    ("US", Rule::Fixed(6, 19), Kind::Public, "Juneteenth"),
+    ("US", Rule::Fixed(7, 4), Kind::Public, "Independence Day"),
+    ("US", Rule::NthWeekday(9, Weekday::Mon, 1), Kind::Public, "Labor Day"),
+    ("US", Rule::NthWeekday(10, Weekday::Mon, 2), Kind::Public, "Columbus Day"),
+    ("US", Rule::Fixed(11, 11), Kind::Public, "Veterans Day"),
+    ("US", Rule::NthWeekday(11, Weekday::Thu, 4), Kind::Public, "Thanksgiving Day"),
+    ("US", Rule::Fixed(12, 25), Kind::Public, "Christmas Day"),
+    ("US", Rule::Fixed(2, 14), Kind::Observance, "Valentine's Day"),
+    ("US", Rule::NthWeekday(5, Weekday::Sun, 2), Kind::Observance, "Mother's Day"),
+    ("US", Rule::NthWeekday(6, Weekday::Sun, 3), Kind::Observance, "Father's Day"),
+    ("US", Rule::Fixed(10, 31), Kind::Observance, "Halloween"),
+    ("GB", Rule::Fixed(1, 1), Kind::Public, "New Year's Day"),
+    ("GB", Rule::NthWeekday(5, Weekday::Mon, 1), Kind::Public, "Early May Bank Holiday"),
+    ("GB", Rule::NthWeekday(5, Weekday::Mon, -1), Kind::Public, "Spring Bank Holiday"),
+    ("GB", Rule::NthWeekday(8, Weekday::Mon, -1), Kind::Public, "Summer Bank Holiday"),
+    ("GB", Rule::Fixed(12, 25), Kind::Public, "Christmas Day"),
+    ("GB", Rule::Fixed(12, 26), Kind::Public, "Boxing Day"),
+    ("GB", Rule::Fixed(11, 5), Kind::Observance, "Guy Fawkes Night"),
+    ("DE", Rule::Fixed(1, 1), Kind::Public, "Neujahr"),
+    ("DE", Rule::Fixed(5, 1), Kind::Public, "Tag der Arbeit"),
+    ("DE", Rule::Fixed(10, 3), Kind::Public, "Tag der Deutschen Einheit"),
+    ("DE", Rule::Fixed(12, 25), Kind::Public, "1. Weihnachtstag"),
+    ("DE", Rule::Fixed(12, 26), Kind::Public, "2. Weihnachtstag"),
+];
+
+/// Строка файла `страна,дата,вид,название`: дата `ГГГГ-ММ-ДД` — один раз, `ММ-ДД` — каждый год.
+fn parse_line(line: &str) -> Option<Holiday> {
+    let mut fields = line.splitn(4, ',');
+    let country = Country::parse(fields.next()?.trim())?.code();
+    let date = fields.next()?.trim();
+    let kind = Kind::parse(fields.next()?)?;
+    let name = fields.next()?.trim().to_string();
+
+    let rule = match NaiveDate::parse_from_str(date, "%Y-%m-%d") {
+        Ok(date) => Rule::Once(date),
+        Err(_) => {
+            let (month, day) = date.split_once('-')?;
+            let (month, day) = (month.parse().ok()?, day.parse().ok()?);
+            // Проверка, что такой день вообще бывает (29.02 — в високосный год)
+            NaiveDate::from_ymd_opt(2024, month, day)?;
+            Rule::Fixed(month, day)
+        }
+    };
+    Some(Holiday { country, rule, kind, name })
+}
+
+/// Встроенные праздники и праздники из `HOLIDAYS_FILE`, загружаются один раз.
+fn all() -> &'static [Holiday] {
+    static HOLIDAYS: OnceLock<Vec<Holiday>> = OnceLock::new();
+    HOLIDAYS.get_or_init(|| {
+        let mut holidays = EMBEDDED
+            .iter()
+            .map(|(country, rule, kind, name)| Holiday { country: country.to_string(), rule: *rule, kind: *kind, name: name.to_string() })
+            .collect::<Vec<_>>();
+
+        if let Ok(path) = env::var("HOLIDAYS_FILE") {
+            match fs::read_to_string(&path) {
+                Ok(data) => {
+                    let lines = data.lines().map(str::trim).filter(|line| !line.is_empty() && !line.starts_with('#'));
+                    let (mut loaded, mut skipped) = (0, 0);
+                    for line in lines {
+                        match parse_line(line) {
+                            Some(holiday) => {
+                                holidays.push(holiday);
+                                loaded += 1;
+                            }
+                            None => skipped += 1,
+                        }
+                    }
+                    log::info!("Loaded {} holidays from {}, {} lines skipped", loaded, path, skipped);
+                }
+                Err(e) => log::error!("Failed to read holidays from {}: {}", path, e),
+            }
+        }
+        holidays
+    })
+}
+
+/// Строка сводки о праздниках страны в этот день; `None`, если день обычный.
+pub fn describe_day(country: Country, date: NaiveDate, lang: Lang) -> Option<String> {
+    let code = country.code();
+    let names = |kind: Kind| {
+        all()
+            .iter()
+            .filter(|holiday| holiday.country == code && holiday.kind == kind && holiday.rule.matches(date))
+            .map(|holiday| holiday.name.as_str())
+            .collect::<Vec<_>>()
+    };
+
+    let lines = [(Kind::Public, "holiday_public"), (Kind::Observance, "holiday_observance")]
+        .into_iter()
+        .filter_map(|(kind, key)| {
+            let names = names(kind);
+            (!names.is_empty()).then(|| tf(lang, key, &[("names", &names.join(", "))]))
+        })
+        .collect::<Vec<_>>();
+    (!lines.is_empty()).then(|| lines.join("\n"))
+}
+
+pub fn describe_country(country: Option<Country>, lang: Lang) -> String {
+    country.map_or_else(|| t(lang, "quiet_off"), Country::code)
+}
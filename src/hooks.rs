@@ -0,0 +1,78 @@
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::Json;
+use chrono::{DateTime, NaiveDateTime};
+use serde::Deserialize;
+use serde_json::{Value, json};
+
+use crate::settings::{self, Settings};
+use crate::tokens::{self, AuthError, Scope};
+use crate::clock::{self, Clock};
+use crate::config;
+use crate::{Db, EVENT_TIME_FORMAT, in_transaction, insert_event, timezone};
+
+/// Предел длины текста: столько же принимает Telegram в одном сообщении.
+const MAX_TEXT_LEN: usize = 4096;
+
+#[derive(Debug, Deserialize)]
+pub struct HookRequest {
+    text: String,
+    at: String,
+}
+
+/// Время из запроса: момент в RFC 3339 (`2025-04-03T18:30:00+03:00`) переводится на часы владельца,
+/// остальное разбирается как время в сообщении боту: `18:30`, `03.04 18:30`, `2025-04-03 6pm`.
+fn resolve_at(at: &str, settings: &Settings) -> Option<NaiveDateTime> {
+    if let Ok(moment) = DateTime::parse_from_rfc3339(at.trim()) {
+        return Some(timezone::from_utc(moment.naive_utc(), settings.timezone));
+    }
+    let parsed = config::get().parsers.parse(&format!("@{}", at.trim()), settings.locale(), clock::SYSTEM.now(), settings.timezone);
+    parsed.ok().map(|parsed| parsed.datetime)
+}
+
+fn error(status: StatusCode, message: &str) -> (StatusCode, Json<Value>) {
+    (status, Json(json!({ "error": message })))
+}
+
+/// `POST /hooks/<token>` с `{"text": "...", "at": "..."}` — напоминание владельцу токена в личный чат.
+/// Нужен токен с правом `write`.
+pub async fn handle_hook(
+    State(db): State<Db>,
+    Path(token): Path<String>,
+    Json(request): Json<HookRequest>,
+) -> (StatusCode, Json<Value>) {
+    let text = request.text.trim().to_string();
+    if text.is_empty() || text.len() > MAX_TEXT_LEN {
+        return error(StatusCode::BAD_REQUEST, "text must be between 1 and 4096 bytes");
+    }
+
+    let result = db.call(move |conn| {
+        let owner = match tokens::authenticate(conn, &token, Scope::Write)? {
+            Ok(owner) => owner,
+            Err(e) => return Ok(Err(e)),
+        };
+        let settings = settings::resolve(conn, owner.telegram_id, owner.telegram_id)?;
+        let Some(event_time) = resolve_at(&request.at, &settings) else {
+            return Ok(Ok((owner.telegram_id, None)));
+        };
+        let event_time = event_time.format(EVENT_TIME_FORMAT).to_string();
+        let event_id = in_transaction(conn, |tx| {
+            insert_event(tx, &owner.tenant, owner.user_id, owner.telegram_id, &text, &event_time)
+        })?;
+        Ok(Ok((owner.telegram_id, Some((event_id, event_time)))))
+    }).await;
+
+    match result {
+        Ok(Ok((telegram_id, Some((event_id, event_time))))) => {
+            log::info!("Webhook created event {} for user {}", event_id, telegram_id);
+            (StatusCode::CREATED, Json(json!({ "id": event_id, "at": event_time })))
+        }
+        Ok(Ok((_, None))) => error(StatusCode::BAD_REQUEST, "unrecognized time in \"at\""),
+        Ok(Err(AuthError::Unknown)) => error(StatusCode::UNAUTHORIZED, "unknown token"),
+        Ok(Err(AuthError::MissingScope)) => error(StatusCode::FORBIDDEN, "token lacks the write scope"),
+        Err(e) => {
+            log::error!("Webhook failed: {}", e);
+            error(StatusCode::INTERNAL_SERVER_ERROR, "internal error")
+        }
+    }
+}
@@ -0,0 +1,42 @@
+use std::env;
+use std::net::SocketAddr;
+
+use axum::routing::{get, post};
+use axum::Router;
+
+use crate::{Db, feed, guests, health, hooks, metrics};
+
+/// Адрес HTTP-сервера из `HTTP_ADDR`, например `0.0.0.0:8080`. Без него сервер не запускается.
+pub fn address() -> Option<SocketAddr> {
+    let addr = env::var("HTTP_ADDR").ok()?;
+    match addr.parse() {
+        Ok(addr) => Some(addr),
+        Err(e) => {
+            log::error!("Invalid HTTP_ADDR {}: {}", addr, e);
+            None
+        }
+    }
+}
+
+/// Запускает HTTP-сервер для интеграций рядом с ботом.
+pub fn spawn(db: Db) {
+    let Some(addr) = address() else {
+        log::info!("HTTP server is disabled");
+        return;
+    };
+
+    let app = Router::new()
+        .route("/hooks/:token", post(hooks::handle_hook))
+        .route("/feed/:token", get(feed::handle_feed))
+        .route("/guest/:code", get(guests::handle_page))
+        .route("/metrics", get(metrics::handle_metrics))
+        .route("/healthz", get(health::handle_healthz))
+        .with_state(db);
+
+    tokio::spawn(async move {
+        log::info!("HTTP server listening on {}", addr);
+        if let Err(e) = axum::Server::bind(&addr).serve(app.into_make_service()).await {
+            log::error!("HTTP server stopped: {}", e);
+        }
+    });
+}
@@ -0,0 +1,68 @@
+use chrono::Duration;
+
+use crate::i18n::{t, tf, Lang};
+
+/// Число со словом в нужной форме: «1 день», «3 дня», «5 дней». Формы в каталоге записываются
+/// через `|` (для русского — «день|дня|дней», для английского — «day|days»).
+pub fn count(lang: Lang, key: &str, n: i64) -> String {
+    let forms = t(lang, key);
+    let forms = forms.split('|').collect::<Vec<_>>();
+    let index = match lang {
+        Lang::Ru => {
+            let (n10, n100) = (n % 10, n % 100);
+            if n10 == 1 && n100 != 11 {
+                0
+            } else if (2..=4).contains(&n10) && !(12..=14).contains(&n100) {
+                1
+            } else {
+                2
+            }
+        }
+        Lang::En => usize::from(n != 1),
+    };
+    format!("{} {}", n, forms.get(index).or(forms.last()).unwrap_or(&""))
+}
+
+/// Длительность двумя старшими ненулевыми единицами: «2 дня 3 часа», «5 минут».
+pub fn duration(lang: Lang, value: Duration) -> String {
+    let days = value.num_days();
+    let hours = value.num_hours() % 24;
+    let minutes = (value.num_minutes() % 60).max(if value.num_hours() == 0 { 1 } else { 0 });
+
+    let parts = [(days, "unit_days"), (hours, "unit_hours"), (minutes, "unit_minutes")]
+        .into_iter()
+        .skip_while(|(n, _)| *n == 0)
+        .take(2)
+        .filter(|(n, _)| *n > 0)
+        .map(|(n, key)| count(lang, key, n))
+        .collect::<Vec<_>>();
+    parts.join(" ")
+}
+
+/// Длительность в минутах: «25 минут», «1 час 30 минут».
+pub fn minutes(lang: Lang, minutes: i64) -> String {
+    duration(lang, Duration::minutes(minutes))
+}
+
+/// Дни: «1 день», «30 дней».
+pub fn days(lang: Lang, days: i64) -> String {
+    count(lang, "unit_days", days)
+}
+
+/// Годы: «1 год», «5 лет».
+pub fn years(lang: Lang, years: i64) -> String {
+    count(lang, "unit_years", years)
+}
+
+/// «через 2 дня 3 часа» или пометка, что время уже прошло.
+pub fn until(lang: Lang, value: Duration) -> String {
+    if value <= Duration::zero() {
+        return t(lang, "humanize_past");
+    }
+    tf(lang, "humanize_until", &[("duration", &duration(lang, value))])
+}
+
+/// «2 дня назад» для прошедшего времени.
+pub fn ago(lang: Lang, value: Duration) -> String {
+    tf(lang, "humanize_ago", &[("duration", &duration(lang, value.max(Duration::zero())))])
+}
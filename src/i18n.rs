@@ -0,0 +1,905 @@
+use std::collections::{BTreeSet, HashMap};
+use std::env;
+use std::fmt::Display;
+use std::path::Path;
+use std::sync::{Arc, OnceLock, RwLock};
+
+use regex::Regex;
+
+use crate::accessibility;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Lang {
+    Ru,
+    En,
+}
+
+impl Lang {
+    pub const ALL: [Lang; 2] = [Lang::Ru, Lang::En];
+
+    pub fn code(self) -> &'static str {
+        match self {
+            Lang::Ru => "ru",
+            Lang::En => "en",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.to_lowercase().as_str() {
+            "ru" | "русский" => Some(Lang::Ru),
+            "en" | "english" => Some(Lang::En),
+            _ => None,
+        }
+    }
+}
+
+/// Ключ, русский текст, английский текст. Подстановки пишутся как `{name}`.
+const MESSAGES: &[(&str, &str, &str)] = &[
+    ("help_overview",
+        "Привет! Чтобы создать событие, напишите время после @ и текст:\n\
+        <code>@18:30 Позвонить маме</code>\n\
+        <code>@25.12 10:00 Купить подарки</code>\n\n\
+        Подробнее по разделам:\n{topics}",
+        "Hi! To create an event, send a time after @ and a text:\n\
+        <code>@18:30 Call mom</code>\n\
+        <code>@25.12 10:00 Buy presents</code>\n\n\
+        More by topic:\n{topics}"),
+    ("help_unknown_topic", "Такого раздела справки нет", "There is no such help topic"),
+    ("no_events", "У вас пока нет запланированных событий", "You have no scheduled events yet"),
+    ("events_header", "Ваши события:\n{events}", "Your events:\n{events}"),
+    ("stats_report",
+        "📊 Статистика\n\nПо неделям (создано · выполнено):\n{weeks}\n\nСамые загруженные дни:\n{weekdays}\n\nВ среднем откладываний на событие: {snoozes}\n\nСерии привычек:\n{habits}",
+        "📊 Stats\n\nPer week (created · done):\n{weeks}\n\nBusiest weekdays:\n{weekdays}\n\nAverage snoozes per event: {snoozes}\n\nHabit streaks:\n{habits}"),
+    ("stats_week", "{week} {created_bar} {created} · ✅ {done_bar} {done}", "{week} {created_bar} {created} · ✅ {done_bar} {done}"),
+    ("stats_habit", "• {name} — {streak}", "• {name} — {streak}"),
+    ("stats_no_habits", "привычек пока нет, см. /habit", "no habits yet, see /habit"),
+    ("stats_chart_weeks", "События по неделям", "Events per week"),
+    ("stats_chart_weekdays", "События по дням недели", "Events per weekday"),
+    ("stats_created", "создано", "created"),
+    ("stats_done", "выполнено", "done"),
+    ("stats_events", "события", "events"),
+    ("stats_empty", "Статистики пока нет: создайте первое событие", "No stats yet: create your first event"),
+    ("next_event", "⏭ {time} — {text}\n{until}", "⏭ {time} — {text}\n{until}"),
+    ("event_saved_date",
+        "Сохранено событие на {date} в {time} ({until})\nТекст события: {text}",
+        "Saved event for {date} at {time} ({until})\nEvent text: {text}"),
+    ("event_saved_today",
+        "Сохранено событие на сегодня в {time} ({until})\nТекст события: {text}",
+        "Saved event for today at {time} ({until})\nEvent text: {text}"),
+    ("reminder", "🔔 Напоминание!\n{text}\nВремя: {time}", "🔔 Reminder!\n{text}\nTime: {time}"),
+    ("event_updated",
+        "✏️ Событие обновлено: {time}\nТекст события: {text}",
+        "✏️ Event updated: {time}\nEvent text: {text}"),
+    ("event_not_found", "Событие не найдено", "Event not found"),
+    ("event_not_yours", "Изменить событие может только его автор", "Only the event author can change it"),
+    ("event_ack", "✅ Готово", "✅ Done"),
+    ("event_acknowledged", "Событие завершено", "Event completed"),
+    ("events_usage", "Формат: /events [sort:time|created|priority] или /events 💼", "Format: /events [sort:time|created|priority] or /events 💼"),
+    ("agenda_today", "Сегодня", "Today"),
+    ("agenda_tomorrow", "Завтра", "Tomorrow"),
+    ("humanize_until", "через {duration}", "in {duration}"),
+    ("humanize_ago", "{duration} назад", "{duration} ago"),
+    ("humanize_past", "время уже прошло", "already passed"),
+    ("unit_events", "событие|события|событий", "event|events"),
+    ("unit_days", "день|дня|дней", "day|days"),
+    ("unit_years", "год|года|лет", "year|years"),
+    ("unit_hours", "час|часа|часов", "hour|hours"),
+    ("unit_minutes", "минуту|минуты|минут", "minute|minutes"),
+    ("calendar_months",
+        "Январь,Февраль,Март,Апрель,Май,Июнь,Июль,Август,Сентябрь,Октябрь,Ноябрь,Декабрь",
+        "January,February,March,April,May,June,July,August,September,October,November,December"),
+    ("calendar_weekdays", "Пн Вт Ср Чт Пт Сб Вс", "Mo Tu We Th Fr Sa Su"),
+    ("calendar_day", "События на {date}:\n{events}", "Events on {date}:\n{events}"),
+    ("calendar_day_empty", "На {date} событий нет", "No events on {date}"),
+    ("checklist_done", "✅ Все пункты выполнены", "✅ All items are done"),
+    ("duplicate_warning",
+        "⚠️ Такое событие уже запланировано: {time}\n{text}\nСоздать ещё одно?",
+        "⚠️ This event is already scheduled: {time}\n{text}\nCreate another one?"),
+    ("duplicate_create", "Создать всё равно", "Create anyway"),
+    ("duplicate_cancel", "Не создавать", "Don't create"),
+    ("duplicate_discarded", "Повторное событие не создано", "The duplicate event was not created"),
+    ("template_usage",
+        "Шаблоны:\n\
+        /template save имя @вт 19:00 текст - сохранить шаблон (день: дата ДД.ММ, сегодня, завтра или день недели пн-вс)\n\
+        /template use имя - создать событие по шаблону\n\
+        /template delete имя - удалить шаблон\n\
+        /templates - список шаблонов",
+        "Templates:\n\
+        /template save name @tue 19:00 text - save a template (day: date DD.MM, today, tomorrow or weekday mon-sun)\n\
+        /template use name - create an event from a template\n\
+        /template delete name - delete a template\n\
+        /templates - list templates"),
+    ("template_saved", "Шаблон «{name}» сохранён", "Template \"{name}\" saved"),
+    ("template_deleted", "Шаблон «{name}» удалён", "Template \"{name}\" deleted"),
+    ("template_not_found", "Шаблон «{name}» не найден", "Template \"{name}\" not found"),
+    ("templates_empty",
+        "У вас пока нет шаблонов. Сохраните первый: /template save имя @вт 19:00 текст",
+        "You have no templates yet. Save one: /template save name @tue 19:00 text"),
+    ("templates_list", "Ваши шаблоны:\n{templates}", "Your templates:\n{templates}"),
+    ("duplicate_usage",
+        "Формат: /duplicate #id @ДД.ММ ЧЧ:ММ\nНомер события можно узнать в /events",
+        "Format: /duplicate #id @DD.MM HH:MM\nYou can find the event number in /events"),
+    ("duplicate_expired", "Это предложение уже неактуально", "This offer has expired"),
+    ("event_invalid_time", "Не удалось разобрать дату и время: {value}", "Could not parse date and time: {value}"),
+
+    ("poll_private_only", "Голосование можно создать только в группе", "Polls can only be created in groups"),
+    ("poll_usage",
+        "Формат: /poll 18:00|19:00|20:00 текст события\n\
+        Варианты можно указывать с датой: /poll 20.03 18:00|21.03 18:00 текст",
+        "Format: /poll 18:00|19:00|20:00 event text\n\
+        Options may include a date: /poll 20.03 18:00|21.03 18:00 text"),
+    ("poll_too_many", "В голосовании может быть не больше 10 вариантов", "A poll can have at most 10 options"),
+    ("poll_past_options", "Все варианты времени должны быть в будущем", "All time options must be in the future"),
+    ("poll_question", "Когда: {text}?", "When: {text}?"),
+    ("poll_opened",
+        "Голосование открыто до {deadline}. Автор может закрыть его раньше, ответив на опрос командой /closepoll",
+        "The poll is open until {deadline}. The author can close it earlier by replying to it with /closepoll"),
+    ("poll_close_usage", "Ответьте командой /closepoll на сообщение с голосованием", "Reply to the poll message with /closepoll"),
+    ("poll_not_found", "Открытое голосование не найдено", "No open poll found"),
+    ("poll_close_not_author", "Закрыть голосование может только его автор", "Only the poll author can close it"),
+    ("poll_no_votes",
+        "Голосование «{text}» завершено без голосов, событие не создано",
+        "The poll \"{text}\" ended without votes, no event was created"),
+    ("poll_finished",
+        "🗳 Голосование завершено: {text}\nВыбрано время: {time} (голосов: {votes})\nНапоминание придёт в этот чат",
+        "🗳 Poll finished: {text}\nChosen time: {time} (votes: {votes})\nThe reminder will be sent to this chat"),
+
+    ("policy_everyone", "все участники", "all members"),
+    ("policy_admins", "только администраторы", "admins only"),
+    ("policy_whitelist", "администраторы и участники из белого списка", "admins and whitelisted members"),
+    ("group_not_allowed", "В этой группе управлять событиями могут {policy}", "In this group events can be managed by {policy}"),
+    ("group_settings_private_only", "Настройки группы доступны только в группах", "Group settings are only available in groups"),
+    ("group_settings_current",
+        "Создавать и удалять события могут: {policy}\n\
+        Язык: {lang}\n\
+        Формат времени: {clock}\n\
+        Тихие часы: {quiet}\n\
+        Уборка сообщений бота: {cleanup}",
+        "Events can be created and deleted by: {policy}\n\
+        Language: {lang}\n\
+        Time format: {clock}\n\
+        Quiet hours: {quiet}\n\
+        Bot message cleanup: {cleanup}"),
+    ("group_whitelist", "Белый список: {names}", "Whitelist: {names}"),
+    ("group_settings_help",
+        "Команды для администраторов:\n\
+        /groupsettings everyone|admins|whitelist - кто может управлять событиями\n\
+        /groupsettings allow - ответом на сообщение участника, добавить в белый список\n\
+        /groupsettings deny - ответом на сообщение участника, убрать из белого списка\n\
+        /groupsettings lang ru|en|default - язык сообщений в группе\n\
+        /groupsettings time 24h|12h|default - формат времени\n\
+        /groupsettings quiet 23:00-08:00|off|default - тихие часы для напоминаний\n\
+        /groupsettings cleanup 10|off - удалять подтверждения, ошибки и команды через N минут",
+        "Admin commands:\n\
+        /groupsettings everyone|admins|whitelist - who can manage events\n\
+        /groupsettings allow - as a reply to a member's message, add them to the whitelist\n\
+        /groupsettings deny - as a reply to a member's message, remove them from the whitelist\n\
+        /groupsettings lang ru|en|default - message language in the group\n\
+        /groupsettings time 24h|12h|default - time format\n\
+        /groupsettings quiet 23:00-08:00|off|default - quiet hours for reminders\n\
+        /groupsettings cleanup 10|off - delete confirmations, errors and commands after N minutes"),
+    ("group_admins_only", "Менять настройки группы могут только администраторы", "Only admins can change group settings"),
+    ("group_reply_to_member", "Ответьте этой командой на сообщение участника", "Reply with this command to a member's message"),
+    ("group_whitelist_added", "{name} добавлен в белый список", "{name} was added to the whitelist"),
+    ("group_whitelist_removed", "{name} убран из белого списка", "{name} was removed from the whitelist"),
+    ("group_whitelist_missing", "{name} не было в белом списке", "{name} was not in the whitelist"),
+    ("group_policy_set", "Теперь создавать и удалять события могут {policy}", "Events can now be created and deleted by {policy}"),
+    ("group_unknown_setting",
+        "Неизвестная настройка. Используйте /groupsettings для справки",
+        "Unknown setting. Use /groupsettings for help"),
+    ("group_default", "как у участника", "member's default"),
+
+    ("settings_current",
+        "Ваши настройки:\n\
+        Язык: {lang}\n\
+        Формат времени: {clock}\n\
+        Формат даты: {date}\n\
+        Тихие часы: {quiet}\n\
+        Ежедневная сводка: {digest}\n\
+        Сортировка событий: {sort}\n\
+        Часовой пояс: {zone}\n\
+        Хранение отправленных событий: {retention}\n\
+        Страна для праздников: {country}\n\
+        Простой вывод: {plain}\n\n\
+        /settings lang ru|en - язык\n\
+        /settings time 24h|12h - формат времени\n\
+        /settings date dmy|mdy|iso - формат даты: 03.04, 04/03 или 2025-04-03\n\
+        /settings quiet 23:00-08:00|off - тихие часы, напоминания придут после их окончания\n\
+        /settings digest 09:00|off - ежедневная сводка с событиями на день и задачами\n\
+        /settings sort time|created|priority - порядок событий в /events\n\
+        /settings retention 90|off - через сколько дней удалять отправленные и выполненные события\n\
+        /settings country RU|off - праздники страны в ежедневной сводке\n\
+        /settings plain on|off - простой вывод без эмодзи и оформления, удобнее для экранного диктора\n\
+        /timezone - часовой пояс по геопозиции или названию",
+        "Your settings:\n\
+        Language: {lang}\n\
+        Time format: {clock}\n\
+        Date format: {date}\n\
+        Quiet hours: {quiet}\n\
+        Daily digest: {digest}\n\
+        Event sorting: {sort}\n\
+        Time zone: {zone}\n\
+        Sent events kept for: {retention}\n\
+        Holiday country: {country}\n\
+        Plain output: {plain}\n\n\
+        /settings lang ru|en - language\n\
+        /settings time 24h|12h - time format\n\
+        /settings date dmy|mdy|iso - date format: 03.04, 04/03 or 2025-04-03\n\
+        /settings quiet 23:00-08:00|off - quiet hours, reminders are delivered when they end\n\
+        /settings digest 09:00|off - daily digest with the day's events and tasks\n\
+        /settings sort time|created|priority - event order in /events\n\
+        /settings retention 90|off - days after which sent and completed events are deleted\n\
+        /settings country US|off - the country's holidays in the daily digest\n\
+        /settings plain on|off - plain output without emoji or formatting, friendlier to screen readers\n\
+        /timezone - time zone from your location or by name"),
+    ("todo_usage", "Формат: /todo текст задачи", "Format: /todo task text"),
+    ("todo_saved", "Задача #{id} добавлена: {text}", "Task #{id} added: {text}"),
+    ("todos_empty", "Список задач пуст", "Your to-do list is empty"),
+    ("todos_list", "Ваши задачи:\n{tasks}", "Your tasks:\n{tasks}"),
+    ("todo_done", "Задача выполнена", "Task done"),
+    ("todo_not_yours", "Отметить задачу может только её автор", "Only the task author can complete it"),
+    ("habit_usage",
+        "Формат:\n/habit daily ЧЧ:ММ название - ежедневная привычка\n/habit stop #id - перестать отслеживать",
+        "Format:\n/habit daily HH:MM name - daily habit\n/habit stop #id - stop tracking"),
+    ("habit_saved",
+        "Привычка #{id} «{name}» добавлена, спрошу о ней каждый день в {time}",
+        "Habit #{id} \"{name}\" added, I'll ask about it every day at {time}"),
+    ("habit_stopped", "Привычка #{id} больше не отслеживается", "Habit #{id} is no longer tracked"),
+    ("habit_not_found", "Привычка #{id} не найдена", "Habit #{id} not found"),
+    ("habit_not_yours", "Ответить может только автор привычки", "Only the habit author can answer"),
+    ("habit_question", "🔁 {name}\nСделали сегодня?", "🔁 {name}\nDid you do it today?"),
+    ("habit_yes", "Да", "Yes"),
+    ("habit_no", "Нет", "No"),
+    ("habit_logged_yes", "✅ {name}\nСерия: {streak}", "✅ {name}\nStreak: {streak}"),
+    ("habit_logged_no", "❌ {name}\nСерия: {streak}", "❌ {name}\nStreak: {streak}"),
+    ("anniversary_usage",
+        "Формат:\n/anniversary ДД.ММ.ГГГГ [ЧЧ:ММ] фраза - напоминать каждый год, {years} во фразе заменится числом лет\n\
+         /anniversary remove #id - удалить\n/anniversary - список\n\nПример: /anniversary 15.06.2021 {years} с переезда",
+        "Format:\n/anniversary DD.MM.YYYY [HH:MM] phrase - remind every year, {years} in the phrase becomes the number of years\n\
+         /anniversary remove #id - delete\n/anniversary - list\n\nExample: /anniversary 15.06.2021 {years} since we moved"),
+    ("anniversary_saved", "Годовщина #{id} сохранена. Ближайшее напоминание {date} в {time}:\n{text}",
+        "Anniversary #{id} saved. Next reminder on {date} at {time}:\n{text}"),
+    ("anniversary_removed", "Годовщина #{id} удалена", "Anniversary #{id} deleted"),
+    ("anniversary_not_found", "Годовщина #{id} не найдена", "Anniversary #{id} not found"),
+    ("anniversary_reminder", "🗓 {text}", "🗓 {text}"),
+    ("anniversaries_empty", "Годовщин пока нет, см. /anniversary", "No anniversaries yet, see /anniversary"),
+    ("anniversaries_list", "Ваши годовщины:\n{anniversaries}", "Your anniversaries:\n{anniversaries}"),
+    ("anniversary_line", "#{id} {date} — {text}", "#{id} {date} — {text}"),
+    ("habits_empty", "У вас нет привычек", "You have no habits"),
+    ("habits_report", "Ваши привычки:\n{habits}", "Your habits:\n{habits}"),
+    ("habit_report_line",
+        "#{id} {name} ({time}) - серия: {streak}, за неделю: {percent}%",
+        "#{id} {name} ({time}) - streak: {streak}, this week: {percent}%"),
+    ("pomodoro_usage",
+        "Формат: /pomodoro [работа] [перерыв] [циклы], например /pomodoro 25 5 4\n/pomodoro stop - остановить",
+        "Format: /pomodoro [work] [break] [cycles], e.g. /pomodoro 25 5 4\n/pomodoro stop - stop"),
+    ("pomodoro_work",
+        "🍅 Работаем {duration} (цикл {cycle} из {cycles})",
+        "🍅 Work for {duration} (cycle {cycle} of {cycles})"),
+    ("pomodoro_break",
+        "☕ Отдыхаем {duration} (цикл {cycle} из {cycles})",
+        "☕ Break for {duration} (cycle {cycle} of {cycles})"),
+    ("pomodoro_finished", "🎉 Помодоро завершено", "🎉 Pomodoro finished"),
+    ("pomodoro_pause", "⏸ Пауза", "⏸ Pause"),
+    ("pomodoro_resume", "▶️ Продолжить", "▶️ Resume"),
+    ("pomodoro_stop", "⏹ Стоп", "⏹ Stop"),
+    ("pomodoro_paused", "Помодоро на паузе", "Pomodoro paused"),
+    ("pomodoro_resumed", "Помодоро продолжается", "Pomodoro resumed"),
+    ("pomodoro_stopped", "Помодоро остановлено", "Pomodoro stopped"),
+    ("pomodoro_not_running", "Помодоро не запущено", "No pomodoro is running"),
+    ("pomodoro_not_yours", "Управлять помодоро может только тот, кто его запустил", "Only the person who started the pomodoro can control it"),
+    ("timer_usage",
+        "Формат: /timer 15m [подпись], например /timer 1h30m чай или /timer 90с\n/timer - список, /timer stop [#номер] - остановить",
+        "Format: /timer 15m [label], e.g. /timer 1h30m tea or /timer 90s\n/timer - list, /timer stop [#number] - stop"),
+    ("timer_started", "⏱ Таймер #{id} на {duration}", "⏱ Timer #{id} for {duration}"),
+    ("timer_done", "⏰ Время вышло: {duration}", "⏰ Time's up: {duration}"),
+    ("timer_done_label", "⏰ {label} ({duration})", "⏰ {label} ({duration})"),
+    ("timer_list", "Таймеры:\n{timers}", "Timers:\n{timers}"),
+    ("timer_line", "#{id} {label} — осталось {left}", "#{id} {label} — {left} left"),
+    ("timer_none", "Нет запущенных таймеров", "No timers are running"),
+    ("timer_stopped", "Таймер остановлен", "Timer stopped"),
+    ("timer_too_many", "Не больше {limit} таймеров в чате", "No more than {limit} timers per chat"),
+    ("digest_header", "☀️ Сводка на сегодня", "☀️ Today's digest"),
+    ("digest_events", "События:\n{events}", "Events:\n{events}"),
+    ("digest_tasks", "Невыполненные задачи:\n{tasks}", "Open tasks:\n{tasks}"),
+    ("settings_saved", "Настройки сохранены", "Settings saved"),
+    ("settings_unknown",
+        "Неизвестная настройка. Используйте /settings для справки",
+        "Unknown setting. Use /settings for help"),
+    ("settings_invalid_value", "Некорректное значение: {value}", "Invalid value: {value}"),
+    ("lang_name", "русский", "English"),
+    ("clock_24h", "24 часа", "24-hour"),
+    ("clock_12h", "12 часов (AM/PM)", "12-hour (AM/PM)"),
+    ("quiet_off", "выключены", "off"),
+    ("timezone_server", "время сервера", "server time"),
+    ("retention_days", "{days}", "{days}"),
+    ("retention_forever", "бессрочно", "forever"),
+    ("timezone_usage",
+        "Формат: /timezone Europe/Moscow. В личном чате /timezone без аргументов определит пояс по геопозиции",
+        "Format: /timezone Europe/London. In a private chat /timezone without arguments detects it from your location"),
+    ("timezone_prompt",
+        "Отправьте геопозицию, и я определю ваш часовой пояс. Или укажите его названием: /timezone Europe/Moscow",
+        "Share your location and I'll detect your time zone. Or set it by name: /timezone Europe/London"),
+    ("timezone_share", "📍 Отправить геопозицию", "📍 Share location"),
+    ("timezone_saved", "Часовой пояс: {zone}", "Time zone: {zone}"),
+    ("timezone_unknown", "Неизвестный часовой пояс: {value}", "Unknown time zone: {value}"),
+    ("timezone_not_found",
+        "Не удалось определить часовой пояс по этой точке. Укажите его названием: /timezone Europe/Moscow",
+        "Couldn't detect a time zone for this point. Set it by name: /timezone Europe/London"),
+    ("sort_time", "по времени", "by time"),
+    ("sort_created", "по дате создания", "by creation date"),
+    ("sort_priority", "по приоритету", "by priority"),
+    ("admin_only", "Команда доступна только администраторам бота", "This command is only available to bot admins"),
+    ("admin_usage",
+        "Формат:\n/admin backup now - сделать резервную копию базы\n/admin usage [дни] - какие команды и функции используются\n\
+        /admin referrals [дни] - сколько пользователей пришло по ссылкам ?start=ref_<код>\n\
+        /admin maintenance on \"текст\" - режим обслуживания с объявлением\n/admin maintenance off - выключить и создать отложенные события\n\
+        /admin block <id> [причина] - игнорировать пользователя\n/admin unblock <id> - снять блокировку\n/admin blocked - заблокированные пользователи\n\
+        /admin reload - перечитать настройки из .env",
+        "Format:\n/admin backup now - back up the database\n/admin usage [days] - which commands and features are used\n\
+        /admin referrals [days] - how many users came via ?start=ref_<code> links\n\
+        /admin maintenance on \"text\" - maintenance mode with an announcement\n/admin maintenance off - turn it off and create buffered events\n\
+        /admin block <id> [reason] - ignore a user\n/admin unblock <id> - lift the block\n/admin blocked - blocked users\n\
+        /admin reload - re-read settings from .env"),
+    ("admin_referrals_report",
+        "Новые пользователи по реферальным кодам ({period}):\n{codes}",
+        "New users by referral code ({period}):\n{codes}"),
+    ("admin_referral_line", "{code} — {signups}, создали события: {active}", "{code} — {signups}, created events: {active}"),
+    ("admin_referrals_empty", "По реферальным ссылкам никто не пришёл ({period})", "Nobody came via referral links ({period})"),
+    ("admin_referrals_all_time", "за всё время", "all time"),
+    ("admin_referrals_days", "за {days}", "over {days}"),
+    ("admin_backup_done", "Резервная копия сохранена: {path}", "Backup saved: {path}"),
+    ("admin_backup_failed", "Не удалось сделать резервную копию: {error}", "Backup failed: {error}"),
+    ("audit_usage", "Формат: /audit #id", "Format: /audit #id"),
+    ("audit_empty", "Нет истории изменений события #{id} в этом чате", "No change history for event #{id} in this chat"),
+    ("audit_history", "История события #{id}:\n{entries}", "History of event #{id}:\n{entries}"),
+    ("audit_created", "{when} {who} создал(а): {new}", "{when} {who} created: {new}"),
+    ("audit_edited", "{when} {who} изменил(а): {old} → {new}", "{when} {who} edited: {old} → {new}"),
+    ("audit_deleted", "{when} {who} удалил(а): {old}", "{when} {who} deleted: {old}"),
+    ("audit_snoozed", "{when} {who} отложил(а): {old} → {new}", "{when} {who} snoozed: {old} → {new}"),
+    ("audit_changed", "{when} {who}: {action}", "{when} {who}: {action}"),
+    ("audit_system", "бот", "the bot"),
+    ("token_usage",
+        "Формат:\n/token create [read,write] [название] - новый токен (по умолчанию только чтение)\n/token list - ваши токены\n/token revoke <id> - отозвать токен",
+        "Format:\n/token create [read,write] [name] - new token (read-only by default)\n/token list - your tokens\n/token revoke <id> - revoke a token"),
+    ("token_private_only", "Токенами можно управлять только в личном чате с ботом", "Tokens can only be managed in a private chat with the bot"),
+    ("token_created",
+        "Токен #{id} создан. Сохраните его, больше он показан не будет:\n{token}",
+        "Token #{id} created. Save it now, it won't be shown again:\n{token}"),
+    ("token_list", "Ваши токены:\n{tokens}", "Your tokens:\n{tokens}"),
+    ("token_list_empty", "У вас нет токенов", "You have no tokens"),
+    ("token_line",
+        "#{id} {name} ({hint}…) права: {scopes}, создан {created}, использован: {used}",
+        "#{id} {name} ({hint}…) scopes: {scopes}, created {created}, last used: {used}"),
+    ("token_never_used", "никогда", "never"),
+    ("token_revoked", "Токен #{id} отозван", "Token #{id} revoked"),
+    ("token_not_found", "Токен #{id} не найден", "Token #{id} not found"),
+    ("ics_button", "📅 Добавить в календарь", "📅 Add to calendar"),
+    ("import_unsupported",
+        "Импортировать можно выгрузку Todoist (.csv) или Google Tasks (.json)",
+        "Only Todoist (.csv) and Google Tasks (.json) exports can be imported"),
+    ("import_too_large", "Файл больше 1 МБ, импорт невозможен", "The file is larger than 1 MB and cannot be imported"),
+    ("import_failed", "Не удалось прочитать файл выгрузки", "Could not read the export file"),
+    ("import_preview", "Будет создано событий: {count}", "Events to create: {count}"),
+    ("import_more", "…и ещё {count}", "…and {count} more"),
+    ("import_skipped", "Пропущено: {count}", "Skipped: {count}"),
+    ("import_skip_no_title", "нет названия", "no title"),
+    ("import_skip_no_date", "нет срока", "no due date"),
+    ("import_skip_bad_date", "срок не распознан", "due date not recognized"),
+    ("import_skip_completed", "уже выполнена", "already completed"),
+    ("import_skip_past", "срок прошёл", "due date has passed"),
+    ("import_skip_limit", "превышен лимит в 500 событий", "over the limit of 500 events"),
+    ("import_nothing", "Импортировать нечего", "Nothing to import"),
+    ("import_confirm", "Импортировать", "Import"),
+    ("import_cancel", "Отмена", "Cancel"),
+    ("import_done", "Импортировано событий: {count}", "Imported events: {count}"),
+    ("import_cancelled", "Импорт отменён", "Import cancelled"),
+    ("import_expired", "Этот импорт уже обработан", "This import has already been handled"),
+    ("subscription_usage",
+        "Используйте:\n/subscribe <ссылка .ics> [название] - подписать чат на календарь\n/subscribe list - подписки чата\n/subscribe remove <id> - отписаться\n/subscribe conflicts <id> latest|local|ask - если событие изменили и здесь, и в календаре: брать последнее изменение, оставлять правку в боте или спрашивать",
+        "Usage:\n/subscribe <.ics link> [name] - subscribe the chat to a calendar\n/subscribe list - the chat's subscriptions\n/subscribe remove <id> - unsubscribe\n/subscribe conflicts <id> latest|local|ask - when an event changed both here and in the calendar: take the latest change, keep the edit made here or ask"),
+    ("subscription_added",
+        "Подписка #{id} «{name}» добавлена, событий на ближайшие 60 дней: {count}. Календарь будет обновляться сам",
+        "Subscription #{id} \"{name}\" added, events in the next 60 days: {count}. The calendar will be refreshed automatically"),
+    ("subscription_failed", "Не удалось прочитать календарь: {error}", "Could not read the calendar: {error}"),
+    ("subscription_limit", "В чате уже {limit} подписок — больше нельзя", "The chat already has {limit} subscriptions, the maximum"),
+    ("subscription_line", "#{id} {name}, обновлено: {refreshed}, конфликты: {policy}", "#{id} {name}, refreshed: {refreshed}, conflicts: {policy}"),
+    ("subscription_error", " (ошибка: {error})", " (error: {error})"),
+    ("subscription_list_empty", "В чате нет подписок на календари", "The chat has no calendar subscriptions"),
+    ("subscription_removed", "Подписка #{id} удалена вместе с предстоящими событиями", "Subscription #{id} removed along with its upcoming events"),
+    ("subscription_not_found", "Подписка #{id} не найдена", "Subscription #{id} not found"),
+    ("subscription_changed",
+        "📅 Календарь «{name}» изменился: перенесено {updated}, отменено {cancelled}, добавлено {added}, оставлено правок из бота {kept}",
+        "📅 Calendar \"{name}\" changed: {updated} moved, {cancelled} cancelled, {added} added, {kept} edits made here kept"),
+    ("subscription_policy_set", "Подписка #{id}: конфликты — {policy}", "Subscription #{id}: conflicts — {policy}"),
+    ("sync_policy_latest", "побеждает последнее изменение", "the latest change wins"),
+    ("sync_policy_local", "правка в боте важнее", "edits made here win"),
+    ("sync_policy_ask", "спрашивать", "ask"),
+    ("sync_conflict",
+        "⚠️ Событие изменили и здесь, и в календаре «{origin}».\nЗдесь: {local}\nВ календаре: {remote}\nКакую версию оставить?",
+        "⚠️ The event changed both here and in the calendar \"{origin}\".\nHere: {local}\nIn the calendar: {remote}\nWhich version should stay?"),
+    ("sync_remote_cancelled", "отменено", "cancelled"),
+    ("sync_keep_local", "Оставить эту", "Keep this one"),
+    ("sync_take_remote", "Взять из календаря", "Take the calendar's"),
+    ("sync_kept_local", "Оставлена версия из бота", "Kept the version made here"),
+    ("sync_took_remote", "Взята версия из календаря", "Took the calendar's version"),
+    ("sync_conflict_expired", "Этот конфликт уже решён", "This conflict has already been settled"),
+    ("guest_usage",
+        "Используйте:\n/guest [дни] - ссылка на события чата только для чтения (по умолчанию на 7 дней, не больше 90)\n/guest list - действующие ссылки\n/guest revoke <id> - отозвать ссылку",
+        "Usage:\n/guest [days] - a read-only link to the chat's events (7 days by default, 90 at most)\n/guest list - active links\n/guest revoke <id> - revoke a link"),
+    ("guest_disabled", "Гостевые ссылки недоступны: у бота не включён HTTP-сервер", "Guest links are unavailable: the bot's HTTP server is not enabled"),
+    ("guest_created", "Ссылку можно отправить родственникам без Telegram:\n{link}", "Send this link to relatives without Telegram:\n{link}"),
+    ("guest_line", "#{id} {url} (до {expires})", "#{id} {url} (until {expires})"),
+    ("guest_list_empty", "Действующих гостевых ссылок нет", "No active guest links"),
+    ("guest_revoked", "Ссылка #{id} отозвана", "Link #{id} revoked"),
+    ("guest_not_found", "Ссылка #{id} не найдена", "Link #{id} not found"),
+    ("guest_page_title", "Ближайшие события", "Upcoming events"),
+    ("guest_page_empty", "В ближайшие 30 дней событий нет", "No events in the next 30 days"),
+    ("share_usage", "Используйте: /share #id", "Usage: /share #id"),
+    ("mirror_unavailable",
+        "Дублирование в Slack, Discord и MQTT не включено в этой сборке бота",
+        "Slack, Discord and MQTT mirroring is not enabled in this build of the bot"),
+    ("mirror_private_only",
+        "Адрес вебхука секретный — настройте дублирование в личном чате с ботом",
+        "Webhook URLs are secret, set up mirroring in a private chat with the bot"),
+    ("mirror_usage",
+        "Используйте:\n/mirror <{kinds}> <url вебхука> - дублировать напоминания\n/mirror mqtt <имя темы> - публиковать напоминания в MQTT для умного дома, если он включён\n/mirror off <{kinds}> - отключить",
+        "Usage:\n/mirror <{kinds}> <webhook url> - mirror reminders\n/mirror mqtt <topic name> - publish reminders to MQTT for home automation, if enabled\n/mirror off <{kinds}> - turn off"),
+    ("mirror_list", "Напоминания дублируются в: {kinds}", "Reminders are mirrored to: {kinds}"),
+    ("mirror_saved", "Напоминания будут дублироваться в {kind}", "Reminders will be mirrored to {kind}"),
+    ("mirror_invalid_url", "Это не адрес входящего вебхука {kind}", "This is not a {kind} incoming webhook URL"),
+    ("mirror_invalid_topic",
+        "Имя темы MQTT — до 64 латинских букв, цифр, _ и -",
+        "An MQTT topic name is up to 64 Latin letters, digits, _ and -"),
+    ("mirror_removed", "Дублирование в {kind} отключено", "Mirroring to {kind} is turned off"),
+    ("mirror_not_found", "Дублирование в {kind} не настроено", "Mirroring to {kind} is not set up"),
+    ("voice_reminder", "Напоминание: {text}", "Reminder: {text}"),
+    ("voice_unavailable", "Голосовые напоминания не настроены на этом сервере", "Voice reminders are not set up on this server"),
+    ("voice_usage",
+        "Используйте:\n/voice on|off - все напоминания ещё и голосовым сообщением или только текстом\n/voice #id on|off|default - то же для одного события",
+        "Usage:\n/voice on|off - send every reminder as a voice message too, or text only\n/voice #id on|off|default - the same for one event"),
+    ("voice_status_on", "Напоминания приходят и голосом. Выключить: /voice off", "Reminders also come as voice messages. Turn off: /voice off"),
+    ("voice_status_off", "Напоминания приходят только текстом. Включить голос: /voice on", "Reminders come as text only. Turn on voice: /voice on"),
+    ("voice_enabled", "Напоминания будут приходить и голосом", "Reminders will also come as voice messages"),
+    ("voice_disabled", "Напоминания будут приходить только текстом", "Reminders will come as text only"),
+    ("voice_event_on", "Напоминание о #{id} придёт и голосом", "The reminder for #{id} will also come as a voice message"),
+    ("voice_event_off", "Напоминание о #{id} придёт только текстом", "The reminder for #{id} will come as text only"),
+    ("voice_event_default", "Для #{id} действует общая настройка /voice", "#{id} follows your /voice setting"),
+    ("webhook_usage",
+        "Используйте:\n/webhook <https-адрес> - присылать напоминания JSON-запросом на свой адрес\n/webhook test - пробная доставка во все каналы\n/webhook off - отключить",
+        "Usage:\n/webhook <https url> - send reminders as JSON requests to your own endpoint\n/webhook test - a test delivery to every channel\n/webhook off - turn off"),
+    ("webhook_enabled",
+        "Вебхук включён. Проверить его: /webhook test, отключить: /webhook off",
+        "The webhook is on. Test it with /webhook test, turn it off with /webhook off"),
+    ("webhook_saved",
+        "Напоминания будут приходить на этот адрес. Секрет подписи (показывается один раз):\n{secret}\n\nЗапросы подписаны заголовком X-Reventor-Signature: sha256=HMAC-SHA256(секрет, \"<X-Reventor-Timestamp>.<тело>\"). Неудачные доставки повторяются с растущей паузой",
+        "Reminders will be sent to this URL. Signing secret (shown once):\n{secret}\n\nRequests carry X-Reventor-Signature: sha256=HMAC-SHA256(secret, \"<X-Reventor-Timestamp>.<body>\"). Failed deliveries are retried with a growing delay"),
+    ("webhook_removed", "Вебхук отключён", "The webhook is turned off"),
+    ("webhook_not_found", "Вебхук не настроен", "No webhook is set up"),
+    ("webhook_test_none", "Нет ни вебхука, ни дублирования — проверять нечего", "There is no webhook or mirror to test"),
+    ("webhook_test_text", "🔔 Пробное напоминание от бота", "🔔 A test reminder from the bot"),
+    ("webhook_test_ok", "✅ {kind}: {status}", "✅ {kind}: {status}"),
+    ("webhook_test_failed", "❌ {kind}: {error}", "❌ {kind}: {error}"),
+    ("weather_unavailable", "Прогноз погоды не настроен на этом сервере", "Weather forecasts are not configured on this server"),
+    ("weather_usage",
+        "Отправьте /weather в личном чате с ботом, чтобы выбрать место для прогноза, или /weather off",
+        "Send /weather in a private chat with the bot to choose a place for forecasts, or /weather off"),
+    ("weather_prompt",
+        "Отправьте геопозицию: прогноз для этого места появится в сводке и напоминаниях. Чтобы указать место события, ответьте геопозицией на его подтверждение",
+        "Share your location: the forecast for this place will appear in the digest and reminders. To set an event's place, reply to its confirmation with a location"),
+    ("weather_saved", "Место для прогноза сохранено", "Forecast location saved"),
+    ("weather_off", "Прогноз погоды отключён", "Weather forecast turned off"),
+    ("weather_event_location_saved",
+        "Место события сохранено, в напоминании будет прогноз для него",
+        "Event location saved, the reminder will include its forecast"),
+    ("weather_line", "{emoji} {min}…{max}°C, {sky}", "{emoji} {min}…{max}°C, {sky}"),
+    ("weather_clear", "ясно", "clear"),
+    ("weather_cloudy", "облачно", "cloudy"),
+    ("weather_fog", "туман", "fog"),
+    ("weather_drizzle", "морось", "drizzle"),
+    ("weather_rain", "дождь", "rain"),
+    ("weather_snow", "снег", "snow"),
+    ("weather_thunder", "гроза", "thunderstorm"),
+    ("holiday_public", "Сегодня праздник: {names}", "Today: public holiday ({names})"),
+    ("holiday_observance", "Сегодня: {names}", "Today: {names}"),
+    ("place_private_only",
+        "Место выбирается геопозицией — отправьте /place в личном чате с ботом",
+        "The place is chosen by location, send /place in a private chat with the bot"),
+    ("place_usage",
+        "Используйте: /place [радиус в метрах] текст, например /place 300 купить лекарства\n/place cancel #id - отменить",
+        "Usage: /place [radius in meters] text, e.g. /place 300 buy medicine\n/place cancel #id - cancel"),
+    ("place_prompt",
+        "Отправьте геопозицию места: через скрепку можно выбрать любую точку на карте",
+        "Send the place's location: use the attachment menu to pick any point on the map"),
+    ("place_saved",
+        "Напоминание #{id} сработает в радиусе {radius} м. Включите трансляцию геопозиции в этом чате, чтобы бот узнал, что вы рядом",
+        "Reminder #{id} will fire within {radius} m. Share your live location in this chat so the bot knows when you are nearby"),
+    ("place_reminder", "📍 Вы рядом: {text}", "📍 You are nearby: {text}"),
+    ("places_empty", "Нет напоминаний у мест", "No place reminders"),
+    ("places_list", "Напоминания у мест:\n{places}", "Place reminders:\n{places}"),
+    ("place_line", "#{id} ({radius} м) {text}", "#{id} ({radius} m) {text}"),
+    ("place_cancelled", "Напоминание #{id} отменено", "Reminder #{id} cancelled"),
+    ("place_not_found", "Напоминание #{id} не найдено", "Reminder #{id} not found"),
+    ("query_header", "События на {period}:\n{events}", "Events for {period}:\n{events}"),
+    ("query_empty", "На {period} ничего не запланировано", "Nothing planned for {period}"),
+    ("agenda_usage", "Используйте: /agenda [image] [ДД.ММ], например /agenda image 15.03", "Usage: /agenda [image] [DD.MM], e.g. /agenda image 03/15"),
+    ("agenda_day_empty", "На {date} событий нет", "No events on {date}"),
+    ("agenda_card_subtitle", "{date} · {events}", "{date} · {events}"),
+    ("agenda_card_more", "и ещё {count}", "and {count} more"),
+    ("busy_usage", "Используйте: /busy [ДД.ММ], например /busy 15.03", "Usage: /busy [DD.MM], e.g. /busy 03/15"),
+    ("busy_header", "Занятость на {date}:\n{slots}", "Schedule for {date}:\n{slots}"),
+    ("overlap_warning", "⚠️ Пересекается с «{text}» {time}", "⚠️ Overlaps with '{text}' {time}"),
+    ("overlap_keep", "Оставить", "Keep"),
+    ("overlap_move", "Другое время", "Pick another time"),
+    ("overlap_kept", "Событие оставлено как есть", "The event is kept as is"),
+    ("overlap_move_prompt",
+        "Ответьте на это сообщение новым временем для события #{id}, например @16:00 или @16:00-17:00",
+        "Reply to this message with a new time for event #{id}, e.g. @16:00 or @16:00-17:00"),
+    ("leave_usage",
+        "Используйте: /leave #id [минуты в пути] или /leave off #id",
+        "Usage: /leave #id [travel minutes] or /leave off #id"),
+    ("leave_no_location",
+        "У события нет места. Ответьте на его подтверждение геопозицией, затем повторите /leave",
+        "The event has no place. Reply to its confirmation with a location, then repeat /leave"),
+    ("leave_saved",
+        "Напомню выходить на событие #{id} в {time} (дорога займёт {duration})",
+        "I'll remind you to leave for event #{id} at {time} ({duration} of travel)"),
+    ("leave_saved_route",
+        "Дорога от вашего места займёт около {duration}. Напомню выходить на событие #{id} в {time}",
+        "The trip from your place takes about {duration}. I'll remind you to leave for event #{id} at {time}"),
+    ("leave_cancelled", "Напоминание о выходе на событие #{id} отменено", "The leave reminder for event #{id} is cancelled"),
+    ("leave_not_found", "Напоминание о выходе на событие #{id} не найдено", "No leave reminder found for event #{id}"),
+    ("leave_reminder",
+        "🚶 Пора выходить: «{text}» в {time}, дорога займёт около {duration}",
+        "🚶 Time to leave: '{text}' at {time}, about {duration} to get there"),
+    ("star_usage", "Используйте: /star #id", "Usage: /star #id"),
+    ("star_added",
+        "⭐ Событие #{id} в избранном: оно будет вверху /events и в каждой сводке, а напоминание закрепится в чате",
+        "⭐ Event #{id} is starred: it stays on top of /events and in every digest, and its reminder gets pinned"),
+    ("star_removed", "Событие #{id} больше не в избранном", "Event #{id} is no longer starred"),
+    ("events_starred", "⭐ Избранное:\n{events}", "⭐ Starred:\n{events}"),
+    ("digest_starred", "⭐ Избранное:\n{events}", "⭐ Starred:\n{events}"),
+    ("group_cleanup_after", "через {duration}", "after {duration}"),
+    ("reaction_snoozed", "😴 Отложено, напомню в {time}", "😴 Snoozed, I'll remind you at {time}"),
+    ("overdue_empty", "Пропущенных событий нет", "No overdue events"),
+    ("overdue_list", "⏰ Пропущенные события:\n{events}", "⏰ Overdue events:\n{events}"),
+    ("digest_overdue", "⏰ Пропущено:\n{events}", "⏰ Overdue:\n{events}"),
+    ("overdue_hour", "#{id} +1 ч", "#{id} +1 h"),
+    ("overdue_tomorrow", "Завтра", "Tomorrow"),
+    ("overdue_rescheduled", "Перенесено на {time}", "Rescheduled to {time}"),
+    ("meds_usage",
+        "Формат:\n/meds 08:00,14:00,20:00 на 10 дней название - курс приёмов\n/meds stop #id - прекратить курс\n/meds - отметки по дням\n\n\
+         Приём отмечается кнопкой «Готово» у напоминания",
+        "Format:\n/meds 08:00,14:00,20:00 for 10 days name - a course of doses\n/meds stop #id - stop the course\n/meds - marks by day\n\n\
+         Mark a dose with the \"Done\" button under its reminder"),
+    ("meds_saved", "💊 Курс #{id} «{name}»: {per_day} раз в день ({times}), {days} дн., всего приёмов: {count}\nОтмечайте приём кнопкой «Готово», в конце пришлю итог",
+        "💊 Course #{id} \"{name}\": {per_day} times a day ({times}) for {days} days, {count} doses in total\nMark each dose with \"Done\", I'll send a summary at the end"),
+    ("meds_dose", "💊 {name}", "💊 {name}"),
+    ("meds_course", "💊 #{id} {name}: принято {taken} из {total}\n{days}", "💊 #{id} {name}: {taken} of {total} taken\n{days}"),
+    ("meds_day", "{date} — {taken}/{total} {marks}", "{date} — {taken}/{total} {marks}"),
+    ("meds_summary", "💊 Курс «{name}» завершён: принято {taken} из {total} ({percent}%)",
+        "💊 Course \"{name}\" is over: {taken} of {total} doses taken ({percent}%)"),
+    ("meds_stopped", "Курс #{id} прекращён, оставшиеся приёмы удалены", "Course #{id} stopped, the remaining doses are deleted"),
+    ("meds_not_found", "Курс #{id} не найден", "Course #{id} not found"),
+    ("meds_empty", "Курсов пока нет, см. /meds 08:00,20:00 на 7 дней название", "No courses yet, see /meds 08:00,20:00 for 7 days name"),
+    ("deadline_usage",
+        "Формат:\n/deadline @ДД.ММ ЧЧ:ММ текст - дедлайн с предупреждениями за неделю, 3 дня, день и 3 часа\n\
+         /deadline #id - сделать дедлайном существующее событие\n/deadline off #id - выключить предупреждения",
+        "Format:\n/deadline @DD.MM HH:MM text - a deadline with warnings a week, 3 days, a day and 3 hours before\n\
+         /deadline #id - turn an existing event into a deadline\n/deadline off #id - turn the warnings off"),
+    ("deadline_saved", "⏳ Дедлайн #{id} на {time}: {text}\nПредупрежу за неделю, за 3 дня, за день и за 3 часа",
+        "⏳ Deadline #{id} at {time}: {text}\nI'll warn you a week, 3 days, a day and 3 hours before"),
+    ("deadline_on", "⏳ Событие #{id} теперь дедлайн: предупрежу за неделю, за 3 дня, за день и за 3 часа",
+        "⏳ Event #{id} is now a deadline: I'll warn you a week, 3 days, a day and 3 hours before"),
+    ("deadline_off", "Предупреждения о дедлайне #{id} выключены", "Deadline warnings for event #{id} are turned off"),
+    ("deadline_week", "🗓 Через неделю дедлайн: {text}\nСрок: {time}. Самое время составить план.",
+        "🗓 Deadline in a week: {text}\nDue: {time}. A good time to make a plan."),
+    ("deadline_3d", "⏳ До дедлайна осталось {left}: {text}\nСрок: {time}. Пора браться за дело.",
+        "⏳ {left} left until the deadline: {text}\nDue: {time}. Time to get to work."),
+    ("deadline_1d", "⚠️ Дедлайн завтра: {text}\nСрок: {time}, осталось {left}. Отложите остальное.",
+        "⚠️ Deadline tomorrow: {text}\nDue: {time}, {left} left. Put everything else aside."),
+    ("deadline_3h", "🚨 ДО ДЕДЛАЙНА {left}: {text}\nСрок: {time}. Заканчивайте и сдавайте!",
+        "🚨 {left} UNTIL THE DEADLINE: {text}\nDue: {time}. Wrap it up and hand it in!"),
+    ("repeat_usage",
+        "Используйте: /repeat #id 10m [сколько раз] или /repeat off #id",
+        "Usage: /repeat #id 10m [times] or /repeat off #id"),
+    ("repeat_saved",
+        "🔁 Напоминание о событии #{id} будет повторяться каждые {minutes} мин, до {times} раз, пока вы не нажмёте «Готово»",
+        "🔁 The reminder for event #{id} will repeat every {minutes} min, up to {times} times, until you press \"Done\""),
+    ("repeat_off", "Повтор напоминания о событии #{id} выключен", "Repeating the reminder for event #{id} is turned off"),
+    ("repeat_reminder", "🔁 Напоминание ({count}/{limit})\n{text}", "🔁 Reminder ({count}/{limit})\n{text}"),
+    ("icon_usage",
+        "Используйте: /icon #id 💼 или /icon #id off\nКатегории: {categories}",
+        "Usage: /icon #id 💼 or /icon #id off\nCategories: {categories}"),
+    ("icon_saved", "{icon} Событие #{id} отмечено значком, отбор: /events {icon}", "{icon} Event #{id} is marked with an icon, filter: /events {icon}"),
+    ("icon_removed", "Значок события #{id} убран", "The icon of event #{id} is removed"),
+    ("onboarding_welcome",
+        "Привет! Я напоминаю о событиях: напишите время и текст, и я пришлю напоминание вовремя.\nДля начала выберите язык:",
+        "Hi! I remind you about events: send a time and a text, and I will remind you on time.\nFirst, choose your language:"),
+    ("onboarding_timezone",
+        "Выберите часовой пояс, чтобы напоминания приходили по вашим часам. Другой пояс можно задать командой /timezone, в том числе по геопозиции",
+        "Choose your timezone so reminders arrive by your clock. Any other zone can be set with /timezone, including by location"),
+    ("onboarding_skip", "Пропустить", "Skip"),
+    ("contact_usage",
+        "Формат:\n/contact - контакты и общие напоминания\n/contact add мама @username - попросить разрешения ставить напоминания\n\
+        /contact remove мама|@username - снять привязку\n/contact cancel #id - отменить общее напоминание\n/for мама @18:00 текст - напоминание в чат контакта",
+        "Format:\n/contact - contacts and shared reminders\n/contact add mom @username - ask for permission to set reminders\n\
+        /contact remove mom|@username - remove the link\n/contact cancel #id - cancel a shared reminder\n/for mom @18:00 text - a reminder in the contact's chat"),
+    ("contact_private_only", "Контакты настраиваются в личном чате с ботом", "Contacts are managed in a private chat with the bot"),
+    ("contact_none",
+        "Контактов пока нет. Попросите разрешения: /contact add мама @username — человек должен хотя бы раз написать боту",
+        "No contacts yet. Ask for permission: /contact add mom @username — they need to have messaged the bot at least once"),
+    ("contact_list", "Контакты:\n{contacts}\n\nОбщие напоминания:\n{events}", "Contacts:\n{contacts}\n\nShared reminders:\n{events}"),
+    ("contact_line_mine", "{name} — {user}", "{name} — {user}"),
+    ("contact_line_pending", "{name} — {user}, ждёт согласия", "{name} — {user}, awaiting consent"),
+    ("contact_line_theirs", "{user} может ставить вам напоминания", "{user} can set reminders for you"),
+    ("contact_no_events", "нет", "none"),
+    ("contact_event_for", "#{id} {time} для {name}: {text}", "#{id} {time} for {name}: {text}"),
+    ("contact_event_from", "#{id} {time} от {user}: {text}", "#{id} {time} from {user}: {text}"),
+    ("contact_unknown_user",
+        "Не нашёл такого пользователя. Попросите его написать боту /start и повторите",
+        "Couldn't find that user. Ask them to send /start to the bot and try again"),
+    ("contact_renamed", "Контакт теперь называется {name}, согласие уже дано", "The contact is now called {name}, consent is already given"),
+    ("contact_requested", "Запрос отправлен. Напоминания можно будет ставить, когда его примут", "Request sent. You can set reminders once it is accepted"),
+    ("contact_request",
+        "{name} ({user}) хочет ставить вам напоминания: они будут приходить в этот чат. Вы всегда сможете их отменить. Разрешить?",
+        "{name} ({user}) wants to set reminders for you: they will arrive in this chat. You can always cancel them. Allow?"),
+    ("contact_allow", "Разрешить", "Allow"),
+    ("contact_decline", "Отклонить", "Decline"),
+    ("contact_accepted", "Разрешено. Список и отмена напоминаний: /contact", "Allowed. List and cancel reminders: /contact"),
+    ("contact_declined", "Запрос отклонён", "Request declined"),
+    ("contact_accepted_by", "{user} разрешил(а) напоминания: /for {name} @время текст", "{user} allowed reminders: /for {name} @time text"),
+    ("contact_declined_by", "{user} отклонил(а) запрос на напоминания", "{user} declined the reminder request"),
+    ("contact_request_expired", "Запрос уже не действует", "This request is no longer valid"),
+    ("contact_not_found", "Нет такого контакта или он ещё не дал согласие, см. /contact", "No such contact, or they haven't agreed yet, see /contact"),
+    ("contact_removed", "Привязка снята, отменено напоминаний: {count}", "Link removed, reminders cancelled: {count}"),
+    ("contact_removed_by", "{user} снял(а) привязку для напоминаний", "{user} removed the reminder link"),
+    ("contact_event_cancelled", "Напоминание #{id} отменено", "Reminder #{id} cancelled"),
+    ("contact_event_cancelled_by", "{user} отменил(а) напоминание: {text}", "{user} cancelled the reminder: {text}"),
+    ("contact_for_usage", "Формат: /for мама @18:00 выпить лекарство", "Format: /for mom @18:00 take medicine"),
+    ("contact_for_saved", "Напоминание #{id} для контакта на {time}: {text}", "Reminder #{id} for your contact at {time}: {text}"),
+    ("contact_for_received",
+        "{user} поставил(а) вам напоминание #{id} на {time}: {text}\nОтменить: /contact cancel #{id}",
+        "{user} set reminder #{id} for you at {time}: {text}\nCancel: /contact cancel #{id}"),
+    ("onboarding_samples_offer",
+        "Хотите посмотреть, как приходят напоминания? Создам два примера на ближайшие минуты, потом их можно удалить одной кнопкой",
+        "Want to see how reminders arrive? I'll create two examples due in the next few minutes, and you can delete them with one tap afterwards"),
+    ("onboarding_samples_create", "Создать примеры", "Create examples"),
+    ("onboarding_samples_created",
+        "Примеры созданы, напоминания придут в {times}. Под ними будут кнопки: отметить выполненным или отложить",
+        "Examples created, reminders will arrive at {times}. They come with buttons to mark them done or snooze them"),
+    ("onboarding_samples_seen",
+        "Так выглядят напоминания. Примеры больше не нужны? Удалите их, настоящие события останутся",
+        "That's how reminders look. Done with the examples? Delete them, your real events stay"),
+    ("onboarding_samples_delete", "Удалить примеры", "Delete examples"),
+    ("onboarding_samples_deleted", "Удалено примеров: {count}", "Examples deleted: {count}"),
+    ("onboarding_sample_first", "Пример: выпить стакан воды", "Example: drink a glass of water"),
+    ("onboarding_sample_second", "Пример: встать и размяться", "Example: get up and stretch"),
+    ("onboarding_done",
+        "Готово! Попробуйте отправить одно из сообщений:\n\n@{soon} Позвонить маме\n@{tomorrow} 10:00 Записаться к врачу\n\nВсе форматы и команды — в /help",
+        "All set! Try sending one of these messages:\n\n@{soon} Call mom\n@{tomorrow} 10:00 Book a doctor appointment\n\nAll formats and commands are in /help"),
+    ("feedback_usage",
+        "Напишите отзыв после команды: /feedback текст (до 2000 символов)",
+        "Write your feedback after the command: /feedback text (up to 2000 characters)"),
+    ("feedback_thanks", "Спасибо! Сообщение передано разработчикам 🙏", "Thank you! Your message has been passed to the developers 🙏"),
+    ("feedback_admin",
+        "📝 Отзыв #{id} от {user}, версия {version}\nОтветьте на это сообщение, чтобы ответ получил пользователь.\n\n{text}",
+        "📝 Feedback #{id} from {user}, version {version}\nReply to this message to answer the user.\n\n{text}"),
+    ("feedback_reply", "💬 Ответ на ваш отзыв #{id}:\n{text}", "💬 Reply to your feedback #{id}:\n{text}"),
+    ("feedback_reply_sent", "Ответ на отзыв #{id} отправлен", "The reply to feedback #{id} has been sent"),
+    ("feedback_reply_failed", "Не удалось отправить ответ на отзыв #{id}", "Failed to send the reply to feedback #{id}"),
+    ("admin_usage_report", "Использование за {days}:\n{usage}", "Usage over {days}:\n{usage}"),
+    ("admin_usage_empty", "За {days} использование не записано", "No usage recorded over {days}"),
+    ("admin_usage_chart", "Использование за {days}", "Usage over {days}"),
+    ("admin_usage_lag",
+        "Доставлено напоминаний: {count}, задержка в среднем {average} с, максимум {max} с",
+        "Reminders delivered: {count}, delay {average} s on average, {max} s at most"),
+    ("integrity_report",
+        "🩺 Проверка базы при запуске: исправлено событий — {repaired}, в карантине (таблица quarantined_events) — {quarantined}: {events}",
+        "🩺 Startup database check: events repaired — {repaired}, quarantined (quarantined_events table) — {quarantined}: {events}"),
+    ("maintenance_notice", "🛠 {notice}", "🛠 {notice}"),
+    ("maintenance_buffered",
+        "🛠 {notice}\nСобытие сохранено и будет создано, как только бот снова заработает",
+        "🛠 {notice}\nYour event is saved and will be created as soon as the bot is back"),
+    ("maintenance_on",
+        "Режим обслуживания включён: пользователи получат объявление, события будут накапливаться, сводки и повторы приостановлены",
+        "Maintenance mode is on: users get the announcement, events are buffered, digests and repeats are paused"),
+    ("maintenance_off", "Режим обслуживания выключен, создано отложенных событий: {count}", "Maintenance mode is off, buffered events created: {count}"),
+    ("blocklist_blocked",
+        "Пользователь {id} заблокирован: его сообщения игнорируются, напоминания приостановлены",
+        "User {id} is blocked: their messages are ignored and reminders suspended"),
+    ("blocklist_unblocked", "Пользователь {id} разблокирован", "User {id} is unblocked"),
+    ("blocklist_not_blocked", "Пользователь {id} не заблокирован", "User {id} is not blocked"),
+    ("blocklist_list", "Заблокированные пользователи:\n{users}", "Blocked users:\n{users}"),
+    ("blocklist_empty", "Заблокированных пользователей нет", "No blocked users"),
+    ("privacy_usage", "Формат: /privacy on или /privacy off", "Format: /privacy on or /privacy off"),
+    ("privacy_status_on",
+        "Вы не учитываетесь в статистике использования и журнале изменений. Вернуть учёт: /privacy off",
+        "You are left out of usage statistics and the change log. To be counted again: /privacy off"),
+    ("privacy_status_off",
+        "Ваши действия учитываются в обезличенной статистике и журнале изменений. Отказаться: /privacy on",
+        "Your actions count towards anonymous usage statistics and the change log. To opt out: /privacy on"),
+    ("privacy_enabled",
+        "Готово: вы больше не учитываетесь в статистике и журнале изменений",
+        "Done: you are no longer counted in statistics or the change log"),
+    ("privacy_disabled", "Учёт в статистике и журнале изменений снова включён", "Statistics and change log are back on for you"),
+    ("admin_reloaded", "Настройки перечитаны", "Configuration reloaded"),
+    ("admin_reload_failed", "Не удалось перечитать настройки: {error}", "Failed to reload configuration: {error}"),
+    ("share_link",
+        "Отсканируйте код или откройте ссылку, чтобы добавить событие #{id} себе:\n{link}",
+        "Scan the code or open the link to add event #{id} to your reminders:\n{link}"),
+    ("share_not_found",
+        "Событие по этой ссылке не найдено или уже прошло",
+        "The event behind this link was not found or has already passed"),
+    ("plain_on", "включён: без эмодзи и украшений", "on: no emoji or decorations"),
+    ("plain_off", "выключен", "off"),
+    ("plain_priority_1", "Важно", "Important"),
+    ("plain_priority_2", "Высокий приоритет", "High priority"),
+    ("plain_priority_3", "Наивысший приоритет", "Top priority"),
+    ("plain_checked", "Сделано", "Done"),
+    ("plain_unchecked", "Не сделано", "To do"),
+    ("plain_free", "свободно", "free"),
+];
+
+/// Варианты для простого вывода там, где значок нёс смысл, который при удалении потерялся бы.
+/// Остальные тексты в этом режиме просто очищаются от значков, см. `accessibility::strip`.
+const PLAIN_MESSAGES: &[(&str, &str, &str)] = &[
+    ("reminder", "Напоминание: {text}\nВремя: {time}", "Reminder: {text}\nTime: {time}"),
+    ("repeat_reminder", "Повторное напоминание, {count} из {limit}: {text}", "Repeated reminder, {count} of {limit}: {text}"),
+    ("next_event", "Ближайшее событие: {time} — {text}\n{until}", "Next event: {time} — {text}\n{until}"),
+    ("stats_week", "{week}: создано {created}, выполнено {done}", "{week}: {created} created, {done} done"),
+    ("habit_logged_yes", "Сделано: {name}\nСерия: {streak}", "Done: {name}\nStreak: {streak}"),
+    ("habit_logged_no", "Не сделано: {name}\nСерия: {streak}", "Not done: {name}\nStreak: {streak}"),
+    ("duplicate_warning",
+        "Внимание: такое событие уже запланировано: {time}\n{text}\nСоздать ещё одно?",
+        "Warning: this event is already scheduled: {time}\n{text}\nCreate another one?"),
+    ("overlap_warning", "Внимание: пересекается с «{text}» {time}", "Warning: overlaps with '{text}' {time}"),
+    ("sync_conflict",
+        "Внимание: событие изменили и здесь, и в календаре «{origin}».\nЗдесь: {local}\nВ календаре: {remote}\nКакую версию оставить?",
+        "Warning: the event changed both here and in the calendar \"{origin}\".\nHere: {local}\nIn the calendar: {remote}\nWhich version should stay?"),
+    ("webhook_test_ok", "Успешно, {kind}: {status}", "Success, {kind}: {status}"),
+    ("webhook_test_failed", "Ошибка, {kind}: {error}", "Failed, {kind}: {error}"),
+    ("weather_line", "Погода: от {min} до {max}°C, {sky}", "Weather: {min} to {max}°C, {sky}"),
+];
+
+/// Тексты оператора из `MESSAGES_DIR`: `ru.json` и `en.json` с объектами «ключ → шаблон».
+/// Заменяют встроенные тексты целиком, в том числе варианты простого вывода.
+type Overrides = HashMap<(Lang, String), String>;
+
+fn overrides() -> &'static RwLock<Arc<Overrides>> {
+    static OVERRIDES: OnceLock<RwLock<Arc<Overrides>>> = OnceLock::new();
+    OVERRIDES.get_or_init(Default::default)
+}
+
+/// Подстановки шаблона: `{name}`.
+fn placeholders(template: &str) -> BTreeSet<&str> {
+    static PLACEHOLDER: OnceLock<Regex> = OnceLock::new();
+    PLACEHOLDER
+        .get_or_init(|| Regex::new(r"\{([a-z_]+)\}").unwrap())
+        .captures_iter(template)
+        .filter_map(|captures| Some(captures.get(1)?.as_str()))
+        .collect()
+}
+
+/// Разбирает файл текстов одного языка. Ключ должен существовать, а шаблон — обходиться
+/// подстановками встроенного текста: других код не передаёт, и они остались бы в сообщении как есть.
+/// Подстановки можно и опускать.
+fn parse_overrides(lang: Lang, file: &str, json: &str) -> Result<Vec<(String, String)>, Vec<String>> {
+    let templates: HashMap<String, String> = serde_json::from_str(json).map_err(|e| vec![format!("{}: {}", file, e)])?;
+    let mut errors = Vec::new();
+    let mut parsed = Vec::new();
+    for (key, template) in templates {
+        let Some((_, ru, en)) = MESSAGES.iter().find(|(k, _, _)| *k == key) else {
+            errors.push(format!("{}: unknown message key \"{}\"", file, key));
+            continue;
+        };
+        let known = placeholders(if lang == Lang::Ru { ru } else { en });
+        let unknown = placeholders(&template).into_iter().filter(|name| !known.contains(name)).collect::<Vec<_>>();
+        if unknown.is_empty() {
+            parsed.push((key, template));
+        } else {
+            let unknown = unknown.iter().map(|name| format!("{{{}}}", name)).collect::<Vec<_>>().join(", ");
+            errors.push(format!("{}: \"{}\" uses unknown placeholders {}", file, key, unknown));
+        }
+    }
+    if errors.is_empty() { Ok(parsed) } else { Err(errors) }
+}
+
+/// Загружает тексты оператора при запуске и по `/admin reload`. С ошибками в любом файле
+/// не меняется ничего: остаются прежние тексты, а ошибки перечисляются все сразу.
+pub fn load_overrides() -> Result<usize, String> {
+    let mut loaded = Overrides::new();
+    if let Ok(dir) = env::var("MESSAGES_DIR") {
+        let mut errors = Vec::new();
+        for lang in Lang::ALL {
+            let path = Path::new(&dir).join(format!("{}.json", lang.code()));
+            let json = match std::fs::read_to_string(&path) {
+                Ok(json) => json,
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+                Err(e) => {
+                    errors.push(format!("{}: {}", path.display(), e));
+                    continue;
+                }
+            };
+            match parse_overrides(lang, &path.display().to_string(), &json) {
+                Ok(templates) => loaded.extend(templates.into_iter().map(|(key, template)| ((lang, key), template))),
+                Err(file_errors) => errors.extend(file_errors),
+            }
+        }
+        if !errors.is_empty() {
+            errors.sort();
+            return Err(errors.join("\n"));
+        }
+    }
+    let count = loaded.len();
+    *overrides().write().unwrap_or_else(|poisoned| poisoned.into_inner()) = Arc::new(loaded);
+    Ok(count)
+}
+
+pub fn t(lang: Lang, key: &str) -> String {
+    let custom = overrides().read().unwrap_or_else(|poisoned| poisoned.into_inner()).get(&(lang, key.to_string())).cloned();
+    if accessibility::current().is_none() {
+        return custom.unwrap_or_else(|| lookup(MESSAGES, lang, key));
+    }
+    let text = match custom {
+        Some(text) => text,
+        None if PLAIN_MESSAGES.iter().any(|(k, _, _)| *k == key) => lookup(PLAIN_MESSAGES, lang, key),
+        None => lookup(MESSAGES, lang, key),
+    };
+    accessibility::strip(&text)
+}
+
+fn lookup(messages: &[(&str, &str, &str)], lang: Lang, key: &str) -> String {
+    match messages.iter().find(|(k, _, _)| *k == key) {
+        Some((_, ru, en)) => match lang {
+            Lang::Ru => ru.to_string(),
+            Lang::En => en.to_string(),
+        },
+        None => {
+            log::error!("Missing message key: {}", key);
+            key.to_string()
+        }
+    }
+}
+
+/// Подставляет аргументы за один проход, чтобы `{...}` внутри пользовательского текста не раскрывались.
+pub fn tf(lang: Lang, key: &str, args: &[(&str, &(dyn Display + Sync))]) -> String {
+    let template = t(lang, key);
+    let mut result = String::with_capacity(template.len());
+    let mut rest = template.as_str();
+
+    while let Some(start) = rest.find('{') {
+        result.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+        let value = after
+            .find('}')
+            .and_then(|end| args.iter().find(|(name, _)| *name == &after[..end]).map(|(_, v)| (end, v)));
+        match value {
+            Some((end, value)) => {
+                result.push_str(&value.to_string());
+                rest = &after[end + 1..];
+            }
+            None => {
+                result.push('{');
+                rest = after;
+            }
+        }
+    }
+    result.push_str(rest);
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn overrides_keep_to_known_placeholders() {
+        let parsed = parse_overrides(Lang::En, "en.json", r#"{"reminder": "⏰ {text} at {time}", "event_saved_today": "Saved for {time}"}"#).unwrap();
+        assert_eq!(parsed.len(), 2);
+        let errors = parse_overrides(Lang::En, "en.json", r#"{"reminder": "{text} for {user}", "no_such_key": "x"}"#).unwrap_err();
+        assert_eq!(errors.len(), 2);
+        assert!(errors.iter().any(|error| error.contains("{user}")), "{:?}", errors);
+        assert!(parse_overrides(Lang::Ru, "ru.json", "[1]").is_err());
+    }
+}
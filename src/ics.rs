@@ -0,0 +1,405 @@
+use teloxide::prelude::*;
+use teloxide::types::{InlineKeyboardButton, InlineKeyboardMarkup, InputFile};
+use chrono::{Datelike, Duration, NaiveDate, NaiveDateTime, NaiveTime, Utc, Weekday};
+use chrono_tz::Tz;
+
+use crate::i18n::{t, Lang};
+use crate::settings;
+use crate::timezone;
+use crate::{DatabaseError, Db, UTC_FORMAT, event_instant, get_event};
+
+/// Во сколько напоминать о событии на весь день из чужого календаря.
+const ALL_DAY_TIME: (u32, u32) = (9, 0);
+/// Сколько шагов правила повтора разбирается самое большее: бесконечные правила не должны зациклить обновление.
+const MAX_RULE_STEPS: i64 = 20_000;
+
+/// Кнопка под подтверждением события.
+pub fn keyboard(lang: Lang, event_id: i64) -> InlineKeyboardMarkup {
+    InlineKeyboardMarkup::new(vec![vec![
+        InlineKeyboardButton::callback(t(lang, "ics_button"), format!("ics:{}", event_id)),
+    ]])
+}
+
+/// Экранирование текста по RFC 5545.
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace('\r', "")
+        .replace('\n', "\\n")
+}
+
+/// Строки длиннее 75 байт переносятся с пробелом в начале продолжения, не разрывая символы UTF-8.
+fn fold(line: &str) -> String {
+    let mut folded = String::with_capacity(line.len());
+    let mut width = 0;
+    for c in line.chars() {
+        if width + c.len_utf8() > 75 {
+            folded.push_str("\r\n ");
+            width = 1;
+        }
+        folded.push(c);
+        width += c.len_utf8();
+    }
+    folded
+}
+
+fn format_utc(utc: NaiveDateTime) -> String {
+    utc.format("%Y%m%dT%H%M%SZ").to_string()
+}
+
+/// Календарь из одного события с напоминанием в момент начала. Без окончания событие
+/// получается нулевой длительности, как и положено по RFC 5545.
+fn render(event_id: i64, text: &str, start: NaiveDateTime, end: Option<NaiveDateTime>) -> String {
+    let summary = text.lines().next().unwrap_or_default();
+    let mut lines = vec![
+        "BEGIN:VCALENDAR".to_string(),
+        "VERSION:2.0".to_string(),
+        "PRODID:-//reventor//reventor//EN".to_string(),
+        "BEGIN:VEVENT".to_string(),
+        format!("UID:event-{}@reventor", event_id),
+        format!("DTSTAMP:{}", format_utc(Utc::now().naive_utc())),
+        format!("DTSTART:{}", format_utc(start)),
+    ];
+    lines.extend(end.map(|end| format!("DTEND:{}", format_utc(end))));
+    lines.extend([
+        format!("SUMMARY:{}", escape(summary)),
+        format!("DESCRIPTION:{}", escape(text)),
+        "BEGIN:VALARM".to_string(),
+        "ACTION:DISPLAY".to_string(),
+        format!("DESCRIPTION:{}", escape(summary)),
+        "TRIGGER:PT0M".to_string(),
+        "END:VALARM".to_string(),
+        "END:VEVENT".to_string(),
+        "END:VCALENDAR".to_string(),
+    ]);
+    lines.iter().map(|line| fold(line) + "\r\n").collect()
+}
+
+/// Присылает файл .ics с событием в чат, где нажата кнопка. Файл может забрать любой участник чата.
+pub async fn handle_callback(bot: &Bot, q: &CallbackQuery, db: &Db, args: &str) -> ResponseResult<()> {
+    let event_id: i64 = args.parse().unwrap_or_default();
+    let Some(message) = q.message.as_ref() else {
+        bot.answer_callback_query(q.id.clone()).await?;
+        return Ok(());
+    };
+
+    let (telegram_id, chat_id) = (q.from.id.0 as i64, message.chat.id.0);
+    let (lang, found) = db.call(move |conn| {
+        let lang = settings::resolve(conn, telegram_id, chat_id)?.lang;
+        let Some(event) = get_event(conn, event_id)?.filter(|event| event.chat_id == chat_id) else {
+            return Ok((lang, None));
+        };
+        let instant = |time: &str| -> Result<Option<NaiveDateTime>, rusqlite::Error> {
+            Ok(event_instant(conn, event.user_id, time)?.and_then(|utc| NaiveDateTime::parse_from_str(&utc, UTC_FORMAT).ok()))
+        };
+        let start = instant(&event.event_time)?;
+        let end = match event.end_time.as_deref() {
+            Some(end_time) => instant(end_time)?,
+            None => None,
+        };
+        Ok((lang, start.map(|start| (event.text, start, end))))
+    }).await.map_err(DatabaseError)?;
+
+    let Some((text, start, end)) = found else {
+        bot.answer_callback_query(q.id.clone()).text(t(lang, "event_not_found")).await?;
+        return Ok(());
+    };
+
+    bot.answer_callback_query(q.id.clone()).await?;
+    let file = InputFile::memory(render(event_id, &text, start, end).into_bytes()).file_name(format!("event-{}.ics", event_id));
+    bot.send_document(message.chat.id, file).await?;
+    Ok(())
+}
+
+/// Как записано время в чужом календаре.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Zone {
+    Utc,
+    Named(Tz),
+    /// Без пояса, а также с поясом, которого нет в базе (например, из Outlook): по часам подписчика.
+    Floating,
+    AllDay,
+}
+
+/// Момент из `DTSTART`, `DTEND`, `EXDATE` или `RECURRENCE-ID`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Moment {
+    pub local: NaiveDateTime,
+    pub zone: Zone,
+}
+
+impl Moment {
+    fn parse(params: &str, value: &str) -> Option<Moment> {
+        let value = value.trim();
+        let tzid = params.split(';').find_map(|param| param.strip_prefix("TZID=")).map(|tzid| tzid.trim_matches('"'));
+        if let Ok(date) = NaiveDate::parse_from_str(value, "%Y%m%d") {
+            return Some(Moment { local: date.and_time(NaiveTime::MIN), zone: Zone::AllDay });
+        }
+        let (value, utc) = match value.strip_suffix('Z') {
+            Some(value) => (value, true),
+            None => (value, false),
+        };
+        let local = NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%S").ok()?;
+        let zone = match (utc, tzid.and_then(timezone::parse_zone)) {
+            (true, _) => Zone::Utc,
+            (false, Some(tz)) => Zone::Named(tz),
+            (false, None) => Zone::Floating,
+        };
+        Some(Moment { local, zone })
+    }
+
+    /// Момент в UTC; время без пояса и дни без времени — по часам подписчика `tz`.
+    pub fn to_utc(self, tz: Option<Tz>) -> NaiveDateTime {
+        match self.zone {
+            Zone::Utc => self.local,
+            Zone::Named(zone) => timezone::to_utc(self.local, Some(zone)),
+            Zone::Floating => timezone::to_utc(self.local, tz),
+            Zone::AllDay => {
+                let time = NaiveTime::from_hms_opt(ALL_DAY_TIME.0, ALL_DAY_TIME.1, 0).unwrap();
+                timezone::to_utc(self.local.date().and_time(time), tz)
+            }
+        }
+    }
+
+    /// Метка повтора для ключей экземпляров.
+    pub fn stamp(self) -> String {
+        self.local.format("%Y%m%dT%H%M%S").to_string()
+    }
+}
+
+/// Событие чужого календаря: свойства `VEVENT`, нужные для напоминаний.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RemoteEvent {
+    pub uid: String,
+    pub summary: String,
+    pub location: String,
+    pub start: Option<Moment>,
+    pub end: Option<Moment>,
+    pub rule: Option<String>,
+    pub exdates: Vec<Moment>,
+    /// Изменённый экземпляр повторяющегося события: какой повтор он заменяет.
+    pub recurrence_id: Option<Moment>,
+    pub sequence: i64,
+    pub cancelled: bool,
+    /// Когда событие последний раз меняли в источнике, UTC: `LAST-MODIFIED`, иначе `DTSTAMP`.
+    pub modified: Option<NaiveDateTime>,
+}
+
+#[derive(Debug, Default)]
+pub struct Calendar {
+    /// `X-WR-CALNAME`, если календарь его указал.
+    pub name: Option<String>,
+    pub events: Vec<RemoteEvent>,
+}
+
+fn unescape(text: &str) -> String {
+    let mut unescaped = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n' | 'N') => unescaped.push('\n'),
+            Some(other) => unescaped.push(other),
+            None => {}
+        }
+    }
+    unescaped
+}
+
+/// Имя, параметры и значение строки свойства; двоеточие в кавычках параметров значение не начинает.
+fn split_property(line: &str) -> Option<(String, &str, &str)> {
+    let mut quoted = false;
+    let colon = line.char_indices().find_map(|(index, c)| {
+        match c {
+            '"' => quoted = !quoted,
+            ':' if !quoted => return Some(index),
+            _ => {}
+        }
+        None
+    })?;
+    let (head, value) = (&line[..colon], &line[colon + 1..]);
+    let (name, params) = head.split_once(';').unwrap_or((head, ""));
+    Some((name.to_uppercase(), params, value))
+}
+
+/// Разбор календаря по RFC 5545. `None` — это не iCalendar. Вложенные `VALARM` и `VTIMEZONE`
+/// пропускаются: пояса берутся по `TZID` из базы поясов.
+pub fn parse_calendar(data: &str) -> Option<Calendar> {
+    let data = data.trim_start_matches('\u{feff}').replace("\r\n", "\n");
+    let mut lines: Vec<String> = Vec::new();
+    for line in data.split('\n') {
+        match (line.strip_prefix([' ', '\t']), lines.last_mut()) {
+            (Some(rest), Some(last)) => last.push_str(rest),
+            _ => lines.push(line.to_string()),
+        }
+    }
+    if !lines.iter().any(|line| line.trim().eq_ignore_ascii_case("BEGIN:VCALENDAR")) {
+        return None;
+    }
+
+    let mut calendar = Calendar::default();
+    let mut event: Option<RemoteEvent> = None;
+    let mut nested = 0;
+    for line in &lines {
+        let Some((name, params, value)) = split_property(line.trim_end()) else {
+            continue;
+        };
+        match (name.as_str(), value.to_uppercase().as_str()) {
+            ("BEGIN", "VEVENT") => event = Some(RemoteEvent::default()),
+            ("END", "VEVENT") => calendar.events.extend(event.take()),
+            ("BEGIN", _) if event.is_some() => nested += 1,
+            ("END", _) if nested > 0 => nested -= 1,
+            _ if nested > 0 => {}
+            ("X-WR-CALNAME", _) if event.is_none() => calendar.name = Some(unescape(value)).filter(|name| !name.trim().is_empty()),
+            _ => {
+                let Some(event) = event.as_mut() else {
+                    continue;
+                };
+                match name.as_str() {
+                    "UID" => event.uid = value.trim().to_string(),
+                    "SUMMARY" => event.summary = unescape(value).trim().to_string(),
+                    "LOCATION" => event.location = unescape(value).trim().to_string(),
+                    "DTSTART" => event.start = Moment::parse(params, value),
+                    "DTEND" => event.end = Moment::parse(params, value),
+                    "RRULE" => event.rule = Some(value.trim().to_uppercase()),
+                    "EXDATE" => event.exdates.extend(value.split(',').filter_map(|value| Moment::parse(params, value))),
+                    "RECURRENCE-ID" => event.recurrence_id = Moment::parse(params, value),
+                    "SEQUENCE" => event.sequence = value.trim().parse().unwrap_or_default(),
+                    "STATUS" => event.cancelled = value.trim().eq_ignore_ascii_case("CANCELLED"),
+                    "LAST-MODIFIED" => event.modified = Moment::parse(params, value).map(|moment| moment.to_utc(None)),
+                    "DTSTAMP" if event.modified.is_none() => event.modified = Moment::parse(params, value).map(|moment| moment.to_utc(None)),
+                    _ => {}
+                }
+            }
+        }
+    }
+    Some(calendar)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Frequency {
+    Daily,
+    Weekly,
+    Monthly,
+    Yearly,
+}
+
+/// Правило повтора. Поддерживаются `FREQ`, `INTERVAL`, `COUNT`, `UNTIL` и `BYDAY` у еженедельных;
+/// с остальными частями событие считается неповторяющимся.
+#[derive(Debug)]
+struct Rule {
+    frequency: Frequency,
+    interval: i64,
+    count: Option<usize>,
+    until: Option<Moment>,
+    days: Vec<Weekday>,
+}
+
+impl Rule {
+    fn parse(value: &str) -> Option<Rule> {
+        let mut rule = Rule { frequency: Frequency::Daily, interval: 1, count: None, until: None, days: Vec::new() };
+        let mut frequency = None;
+        for part in value.split(';') {
+            let (key, value) = part.split_once('=')?;
+            match key {
+                "FREQ" => {
+                    frequency = Some(match value {
+                        "DAILY" => Frequency::Daily,
+                        "WEEKLY" => Frequency::Weekly,
+                        "MONTHLY" => Frequency::Monthly,
+                        "YEARLY" => Frequency::Yearly,
+                        _ => return None,
+                    })
+                }
+                "INTERVAL" => rule.interval = value.parse().ok().filter(|interval| *interval > 0)?,
+                "COUNT" => rule.count = Some(value.parse().ok()?),
+                "UNTIL" => rule.until = Some(Moment::parse("", value)?),
+                "BYDAY" => {
+                    rule.days = value
+                        .split(',')
+                        .map(|day| match day {
+                            "MO" => Some(Weekday::Mon),
+                            "TU" => Some(Weekday::Tue),
+                            "WE" => Some(Weekday::Wed),
+                            "TH" => Some(Weekday::Thu),
+                            "FR" => Some(Weekday::Fri),
+                            "SA" => Some(Weekday::Sat),
+                            "SU" => Some(Weekday::Sun),
+                            _ => None,
+                        })
+                        .collect::<Option<Vec<_>>>()?;
+                    rule.days.sort_by_key(|day| day.num_days_from_monday());
+                }
+                "WKST" => {}
+                _ => return None,
+            }
+        }
+        rule.frequency = frequency?;
+        if !rule.days.is_empty() && rule.frequency != Frequency::Weekly {
+            return None;
+        }
+        Some(rule)
+    }
+
+    /// Кандидаты шага `step`: повторы, которые правило даёт в этот день, неделю, месяц или год.
+    fn candidates(&self, start: NaiveDateTime, step: i64) -> Vec<NaiveDateTime> {
+        let add_months = |months: i64| -> Option<NaiveDateTime> {
+            let total = start.year() as i64 * 12 + start.month0() as i64 + months;
+            let date = NaiveDate::from_ymd_opt((total / 12) as i32, (total % 12) as u32 + 1, start.day())?;
+            Some(date.and_time(start.time()))
+        };
+        let offset = step * self.interval;
+        match self.frequency {
+            Frequency::Daily => vec![start + Duration::days(offset)],
+            Frequency::Weekly if self.days.is_empty() => vec![start + Duration::weeks(offset)],
+            Frequency::Weekly => {
+                let monday = start.date() - Duration::days(start.weekday().num_days_from_monday().into()) + Duration::weeks(offset);
+                self.days
+                    .iter()
+                    .map(|day| (monday + Duration::days(day.num_days_from_monday().into())).and_time(start.time()))
+                    .filter(|candidate| *candidate >= start)
+                    .collect()
+            }
+            // 31-е число бывает не в каждом месяце: такие повторы пропускаются, как в RFC 5545
+            Frequency::Monthly => add_months(offset).into_iter().collect(),
+            Frequency::Yearly => add_months(offset * 12).into_iter().collect(),
+        }
+    }
+}
+
+/// Повторы события с началом в `from..=until` (UTC). Неповторяющееся событие — один повтор;
+/// правило, которое не удалось разобрать, тоже даёт только первый.
+pub fn occurrences(event: &RemoteEvent, from: NaiveDateTime, until: NaiveDateTime, tz: Option<Tz>) -> Vec<Moment> {
+    let Some(start) = event.start else {
+        return Vec::new();
+    };
+    let in_range = |moment: Moment| (from..=until).contains(&moment.to_utc(tz));
+    let Some(rule) = event.rule.as_deref().and_then(Rule::parse) else {
+        return if in_range(start) { vec![start] } else { Vec::new() };
+    };
+
+    let excluded = event.exdates.iter().map(|moment| moment.to_utc(tz)).collect::<Vec<_>>();
+    let mut found = Vec::new();
+    let mut generated = 0;
+    for step in 0..MAX_RULE_STEPS {
+        for local in rule.candidates(start.local, step) {
+            let moment = Moment { local, zone: start.zone };
+            let utc = moment.to_utc(tz);
+            if utc > until || rule.until.is_some_and(|last| utc > last.to_utc(tz)) {
+                return found;
+            }
+            generated += 1;
+            if rule.count.is_some_and(|count| generated > count) {
+                return found;
+            }
+            if in_range(moment) && !excluded.contains(&utc) {
+                found.push(moment);
+            }
+        }
+    }
+    found
+}
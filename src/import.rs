@@ -0,0 +1,399 @@
+use teloxide::prelude::*;
+use teloxide::net::Download;
+use teloxide::types::{Document, InlineKeyboardButton, InlineKeyboardMarkup};
+use chrono::{NaiveDate, NaiveDateTime, NaiveTime};
+use rusqlite::{Connection, params, OptionalExtension};
+use serde::Deserialize;
+
+use crate::chunks;
+use crate::i18n::{t, tf, Lang};
+use crate::settings::{self, Settings};
+use crate::timezone;
+use crate::{DatabaseError, Db, EVENT_TIME_FORMAT, crypto, ensure_user_exists, groups, in_transaction, insert_event, tenants};
+
+/// Файлы больше этого размера не скачиваются: выгрузки задач обычно весят десятки килобайт.
+const MAX_FILE_SIZE: u32 = 1024 * 1024;
+/// Сколько событий можно создать одним импортом.
+const MAX_ITEMS: usize = 500;
+/// Время для задач, у которых в выгрузке есть только дата.
+const DEFAULT_TIME: (u32, u32) = (9, 0);
+/// Сколько строк показывать в предпросмотре.
+const PREVIEW_LINES: usize = 15;
+
+/// Задача из выгрузки, готовая стать событием.
+#[derive(Debug)]
+struct ImportItem {
+    text: String,
+    event_time: NaiveDateTime,
+}
+
+/// Почему задача из выгрузки не будет импортирована.
+#[derive(Debug, Clone, Copy)]
+pub enum SkipReason {
+    NoTitle,
+    NoDate,
+    BadDate,
+    Completed,
+    Past,
+    Limit,
+}
+
+impl SkipReason {
+    fn key(self) -> &'static str {
+        match self {
+            SkipReason::NoTitle => "import_skip_no_title",
+            SkipReason::NoDate => "import_skip_no_date",
+            SkipReason::BadDate => "import_skip_bad_date",
+            SkipReason::Completed => "import_skip_completed",
+            SkipReason::Past => "import_skip_past",
+            SkipReason::Limit => "import_skip_limit",
+        }
+    }
+}
+
+/// Что будет создано и что пропущено; создаётся только после подтверждения, см. `offer`.
+#[derive(Debug, Default)]
+pub struct ImportReport {
+    items: Vec<ImportItem>,
+    skipped: Vec<(String, SkipReason)>,
+}
+
+impl ImportReport {
+    pub fn push(&mut self, title: &str, notes: &str, due: Result<NaiveDateTime, SkipReason>, now: NaiveDateTime) {
+        let title = title.trim();
+        let result = if title.is_empty() {
+            Err(SkipReason::NoTitle)
+        } else if self.items.len() >= MAX_ITEMS {
+            Err(SkipReason::Limit)
+        } else {
+            due.and_then(|time| if time > now { Ok(time) } else { Err(SkipReason::Past) })
+        };
+        match result {
+            Ok(event_time) => {
+                let notes = notes.trim();
+                let text = if notes.is_empty() { title.to_string() } else { format!("{}\n{}", title, notes) };
+                self.items.push(ImportItem { text, event_time });
+            }
+            Err(reason) => self.skipped.push((title.to_string(), reason)),
+        }
+    }
+}
+
+/// Формат выгрузки определяется по расширению файла.
+#[derive(Debug, Clone, Copy)]
+enum Source {
+    TodoistCsv,
+    GoogleTasksJson,
+}
+
+#[derive(Debug, Deserialize)]
+struct GoogleTaskLists {
+    #[serde(default)]
+    items: Vec<GoogleTaskList>,
+}
+
+#[derive(Debug, Deserialize)]
+struct GoogleTaskList {
+    #[serde(default)]
+    items: Vec<GoogleTask>,
+}
+
+#[derive(Debug, Deserialize)]
+struct GoogleTask {
+    title: Option<String>,
+    notes: Option<String>,
+    due: Option<String>,
+    status: Option<String>,
+    #[serde(default)]
+    deleted: bool,
+}
+
+pub fn init_tables(conn: &Connection) -> Result<(), rusqlite::Error> {
+    // Разобранный файл ждёт подтверждения; items — JSON-массив пар [текст, время]
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS import_drafts (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            user_id INTEGER NOT NULL,
+            chat_id INTEGER NOT NULL,
+            items TEXT NOT NULL,
+            created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            FOREIGN KEY(user_id) REFERENCES users(id) ON DELETE CASCADE
+        )",
+        [],
+    )?;
+    Ok(())
+}
+
+fn detect_source(file_name: &str) -> Option<Source> {
+    let file_name = file_name.to_lowercase();
+    if file_name.ends_with(".csv") {
+        Some(Source::TodoistCsv)
+    } else if file_name.ends_with(".json") {
+        Some(Source::GoogleTasksJson)
+    } else {
+        None
+    }
+}
+
+/// Разбор CSV по RFC 4180: поля в кавычках могут содержать запятые, переводы строк и `""`.
+fn parse_csv(data: &str) -> Vec<Vec<String>> {
+    let mut rows = Vec::new();
+    let mut row = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = data.trim_start_matches('\u{feff}').chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => row.push(std::mem::take(&mut field)),
+            '\r' if !quoted => {}
+            '\n' if !quoted => {
+                row.push(std::mem::take(&mut field));
+                rows.push(std::mem::take(&mut row));
+            }
+            _ => field.push(c),
+        }
+    }
+    if !field.is_empty() || !row.is_empty() {
+        row.push(field);
+        rows.push(row);
+    }
+    rows
+}
+
+/// Дата из выгрузки: ISO с временем или без, а также `Dec 18 2025 [10:00]`, как пишет Todoist.
+/// Повторяющиеся сроки вида `every monday` не поддерживаются.
+fn parse_due(value: &str) -> Result<NaiveDateTime, SkipReason> {
+    let value = value.trim();
+    if value.is_empty() {
+        return Err(SkipReason::NoDate);
+    }
+    let default_time = NaiveTime::from_hms_opt(DEFAULT_TIME.0, DEFAULT_TIME.1, 0).unwrap();
+    for format in ["%Y-%m-%d %H:%M", "%Y-%m-%dT%H:%M:%S", "%Y-%m-%dT%H:%M", "%b %d %Y %H:%M", "%d %b %Y %H:%M"] {
+        if let Ok(datetime) = NaiveDateTime::parse_from_str(value, format) {
+            return Ok(datetime);
+        }
+    }
+    for format in ["%Y-%m-%d", "%b %d %Y", "%d %b %Y"] {
+        if let Ok(date) = NaiveDate::parse_from_str(value, format) {
+            return Ok(date.and_time(default_time));
+        }
+    }
+    Err(SkipReason::BadDate)
+}
+
+/// Выгрузка Todoist: строки с `TYPE = task`, название в `CONTENT`, срок в `DATE`.
+fn parse_todoist(data: &str, now: NaiveDateTime) -> Option<ImportReport> {
+    let mut rows = parse_csv(data).into_iter();
+    let header = rows.next()?;
+    let column = |name: &str| header.iter().position(|title| title.trim().eq_ignore_ascii_case(name));
+    let (kind, content, description, date) = (column("TYPE")?, column("CONTENT")?, column("DESCRIPTION"), column("DATE")?);
+
+    let mut report = ImportReport::default();
+    for row in rows {
+        let field = |index: usize| row.get(index).map(String::as_str).unwrap_or_default();
+        if !field(kind).trim().eq_ignore_ascii_case("task") {
+            continue;
+        }
+        let notes = description.map(field).unwrap_or_default();
+        report.push(field(content), notes, parse_due(field(date)), now);
+    }
+    Some(report)
+}
+
+/// Выгрузка Google Tasks из Takeout. Срок там только дата (время всегда `00:00Z`), поэтому берётся день.
+fn parse_google_tasks(data: &str, now: NaiveDateTime) -> Option<ImportReport> {
+    let lists: GoogleTaskLists = serde_json::from_str(data.trim_start_matches('\u{feff}')).ok()?;
+
+    let mut report = ImportReport::default();
+    for task in lists.items.into_iter().flat_map(|list| list.items) {
+        if task.deleted {
+            continue;
+        }
+        let title = task.title.unwrap_or_default();
+        let due = if task.status.as_deref() == Some("completed") {
+            Err(SkipReason::Completed)
+        } else {
+            parse_due(task.due.as_deref().map(|due| due.get(..10).unwrap_or(due)).unwrap_or_default())
+        };
+        report.push(&title, task.notes.as_deref().unwrap_or_default(), due, now);
+    }
+    Some(report)
+}
+
+fn save_draft(conn: &Connection, user_id: i64, chat_id: i64, items: &[ImportItem]) -> Result<i64, rusqlite::Error> {
+    let items = items
+        .iter()
+        .map(|item| (item.text.as_str(), item.event_time.format(EVENT_TIME_FORMAT).to_string()))
+        .collect::<Vec<_>>();
+    let items = serde_json::to_string(&items).unwrap_or_default();
+    conn.execute(
+        "INSERT INTO import_drafts (user_id, chat_id, items) VALUES (?, ?, ?)",
+        params![user_id, chat_id, crypto::seal(&items)],
+    )?;
+    Ok(conn.last_insert_rowid())
+}
+
+fn draft_owner(conn: &Connection, draft_id: i64) -> Result<Option<i64>, rusqlite::Error> {
+    conn.query_row(
+        "SELECT u.telegram_id FROM import_drafts d JOIN users u ON d.user_id = u.id WHERE d.id = ?",
+        params![draft_id],
+        |row| row.get(0),
+    ).optional()
+}
+
+/// Забирает черновик и, если нужно, создаёт события. Возвращает число созданных событий.
+fn take_draft(conn: &Connection, tenant: &str, draft_id: i64, create: bool) -> Result<Option<usize>, rusqlite::Error> {
+    let draft: Option<(i64, i64, String)> = conn.query_row(
+        "SELECT user_id, chat_id, items FROM import_drafts WHERE id = ?",
+        params![draft_id],
+        |row| Ok((row.get(0)?, row.get(1)?, crypto::open(row.get(2)?))),
+    ).optional()?;
+    let Some((user_id, chat_id, items)) = draft else {
+        return Ok(None);
+    };
+
+    in_transaction(conn, |tx| {
+        tx.execute("DELETE FROM import_drafts WHERE id = ?", params![draft_id])?;
+        if !create {
+            return Ok(Some(0));
+        }
+        let items: Vec<(String, String)> = serde_json::from_str(&items).unwrap_or_default();
+        for (text, event_time) in &items {
+            insert_event(tx, tenant, user_id, chat_id, text, event_time)?;
+        }
+        Ok(Some(items.len()))
+    })
+}
+
+fn describe_report(lang: Lang, report: &ImportReport, settings: &Settings) -> String {
+    let mut lines = vec![tf(lang, "import_preview", &[("count", &report.items.len())])];
+    for item in report.items.iter().take(PREVIEW_LINES) {
+        let title = item.text.lines().next().unwrap_or_default();
+        lines.push(format!("{} {}", settings.format_datetime(item.event_time), title));
+    }
+    if report.items.len() > PREVIEW_LINES {
+        lines.push(tf(lang, "import_more", &[("count", &(report.items.len() - PREVIEW_LINES))]));
+    }
+
+    if !report.skipped.is_empty() {
+        lines.push(String::new());
+        lines.push(tf(lang, "import_skipped", &[("count", &report.skipped.len())]));
+        for (title, reason) in report.skipped.iter().take(PREVIEW_LINES) {
+            let title = if title.is_empty() { "—" } else { title.as_str() };
+            lines.push(format!("• {} — {}", title, t(lang, reason.key())));
+        }
+        if report.skipped.len() > PREVIEW_LINES {
+            lines.push(tf(lang, "import_more", &[("count", &(report.skipped.len() - PREVIEW_LINES))]));
+        }
+    }
+    lines.join("\n")
+}
+
+/// Загруженный в чат файл `.csv` (Todoist) или `.json` (Google Tasks): предпросмотр и кнопки подтверждения.
+pub async fn handle_document(bot: &Bot, msg: &Message, db: &Db, document: &Document, settings: &Settings) -> ResponseResult<()> {
+    let lang = settings.lang;
+    let Some(source) = document.file_name.as_deref().and_then(detect_source) else {
+        bot.send_message(msg.chat.id, t(lang, "import_unsupported")).await?;
+        return Ok(());
+    };
+    if !groups::can_manage_events(bot, msg, db).await? {
+        return groups::reply_not_allowed(bot, msg, db).await;
+    }
+    if document.file.size > MAX_FILE_SIZE {
+        bot.send_message(msg.chat.id, t(lang, "import_too_large")).await?;
+        return Ok(());
+    }
+
+    let file = bot.get_file(&document.file.id).await?;
+    let mut data = Vec::new();
+    if let Err(e) = bot.download_file(&file.path, &mut data).await {
+        log::error!("Failed to download import file: {}", e);
+        bot.send_message(msg.chat.id, t(lang, "import_failed")).await?;
+        return Ok(());
+    }
+
+    let now = timezone::now_in(settings.timezone);
+    let data = String::from_utf8_lossy(&data);
+    let report = match source {
+        Source::TodoistCsv => parse_todoist(&data, now),
+        Source::GoogleTasksJson => parse_google_tasks(&data, now),
+    };
+    let Some(report) = report else {
+        bot.send_message(msg.chat.id, t(lang, "import_failed")).await?;
+        return Ok(());
+    };
+    offer(bot, msg, db, report, settings).await
+}
+
+/// Предпросмотр и кнопки подтверждения: события создаст только тот, кто прислал сообщение.
+pub async fn offer(bot: &Bot, msg: &Message, db: &Db, report: ImportReport, settings: &Settings) -> ResponseResult<()> {
+    let lang = settings.lang;
+    let Some(user) = msg.from() else {
+        return Ok(());
+    };
+    let preview = describe_report(lang, &report, settings);
+    if report.items.is_empty() {
+        chunks::send(bot, msg.chat.id, format!("{}\n\n{}", preview, t(lang, "import_nothing"))).await?;
+        return Ok(());
+    }
+
+    let (telegram_id, username, tenant, chat_id) = (user.id.0 as i64, user.username.clone(), tenants::name_of(bot), msg.chat.id.0);
+    let draft_id = db.call(move |conn| {
+        let user_id = ensure_user_exists(conn, tenant, telegram_id, username)?;
+        save_draft(conn, user_id, chat_id, &report.items)
+    }).await.map_err(DatabaseError)?;
+
+    let keyboard = InlineKeyboardMarkup::new(vec![vec![
+        InlineKeyboardButton::callback(t(lang, "import_confirm"), format!("imp:create:{}", draft_id)),
+        InlineKeyboardButton::callback(t(lang, "import_cancel"), format!("imp:cancel:{}", draft_id)),
+    ]]);
+    chunks::send_last(bot, msg.chat.id, preview).await?.reply_markup(keyboard).await?;
+    Ok(())
+}
+
+pub async fn handle_callback(bot: &Bot, q: &CallbackQuery, db: &Db, args: &str) -> ResponseResult<()> {
+    let Some((action, draft_id)) = args.split_once(':') else {
+        bot.answer_callback_query(q.id.clone()).await?;
+        return Ok(());
+    };
+    let draft_id: i64 = draft_id.parse().unwrap_or_default();
+    let Some(message) = q.message.as_ref() else {
+        bot.answer_callback_query(q.id.clone()).await?;
+        return Ok(());
+    };
+
+    let (telegram_id, chat_id) = (q.from.id.0 as i64, message.chat.id.0);
+    let (create, tenant) = (action == "create", tenants::name_of(bot));
+    let (lang, outcome) = db.call(move |conn| {
+        let lang = settings::resolve(conn, telegram_id, chat_id)?.lang;
+        // Подтвердить импорт может только тот, кто загрузил файл
+        if draft_owner(conn, draft_id)?.is_some_and(|owner| owner != telegram_id) {
+            return Ok((lang, Err("event_not_yours")));
+        }
+        Ok((lang, take_draft(conn, tenant, draft_id, create)?.ok_or("import_expired")))
+    }).await.map_err(DatabaseError)?;
+
+    let count = match outcome {
+        Ok(count) => count,
+        Err(key) => {
+            bot.answer_callback_query(q.id.clone()).text(t(lang, key)).await?;
+            return Ok(());
+        }
+    };
+
+    bot.answer_callback_query(q.id.clone()).await?;
+    let response = if create {
+        log::info!("User {} imported {} events", telegram_id, count);
+        tf(lang, "import_done", &[("count", &count)])
+    } else {
+        t(lang, "import_cancelled")
+    };
+    bot.edit_message_text(message.chat.id, message.id, response).await?;
+    Ok(())
+}
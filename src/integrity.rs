@@ -0,0 +1,150 @@
+//! Проверка базы при запуске: испорченные строки событий чинятся, если время можно
+//! восстановить, а остальные переносятся в карантин, чтобы не ронять планировщик.
+
+use rusqlite::{Connection, params};
+use teloxide::prelude::*;
+
+use crate::i18n::tf;
+use crate::migrate;
+use crate::{Db, UTC_FORMAT, EVENT_TIME_FORMAT, config, in_transaction, parse_event_time, settings, tenants, timezone};
+
+/// Что сделала проверка.
+#[derive(Debug, Default, PartialEq)]
+pub struct Report {
+    pub repaired: usize,
+    /// Id событий в карантине и причина.
+    pub quarantined: Vec<(i64, &'static str)>,
+}
+
+impl Report {
+    pub fn is_clean(&self) -> bool {
+        self.repaired == 0 && self.quarantined.is_empty()
+    }
+}
+
+pub fn init_tables(conn: &Connection) -> Result<(), rusqlite::Error> {
+    // Строки событий, которые нельзя ни отправить, ни починить; текст остаётся зашифрованным
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS quarantined_events (
+            id INTEGER PRIMARY KEY,
+            user_id INTEGER,
+            chat_id INTEGER,
+            text TEXT,
+            event_time TEXT,
+            event_utc TEXT,
+            status TEXT,
+            reason TEXT NOT NULL,
+            quarantined_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP
+        )",
+        [],
+    )?;
+    Ok(())
+}
+
+fn quarantine(conn: &Connection, event_id: i64, reason: &'static str, report: &mut Report) -> Result<(), rusqlite::Error> {
+    conn.execute(
+        "INSERT OR REPLACE INTO quarantined_events (id, user_id, chat_id, text, event_time, event_utc, status, reason)
+         SELECT id, user_id, chat_id, text, event_time, event_utc, status, ? FROM events WHERE id = ?",
+        params![reason, event_id],
+    )?;
+    conn.execute("DELETE FROM events WHERE id = ?", params![event_id])?;
+    log::error!("Event {} quarantined: {}", event_id, reason);
+    report.quarantined.push((event_id, reason));
+    Ok(())
+}
+
+/// Проверяет события: старые `'done'` переводятся тем же путём, что `migrate-done`,
+/// нечитаемое время восстанавливается из `event_utc`, а события без владельца
+/// или без какого-либо времени уходят в карантин.
+pub fn check(conn: &Connection) -> Result<Report, rusqlite::Error> {
+    in_transaction(conn, |tx| {
+        let mut report = Report { repaired: migrate::migrate_done(tx, false)?, ..Report::default() };
+
+        let orphans = {
+            let mut stmt = tx.prepare("SELECT e.id FROM events e LEFT JOIN users u ON e.user_id = u.id WHERE u.id IS NULL")?;
+            let orphans = stmt.query_map([], |row| row.get::<_, i64>(0))?.collect::<Result<Vec<_>, _>>()?;
+            orphans
+        };
+        for event_id in orphans {
+            quarantine(tx, event_id, "orphaned", &mut report)?;
+        }
+
+        let events = {
+            let mut stmt = tx.prepare("SELECT id, user_id, event_time, event_utc FROM events")?;
+            let events = stmt
+                .query_map([], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, i64>(1)?, row.get::<_, Option<String>>(2)?, row.get::<_, Option<String>>(3)?)))?
+                .collect::<Result<Vec<_>, _>>()?;
+            events
+        };
+        for (event_id, user_id, event_time, event_utc) in events {
+            if event_time.as_deref().and_then(parse_event_time).is_some() {
+                continue;
+            }
+            let utc = event_utc.and_then(|utc| chrono::NaiveDateTime::parse_from_str(&utc, UTC_FORMAT).ok());
+            match utc {
+                Some(utc) => {
+                    let local = timezone::from_utc(utc, timezone::user_zone(tx, user_id)?);
+                    tx.execute(
+                        "UPDATE events SET event_time = ? WHERE id = ?",
+                        params![local.format(EVENT_TIME_FORMAT).to_string(), event_id],
+                    )?;
+                    log::info!("Event {} had unreadable time {:?}, restored from UTC", event_id, event_time);
+                    report.repaired += 1;
+                }
+                None => quarantine(tx, event_id, "unreadable time", &mut report)?,
+            }
+        }
+        Ok(report)
+    })
+}
+
+/// Сообщает администраторам, что проверка что-то нашла.
+pub async fn notify_admins(db: &Db, report: &Report) {
+    if report.is_clean() {
+        return;
+    }
+    let events = report.quarantined.iter().map(|(id, reason)| format!("#{} ({})", id, reason)).collect::<Vec<_>>().join(", ");
+    for admin_id in config::get().admin_ids.clone() {
+        let lang = db.call(move |conn| settings::resolve(conn, admin_id, admin_id)).await.unwrap_or_default().lang;
+        let text = tf(lang, "integrity_report", &[
+            ("repaired", &report.repaired),
+            ("quarantined", &report.quarantined.len()),
+            ("events", &if events.is_empty() { "—" } else { events.as_str() }),
+        ]);
+        if let Err(e) = tenants::bot(tenants::DEFAULT).send_message(ChatId(admin_id), text).await {
+            log::error!("Failed to send integrity report to admin {}: {}", admin_id, e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::tests::memory_db;
+    use crate::{ensure_user_exists, insert_event};
+
+    #[tokio::test]
+    async fn repairs_what_it_can_and_quarantines_the_rest() {
+        let db = memory_db();
+        let (ids, report, times, quarantined) = db.call(|conn| {
+            let user_id = ensure_user_exists(conn, tenants::DEFAULT, 42, None)?;
+            let event = |time| insert_event(conn, tenants::DEFAULT, user_id, 42, "Событие", &format!("01.01.2030 {}", time));
+            let (fine, restorable, lost, orphan) = (event("10:00")?, event("11:00")?, event("12:00")?, event("13:00")?);
+            conn.execute("UPDATE events SET event_time = 'завтра' WHERE id IN (?, ?)", params![restorable, lost])?;
+            conn.execute("UPDATE events SET event_utc = NULL WHERE id = ?", params![lost])?;
+            conn.pragma_update(None, "foreign_keys", false)?;
+            conn.execute("UPDATE events SET user_id = 999 WHERE id = ?", params![orphan])?;
+            conn.pragma_update(None, "foreign_keys", true)?;
+
+            let report = check(conn)?;
+            let mut stmt = conn.prepare("SELECT id, event_time FROM events ORDER BY id")?;
+            let times = stmt.query_map([], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?)))?.collect::<Result<Vec<_>, _>>()?;
+            let quarantined: i64 = conn.query_row("SELECT COUNT(*) FROM quarantined_events", [], |row| row.get(0))?;
+            Ok(([fine, restorable, lost, orphan], report, times, quarantined))
+        }).await.unwrap();
+        let [fine, restorable, lost, orphan] = ids;
+        assert_eq!(report, Report { repaired: 1, quarantined: vec![(orphan, "orphaned"), (lost, "unreadable time")] });
+        assert_eq!(times, vec![(fine, "01.01.2030 10:00".to_string()), (restorable, "01.01.2030 11:00".to_string())]);
+        assert_eq!(quarantined, 2);
+    }
+}
@@ -0,0 +1,3 @@
+//! Части бота, которыми пользуются и другие программы: веб-приложение и API.
+
+pub mod parse;
@@ -1,12 +1,112 @@
-use teloxide::{prelude::*, utils::command::BotCommands};
+use teloxide::prelude::*;
 use teloxide::RequestError;
+use teloxide::dispatching::UpdateHandler;
 use dotenv::dotenv;
 use std::env;
-use regex::Regex;
-use chrono::{NaiveDateTime, Datelike, Timelike};
-use rusqlite::{Connection, params, OptionalExtension};
 use std::sync::Arc;
-use tokio::sync::Mutex;
+use chrono::{NaiveDate, NaiveDateTime, NaiveTime};
+use rusqlite::{Connection, params, OptionalExtension};
+use reventor::parse::{self, DATE_PATTERN, Recurrence, TIME_PATTERN};
+
+mod accessibility;
+mod admin;
+mod agenda;
+mod anniversaries;
+mod audit;
+mod backup;
+mod blocklist;
+mod busy;
+mod calendar;
+mod categories;
+mod chunks;
+mod clock;
+mod checklist;
+mod cleanup;
+mod config;
+mod contacts;
+mod crypto;
+mod deadlines;
+mod digest;
+mod duplicates;
+mod edit;
+mod feed;
+mod feedback;
+mod followups;
+mod forwarded;
+mod geofence;
+mod groups;
+mod guests;
+mod habits;
+mod health;
+mod help;
+mod holidays;
+mod hooks;
+mod http;
+mod humanize;
+mod i18n;
+mod integrity;
+mod ics;
+mod import;
+mod maintenance;
+mod meds;
+mod metrics;
+mod migrate;
+mod mqtt;
+mod notifications;
+mod notifiers;
+mod onboarding;
+mod outbox;
+mod overdue;
+mod overlaps;
+mod poll;
+mod pomodoro;
+mod privacy;
+mod query;
+mod referrals;
+mod reactions;
+mod render;
+mod repeats;
+mod restore;
+mod retention;
+mod s3;
+mod settings;
+mod share;
+mod shards;
+mod simulate;
+mod stats;
+mod stars;
+mod storage;
+mod subscriptions;
+mod supervisor;
+mod sync;
+mod tasks;
+mod templates;
+mod tenants;
+#[cfg(test)]
+mod testing;
+mod timers;
+mod timezone;
+mod tokens;
+mod travel;
+mod updates;
+mod voice;
+mod weather;
+
+use clock::Clock;
+use i18n::tf;
+use settings::{DateFormat, Settings};
+use shards::Shard;
+use storage::Db;
+
+/// Файл базы рядом с ботом.
+const DB_PATH: &str = "reventor.db";
+/// Версия схемы в `PRAGMA user_version`; увеличивается вместе с миграциями в `init_db`.
+const SCHEMA_VERSION: i32 = 1;
+
+/// Формат хранения времени события в БД.
+const EVENT_TIME_FORMAT: &str = parse::STORAGE_FORMAT;
+/// Формат `events.event_utc`: сравнение строк совпадает с хронологическим порядком.
+const UTC_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
 
 #[derive(Debug)]
 struct DatabaseError(rusqlite::Error);
@@ -18,23 +118,39 @@ impl From<DatabaseError> for RequestError {
 }
 
 #[derive(Debug)]
-struct Event {
+struct UserEvent {
+    id: i64,
     text: String,
-    time: String,
-    date: Option<String>,
+    event_time: String,
+    end_time: Option<String>,
+    priority: i64,
+    starred: bool,
+    icon: Option<String>,
 }
 
 #[derive(Debug)]
-struct UserEvent {
+struct StoredEvent {
+    id: i64,
+    tenant: String,
+    user_id: i64,
+    owner_id: i64,
+    chat_id: i64,
     text: String,
     event_time: String,
+    end_time: Option<String>,
 }
 
 #[derive(Debug)]
 struct NotificationEvent {
-    telegram_id: i64,
+    id: i64,
+    tenant: String,
+    owner_id: i64,
+    chat_id: i64,
     text: String,
     event_time: String,
+    event_utc: String,
+    starred: bool,
+    icon: Option<String>,
 }
 
 fn init_db(conn: &Connection) -> Result<(), rusqlite::Error> {
@@ -55,95 +171,365 @@ fn init_db(conn: &Connection) -> Result<(), rusqlite::Error> {
             text TEXT NOT NULL,
             event_time DATETIME NOT NULL,
             created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
-            FOREIGN KEY(user_id) REFERENCES users(id)
+            FOREIGN KEY(user_id) REFERENCES users(id) ON DELETE CASCADE
+        )",
+        [],
+    )?;
+
+    // Чат, в который придёт напоминание (личка или группа)
+    add_column_if_missing(conn, "events", "chat_id", "INTEGER")?;
+    // pending - ждёт напоминания, sent - напоминание отправлено, done - выполнено
+    add_column_if_missing(conn, "events", "status", "TEXT NOT NULL DEFAULT 'pending'")?;
+    // 0 - обычное событие, 1..3 - число восклицательных знаков в начале текста
+    add_column_if_missing(conn, "events", "priority", "INTEGER NOT NULL DEFAULT 0")?;
+    // Момент напоминания в UTC; event_time остаётся временем на часах владельца
+    add_column_if_missing(conn, "events", "event_utc", "TEXT")?;
+    // Окончание события в том же формате, что event_time; NULL — длительность не указана
+    add_column_if_missing(conn, "events", "end_time", "TEXT")?;
+    // Последняя правка текста или времени, UTC; по ней синхронизация решает, чья версия новее
+    add_column_if_missing(conn, "events", "updated_at", "DATETIME")?;
+    // Расписание `@cron` серии; хранится только у ближайшего события серии, см. `schedule_next_occurrence`
+    add_column_if_missing(conn, "events", "cron", "TEXT")?;
+
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS polls (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            poll_id TEXT NOT NULL UNIQUE,
+            chat_id INTEGER NOT NULL,
+            message_id INTEGER NOT NULL,
+            user_id INTEGER NOT NULL,
+            text TEXT NOT NULL,
+            options TEXT NOT NULL,
+            deadline DATETIME NOT NULL,
+            closed INTEGER NOT NULL DEFAULT 0,
+            created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            FOREIGN KEY(user_id) REFERENCES users(id) ON DELETE CASCADE
         )",
         [],
     )?;
+    // Срок голосования в UTC; у старых записей NULL, их `deadline` — время сервера
+    add_column_if_missing(conn, "polls", "deadline_utc", "DATETIME")?;
 
+    // Сообщения в чате, относящиеся к событию (например, подтверждение создания)
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS event_messages (
+            chat_id INTEGER NOT NULL,
+            message_id INTEGER NOT NULL,
+            event_id INTEGER NOT NULL,
+            kind TEXT NOT NULL,
+            PRIMARY KEY(chat_id, message_id),
+            FOREIGN KEY(event_id) REFERENCES events(id) ON DELETE CASCADE
+        )",
+        [],
+    )?;
+
+    anniversaries::init_tables(conn)?;
+    audit::init_tables(conn)?;
+    blocklist::init_tables(conn)?;
+    categories::init_tables(conn)?;
+    checklist::init_tables(conn)?;
+    cleanup::init_tables(conn)?;
+    digest::init_tables(conn)?;
+    contacts::init_tables(conn)?;
+    deadlines::init_tables(conn)?;
+    duplicates::init_tables(conn)?;
+    feedback::init_tables(conn)?;
+    geofence::init_tables(conn)?;
+    groups::init_tables(conn)?;
+    guests::init_tables(conn)?;
+    habits::init_tables(conn)?;
+    import::init_tables(conn)?;
+    integrity::init_tables(conn)?;
+    maintenance::init_tables(conn)?;
+    meds::init_tables(conn)?;
+    metrics::init_tables(conn)?;
+    notifications::init_tables(conn)?;
+    outbox::init_tables(conn)?;
+    shards::init_tables(conn)?;
+    notifiers::init_tables(conn)?;
+    onboarding::init_tables(conn)?;
+    privacy::init_tables(conn)?;
+    referrals::init_tables(conn)?;
+    repeats::init_tables(conn)?;
+    settings::init_tables(conn)?;
+    share::init_tables(conn)?;
+    stars::init_tables(conn)?;
+    stats::init_tables(conn)?;
+    subscriptions::init_tables(conn)?;
+    sync::init_tables(conn)?;
+    tasks::init_tables(conn)?;
+    templates::init_tables(conn)?;
+    tenants::init_tables(conn)?;
+    timezone::init_tables(conn)?;
+    tokens::init_tables(conn)?;
+    travel::init_tables(conn)?;
+    updates::init_tables(conn)?;
+    voice::init_tables(conn)?;
+    weather::init_tables(conn)?;
+
+    conn.pragma_update(None, "user_version", SCHEMA_VERSION)?;
     Ok(())
 }
 
-fn ensure_user_exists(conn: &Connection, telegram_id: i64, username: Option<String>) -> Result<i64, rusqlite::Error> {
-    let existing_id: Option<i64> = conn.query_row(
-        "SELECT id FROM users WHERE telegram_id = ?",
-        params![telegram_id],
-        |row| row.get(0),
-    ).optional()?;
+fn add_column_if_missing(conn: &Connection, table: &str, column: &str, definition: &str) -> Result<(), rusqlite::Error> {
+    let mut stmt = conn.prepare(&format!("PRAGMA table_info({})", table))?;
+    let exists = stmt
+        .query_map([], |row| row.get::<_, String>(1))?
+        .collect::<Result<Vec<_>, _>>()?
+        .iter()
+        .any(|name| name == column);
 
-    match existing_id {
-        Some(id) => Ok(id),
-        None => {
-            conn.execute(
-                "INSERT INTO users (telegram_id, username) VALUES (?, ?)",
-                params![telegram_id, username],
-            )?;
-            Ok(conn.last_insert_rowid())
-        }
+    if !exists {
+        conn.execute(&format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, definition), [])?;
     }
+    Ok(())
 }
 
-fn save_event(conn: &Connection, user_id: i64, event: &Event) -> Result<(), rusqlite::Error> {
-    let event_time = match &event.date {
-        Some(date) => {
-            if date.matches('.').count() == 1 {
-                let current_year = chrono::Local::now().year();
-                format!("{}.{} {}", date, current_year, event.time)
-            } else {
-                format!("{} {}", date, event.time)
-            }
-        },
-        None => {
-            let today = chrono::Local::now().format("%d.%m.%Y").to_string();
-            format!("{} {}", today, event.time)
-        }
+/// Создаёт пользователя или обновляет его username одной командой,
+/// чтобы два одновременных сообщения не создали двух пользователей.
+/// `tenant` — бот, через которого пользователь пишет сейчас.
+fn ensure_user_exists(conn: &Connection, tenant: &str, telegram_id: i64, username: Option<String>) -> Result<i64, rusqlite::Error> {
+    conn.query_row(
+        "INSERT INTO users (telegram_id, username, tenant) VALUES (?, ?, ?)
+         ON CONFLICT(telegram_id) DO UPDATE SET username = COALESCE(excluded.username, users.username),
+            tenant = excluded.tenant
+         RETURNING id",
+        params![telegram_id, username, tenant],
+        |row| row.get(0),
+    )
+}
+
+/// Выполняет несколько записей атомарно: при ошибке все изменения откатываются.
+fn in_transaction<T>(
+    conn: &Connection,
+    f: impl FnOnce(&Connection) -> Result<T, rusqlite::Error>,
+) -> Result<T, rusqlite::Error> {
+    let tx = conn.unchecked_transaction()?;
+    let result = f(&tx)?;
+    tx.commit()?;
+    Ok(result)
+}
+
+/// Дата во вводе; порядок дня и месяца в коротких формах задаётся настройкой пользователя.
+fn resolve_date(value: &str, today: NaiveDate, format: DateFormat) -> Option<NaiveDate> {
+    parse::date(value, today, format.order())
+}
+
+/// Дата без года дополняется текущим годом, без даты — сегодняшним днём пользователя.
+fn resolve_event_time(clock: &dyn Clock, date: Option<&str>, time: &str, settings: &Settings) -> Option<NaiveDateTime> {
+    let today = clock.now_in(settings.timezone).date();
+    let date = match date {
+        Some(date) => resolve_date(date, today, settings.date_format)?,
+        None => today,
     };
+    println!("Parsing datetime: {:?} {}", date, time);
 
-    println!("Parsing datetime: {}", event_time);
+    Some(date.and_time(parse::time(time)?))
+}
+
+fn event_priority(text: &str) -> i64 {
+    parse::priority(text).into()
+}
 
-    // Преобразуем в нужный формат без секунд
-    let event_datetime = NaiveDateTime::parse_from_str(&format!("{}:00", event_time), "%d.%m.%Y %H:%M:%S")
-        .unwrap_or_else(|_| panic!("Failed to parse date: {}", event_time))
-        .format("%d.%m.%Y %H:%M")
-        .to_string();
+/// Момент в UTC для времени события на часах его владельца.
+fn event_instant(conn: &Connection, user_id: i64, event_time: &str) -> Result<Option<String>, rusqlite::Error> {
+    let tz = timezone::user_zone(conn, user_id)?;
+    Ok(parse_event_time(event_time).map(|local| timezone::to_utc(local, tz).format(UTC_FORMAT).to_string()))
+}
 
+/// `tenant` — бот, через которого событие создано; он же доставит напоминание.
+fn insert_event(conn: &Connection, tenant: &str, user_id: i64, chat_id: i64, text: &str, event_time: &str) -> Result<i64, rusqlite::Error> {
+    let event_utc = event_instant(conn, user_id, event_time)?;
     conn.execute(
-        "INSERT INTO events (user_id, text, event_time) VALUES (?, ?, ?)",
-        params![user_id, event.text, event_datetime],
+        "INSERT INTO events (tenant, user_id, chat_id, text, event_time, event_utc, priority) VALUES (?, ?, ?, ?, ?, ?, ?)",
+        params![tenant, user_id, chat_id, crypto::seal(text), event_time, event_utc, event_priority(text)],
     )?;
+    let event_id = conn.last_insert_rowid();
+    checklist::save_items(conn, event_id, text)?;
+    audit::record(conn, event_id, Some(user_id), "create", None, Some((event_time, text)))?;
+
+    Ok(event_id)
+}
 
+fn get_event(conn: &Connection, event_id: i64) -> Result<Option<StoredEvent>, rusqlite::Error> {
+    conn.query_row(
+        "SELECT e.id, e.user_id, u.telegram_id, COALESCE(e.chat_id, u.telegram_id), e.text, e.event_time, e.tenant, e.end_time
+         FROM events e
+         JOIN users u ON e.user_id = u.id
+         WHERE e.id = ?",
+        params![event_id],
+        |row| {
+            Ok(StoredEvent {
+                id: row.get(0)?,
+                tenant: row.get(6)?,
+                user_id: row.get(1)?,
+                owner_id: row.get(2)?,
+                chat_id: row.get(3)?,
+                text: crypto::open(row.get(4)?),
+                event_time: row.get(5)?,
+                end_time: row.get(7)?,
+            })
+        },
+    ).optional()
+}
+
+fn set_event_end(conn: &Connection, event_id: i64, end_time: Option<&str>) -> Result<(), rusqlite::Error> {
+    conn.execute("UPDATE events SET end_time = ? WHERE id = ?", params![end_time, event_id])?;
     Ok(())
 }
 
-fn parse_event(text: &str) -> Option<Event> {
-    let re = Regex::new(r"@(?:(\d{2}\.\d{2}(?:\.\d{4})?)\s+)?(\d{2}:\d{2})").unwrap();
-    
-    if let Some(captures) = re.captures(text) {
-        let time = captures.get(2).unwrap().as_str().to_string();
-        let date = captures.get(1).map(|m| m.as_str().to_string());
-        
-        Some(Event {
-            text: text.to_string(),
-            time,
-            date,
-        })
-    } else {
-        None
+/// Окончание события, начатого в `start`, с той же длительностью; `None`, если длительность не указана.
+fn shifted_end(event: &StoredEvent, start: NaiveDateTime) -> Option<String> {
+    let duration = event.end_time.as_deref().and_then(parse_event_time)
+        .zip(parse_event_time(&event.event_time))
+        .map(|(end, old_start)| end - old_start);
+    duration.map(|duration| (start + duration).format(EVENT_TIME_FORMAT).to_string())
+}
+
+/// Переносит событие на новое время начала, сохраняя длительность. `action` — запись в истории,
+/// см. `change_event`. Вызывается внутри транзакции.
+fn reschedule_event(conn: &Connection, event: &StoredEvent, start: NaiveDateTime, action: &str) -> Result<(), rusqlite::Error> {
+    let end_time = shifted_end(event, start);
+    change_event(conn, event.id, &event.text, &start.format(EVENT_TIME_FORMAT).to_string(), action)?;
+    set_event_end(conn, event.id, end_time.as_deref())
+}
+
+/// Откладывает событие (😴 или кнопка в списке пропущенных): в истории и статистике это
+/// отдельное действие, а не правка. Вызывается внутри транзакции.
+fn snooze_event(conn: &Connection, event: &StoredEvent, start: NaiveDateTime) -> Result<(), rusqlite::Error> {
+    reschedule_event(conn, event, start, "snooze")?;
+    stats::record_snooze(conn, event.id)
+}
+
+/// Запоминает расписание события. Хранится только `@cron`: остальные повторы пока не переставляются.
+fn set_event_recurrence(conn: &Connection, event_id: i64, recurrence: Option<&Recurrence>) -> Result<(), rusqlite::Error> {
+    let cron = match recurrence {
+        Some(Recurrence::Cron(expression)) => Some(expression.as_str()),
+        _ => None,
+    };
+    conn.execute("UPDATE events SET cron = ? WHERE id = ?", params![cron, event_id])?;
+    Ok(())
+}
+
+/// Ставит следующее событие серии `@cron`, когда напоминание о текущем уходит в очередь.
+/// Расписание переходит к новому событию: повторное напоминание о старом (после 😴) серию
+/// не размножит, а удаление предстоящего события её остановит. Время, пропущенное, пока бот
+/// не работал, не наверстывается. Вызывается внутри транзакции.
+fn schedule_next_occurrence(conn: &Connection, event_id: i64, now: NaiveDateTime) -> Result<Option<i64>, rusqlite::Error> {
+    let series: Option<(Option<String>, bool)> = conn.query_row(
+        "SELECT cron, deadline FROM events WHERE id = ?",
+        params![event_id],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    ).optional()?;
+    let (Some((Some(cron), deadline)), Some(event)) = (series, get_event(conn, event_id)?) else {
+        return Ok(None);
+    };
+    let Some(start) = parse_event_time(&event.event_time) else {
+        return Ok(None);
+    };
+    let local_now = timezone::from_utc(now, timezone::user_zone(conn, event.user_id)?);
+    let recurrence = Recurrence::Cron(cron);
+    let Some(next) = recurrence.next(start.max(local_now)) else {
+        return Ok(None);
+    };
+    let next_id = insert_event(conn, &event.tenant, event.user_id, event.chat_id, &event.text, &parse::store(next))?;
+    let end_time = event.end_time.as_deref().and_then(parse_event_time).map(|end| parse::store(next + (end - start)));
+    set_event_end(conn, next_id, end_time.as_deref())?;
+    set_event_recurrence(conn, next_id, Some(&recurrence))?;
+    set_event_recurrence(conn, event_id, None)?;
+    if deadline {
+        deadlines::set_deadline(conn, next_id, true, now)?;
     }
+    Ok(Some(next_id))
+}
+
+/// Время события для списков: «14:00» или «14:00–15:30 (1 час 30 минут)».
+fn format_event_span(settings: &Settings, start: NaiveDateTime, end_time: Option<&str>) -> String {
+    let start_str = settings.format_time(start.time());
+    match end_time.and_then(parse_event_time) {
+        Some(end) if end > start => format!(
+            "{}–{} ({})",
+            start_str,
+            settings.format_time(end.time()),
+            humanize::duration(settings.lang, end - start)
+        ),
+        _ => start_str,
+    }
+}
+
+/// Обновляет событие; при переносе на другое время напоминание снова ставится в очередь.
+/// Править событие может только владелец, поэтому он же записывается автором правки.
+fn update_event(conn: &Connection, event_id: i64, text: &str, event_time: &str) -> Result<(), rusqlite::Error> {
+    change_event(conn, event_id, text, event_time, "edit")
+}
+
+/// `update_event` с действием для истории: `edit` или `snooze`.
+fn change_event(conn: &Connection, event_id: i64, text: &str, event_time: &str, action: &str) -> Result<(), rusqlite::Error> {
+    let (user_id, old_text, old_time): (i64, String, String) = conn.query_row(
+        "SELECT user_id, text, event_time FROM events WHERE id = ?",
+        params![event_id],
+        |row| Ok((row.get(0)?, crypto::open(row.get(1)?), row.get(2)?)),
+    )?;
+    let event_utc = event_instant(conn, user_id, event_time)?;
+    conn.execute(
+        "UPDATE events SET text = ?,
+            status = CASE WHEN event_time = ? THEN status ELSE 'pending' END,
+            event_time = ?,
+            event_utc = ?,
+            priority = ?,
+            updated_at = CURRENT_TIMESTAMP
+         WHERE id = ?",
+        params![crypto::seal(text), event_time, event_time, event_utc, event_priority(text), event_id],
+    )?;
+    checklist::save_items(conn, event_id, text)?;
+    audit::record(conn, event_id, Some(user_id), action, Some((&old_time, &old_text)), Some((event_time, text)))?;
+    Ok(())
+}
+
+fn link_event_message(conn: &Connection, chat_id: i64, message_id: i32, event_id: i64, kind: &str) -> Result<(), rusqlite::Error> {
+    conn.execute(
+        "INSERT OR REPLACE INTO event_messages (chat_id, message_id, event_id, kind) VALUES (?, ?, ?, ?)",
+        params![chat_id, message_id, event_id, kind],
+    )?;
+    Ok(())
+}
+
+fn find_event_by_message(conn: &Connection, chat_id: i64, message_id: i32, kind: &str) -> Result<Option<i64>, rusqlite::Error> {
+    conn.query_row(
+        "SELECT event_id FROM event_messages WHERE chat_id = ? AND message_id = ? AND kind = ?",
+        params![chat_id, message_id, kind],
+        |row| row.get(0),
+    ).optional()
+}
+
+fn parse_time_input(value: &str) -> Option<NaiveTime> {
+    parse::time(value)
+}
+
+/// Время из ввода в виде `ЧЧ:ММ`; нераспознанное значение остаётся как есть,
+/// чтобы пользователь увидел его в сообщении об ошибке.
+fn normalize_time_input(value: &str) -> String {
+    parse_time_input(value).map_or_else(|| value.to_string(), |time| time.format("%H:%M").to_string())
 }
 
 fn get_user_events(conn: &Connection, telegram_id: i64) -> Result<Vec<UserEvent>, rusqlite::Error> {
     let mut stmt = conn.prepare(
-        "SELECT e.text, e.event_time 
-         FROM events e 
+        "SELECT e.id, e.text, e.event_time, e.priority, e.end_time, e.starred, e.icon
+         FROM events e
          JOIN users u ON e.user_id = u.id 
-         WHERE u.telegram_id = ? 
+         WHERE u.telegram_id = ? AND e.status = 'pending' AND e.event_time != 'done'
          ORDER BY e.event_time"
     )?;
 
     let events = stmt.query_map(params![telegram_id], |row| {
         Ok(UserEvent {
-            text: row.get(0)?,
-            event_time: row.get(1)?,
+            id: row.get(0)?,
+            text: crypto::open(row.get(1)?),
+            event_time: row.get(2)?,
+            end_time: row.get(4)?,
+            priority: row.get(3)?,
+            starred: row.get(5)?,
+            icon: row.get(6)?,
         })
     })?
     .collect::<Result<Vec<_>, _>>()?;
@@ -151,26 +537,36 @@ fn get_user_events(conn: &Connection, telegram_id: i64) -> Result<Vec<UserEvent>
     Ok(events)
 }
 
-fn get_due_events(conn: &Connection) -> Result<Vec<NotificationEvent>, rusqlite::Error> {
-    let now = chrono::Local::now().format("%d.%m.%Y %H:%M").to_string();
-    println!("Checking events at: {}", now);
+fn parse_event_time(event_time: &str) -> Option<NaiveDateTime> {
+    parse::load(event_time)
+}
 
-    let mut stmt = conn.prepare(
-        "SELECT u.telegram_id, e.text, e.event_time 
+/// Все неотправленные события, время которых уже наступило. Отложенные
+/// из-за тихих часов события остаются в выборке, пока их не отправят.
+fn get_due_events(conn: &Connection, now: NaiveDateTime, shard: Shard) -> Result<Vec<NotificationEvent>, rusqlite::Error> {
+    let now = now.format(UTC_FORMAT).to_string();
+    println!("Checking events at: {} UTC", now);
+
+    let mut stmt = conn.prepare(&format!(
+        "SELECT e.id, u.telegram_id, COALESCE(e.chat_id, u.telegram_id), e.text, e.event_time, e.event_utc, e.tenant, e.starred, e.icon
          FROM events e 
          JOIN users u ON e.user_id = u.id 
-         WHERE e.event_time = ?"
-    )?;
+         WHERE e.status = 'pending' AND e.event_utc <= ? AND {} AND {}",
+        blocklist::NOT_BLOCKED,
+        shard.condition()
+    ))?;
 
     let events = stmt.query_map(params![now], |row| {
-        let event_time: String = row.get(2)?;
-        let telegram_id: i64 = row.get(0)?;
-        println!("Found matching event: time={}, telegram_id={}", event_time, telegram_id);
-        
         Ok(NotificationEvent {
-            telegram_id,
-            text: row.get(1)?,
-            event_time,
+            id: row.get(0)?,
+            tenant: row.get(6)?,
+            owner_id: row.get(1)?,
+            chat_id: row.get(2)?,
+            text: crypto::open(row.get(3)?),
+            event_time: row.get(4)?,
+            event_utc: row.get(5)?,
+            starred: row.get(7)?,
+            icon: row.get(8)?,
         })
     })?
     .collect::<Result<Vec<_>, _>>()?;
@@ -183,106 +579,542 @@ fn get_due_events(conn: &Connection) -> Result<Vec<NotificationEvent>, rusqlite:
     Ok(events)
 }
 
-fn mark_event_sent(conn: &Connection, telegram_id: i64, event_time: &str) -> Result<(), rusqlite::Error> {
-    conn.execute(
-        "UPDATE events SET event_time = 'done' 
-         WHERE user_id IN (SELECT id FROM users WHERE telegram_id = ?) 
-         AND event_time = ?",
-        params![telegram_id, event_time],
-    )?;
+/// Достаёт аргументы команды, учитывая форму `/cmd@botname` в группах.
+fn command_args<'a>(text: &'a str, command: &str) -> Option<&'a str> {
+    let rest = text.strip_prefix(command)?;
+    let rest = match rest.strip_prefix('@') {
+        Some(mention) => mention.split_once(char::is_whitespace).map_or("", |(_, args)| args),
+        None if rest.is_empty() || rest.starts_with(char::is_whitespace) => rest,
+        None => return None,
+    };
+    Some(rest.trim())
+}
+
+/// Всё сообщение обрабатывается в режиме вывода отправителя, см. `accessibility`.
+/// Обновление отмечается обработанным только после успешной обработки, см. `updates::mark_processed`.
+async fn handle_message(bot: Bot, msg: Message, db: Db, sessions: pomodoro::Sessions, update: Update) -> ResponseResult<()> {
+    let plain = settings::for_message(&db, &msg).await.map_err(DatabaseError)?;
+    let tenant = tenants::name_of(&bot);
+    accessibility::scope(accessibility::of(&plain), route_message(bot, msg, db.clone(), sessions)).await?;
+    updates::mark_processed(&db, tenant, update.id).await;
+    Ok(())
+}
+
+async fn route_message(bot: Bot, msg: Message, db: Db, sessions: pomodoro::Sessions) -> ResponseResult<()> {
+    let sender = msg.from().map(|user| user.id.0 as i64);
+    if let Some(telegram_id) = sender {
+        if blocklist::is_blocked(&db, telegram_id).await {
+            return Ok(());
+        }
+    }
+    if maintenance::intercept(&bot, &msg, &db).await? {
+        return Ok(());
+    }
+    if let Some(location) = msg.location() {
+        metrics::track(&db, sender, "location").await;
+        let lang = settings::for_message(&db, &msg).await.map_err(DatabaseError)?.lang;
+        if !weather::attach_to_event(&bot, &msg, &db, location, lang).await? {
+            timezone::handle_location(&bot, &msg, &db, location, lang).await?;
+        }
+        return Ok(());
+    }
+
+    if let Some(document) = msg.document() {
+        metrics::track(&db, sender, "document").await;
+        let settings = settings::for_message(&db, &msg).await.map_err(DatabaseError)?;
+        import::handle_document(&bot, &msg, &db, document, &settings).await?;
+        return Ok(());
+    }
+
+    if let Some(text) = msg.text() {
+        let settings = settings::for_message(&db, &msg).await.map_err(DatabaseError)?;
+        let lang = settings.lang;
+        if text.starts_with('/') {
+            cleanup::schedule(&bot, &db, &msg).await;
+            metrics::track(&db, sender, metrics::command_feature(text)).await;
+        }
+        if feedback::relay_reply(&bot, &msg, &db, lang).await? {
+            return Ok(());
+        }
+
+        if let Some(args) = command_args(text, "/help") {
+            help::handle_help_command(&bot, &msg, args, lang).await?;
+        } else if let Some(args) = command_args(text, "/events") {
+            agenda::handle_events_command(&bot, &msg, &db, args, &settings).await?;
+        } else if command_args(text, "/next").is_some() {
+            agenda::handle_next_command(&bot, &msg, &db, &settings).await?;
+        } else if command_args(text, "/overdue").is_some() {
+            overdue::handle_overdue_command(&bot, &msg, &db, &settings).await?;
+        } else if let Some(args) = command_args(text, "/agenda") {
+            agenda::handle_agenda_command(&bot, &msg, &db, args, &settings).await?;
+        } else if let Some(args) = command_args(text, "/busy") {
+            busy::handle_busy_command(&bot, &msg, &db, args, &settings).await?;
+        } else if command_args(text, "/calendar").is_some() {
+            calendar::handle_calendar_command(&bot, &msg, &db, &settings).await?;
+        } else if let Some(args) = command_args(text, "/duplicate") {
+            duplicates::handle_duplicate_command(&bot, &msg, &db, args, &settings).await?;
+        } else if command_args(text, "/templates").is_some() {
+            templates::handle_templates_list(&bot, &msg, &db, &settings).await?;
+        } else if let Some(args) = command_args(text, "/template") {
+            templates::handle_template_command(&bot, &msg, &db, args, &settings).await?;
+        } else if command_args(text, "/stats").is_some() {
+            stats::handle_stats_command(&bot, &msg, &db, &settings).await?;
+        } else if let Some(args) = command_args(text, "/anniversary") {
+            anniversaries::handle_anniversary_command(&bot, &msg, &db, args, &settings).await?;
+        } else if command_args(text, "/habits").is_some() {
+            habits::handle_habits_report(&bot, &msg, &db, &settings).await?;
+        } else if let Some(args) = command_args(text, "/habit") {
+            habits::handle_habit_command(&bot, &msg, &db, args, &settings).await?;
+        } else if let Some(args) = command_args(text, "/pomodoro") {
+            pomodoro::handle_pomodoro_command(&bot, &msg, &sessions, args, lang).await?;
+        } else if let Some(args) = command_args(text, "/timer") {
+            timers::handle_timer_command(&bot, &msg, args, lang).await?;
+        } else if command_args(text, "/todos").is_some() {
+            tasks::handle_todos_list(&bot, &msg, &db, lang).await?;
+        } else if let Some(args) = command_args(text, "/todo") {
+            tasks::handle_todo_command(&bot, &msg, &db, args, lang).await?;
+        } else if let Some(args) = command_args(text, "/settings") {
+            settings::handle_settings(&bot, &msg, &db, args).await?;
+        } else if let Some(args) = command_args(text, "/timezone") {
+            timezone::handle_timezone_command(&bot, &msg, &db, args, lang).await?;
+        } else if command_args(text, "/places").is_some() {
+            geofence::handle_places_list(&bot, &msg, &db, lang).await?;
+        } else if let Some(args) = command_args(text, "/place") {
+            geofence::handle_place_command(&bot, &msg, &db, args, lang).await?;
+        } else if let Some(args) = command_args(text, "/leave") {
+            travel::handle_leave_command(&bot, &msg, &db, args, &settings).await?;
+        } else if let Some(args) = command_args(text, "/weather") {
+            weather::handle_weather_command(&bot, &msg, &db, args, lang).await?;
+        } else if let Some(args) = command_args(text, "/token") {
+            tokens::handle_token_command(&bot, &msg, &db, args, lang).await?;
+        } else if let Some(args) = command_args(text, "/meds") {
+            meds::handle_meds_command(&bot, &msg, &db, args, &settings).await?;
+        } else if let Some(args) = command_args(text, "/deadline") {
+            deadlines::handle_deadline_command(&bot, &msg, &db, args, &settings).await?;
+        } else if let Some(args) = command_args(text, "/repeat") {
+            repeats::handle_repeat_command(&bot, &msg, &db, args, &settings).await?;
+        } else if let Some(args) = command_args(text, "/icon") {
+            categories::handle_icon_command(&bot, &msg, &db, args, &settings).await?;
+        } else if let Some(args) = command_args(text, "/star") {
+            stars::handle_star_command(&bot, &msg, &db, args, &settings).await?;
+        } else if let Some(args) = command_args(text, "/subscribe") {
+            subscriptions::handle_subscribe_command(&bot, &msg, &db, args, &settings).await?;
+        } else if let Some(args) = command_args(text, "/guest") {
+            guests::handle_guest_command(&bot, &msg, &db, args, &settings).await?;
+        } else if let Some(args) = command_args(text, "/share") {
+            share::handle_share_command(&bot, &msg, &db, args, &settings).await?;
+        } else if let Some(code) = command_args(text, "/start").and_then(share::start_code) {
+            share::handle_start(&bot, &msg, &db, code, &settings).await?;
+        } else if let Some(args) = command_args(text, "/start") {
+            onboarding::handle_start(&bot, &msg, &db, args, &settings).await?;
+        } else if let Some(args) = command_args(text, "/privacy") {
+            privacy::handle_privacy_command(&bot, &msg, &db, args, lang).await?;
+        } else if let Some(args) = command_args(text, "/contact") {
+            contacts::handle_contact_command(&bot, &msg, &db, args, &settings).await?;
+        } else if let Some(args) = command_args(text, "/for") {
+            contacts::handle_for_command(&bot, &msg, &db, args, &settings).await?;
+        } else if let Some(args) = command_args(text, "/feedback") {
+            feedback::handle_feedback_command(&bot, &msg, &db, args, lang).await?;
+        } else if let Some(args) = command_args(text, "/mirror") {
+            notifiers::handle_mirror_command(&bot, &msg, &db, args, lang).await?;
+        } else if let Some(args) = command_args(text, "/voice") {
+            voice::handle_voice_command(&bot, &msg, &db, args, &settings).await?;
+        } else if let Some(args) = command_args(text, "/webhook") {
+            notifiers::handle_webhook_command(&bot, &msg, &db, args, lang).await?;
+        } else if let Some(args) = command_args(text, "/audit") {
+            audit::handle_audit_command(&bot, &msg, &db, args, &settings).await?;
+        } else if let Some(args) = command_args(text, "/admin") {
+            admin::handle_admin_command(&bot, &msg, &db, args, lang).await?;
+        } else if let Some(args) = command_args(text, "/groupsettings") {
+            groups::handle_group_settings(&bot, &msg, &db, args).await?;
+        } else if let Some(args) = command_args(text, "/poll") {
+            if !groups::can_manage_events(&bot, &msg, &db).await? {
+                return groups::reply_not_allowed(&bot, &msg, &db).await;
+            }
+            poll::handle_poll_command(&bot, &msg, &db, args).await?;
+        } else if command_args(text, "/closepoll").is_some() {
+            poll::handle_close_command(&bot, &msg, &db).await?;
+        } else if forwarded::offer(&bot, &msg, &db, text, &settings).await? {
+            metrics::track(&db, sender, "forwarded").await;
+        } else if let Some(event_id) = edit::replied_event(&msg, &db).await? {
+            metrics::track(&db, sender, "reply_edit").await;
+            edit::amend_from_reply(&bot, &msg, &db, event_id, &settings).await?;
+        } else if let Some(period) = query::parse_query(text, &settings) {
+            metrics::track(&db, sender, "query").await;
+            agenda::handle_query(&bot, &msg, &db, period, &settings).await?;
+        } else if config::get().parsers.matches(text) {
+            metrics::track(&db, sender, "event").await;
+            handle_new_event(&bot, &msg, &db, text, &settings).await?;
+        } else {
+            help::handle_help_command(&bot, &msg, "", lang).await?;
+        }
+    }
     Ok(())
 }
 
+fn event_confirmation(settings: &Settings, text: &str, event_time: NaiveDateTime) -> String {
+    let time = settings.format_time(event_time.time());
+    let now = timezone::now_in(settings.timezone);
+    let until = humanize::until(settings.lang, event_time - now);
+    if event_time.date() == now.date() {
+        tf(settings.lang, "event_saved_today", &[("time", &time), ("until", &until), ("text", &text)])
+    } else {
+        let date = settings.format_date(event_time.date());
+        tf(settings.lang, "event_saved_date", &[("date", &date), ("time", &time), ("until", &until), ("text", &text)])
+    }
+}
+
+async fn handle_new_event(bot: &Bot, msg: &Message, db: &Db, text: &str, settings: &Settings) -> ResponseResult<()> {
+    if !groups::can_manage_events(bot, msg, db).await? {
+        return groups::reply_not_allowed(bot, msg, db).await;
+    }
+
+    let parsed = match config::get().parsers.parse(text, settings.locale(), clock::SYSTEM.now(), settings.timezone) {
+        Ok(parsed) => parsed,
+        Err(e) => {
+            let value = match e {
+                parse::ParseError::InvalidDate(value) | parse::ParseError::InvalidTime(value) | parse::ParseError::InvalidEnd(value) => value,
+                parse::ParseError::MissingTime => text.to_string(),
+            };
+            let error = chunks::send(bot, msg.chat.id, tf(settings.lang, "event_invalid_time", &[("value", &value)])).await?;
+            cleanup::schedule(bot, db, &error).await;
+            return Ok(());
+        }
+    };
+    let event_time = parsed.datetime;
+    let stored_time = parse::store(event_time);
+    let end_time = parsed.end.map(parse::store);
+
+    let user = msg.from().unwrap();
+    let (telegram_id, username) = (user.id.0 as i64, user.username.clone());
+    let (chat_id, message_id) = (msg.chat.id.0, msg.id.0);
+    let (text, time, tenant, recurrence) = (parsed.text.clone(), stored_time.clone(), tenants::name_of(bot), parsed.recurrence.clone());
+    // Пользователь, проверка на дубликат и событие со ссылкой на сообщение — одной транзакцией
+    let (user_id, created) = db.call(move |conn| in_transaction(conn, |tx| {
+        let user_id = ensure_user_exists(tx, tenant, telegram_id, username)?;
+        // Повторная доставка сообщения, на котором бот упал после записи события: второе событие
+        // не создаётся, а подтверждение, которое могло не дойти, отправляется ещё раз.
+        // Ключ `event_messages` — чат и сообщение, поэтому источник у события один
+        if let Some(event_id) = find_event_by_message(tx, chat_id, message_id, "source")? {
+            let overlaps = overlaps::find_overlaps(tx, user_id, chat_id, event_id, event_time, end_time.as_deref())?;
+            return Ok((user_id, Some((event_id, overlaps))));
+        }
+        if duplicates::find_duplicate(tx, user_id, chat_id, &text, &time)? {
+            return Ok((user_id, None));
+        }
+        let event_id = insert_event(tx, tenant, user_id, chat_id, &text, &time)?;
+        set_event_end(tx, event_id, end_time.as_deref())?;
+        set_event_recurrence(tx, event_id, recurrence.as_ref())?;
+        link_event_message(tx, chat_id, message_id, event_id, "source")?;
+        let overlaps = overlaps::find_overlaps(tx, user_id, chat_id, event_id, event_time, end_time.as_deref())?;
+        Ok((user_id, Some((event_id, overlaps))))
+    })).await.map_err(DatabaseError)?;
+
+    let Some((event_id, overlaps)) = created else {
+        return duplicates::warn_duplicate(bot, msg, db, user_id, &parsed.text, &stored_time, settings).await;
+    };
+
+    let confirmation = event_confirmation(settings, &parsed.text, event_time);
+    let confirmation = if overlaps.is_empty() {
+        chunks::send_last(bot, msg.chat.id, confirmation).await?
+            .reply_markup(ics::keyboard(settings.lang, event_id))
+            .await?
+    } else {
+        chunks::send_last(bot, msg.chat.id, format!("{}\n\n{}", confirmation, overlaps::warning(settings, &overlaps))).await?
+            .reply_markup(overlaps::keyboard(settings.lang, event_id))
+            .await?
+    };
+    cleanup::schedule(bot, db, &confirmation).await;
+
+    db.call(move |conn| link_event_message(conn, chat_id, confirmation.id.0, event_id, "confirmation"))
+        .await
+        .map_err(DatabaseError)?;
+    Ok(())
+}
+
+async fn handle_callback(bot: Bot, q: CallbackQuery, db: Db, sessions: pomodoro::Sessions, update: Update) -> ResponseResult<()> {
+    let (telegram_id, chat_id) = (q.from.id.0 as i64, q.message.as_ref().map_or(q.from.id.0 as i64, |message| message.chat.id.0));
+    let plain = db.call(move |conn| settings::resolve(conn, telegram_id, chat_id)).await.map_err(DatabaseError)?;
+    let tenant = tenants::name_of(&bot);
+    accessibility::scope(accessibility::of(&plain), route_callback(bot, q, db.clone(), sessions)).await?;
+    updates::mark_processed(&db, tenant, update.id).await;
+    Ok(())
+}
+
+async fn route_callback(bot: Bot, q: CallbackQuery, db: Db, sessions: pomodoro::Sessions) -> ResponseResult<()> {
+    if blocklist::is_blocked(&db, q.from.id.0 as i64).await {
+        bot.answer_callback_query(q.id).await?;
+        return Ok(());
+    }
+    if maintenance::intercept_callback(&bot, &q, &db).await? {
+        return Ok(());
+    }
+    let data = q.data.clone().unwrap_or_default();
+    if let Some((prefix, _)) = data.split_once(':') {
+        metrics::track(&db, Some(q.from.id.0 as i64), metrics::button_feature(prefix)).await;
+    }
+    match data.split_once(':') {
+        Some(("dup", args)) => duplicates::handle_callback(&bot, &q, &db, args).await?,
+        Some(("cal", args)) => calendar::handle_callback(&bot, &q, &db, args).await?,
+        Some(("chk", args)) => checklist::handle_callback(&bot, &q, &db, args).await?,
+        Some(("hab", args)) => habits::handle_callback(&bot, &q, &db, args).await?,
+        Some(("pom", action)) => pomodoro::handle_callback(&bot, &q, &db, &sessions, action).await?,
+        Some(("ack", args)) => followups::handle_callback(&bot, &q, &db, args).await?,
+        Some(("todo", args)) => tasks::handle_callback(&bot, &q, &db, args).await?,
+        Some(("ics", args)) => ics::handle_callback(&bot, &q, &db, args).await?,
+        Some(("imp", args)) => import::handle_callback(&bot, &q, &db, args).await?,
+        Some(("sync", args)) => sync::handle_callback(&bot, &q, &db, args).await?,
+        Some(("ovl", args)) => overlaps::handle_callback(&bot, &q, &db, args).await?,
+        Some(("ovd", args)) => overdue::handle_callback(&bot, &q, &db, args).await?,
+        Some(("onb", args)) => onboarding::handle_callback(&bot, &q, &db, args).await?,
+        Some(("contact", args)) => contacts::handle_callback(&bot, &q, &db, args).await?,
+        _ => {
+            bot.answer_callback_query(q.id).await?;
+        }
+    }
+    Ok(())
+}
+
+/// Отправляет напоминания о событиях, время которых наступило.
+async fn send_due_events(db: &Db, clock: &dyn Clock, shard: Shard) {
+    println!("Checking for due events in shard {}/{}...", shard.index, shard.count);
+    let now = clock.now_utc();
+    // События из окна допуска ставятся в очередь заранее и уходят точно в срок, см. `Config::delivery_tolerance`
+    let horizon = now + chrono::Duration::from_std(config::get().delivery_tolerance).unwrap_or_default();
+    let due = db.call(move |conn| {
+        get_due_events(conn, horizon, shard)?
+            .into_iter()
+            .map(|event| {
+                let settings = settings::resolve(conn, event.owner_id, event.chat_id).unwrap_or_default();
+                let keyboard = accessibility::with(accessibility::of(&settings), || checklist::keyboard(conn, event.id)).ok().flatten();
+                let place = weather::event_place(conn, event.id).ok().flatten();
+                Ok((event, settings, keyboard, place))
+            })
+            .collect::<Result<Vec<_>, rusqlite::Error>>()
+    }).await;
+
+    if let Ok(events) = due {
+        println!("Found {} due events", events.len());
+        let mut messages = Vec::new();
+        for (event, settings, keyboard, place) in events {
+            if settings.is_quiet(clock.now_in(settings.timezone).time()) {
+                println!("Postponing event {} until quiet hours end", event.id);
+                continue;
+            }
+
+            println!("Queueing notification for event: {:?}", event);
+            let message = accessibility::scope(accessibility::of(&settings), async {
+                let local_time = parse_event_time(&event.event_time);
+                let time = local_time.map_or_else(|| event.event_time.clone(), |time| settings.format_datetime(time));
+                let text = categories::label(event.icon.as_deref(), &event.text);
+                let mut reminder = tf(settings.lang, "reminder", &[("text", &text), ("time", &time)]);
+                if let Some((place, local_time)) = place.zip(local_time) {
+                    if let Some(forecast) = weather::forecast_line(db, place, local_time.date(), settings.lang).await {
+                        reminder = format!("{}\n{}", reminder, forecast);
+                    }
+                }
+                outbox::Outgoing {
+                    tenant: event.tenant.clone(),
+                    chat_id: event.chat_id,
+                    text: reminder,
+                    // Событие с чек-листом завершается отметкой всех пунктов, остальные — кнопкой «Готово»
+                    reply_markup: Some(keyboard.unwrap_or_else(|| followups::ack_keyboard(settings.lang, event.id))),
+                    reminder: Some(outbox::Reminder {
+                        event_id: event.id,
+                        event_utc: event.event_utc.clone(),
+                        owner_id: event.owner_id,
+                        starred: event.starred,
+                    }),
+                }
+            }).await;
+            messages.push(message);
+        }
+        if messages.is_empty() {
+            return;
+        }
+
+        // События отмечаются отправленными вместе с записью в очередь: доставляет её
+        // `outbox::run` с повторами, а повторный проход напоминание не продублирует
+        let event_ids = messages.iter().filter_map(|message| Some(message.reminder.as_ref()?.event_id)).collect::<Vec<_>>();
+        match db.call(move |conn| notifications::claim(conn, &messages, now)).await {
+            Ok(claimed) => {
+                for (event_id, claimed) in event_ids.into_iter().zip(claimed) {
+                    if !claimed {
+                        println!("Notification for event {} was already sent", event_id);
+                    }
+                }
+            }
+            Err(e) => log::error!("Failed to queue notifications for events {:?}: {}", event_ids, e),
+        }
+    }
+}
+
+/// Напоминания о событиях одного шарда. Шард обслуживается, пока процесс держит его аренду,
+/// поэтому несколько процессов с общей базой не просматривают одних и тех же пользователей.
+async fn run_scheduler(db: Db, clock: Arc<dyn Clock>, shard: Shard) {
+    let clock = clock.as_ref();
+    loop {
+        let poll_interval = config::get().poll_interval;
+        let now = clock.now_utc();
+        match db.call(move |conn| shards::acquire(conn, shard, now, poll_interval * 3)).await {
+            Ok(true) => {
+                send_due_events(&db, clock, shard).await;
+                health::record_shard_tick(shard);
+            }
+            Ok(false) => println!("Shard {}/{} is served by another process", shard.index, shard.count),
+            Err(e) => log::error!("Failed to lease shard {}/{}: {}", shard.index, shard.count, e),
+        }
+        tokio::time::sleep(poll_interval).await;
+    }
+}
+
+/// Разбор обновлений по обработчикам; зависимости — `Db` и сессии помодоро.
+fn schema() -> UpdateHandler<RequestError> {
+    dptree::entry()
+        .branch(Update::filter_message().endpoint(handle_message))
+        .branch(Update::filter_edited_message().endpoint(edit::handle_edited_message))
+        .branch(Update::filter_callback_query().endpoint(handle_callback))
+        .branch(Update::filter_poll().endpoint(poll::handle_poll_update))
+}
+
+/// Напоминания, сводки и остальные периодические отправки; пауза между проходами из настроек.
+async fn run_notifications(db: Db, sessions: pomodoro::Sessions, clock: Arc<dyn Clock>) {
+    let clock = clock.as_ref();
+    loop {
+        // Сессии помодоро живут в памяти процесса, остальное делается одним процессом на базу
+        if shards::holds_task(&db, "notifications", config::get().poll_interval).await {
+            send_periodic(&db, clock).await;
+        }
+        pomodoro::tick(&sessions, clock).await;
+
+        health::record_tick();
+        tokio::time::sleep(config::get().poll_interval).await;
+    }
+}
+
+/// Один проход периодических отправок из `run_notifications`.
+async fn send_periodic(db: &Db, clock: &dyn Clock) {
+    poll::close_expired_polls(db, clock).await;
+    // Во время обслуживания уходят только напоминания о событиях и о выходе
+    let paused = maintenance::is_on(db).await;
+    if !paused {
+        digest::send_due_digests(db, clock).await;
+        habits::send_due_habits(db, clock).await;
+        anniversaries::send_due_anniversaries(db, clock).await;
+        meds::send_due_summaries(db, clock).await;
+    }
+    travel::send_due_leave_reminders(db, clock).await;
+    cleanup::delete_due_messages(db, clock).await;
+    if !paused {
+        repeats::send_due_repeats(db, clock).await;
+        deadlines::send_due_deadlines(db, clock).await;
+    }
+}
+
 #[tokio::main]
 async fn main() {
     dotenv().ok();
     pretty_env_logger::init();
+
+    let args = env::args().skip(1).collect::<Vec<_>>();
+    // Служебные подкоманды выполняются вместо запуска бота
+    let command = match args.first().map(String::as_str) {
+        Some("restore") => Some(restore::run(&args[1..])),
+        Some("migrate-done") => Some(migrate::run(&args[1..])),
+        Some("simulate") => Some(simulate::run(&args[1..]).await),
+        _ => None,
+    };
+    if let Some(result) = command {
+        if let Err(e) = result {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+
     log::info!("Starting reminder bot...");
+    // Ошибка в текстах оператора видна сразу, а не в первом сообщении с испорченным шаблоном
+    match i18n::load_overrides() {
+        Ok(0) => {}
+        Ok(count) => log::info!("Loaded {} message overrides", count),
+        Err(e) => {
+            eprintln!("Invalid message templates in MESSAGES_DIR:\n{}", e);
+            std::process::exit(1);
+        }
+    }
+    health::mark_started();
+    config::spawn_reload_on_sighup();
 
-    let token = env::var("TELOXIDE_TOKEN").expect("TELOXIDE_TOKEN не найден в .env файле");
-    let bot = Bot::new(token);
+    let tenants = tenants::all();
+    log::info!("Serving bots: {}", tenants.iter().map(|tenant| tenant.name.as_str()).collect::<Vec<_>>().join(", "));
 
-    let conn = Connection::open("reventor.db").expect("Failed to open database");
+    // `--ephemeral` — попробовать бота без файла базы: всё хранится в памяти до выхода
+    let ephemeral = args.iter().any(|arg| arg == "--ephemeral");
+    let conn = if ephemeral {
+        log::info!("Running with an in-memory database, nothing will be saved");
+        storage::open_in_memory()
+    } else {
+        storage::open(DB_PATH)
+    };
+    let conn = conn.expect("Failed to open database");
     init_db(&conn).expect("Failed to initialize database");
-    let db = Arc::new(Mutex::new(conn));
-
-    let bot_for_notifications = bot.clone();
-    let db_for_notifications = db.clone();
-
-    tokio::spawn(async move {
-        loop {
-            let conn = db_for_notifications.lock().await;
-            println!("Checking for due events...");
-            
-            if let Ok(events) = get_due_events(&conn) {
-                println!("Found {} due events", events.len());
-                for event in events {
-                    println!("Sending notification for event: {:?}", event);
-                    let _ = bot_for_notifications
-                        .send_message(
-                            ChatId(event.telegram_id),
-                            format!("🔔 Напоминание!\n{}\nВремя: {}", event.text, event.event_time)
-                        )
-                        .await;
-                    
-                    // Очищаем event_time после отправки уведомления
-                    let _ = mark_event_sent(&conn, event.telegram_id, &event.event_time);
-                }
-            }
-            drop(conn);
-            
-            tokio::time::sleep(tokio::time::Duration::from_secs(10)).await;
-        }
+    storage::enable_foreign_keys(&conn).expect("Failed to enable foreign keys");
+    crypto::encrypt_existing(&conn).expect("Failed to encrypt event texts");
+    // Испорченные строки чинятся или убираются до первого прохода планировщика
+    let integrity = integrity::check(&conn).expect("Failed to check database integrity");
+    if !integrity.is_clean() {
+        log::error!("Integrity check: {:?}", integrity);
+    }
+    let db = Db::new(conn);
+    let db_for_integrity = db.clone();
+    tokio::spawn(async move { integrity::notify_admins(&db_for_integrity, &integrity).await });
+
+    // Копировать базу в памяти незачем
+    if !ephemeral {
+        backup::spawn_scheduler(db.clone());
+    }
+    retention::spawn_scheduler(db.clone());
+    http::spawn(db.clone());
+
+    let sessions = pomodoro::new_sessions();
+    let (db_for_notifications, sessions_for_notifications) = (db.clone(), sessions.clone());
+    let clock: Arc<dyn Clock> = Arc::new(clock::SystemClock);
+    let clock_for_notifications = clock.clone();
+    supervisor::spawn("notifications", move || {
+        run_notifications(db_for_notifications.clone(), sessions_for_notifications.clone(), clock_for_notifications.clone())
     });
+    for shard in shards::all() {
+        let (db, clock) = (db.clone(), clock.clone());
+        supervisor::spawn(shard.task_name(), move || run_scheduler(db.clone(), clock.clone(), shard));
+    }
+    let db_for_outbox = db.clone();
+    supervisor::spawn("outbox", move || outbox::run(db_for_outbox.clone()));
+    let db_for_mirrors = db.clone();
+    supervisor::spawn("mirrors", move || notifiers::run(db_for_mirrors.clone()));
+    let db_for_subscriptions = db.clone();
+    supervisor::spawn("subscriptions", move || subscriptions::run(db_for_subscriptions.clone()));
 
-    teloxide::repl(bot, move |bot: Bot, msg: Message| {
-        let db = db.clone();
-        async move {
-            if let Some(text) = msg.text() {
-                if text == "/events" {
-                    let conn = db.lock().await;
-                    let events = get_user_events(&conn, msg.from().unwrap().id.0 as i64)
-                        .map_err(|e| DatabaseError(e))?;
-
-                    if events.is_empty() {
-                        bot.send_message(msg.chat.id, "У вас пока нет запланированных событий").await?;
-                    } else {
-                        let events_text = events
-                            .iter()
-                            .enumerate()
-                            .map(|(i, e)| format!("{}. {} - {}", i + 1, e.event_time, e.text))
-                            .collect::<Vec<_>>()
-                            .join("\n");
-                        
-                        bot.send_message(msg.chat.id, format!("Ваши события:\n{}", events_text)).await?;
-                    }
-                } else if let Some(event) = parse_event(text) {
-                    let conn = db.lock().await;
-                    let user_id = ensure_user_exists(
-                        &conn,
-                        msg.from().unwrap().id.0 as i64,
-                        msg.from().unwrap().username.clone()
-                    ).map_err(|e| DatabaseError(e))?;
-
-                    save_event(&conn, user_id, &event)
-                        .map_err(|e| DatabaseError(e))?;
-
-                    let response = match event.date {
-                        Some(date) => format!("Сохранено событие на {} в {}\nТекст события: {}", 
-                            date, event.time, event.text),
-                        None => format!("Сохранено событие на сегодня в {}\nТекст события: {}", 
-                            event.time, event.text),
-                    };
-                    bot.send_message(msg.chat.id, response).await?;
-                } else {
-                    bot.send_message(msg.chat.id, "Привет! Чтобы создать событие, используйте форматы:\n\
-                        @ЧЧ:ММ - событие на сегодня\n\
-                        @ДД.ММ ЧЧ:ММ - событие на конкретную дату\n\
-                        @ДД.ММ.ГГГГ ЧЧ:ММ - событие на конкретную дату с годом").await?;
-                }
-            }
-            Ok(())
-        }
-    })
-    .await;
+    let handler = schema();
+
+    // Каждый бот получает обновления сам, база и планировщик общие
+    let dispatchers = tenants
+        .iter()
+        .map(|tenant| {
+            let mut dispatcher = Dispatcher::builder(tenant.bot.clone(), handler.clone())
+                .dependencies(dptree::deps![db.clone(), sessions.clone()])
+                .enable_ctrlc_handler()
+                .build();
+            let (bot, db) = (tenant.bot.clone(), db.clone());
+            tokio::spawn(async move {
+                let listener = reactions::listener(bot, db).await;
+                dispatcher
+                    .dispatch_with_listener(listener, LoggingErrorHandler::with_custom_text("An error from the update listener"))
+                    .await
+            })
+        })
+        .collect::<Vec<_>>();
+    for dispatcher in dispatchers {
+        let _ = dispatcher.await;
+    }
 }
+
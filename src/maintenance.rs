@@ -0,0 +1,155 @@
+use teloxide::prelude::*;
+use rusqlite::{Connection, params, OptionalExtension};
+
+use crate::admin;
+use crate::i18n::{t, tf, Lang};
+use crate::settings;
+use crate::{DatabaseError, Db, config, handle_new_event, tenants};
+
+pub fn init_tables(conn: &Connection) -> Result<(), rusqlite::Error> {
+    // Включённый режим обслуживания — единственная строка с текстом объявления
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS maintenance (
+            id INTEGER PRIMARY KEY CHECK (id = 1),
+            notice TEXT NOT NULL,
+            started_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP
+        )",
+        [],
+    )?;
+    // Сообщения с событиями, пришедшие во время обслуживания; разбираются после выключения
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS maintenance_queue (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            tenant TEXT NOT NULL DEFAULT 'default',
+            message TEXT NOT NULL,
+            created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP
+        )",
+        [],
+    )?;
+    Ok(())
+}
+
+fn notice(conn: &Connection) -> Result<Option<String>, rusqlite::Error> {
+    conn.query_row("SELECT notice FROM maintenance WHERE id = 1", [], |row| row.get(0)).optional()
+}
+
+fn start(conn: &Connection, notice: &str) -> Result<(), rusqlite::Error> {
+    conn.execute(
+        "INSERT INTO maintenance (id, notice) VALUES (1, ?)
+         ON CONFLICT(id) DO UPDATE SET notice = excluded.notice",
+        params![notice],
+    )?;
+    Ok(())
+}
+
+/// Выключает обслуживание и забирает накопленные сообщения.
+fn finish(conn: &Connection) -> Result<Vec<(String, String)>, rusqlite::Error> {
+    conn.execute("DELETE FROM maintenance", [])?;
+    let mut stmt = conn.prepare("SELECT tenant, message FROM maintenance_queue ORDER BY id")?;
+    let queued = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+        .collect::<Result<Vec<_>, _>>()?;
+    conn.execute("DELETE FROM maintenance_queue", [])?;
+    Ok(queued)
+}
+
+/// Включён ли режим обслуживания. При ошибке базы считается выключенным.
+pub async fn is_on(db: &Db) -> bool {
+    match db.call(notice).await {
+        Ok(notice) => notice.is_some(),
+        Err(e) => {
+            log::error!("Failed to read maintenance state: {}", e);
+            false
+        }
+    }
+}
+
+/// Во время обслуживания отвечает объявлением вместо обработки сообщения. Сообщения
+/// с событиями сохраняются и создаются после выключения. Администраторы работают как обычно.
+/// Возвращает `true`, если сообщение перехвачено.
+pub async fn intercept(bot: &Bot, msg: &Message, db: &Db) -> ResponseResult<bool> {
+    if admin::is_admin(msg) {
+        return Ok(false);
+    }
+    let Some(notice) = db.call(notice).await.map_err(DatabaseError)? else {
+        return Ok(false);
+    };
+
+    let text = msg.text().unwrap_or_default();
+    let is_event = config::get().parsers.matches(text) && !text.starts_with('/');
+    // В группах бот отвечает только на команды и события, чтобы не отвечать на каждую реплику
+    if !msg.chat.is_private() && !is_event && !text.starts_with('/') {
+        return Ok(true);
+    }
+
+    let lang = settings::for_message(db, msg).await.map_err(DatabaseError)?.lang;
+    let key = if is_event {
+        let (tenant, message) = (tenants::name_of(bot), serde_json::to_string(msg).unwrap_or_default());
+        db.call(move |conn| {
+            conn.execute("INSERT INTO maintenance_queue (tenant, message) VALUES (?, ?)", params![tenant, message])
+        }).await.map_err(DatabaseError)?;
+        "maintenance_buffered"
+    } else {
+        "maintenance_notice"
+    };
+    bot.send_message(msg.chat.id, tf(lang, key, &[("notice", &notice)])).await?;
+    Ok(true)
+}
+
+/// Ответ на нажатие кнопки во время обслуживания.
+pub async fn intercept_callback(bot: &Bot, q: &CallbackQuery, db: &Db) -> ResponseResult<bool> {
+    if admin::is_admin_user(&q.from) {
+        return Ok(false);
+    }
+    let Some(notice) = db.call(notice).await.map_err(DatabaseError)? else {
+        return Ok(false);
+    };
+    bot.answer_callback_query(q.id.clone()).text(notice).show_alert(true).await?;
+    Ok(true)
+}
+
+/// Создаёт события из сообщений, накопленных за время обслуживания.
+async fn replay(db: &Db, queued: Vec<(String, String)>) -> usize {
+    let mut created = 0;
+    for (tenant, message) in queued {
+        let msg = match serde_json::from_str::<Message>(&message) {
+            Ok(msg) => msg,
+            Err(e) => {
+                log::error!("Failed to restore buffered message: {}", e);
+                continue;
+            }
+        };
+        let Some(text) = msg.text().filter(|text| config::get().parsers.matches(text)) else {
+            continue;
+        };
+        let result = async {
+            let settings = settings::for_message(db, &msg).await.map_err(DatabaseError)?;
+            handle_new_event(tenants::bot(&tenant), &msg, db, text, &settings).await
+        };
+        match result.await {
+            Ok(()) => created += 1,
+            Err(e) => log::error!("Failed to create buffered event from message {}: {}", msg.id.0, e),
+        }
+    }
+    created
+}
+
+/// `/admin maintenance on "текст"` и `/admin maintenance off`.
+pub async fn handle_admin(bot: &Bot, msg: &Message, db: &Db, args: &str, lang: Lang) -> ResponseResult<()> {
+    let response = match args.split_once(char::is_whitespace).map_or((args, ""), |(mode, rest)| (mode, rest.trim())) {
+        ("on", notice) if !notice.is_empty() => {
+            let notice = notice.trim_matches(|c| c == '"' || c == '«' || c == '»').to_string();
+            log::info!("Maintenance mode on: {}", notice);
+            db.call(move |conn| start(conn, &notice)).await.map_err(DatabaseError)?;
+            t(lang, "maintenance_on")
+        }
+        ("off", "") => {
+            let queued = db.call(finish).await.map_err(DatabaseError)?;
+            let created = replay(db, queued).await;
+            log::info!("Maintenance mode off, {} buffered events created", created);
+            tf(lang, "maintenance_off", &[("count", &created)])
+        }
+        _ => t(lang, "admin_usage"),
+    };
+    bot.send_message(msg.chat.id, response).await?;
+    Ok(())
+}
@@ -0,0 +1,319 @@
+//! Курс лекарств: `/meds 08:00,14:00,20:00 for 10 days amoxicillin` разворачивается в события
+//! на каждый приём. Приём отмечается кнопкой «Готово» у напоминания, `/meds` показывает отметки
+//! по дням, а после последнего приёма приходит итог: сколько из назначенного принято.
+
+use std::collections::BTreeMap;
+
+use teloxide::prelude::*;
+use chrono::{Duration, NaiveDate, NaiveDateTime, NaiveTime};
+use regex::Regex;
+use rusqlite::{Connection, params};
+
+use reventor::parse;
+
+use crate::accessibility;
+use crate::blocklist;
+use crate::chunks;
+use crate::clock::{self, Clock};
+use crate::groups;
+use crate::i18n::{t, tf, Lang};
+use crate::outbox::{self, Outgoing};
+use crate::settings::{self, Settings};
+use crate::{DatabaseError, Db, UTC_FORMAT, add_column_if_missing, ensure_user_exists, in_transaction, insert_event, parse_time_input, tenants};
+
+const MAX_DAYS: u32 = 90;
+const MAX_TIMES_PER_DAY: usize = 12;
+/// Сколько ждать отметки последнего приёма, прежде чем подводить итог.
+const SUMMARY_GRACE_HOURS: i64 = 3;
+
+/// Курс из `/meds`: название, время приёмов и число дней.
+#[derive(Debug, PartialEq)]
+struct Plan {
+    name: String,
+    times: Vec<NaiveTime>,
+    days: u32,
+}
+
+/// Приём курса: день по часам пользователя, отмечен ли он и не прошло ли ещё его время.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Dose {
+    Taken,
+    Missed,
+    Upcoming,
+}
+
+pub fn init_tables(conn: &Connection) -> Result<(), rusqlite::Error> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS med_courses (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            tenant TEXT NOT NULL DEFAULT 'default',
+            user_id INTEGER NOT NULL,
+            chat_id INTEGER NOT NULL,
+            name TEXT NOT NULL,
+            summary_utc TEXT,
+            summarized INTEGER NOT NULL DEFAULT 0,
+            created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            FOREIGN KEY(user_id) REFERENCES users(id) ON DELETE CASCADE
+        )",
+        [],
+    )?;
+    // Приём, к которому относится событие
+    add_column_if_missing(conn, "events", "course_id", "INTEGER")?;
+    Ok(())
+}
+
+/// Разбирает `08:00,14:00,20:00 for 10 days название` или `8:00,20:00 на 7 дней название`.
+fn parse_plan(args: &str) -> Option<Plan> {
+    let re = Regex::new(r"(?i)^(\S+)\s+(?:for|на)\s+(\d+)\s*(?:days?|d|дн[а-я]*|д)\s+(.+)$").unwrap();
+    let captures = re.captures(args.trim())?;
+    let mut times = captures[1].split(',').map(parse_time_input).collect::<Option<Vec<_>>>()?;
+    times.sort();
+    times.dedup();
+    let days = captures[2].parse().ok().filter(|days| (1..=MAX_DAYS).contains(days))?;
+    let name = captures[3].trim().to_string();
+    (!times.is_empty() && times.len() <= MAX_TIMES_PER_DAY).then_some(Plan { name, times, days })
+}
+
+/// Время приёмов начиная с сегодняшнего дня; уже прошедшие сегодня пропускаются,
+/// а если прошли все, курс начинается завтра.
+fn doses(plan: &Plan, now: NaiveDateTime) -> Vec<NaiveDateTime> {
+    let today = now.date();
+    let start = if plan.times.iter().any(|time| today.and_time(*time) > now) { today } else { today + Duration::days(1) };
+    (0..i64::from(plan.days))
+        .flat_map(|day| plan.times.iter().map(move |time| (start + Duration::days(day)).and_time(*time)))
+        .filter(|dose| *dose > now)
+        .collect()
+}
+
+/// Создаёт курс и по событию на каждый приём; итог подводится через несколько часов после последнего.
+fn create(conn: &Connection, tenant: &str, user_id: i64, chat_id: i64, lang: Lang, plan: &Plan, now: NaiveDateTime) -> Result<(i64, usize), rusqlite::Error> {
+    conn.execute(
+        "INSERT INTO med_courses (tenant, user_id, chat_id, name) VALUES (?, ?, ?, ?)",
+        params![tenant, user_id, chat_id, plan.name],
+    )?;
+    let course_id = conn.last_insert_rowid();
+    let doses = doses(plan, now);
+    for dose in &doses {
+        let event_id = insert_event(conn, tenant, user_id, chat_id, &tf(lang, "meds_dose", &[("name", &plan.name)]), &parse::store(*dose))?;
+        conn.execute("UPDATE events SET course_id = ? WHERE id = ?", params![course_id, event_id])?;
+    }
+    conn.execute(
+        "UPDATE med_courses SET summary_utc = (
+            SELECT strftime('%Y-%m-%d %H:%M:%S', MAX(event_utc), '+' || ? || ' hours') FROM events WHERE course_id = ?
+         ) WHERE id = ?",
+        params![SUMMARY_GRACE_HOURS, course_id, course_id],
+    )?;
+    Ok((course_id, doses.len()))
+}
+
+/// Приёмы курса по дням по часам пользователя.
+fn dose_log(conn: &Connection, course_id: i64, now_utc: NaiveDateTime) -> Result<BTreeMap<NaiveDate, Vec<Dose>>, rusqlite::Error> {
+    let mut stmt = conn.prepare("SELECT event_time, event_utc, status FROM events WHERE course_id = ? ORDER BY event_utc")?;
+    let rows = stmt.query_map(params![course_id], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?, row.get::<_, String>(2)?)))?
+        .collect::<Result<Vec<_>, _>>()?;
+    let mut days = BTreeMap::<NaiveDate, Vec<Dose>>::new();
+    for (event_time, event_utc, status) in rows {
+        let Some(local) = parse::load(&event_time) else {
+            continue;
+        };
+        let due = NaiveDateTime::parse_from_str(&event_utc, UTC_FORMAT).is_ok_and(|utc| utc <= now_utc);
+        let dose = match status.as_str() {
+            "done" => Dose::Taken,
+            _ if due => Dose::Missed,
+            _ => Dose::Upcoming,
+        };
+        days.entry(local.date()).or_default().push(dose);
+    }
+    Ok(days)
+}
+
+/// Сколько приёмов отмечено из скольких.
+fn adherence(days: &BTreeMap<NaiveDate, Vec<Dose>>) -> (usize, usize) {
+    let doses = days.values().flatten();
+    (doses.clone().filter(|dose| **dose == Dose::Taken).count(), doses.count())
+}
+
+fn percent(taken: usize, total: usize) -> usize {
+    (taken * 100).checked_div(total).unwrap_or(0)
+}
+
+fn day_line(lang: Lang, settings: &Settings, day: NaiveDate, doses: &[Dose]) -> String {
+    let marks = doses.iter().map(|dose| match dose {
+        Dose::Taken => "✅",
+        Dose::Missed => "❌",
+        Dose::Upcoming => "⏳",
+    }).collect::<String>();
+    let taken = doses.iter().filter(|dose| **dose == Dose::Taken).count();
+    tf(lang, "meds_day", &[("date", &settings.format_short_date(day)), ("taken", &taken), ("total", &doses.len()), ("marks", &marks)])
+}
+
+/// Ставит в очередь итоги курсов, последний приём которых прошёл.
+fn queue_summaries(conn: &Connection, now: NaiveDateTime) -> Result<Vec<i64>, rusqlite::Error> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT c.id, c.tenant, u.telegram_id, c.chat_id, c.name
+         FROM med_courses c
+         JOIN users u ON c.user_id = u.id
+         WHERE c.summarized = 0 AND c.summary_utc <= ? AND {}",
+        blocklist::NOT_BLOCKED
+    ))?;
+    let due = stmt.query_map(params![now.format(UTC_FORMAT).to_string()], |row| {
+        Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?, row.get::<_, i64>(2)?, row.get::<_, i64>(3)?, row.get::<_, String>(4)?))
+    })?
+    .collect::<Result<Vec<_>, _>>()?;
+
+    let mut queued = Vec::new();
+    for (course_id, tenant, owner_id, chat_id, name) in due {
+        let settings = settings::resolve(conn, owner_id, chat_id).unwrap_or_default();
+        let (taken, total) = adherence(&dose_log(conn, course_id, now)?);
+        let text = accessibility::with(accessibility::of(&settings), || {
+            tf(settings.lang, "meds_summary", &[("name", &name), ("taken", &taken), ("total", &total), ("percent", &percent(taken, total))])
+        });
+        in_transaction(conn, |tx| {
+            tx.execute("UPDATE med_courses SET summarized = 1 WHERE id = ?", params![course_id])?;
+            outbox::push(tx, &Outgoing { tenant, chat_id, text, reply_markup: None, reminder: None }, now)
+        })?;
+        queued.push(course_id);
+    }
+    Ok(queued)
+}
+
+pub async fn send_due_summaries(db: &Db, clock: &dyn Clock) {
+    let now = clock.now_utc();
+    match db.call(move |conn| queue_summaries(conn, now)).await {
+        Ok(queued) if !queued.is_empty() => log::info!("Queued medication summaries for courses {:?}", queued),
+        Ok(_) => {}
+        Err(e) => log::error!("Failed to queue medication summaries: {}", e),
+    }
+}
+
+/// Курсы пользователя, по которым ещё не подведён итог.
+fn active_courses(conn: &Connection, telegram_id: i64) -> Result<Vec<(i64, String)>, rusqlite::Error> {
+    let mut stmt = conn.prepare(
+        "SELECT c.id, c.name FROM med_courses c JOIN users u ON c.user_id = u.id
+         WHERE u.telegram_id = ? AND c.summarized = 0 ORDER BY c.id",
+    )?;
+    let courses = stmt.query_map(params![telegram_id], |row| Ok((row.get(0)?, row.get(1)?)))?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(courses)
+}
+
+/// Отменяет оставшиеся приёмы курса; итог по уже прошедшим придёт сразу.
+fn stop(conn: &Connection, telegram_id: i64, course_id: i64, now: NaiveDateTime) -> Result<bool, rusqlite::Error> {
+    let now = now.format(UTC_FORMAT).to_string();
+    let updated = conn.execute(
+        "UPDATE med_courses SET summary_utc = ?
+         WHERE id = ? AND summarized = 0 AND user_id = (SELECT id FROM users WHERE telegram_id = ?)",
+        params![now, course_id, telegram_id],
+    )?;
+    if updated > 0 {
+        conn.execute("DELETE FROM events WHERE course_id = ? AND status = 'pending' AND event_utc > ?", params![course_id, now])?;
+    }
+    Ok(updated > 0)
+}
+
+/// `/meds 08:00,14:00,20:00 for 10 days название` — курс приёмов; `/meds stop #id` — прекратить курс;
+/// без аргументов — отметки по дням.
+pub async fn handle_meds_command(bot: &Bot, msg: &Message, db: &Db, args: &str, settings: &Settings) -> ResponseResult<()> {
+    let lang = settings.lang;
+    let Some(user) = msg.from() else {
+        return Ok(());
+    };
+    let (telegram_id, username, chat_id, tenant) = (user.id.0 as i64, user.username.clone(), msg.chat.id.0, tenants::name_of(bot));
+    let now_utc = clock::SYSTEM.now_utc();
+    let args = args.trim();
+
+    let response = if args.is_empty() {
+        let courses = db.call(move |conn| {
+            active_courses(conn, telegram_id)?
+                .into_iter()
+                .map(|(id, name)| Ok((id, name, dose_log(conn, id, now_utc)?)))
+                .collect::<Result<Vec<_>, rusqlite::Error>>()
+        }).await.map_err(DatabaseError)?;
+        if courses.is_empty() {
+            t(lang, "meds_empty")
+        } else {
+            let blocks = courses
+                .iter()
+                .map(|(id, name, days)| {
+                    let (taken, total) = adherence(days);
+                    let lines = days.iter().map(|(day, doses)| day_line(lang, settings, *day, doses)).collect::<Vec<_>>();
+                    tf(lang, "meds_course", &[("id", id), ("name", name), ("taken", &taken), ("total", &total), ("days", &lines.join("\n"))])
+                })
+                .collect::<Vec<_>>();
+            blocks.join("\n\n")
+        }
+    } else if let Some(id) = args.strip_prefix("stop") {
+        match id.trim().trim_start_matches('#').parse::<i64>() {
+            Ok(course_id) => {
+                let stopped = db.call(move |conn| in_transaction(conn, |tx| stop(tx, telegram_id, course_id, now_utc)))
+                    .await
+                    .map_err(DatabaseError)?;
+                tf(lang, if stopped { "meds_stopped" } else { "meds_not_found" }, &[("id", &course_id)])
+            }
+            Err(_) => t(lang, "meds_usage"),
+        }
+    } else if let Some(plan) = parse_plan(args) {
+        if !groups::can_manage_events(bot, msg, db).await? {
+            return groups::reply_not_allowed(bot, msg, db).await;
+        }
+        let now = clock::SYSTEM.now_in(settings.timezone);
+        let (name, days, per_day) = (plan.name.clone(), plan.days, plan.times.len());
+        let times = plan.times.iter().map(|time| settings.format_time(*time)).collect::<Vec<_>>().join(", ");
+        let (course_id, count) = db.call(move |conn| in_transaction(conn, |tx| {
+            let user_id = ensure_user_exists(tx, tenant, telegram_id, username)?;
+            create(tx, tenant, user_id, chat_id, lang, &plan, now)
+        })).await.map_err(DatabaseError)?;
+        log::info!("Created medication course {} with {} doses", course_id, count);
+        tf(lang, "meds_saved", &[("id", &course_id), ("name", &name), ("times", &times), ("days", &days), ("count", &count), ("per_day", &per_day)])
+    } else {
+        t(lang, "meds_usage")
+    };
+    chunks::send(bot, msg.chat.id, response).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::followups::complete_event;
+    use crate::storage::tests::memory_db;
+    use crate::timezone;
+
+    fn at(value: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(value, UTC_FORMAT).unwrap()
+    }
+
+    #[test]
+    fn plans_accept_both_languages() {
+        let plan = parse_plan("20:00,08:00 for 10 days amoxicillin").unwrap();
+        assert_eq!(plan.times, vec![NaiveTime::from_hms_opt(8, 0, 0).unwrap(), NaiveTime::from_hms_opt(20, 0, 0).unwrap()]);
+        assert_eq!((plan.days, plan.name.as_str()), (10, "amoxicillin"));
+        assert_eq!(parse_plan("9:00 на 5 дней Витамин D").map(|plan| plan.days), Some(5));
+        assert_eq!(parse_plan("08:00 for 0 days x"), None);
+        assert_eq!(parse_plan("25:00 for 3 days x"), None);
+    }
+
+    #[tokio::test]
+    async fn summary_counts_taken_doses() {
+        let db = memory_db();
+        let (count, summary) = db.call(|conn| {
+            let user_id = ensure_user_exists(conn, tenants::DEFAULT, 42, None)?;
+            timezone::set_timezone(conn, 42, chrono_tz::UTC)?;
+            // В 10 утра утренний приём первого дня уже прошёл
+            let plan = parse_plan("08:00,20:00 for 2 days amoxicillin").unwrap();
+            let (course_id, count) = create(conn, tenants::DEFAULT, user_id, 42, Lang::Ru, &plan, at("2030-01-01 10:00:00"))?;
+            let mut stmt = conn.prepare("SELECT id FROM events WHERE course_id = ? ORDER BY event_utc LIMIT 2")?;
+            let taken = stmt.query_map(params![course_id], |row| row.get::<_, i64>(0))?.collect::<Result<Vec<_>, _>>()?;
+            for event_id in taken {
+                conn.execute("UPDATE events SET status = 'sent' WHERE id = ?", params![event_id])?;
+                complete_event(conn, event_id)?;
+            }
+            assert!(queue_summaries(conn, at("2030-01-02 21:00:00"))?.is_empty());
+            assert_eq!(queue_summaries(conn, at("2030-01-02 23:00:00"))?, vec![course_id]);
+            let summary = conn.query_row("SELECT text FROM outbox", [], |row| row.get(0)).map(crate::crypto::open)?;
+            Ok((count, summary))
+        }).await.unwrap();
+        assert_eq!(count, 3);
+        assert_eq!(summary, "💊 Курс «amoxicillin» завершён: принято 2 из 3 (66%)");
+    }
+}
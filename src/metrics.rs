@@ -0,0 +1,153 @@
+use axum::extract::State;
+use axum::http::{StatusCode, header};
+use axum::response::{IntoResponse, Response};
+use rusqlite::{Connection, params};
+
+use crate::Db;
+use crate::privacy;
+
+/// За сколько дней `/admin usage` показывает статистику по умолчанию.
+pub const DEFAULT_DAYS: u32 = 7;
+const MAX_FEATURE_LEN: usize = 24;
+
+pub fn init_tables(conn: &Connection) -> Result<(), rusqlite::Error> {
+    // Счётчики использования по дням без привязки к пользователям
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS usage_metrics (
+            day TEXT NOT NULL,
+            feature TEXT NOT NULL,
+            count INTEGER NOT NULL DEFAULT 0,
+            PRIMARY KEY(day, feature)
+        )",
+        [],
+    )?;
+    // Задержка доставки напоминаний по дням: сумма для среднего и максимум
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS delivery_lag (
+            day TEXT PRIMARY KEY,
+            count INTEGER NOT NULL DEFAULT 0,
+            total_secs INTEGER NOT NULL DEFAULT 0,
+            max_secs INTEGER NOT NULL DEFAULT 0
+        )",
+        [],
+    )?;
+    Ok(())
+}
+
+fn record(conn: &Connection, feature: &str) -> Result<(), rusqlite::Error> {
+    conn.execute(
+        "INSERT INTO usage_metrics (day, feature, count) VALUES (date('now'), ?, 1)
+         ON CONFLICT(day, feature) DO UPDATE SET count = count + 1",
+        params![feature],
+    )?;
+    Ok(())
+}
+
+/// Учитывает задержку доставки напоминания в секундах.
+pub fn record_lag(conn: &Connection, lag_secs: i64) -> Result<(), rusqlite::Error> {
+    conn.execute(
+        "INSERT INTO delivery_lag (day, count, total_secs, max_secs) VALUES (date('now'), 1, ?1, ?1)
+         ON CONFLICT(day) DO UPDATE SET count = count + 1, total_secs = total_secs + ?1, max_secs = MAX(max_secs, ?1)",
+        params![lag_secs],
+    )?;
+    Ok(())
+}
+
+/// Число доставленных напоминаний, средняя и наибольшая задержка в секундах за последние `days` дней.
+pub fn lag(conn: &Connection, days: u32) -> Result<Option<(i64, i64, i64)>, rusqlite::Error> {
+    let (count, total, max): (i64, i64, i64) = conn.query_row(
+        "SELECT COALESCE(SUM(count), 0), COALESCE(SUM(total_secs), 0), COALESCE(MAX(max_secs), 0)
+         FROM delivery_lag WHERE day > date('now', ?)",
+        params![format!("-{} days", days)],
+        |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+    )?;
+    Ok((count > 0).then(|| (count, total / count, max)))
+}
+
+/// Название команды для счётчика: `/events@bot sort:time` → `/events`.
+/// Непохожие на команду строки считаются вместе, чтобы произвольный текст не попадал в метрики.
+pub fn command_feature(text: &str) -> String {
+    let command = text.split_whitespace().next().unwrap_or_default();
+    let command = command.split('@').next().unwrap_or_default();
+    let valid = command.len() <= MAX_FEATURE_LEN
+        && command.strip_prefix('/').is_some_and(|name| !name.is_empty() && name.chars().all(|c| c.is_ascii_lowercase()));
+    if valid { command.to_string() } else { "/unknown".to_string() }
+}
+
+/// Название кнопки для счётчика по префиксу данных: `ack` → `button:ack`.
+pub fn button_feature(prefix: &str) -> String {
+    let valid = prefix.len() <= MAX_FEATURE_LEN && prefix.chars().all(|c| c.is_ascii_lowercase());
+    if valid { format!("button:{}", prefix) } else { "button:unknown".to_string() }
+}
+
+/// Увеличивает счётчик функции за сегодня, если пользователь не включил `/privacy`.
+/// Ошибка записи не мешает обработке сообщения.
+pub async fn track(db: &Db, telegram_id: Option<i64>, feature: impl Into<String>) {
+    let feature = feature.into();
+    let result = db.call(move |conn| {
+        if telegram_id.map(|id| privacy::is_private(conn, id)).transpose()?.unwrap_or(false) {
+            return Ok(());
+        }
+        record(conn, &feature)
+    }).await;
+    if let Err(e) = result {
+        log::error!("Failed to record usage metric: {}", e);
+    }
+}
+
+/// Суммы по функциям за последние `days` дней, самые популярные первыми.
+pub fn usage(conn: &Connection, days: u32) -> Result<Vec<(String, i64)>, rusqlite::Error> {
+    let mut stmt = conn.prepare(
+        "SELECT feature, SUM(count) AS total FROM usage_metrics
+         WHERE day > date('now', ?)
+         GROUP BY feature
+         ORDER BY total DESC, feature",
+    )?;
+    let rows = stmt.query_map(params![format!("-{} days", days)], |row| Ok((row.get(0)?, row.get(1)?)))?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(rows)
+}
+
+/// Счётчики в текстовом формате Prometheus: сумма за всё время по каждой функции.
+fn exposition(conn: &Connection) -> Result<String, rusqlite::Error> {
+    let mut stmt = conn.prepare("SELECT feature, SUM(count) FROM usage_metrics GROUP BY feature ORDER BY feature")?;
+    let rows = stmt.query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?)))?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut body = String::from(
+        "# HELP reventor_feature_usage_total Number of times a command or feature was used.\n\
+         # TYPE reventor_feature_usage_total counter\n",
+    );
+    for (feature, total) in rows {
+        let feature = feature.replace('\\', "\\\\").replace('"', "\\\"");
+        body.push_str(&format!("reventor_feature_usage_total{{feature=\"{}\"}} {}\n", feature, total));
+    }
+
+    let (count, total, max): (i64, i64, i64) = conn.query_row(
+        "SELECT COALESCE(SUM(count), 0), COALESCE(SUM(total_secs), 0), COALESCE(MAX(max_secs), 0) FROM delivery_lag",
+        [],
+        |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+    )?;
+    body.push_str(&format!(
+        "# HELP reventor_notification_lag_seconds Delay between the event time and the reminder delivery.\n\
+         # TYPE reventor_notification_lag_seconds summary\n\
+         reventor_notification_lag_seconds_sum {}\n\
+         reventor_notification_lag_seconds_count {}\n\
+         # HELP reventor_notification_lag_seconds_max Largest reminder delivery delay.\n\
+         # TYPE reventor_notification_lag_seconds_max gauge\n\
+         reventor_notification_lag_seconds_max {}\n",
+        total, count, max,
+    ));
+    Ok(body)
+}
+
+/// `GET /metrics` для Prometheus.
+pub async fn handle_metrics(State(db): State<Db>) -> Response {
+    match db.call(exposition).await {
+        Ok(body) => ([(header::CONTENT_TYPE, "text/plain; version=0.0.4")], body).into_response(),
+        Err(e) => {
+            log::error!("Failed to export metrics: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
@@ -0,0 +1,51 @@
+use chrono::NaiveDateTime;
+use rusqlite::{Connection, params};
+
+use crate::{DB_PATH, EVENT_TIME_FORMAT, UTC_FORMAT, in_transaction, init_db, storage, timezone};
+
+/// `reventor migrate-done [--dry-run]` — переводит старые события с `event_time = 'done'`
+/// на статус `done`. Исходное время потеряно, вместо него берётся время создания события.
+pub fn run(args: &[String]) -> Result<(), String> {
+    let dry_run = args.iter().any(|arg| arg == "--dry-run");
+    let conn = storage::open(DB_PATH).map_err(|e| format!("Failed to open database: {}", e))?;
+    // Колонки status и event_utc появились позже самих строк
+    init_db(&conn).map_err(|e| format!("Failed to initialize database: {}", e))?;
+
+    let migrated = in_transaction(&conn, |tx| migrate_done(tx, dry_run))
+        .map_err(|e| format!("Migration failed: {}", e))?;
+    if dry_run {
+        println!("{} legacy 'done' events would be migrated", migrated);
+    } else {
+        println!("Migrated {} legacy 'done' events", migrated);
+    }
+    Ok(())
+}
+
+pub fn migrate_done(conn: &Connection, dry_run: bool) -> Result<usize, rusqlite::Error> {
+    let rows = {
+        let mut stmt = conn.prepare("SELECT id, user_id, created_at FROM events WHERE event_time = 'done'")?;
+        let rows = stmt
+            .query_map([], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, i64>(1)?, row.get::<_, String>(2)?)))?
+            .collect::<Result<Vec<_>, _>>()?;
+        rows
+    };
+
+    for (id, user_id, created_at) in &rows {
+        // created_at пишется SQLite в UTC
+        let Ok(created_utc) = NaiveDateTime::parse_from_str(created_at, UTC_FORMAT) else {
+            log::error!("Event {} has unreadable created_at {:?}, skipped", id, created_at);
+            continue;
+        };
+        let local = timezone::from_utc(created_utc, timezone::user_zone(conn, *user_id)?);
+        let event_time = local.format(EVENT_TIME_FORMAT).to_string();
+        println!("Event {}: 'done' -> {} (status done)", id, event_time);
+        if dry_run {
+            continue;
+        }
+        conn.execute(
+            "UPDATE events SET event_time = ?, event_utc = ?, status = 'done' WHERE id = ?",
+            params![event_time, created_utc.format(UTC_FORMAT).to_string(), id],
+        )?;
+    }
+    Ok(rows.len())
+}
@@ -0,0 +1,254 @@
+//! Публикация напоминаний в MQTT, чтобы Home Assistant и похожие системы мигали светом или
+//! объявляли напоминание на колонках. Свой минимальный клиент MQTT 3.1.1: только CONNECT и
+//! PUBLISH с QoS 1, больше боту не нужно.
+
+use std::env;
+use std::sync::OnceLock;
+use std::time::Duration;
+
+use reqwest::Url;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+/// Сколько ждать брокер на всю публикацию: подключение, подтверждение и ответ.
+const TIMEOUT: Duration = Duration::from_secs(30);
+/// Keep-alive в CONNECT; соединение живёт одну публикацию, но брокеры требуют значение.
+const KEEP_ALIVE_SECS: u16 = 30;
+/// Номер пакета единственной публикации в соединении.
+const PACKET_ID: u16 = 1;
+
+/// Брокер из настроек сервера.
+#[derive(Debug)]
+pub struct MqttConfig {
+    host: String,
+    port: u16,
+    tls: bool,
+    username: Option<String>,
+    password: Option<String>,
+    client_id: String,
+    prefix: String,
+}
+
+/// Настройки из `MQTT_URL` (`mqtt://host:1883` или `mqtts://host:8883` с TLS), необязательных
+/// `MQTT_USERNAME`, `MQTT_PASSWORD`, `MQTT_CLIENT_ID` (reventor) и `MQTT_TOPIC_PREFIX` (reventor).
+/// Без адреса канал выключен.
+pub fn config() -> Option<&'static MqttConfig> {
+    static CONFIG: OnceLock<Option<MqttConfig>> = OnceLock::new();
+    CONFIG.get_or_init(|| {
+        let url = env::var("MQTT_URL").ok()?;
+        let parsed = Url::parse(&url)
+            .ok()
+            .filter(|parsed| matches!(parsed.scheme(), "mqtt" | "mqtts"))
+            .and_then(|parsed| Some((parsed.host_str()?.to_string(), parsed)));
+        let Some((host, parsed)) = parsed else {
+            log::error!("Invalid MQTT_URL: {}", url);
+            return None;
+        };
+        let tls = parsed.scheme() == "mqtts";
+        Some(MqttConfig {
+            host,
+            port: parsed.port().unwrap_or(if tls { 8883 } else { 1883 }),
+            tls,
+            username: env::var("MQTT_USERNAME").ok().filter(|name| !name.is_empty()),
+            password: env::var("MQTT_PASSWORD").ok().filter(|password| !password.is_empty()),
+            client_id: env::var("MQTT_CLIENT_ID").unwrap_or_else(|_| "reventor".to_string()),
+            prefix: env::var("MQTT_TOPIC_PREFIX").unwrap_or_else(|_| "reventor".to_string()).trim_end_matches('/').to_string(),
+        })
+    }).as_ref()
+}
+
+/// Тема пользователя: `<префикс>/<telegram id>/<имя>`. Имя выбирает пользователь, остальное —
+/// сервер, чтобы никто не писал в чужие темы брокера.
+pub fn topic(config: &MqttConfig, telegram_id: i64, name: &str) -> String {
+    format!("{}/{}/{}", config.prefix, telegram_id, name)
+}
+
+/// Имя темы из `/mirror mqtt <имя>`: латиница, цифры, `_` и `-`, без спецсимволов MQTT.
+pub fn valid_name(name: &str) -> bool {
+    !name.is_empty() && name.len() <= 64 && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+}
+
+/// Длина остатка пакета: по 7 бит в байте, старший бит — «дальше ещё».
+fn remaining_length(mut length: usize, packet: &mut Vec<u8>) {
+    loop {
+        let mut byte = (length % 128) as u8;
+        length /= 128;
+        if length > 0 {
+            byte |= 0x80;
+        }
+        packet.push(byte);
+        if length == 0 {
+            break;
+        }
+    }
+}
+
+fn string(value: &str, out: &mut Vec<u8>) {
+    out.extend_from_slice(&(value.len() as u16).to_be_bytes());
+    out.extend_from_slice(value.as_bytes());
+}
+
+fn packet(header: u8, body: &[u8]) -> Vec<u8> {
+    let mut packet = vec![header];
+    remaining_length(body.len(), &mut packet);
+    packet.extend_from_slice(body);
+    packet
+}
+
+fn connect_packet(config: &MqttConfig) -> Vec<u8> {
+    let mut flags = 0x02; // clean session
+    let mut body = Vec::new();
+    string("MQTT", &mut body);
+    body.push(4); // MQTT 3.1.1
+    if config.username.is_some() {
+        flags |= 0x80;
+    }
+    if config.password.is_some() {
+        flags |= 0x40;
+    }
+    body.push(flags);
+    body.extend_from_slice(&KEEP_ALIVE_SECS.to_be_bytes());
+    string(&config.client_id, &mut body);
+    for value in [&config.username, &config.password].into_iter().flatten() {
+        string(value, &mut body);
+    }
+    packet(0x10, &body)
+}
+
+fn publish_packet(topic: &str, payload: &[u8]) -> Vec<u8> {
+    let mut body = Vec::new();
+    string(topic, &mut body);
+    body.extend_from_slice(&PACKET_ID.to_be_bytes());
+    body.extend_from_slice(payload);
+    packet(0x32, &body) // PUBLISH, QoS 1
+}
+
+/// Ошибка публикации и стоит ли повторять: отказ брокера в доступе повтором не исправится.
+pub type PublishError = (String, bool);
+
+async fn read_packet<S: AsyncRead + Unpin>(stream: &mut S) -> Result<(u8, Vec<u8>), PublishError> {
+    let io = |e: std::io::Error| (e.to_string(), true);
+    let header = stream.read_u8().await.map_err(io)?;
+    let (mut length, mut shift) = (0usize, 0);
+    loop {
+        let byte = stream.read_u8().await.map_err(io)?;
+        length |= ((byte & 0x7f) as usize) << shift;
+        shift += 7;
+        if byte & 0x80 == 0 {
+            break;
+        }
+        if shift > 21 {
+            return Err(("malformed packet length".to_string(), true));
+        }
+    }
+    let mut body = vec![0; length];
+    stream.read_exact(&mut body).await.map_err(io)?;
+    Ok((header, body))
+}
+
+/// Подключение, публикация с подтверждением и отключение поверх готового соединения.
+async fn exchange<S: AsyncRead + AsyncWrite + Unpin>(stream: &mut S, config: &MqttConfig, topic: &str, payload: &[u8]) -> Result<(), PublishError> {
+    let io = |e: std::io::Error| (e.to_string(), true);
+    stream.write_all(&connect_packet(config)).await.map_err(io)?;
+    match read_packet(stream).await? {
+        (0x20, body) if body.len() == 2 && body[1] == 0 => {}
+        // 4 — неверный логин или пароль, 5 — нет прав
+        (0x20, body) if body.len() == 2 => return Err((format!("broker refused connection (code {})", body[1]), !matches!(body[1], 4 | 5))),
+        (header, _) => return Err((format!("unexpected packet 0x{:02x} instead of CONNACK", header), true)),
+    }
+    stream.write_all(&publish_packet(topic, payload)).await.map_err(io)?;
+    match read_packet(stream).await? {
+        (0x40, body) if body == PACKET_ID.to_be_bytes() => {}
+        (header, _) => return Err((format!("unexpected packet 0x{:02x} instead of PUBACK", header), true)),
+    }
+    stream.write_all(&[0xe0, 0x00]).await.map_err(io)?;
+    stream.shutdown().await.map_err(io)
+}
+
+#[cfg(feature = "mqtt")]
+async fn exchange_tls(stream: TcpStream, config: &MqttConfig, topic: &str, payload: &[u8]) -> Result<(), PublishError> {
+    let connector = tokio_native_tls::native_tls::TlsConnector::new().map_err(|e| (e.to_string(), false))?;
+    let mut stream = tokio_native_tls::TlsConnector::from(connector)
+        .connect(&config.host, stream)
+        .await
+        .map_err(|e| (e.to_string(), true))?;
+    exchange(&mut stream, config, topic, payload).await
+}
+
+#[cfg(not(feature = "mqtt"))]
+async fn exchange_tls(_stream: TcpStream, _config: &MqttConfig, _topic: &str, _payload: &[u8]) -> Result<(), PublishError> {
+    Err(("mqtts:// needs the mqtt feature".to_string(), false))
+}
+
+/// Публикует сообщение с QoS 1 и ждёт подтверждения брокера.
+pub async fn publish(config: &MqttConfig, topic: &str, payload: &[u8]) -> Result<(), PublishError> {
+    let work = async {
+        let mut stream = TcpStream::connect((config.host.as_str(), config.port)).await.map_err(|e| (e.to_string(), true))?;
+        if config.tls {
+            exchange_tls(stream, config, topic, payload).await
+        } else {
+            exchange(&mut stream, config, topic, payload).await
+        }
+    };
+    tokio::time::timeout(TIMEOUT, work).await.map_err(|_| ("broker timed out".to_string(), true))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> MqttConfig {
+        MqttConfig {
+            host: "localhost".to_string(),
+            port: 1883,
+            tls: false,
+            username: Some("ha".to_string()),
+            password: Some("pw".to_string()),
+            client_id: "reventor".to_string(),
+            prefix: "reventor".to_string(),
+        }
+    }
+
+    #[test]
+    fn lengths_use_variable_byte_encoding() {
+        for (length, expected) in [(0, vec![0x00]), (127, vec![0x7f]), (128, vec![0x80, 0x01]), (16_383, vec![0xff, 0x7f]), (16_384, vec![0x80, 0x80, 0x01])] {
+            let mut encoded = Vec::new();
+            remaining_length(length, &mut encoded);
+            assert_eq!(encoded, expected, "{}", length);
+        }
+        assert!(valid_name("kitchen-lights"));
+        assert!(!valid_name("a/#"));
+    }
+
+    #[tokio::test]
+    async fn publishes_after_connack_and_waits_for_puback() {
+        let (mut client, mut broker) = tokio::io::duplex(1024);
+        let broker = tokio::spawn(async move {
+            let connect = read_packet(&mut broker).await.unwrap();
+            broker.write_all(&[0x20, 0x02, 0x00, 0x00]).await.unwrap();
+            let publish = read_packet(&mut broker).await.unwrap();
+            broker.write_all(&[0x40, 0x02, 0x00, 0x01]).await.unwrap();
+            let disconnect = read_packet(&mut broker).await.unwrap();
+            (connect, publish, disconnect)
+        });
+        exchange(&mut client, &config(), "reventor/42/lights", b"{}").await.unwrap();
+        let ((connect_header, connect), (publish_header, publish), (disconnect, _)) = broker.await.unwrap();
+        assert_eq!(connect_header, 0x10);
+        assert_eq!(&connect[..7], b"\x00\x04MQTT\x04");
+        assert_eq!(connect[7], 0xc2);
+        assert_eq!(publish_header, 0x32);
+        assert_eq!(publish, [b"\x00\x12reventor/42/lights".as_slice(), b"\x00\x01{}"].concat());
+        assert_eq!(disconnect, 0xe0);
+    }
+
+    #[tokio::test]
+    async fn rejected_credentials_are_not_retried() {
+        let (mut client, mut broker) = tokio::io::duplex(1024);
+        tokio::spawn(async move {
+            read_packet(&mut broker).await.unwrap();
+            broker.write_all(&[0x20, 0x02, 0x00, 0x05]).await.unwrap();
+        });
+        let error = exchange(&mut client, &config(), "reventor/42/lights", b"{}").await.unwrap_err();
+        assert!(!error.1, "{}", error.0);
+    }
+}
@@ -0,0 +1,80 @@
+use chrono::NaiveDateTime;
+use rusqlite::{Connection, params};
+
+use crate::{UTC_FORMAT, add_column_if_missing, in_transaction, schedule_next_occurrence};
+use crate::outbox::{self, Outgoing};
+
+pub fn init_tables(conn: &Connection) -> Result<(), rusqlite::Error> {
+    // Журнал отправленных напоминаний: одно событие на один момент времени напоминается один раз.
+    // Перенос события меняет event_utc, поэтому новое время даёт новую запись.
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS sent_notifications (
+            event_id INTEGER NOT NULL,
+            event_utc TEXT NOT NULL,
+            chat_id INTEGER NOT NULL,
+            message_id INTEGER,
+            created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            PRIMARY KEY(event_id, event_utc),
+            FOREIGN KEY(event_id) REFERENCES events(id) ON DELETE CASCADE
+        )",
+        [],
+    )?;
+    // Журнал доставки: когда напоминание дошло и на сколько секунд позже времени события
+    add_column_if_missing(conn, "sent_notifications", "delivered_utc", "TEXT")?;
+    add_column_if_missing(conn, "sent_notifications", "lag_secs", "INTEGER")?;
+    Ok(())
+}
+
+/// Помечает события отправленными и ставит напоминания в очередь одной транзакцией на весь проход:
+/// при всплеске напоминаний база блокируется один раз, а не на каждое событие. Отправитель доставит
+/// напоминание не раньше времени события, даже если планировщик поставил его заранее.
+/// Для каждого сообщения возвращает `false`, если напоминание на этот момент уже было поставлено
+/// (например, до перезапуска) или сообщение не о событии.
+pub fn claim(conn: &Connection, messages: &[Outgoing], now: NaiveDateTime) -> Result<Vec<bool>, rusqlite::Error> {
+    in_transaction(conn, |tx| {
+        let mut record = tx.prepare_cached(
+            "INSERT OR IGNORE INTO sent_notifications (event_id, event_utc, chat_id) VALUES (?, ?, ?)",
+        )?;
+        let mut mark_sent = tx.prepare_cached("UPDATE events SET status = 'sent' WHERE id = ? AND status = 'pending'")?;
+        messages
+            .iter()
+            .map(|message| {
+                let Some(reminder) = &message.reminder else {
+                    return Ok(false);
+                };
+                if record.execute(params![reminder.event_id, reminder.event_utc, message.chat_id])? == 0 {
+                    return Ok(false);
+                }
+                mark_sent.execute(params![reminder.event_id])?;
+                schedule_next_occurrence(tx, reminder.event_id, now)?;
+                let send_at = NaiveDateTime::parse_from_str(&reminder.event_utc, UTC_FORMAT).map_or(now, |at| at.max(now));
+                outbox::push(tx, message, send_at)?;
+                Ok(true)
+            })
+            .collect()
+    })
+}
+
+/// Запоминает сообщение, которым доставлено напоминание.
+pub fn confirm(conn: &Connection, event_id: i64, event_utc: &str, message_id: i32) -> Result<(), rusqlite::Error> {
+    conn.execute(
+        "UPDATE sent_notifications SET message_id = ? WHERE event_id = ? AND event_utc = ?",
+        params![message_id, event_id, event_utc],
+    )?;
+    Ok(())
+}
+
+/// Записывает время доставки и задержку относительно времени события. Возвращает задержку
+/// в секундах, если доставка первая: повторы напоминания её не меняют.
+pub fn record_delivery(conn: &Connection, event_id: i64, event_utc: &str, delivered: NaiveDateTime) -> Result<Option<i64>, rusqlite::Error> {
+    let Ok(scheduled) = NaiveDateTime::parse_from_str(event_utc, UTC_FORMAT) else {
+        return Ok(None);
+    };
+    let lag = (delivered - scheduled).num_seconds().max(0);
+    let updated = conn.execute(
+        "UPDATE sent_notifications SET delivered_utc = ?, lag_secs = ?
+         WHERE event_id = ? AND event_utc = ? AND delivered_utc IS NULL",
+        params![delivered.format(UTC_FORMAT).to_string(), lag, event_id, event_utc],
+    )?;
+    Ok((updated > 0).then_some(lag))
+}
@@ -0,0 +1,516 @@
+use std::time::Duration as StdDuration;
+
+use teloxide::prelude::*;
+use aes_gcm::aead::OsRng;
+use aes_gcm::aead::rand_core::RngCore;
+use chrono::{Duration, NaiveDateTime, Utc};
+use hmac::{Hmac, Mac};
+use reqwest::StatusCode;
+use rusqlite::{Connection, params, OptionalExtension};
+use sha2::Sha256;
+
+use crate::chunks;
+use crate::mqtt;
+use crate::shards;
+use crate::i18n::{t, tf, Lang};
+use crate::subscriptions::is_public;
+use crate::{DatabaseError, Db, UTC_FORMAT, add_column_if_missing, crypto, ensure_user_exists, tenants};
+
+/// Ожидание ответа стороннего сервиса: медленный вебхук не должен задерживать напоминания.
+const REQUEST_TIMEOUT: StdDuration = StdDuration::from_secs(10);
+/// Как часто очередь доставок проверяется на наступившие повторы.
+const POLL_INTERVAL: StdDuration = StdDuration::from_secs(5);
+/// Сколько доставок отправляется за проход.
+const BATCH: i64 = 50;
+/// После стольких неудач подряд доставка бросается.
+const MAX_ATTEMPTS: u32 = 8;
+/// Пауза перед повтором после первой неудачи; дальше удваивается до `MAX_RETRY_DELAY`.
+const RETRY_DELAY: Duration = Duration::seconds(30);
+const MAX_RETRY_DELAY: Duration = Duration::hours(1);
+
+/// Канал, куда дублируются напоминания помимо Telegram. Slack, Discord и MQTT включаются
+/// отдельными фичами сборки (`slack`, `discord`, `mqtt`); без них `/mirror` сообщает, что зеркалирование
+/// недоступно. Свой вебхук (`/webhook`) доступен всегда.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Kind {
+    Slack,
+    Discord,
+    Mqtt,
+    Webhook,
+}
+
+impl Kind {
+    const ALL: [Kind; 4] = [Kind::Slack, Kind::Discord, Kind::Mqtt, Kind::Webhook];
+
+    pub fn code(self) -> &'static str {
+        match self {
+            Kind::Slack => "slack",
+            Kind::Discord => "discord",
+            Kind::Mqtt => "mqtt",
+            Kind::Webhook => "webhook",
+        }
+    }
+
+    fn enabled(self) -> bool {
+        match self {
+            Kind::Slack => cfg!(feature = "slack"),
+            Kind::Discord => cfg!(feature = "discord"),
+            // Брокер общий для всех и задаётся в настройках сервера
+            Kind::Mqtt => cfg!(feature = "mqtt") && mqtt::config().is_some(),
+            Kind::Webhook => true,
+        }
+    }
+
+    /// Каналы, включённые в этой сборке.
+    fn available() -> Vec<Kind> {
+        Self::ALL.into_iter().filter(|kind| kind.enabled()).collect()
+    }
+
+    /// Каналы для `/mirror`: сервисы со своими входящими вебхуками.
+    fn mirrors() -> Vec<Kind> {
+        Self::available().into_iter().filter(|kind| *kind != Kind::Webhook).collect()
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        Self::available().into_iter().find(|kind| kind.code() == value.to_lowercase())
+    }
+
+    /// Для Slack и Discord принимаются только адреса входящих вебхуков самого сервиса, для своего
+    /// вебхука — любой HTTPS; что адрес не ведёт во внутреннюю сеть, проверяется при каждой доставке.
+    /// У MQTT вместо адреса — имя темы пользователя.
+    fn accepts(self, url: &str) -> bool {
+        let prefixes: &[&str] = match self {
+            Kind::Slack => &["https://hooks.slack.com/"],
+            Kind::Discord => &["https://discord.com/api/webhooks/", "https://discordapp.com/api/webhooks/"],
+            Kind::Mqtt => return mqtt::valid_name(url),
+            Kind::Webhook => &["https://"],
+        };
+        prefixes.iter().any(|prefix| url.starts_with(prefix))
+    }
+
+    /// Тело запроса: поле с текстом и ограничение сервиса на длину сообщения. Свой вебхук и MQTT получают
+    /// ещё тип события (`reminder` или `test`) и номер доставки, одинаковый у всех повторов.
+    fn payload(self, event: &str, delivery: i64, text: &str) -> serde_json::Value {
+        let text = |limit: usize| text.chars().take(limit).collect::<String>();
+        match self {
+            Kind::Slack => serde_json::json!({ "text": text(40_000) }),
+            Kind::Discord => serde_json::json!({ "content": text(2000) }),
+            Kind::Mqtt | Kind::Webhook => serde_json::json!({
+                "type": event,
+                "delivery": delivery,
+                "text": text(4096),
+                "sent_at": Utc::now().to_rfc3339(),
+            }),
+        }
+    }
+}
+
+/// Вебхук пользователя: канал, адрес и секрет подписи.
+struct Webhook {
+    kind: Kind,
+    /// У MQTT — имя темы.
+    url: String,
+    /// `None` у вебхуков, заведённых до появления подписи.
+    secret: Option<String>,
+}
+
+pub fn init_tables(conn: &Connection) -> Result<(), rusqlite::Error> {
+    // Адрес вебхука даёт право писать в чужой канал, поэтому хранится зашифрованным
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS mirror_webhooks (
+            user_id INTEGER NOT NULL,
+            kind TEXT NOT NULL,
+            url TEXT NOT NULL,
+            created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            PRIMARY KEY(user_id, kind),
+            FOREIGN KEY(user_id) REFERENCES users(id) ON DELETE CASCADE
+        )",
+        [],
+    )?;
+    // Секрет HMAC-подписи запросов, тоже зашифрованный
+    add_column_if_missing(conn, "mirror_webhooks", "secret", "TEXT")?;
+    // Очередь доставок: неудачные повторяются с растущей паузой, как сообщения в outbox
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS mirror_deliveries (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            user_id INTEGER NOT NULL,
+            kind TEXT NOT NULL,
+            text TEXT NOT NULL,
+            status TEXT NOT NULL DEFAULT 'pending',
+            attempts INTEGER NOT NULL DEFAULT 0,
+            next_attempt_utc TEXT NOT NULL,
+            last_error TEXT,
+            created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            FOREIGN KEY(user_id, kind) REFERENCES mirror_webhooks(user_id, kind) ON DELETE CASCADE
+        )",
+        [],
+    )?;
+    conn.execute("CREATE INDEX IF NOT EXISTS idx_mirror_deliveries_due ON mirror_deliveries(status, next_attempt_utc)", [])?;
+    Ok(())
+}
+
+fn generate_secret() -> String {
+    let mut bytes = [0u8; 32];
+    OsRng.fill_bytes(&mut bytes);
+    hex::encode(bytes)
+}
+
+/// Сохраняет вебхук с новым секретом и возвращает секрет: показать его можно только сейчас.
+fn save_webhook(conn: &Connection, user_id: i64, kind: Kind, url: &str) -> Result<String, rusqlite::Error> {
+    let secret = generate_secret();
+    conn.execute(
+        "INSERT INTO mirror_webhooks (user_id, kind, url, secret) VALUES (?, ?, ?, ?)
+         ON CONFLICT(user_id, kind) DO UPDATE SET url = excluded.url, secret = excluded.secret, created_at = CURRENT_TIMESTAMP",
+        params![user_id, kind.code(), crypto::seal(url), crypto::seal(&secret)],
+    )?;
+    Ok(secret)
+}
+
+fn delete_webhook(conn: &Connection, user_id: i64, kind: Kind) -> Result<bool, rusqlite::Error> {
+    let deleted = conn.execute(
+        "DELETE FROM mirror_webhooks WHERE user_id = ? AND kind = ?",
+        params![user_id, kind.code()],
+    )?;
+    Ok(deleted > 0)
+}
+
+/// Вебхуки пользователя; каналы, выключенные в этой сборке, пропускаются.
+fn user_webhooks(conn: &Connection, telegram_id: i64) -> Result<Vec<Webhook>, rusqlite::Error> {
+    let mut stmt = conn.prepare(
+        "SELECT w.kind, w.url, w.secret FROM mirror_webhooks w JOIN users u ON w.user_id = u.id WHERE u.telegram_id = ? ORDER BY w.kind"
+    )?;
+    let webhooks = stmt
+        .query_map(params![telegram_id], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?, row.get::<_, Option<String>>(2)?)))?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(webhooks
+        .into_iter()
+        .filter_map(|(kind, url, secret)| Some(Webhook { kind: Kind::parse(&kind)?, url: crypto::open(url), secret: secret.map(crypto::open) }))
+        .collect())
+}
+
+/// Ставит напоминание в очередь доставки во все каналы владельца события.
+/// Вызывается в той же транзакции, что отмечает сообщение доставленным.
+pub fn mirror(conn: &Connection, telegram_id: i64, text: &str) -> Result<(), rusqlite::Error> {
+    let kinds = Kind::available().iter().map(|kind| format!("'{}'", kind.code())).collect::<Vec<_>>().join(", ");
+    conn.execute(
+        &format!(
+            "INSERT INTO mirror_deliveries (user_id, kind, text, next_attempt_utc)
+             SELECT w.user_id, w.kind, ?, ? FROM mirror_webhooks w JOIN users u ON w.user_id = u.id
+             WHERE u.telegram_id = ? AND w.kind IN ({})",
+            kinds,
+        ),
+        params![crypto::seal(text), Utc::now().naive_utc().format(UTC_FORMAT).to_string(), telegram_id],
+    )?;
+    Ok(())
+}
+
+/// Подпись тела запроса: HMAC-SHA256 от `<timestamp>.<тело>` в hex. Получатель пересчитывает её
+/// своим секретом и сверяет с заголовком `X-Reventor-Signature: sha256=<подпись>`, а по
+/// `X-Reventor-Timestamp` отбрасывает старые запросы.
+fn sign(secret: &str, timestamp: i64, body: &[u8]) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any length");
+    mac.update(format!("{}.", timestamp).as_bytes());
+    mac.update(body);
+    hex::encode(mac.finalize().into_bytes())
+}
+
+/// Одна попытка доставки; при успехе — что ответил получатель. Ошибка — описание и стоит ли
+/// повторять: сетевые сбои, 429 и 5xx повтором могут исправиться, остальные ответы — нет.
+async fn post(client: &reqwest::Client, webhook: &Webhook, telegram_id: i64, event: &str, delivery: i64, text: &str) -> Result<String, (String, bool)> {
+    if webhook.kind == Kind::Mqtt {
+        let config = mqtt::config().ok_or(("MQTT is not configured".to_string(), false))?;
+        let payload = serde_json::to_vec(&webhook.kind.payload(event, delivery, text)).expect("JSON values always serialize");
+        let topic = mqtt::topic(config, telegram_id, &webhook.url);
+        mqtt::publish(config, &topic, &payload).await?;
+        return Ok(topic);
+    }
+    if webhook.kind == Kind::Webhook {
+        let url = reqwest::Url::parse(&webhook.url).map_err(|e| (e.to_string(), false))?;
+        if !is_public(&url).await {
+            return Err(("address is not public".to_string(), false));
+        }
+    }
+    let body = serde_json::to_vec(&webhook.kind.payload(event, delivery, text)).expect("JSON values always serialize");
+    let mut request = client
+        .post(&webhook.url)
+        .timeout(REQUEST_TIMEOUT)
+        .header(reqwest::header::CONTENT_TYPE, "application/json")
+        .header("X-Reventor-Delivery", delivery.to_string());
+    if let Some(secret) = &webhook.secret {
+        let timestamp = Utc::now().timestamp();
+        request = request
+            .header("X-Reventor-Timestamp", timestamp.to_string())
+            .header("X-Reventor-Signature", format!("sha256={}", sign(secret, timestamp, &body)));
+    }
+    match request.body(body).send().await {
+        Ok(response) if response.status().is_success() => Ok(response.status().to_string()),
+        Ok(response) => {
+            let status = response.status();
+            Err((status.to_string(), status.is_server_error() || status == StatusCode::TOO_MANY_REQUESTS))
+        }
+        Err(e) => Err((e.without_url().to_string(), true)),
+    }
+}
+
+/// Доставка из очереди вместе с вебхуком, куда она идёт.
+struct Delivery {
+    id: i64,
+    attempts: u32,
+    telegram_id: i64,
+    text: String,
+    webhook: Webhook,
+}
+
+fn take_due(conn: &Connection, now: NaiveDateTime) -> Result<Vec<Delivery>, rusqlite::Error> {
+    let mut stmt = conn.prepare(
+        "SELECT d.id, d.attempts, u.telegram_id, d.text, d.kind, w.url, w.secret
+         FROM mirror_deliveries d
+         JOIN mirror_webhooks w ON d.user_id = w.user_id AND d.kind = w.kind
+         JOIN users u ON d.user_id = u.id
+         WHERE d.status = 'pending' AND d.next_attempt_utc <= ?
+         ORDER BY d.id
+         LIMIT ?",
+    )?;
+    let rows = stmt.query_map(params![now.format(UTC_FORMAT).to_string(), BATCH], |row| {
+        Ok((
+            row.get::<_, i64>(0)?,
+            row.get::<_, u32>(1)?,
+            row.get::<_, i64>(2)?,
+            row.get::<_, String>(3)?,
+            row.get::<_, String>(4)?,
+            row.get::<_, String>(5)?,
+            row.get::<_, Option<String>>(6)?,
+        ))
+    })?;
+    let rows = rows.collect::<Result<Vec<_>, _>>()?;
+    Ok(rows
+        .into_iter()
+        .filter_map(|(id, attempts, telegram_id, text, kind, url, secret)| {
+            Some(Delivery {
+                id,
+                attempts,
+                telegram_id,
+                text: crypto::open(text),
+                webhook: Webhook { kind: Kind::parse(&kind)?, url: crypto::open(url), secret: secret.map(crypto::open) },
+            })
+        })
+        .collect())
+}
+
+/// Неудачная попытка: повтор с растущей паузой, после `MAX_ATTEMPTS` или неисправимой ошибки — отказ.
+fn failed(conn: &Connection, id: i64, attempts: u32, retry: bool, error: &str, now: NaiveDateTime) -> Result<(), rusqlite::Error> {
+    let attempts = attempts + 1;
+    let status = if retry && attempts < MAX_ATTEMPTS { "pending" } else { "failed" };
+    let delay = (RETRY_DELAY * 2i32.pow(attempts.min(16) - 1)).min(MAX_RETRY_DELAY);
+    conn.execute(
+        "UPDATE mirror_deliveries SET status = ?, attempts = ?, last_error = ?, next_attempt_utc = ? WHERE id = ?",
+        params![status, attempts, error, (now + delay).format(UTC_FORMAT).to_string(), id],
+    )?;
+    Ok(())
+}
+
+/// Один проход по очереди доставок.
+async fn drain(db: &Db, client: &reqwest::Client) {
+    let now = Utc::now().naive_utc();
+    let due = match db.call(move |conn| take_due(conn, now)).await {
+        Ok(due) => due,
+        Err(e) => {
+            log::error!("Failed to read mirror deliveries: {}", e);
+            return;
+        }
+    };
+    for delivery in due {
+        let (id, attempts) = (delivery.id, delivery.attempts);
+        let result = match post(client, &delivery.webhook, delivery.telegram_id, "reminder", id, &delivery.text).await {
+            Ok(_) => db.call(move |conn| conn.execute("DELETE FROM mirror_deliveries WHERE id = ?", params![id]).map(|_| ())).await,
+            Err((error, retry)) => {
+                log::error!(
+                    "Failed to mirror reminder to {} for user {} (attempt {}, retry: {}): {}",
+                    delivery.webhook.kind.code(), delivery.telegram_id, attempts + 1, retry, error,
+                );
+                db.call(move |conn| failed(conn, id, attempts, retry, &error, now)).await
+            }
+        };
+        if let Err(e) = result {
+            log::error!("Failed to update mirror delivery {}: {}", id, e);
+        }
+    }
+}
+
+/// Фоновая доставка напоминаний в Slack, Discord, MQTT и свои вебхуки.
+pub async fn run(db: Db) {
+    let client = reqwest::Client::new();
+    loop {
+        if shards::holds_task(&db, "mirrors", POLL_INTERVAL).await {
+            drain(&db, &client).await;
+        }
+        tokio::time::sleep(POLL_INTERVAL).await;
+    }
+}
+
+fn describe_kinds() -> String {
+    Kind::mirrors().iter().map(|kind| kind.code()).collect::<Vec<_>>().join("|")
+}
+
+fn parse_mirror(value: &str) -> Option<Kind> {
+    Kind::parse(value).filter(|kind| *kind != Kind::Webhook)
+}
+
+/// `/mirror <канал> <url>`, `/mirror off <канал>`, `/mirror` — дублирование напоминаний в Slack или Discord.
+/// Адрес вебхука секретный, поэтому команда работает только в личном чате.
+pub async fn handle_mirror_command(bot: &Bot, msg: &Message, db: &Db, args: &str, lang: Lang) -> ResponseResult<()> {
+    if Kind::mirrors().is_empty() {
+        bot.send_message(msg.chat.id, t(lang, "mirror_unavailable")).await?;
+        return Ok(());
+    }
+    if !msg.chat.is_private() {
+        bot.send_message(msg.chat.id, t(lang, "mirror_private_only")).await?;
+        return Ok(());
+    }
+    let Some(user) = msg.from() else {
+        return Ok(());
+    };
+    let (telegram_id, username, tenant) = (user.id.0 as i64, user.username.clone(), tenants::name_of(bot));
+    let user_id = db.call(move |conn| ensure_user_exists(conn, tenant, telegram_id, username)).await.map_err(DatabaseError)?;
+    let usage = tf(lang, "mirror_usage", &[("kinds", &describe_kinds())]);
+
+    let mut parts = args.split_whitespace();
+    let response = match (parts.next(), parts.next()) {
+        (None, _) => {
+            let webhooks = db.call(move |conn| user_webhooks(conn, telegram_id)).await.map_err(DatabaseError)?;
+            if webhooks.is_empty() {
+                usage
+            } else {
+                let kinds = webhooks.iter().map(|webhook| webhook.kind.code()).collect::<Vec<_>>().join(", ");
+                tf(lang, "mirror_list", &[("kinds", &kinds)])
+            }
+        }
+        (Some("off"), Some(kind)) => match parse_mirror(kind) {
+            Some(kind) => {
+                let deleted = db.call(move |conn| delete_webhook(conn, user_id, kind)).await.map_err(DatabaseError)?;
+                let key = if deleted { "mirror_removed" } else { "mirror_not_found" };
+                tf(lang, key, &[("kind", &kind.code())])
+            }
+            None => usage,
+        },
+        (Some(kind), Some(url)) => match parse_mirror(kind) {
+            Some(kind) if kind.accepts(url) => {
+                let url = url.to_string();
+                db.call(move |conn| save_webhook(conn, user_id, kind, &url)).await.map_err(DatabaseError)?;
+                log::info!("User {} enabled {} mirror", telegram_id, kind.code());
+                tf(lang, "mirror_saved", &[("kind", &kind.code())])
+            }
+            Some(Kind::Mqtt) => t(lang, "mirror_invalid_topic"),
+            Some(kind) => tf(lang, "mirror_invalid_url", &[("kind", &kind.code())]),
+            None => usage,
+        },
+        _ => usage,
+    };
+    chunks::send(bot, msg.chat.id, response).await?;
+    Ok(())
+}
+
+/// `/webhook <https-адрес>`, `/webhook off`, `/webhook test`, `/webhook` — напоминания JSON-запросом
+/// на свой адрес с подписью HMAC. `test` делает пробную доставку во все каналы пользователя и сообщает,
+/// что ответил каждый. Секрет подписи показывается один раз, поэтому команда работает только в личном чате.
+pub async fn handle_webhook_command(bot: &Bot, msg: &Message, db: &Db, args: &str, lang: Lang) -> ResponseResult<()> {
+    if !msg.chat.is_private() {
+        bot.send_message(msg.chat.id, t(lang, "mirror_private_only")).await?;
+        return Ok(());
+    }
+    let Some(user) = msg.from() else {
+        return Ok(());
+    };
+    let (telegram_id, username, tenant) = (user.id.0 as i64, user.username.clone(), tenants::name_of(bot));
+    let user_id = db.call(move |conn| ensure_user_exists(conn, tenant, telegram_id, username)).await.map_err(DatabaseError)?;
+
+    let response = match args.split_whitespace().next() {
+        None => {
+            let enabled = db.call(move |conn| {
+                conn.query_row(
+                    "SELECT 1 FROM mirror_webhooks WHERE user_id = ? AND kind = ?",
+                    params![user_id, Kind::Webhook.code()],
+                    |_| Ok(()),
+                ).optional()
+            }).await.map_err(DatabaseError)?;
+            t(lang, if enabled.is_some() { "webhook_enabled" } else { "webhook_usage" })
+        }
+        Some("off") => {
+            let deleted = db.call(move |conn| delete_webhook(conn, user_id, Kind::Webhook)).await.map_err(DatabaseError)?;
+            t(lang, if deleted { "webhook_removed" } else { "webhook_not_found" })
+        }
+        Some("test") => {
+            let webhooks = db.call(move |conn| user_webhooks(conn, telegram_id)).await.map_err(DatabaseError)?;
+            if webhooks.is_empty() {
+                t(lang, "webhook_test_none")
+            } else {
+                let client = reqwest::Client::new();
+                let mut lines = Vec::new();
+                for webhook in &webhooks {
+                    let line = match post(&client, webhook, telegram_id, "test", 0, &t(lang, "webhook_test_text")).await {
+                        Ok(status) => tf(lang, "webhook_test_ok", &[("kind", &webhook.kind.code()), ("status", &status)]),
+                        Err((error, _)) => tf(lang, "webhook_test_failed", &[("kind", &webhook.kind.code()), ("error", &error)]),
+                    };
+                    lines.push(line);
+                }
+                log::info!("User {} tested {} webhooks", telegram_id, webhooks.len());
+                lines.join("\n")
+            }
+        }
+        Some(url) => match reqwest::Url::parse(url) {
+            Ok(parsed) if Kind::Webhook.accepts(url) && is_public(&parsed).await => {
+                let url = url.to_string();
+                let secret = db.call(move |conn| save_webhook(conn, user_id, Kind::Webhook, &url)).await.map_err(DatabaseError)?;
+                log::info!("User {} enabled a signed webhook", telegram_id);
+                tf(lang, "webhook_saved", &[("secret", &secret)])
+            }
+            _ => t(lang, "webhook_usage"),
+        },
+    };
+    chunks::send(bot, msg.chat.id, response).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::tests::memory_db;
+
+    #[test]
+    fn signature_covers_timestamp_and_body() {
+        // Пример из документации для получателей: echo -n '1700000000.{"type":"test"}' | openssl dgst -sha256 -hmac secret
+        assert_eq!(
+            sign("secret", 1_700_000_000, br#"{"type":"test"}"#),
+            "5164242d2d7c1061af198b4bfea622c8f5aeec1b9276e38d50a14d7f9dd39bee",
+        );
+        assert_ne!(sign("secret", 1_700_000_001, br#"{"type":"test"}"#), sign("secret", 1_700_000_000, br#"{"type":"test"}"#));
+    }
+
+    #[tokio::test]
+    async fn failed_deliveries_back_off_and_give_up() {
+        let db = memory_db();
+        let attempts = db.call(|conn| {
+            let user_id = ensure_user_exists(conn, tenants::DEFAULT, 42, None)?;
+            save_webhook(conn, user_id, Kind::Webhook, "https://example.com/hook")?;
+            mirror(conn, 42, "Напоминание")?;
+            let id = conn.query_row("SELECT id FROM mirror_deliveries", [], |row| row.get::<_, i64>(0))?;
+            let now = NaiveDateTime::parse_from_str("2030-01-01 09:00:00", UTC_FORMAT).unwrap();
+            let mut attempts = Vec::new();
+            for attempt in 0..MAX_ATTEMPTS {
+                failed(conn, id, attempt, true, "503 Service Unavailable", now)?;
+                let (status, next): (String, String) = conn.query_row(
+                    "SELECT status, next_attempt_utc FROM mirror_deliveries WHERE id = ?",
+                    params![id],
+                    |row| Ok((row.get(0)?, row.get(1)?)),
+                )?;
+                let delay = NaiveDateTime::parse_from_str(&next, UTC_FORMAT).unwrap() - now;
+                attempts.push((status, delay.num_seconds()));
+            }
+            Ok(attempts)
+        }).await.unwrap();
+        let delays = attempts.iter().map(|(_, delay)| *delay).collect::<Vec<_>>();
+        assert_eq!(delays, vec![30, 60, 120, 240, 480, 960, 1920, 3600]);
+        assert_eq!(attempts.last().unwrap().0, "failed");
+        assert!(attempts[..7].iter().all(|(status, _)| status == "pending"));
+    }
+}
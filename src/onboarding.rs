@@ -0,0 +1,264 @@
+use teloxide::prelude::*;
+use teloxide::types::{InlineKeyboardButton, InlineKeyboardMarkup};
+use chrono::{Duration, NaiveDateTime, Timelike};
+use rusqlite::{Connection, params, OptionalExtension};
+
+use crate::i18n::{t, tf, Lang};
+use crate::outbox::{self, Outgoing};
+use crate::referrals;
+use crate::settings::{self, Settings};
+use crate::timezone;
+use crate::{DatabaseError, Db, EVENT_TIME_FORMAT, add_column_if_missing, ensure_user_exists, in_transaction, insert_event, tenants};
+
+/// Часовые пояса, которые предлагаются кнопками; остальные задаются через `/timezone`.
+const ZONES: &[&str] = &[
+    "Europe/Kaliningrad",
+    "Europe/Moscow",
+    "Asia/Yekaterinburg",
+    "Asia/Novosibirsk",
+    "Asia/Vladivostok",
+    "Europe/London",
+    "Europe/Berlin",
+    "America/New_York",
+];
+/// Примеры событий в конце знакомства: через сколько минут и ключ текста.
+/// Первое приходит быстро, второе — когда с первым уже разобрались.
+const SAMPLES: &[(i64, &str)] = &[(2, "onboarding_sample_first"), (5, "onboarding_sample_second")];
+
+pub fn init_tables(conn: &Connection) -> Result<(), rusqlite::Error> {
+    // Примеры из знакомства: их удаляет одна кнопка, не трогая настоящие события
+    add_column_if_missing(conn, "events", "sample", "INTEGER NOT NULL DEFAULT 0")?;
+    Ok(())
+}
+
+fn language_keyboard() -> InlineKeyboardMarkup {
+    InlineKeyboardMarkup::new(vec![vec![
+        InlineKeyboardButton::callback("Русский", "onb:lang:ru"),
+        InlineKeyboardButton::callback("English", "onb:lang:en"),
+    ]])
+}
+
+/// По две зоны в ряд и кнопка «Пропустить».
+fn timezone_keyboard(lang: Lang) -> InlineKeyboardMarkup {
+    let mut rows = ZONES
+        .chunks(2)
+        .map(|pair| {
+            pair.iter()
+                .map(|zone| InlineKeyboardButton::callback(zone.replace('_', " "), format!("onb:tz:{}", zone)))
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+    rows.push(vec![InlineKeyboardButton::callback(t(lang, "onboarding_skip"), "onb:tz:skip")]);
+    InlineKeyboardMarkup::new(rows)
+}
+
+fn samples_keyboard(lang: Lang) -> InlineKeyboardMarkup {
+    InlineKeyboardMarkup::new(vec![vec![
+        InlineKeyboardButton::callback(t(lang, "onboarding_samples_create"), "onb:sample:yes"),
+        InlineKeyboardButton::callback(t(lang, "onboarding_skip"), "onb:sample:no"),
+    ]])
+}
+
+fn cleanup_keyboard(lang: Lang) -> InlineKeyboardMarkup {
+    InlineKeyboardMarkup::new(vec![vec![InlineKeyboardButton::callback(t(lang, "onboarding_samples_delete"), "onb:cleanup")]])
+}
+
+/// Создаёт примеры в личном чате, если их ещё нет, и возвращает время каждого.
+fn create_samples(conn: &Connection, tenant: &str, telegram_id: i64, settings: &Settings) -> Result<Vec<NaiveDateTime>, rusqlite::Error> {
+    let user_id = ensure_user_exists(conn, tenant, telegram_id, None)?;
+    let existing: Option<i64> = conn.query_row(
+        "SELECT id FROM events WHERE user_id = ? AND sample = 1 AND status = 'pending' LIMIT 1",
+        params![user_id],
+        |row| row.get(0),
+    ).optional()?;
+    if existing.is_some() {
+        return Ok(Vec::new());
+    }
+
+    let now = timezone::now_in(settings.timezone);
+    let mut times = Vec::new();
+    for (minutes, key) in SAMPLES {
+        let time = (now + Duration::minutes(*minutes)).with_second(0).and_then(|time| time.with_nanosecond(0)).unwrap_or(now);
+        let event_id = insert_event(conn, tenant, user_id, telegram_id, &t(settings.lang, key), &time.format(EVENT_TIME_FORMAT).to_string())?;
+        conn.execute("UPDATE events SET sample = 1 WHERE id = ?", params![event_id])?;
+        times.push(time);
+    }
+    log::info!("Created sample events for user {}", telegram_id);
+    Ok(times)
+}
+
+/// Удаляет примеры пользователя, отправленные и нет; возвращает, сколько удалено.
+fn delete_samples(conn: &Connection, telegram_id: i64) -> Result<usize, rusqlite::Error> {
+    conn.execute(
+        "DELETE FROM events WHERE sample = 1 AND user_id IN (SELECT id FROM users WHERE telegram_id = ?)",
+        params![telegram_id],
+    )
+}
+
+/// После напоминания о последнем примере предлагает их удалить. Вызывается при доставке,
+/// сообщение ставится в очередь сразу за напоминанием.
+pub fn after_reminder(conn: &Connection, tenant: &str, event_id: i64, owner_id: i64, now: NaiveDateTime) -> Result<(), rusqlite::Error> {
+    let last_sample: bool = conn.query_row(
+        "SELECT e.sample = 1 AND NOT EXISTS (
+            SELECT 1 FROM events other WHERE other.user_id = e.user_id AND other.sample = 1 AND other.status = 'pending'
+         )
+         FROM events e WHERE e.id = ?",
+        params![event_id],
+        |row| row.get(0),
+    ).optional()?.unwrap_or(false);
+    if !last_sample {
+        return Ok(());
+    }
+    let lang = settings::resolve(conn, owner_id, owner_id)?.lang;
+    outbox::push(conn, &Outgoing {
+        tenant: tenant.to_string(),
+        chat_id: owner_id,
+        text: t(lang, "onboarding_samples_seen"),
+        reply_markup: Some(cleanup_keyboard(lang)),
+        reminder: None,
+    }, now)?;
+    Ok(())
+}
+
+/// Итог знакомства с двумя примерами, которые можно сразу отправить боту.
+fn finish_text(settings: &Settings) -> String {
+    let now = timezone::now_in(settings.timezone);
+    let soon = (now + Duration::minutes(30)).format("%H:%M").to_string();
+    let tomorrow = (now.date() + Duration::days(1)).format("%d.%m").to_string();
+    tf(settings.lang, "onboarding_done", &[("soon", &soon), ("tomorrow", &tomorrow)])
+}
+
+/// `/start` без кода ссылки на событие: регистрирует пользователя и спрашивает язык,
+/// затем часовой пояс. Язык заранее подбирается по настройкам Telegram. С `ref_<код>`
+/// новый пользователь засчитывается реферальному коду.
+pub async fn handle_start(bot: &Bot, msg: &Message, db: &Db, args: &str, settings: &Settings) -> ResponseResult<()> {
+    let Some(user) = msg.from() else {
+        return Ok(());
+    };
+    let lang = user
+        .language_code
+        .as_deref()
+        .and_then(|code| Lang::parse(code.split('-').next().unwrap_or(code)))
+        .unwrap_or(settings.lang);
+    let (telegram_id, username, tenant) = (user.id.0 as i64, user.username.clone(), tenants::name_of(bot));
+    let referral = referrals::start_code(args).map(str::to_string);
+    db.call(move |conn| {
+        if let Some(code) = referral {
+            referrals::attribute(conn, tenant, telegram_id, &code)?;
+        }
+        ensure_user_exists(conn, tenant, telegram_id, username)
+    }).await.map_err(DatabaseError)?;
+
+    log::info!("Onboarding started for user {}", telegram_id);
+    bot.send_message(msg.chat.id, t(lang, "onboarding_welcome"))
+        .reply_markup(language_keyboard())
+        .await?;
+    Ok(())
+}
+
+/// Кнопки знакомства: `lang:<код>` сохраняет язык, `tz:<зона>` или `tz:skip` завершают настройку,
+/// `sample:yes|no` создаёт примеры событий или отказывается от них, `cleanup` удаляет примеры.
+pub async fn handle_callback(bot: &Bot, q: &CallbackQuery, db: &Db, args: &str) -> ResponseResult<()> {
+    let Some(message) = q.message.as_ref() else {
+        bot.answer_callback_query(q.id.clone()).await?;
+        return Ok(());
+    };
+    let telegram_id = q.from.id.0 as i64;
+
+    match args.split_once(':') {
+        Some(("lang", code)) => {
+            let Some(lang) = Lang::parse(code) else {
+                bot.answer_callback_query(q.id.clone()).await?;
+                return Ok(());
+            };
+            let code = code.to_string();
+            db.call(move |conn| settings::set_user_setting(conn, telegram_id, "language", &code))
+                .await
+                .map_err(DatabaseError)?;
+            bot.answer_callback_query(q.id.clone()).await?;
+            bot.edit_message_text(message.chat.id, message.id, t(lang, "onboarding_timezone"))
+                .reply_markup(timezone_keyboard(lang))
+                .await?;
+        }
+        Some(("tz", zone)) => {
+            let tz = timezone::parse_zone(zone);
+            let chat_id = message.chat.id.0;
+            let settings = db.call(move |conn| {
+                if let Some(tz) = tz {
+                    in_transaction(conn, |tx| timezone::set_timezone(tx, telegram_id, tz))?;
+                }
+                settings::resolve(conn, telegram_id, chat_id)
+            }).await.map_err(DatabaseError)?;
+            bot.answer_callback_query(q.id.clone()).await?;
+            let text = format!("{}\n\n{}", finish_text(&settings), t(settings.lang, "onboarding_samples_offer"));
+            bot.edit_message_text(message.chat.id, message.id, text)
+                .reply_markup(samples_keyboard(settings.lang))
+                .await?;
+        }
+        Some(("sample", answer)) => {
+            let (chat_id, tenant, create) = (message.chat.id.0, tenants::name_of(bot), answer == "yes");
+            let (settings, times) = db.call(move |conn| {
+                let settings = settings::resolve(conn, telegram_id, chat_id)?;
+                let times = match create {
+                    true => in_transaction(conn, |tx| create_samples(tx, tenant, telegram_id, &settings))?,
+                    false => Vec::new(),
+                };
+                Ok((settings, times))
+            }).await.map_err(DatabaseError)?;
+            bot.answer_callback_query(q.id.clone()).await?;
+            if times.is_empty() {
+                bot.edit_message_text(message.chat.id, message.id, finish_text(&settings)).await?;
+            } else {
+                let times = times.iter().map(|time| settings.format_time(time.time())).collect::<Vec<_>>().join(", ");
+                let text = format!("{}\n\n{}", finish_text(&settings), tf(settings.lang, "onboarding_samples_created", &[("times", &times)]));
+                bot.edit_message_text(message.chat.id, message.id, text)
+                    .reply_markup(cleanup_keyboard(settings.lang))
+                    .await?;
+            }
+        }
+        None if args == "cleanup" => {
+            let chat_id = message.chat.id.0;
+            let (lang, deleted) = db.call(move |conn| {
+                Ok((settings::resolve(conn, telegram_id, chat_id)?.lang, delete_samples(conn, telegram_id)?))
+            }).await.map_err(DatabaseError)?;
+            bot.answer_callback_query(q.id.clone()).await?;
+            bot.edit_message_text(message.chat.id, message.id, tf(lang, "onboarding_samples_deleted", &[("count", &deleted)])).await?;
+        }
+        _ => {
+            bot.answer_callback_query(q.id.clone()).await?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::tests::memory_db;
+
+    #[tokio::test]
+    async fn samples_are_offered_for_cleanup_after_the_last_one() {
+        let db = memory_db();
+        let (created, again, offers, deleted, left) = db.call(|conn| {
+            let settings = Settings::default();
+            let created = create_samples(conn, tenants::DEFAULT, 42, &settings)?.len();
+            let again = create_samples(conn, tenants::DEFAULT, 42, &settings)?.len();
+            let user_id = ensure_user_exists(conn, tenants::DEFAULT, 42, None)?;
+            insert_event(conn, tenants::DEFAULT, user_id, 42, "Настоящее событие", "01.01.2030 09:00")?;
+            let samples = conn.prepare("SELECT id FROM events WHERE sample = 1 ORDER BY id")?
+                .query_map([], |row| row.get::<_, i64>(0))?
+                .collect::<Result<Vec<_>, _>>()?;
+            let now = timezone::now_in(None);
+            let mut offers = Vec::new();
+            for event_id in samples {
+                conn.execute("UPDATE events SET status = 'sent' WHERE id = ?", params![event_id])?;
+                after_reminder(conn, tenants::DEFAULT, event_id, 42, now)?;
+                offers.push(conn.query_row("SELECT COUNT(*) FROM outbox", [], |row| row.get::<_, i64>(0))?);
+            }
+            let deleted = delete_samples(conn, 42)?;
+            let left = conn.query_row("SELECT COUNT(*) FROM events", [], |row| row.get::<_, i64>(0))?;
+            Ok((created, again, offers, deleted, left))
+        }).await.unwrap();
+        assert_eq!((created, again, offers, deleted, left), (2, 0, vec![0, 1], 2, 1));
+    }
+}
@@ -0,0 +1,432 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration as StdDuration, Instant};
+
+use futures::stream::{self, StreamExt};
+use teloxide::prelude::*;
+use teloxide::types::InlineKeyboardMarkup;
+use teloxide::RequestError;
+use chrono::{Duration, NaiveDateTime};
+use rusqlite::{Connection, params};
+
+use crate::chunks;
+use crate::clock::{self, Clock};
+use crate::{Db, UTC_FORMAT, config, crypto, metrics, notifications, notifiers, onboarding, repeats, stars, tenants, voice};
+
+/// Наименьшая пауза между отправками одного бота.
+const SEND_INTERVAL: StdDuration = StdDuration::from_millis(35);
+/// Сколько сообщений отправляется за проход.
+const BATCH: i64 = 50;
+/// После стольких сетевых сбоев подряд сообщение помечается неотправленным.
+const MAX_ATTEMPTS: u32 = 10;
+/// Пауза перед повтором после первого сбоя; дальше удваивается до `MAX_RETRY_DELAY`.
+const RETRY_DELAY: Duration = Duration::seconds(5);
+const MAX_RETRY_DELAY: Duration = Duration::minutes(15);
+/// Сколько забранное сообщение принадлежит отправителю; проход с `BATCH` сообщений укладывается с запасом.
+const CLAIM_TTL: Duration = Duration::minutes(5);
+
+/// Напоминание о событии: после доставки сообщение запоминается и, если нужно, закрепляется.
+#[derive(Debug, Clone)]
+pub struct Reminder {
+    pub event_id: i64,
+    pub event_utc: String,
+    /// Telegram id владельца события.
+    pub owner_id: i64,
+    pub starred: bool,
+}
+
+/// Сообщение в очереди на отправку.
+#[derive(Debug, Clone)]
+pub struct Outgoing {
+    pub tenant: String,
+    pub chat_id: i64,
+    pub text: String,
+    pub reply_markup: Option<InlineKeyboardMarkup>,
+    pub reminder: Option<Reminder>,
+}
+
+pub fn init_tables(conn: &Connection) -> Result<(), rusqlite::Error> {
+    // Исходящие сообщения: планировщик пишет сюда в той же транзакции, что отмечает событие
+    // отправленным, а отправитель доставляет их и повторяет при сетевых сбоях
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS outbox (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            tenant TEXT NOT NULL,
+            chat_id INTEGER NOT NULL,
+            text TEXT NOT NULL,
+            reply_markup TEXT,
+            event_id INTEGER,
+            event_utc TEXT,
+            owner_id INTEGER,
+            starred INTEGER NOT NULL DEFAULT 0,
+            status TEXT NOT NULL DEFAULT 'pending',
+            attempts INTEGER NOT NULL DEFAULT 0,
+            next_attempt_utc TEXT NOT NULL,
+            last_error TEXT,
+            created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP
+        )",
+        [],
+    )?;
+    conn.execute("CREATE INDEX IF NOT EXISTS idx_outbox_due ON outbox(status, next_attempt_utc)", [])?;
+    Ok(())
+}
+
+/// Ставит сообщение в очередь; отправлено оно будет не раньше `send_at`.
+/// Вызывается внутри транзакции вместе с изменением, ради которого оно отправляется.
+pub fn push(conn: &Connection, message: &Outgoing, send_at: NaiveDateTime) -> Result<i64, rusqlite::Error> {
+    let reply_markup = message.reply_markup.as_ref().and_then(|markup| serde_json::to_string(markup).ok());
+    let reminder = message.reminder.as_ref();
+    conn.prepare_cached(
+        "INSERT INTO outbox (tenant, chat_id, text, reply_markup, event_id, event_utc, owner_id, starred, next_attempt_utc)
+         VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)",
+    )?.execute(params![
+            message.tenant,
+            message.chat_id,
+            crypto::seal(&message.text),
+            reply_markup,
+            reminder.map(|reminder| reminder.event_id),
+            reminder.map(|reminder| reminder.event_utc.as_str()),
+            reminder.map(|reminder| reminder.owner_id),
+            reminder.is_some_and(|reminder| reminder.starred),
+            send_at.format(UTC_FORMAT).to_string(),
+        ],
+    )?;
+    Ok(conn.last_insert_rowid())
+}
+
+/// Забирает наступившие сообщения одним запросом: они помечаются `sending` до `now + CLAIM_TTL`,
+/// поэтому процессы с общей базой не отправят одно сообщение дважды. Сообщение, забранное
+/// упавшим процессом, по истечении срока снова становится доступным.
+fn take_due(conn: &Connection, now: NaiveDateTime) -> Result<Vec<(i64, u32, Outgoing)>, rusqlite::Error> {
+    let mut stmt = conn.prepare(
+        "UPDATE outbox SET status = 'sending', next_attempt_utc = ?1
+         WHERE id IN (
+             SELECT id FROM outbox o
+             WHERE status IN ('pending', 'sending') AND next_attempt_utc <= ?2
+               AND NOT EXISTS (
+                   SELECT 1 FROM outbox earlier
+                   WHERE earlier.tenant = o.tenant AND earlier.chat_id = o.chat_id AND earlier.id < o.id
+                     AND earlier.status IN ('pending', 'sending') AND earlier.next_attempt_utc > ?2
+               )
+             ORDER BY id
+             LIMIT ?3
+         )
+         RETURNING id, attempts, tenant, chat_id, text, reply_markup, event_id, event_utc, owner_id, starred",
+    )?;
+    let claimed_until = (now + CLAIM_TTL).format(UTC_FORMAT).to_string();
+    let rows = stmt.query_map(params![claimed_until, now.format(UTC_FORMAT).to_string(), BATCH], |row| {
+        let reply_markup: Option<String> = row.get(5)?;
+        let event_id: Option<i64> = row.get(6)?;
+        let reminder = match event_id {
+            Some(event_id) => Some(Reminder {
+                event_id,
+                event_utc: row.get::<_, Option<String>>(7)?.unwrap_or_default(),
+                owner_id: row.get::<_, Option<i64>>(8)?.unwrap_or_default(),
+                starred: row.get(9)?,
+            }),
+            None => None,
+        };
+        Ok((row.get(0)?, row.get(1)?, Outgoing {
+            tenant: row.get(2)?,
+            chat_id: row.get(3)?,
+            text: crypto::open(row.get(4)?),
+            reply_markup: reply_markup.and_then(|markup| serde_json::from_str(&markup).ok()),
+            reminder,
+        }))
+    })?;
+    // RETURNING не сохраняет порядок, а в один чат сообщения уходят по очереди
+    let mut due = rows.collect::<Result<Vec<_>, _>>()?;
+    due.sort_by_key(|(id, _, _)| *id);
+    Ok(due)
+}
+
+/// Доставленное сообщение из очереди больше не нужно.
+fn delivered(conn: &Connection, id: i64) -> Result<(), rusqlite::Error> {
+    conn.execute("DELETE FROM outbox WHERE id = ?", params![id])?;
+    Ok(())
+}
+
+/// Сетевой сбой: повтор с растущей паузой, после `MAX_ATTEMPTS` — отказ.
+/// Ошибка API (бот заблокирован, чат удалён) повтором не исправится.
+fn failed(conn: &Connection, id: i64, attempts: u32, retry: bool, error: &str, now: NaiveDateTime) -> Result<(), rusqlite::Error> {
+    let attempts = attempts + 1;
+    let status = if retry && attempts < MAX_ATTEMPTS { "pending" } else { "failed" };
+    let delay = (RETRY_DELAY * 2i32.pow(attempts.min(16) - 1)).min(MAX_RETRY_DELAY);
+    conn.execute(
+        "UPDATE outbox SET status = ?, attempts = ?, last_error = ?, next_attempt_utc = ? WHERE id = ?",
+        params![status, attempts, error, (now + delay).format(UTC_FORMAT).to_string(), id],
+    )?;
+    Ok(())
+}
+
+/// Telegram попросил подождать: повтор ровно через указанную паузу, попытка не засчитывается.
+fn throttled(conn: &Connection, id: i64, error: &str, until: NaiveDateTime) -> Result<(), rusqlite::Error> {
+    conn.execute(
+        "UPDATE outbox SET status = 'pending', last_error = ?, next_attempt_utc = ? WHERE id = ?",
+        params![error, until.format(UTC_FORMAT).to_string(), id],
+    )?;
+    Ok(())
+}
+
+/// Возвращает в очередь забранные, но не отправленные сообщения чата. Раньше более
+/// раннего сообщения, ждущего повтора, `take_due` их не отдаст.
+fn release(conn: &Connection, ids: &[i64], now: NaiveDateTime) -> Result<(), rusqlite::Error> {
+    for id in ids {
+        conn.execute(
+            "UPDATE outbox SET status = 'pending', next_attempt_utc = ? WHERE id = ? AND status = 'sending'",
+            params![now.format(UTC_FORMAT).to_string(), id],
+        )?;
+    }
+    Ok(())
+}
+
+async fn send(message: &Outgoing) -> ResponseResult<Message> {
+    let request = chunks::send_last(tenants::bot(&message.tenant), ChatId(message.chat_id), message.text.clone()).await?;
+    match message.reply_markup.clone() {
+        Some(markup) => request.reply_markup(markup).await,
+        None => request.await,
+    }
+}
+
+/// Равномерно распределяет отправки одного бота: Telegram ограничивает бота
+/// примерно 30 сообщениями в секунду на все чаты.
+struct RateLimiter {
+    next: Mutex<Instant>,
+}
+
+impl RateLimiter {
+    fn for_tenant(tenant: &str) -> Arc<RateLimiter> {
+        static LIMITERS: OnceLock<Mutex<HashMap<String, Arc<RateLimiter>>>> = OnceLock::new();
+        let mut limiters = LIMITERS.get_or_init(Default::default).lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        limiters
+            .entry(tenant.to_string())
+            .or_insert_with(|| Arc::new(RateLimiter { next: Mutex::new(Instant::now()) }))
+            .clone()
+    }
+
+    /// Ждёт своей очереди: каждая отправка занимает следующий свободный интервал.
+    async fn wait(&self) {
+        let slot = {
+            let mut next = self.next.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+            let slot = (*next).max(Instant::now());
+            *next = slot + SEND_INTERVAL;
+            slot
+        };
+        tokio::time::sleep_until(slot.into()).await;
+    }
+}
+
+/// Сообщения по чатам с сохранением порядка: в один чат они уходят по очереди,
+/// а разные чаты не ждут друг друга.
+fn by_chat(due: Vec<(i64, u32, Outgoing)>) -> Vec<Vec<(i64, u32, Outgoing)>> {
+    let mut chats: Vec<Vec<(i64, u32, Outgoing)>> = Vec::new();
+    for item in due {
+        match chats.iter_mut().find(|chat| chat[0].2.tenant == item.2.tenant && chat[0].2.chat_id == item.2.chat_id) {
+            Some(chat) => chat.push(item),
+            None => chats.push(vec![item]),
+        }
+    }
+    chats
+}
+
+/// `false`, если сообщение ждёт повтора: следующие сообщения чата тогда тоже ждут.
+async fn deliver(db: &Db, clock: &dyn Clock, now: NaiveDateTime, id: i64, attempts: u32, message: Outgoing) -> bool {
+    RateLimiter::for_tenant(&message.tenant).wait().await;
+    let result = match send(&message).await {
+        Ok(sent) => {
+            if let Some(reminder) = message.reminder.clone() {
+                voice::send(&message.tenant, db, message.chat_id, reminder.event_id, reminder.owner_id);
+                if reminder.starred {
+                    stars::pin(tenants::bot(&message.tenant), db, reminder.event_id, &sent).await;
+                }
+            }
+            let delivered_at = clock.now_utc();
+            db.call(move |conn| {
+                if let Some(reminder) = &message.reminder {
+                    notifiers::mirror(conn, reminder.owner_id, &message.text)?;
+                    notifications::confirm(conn, reminder.event_id, &reminder.event_utc, sent.id.0)?;
+                    if let Some(lag) = notifications::record_delivery(conn, reminder.event_id, &reminder.event_utc, delivered_at)? {
+                        metrics::record_lag(conn, lag)?;
+                    }
+                    repeats::arm(conn, reminder.event_id, now)?;
+                    onboarding::after_reminder(conn, &message.tenant, reminder.event_id, reminder.owner_id, now)?;
+                }
+                delivered(conn, id)
+            }).await.map(|_| true)
+        }
+        Err(RequestError::RetryAfter(pause)) => {
+            log::warn!("Telegram asked to retry outbox message {} in {:?}", id, pause);
+            let (error, until) = (format!("retry after {:?}", pause), now + Duration::from_std(pause).unwrap_or(RETRY_DELAY));
+            db.call(move |conn| throttled(conn, id, &error, until)).await.map(|_| false)
+        }
+        Err(e) => {
+            let retry = matches!(e, RequestError::Network(_) | RequestError::Io(_));
+            log::error!("Failed to send outbox message {} (attempt {}, retry: {}): {}", id, attempts + 1, retry, e);
+            let error = e.to_string();
+            db.call(move |conn| failed(conn, id, attempts, retry, &error, now)).await.map(|_| !retry)
+        }
+    };
+    result.unwrap_or_else(|e| {
+        log::error!("Failed to update outbox message {}: {}", id, e);
+        true
+    })
+}
+
+/// Один проход: отправляет наступившие сообщения из очереди. Чаты обслуживают до
+/// `Config::outbox_workers` отправителей сразу, чтобы медленный чат не задерживал остальные.
+pub async fn drain(db: &Db, clock: &dyn Clock) {
+    let now = clock.now_utc();
+    let due = match db.call(move |conn| take_due(conn, now)).await {
+        Ok(due) => due,
+        Err(e) => {
+            log::error!("Failed to read outbox: {}", e);
+            return;
+        }
+    };
+
+    stream::iter(by_chat(due))
+        .for_each_concurrent(config::get().outbox_workers, |chat| async move {
+            let mut chat = chat.into_iter();
+            while let Some((id, attempts, message)) = chat.next() {
+                if !deliver(db, clock, now, id, attempts, message).await {
+                    // Остальные сообщения чата не обгоняют то, что ждёт повтора
+                    let rest = chat.map(|(id, _, _)| id).collect::<Vec<_>>();
+                    if let Err(e) = db.call(move |conn| release(conn, &rest, now)).await {
+                        log::error!("Failed to release outbox messages: {}", e);
+                    }
+                    break;
+                }
+            }
+        })
+        .await;
+}
+
+/// Для `reventor simulate`: забирает наступившие сообщения, как при успешной отправке,
+/// но ничего не отправляет, а возвращает их.
+pub fn dry_run(conn: &Connection, now: NaiveDateTime) -> Result<Vec<Outgoing>, rusqlite::Error> {
+    let due = take_due(conn, now)?;
+    for (id, _, message) in &due {
+        if let Some(reminder) = &message.reminder {
+            notifications::record_delivery(conn, reminder.event_id, &reminder.event_utc, now)?;
+            repeats::arm(conn, reminder.event_id, now)?;
+        }
+        delivered(conn, *id)?;
+    }
+    Ok(due.into_iter().map(|(_, _, message)| message).collect())
+}
+
+/// Отправитель очереди; работает отдельно от планировщика, чтобы сбои сети не задерживали проходы.
+pub async fn run(db: Db) {
+    loop {
+        drain(&db, &clock::SYSTEM).await;
+        tokio::time::sleep(config::get().outbox_interval).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::tests::memory_db;
+    use crate::{ensure_user_exists, insert_event};
+
+    fn reminder_for(event_id: i64) -> Outgoing {
+        Outgoing {
+            tenant: tenants::DEFAULT.to_string(),
+            chat_id: 42,
+            text: "Напоминание".to_string(),
+            reply_markup: None,
+            reminder: Some(Reminder { event_id, event_utc: "2030-01-01 07:00:00".to_string(), owner_id: 42, starred: false }),
+        }
+    }
+
+    #[tokio::test]
+    async fn claim_queues_once_until_event_time() {
+        let db = memory_db();
+        let now = clock::SYSTEM.now_utc();
+        let (first, second, queued) = db.call(move |conn| {
+            let user_id = ensure_user_exists(conn, tenants::DEFAULT, 42, None)?;
+            let event_id = insert_event(conn, tenants::DEFAULT, user_id, 42, "Событие", "01.01.2030 10:00")?;
+            let message = reminder_for(event_id);
+            let first = notifications::claim(conn, std::slice::from_ref(&message), now)?;
+            let second = notifications::claim(conn, &[message], now)?;
+            // Поставленное заранее напоминание ждёт времени события
+            assert!(take_due(conn, now)?.is_empty());
+            let scheduled = NaiveDateTime::parse_from_str("2030-01-01 07:00:00", UTC_FORMAT).unwrap();
+            Ok((first, second, take_due(conn, scheduled)?))
+        }).await.unwrap();
+        assert_eq!((first, second), (vec![true], vec![false]));
+        assert_eq!(queued.len(), 1);
+        assert_eq!(queued[0].2.text, "Напоминание");
+    }
+
+    #[tokio::test]
+    async fn network_failures_back_off_then_give_up() {
+        let db = memory_db();
+        let now = clock::SYSTEM.now_utc();
+        let (retry_at, status) = db.call(move |conn| {
+            let id = push(conn, &reminder_for(1), now)?;
+            failed(conn, id, 0, true, "timeout", now)?;
+            // Повтор отложен, поэтому сейчас отправлять нечего
+            assert!(take_due(conn, now)?.is_empty());
+            let retry_at: String = conn.query_row("SELECT next_attempt_utc FROM outbox WHERE id = ?", [id], |row| row.get(0))?;
+            assert_eq!(take_due(conn, now + RETRY_DELAY)?.len(), 1);
+
+            failed(conn, id, MAX_ATTEMPTS - 1, true, "timeout", now)?;
+            let status: String = conn.query_row("SELECT status FROM outbox WHERE id = ?", [id], |row| row.get(0))?;
+            Ok((retry_at, status))
+        }).await.unwrap();
+        assert_eq!(retry_at, (now + RETRY_DELAY).format(UTC_FORMAT).to_string());
+        assert_eq!(status, "failed");
+    }
+
+    #[tokio::test]
+    async fn claimed_messages_are_taken_once_until_the_claim_expires() {
+        let db = memory_db();
+        let now = clock::SYSTEM.now_utc();
+        let taken = db.call(move |conn| {
+            push(conn, &reminder_for(1), now)?;
+            // Второй процесс с той же базой не получает уже забранное сообщение
+            Ok((take_due(conn, now)?.len(), take_due(conn, now)?.len(), take_due(conn, now + CLAIM_TTL)?.len()))
+        }).await.unwrap();
+        assert_eq!(taken, (1, 0, 1));
+    }
+
+    #[tokio::test]
+    async fn waiting_message_holds_back_its_chat() {
+        let db = memory_db();
+        let now = clock::SYSTEM.now_utc();
+        let (taken, retry_at, attempts, after) = db.call(move |conn| {
+            let first = push(conn, &reminder_for(1), now)?;
+            let second = push(conn, &reminder_for(2), now)?;
+            let other = push(conn, &Outgoing { chat_id: 7, ..reminder_for(3) }, now)?;
+            assert_eq!(take_due(conn, now)?.len(), 3);
+            throttled(conn, first, "retry after 30s", now + Duration::seconds(30))?;
+            release(conn, &[second], now)?;
+            delivered(conn, other)?;
+            let taken = take_due(conn, now)?.len();
+            let (retry_at, attempts): (String, u32) = conn.query_row(
+                "SELECT next_attempt_utc, attempts FROM outbox WHERE id = ?",
+                [first],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )?;
+            let after = take_due(conn, now + Duration::seconds(30))?.into_iter().map(|(id, _, _)| id).collect::<Vec<_>>();
+            Ok((taken, retry_at, attempts, after == vec![first, second]))
+        }).await.unwrap();
+        // Второе сообщение чата не уходит раньше первого, ждущего повтора
+        assert_eq!(taken, 0);
+        assert_eq!(retry_at, (now + Duration::seconds(30)).format(UTC_FORMAT).to_string());
+        assert_eq!(attempts, 0);
+        assert!(after);
+    }
+
+    #[test]
+    fn chats_keep_their_order() {
+        let message = |chat_id, event_id| Outgoing { chat_id, ..reminder_for(event_id) };
+        let due = vec![(1, 0, message(1, 1)), (2, 0, message(2, 2)), (3, 0, message(1, 3))];
+        let chats = by_chat(due)
+            .into_iter()
+            .map(|chat| chat.into_iter().map(|(id, _, _)| id).collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+        assert_eq!(chats, vec![vec![1, 3], vec![2]]);
+    }
+}
@@ -0,0 +1,166 @@
+use teloxide::prelude::*;
+use teloxide::types::{InlineKeyboardButton, InlineKeyboardMarkup};
+use chrono::{Duration, NaiveDateTime};
+use rusqlite::{Connection, params};
+
+use crate::categories;
+use crate::chunks;
+use crate::followups::{self, FollowUp};
+use crate::humanize;
+use crate::i18n::{t, tf, Lang};
+use crate::settings::{self, Settings};
+use crate::stars;
+use crate::timezone;
+use crate::{DatabaseError, Db, crypto, get_event, in_transaction, parse_event_time, snooze_event};
+
+/// Кнопки переноса показываются для первых событий, чтобы клавиатура оставалась обозримой.
+const MAX_BUTTON_ROWS: usize = 10;
+
+/// Напомненное, но не отмеченное выполненным событие.
+#[derive(Debug)]
+pub struct OverdueEvent {
+    id: i64,
+    text: String,
+    event_time: NaiveDateTime,
+}
+
+/// События пользователя, о которых напомнили, но которые так и не отметили «Готово».
+pub fn get_overdue(conn: &Connection, telegram_id: i64) -> Result<Vec<OverdueEvent>, rusqlite::Error> {
+    let mut stmt = conn.prepare(
+        "SELECT e.id, e.text, e.event_time, e.icon FROM events e
+         JOIN users u ON e.user_id = u.id
+         WHERE u.telegram_id = ? AND e.status = 'sent'
+         ORDER BY e.event_utc",
+    )?;
+    let events = stmt.query_map(params![telegram_id], |row| {
+        let text = categories::label(row.get::<_, Option<String>>(3)?.as_deref(), &crypto::open(row.get(1)?));
+        Ok((row.get::<_, i64>(0)?, text, row.get::<_, String>(2)?))
+    })?
+    .collect::<Result<Vec<_>, _>>()?
+    .into_iter()
+    .filter_map(|(id, text, time)| Some(OverdueEvent { id, text, event_time: parse_event_time(&time)? }))
+    .collect();
+    Ok(events)
+}
+
+enum Outcome {
+    Rescheduled(NaiveDateTime),
+    Done(Vec<FollowUp>),
+}
+
+fn is_overdue(conn: &Connection, event_id: i64) -> Result<bool, rusqlite::Error> {
+    conn.query_row("SELECT status = 'sent' FROM events WHERE id = ?", params![event_id], |row| row.get(0))
+}
+
+/// Строки «#12 14.03 10:00 - текст (2 дня назад)».
+pub fn format_list(settings: &Settings, events: &[OverdueEvent]) -> String {
+    let now = timezone::now_in(settings.timezone);
+    events
+        .iter()
+        .map(|event| {
+            format!(
+                "#{} {} - {} ({})",
+                event.id,
+                settings.format_datetime(event.event_time),
+                event.text.lines().next().unwrap_or_default(),
+                humanize::ago(settings.lang, now - event.event_time),
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// По строке кнопок на событие: перенести на час, на завтра или отметить выполненным.
+pub fn keyboard(lang: Lang, events: &[OverdueEvent]) -> InlineKeyboardMarkup {
+    InlineKeyboardMarkup::new(events.iter().take(MAX_BUTTON_ROWS).map(|event| {
+        vec![
+            InlineKeyboardButton::callback(tf(lang, "overdue_hour", &[("id", &event.id)]), format!("ovd:hour:{}", event.id)),
+            InlineKeyboardButton::callback(t(lang, "overdue_tomorrow"), format!("ovd:tomorrow:{}", event.id)),
+            InlineKeyboardButton::callback(t(lang, "event_ack"), format!("ovd:done:{}", event.id)),
+        ]
+    }))
+}
+
+/// `/overdue` — пропущенные события с кнопками переноса.
+pub async fn handle_overdue_command(bot: &Bot, msg: &Message, db: &Db, settings: &Settings) -> ResponseResult<()> {
+    let lang = settings.lang;
+    let Some(user) = msg.from() else {
+        return Ok(());
+    };
+    let telegram_id = user.id.0 as i64;
+    let events = db.call(move |conn| get_overdue(conn, telegram_id)).await.map_err(DatabaseError)?;
+
+    if events.is_empty() {
+        bot.send_message(msg.chat.id, t(lang, "overdue_empty")).await?;
+        return Ok(());
+    }
+    chunks::send_last(bot, msg.chat.id, tf(lang, "overdue_list", &[("events", &format_list(settings, &events))])).await?
+        .reply_markup(keyboard(lang, &events))
+        .await?;
+    Ok(())
+}
+
+/// Кнопки из `/overdue` и сводки. Перенесённое событие снова ждёт напоминания,
+/// а клавиатура сообщения обновляется по оставшимся пропущенным.
+pub async fn handle_callback(bot: &Bot, q: &CallbackQuery, db: &Db, args: &str) -> ResponseResult<()> {
+    let Some((action, event_id)) = args.split_once(':') else {
+        bot.answer_callback_query(q.id.clone()).await?;
+        return Ok(());
+    };
+    let event_id: i64 = event_id.parse().unwrap_or_default();
+    let Some(message) = q.message.as_ref() else {
+        bot.answer_callback_query(q.id.clone()).await?;
+        return Ok(());
+    };
+
+    let (telegram_id, chat_id, action) = (q.from.id.0 as i64, message.chat.id.0, action.to_string());
+    let (settings, result) = db.call(move |conn| {
+        let settings = settings::resolve(conn, telegram_id, chat_id)?;
+        // Кнопки работают только для своих событий, которые всё ещё пропущены
+        let Some(event) = get_event(conn, event_id)?.filter(|event| event.owner_id == telegram_id) else {
+            return Ok((settings, None));
+        };
+        if !is_overdue(conn, event_id)? {
+            return Ok((settings, None));
+        }
+        let now = timezone::now_in(settings.timezone);
+        let start = match action.as_str() {
+            "hour" => Some(now + Duration::hours(1)),
+            "tomorrow" => parse_event_time(&event.event_time).map(|time| (now.date() + Duration::days(1)).and_time(time.time())),
+            _ => None,
+        };
+        let outcome = match start {
+            Some(start) => {
+                in_transaction(conn, |tx| snooze_event(tx, &event, start))?;
+                Some(Outcome::Rescheduled(start))
+            }
+            None => in_transaction(conn, |tx| followups::complete_event(tx, event_id))?.map(Outcome::Done),
+        };
+        let remaining = get_overdue(conn, telegram_id)?;
+        Ok((settings, Some((outcome, remaining))))
+    }).await.map_err(DatabaseError)?;
+    let lang = settings.lang;
+
+    let Some((outcome, remaining)) = result else {
+        bot.answer_callback_query(q.id.clone()).text(t(lang, "event_not_found")).await?;
+        return Ok(());
+    };
+    match outcome {
+        Some(Outcome::Rescheduled(start)) => {
+            let time = settings.format_datetime(start);
+            bot.answer_callback_query(q.id.clone()).text(tf(lang, "overdue_rescheduled", &[("time", &time)])).await?;
+        }
+        Some(Outcome::Done(follow_ups)) => {
+            bot.answer_callback_query(q.id.clone()).text(t(lang, "event_acknowledged")).await?;
+            stars::unpin(bot, db, event_id).await?;
+            followups::announce(bot, db, &settings, &follow_ups).await?;
+        }
+        None => {
+            bot.answer_callback_query(q.id.clone()).await?;
+        }
+    }
+    bot.edit_message_reply_markup(message.chat.id, message.id)
+        .reply_markup(keyboard(lang, &remaining))
+        .await?;
+    Ok(())
+}
@@ -0,0 +1,129 @@
+use teloxide::prelude::*;
+use teloxide::types::{ForceReply, InlineKeyboardButton, InlineKeyboardMarkup};
+use chrono::{Duration, NaiveDateTime};
+use rusqlite::{Connection, params};
+
+use crate::busy::DEFAULT_DURATION_MINUTES;
+use crate::chunks;
+use crate::i18n::{t, tf, Lang};
+use crate::settings::{self, Settings};
+use crate::{DatabaseError, Db, crypto, format_event_span, get_event, ics, link_event_message, parse_event_time};
+
+/// Событие, с которым пересекается новое.
+#[derive(Debug)]
+pub struct Overlap {
+    text: String,
+    start: NaiveDateTime,
+    end_time: Option<String>,
+}
+
+fn span(start: NaiveDateTime, end_time: Option<&str>) -> (NaiveDateTime, NaiveDateTime) {
+    let end = end_time.and_then(parse_event_time).filter(|end| *end > start)
+        .unwrap_or(start + Duration::minutes(DEFAULT_DURATION_MINUTES));
+    (start, end)
+}
+
+/// Предстоящие события пользователя, пересекающиеся с отрезком нового события.
+/// В группе сравниваются только события этого чата, чтобы не показать в ней личные.
+pub fn find_overlaps(
+    conn: &Connection,
+    user_id: i64,
+    chat_id: i64,
+    event_id: i64,
+    start: NaiveDateTime,
+    end_time: Option<&str>,
+) -> Result<Vec<Overlap>, rusqlite::Error> {
+    let (start, end) = span(start, end_time);
+    let mut stmt = conn.prepare(
+        "SELECT e.event_time, e.text, e.end_time FROM events e
+         JOIN users u ON e.user_id = u.id
+         WHERE e.user_id = ? AND e.id != ? AND e.status = 'pending'
+           AND (COALESCE(e.chat_id, u.telegram_id) = ? OR u.telegram_id = ?)",
+    )?;
+    let mut overlaps = stmt
+        .query_map(params![user_id, event_id, chat_id, chat_id], |row| {
+            Ok((row.get::<_, String>(0)?, crypto::open(row.get(1)?), row.get::<_, Option<String>>(2)?))
+        })?
+        .collect::<Result<Vec<_>, _>>()?
+        .into_iter()
+        .filter_map(|(time, text, end_time)| {
+            let other_start = parse_event_time(&time)?;
+            let (other_start, other_end) = span(other_start, end_time.as_deref());
+            (other_start < end && other_end > start).then_some(Overlap { text, start: other_start, end_time })
+        })
+        .collect::<Vec<_>>();
+    overlaps.sort_by_key(|overlap| overlap.start);
+    Ok(overlaps)
+}
+
+/// Строки предупреждения для подтверждения: «⚠️ Пересекается с «стоматолог» 14:00–15:00».
+pub fn warning(settings: &Settings, overlaps: &[Overlap]) -> String {
+    overlaps
+        .iter()
+        .map(|overlap| {
+            let (start, end) = span(overlap.start, overlap.end_time.as_deref());
+            let time = match overlap.end_time {
+                Some(_) => format_event_span(settings, start, overlap.end_time.as_deref()),
+                None => format!("{}–{}", settings.format_time(start.time()), settings.format_time(end.time())),
+            };
+            let text = overlap.text.lines().next().unwrap_or_default();
+            tf(settings.lang, "overlap_warning", &[("text", &text), ("time", &time)])
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Кнопки подтверждения с пересечением: выгрузка в календарь и выбор, что делать дальше.
+pub fn keyboard(lang: Lang, event_id: i64) -> InlineKeyboardMarkup {
+    ics::keyboard(lang, event_id).append_row(vec![
+        InlineKeyboardButton::callback(t(lang, "overlap_keep"), format!("ovl:keep:{}", event_id)),
+        InlineKeyboardButton::callback(t(lang, "overlap_move"), format!("ovl:move:{}", event_id)),
+    ])
+}
+
+/// «Оставить» убирает кнопки выбора, «Другое время» просит ответить новым `@временем`:
+/// ответ на подсказку правит событие так же, как ответ на подтверждение.
+pub async fn handle_callback(bot: &Bot, q: &CallbackQuery, db: &Db, args: &str) -> ResponseResult<()> {
+    let Some((action, event_id)) = args.split_once(':') else {
+        bot.answer_callback_query(q.id.clone()).await?;
+        return Ok(());
+    };
+    let event_id: i64 = event_id.parse().unwrap_or_default();
+    let Some(message) = q.message.as_ref() else {
+        bot.answer_callback_query(q.id.clone()).await?;
+        return Ok(());
+    };
+
+    let (telegram_id, chat_id) = (q.from.id.0 as i64, message.chat.id.0);
+    let (lang, event) = db.call(move |conn| {
+        let lang = settings::resolve(conn, telegram_id, chat_id)?.lang;
+        Ok((lang, get_event(conn, event_id)?.filter(|event| event.chat_id == chat_id)))
+    }).await.map_err(DatabaseError)?;
+
+    let Some(event) = event else {
+        bot.answer_callback_query(q.id.clone()).text(t(lang, "event_not_found")).await?;
+        return Ok(());
+    };
+    // Нажать на кнопку может только автор события
+    if event.owner_id != telegram_id {
+        bot.answer_callback_query(q.id.clone()).text(t(lang, "event_not_yours")).await?;
+        return Ok(());
+    }
+
+    bot.edit_message_reply_markup(message.chat.id, message.id)
+        .reply_markup(ics::keyboard(lang, event_id))
+        .await?;
+    if action != "move" {
+        bot.answer_callback_query(q.id.clone()).text(t(lang, "overlap_kept")).await?;
+        return Ok(());
+    }
+
+    bot.answer_callback_query(q.id.clone()).await?;
+    let prompt = chunks::send_last(bot, message.chat.id, tf(lang, "overlap_move_prompt", &[("id", &event_id)])).await?
+        .reply_markup(ForceReply::new())
+        .await?;
+    db.call(move |conn| link_event_message(conn, chat_id, prompt.id.0, event_id, "confirmation"))
+        .await
+        .map_err(DatabaseError)?;
+    Ok(())
+}
@@ -0,0 +1,529 @@
+//! Разбор текста события: `@25.12 10:00-11:30 !!Купить подарки #дом`. Один разбор на всех —
+//! обработчики бота, веб-приложение и API получают одинаковый результат.
+
+use std::fmt;
+use std::sync::Arc;
+
+use chrono::{DateTime, Datelike, Duration, NaiveDate, NaiveDateTime, NaiveTime, Timelike, Utc};
+use chrono_tz::Tz;
+use regex::Regex;
+
+mod cron;
+mod natural;
+
+pub use cron::CronParser;
+pub use natural::{EnglishParser, RussianParser};
+
+/// Дата во вводе, см. `date`.
+pub const DATE_PATTERN: &str = r"(?:\d{4}-\d{2}-\d{2}|\d{1,2}[./]\d{1,2}(?:[./]\d{4})?)";
+/// Время во вводе: `18:30`, `6:30pm`, `6:30 PM` или `6pm`.
+pub const TIME_PATTERN: &str = r"(?:\d{1,2}:\d{2}(?:\s?[aApP][mM])?|\d{1,2}\s?[aApP][mM])";
+
+/// Формат хранения времени события в БД.
+pub const STORAGE_FORMAT: &str = "%d.%m.%Y %H:%M";
+
+/// Порядок дня и месяца в коротких датах `03.04` и `04/03`.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum DateOrder {
+    #[default]
+    DayMonth,
+    MonthDay,
+}
+
+/// Привычки пользователя, от которых зависит разбор.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Locale {
+    pub date_order: DateOrder,
+}
+
+/// Повтор события из слов `ежедневно`, `каждую неделю`, `monthly`, `every year`
+/// или из расписания `@cron`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Recurrence {
+    Daily,
+    Weekly,
+    Monthly,
+    Yearly,
+    /// Расписание cron как оно записано: `0 9 * * 1-5`.
+    Cron(String),
+}
+
+impl Recurrence {
+    /// Следующее время повтора строго после `after`. Слова `ежедневно` и подобные пока только
+    /// распознаются, само событие переставляется лишь по расписанию `@cron`.
+    pub fn next(&self, after: NaiveDateTime) -> Option<NaiveDateTime> {
+        match self {
+            Recurrence::Cron(expression) => cron::next(expression, after),
+            _ => None,
+        }
+    }
+}
+
+/// Строка `-> 3d текст`: через сколько после подтверждения напомнить и о чём.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Offset {
+    pub delay: Duration,
+    pub text: String,
+}
+
+/// Время из текста как оно записано: `@25.12 6pm-7:30pm`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Mention<'a> {
+    pub date: Option<&'a str>,
+    pub time: &'a str,
+    pub end: Option<&'a str>,
+}
+
+/// Когда событие, по мнению одного из парсеров.
+#[derive(Debug, Clone, PartialEq)]
+pub struct When {
+    pub datetime: NaiveDateTime,
+    pub end: Option<NaiveDateTime>,
+    pub recurrence: Option<Recurrence>,
+}
+
+impl When {
+    pub fn at(datetime: NaiveDateTime) -> When {
+        When { datetime, end: None, recurrence: None }
+    }
+}
+
+/// Один синтаксис времени события. Парсеры пробуются по порядку, см. `Pipeline`.
+pub trait Parser: Send + Sync {
+    /// Имя для `PARSERS`: `syntax`, `cron`, `ru`, `en`.
+    fn name(&self) -> &'static str;
+
+    /// `None` — синтаксис не узнан, очередь следующего парсера; ошибка — узнан, но записан неверно.
+    /// `now` — текущее время на часах пользователя.
+    fn parse(&self, text: &str, locale: Locale, now: NaiveDateTime) -> Option<Result<When, ParseError>>;
+}
+
+/// Основной синтаксис бота: `@25.12 10:00-11:30`.
+pub struct SyntaxParser;
+
+impl Parser for SyntaxParser {
+    fn name(&self) -> &'static str {
+        "syntax"
+    }
+
+    /// Дата без года дополняется текущим годом, без даты — сегодняшним днём.
+    fn parse(&self, text: &str, locale: Locale, now: NaiveDateTime) -> Option<Result<When, ParseError>> {
+        let mention = find(text)?;
+        Some((|| {
+            let date = match mention.date {
+                Some(value) => date(value, now.date(), locale.date_order).ok_or_else(|| ParseError::InvalidDate(value.to_string()))?,
+                None => now.date(),
+            };
+            let start = time(mention.time).ok_or_else(|| ParseError::InvalidTime(mention.time.to_string()))?;
+            let datetime = date.and_time(start);
+            let end = match mention.end {
+                Some(value) => Some(end(datetime, time(value).ok_or_else(|| ParseError::InvalidEnd(value.to_string()))?)),
+                None => None,
+            };
+            Ok(When { datetime, end, recurrence: None })
+        })())
+    }
+}
+
+/// Цепочка парсеров. Первый узнавший текст решает, как его понять.
+#[derive(Clone)]
+pub struct Pipeline {
+    parsers: Vec<Arc<dyn Parser>>,
+}
+
+/// Все встроенные парсеры в порядке по умолчанию.
+pub const DEFAULT_PARSERS: &str = "syntax,cron,ru,en";
+
+impl Pipeline {
+    pub fn new(parsers: Vec<Arc<dyn Parser>>) -> Pipeline {
+        Pipeline { parsers }
+    }
+
+    /// Цепочка из имён через запятую, например `syntax,ru`.
+    pub fn from_names(names: &str) -> Result<Pipeline, String> {
+        let parsers = names
+            .split(',')
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+            .map(|name| -> Result<Arc<dyn Parser>, String> {
+                match name {
+                    "syntax" => Ok(Arc::new(SyntaxParser)),
+                    "cron" => Ok(Arc::new(CronParser)),
+                    "ru" => Ok(Arc::new(RussianParser)),
+                    "en" => Ok(Arc::new(EnglishParser)),
+                    _ => Err(format!("unknown parser: {}", name)),
+                }
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        if parsers.is_empty() {
+            return Err("no parsers configured".to_string());
+        }
+        Ok(Pipeline { parsers })
+    }
+
+    /// Добавляет парсер в конец цепочки.
+    pub fn with(mut self, parser: Arc<dyn Parser>) -> Pipeline {
+        self.parsers.push(parser);
+        self
+    }
+
+    /// Узнаёт ли какой-нибудь парсер в тексте время события.
+    pub fn matches(&self, text: &str) -> bool {
+        let now = Utc::now().naive_utc();
+        self.parsers.iter().any(|parser| parser.parse(text, Locale::default(), now).is_some())
+    }
+
+    /// Разбирает событие; без пояса время считается по часам сервера.
+    pub fn parse(&self, text: &str, locale: Locale, now: DateTime<Utc>, tz: Option<Tz>) -> Result<ParsedEvent, ParseError> {
+        let local = match tz {
+            Some(tz) => now.with_timezone(&tz).naive_local(),
+            None => now.with_timezone(&chrono::Local).naive_local(),
+        };
+        let when = self.parsers
+            .iter()
+            .find_map(|parser| parser.parse(text, locale, local))
+            .ok_or(ParseError::MissingTime)??;
+
+        Ok(ParsedEvent {
+            text: text.to_string(),
+            datetime: normalize(when.datetime),
+            end: when.end.map(normalize),
+            recurrence: when.recurrence.or_else(|| recurrence(text)),
+            offsets: offsets(text),
+            tags: tags(text),
+            priority: priority(text),
+        })
+    }
+}
+
+impl Default for Pipeline {
+    fn default() -> Pipeline {
+        Pipeline::from_names(DEFAULT_PARSERS).unwrap()
+    }
+}
+
+impl fmt::Debug for Pipeline {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.parsers.iter().map(|parser| parser.name())).finish()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParsedEvent {
+    /// Текст целиком, как его прислал пользователь.
+    pub text: String,
+    /// Начало на часах пользователя.
+    pub datetime: NaiveDateTime,
+    /// Окончание из `@14:00-15:30`.
+    pub end: Option<NaiveDateTime>,
+    pub recurrence: Option<Recurrence>,
+    pub offsets: Vec<Offset>,
+    /// Хэштеги без `#`, в нижнем регистре.
+    pub tags: Vec<String>,
+    /// Число `!` в начале текста, от 0 до 3.
+    pub priority: u8,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParseError {
+    /// В тексте нет `@время`.
+    MissingTime,
+    InvalidDate(String),
+    InvalidTime(String),
+    InvalidEnd(String),
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseError::MissingTime => write!(f, "no @time in the text"),
+            ParseError::InvalidDate(value) => write!(f, "invalid date: {}", value),
+            ParseError::InvalidTime(value) => write!(f, "invalid time: {}", value),
+            ParseError::InvalidEnd(value) => write!(f, "invalid end time: {}", value),
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// Разбирает событие всеми встроенными парсерами, см. `Pipeline`.
+pub fn parse(text: &str, locale: Locale, now: DateTime<Utc>, tz: Option<Tz>) -> Result<ParsedEvent, ParseError> {
+    Pipeline::default().parse(text, locale, now, tz)
+}
+
+/// Время события с точностью до минуты: так оно хранится и показывается, поэтому
+/// секунды из «через 5 минут» не должны сдвигать момент после сохранения.
+pub fn normalize(datetime: NaiveDateTime) -> NaiveDateTime {
+    datetime.with_second(0).and_then(|datetime| datetime.with_nanosecond(0)).unwrap_or(datetime)
+}
+
+/// Время события в формате БД.
+pub fn store(datetime: NaiveDateTime) -> String {
+    normalize(datetime).format(STORAGE_FORMAT).to_string()
+}
+
+/// Время события из БД.
+pub fn load(value: &str) -> Option<NaiveDateTime> {
+    NaiveDateTime::parse_from_str(value, STORAGE_FORMAT).ok()
+}
+
+/// Первое `@время` в тексте.
+pub fn find(text: &str) -> Option<Mention<'_>> {
+    let re = Regex::new(&format!(r"@(?:({})\s+)?({})(?:\s*[-–]\s*({}))?", DATE_PATTERN, TIME_PATTERN, TIME_PATTERN)).unwrap();
+    let captures = re.captures(text)?;
+    Some(Mention {
+        date: captures.get(1).map(|m| m.as_str()),
+        time: captures.get(2)?.as_str(),
+        end: captures.get(3).map(|m| m.as_str()),
+    })
+}
+
+/// Дата во вводе: `03.04`, `03.04.2025`, `04/03`, `04/03/2025` или `2025-04-03`.
+pub fn date(value: &str, today: NaiveDate, order: DateOrder) -> Option<NaiveDate> {
+    if let Ok(date) = NaiveDate::parse_from_str(value, "%Y-%m-%d") {
+        return Some(date);
+    }
+
+    let parts = value.split(['.', '/']).collect::<Vec<_>>();
+    let first: u32 = parts.first()?.parse().ok()?;
+    let second: u32 = parts.get(1)?.parse().ok()?;
+    let year = match parts.get(2) {
+        Some(year) => year.parse().ok()?,
+        None => today.year(),
+    };
+    let (day, month) = match order {
+        DateOrder::MonthDay => (second, first),
+        DateOrder::DayMonth => (first, second),
+    };
+    NaiveDate::from_ymd_opt(year, month, day)
+}
+
+/// Время в 24-часовом формате или с am/pm.
+pub fn time(value: &str) -> Option<NaiveTime> {
+    let value = value.trim().to_lowercase().replace(' ', "");
+    let (clock, pm) = match (value.strip_suffix("am"), value.strip_suffix("pm")) {
+        (Some(clock), _) => (clock, Some(false)),
+        (_, Some(clock)) => (clock, Some(true)),
+        _ => return NaiveTime::parse_from_str(&value, "%H:%M").ok(),
+    };
+
+    let (hour, minute) = clock.split_once(':').unwrap_or((clock, "0"));
+    let hour: u32 = hour.parse().ok()?;
+    let minute: u32 = minute.parse().ok()?;
+    if !(1..=12).contains(&hour) {
+        return None;
+    }
+    let hour = match pm {
+        Some(true) => hour % 12 + 12,
+        _ => hour % 12,
+    };
+    NaiveTime::from_hms_opt(hour, minute, 0)
+}
+
+/// Окончание события: время раньше начала относится к следующему дню.
+pub fn end(start: NaiveDateTime, end: NaiveTime) -> NaiveDateTime {
+    let end = start.date().and_time(end);
+    if end > start { end } else { end + Duration::days(1) }
+}
+
+/// Приоритет события: `!`, `!!` или `!!!` в начале текста.
+pub fn priority(text: &str) -> u8 {
+    text.trim_start().chars().take_while(|c| *c == '!').take(3).count() as u8
+}
+
+/// Строки текста вида `-> 3d текст`.
+pub fn offsets(text: &str) -> Vec<Offset> {
+    let re = Regex::new(r"^(?:->|→)\s*(\d+)\s*(m|min|м|мин|h|ч|d|д|w|н)\s+(.+)$").unwrap();
+    text.lines()
+        .filter_map(|line| {
+            let captures = re.captures(line.trim())?;
+            let amount: i64 = captures.get(1)?.as_str().parse().ok()?;
+            let delay = match captures.get(2)?.as_str() {
+                "m" | "min" | "м" | "мин" => Duration::try_minutes(amount)?,
+                "h" | "ч" => Duration::try_hours(amount)?,
+                "d" | "д" => Duration::try_days(amount)?,
+                _ => Duration::try_weeks(amount)?,
+            };
+            Some(Offset { delay, text: captures.get(3)?.as_str().trim().to_string() })
+        })
+        .collect()
+}
+
+/// Хэштеги `#дом`; `#12` — это номер события, а не тег.
+pub fn tags(text: &str) -> Vec<String> {
+    let re = Regex::new(r"(?:^|\s)#(\w+)").unwrap();
+    let mut tags = Vec::new();
+    for captures in re.captures_iter(text) {
+        let tag = captures[1].to_lowercase();
+        if !tag.chars().all(|c| c.is_ascii_digit()) && !tags.contains(&tag) {
+            tags.push(tag);
+        }
+    }
+    tags
+}
+
+pub fn recurrence(text: &str) -> Option<Recurrence> {
+    const WORDS: &[(Recurrence, &[&str])] = &[
+        (Recurrence::Daily, &["ежедневно", "каждый день", "daily", "every day"]),
+        (Recurrence::Weekly, &["еженедельно", "каждую неделю", "weekly", "every week"]),
+        (Recurrence::Monthly, &["ежемесячно", "каждый месяц", "monthly", "every month"]),
+        (Recurrence::Yearly, &["ежегодно", "каждый год", "yearly", "every year"]),
+    ];
+    let text = text.to_lowercase();
+    let re = Regex::new(r"[\w ]+").unwrap();
+    let phrases = re.find_iter(&text).map(|m| format!(" {} ", m.as_str())).collect::<Vec<_>>();
+    WORDS.iter()
+        .find(|(_, words)| words.iter().any(|word| phrases.iter().any(|phrase| phrase.contains(&format!(" {} ", word)))))
+        .map(|(recurrence, _)| recurrence.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+    use proptest::prelude::*;
+
+    use super::*;
+
+    fn now() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2025, 12, 31, 22, 30, 0).unwrap()
+    }
+
+    fn at(year: i32, month: u32, day: u32, hour: u32, minute: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(year, month, day).and_then(|date| date.and_hms_opt(hour, minute, 0)).unwrap()
+    }
+
+    #[test]
+    fn structured_fields() {
+        let text = "!!Созвон #Работа #12 каждую неделю @05.01 6pm-7:30pm\n-> 2h отправить протокол";
+        let parsed = parse(text, Locale::default(), now(), Some(chrono_tz::Europe::Moscow)).unwrap();
+        assert_eq!(parsed.datetime, at(2026, 1, 5, 18, 0));
+        assert_eq!(parsed.end, Some(at(2026, 1, 5, 19, 30)));
+        assert_eq!(parsed.recurrence, Some(Recurrence::Weekly));
+        assert_eq!(parsed.offsets, [Offset { delay: Duration::hours(2), text: "отправить протокол".to_string() }]);
+        assert_eq!(parsed.tags, ["работа"]);
+        assert_eq!(parsed.priority, 2);
+    }
+
+    #[test]
+    fn month_first_and_overnight_end() {
+        let locale = Locale { date_order: DateOrder::MonthDay };
+        let parsed = parse("Смена @01/05/2026 22:00-06:00", locale, now(), None).unwrap();
+        assert_eq!(parsed.datetime, at(2026, 1, 5, 22, 0));
+        assert_eq!(parsed.end, Some(at(2026, 1, 6, 6, 0)));
+    }
+
+    #[test]
+    fn errors() {
+        let tz = Some(chrono_tz::UTC);
+        assert_eq!(parse("без времени", Locale::default(), now(), tz), Err(ParseError::MissingTime));
+        assert_eq!(parse("@31.02 10:00", Locale::default(), now(), tz), Err(ParseError::InvalidDate("31.02".to_string())));
+        assert_eq!(parse("@25:00", Locale::default(), now(), tz), Err(ParseError::InvalidTime("25:00".to_string())));
+        assert_eq!(parse("@10:00-13pm", Locale::default(), now(), tz), Err(ParseError::InvalidEnd("13pm".to_string())));
+    }
+
+    #[test]
+    fn natural_language() {
+        // 31 декабря 2025, среда, 22:30 по UTC
+        let tz = Some(chrono_tz::UTC);
+        let when = |text| parse(text, Locale::default(), now(), tz).unwrap().datetime;
+        assert_eq!(when("завтра в 10 позвонить"), at(2026, 1, 1, 10, 0));
+        assert_eq!(when("через 2 часа выключить духовку"), at(2026, 1, 1, 0, 30));
+        assert_eq!(when("через час"), at(2025, 12, 31, 23, 30));
+        assert_eq!(when("в среду в 9:15 планёрка"), at(2026, 1, 7, 9, 15));
+        assert_eq!(when("sync on friday at 6pm"), at(2026, 1, 2, 18, 0));
+        assert_eq!(when("stretch in 15 minutes"), at(2025, 12, 31, 22, 45));
+        assert_eq!(parse("в четверг в 25:00", Locale::default(), now(), tz), Err(ParseError::InvalidTime("25:00".to_string())));
+    }
+
+    #[test]
+    fn cron_schedule() {
+        let parsed = parse("Планёрка @cron 0 9 * * 1-5", Locale::default(), now(), Some(chrono_tz::UTC)).unwrap();
+        assert_eq!(parsed.datetime, at(2026, 1, 1, 9, 0));
+        assert_eq!(parsed.recurrence, Some(Recurrence::Cron("0 9 * * 1-5".to_string())));
+        // Четверг 1 января, дальше пятница и сразу понедельник
+        let recurrence = parsed.recurrence.unwrap();
+        assert_eq!(recurrence.next(parsed.datetime), Some(at(2026, 1, 2, 9, 0)));
+        assert_eq!(recurrence.next(at(2026, 1, 2, 9, 0)), Some(at(2026, 1, 5, 9, 0)));
+        assert!(matches!(
+            parse("@cron 0 25 * * *", Locale::default(), now(), None),
+            Err(ParseError::InvalidTime(_))
+        ));
+    }
+
+    #[test]
+    fn pipeline_order_and_names() {
+        let text = "завтра в 10 @05.01 18:00";
+        let tz = Some(chrono_tz::UTC);
+        assert_eq!(parse(text, Locale::default(), now(), tz).unwrap().datetime, at(2025, 1, 5, 18, 0));
+        let russian_first = Pipeline::from_names("ru, syntax").unwrap();
+        assert_eq!(russian_first.parse(text, Locale::default(), now(), tz).unwrap().datetime, at(2026, 1, 1, 10, 0));
+
+        let syntax_only = Pipeline::from_names("syntax").unwrap();
+        assert!(!syntax_only.matches("через 2 часа"));
+        assert!(Pipeline::from_names("syntax,klingon").is_err());
+        assert_eq!(format!("{:?}", Pipeline::default()), r#"["syntax", "cron", "ru", "en"]"#);
+    }
+
+    fn any_datetime() -> impl Strategy<Value = NaiveDateTime> {
+        (1i32..=9999, 1u32..=366, 0u32..24, 0u32..60).prop_filter_map("no such day", |(year, ordinal, hour, minute)| {
+            NaiveDate::from_yo_opt(year, ordinal)?.and_hms_opt(hour, minute, 0)
+        })
+    }
+
+    fn any_order() -> impl Strategy<Value = DateOrder> {
+        prop_oneof![Just(DateOrder::DayMonth), Just(DateOrder::MonthDay)]
+    }
+
+    /// Как бот показывает дату и время в каждом из форматов настроек.
+    fn display(datetime: NaiveDateTime, order: DateOrder, iso: bool, h12: bool) -> String {
+        let date = match (iso, order) {
+            (true, _) => "%Y-%m-%d",
+            (false, DateOrder::DayMonth) => "%d.%m.%Y",
+            (false, DateOrder::MonthDay) => "%m/%d/%Y",
+        };
+        let time = if h12 { "%-I:%M %p" } else { "%H:%M" };
+        datetime.format(&format!("{} {}", date, time)).to_string()
+    }
+
+    proptest! {
+        #[test]
+        fn displayed_time_parses_back(datetime in any_datetime(), order in any_order(), iso: bool, h12: bool) {
+            let text = format!("Событие @{}", display(datetime, order, iso, h12));
+            let parsed = parse(&text, Locale { date_order: order }, now(), Some(chrono_tz::UTC)).unwrap();
+            prop_assert_eq!(parsed.datetime, datetime);
+        }
+
+        #[test]
+        fn stored_time_loads_back(seconds in 0i64..60 * 60 * 24 * 365 * 200, offset in 0i64..10_000) {
+            let now = DateTime::from_timestamp(seconds, 0).unwrap() + Duration::milliseconds(offset);
+            let parsed = parse("через 5 минут", Locale::default(), now, Some(chrono_tz::Europe::Moscow)).unwrap();
+            prop_assert_eq!(load(&store(parsed.datetime)), Some(parsed.datetime));
+        }
+
+        #[test]
+        fn date_without_year_is_this_year(day in 1u32..=31, month in 1u32..=12, order in any_order()) {
+            let today = now().date_naive();
+            let value = match order {
+                DateOrder::DayMonth => format!("{:02}.{:02}", day, month),
+                DateOrder::MonthDay => format!("{}/{}", month, day),
+            };
+            let expected = NaiveDate::from_ymd_opt(today.year(), month, day);
+            prop_assert_eq!(date(&value, today, order), expected);
+            let with_year = format!("{}{}{}", value, if order == DateOrder::DayMonth { "." } else { "/" }, today.year() + 1);
+            prop_assert_eq!(date(&with_year, today, order), NaiveDate::from_ymd_opt(today.year() + 1, month, day));
+        }
+
+        #[test]
+        fn twelve_hour_clock_matches_24_hour(hour in 0u32..24, minute in 0u32..60) {
+            let expected = NaiveTime::from_hms_opt(hour, minute, 0);
+            let (clock, suffix) = (if hour % 12 == 0 { 12 } else { hour % 12 }, if hour < 12 { "am" } else { "PM" });
+            prop_assert_eq!(time(&format!("{}:{:02}{}", clock, minute, suffix)), expected);
+            prop_assert_eq!(time(&format!("{}:{:02}", hour, minute)), expected);
+        }
+
+        #[test]
+        fn any_text_is_safe(text in "\\PC{0,60}") {
+            let _ = parse(&text, Locale::default(), now(), None);
+        }
+    }
+}
@@ -0,0 +1,92 @@
+//! Расписание cron: `@cron 0 9 * * 1-5 Планёрка`. Событие ставится на ближайшее
+//! время по расписанию, а само расписание уходит в `Recurrence::Cron`.
+
+use chrono::{Datelike, Duration, NaiveDateTime, NaiveTime, Timelike};
+use regex::Regex;
+
+use super::{Locale, ParseError, Parser, Recurrence, When};
+
+/// Насколько вперёд ищется ближайшее время: расписание на 29 февраля находится за четыре года.
+const SEARCH_DAYS: i64 = 366 * 4 + 1;
+
+pub struct CronParser;
+
+impl Parser for CronParser {
+    fn name(&self) -> &'static str {
+        "cron"
+    }
+
+    fn parse(&self, text: &str, _locale: Locale, now: NaiveDateTime) -> Option<Result<When, ParseError>> {
+        let re = Regex::new(r"@cron\s+(\S+\s+\S+\s+\S+\s+\S+\s+\S+)").unwrap();
+        let expression = re.captures(text)?.get(1)?.as_str().split_whitespace().collect::<Vec<_>>().join(" ");
+        Some(next(&expression, now).map(|datetime| When {
+            datetime,
+            end: None,
+            recurrence: Some(Recurrence::Cron(expression.clone())),
+        }).ok_or(ParseError::InvalidTime(expression)))
+    }
+}
+
+/// Значения поля: `*`, `5`, `1,15`, `1-5`, `*/15`, `0-30/10`.
+fn field(value: &str, min: u32, max: u32) -> Option<Vec<u32>> {
+    let mut values = Vec::new();
+    for part in value.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => (range, step.parse().ok().filter(|step| *step > 0)?),
+            None => (part, 1),
+        };
+        let (from, to) = match range {
+            "*" => (min, max),
+            range => match range.split_once('-') {
+                Some((from, to)) => (from.parse().ok()?, to.parse().ok()?),
+                None => {
+                    let value = range.parse().ok()?;
+                    (value, value)
+                }
+            },
+        };
+        if from < min || to > max || from > to {
+            return None;
+        }
+        values.extend((from..=to).step_by(step));
+    }
+    values.sort_unstable();
+    values.dedup();
+    Some(values)
+}
+
+/// Ближайшее время по расписанию строго после `now`. Если заданы и число, и день недели,
+/// подходит любой из них, как в cron.
+pub(super) fn next(expression: &str, now: NaiveDateTime) -> Option<NaiveDateTime> {
+    let fields = expression.split_whitespace().collect::<Vec<_>>();
+    let minutes = field(fields.first()?, 0, 59)?;
+    let hours = field(fields.get(1)?, 0, 23)?;
+    let days = field(fields.get(2)?, 1, 31)?;
+    let months = field(fields.get(3)?, 1, 12)?;
+    // 0 и 7 — воскресенье
+    let weekdays = field(fields.get(4)?, 0, 7)?.into_iter().map(|day| day % 7).collect::<Vec<_>>();
+    let (any_day, any_weekday) = (fields[2] == "*", fields[4] == "*");
+
+    let now = now.with_second(0)?.with_nanosecond(0)?;
+    for offset in 0..SEARCH_DAYS {
+        let date = now.date() + Duration::days(offset);
+        let day_matches = days.contains(&date.day());
+        let weekday_matches = weekdays.contains(&date.weekday().num_days_from_sunday());
+        let matches = match (any_day, any_weekday) {
+            (false, false) => day_matches || weekday_matches,
+            _ => day_matches && weekday_matches,
+        };
+        if !months.contains(&date.month()) || !matches {
+            continue;
+        }
+        for hour in &hours {
+            for minute in &minutes {
+                let datetime = date.and_time(NaiveTime::from_hms_opt(*hour, *minute, 0)?);
+                if datetime > now {
+                    return Some(datetime);
+                }
+            }
+        }
+    }
+    None
+}
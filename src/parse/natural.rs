@@ -0,0 +1,126 @@
+//! Время словами: «завтра в 10», «через 2 часа», «в пятницу в 18:30»,
+//! `tomorrow at 6pm`, `in 30 minutes`, `on friday at 9:15`.
+
+use chrono::{Datelike, Duration, NaiveDate, NaiveDateTime, Weekday};
+use regex::{Captures, Regex};
+
+use super::{Locale, ParseError, Parser, When, time};
+
+/// Время после дня: `10`, `10:30`, `6pm`.
+const CLOCK: &str = r"\d{1,2}(?::\d{2})?(?:\s?[ap]m)?";
+
+/// Слова одного языка.
+struct Vocabulary {
+    /// `(сколько)? (единица)` после «через» или `in`.
+    relative: &'static str,
+    /// `(день) (время)`.
+    day_at: &'static str,
+    today: &'static [&'static str],
+    tomorrow: &'static [&'static str],
+    after_tomorrow: &'static [&'static str],
+    /// Начала названий дней недели с понедельника.
+    weekdays: [&'static str; 7],
+    /// Начала названий единиц: минуты, часы, дни, недели.
+    units: [&'static [&'static str]; 4],
+    /// Количество словами: «пару», `an`.
+    amounts: &'static [(&'static str, i64)],
+}
+
+const RUSSIAN: Vocabulary = Vocabulary {
+    relative: r"(?:^|\s)через\s+(?:(\d+|пару)\s+)?(минут[уы]?|мин|час(?:а|ов)?|д(?:ень|ня|ней)|недел[юиь])(?:\s|$|[.,!])",
+    day_at: r"(?:^|\s)(сегодня|завтра|послезавтра|в[о]?\s+(?:понедельник|вторник|среду|четверг|пятницу|субботу|воскресенье))\s+в\s+(CLOCK)(?:\s|$|[.,!])",
+    today: &["сегодня"],
+    tomorrow: &["завтра"],
+    after_tomorrow: &["послезавтра"],
+    weekdays: ["понедельник", "вторник", "сред", "четверг", "пятниц", "суббот", "воскресень"],
+    units: [&["мин"], &["час"], &["д"], &["недел"]],
+    amounts: &[("пару", 2)],
+};
+
+const ENGLISH: Vocabulary = Vocabulary {
+    relative: r"(?:^|\s)in\s+(\d+|an?|one)\s+(minutes?|mins?|hours?|days?|weeks?)(?:\s|$|[.,!])",
+    day_at: r"(?:^|\s)(today|tomorrow|(?:on\s+)?(?:monday|tuesday|wednesday|thursday|friday|saturday|sunday))\s+at\s+(CLOCK)(?:\s|$|[.,!])",
+    today: &["today"],
+    tomorrow: &["tomorrow"],
+    after_tomorrow: &[],
+    weekdays: ["mon", "tue", "wed", "thu", "fri", "sat", "sun"],
+    units: [&["min"], &["hour"], &["day"], &["week"]],
+    amounts: &[("a", 1), ("an", 1), ("one", 1)],
+};
+
+pub struct RussianParser;
+
+impl Parser for RussianParser {
+    fn name(&self) -> &'static str {
+        "ru"
+    }
+
+    fn parse(&self, text: &str, _locale: Locale, now: NaiveDateTime) -> Option<Result<When, ParseError>> {
+        natural(&RUSSIAN, text, now)
+    }
+}
+
+pub struct EnglishParser;
+
+impl Parser for EnglishParser {
+    fn name(&self) -> &'static str {
+        "en"
+    }
+
+    fn parse(&self, text: &str, _locale: Locale, now: NaiveDateTime) -> Option<Result<When, ParseError>> {
+        natural(&ENGLISH, text, now)
+    }
+}
+
+fn natural(words: &Vocabulary, text: &str, now: NaiveDateTime) -> Option<Result<When, ParseError>> {
+    let text = text.to_lowercase();
+    let day_at = Regex::new(&words.day_at.replace("CLOCK", CLOCK)).unwrap();
+    if let Some(captures) = day_at.captures(&text) {
+        return Some(day_at_time(words, &captures, now));
+    }
+    let relative = Regex::new(words.relative).unwrap();
+    let captures = relative.captures(&text)?;
+    // «через час»: без количества — одна единица
+    let amount = match captures.get(1).map(|m| m.as_str()) {
+        None => 1,
+        Some(value) => match words.amounts.iter().find(|(word, _)| *word == value) {
+            Some((_, amount)) => *amount,
+            None => value.parse().ok()?,
+        },
+    };
+    let unit = &captures[2];
+    let delay = match words.units.iter().position(|prefixes| prefixes.iter().any(|prefix| unit.starts_with(prefix)))? {
+        0 => Duration::try_minutes(amount),
+        1 => Duration::try_hours(amount),
+        2 => Duration::try_days(amount),
+        _ => Duration::try_weeks(amount),
+    };
+    Some(delay.and_then(|delay| now.checked_add_signed(delay)).map(When::at).ok_or_else(|| ParseError::InvalidTime(captures[0].trim().to_string())))
+}
+
+fn day_at_time(words: &Vocabulary, captures: &Captures, now: NaiveDateTime) -> Result<When, ParseError> {
+    let (day, clock) = (&captures[1], &captures[2]);
+    let clock_time = if clock.contains(':') || clock.ends_with('m') { time(clock) } else { time(&format!("{}:00", clock)) };
+    let clock_time = clock_time.ok_or_else(|| ParseError::InvalidTime(clock.to_string()))?;
+    let date = resolve_day(words, day, now.date(), now.time() < clock_time).ok_or_else(|| ParseError::InvalidDate(day.to_string()))?;
+    Ok(When::at(date.and_time(clock_time)))
+}
+
+/// День недели — ближайший такой день; сегодняшний, только если время ещё не прошло.
+fn resolve_day(words: &Vocabulary, day: &str, today: NaiveDate, time_ahead: bool) -> Option<NaiveDate> {
+    if words.today.contains(&day) {
+        return Some(today);
+    }
+    if words.tomorrow.contains(&day) {
+        return today.succ_opt();
+    }
+    if words.after_tomorrow.contains(&day) {
+        return today.succ_opt()?.succ_opt();
+    }
+    let name = day.split_whitespace().last()?;
+    let index = words.weekdays.iter().position(|prefix| name.starts_with(prefix))?;
+    let weekday = Weekday::try_from(index as u8).ok()?;
+    let ahead = (weekday.num_days_from_monday() + 7 - today.weekday().num_days_from_monday()) % 7;
+    let ahead = if ahead == 0 && !time_ahead { 7 } else { ahead };
+    today.checked_add_signed(Duration::days(ahead.into()))
+}
@@ -0,0 +1,341 @@
+use teloxide::prelude::*;
+use teloxide::types::{MessageId, Poll};
+use chrono::{Duration, NaiveDateTime};
+use regex::Regex;
+use rusqlite::{Connection, params, OptionalExtension};
+
+use crate::chunks;
+use crate::clock::{self, Clock};
+use crate::i18n::{t, tf};
+use crate::settings::{self, Settings};
+use crate::timezone;
+use crate::{
+    DATE_PATTERN, DatabaseError, Db, EVENT_TIME_FORMAT, TIME_PATTERN, UTC_FORMAT, ensure_user_exists, in_transaction, insert_event,
+    ics, link_event_message, normalize_time_input, resolve_event_time, tenants,
+};
+
+#[derive(Debug)]
+struct PollRecord {
+    poll_id: String,
+    chat_id: i64,
+    user_id: i64,
+    telegram_id: i64,
+    text: String,
+    options: Vec<String>,
+}
+
+#[derive(Debug)]
+struct NewPoll<'a> {
+    tenant: &'a str,
+    poll_id: &'a str,
+    chat_id: i64,
+    message_id: i32,
+    user_id: i64,
+    text: &'a str,
+    options: &'a [NaiveDateTime],
+    deadline: NaiveDateTime,
+    deadline_utc: NaiveDateTime,
+}
+
+#[derive(Debug)]
+struct ExpiredPoll {
+    tenant: String,
+    chat_id: i64,
+    message_id: i32,
+}
+
+/// Разбирает `18:00|19:00|20:00 текст` в список вариантов времени и текст события.
+fn parse_poll_args(args: &str, settings: &Settings) -> Option<(Vec<NaiveDateTime>, String)> {
+    let option = format!(r"(?:{}\s+)?{}", DATE_PATTERN, TIME_PATTERN);
+    let re = Regex::new(&format!(r"^({0}(?:\|{0})+)\s+(.+)$", option)).unwrap();
+    let option_re = Regex::new(&format!(r"^(?:({})\s+)?({})$", DATE_PATTERN, TIME_PATTERN)).unwrap();
+
+    let captures = re.captures(args.trim())?;
+    let text = captures.get(2).unwrap().as_str().trim().to_string();
+
+    let options = captures
+        .get(1)
+        .unwrap()
+        .as_str()
+        .split('|')
+        .map(|option| {
+            let parts = option_re.captures(option.trim())?;
+            let time = normalize_time_input(parts.get(2).unwrap().as_str());
+            resolve_event_time(&clock::SYSTEM, parts.get(1).map(|m| m.as_str()), &time, settings)
+        })
+        .collect::<Option<Vec<_>>>()?;
+
+    Some((options, text))
+}
+
+/// Голосование закрывается за час до самого раннего варианта,
+/// но не раньше чем через 10 минут после создания.
+fn poll_deadline(now: NaiveDateTime, earliest: NaiveDateTime) -> NaiveDateTime {
+    let deadline = earliest - Duration::hours(1);
+    let min_deadline = now + Duration::minutes(10);
+    if deadline >= min_deadline {
+        deadline
+    } else {
+        earliest.min(min_deadline)
+    }
+}
+
+fn save_poll(conn: &Connection, poll: &NewPoll) -> Result<(), rusqlite::Error> {
+    let options = poll
+        .options
+        .iter()
+        .map(|o| o.format(EVENT_TIME_FORMAT).to_string())
+        .collect::<Vec<_>>()
+        .join("|");
+
+    conn.execute(
+        "INSERT INTO polls (tenant, poll_id, chat_id, message_id, user_id, text, options, deadline, deadline_utc)
+         VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)",
+        params![
+            poll.tenant,
+            poll.poll_id,
+            poll.chat_id,
+            poll.message_id,
+            poll.user_id,
+            poll.text,
+            options,
+            poll.deadline.format(EVENT_TIME_FORMAT).to_string(),
+            poll.deadline_utc.format(UTC_FORMAT).to_string()
+        ],
+    )?;
+    Ok(())
+}
+
+fn get_expired_polls(conn: &Connection, now_utc: NaiveDateTime) -> Result<Vec<ExpiredPoll>, rusqlite::Error> {
+    let mut stmt = conn.prepare("SELECT chat_id, message_id, deadline, tenant, deadline_utc FROM polls WHERE closed = 0")?;
+    let polls = stmt.query_map([], |row| {
+        Ok((
+            ExpiredPoll { tenant: row.get(3)?, chat_id: row.get(0)?, message_id: row.get(1)? },
+            row.get::<_, String>(2)?,
+            row.get::<_, Option<String>>(4)?,
+        ))
+    })?
+    .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(polls
+        .into_iter()
+        .filter(|(_, deadline, deadline_utc)| {
+            let deadline_utc = match deadline_utc {
+                Some(utc) => NaiveDateTime::parse_from_str(utc, UTC_FORMAT).ok(),
+                None => NaiveDateTime::parse_from_str(deadline, EVENT_TIME_FORMAT).ok().map(|d| timezone::to_utc(d, None)),
+            };
+            deadline_utc.is_none_or(|d| d <= now_utc)
+        })
+        .map(|(poll, _, _)| poll)
+        .collect())
+}
+
+fn find_open_poll_by_message(conn: &Connection, chat_id: i64, message_id: i32) -> Result<Option<i64>, rusqlite::Error> {
+    conn.query_row(
+        "SELECT u.telegram_id FROM polls p
+         JOIN users u ON p.user_id = u.id
+         WHERE p.chat_id = ? AND p.message_id = ? AND p.closed = 0",
+        params![chat_id, message_id],
+        |row| row.get(0),
+    ).optional()
+}
+
+/// Помечает голосование закрытым. Возвращает запись, только если закрыли именно мы,
+/// чтобы событие не создалось дважды (ответ stop_poll и апдейт о закрытии приходят оба).
+fn close_poll(conn: &Connection, poll_id: &str) -> Result<Option<PollRecord>, rusqlite::Error> {
+    let changed = conn.execute(
+        "UPDATE polls SET closed = 1 WHERE poll_id = ? AND closed = 0",
+        params![poll_id],
+    )?;
+    if changed == 0 {
+        return Ok(None);
+    }
+
+    conn.query_row(
+        "SELECT p.poll_id, p.chat_id, p.user_id, u.telegram_id, p.text, p.options
+         FROM polls p
+         JOIN users u ON p.user_id = u.id
+         WHERE p.poll_id = ?",
+        params![poll_id],
+        |row| {
+            let options: String = row.get(5)?;
+            Ok(PollRecord {
+                poll_id: row.get(0)?,
+                chat_id: row.get(1)?,
+                user_id: row.get(2)?,
+                telegram_id: row.get(3)?,
+                text: row.get(4)?,
+                options: options.split('|').map(str::to_string).collect(),
+            })
+        },
+    ).optional()
+}
+
+pub async fn handle_poll_command(bot: &Bot, msg: &Message, db: &Db, args: &str) -> ResponseResult<()> {
+    let settings = settings::for_message(db, msg).await.map_err(DatabaseError)?;
+    let lang = settings.lang;
+
+    if msg.chat.is_private() {
+        bot.send_message(msg.chat.id, t(lang, "poll_private_only")).await?;
+        return Ok(());
+    }
+
+    let Some((options, text)) = parse_poll_args(args, &settings) else {
+        bot.send_message(msg.chat.id, t(lang, "poll_usage")).await?;
+        return Ok(());
+    };
+
+    if options.len() > 10 {
+        bot.send_message(msg.chat.id, t(lang, "poll_too_many")).await?;
+        return Ok(());
+    }
+
+    let now = clock::SYSTEM.now_in(settings.timezone);
+    if options.iter().any(|o| *o <= now) {
+        bot.send_message(msg.chat.id, t(lang, "poll_past_options")).await?;
+        return Ok(());
+    }
+
+    let labels = options
+        .iter()
+        .map(|o| settings.format_datetime(*o))
+        .collect::<Vec<_>>();
+    let deadline = poll_deadline(now, *options.iter().min().unwrap());
+
+    let sent = bot
+        .send_poll(msg.chat.id, tf(lang, "poll_question", &[("text", &text)]), labels)
+        .is_anonymous(false)
+        .await?;
+    let Some(poll) = sent.poll() else {
+        return Ok(());
+    };
+
+    let user = msg.from().unwrap();
+    let (telegram_id, username) = (user.id.0 as i64, user.username.clone());
+    let (poll_id, chat_id, message_id, poll_text) = (poll.id.clone(), msg.chat.id.0, sent.id.0, text.clone());
+    let tenant = tenants::name_of(bot);
+    db.call(move |conn| in_transaction(conn, |tx| {
+        let user_id = ensure_user_exists(tx, tenant, telegram_id, username)?;
+        save_poll(tx, &NewPoll {
+            tenant,
+            poll_id: &poll_id,
+            chat_id,
+            message_id,
+            user_id,
+            text: &poll_text,
+            options: &options,
+            deadline,
+            deadline_utc: timezone::to_utc(deadline, settings.timezone),
+        })
+    })).await.map_err(DatabaseError)?;
+
+    chunks::send(bot, msg.chat.id, tf(lang, "poll_opened", &[("deadline", &settings.format_datetime(deadline))])).await?;
+    Ok(())
+}
+
+pub async fn handle_close_command(bot: &Bot, msg: &Message, db: &Db) -> ResponseResult<()> {
+    let lang = settings::for_message(db, msg).await.map_err(DatabaseError)?.lang;
+
+    let Some(reply) = msg.reply_to_message() else {
+        bot.send_message(msg.chat.id, t(lang, "poll_close_usage")).await?;
+        return Ok(());
+    };
+
+    let (chat_id, message_id) = (msg.chat.id.0, reply.id.0);
+    let creator = db.call(move |conn| find_open_poll_by_message(conn, chat_id, message_id))
+        .await
+        .map_err(DatabaseError)?;
+
+    match creator {
+        None => {
+            bot.send_message(msg.chat.id, t(lang, "poll_not_found")).await?;
+        }
+        Some(creator) if creator != msg.from().unwrap().id.0 as i64 => {
+            bot.send_message(msg.chat.id, t(lang, "poll_close_not_author")).await?;
+        }
+        Some(_) => {
+            let poll = bot.stop_poll(msg.chat.id, reply.id).await?;
+            finish_poll(bot, db, &poll).await?;
+        }
+    }
+    Ok(())
+}
+
+pub async fn handle_poll_update(bot: Bot, poll: Poll, db: Db) -> ResponseResult<()> {
+    if poll.is_closed {
+        finish_poll(&bot, &db, &poll).await?;
+    }
+    Ok(())
+}
+
+pub async fn close_expired_polls(db: &Db, clock: &dyn Clock) {
+    let now = clock.now_utc();
+    let expired = db.call(move |conn| get_expired_polls(conn, now)).await;
+
+    match expired {
+        Ok(polls) => {
+            for expired in polls {
+                println!("Closing expired poll: {:?}", expired);
+                let bot = tenants::bot(&expired.tenant);
+                match bot.stop_poll(ChatId(expired.chat_id), MessageId(expired.message_id)).await {
+                    Ok(poll) => {
+                        let _ = finish_poll(bot, db, &poll).await;
+                    }
+                    Err(e) => log::error!("Failed to stop poll {:?}: {}", expired, e),
+                }
+            }
+        }
+        Err(e) => log::error!("Failed to load expired polls: {}", e),
+    }
+}
+
+/// Создаёт событие на время, набравшее больше всего голосов (при равенстве — на более раннее).
+async fn finish_poll(bot: &Bot, db: &Db, poll: &Poll) -> ResponseResult<()> {
+    let poll_id = poll.id.clone();
+    let closed = db.call(move |conn| {
+        let Some(record) = close_poll(conn, &poll_id)? else {
+            return Ok(None);
+        };
+        let settings = settings::resolve(conn, record.telegram_id, record.chat_id)?;
+        Ok(Some((record, settings)))
+    }).await.map_err(DatabaseError)?;
+    let Some((record, settings)) = closed else {
+        return Ok(());
+    };
+
+    let winner = poll
+        .options
+        .iter()
+        .enumerate()
+        .filter(|(_, option)| option.voter_count > 0)
+        .max_by(|(a_idx, a), (b_idx, b)| a.voter_count.cmp(&b.voter_count).then(b_idx.cmp(a_idx)));
+
+    let Some((index, option)) = winner else {
+        chunks::send(bot, ChatId(record.chat_id), tf(settings.lang, "poll_no_votes", &[("text", &record.text)])).await?;
+        return Ok(());
+    };
+
+    let chosen = record.options.get(index);
+    let parsed = chosen.and_then(|o| NaiveDateTime::parse_from_str(o, EVENT_TIME_FORMAT).ok());
+    let (Some(chosen), Some(chosen_time)) = (chosen, parsed) else {
+        log::error!("Poll {} has no stored option {}", record.poll_id, index);
+        return Ok(());
+    };
+    let (user_id, chat_id, text, chosen) = (record.user_id, record.chat_id, record.text.clone(), chosen.clone());
+    let tenant = tenants::name_of(bot);
+    let event_id = db.call(move |conn| in_transaction(conn, |tx| insert_event(tx, tenant, user_id, chat_id, &text, &chosen)))
+        .await
+        .map_err(DatabaseError)?;
+    let chosen_time = settings.format_datetime(chosen_time);
+
+    let confirmation = chunks::send_last(bot, ChatId(record.chat_id), tf(settings.lang, "poll_finished", &[
+        ("text", &record.text),
+        ("time", &chosen_time),
+        ("votes", &option.voter_count),
+    ])).await?.reply_markup(ics::keyboard(settings.lang, event_id)).await?;
+
+    db.call(move |conn| link_event_message(conn, chat_id, confirmation.id.0, event_id, "confirmation"))
+        .await
+        .map_err(DatabaseError)?;
+    Ok(())
+}
@@ -0,0 +1,224 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use teloxide::prelude::*;
+use teloxide::types::{InlineKeyboardButton, InlineKeyboardMarkup};
+use chrono::{Duration, NaiveDateTime};
+use tokio::sync::Mutex;
+
+use crate::chunks;
+use crate::humanize;
+use crate::clock::Clock;
+use crate::i18n::{t, tf, Lang};
+use crate::settings;
+use crate::{DatabaseError, Db, tenants};
+
+/// Сессии помодоро живут только в памяти: одна на чат, после перезапуска бота не восстанавливаются.
+/// Ключ — бот и чат: личный чат с разными ботами имеет один и тот же id.
+pub type Sessions = Arc<Mutex<HashMap<(&'static str, i64), Session>>>;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Phase {
+    Work,
+    Break,
+}
+
+#[derive(Debug)]
+pub struct Session {
+    owner_id: i64,
+    lang: Lang,
+    work_minutes: i64,
+    break_minutes: i64,
+    cycles: i64,
+    cycle: i64,
+    phase: Phase,
+    phase_ends: NaiveDateTime,
+    /// Сколько оставалось до конца фазы в момент паузы
+    paused: Option<Duration>,
+}
+
+impl Session {
+    fn phase_minutes(&self) -> i64 {
+        match self.phase {
+            Phase::Work => self.work_minutes,
+            Phase::Break => self.break_minutes,
+        }
+    }
+
+    fn phase_message(&self) -> String {
+        let key = match self.phase {
+            Phase::Work => "pomodoro_work",
+            Phase::Break => "pomodoro_break",
+        };
+        tf(self.lang, key, &[
+            ("duration", &humanize::minutes(self.lang, self.phase_minutes())),
+            ("cycle", &self.cycle),
+            ("cycles", &self.cycles),
+        ])
+    }
+
+    /// Переход к следующей фазе; `false`, если все циклы пройдены.
+    fn advance(&mut self, now: NaiveDateTime) -> bool {
+        match self.phase {
+            Phase::Work if self.cycle >= self.cycles => return false,
+            Phase::Work => self.phase = Phase::Break,
+            Phase::Break => {
+                self.phase = Phase::Work;
+                self.cycle += 1;
+            }
+        }
+        self.phase_ends = now + Duration::minutes(self.phase_minutes());
+        true
+    }
+}
+
+pub fn new_sessions() -> Sessions {
+    Arc::new(Mutex::new(HashMap::new()))
+}
+
+fn keyboard(lang: Lang, paused: bool) -> InlineKeyboardMarkup {
+    let toggle = if paused {
+        InlineKeyboardButton::callback(t(lang, "pomodoro_resume"), "pom:resume")
+    } else {
+        InlineKeyboardButton::callback(t(lang, "pomodoro_pause"), "pom:pause")
+    };
+    InlineKeyboardMarkup::new(vec![vec![
+        toggle,
+        InlineKeyboardButton::callback(t(lang, "pomodoro_stop"), "pom:stop"),
+    ]])
+}
+
+/// Разбирает `[работа] [перерыв] [циклы]` в минутах, по умолчанию 25 5 4.
+fn parse_args(args: &str) -> Option<(i64, i64, i64)> {
+    let values = args
+        .split_whitespace()
+        .map(|value| value.parse::<i64>().ok())
+        .collect::<Option<Vec<_>>>()?;
+    if values.len() > 3 {
+        return None;
+    }
+
+    let work = values.first().copied().unwrap_or(25);
+    let rest = values.get(1).copied().unwrap_or(5);
+    let cycles = values.get(2).copied().unwrap_or(4);
+    ((1..=180).contains(&work) && (1..=60).contains(&rest) && (1..=12).contains(&cycles))
+        .then_some((work, rest, cycles))
+}
+
+pub async fn handle_pomodoro_command(bot: &Bot, msg: &Message, sessions: &Sessions, args: &str, lang: Lang) -> ResponseResult<()> {
+    let chat_id = msg.chat.id.0;
+    let key = (tenants::name_of(bot), chat_id);
+
+    if args == "stop" {
+        let stopped = sessions.lock().await.remove(&key).is_some();
+        let key = if stopped { "pomodoro_stopped" } else { "pomodoro_not_running" };
+        chunks::send(bot, msg.chat.id, t(lang, key)).await?;
+        return Ok(());
+    }
+
+    let Some((work_minutes, break_minutes, cycles)) = parse_args(args) else {
+        bot.send_message(msg.chat.id, t(lang, "pomodoro_usage")).await?;
+        return Ok(());
+    };
+
+    let session = Session {
+        owner_id: msg.from().unwrap().id.0 as i64,
+        lang,
+        work_minutes,
+        break_minutes,
+        cycles,
+        cycle: 1,
+        phase: Phase::Work,
+        phase_ends: chrono::Local::now().naive_local() + Duration::minutes(work_minutes),
+        paused: None,
+    };
+    let text = session.phase_message();
+    sessions.lock().await.insert(key, session);
+
+    log::info!("Started pomodoro in chat {}", chat_id);
+    chunks::send_last(bot, msg.chat.id, text).await?.reply_markup(keyboard(lang, false)).await?;
+    Ok(())
+}
+
+/// Вызывается из цикла напоминаний: переключает фазы, время которых вышло.
+pub async fn tick(sessions: &Sessions, clock: &dyn Clock) {
+    let now = clock.now_in(None);
+    let mut notifications = Vec::new();
+
+    let mut sessions = sessions.lock().await;
+    sessions.retain(|&(tenant, chat_id), session| {
+        if session.paused.is_some() || session.phase_ends > now {
+            return true;
+        }
+        if session.advance(now) {
+            notifications.push((tenant, chat_id, session.phase_message(), Some(keyboard(session.lang, false))));
+            true
+        } else {
+            notifications.push((tenant, chat_id, t(session.lang, "pomodoro_finished"), None));
+            false
+        }
+    });
+    drop(sessions);
+
+    for (tenant, chat_id, text, keyboard) in notifications {
+        println!("Pomodoro transition in chat {}", chat_id);
+        let mut request = tenants::bot(tenant).send_message(ChatId(chat_id), text);
+        if let Some(keyboard) = keyboard {
+            request = request.reply_markup(keyboard);
+        }
+        let _ = request.await;
+    }
+}
+
+pub async fn handle_callback(bot: &Bot, q: &CallbackQuery, db: &Db, sessions: &Sessions, action: &str) -> ResponseResult<()> {
+    let Some(message) = q.message.as_ref() else {
+        bot.answer_callback_query(q.id.clone()).await?;
+        return Ok(());
+    };
+    let chat_id = message.chat.id.0;
+    let key = (tenants::name_of(bot), chat_id);
+
+    let telegram_id = q.from.id.0 as i64;
+    let lang = db.call(move |conn| settings::resolve(conn, telegram_id, chat_id)).await.map_err(DatabaseError)?.lang;
+
+    let now = chrono::Local::now().naive_local();
+    let mut sessions = sessions.lock().await;
+    let Some(session) = sessions.get_mut(&key) else {
+        drop(sessions);
+        bot.answer_callback_query(q.id.clone()).text(t(lang, "pomodoro_not_running")).await?;
+        bot.edit_message_reply_markup(message.chat.id, message.id).await?;
+        return Ok(());
+    };
+    if session.owner_id != q.from.id.0 as i64 {
+        drop(sessions);
+        bot.answer_callback_query(q.id.clone()).text(t(lang, "pomodoro_not_yours")).await?;
+        return Ok(());
+    }
+
+    let (answer, markup) = match action {
+        "pause" if session.paused.is_none() => {
+            session.paused = Some(session.phase_ends - now);
+            (t(lang, "pomodoro_paused"), Some(keyboard(lang, true)))
+        }
+        "resume" => {
+            if let Some(remaining) = session.paused.take() {
+                session.phase_ends = now + remaining;
+            }
+            (t(lang, "pomodoro_resumed"), Some(keyboard(lang, false)))
+        }
+        "stop" => {
+            sessions.remove(&key);
+            (t(lang, "pomodoro_stopped"), None)
+        }
+        _ => (String::new(), Some(keyboard(lang, true))),
+    };
+    drop(sessions);
+
+    bot.answer_callback_query(q.id.clone()).text(answer).await?;
+    let mut request = bot.edit_message_reply_markup(message.chat.id, message.id);
+    if let Some(markup) = markup {
+        request = request.reply_markup(markup);
+    }
+    request.await?;
+    Ok(())
+}
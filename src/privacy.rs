@@ -0,0 +1,67 @@
+use teloxide::prelude::*;
+use rusqlite::{Connection, params, OptionalExtension};
+
+use crate::i18n::{t, Lang};
+use crate::{DatabaseError, Db, add_column_if_missing, ensure_user_exists, tenants};
+
+pub fn init_tables(conn: &Connection) -> Result<(), rusqlite::Error> {
+    // Пользователь не учитывается в счётчиках использования и журнале изменений
+    add_column_if_missing(conn, "users", "private_mode", "INTEGER NOT NULL DEFAULT 0")?;
+    Ok(())
+}
+
+/// Исключил ли себя пользователь из статистики. Неизвестные пользователи учитываются.
+pub fn is_private(conn: &Connection, telegram_id: i64) -> Result<bool, rusqlite::Error> {
+    let private = conn
+        .query_row("SELECT private_mode FROM users WHERE telegram_id = ?", params![telegram_id], |row| row.get(0))
+        .optional()?;
+    Ok(private.unwrap_or(false))
+}
+
+/// То же по `users.id`, которым журнал изменений отмечает автора.
+pub fn is_private_user(conn: &Connection, user_id: i64) -> Result<bool, rusqlite::Error> {
+    let private = conn
+        .query_row("SELECT private_mode FROM users WHERE id = ?", params![user_id], |row| row.get(0))
+        .optional()?;
+    Ok(private.unwrap_or(false))
+}
+
+fn set_private(conn: &Connection, telegram_id: i64, private: bool) -> Result<(), rusqlite::Error> {
+    conn.execute("UPDATE users SET private_mode = ? WHERE telegram_id = ?", params![private, telegram_id])?;
+    Ok(())
+}
+
+/// `/privacy on|off` — исключить себя из статистики и журнала изменений или вернуть учёт.
+/// Без аргументов показывает текущее состояние.
+pub async fn handle_privacy_command(bot: &Bot, msg: &Message, db: &Db, args: &str, lang: Lang) -> ResponseResult<()> {
+    let Some(user) = msg.from() else {
+        return Ok(());
+    };
+    let private = match args {
+        "" => None,
+        "on" => Some(true),
+        "off" => Some(false),
+        _ => {
+            bot.send_message(msg.chat.id, t(lang, "privacy_usage")).await?;
+            return Ok(());
+        }
+    };
+
+    let (telegram_id, username, tenant) = (user.id.0 as i64, user.username.clone(), tenants::name_of(bot));
+    let private = db.call(move |conn| {
+        ensure_user_exists(conn, tenant, telegram_id, username)?;
+        match private {
+            Some(private) => set_private(conn, telegram_id, private).map(|_| private),
+            None => is_private(conn, telegram_id),
+        }
+    }).await.map_err(DatabaseError)?;
+
+    let key = match (args, private) {
+        ("", true) => "privacy_status_on",
+        ("", false) => "privacy_status_off",
+        (_, true) => "privacy_enabled",
+        (_, false) => "privacy_disabled",
+    };
+    bot.send_message(msg.chat.id, t(lang, key)).await?;
+    Ok(())
+}
@@ -0,0 +1,124 @@
+use chrono::{Datelike, Duration, NaiveDate, Weekday};
+
+use crate::resolve_date;
+use crate::settings::Settings;
+use crate::timezone;
+
+/// Начала вопросов о расписании; помимо них вопросом считается текст со знаком `?`.
+const QUESTION_PREFIXES: &[&str] = &[
+    "что у меня", "что на", "что в", "какие планы", "какие дела", "покажи", "что запланировано",
+    "what's on", "whats on", "what is on", "what do i have", "what have i got", "show me", "show my", "any plans",
+];
+
+/// Период, о котором спрашивают: дни с `start` по `end` включительно.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Period {
+    pub start: NaiveDate,
+    pub end: NaiveDate,
+}
+
+impl Period {
+    fn day(date: NaiveDate) -> Self {
+        Period { start: date, end: date }
+    }
+
+    pub fn contains(self, date: NaiveDate) -> bool {
+        (self.start..=self.end).contains(&date)
+    }
+}
+
+fn parse_weekday(word: &str) -> Option<Weekday> {
+    // Русские названия — в именительном и винительном падеже («в пятницу»)
+    let weekday = match word {
+        "понедельник" | "пн" | "monday" | "mon" => Weekday::Mon,
+        "вторник" | "вт" | "tuesday" | "tue" => Weekday::Tue,
+        "среда" | "среду" | "ср" | "wednesday" | "wed" => Weekday::Wed,
+        "четверг" | "чт" | "thursday" | "thu" => Weekday::Thu,
+        "пятница" | "пятницу" | "пт" | "friday" | "fri" => Weekday::Fri,
+        "суббота" | "субботу" | "сб" | "saturday" | "sat" => Weekday::Sat,
+        "воскресенье" | "вс" | "sunday" | "sun" => Weekday::Sun,
+        _ => return None,
+    };
+    Some(weekday)
+}
+
+/// Ближайший такой день недели, считая сегодняшний.
+fn next_weekday(today: NaiveDate, weekday: Weekday) -> NaiveDate {
+    let days_ahead = (weekday.num_days_from_monday() + 7 - today.weekday().num_days_from_monday()) % 7;
+    today + Duration::days(days_ahead as i64)
+}
+
+fn week_end(date: NaiveDate) -> NaiveDate {
+    date + Duration::days(6 - date.weekday().num_days_from_monday() as i64)
+}
+
+fn month_end(date: NaiveDate) -> NaiveDate {
+    let (year, month) = if date.month() == 12 { (date.year() + 1, 1) } else { (date.year(), date.month() + 1) };
+    NaiveDate::from_ymd_opt(year, month, 1).unwrap() - Duration::days(1)
+}
+
+/// Период из слов вопроса: «сегодня», «в пятницу», «на следующей неделе», «next month», «15.03».
+fn parse_period(text: &str, today: NaiveDate, settings: &Settings) -> Option<Period> {
+    let words = text
+        .split(|c: char| c.is_whitespace() || matches!(c, '?' | ',' | '!'))
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<_>>();
+    let has = |phrase: &str| text.contains(phrase);
+    let next = has("следующ") || has("next");
+
+    if has("послезавтра") || has("day after tomorrow") {
+        return Some(Period::day(today + Duration::days(2)));
+    }
+    if has("завтра") || has("tomorrow") {
+        return Some(Period::day(today + Duration::days(1)));
+    }
+    if has("сегодня") || has("today") || has("tonight") {
+        return Some(Period::day(today));
+    }
+    if has("выходны") || has("weekend") {
+        let saturday = next_weekday(today, Weekday::Sat);
+        let saturday = if next && today.weekday() != Weekday::Sun { saturday + Duration::days(7) } else { saturday };
+        let start = if today.weekday() == Weekday::Sun { today } else { saturday };
+        return Some(Period { start, end: week_end(saturday) });
+    }
+    // День недели раньше недели: «понедельник» содержит «недел»
+    if let Some(weekday) = words.iter().find_map(|word| parse_weekday(word)) {
+        let date = next_weekday(today, weekday);
+        return Some(Period::day(if next && date == today { date + Duration::days(7) } else { date }));
+    }
+    if has("недел") || has("week") {
+        return Some(if next {
+            let monday = week_end(today) + Duration::days(1);
+            Period { start: monday, end: week_end(monday) }
+        } else {
+            Period { start: today, end: week_end(today) }
+        });
+    }
+    if has("месяц") || has("month") {
+        return Some(if next {
+            let first = month_end(today) + Duration::days(1);
+            Period { start: first, end: month_end(first) }
+        } else {
+            Period { start: today, end: month_end(today) }
+        });
+    }
+    words
+        .iter()
+        .filter(|word| word.chars().next().is_some_and(|c| c.is_ascii_digit()))
+        .find_map(|word| resolve_date(word.trim_end_matches('.'), today, settings.date_format))
+        .map(Period::day)
+}
+
+/// Разбирает вопрос о расписании вида «что у меня в пятницу?» или «what's on next week?».
+/// Текст с `@` — это создание события, а не вопрос.
+pub fn parse_query(text: &str, settings: &Settings) -> Option<Period> {
+    let text = text.trim().to_lowercase().replace('’', "'");
+    if text.contains('@') {
+        return None;
+    }
+    let is_question = text.ends_with('?') || QUESTION_PREFIXES.iter().any(|prefix| text.starts_with(prefix));
+    if !is_question {
+        return None;
+    }
+    parse_period(&text, timezone::now_in(settings.timezone).date(), settings)
+}
@@ -0,0 +1,224 @@
+use std::time::Duration;
+
+use futures::{Stream, StreamExt};
+use teloxide::prelude::*;
+use teloxide::stop::StopToken;
+use teloxide::types::{MessageId, UpdateKind};
+use teloxide::update_listeners::{AsUpdateStream, Polling, StatefulListener, UpdateListener};
+use teloxide::RequestError;
+use chrono::Duration as ChronoDuration;
+use rusqlite::{Connection, params, OptionalExtension};
+use serde::Deserialize;
+
+use crate::chunks;
+use crate::followups::{self, FollowUp};
+use crate::i18n::tf;
+use crate::settings::{self, Settings};
+use crate::timezone;
+use crate::{DatabaseError, Db, blocklist, get_event, in_transaction, metrics, snooze_event, stars, tenants, updates};
+
+/// Реакции, которыми завершают напоминание. ✅ доступна только с Premium,
+/// поэтому принимаются и обычные 👍 и 👌.
+const DONE_EMOJI: &[&str] = &["✅", "👍", "👌"];
+const SNOOZE_EMOJI: &str = "😴";
+const SNOOZE_MINUTES: i64 = 60;
+
+/// Обновления, которые бот запрашивает у Telegram. `message_reaction` приходит,
+/// только если указан явно, а teloxide 0.12 о нём не знает.
+const ALLOWED_UPDATES: &[&str] = &["message", "edited_message", "callback_query", "poll", "message_reaction"];
+
+#[derive(Debug, Deserialize)]
+struct ReactionUpdate {
+    message_reaction: MessageReactionUpdated,
+}
+
+#[derive(Debug, Deserialize)]
+struct MessageReactionUpdated {
+    chat: ReactionChat,
+    message_id: i32,
+    /// Отсутствует у анонимных реакций от имени чата.
+    user: Option<ReactionUser>,
+    old_reaction: Vec<ReactionType>,
+    new_reaction: Vec<ReactionType>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ReactionChat {
+    id: i64,
+}
+
+#[derive(Debug, Deserialize)]
+struct ReactionUser {
+    id: u64,
+}
+
+#[derive(Debug, Deserialize, PartialEq)]
+struct ReactionType {
+    #[serde(rename = "type")]
+    kind: String,
+    emoji: Option<String>,
+}
+
+impl MessageReactionUpdated {
+    /// Эмодзи, которые пользователь только что добавил.
+    fn added(&self) -> impl Iterator<Item = &str> {
+        self.new_reaction
+            .iter()
+            .filter(|reaction| reaction.kind == "emoji" && !self.old_reaction.contains(reaction))
+            .filter_map(|reaction| reaction.emoji.as_deref())
+    }
+}
+
+enum Action {
+    Done,
+    Snooze,
+}
+
+enum Outcome {
+    Done(Vec<FollowUp>),
+    Snoozed(String),
+}
+
+/// Событие, напоминание о котором доставлено этим сообщением.
+fn reminder_event(conn: &Connection, chat_id: i64, message_id: i32) -> Result<Option<i64>, rusqlite::Error> {
+    conn.query_row(
+        "SELECT event_id FROM sent_notifications WHERE chat_id = ? AND message_id = ? ORDER BY created_at DESC LIMIT 1",
+        params![chat_id, message_id],
+        |row| row.get(0),
+    ).optional()
+}
+
+/// Переносит событие на час от текущего момента.
+fn snooze(conn: &Connection, event_id: i64, settings: &Settings) -> Result<Option<String>, rusqlite::Error> {
+    let Some(event) = get_event(conn, event_id)? else {
+        return Ok(None);
+    };
+    let start = timezone::now_in(settings.timezone) + ChronoDuration::minutes(SNOOZE_MINUTES);
+    in_transaction(conn, |tx| snooze_event(tx, &event, start))?;
+    Ok(Some(settings.format_time(start.time())))
+}
+
+/// Обрабатывает реакцию на напоминание: ✅ завершает событие, 😴 откладывает его на час.
+/// Реагировать может только владелец события.
+async fn handle_reaction(bot: Bot, db: Db, reaction: MessageReactionUpdated) -> ResponseResult<()> {
+    let action = reaction.added().find_map(|emoji| match emoji {
+        emoji if DONE_EMOJI.contains(&emoji) => Some(Action::Done),
+        SNOOZE_EMOJI => Some(Action::Snooze),
+        _ => None,
+    });
+    let (Some(action), Some(user)) = (action, reaction.user.as_ref()) else {
+        return Ok(());
+    };
+    if blocklist::is_blocked(&db, user.id as i64).await {
+        return Ok(());
+    }
+
+    metrics::track(&db, Some(user.id as i64), "reaction").await;
+    let (telegram_id, chat_id, message_id) = (user.id as i64, reaction.chat.id, reaction.message_id);
+    let result = db.call(move |conn| {
+        let Some(event_id) = reminder_event(conn, chat_id, message_id)? else {
+            return Ok(None);
+        };
+        if get_event(conn, event_id)?.is_none_or(|event| event.owner_id != telegram_id) {
+            return Ok(None);
+        }
+        let settings = settings::resolve(conn, telegram_id, chat_id)?;
+        let outcome = match action {
+            Action::Done => in_transaction(conn, |tx| followups::complete_event(tx, event_id))?.map(Outcome::Done),
+            Action::Snooze => snooze(conn, event_id, &settings)?.map(Outcome::Snoozed),
+        };
+        Ok(outcome.map(|outcome| (event_id, settings, outcome)))
+    }).await.map_err(DatabaseError)?;
+
+    let Some((event_id, settings, outcome)) = result else {
+        return Ok(());
+    };
+    // Кнопки под напоминанием больше не нужны
+    bot.edit_message_reply_markup(ChatId(chat_id), MessageId(message_id)).await?;
+    match outcome {
+        Outcome::Done(follow_ups) => {
+            log::info!("Event {} completed by reaction", event_id);
+            stars::unpin(&bot, &db, event_id).await?;
+            followups::announce(&bot, &db, &settings, &follow_ups).await?;
+        }
+        Outcome::Snoozed(time) => {
+            log::info!("Event {} snoozed by reaction", event_id);
+            chunks::send_last(&bot, ChatId(chat_id), tf(settings.lang, "reaction_snoozed", &[("time", &time)])).await?
+                .reply_to_message_id(MessageId(message_id))
+                .await?;
+        }
+    }
+    Ok(())
+}
+
+/// Обновление `message_reaction` в том виде, в каком его присылает Telegram.
+/// Как и сообщения, отмечается обработанным только после успешной обработки.
+pub async fn handle_reaction_update(bot: Bot, db: Db, value: serde_json::Value, update_id: i32) -> ResponseResult<()> {
+    let tenant = tenants::name_of(&bot);
+    match serde_json::from_value::<ReactionUpdate>(value) {
+        Ok(update) => {
+            handle_reaction(bot, db.clone(), update.message_reaction).await?;
+            updates::mark_processed(&db, tenant, update_id).await;
+            Ok(())
+        }
+        Err(e) => {
+            log::error!("Failed to parse reaction update: {}", e);
+            Ok(())
+        }
+    }
+}
+
+/// Включает `message_reaction` в списке обновлений бота. Telegram запоминает список,
+/// поэтому дальше опрос без `allowed_updates` продолжает получать реакции.
+/// Запрос без `offset` ничего не подтверждает, так что обновления не теряются.
+async fn subscribe(bot: &Bot) {
+    let url = format!("{}bot{}/getUpdates", bot.api_url(), bot.token());
+    let body = serde_json::json!({ "allowed_updates": ALLOWED_UPDATES, "limit": 1, "timeout": 0 });
+    let response = reqwest::Client::new().post(url).json(&body).timeout(Duration::from_secs(10)).send().await;
+    if let Err(e) = response.and_then(|response| response.error_for_status()) {
+        log::error!("Failed to subscribe to reaction updates: {}", e);
+    }
+}
+
+fn update_stream(state: &mut (Polling<Bot>, Bot, Db)) -> impl Stream<Item = Result<Update, RequestError>> + Send + '_ {
+    let (polling, bot, db) = state;
+    let (bot, db) = (bot.clone(), db.clone());
+    let tenant = tenants::name_of(&bot);
+    polling.as_stream().filter_map(move |update| {
+        let (bot, db) = (bot.clone(), db.clone());
+        async move {
+            // Повторную доставку уже обработанного обновления пропускаем, отметку ставит обработчик
+            if let Ok(Update { id, .. }) = &update {
+                if updates::is_redelivered(&db, tenant, *id).await {
+                    log::info!("Skipping redelivered update {}", id);
+                    return None;
+                }
+            }
+            match update {
+                // Реакции teloxide не разбирает и диспетчер их отбрасывает, поэтому они обрабатываются здесь
+                Ok(Update { id, kind: UpdateKind::Error(value) }) if value.get("message_reaction").is_some() => {
+                    tokio::spawn(async move {
+                        if let Err(e) = handle_reaction_update(bot, db, value, id).await {
+                            log::error!("Failed to handle reaction: {}", e);
+                        }
+                    });
+                    None
+                }
+                other => Some(other),
+            }
+        }
+    })
+}
+
+fn stop_token(state: &mut (Polling<Bot>, Bot, Db)) -> StopToken {
+    state.0.stop_token()
+}
+
+/// Опрос обновлений как у `Dispatcher::dispatch`, но с реакциями на сообщения
+/// и без повторной обработки обновлений, доставленных заново.
+/// Список обновлений задаёт `subscribe`, подсказки диспетчера не применяются.
+pub async fn listener(bot: Bot, db: Db) -> impl UpdateListener<Err = RequestError> {
+    let polling = Polling::builder(bot.clone()).timeout(Duration::from_secs(10)).delete_webhook().await.build();
+    subscribe(&bot).await;
+    StatefulListener::new((polling, bot, db), update_stream, stop_token)
+}
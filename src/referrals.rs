@@ -0,0 +1,92 @@
+//! Реферальные ссылки `t.me/<бот>?start=ref_<код>`: тот, кто продвигает публичный бот,
+//! раздаёт разным сообществам свои коды и видит в `/admin referrals`, откуда пришли пользователи.
+
+use rusqlite::{Connection, params};
+
+/// Параметр `/start`, которым помечены реферальные ссылки.
+const START_PREFIX: &str = "ref_";
+/// Telegram ограничивает параметр `start` 64 символами, из которых часть занимает префикс.
+const MAX_CODE_LEN: usize = 32;
+
+pub fn init_tables(conn: &Connection) -> Result<(), rusqlite::Error> {
+    // Один код на пользователя: засчитывается ссылка, по которой он пришёл впервые
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS referrals (
+            telegram_id INTEGER PRIMARY KEY,
+            code TEXT NOT NULL,
+            tenant TEXT NOT NULL,
+            created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+        )",
+        [],
+    )?;
+    conn.execute("CREATE INDEX IF NOT EXISTS idx_referrals_code ON referrals(code, created_at)", [])?;
+    Ok(())
+}
+
+/// Код из `/start ref_<код>`: латиница, цифры, `_` и `-`, как допускает Telegram.
+pub fn start_code(args: &str) -> Option<&str> {
+    args.trim()
+        .strip_prefix(START_PREFIX)
+        .filter(|code| !code.is_empty() && code.len() <= MAX_CODE_LEN)
+        .filter(|code| code.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-'))
+}
+
+/// Засчитывает пользователя коду, если он пришёл впервые. Вызывается до регистрации:
+/// уже знакомый боту пользователь, открывший чужую ссылку, новым не считается.
+pub fn attribute(conn: &Connection, tenant: &str, telegram_id: i64, code: &str) -> Result<bool, rusqlite::Error> {
+    let inserted = conn.execute(
+        "INSERT OR IGNORE INTO referrals (telegram_id, code, tenant)
+         SELECT ?, ?, ? WHERE NOT EXISTS (SELECT 1 FROM users WHERE telegram_id = ?)",
+        params![telegram_id, code.to_lowercase(), tenant, telegram_id],
+    )?;
+    if inserted > 0 {
+        log::info!("User {} signed up via referral code {}", telegram_id, code);
+    }
+    Ok(inserted > 0)
+}
+
+/// Регистрации по кодам за последние `days` дней или за всё время: код, сколько пришло
+/// и сколько из них создали хотя бы одно событие.
+pub fn report(conn: &Connection, days: Option<u32>) -> Result<Vec<(String, i64, i64)>, rusqlite::Error> {
+    let since = days.map_or_else(|| "-100 years".to_string(), |days| format!("-{} days", days));
+    let mut stmt = conn.prepare(
+        "SELECT r.code, COUNT(*) AS signups,
+                SUM(EXISTS (SELECT 1 FROM events e JOIN users u ON e.user_id = u.id WHERE u.telegram_id = r.telegram_id))
+         FROM referrals r
+         WHERE r.created_at > datetime('now', ?)
+         GROUP BY r.code
+         ORDER BY signups DESC, r.code",
+    )?;
+    let rows = stmt.query_map(params![since], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(rows)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::tests::memory_db;
+    use crate::{ensure_user_exists, insert_event, tenants};
+
+    #[tokio::test]
+    async fn only_new_users_count_towards_a_code() {
+        let db = memory_db();
+        let report = db.call(|conn| {
+            ensure_user_exists(conn, tenants::DEFAULT, 1, None)?;
+            assert!(!attribute(conn, tenants::DEFAULT, 1, "Habr")?);
+            for telegram_id in [2, 3] {
+                assert!(attribute(conn, tenants::DEFAULT, telegram_id, "Habr")?);
+                ensure_user_exists(conn, tenants::DEFAULT, telegram_id, None)?;
+            }
+            // Повторная ссылка с другим кодом первую не перебивает
+            assert!(!attribute(conn, tenants::DEFAULT, 2, "vc")?);
+            let user_id = ensure_user_exists(conn, tenants::DEFAULT, 3, None)?;
+            insert_event(conn, tenants::DEFAULT, user_id, 3, "Первое событие", "01.01.2030 09:00")?;
+            report(conn, Some(30))
+        }).await.unwrap();
+        assert_eq!(report, vec![("habr".to_string(), 2, 1)]);
+        assert_eq!(start_code("ref_spring-2026"), Some("spring-2026"));
+        assert_eq!(start_code("ref_a/b"), None);
+        assert_eq!(start_code("share_abc"), None);
+    }
+}
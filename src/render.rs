@@ -0,0 +1,235 @@
+//! Картинки PNG: диаграммы для `/stats` и `/admin usage`, карточка дня для `/agenda image`.
+//! На телефоне картинку читать проще, чем длинную таблицу. Рисуются только в сборке с фичей
+//! `images` и при найденном шрифте (`CHART_FONT`), иначе ответы остаются текстовыми.
+
+/// Карточка со списком: заголовок, подзаголовок и строки «время — текст».
+#[cfg_attr(not(feature = "images"), allow(dead_code))]
+pub struct Card {
+    pub title: String,
+    pub subtitle: String,
+    pub rows: Vec<(String, String)>,
+    /// Последняя строка мелким шрифтом, например «и ещё 3».
+    pub footer: Option<String>,
+}
+
+/// PNG со столбцами: по одному на категорию в каждой серии, серии рядом.
+/// `None` — в сборке без фичи `images`, без шрифта или при ошибке отрисовки.
+pub fn bar_chart(title: &str, categories: &[String], series: &[(&str, Vec<u64>)]) -> Option<Vec<u8>> {
+    #[cfg(feature = "images")]
+    {
+        if categories.is_empty() || series.is_empty() {
+            return None;
+        }
+        plot::draw(title, |pixels| plot::bar_chart(pixels, title, categories, series), plot::CHART_SIZE)
+    }
+    #[cfg(not(feature = "images"))]
+    {
+        let _ = (title, categories, series);
+        None
+    }
+}
+
+/// PNG с карточкой; `None` в тех же случаях, что у `bar_chart`.
+pub fn card(card: &Card) -> Option<Vec<u8>> {
+    #[cfg(feature = "images")]
+    {
+        plot::draw(&card.title, |pixels| plot::card(pixels, card), plot::card_size(card))
+    }
+    #[cfg(not(feature = "images"))]
+    {
+        let _ = card;
+        None
+    }
+}
+
+#[cfg(feature = "images")]
+mod plot {
+    use std::env;
+    use std::error::Error;
+    use std::io::Cursor;
+    use std::sync::OnceLock;
+
+    use image::{ImageFormat, RgbImage};
+    use plotters::prelude::*;
+    use plotters::style::{FontStyle, register_font};
+
+    use super::Card;
+
+    pub const CHART_SIZE: (u32, u32) = (800, 480);
+    const CARD_WIDTH: u32 = 720;
+    const CARD_HEADER: u32 = 120;
+    const CARD_ROW: u32 = 44;
+    const CARD_PADDING: u32 = 32;
+    /// Ширина категории по оси X в условных единицах; столбцы серий делят её между собой.
+    const SLOT: i32 = 12;
+    const FONT: &str = "reventor";
+    pub(super) const DEFAULT_FONT: &str = "/usr/share/fonts/truetype/dejavu/DejaVuSans.ttf";
+    const COLORS: [RGBColor; 3] = [RGBColor(66, 133, 244), RGBColor(52, 168, 83), RGBColor(251, 188, 5)];
+
+    /// Загружает шрифт один раз: без него plotters не подпишет оси.
+    fn font_ready() -> bool {
+        static READY: OnceLock<bool> = OnceLock::new();
+        *READY.get_or_init(|| {
+            let path = env::var("CHART_FONT").unwrap_or_else(|_| DEFAULT_FONT.to_string());
+            let bytes = match std::fs::read(&path) {
+                Ok(bytes) => bytes,
+                Err(e) => {
+                    log::error!("Failed to read chart font {}: {}", path, e);
+                    return false;
+                }
+            };
+            // Шрифт нужен до выхода, plotters хранит ссылку на байты
+            match register_font(FONT, FontStyle::Normal, Box::leak(bytes.into_boxed_slice())) {
+                Ok(()) => true,
+                Err(_) => {
+                    log::error!("Chart font {} is not a valid TrueType font", path);
+                    false
+                }
+            }
+        })
+    }
+
+    type Pixels<'a> = DrawingArea<BitMapBackend<'a>, plotters::coord::Shift>;
+
+    /// Рисует на белом холсте и кодирует в PNG.
+    pub fn draw(name: &str, paint: impl FnOnce(&Pixels) -> Result<(), Box<dyn Error>>, (width, height): (u32, u32)) -> Option<Vec<u8>> {
+        if !font_ready() {
+            return None;
+        }
+        let mut pixels = vec![0; (width * height * 3) as usize];
+        let result = (|| {
+            {
+                let root = BitMapBackend::with_buffer(&mut pixels, (width, height)).into_drawing_area();
+                root.fill(&WHITE)?;
+                paint(&root)?;
+                root.present()?;
+            }
+            let image = RgbImage::from_raw(width, height, pixels).ok_or("image buffer has a wrong size")?;
+            let mut png = Vec::new();
+            image.write_to(&mut Cursor::new(&mut png), ImageFormat::Png)?;
+            Ok::<_, Box<dyn Error>>(png)
+        })();
+        match result {
+            Ok(png) => Some(png),
+            Err(e) => {
+                log::error!("Failed to render {:?}: {}", name, e);
+                None
+            }
+        }
+    }
+
+    pub fn bar_chart(root: &Pixels, title: &str, categories: &[String], series: &[(&str, Vec<u64>)]) -> Result<(), Box<dyn Error>> {
+        let slots = categories.len() as i32;
+        let max = series.iter().flat_map(|(_, values)| values).copied().max().unwrap_or_default().max(1);
+        // Подписи категорий — посередине их промежутков
+        let x = (0..slots * SLOT).with_key_points((0..slots).map(|slot| slot * SLOT + SLOT / 2).collect());
+        let mut chart = ChartBuilder::on(root)
+            .caption(title, (FONT, 24))
+            .margin(16)
+            .x_label_area_size(32)
+            .y_label_area_size(48)
+            .build_cartesian_2d(x, 0..max + max / 10 + 1)?;
+        chart
+            .configure_mesh()
+            .disable_x_mesh()
+            .label_style((FONT, 14))
+            .x_label_formatter(&|x| categories.get((x / SLOT) as usize).cloned().unwrap_or_default())
+            .draw()?;
+
+        let width = (SLOT - 2) / series.len() as i32;
+        for (index, (label, values)) in series.iter().enumerate() {
+            let color = COLORS[index % COLORS.len()];
+            let offset = 1 + index as i32 * width;
+            chart
+                .draw_series(values.iter().enumerate().map(|(slot, value)| {
+                    let left = slot as i32 * SLOT + offset;
+                    Rectangle::new([(left, 0), (left + width, *value)], color.filled())
+                }))?
+                .label(*label)
+                .legend(move |(x, y)| Rectangle::new([(x, y - 5), (x + 10, y + 5)], color.filled()));
+        }
+        if series.len() > 1 {
+            chart.configure_series_labels().position(SeriesLabelPosition::UpperLeft).label_font((FONT, 14)).background_style(WHITE).border_style(BLACK).draw()?;
+        }
+        Ok(())
+    }
+
+    pub fn card_size(card: &Card) -> (u32, u32) {
+        let rows = card.rows.len().max(1) as u32 + u32::from(card.footer.is_some());
+        (CARD_WIDTH, CARD_HEADER + rows * CARD_ROW + CARD_PADDING)
+    }
+
+    /// Обрезает текст с многоточием, чтобы он поместился в ширину.
+    fn fit(root: &Pixels, text: &str, style: &TextStyle, width: u32) -> String {
+        let fits = |text: &str| root.estimate_text_size(text, style).is_ok_and(|(w, _)| w <= width);
+        if fits(text) {
+            return text.to_string();
+        }
+        let mut chars = text.chars().collect::<Vec<_>>();
+        while chars.pop().is_some() {
+            let shortened = format!("{}…", chars.iter().collect::<String>().trim_end());
+            if fits(&shortened) {
+                return shortened;
+            }
+        }
+        String::new()
+    }
+
+    pub fn card(root: &Pixels, card: &Card) -> Result<(), Box<dyn Error>> {
+        let accent = COLORS[0];
+        let (width, _) = root.dim_in_pixel();
+        let padding = CARD_PADDING as i32;
+        root.draw(&Rectangle::new([(0, 0), (width as i32, CARD_HEADER as i32 - 24)], accent.filled()))?;
+        let title = TextStyle::from((FONT, 34).into_font()).color(&WHITE);
+        root.draw(&Text::new(fit(root, &card.title, &title, width - 2 * CARD_PADDING), (padding, 20), title))?;
+        let subtitle = TextStyle::from((FONT, 18).into_font()).color(&WHITE);
+        root.draw(&Text::new(fit(root, &card.subtitle, &subtitle, width - 2 * CARD_PADDING), (padding, 64), subtitle))?;
+
+        let time_style = TextStyle::from((FONT, 22).into_font()).color(&accent);
+        let text_style = TextStyle::from((FONT, 22).into_font()).color(&BLACK);
+        let time_width = card.rows.iter().filter_map(|(time, _)| root.estimate_text_size(time, &time_style).ok()).map(|(w, _)| w).max().unwrap_or_default();
+        for (index, (time, text)) in card.rows.iter().enumerate() {
+            let y = (CARD_HEADER + index as u32 * CARD_ROW) as i32;
+            if index > 0 {
+                root.draw(&PathElement::new([(padding, y - 10), (width as i32 - padding, y - 10)], RGBColor(225, 225, 225)))?;
+            }
+            root.draw(&Text::new(time.clone(), (padding, y), time_style.clone()))?;
+            let left = CARD_PADDING + time_width + 20;
+            root.draw(&Text::new(fit(root, text, &text_style, width - left - CARD_PADDING), (left as i32, y), text_style.clone()))?;
+        }
+        if let Some(footer) = &card.footer {
+            let y = (CARD_HEADER + card.rows.len() as u32 * CARD_ROW) as i32;
+            root.draw(&Text::new(footer.clone(), (padding, y), TextStyle::from((FONT, 18).into_font()).color(&RGBColor(120, 120, 120))))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(all(test, feature = "images"))]
+mod tests {
+    use super::*;
+    use super::plot::DEFAULT_FONT;
+
+    #[test]
+    fn renders_png_when_font_is_available() {
+        let categories = vec!["Пн".to_string(), "Вт".to_string()];
+        let png = bar_chart("Тест", &categories, &[("создано", vec![3, 1]), ("выполнено", vec![2, 0])]);
+        if std::path::Path::new(DEFAULT_FONT).exists() {
+            assert!(png.unwrap().starts_with(b"\x89PNG"));
+        }
+    }
+
+    #[test]
+    fn card_grows_with_rows() {
+        let card = |rows: usize| Card {
+            title: "Пн 17.03".to_string(),
+            subtitle: "3 события".to_string(),
+            rows: (0..rows).map(|row| (format!("{}:00", 9 + row), "Планёрка".to_string())).collect(),
+            footer: None,
+        };
+        assert!(plot::card_size(&card(5)).1 > plot::card_size(&card(1)).1);
+        if std::path::Path::new(DEFAULT_FONT).exists() {
+            assert!(super::card(&card(3)).unwrap().starts_with(b"\x89PNG"));
+        }
+    }
+}
@@ -0,0 +1,208 @@
+use teloxide::prelude::*;
+use chrono::NaiveDateTime;
+use rusqlite::{Connection, params};
+
+use crate::blocklist;
+use crate::categories;
+use crate::checklist;
+use crate::chunks;
+use crate::clock::Clock;
+use crate::followups;
+use crate::i18n::{t, tf};
+use crate::notifications;
+use crate::settings::{self, Settings};
+use crate::{DatabaseError, Db, UTC_FORMAT, add_column_if_missing, crypto, get_event, tenants};
+
+/// Сколько раз повторять, если число повторов не указано.
+const DEFAULT_TIMES: u32 = 6;
+const MAX_TIMES: u32 = 50;
+const MAX_INTERVAL_MINUTES: u32 = 24 * 60;
+
+/// Повтор напоминания, которое пора отправить.
+#[derive(Debug)]
+struct DueRepeat {
+    event_id: i64,
+    tenant: String,
+    owner_id: i64,
+    chat_id: i64,
+    text: String,
+    event_utc: String,
+    count: u32,
+    limit: u32,
+}
+
+pub fn init_tables(conn: &Connection) -> Result<(), rusqlite::Error> {
+    // Политика повтора: интервал и предел задаёт /repeat, счётчик и время следующего повтора ведёт планировщик
+    add_column_if_missing(conn, "events", "repeat_minutes", "INTEGER")?;
+    add_column_if_missing(conn, "events", "repeat_limit", "INTEGER")?;
+    add_column_if_missing(conn, "events", "repeat_count", "INTEGER NOT NULL DEFAULT 0")?;
+    add_column_if_missing(conn, "events", "next_repeat_utc", "TEXT")?;
+    Ok(())
+}
+
+/// Разбирает `#id 10m [6]`: интервал в минутах (`10`, `10m`, `10мин`) или часах (`1h`, `1ч`).
+fn parse_args(args: &str) -> Option<(i64, u32, u32)> {
+    let mut parts = args.split_whitespace();
+    let event_id = parts.next()?.trim_start_matches('#').parse().ok()?;
+    let interval = parts.next()?;
+    let minutes = if let Some(hours) = interval.strip_suffix('h').or_else(|| interval.strip_suffix('ч')) {
+        hours.parse::<u32>().ok()?.checked_mul(60)?
+    } else {
+        interval.trim_end_matches("min").trim_end_matches('m').trim_end_matches("мин").trim_end_matches('м').parse().ok()?
+    };
+    let times = match parts.next() {
+        Some(times) => times.trim_start_matches('x').trim_start_matches('×').parse().ok()?,
+        None => DEFAULT_TIMES,
+    };
+    let valid = (1..=MAX_INTERVAL_MINUTES).contains(&minutes) && (1..=MAX_TIMES).contains(&times);
+    (valid && parts.next().is_none()).then_some((event_id, minutes, times))
+}
+
+fn set_policy(conn: &Connection, event_id: i64, policy: Option<(u32, u32)>) -> Result<(), rusqlite::Error> {
+    conn.execute(
+        "UPDATE events SET repeat_minutes = ?, repeat_limit = ?, repeat_count = 0, next_repeat_utc = NULL WHERE id = ?",
+        params![policy.map(|(minutes, _)| minutes), policy.map(|(_, times)| times), event_id],
+    )?;
+    Ok(())
+}
+
+/// Запускает отсчёт повторов после отправки напоминания, если у события есть политика повтора.
+pub fn arm(conn: &Connection, event_id: i64, now: NaiveDateTime) -> Result<(), rusqlite::Error> {
+    conn.execute(
+        "UPDATE events SET repeat_count = 0,
+            next_repeat_utc = strftime('%Y-%m-%d %H:%M:%S', ?, '+' || repeat_minutes || ' minutes')
+         WHERE id = ? AND repeat_minutes IS NOT NULL",
+        params![now.format(UTC_FORMAT).to_string(), event_id],
+    )?;
+    Ok(())
+}
+
+/// `/repeat #id 10m [6]` — повторять напоминание, пока его не отметят «Готово»;
+/// `/repeat off #id` — повторять не нужно.
+pub async fn handle_repeat_command(bot: &Bot, msg: &Message, db: &Db, args: &str, settings: &Settings) -> ResponseResult<()> {
+    let lang = settings.lang;
+    let Some(user) = msg.from() else {
+        return Ok(());
+    };
+    let (telegram_id, chat_id) = (user.id.0 as i64, msg.chat.id.0);
+
+    let (event_id, policy) = if let Some(id) = args.strip_prefix("off") {
+        match id.trim().trim_start_matches('#').parse::<i64>() {
+            Ok(event_id) => (event_id, None),
+            Err(_) => {
+                bot.send_message(msg.chat.id, t(lang, "repeat_usage")).await?;
+                return Ok(());
+            }
+        }
+    } else {
+        match parse_args(args) {
+            Some((event_id, minutes, times)) => (event_id, Some((minutes, times))),
+            None => {
+                bot.send_message(msg.chat.id, t(lang, "repeat_usage")).await?;
+                return Ok(());
+            }
+        }
+    };
+
+    let updated = db.call(move |conn| {
+        match get_event(conn, event_id)?.filter(|event| event.chat_id == chat_id && event.owner_id == telegram_id) {
+            Some(_) => set_policy(conn, event_id, policy).map(|_| true),
+            None => Ok(false),
+        }
+    }).await.map_err(DatabaseError)?;
+
+    let response = match (updated, policy) {
+        (false, _) => t(lang, "event_not_found"),
+        (true, Some((minutes, times))) => tf(lang, "repeat_saved", &[("id", &event_id), ("minutes", &minutes), ("times", &times)]),
+        (true, None) => tf(lang, "repeat_off", &[("id", &event_id)]),
+    };
+    chunks::send(bot, msg.chat.id, response).await?;
+    Ok(())
+}
+
+/// Забирает повторы, время которых наступило, и сдвигает следующий повтор.
+/// Выполненные события (`done`) и перенесённые (`pending`) не повторяются.
+fn take_due(conn: &Connection, now: NaiveDateTime) -> Result<Vec<DueRepeat>, rusqlite::Error> {
+    let now = now.format(UTC_FORMAT).to_string();
+    let mut stmt = conn.prepare(&format!(
+        "SELECT e.id, e.tenant, u.telegram_id, COALESCE(e.chat_id, u.telegram_id), e.text, e.event_utc, e.repeat_count, e.repeat_limit, e.icon
+         FROM events e
+         JOIN users u ON e.user_id = u.id
+         WHERE e.status = 'sent' AND e.repeat_minutes IS NOT NULL
+           AND e.next_repeat_utc <= ? AND e.repeat_count < e.repeat_limit AND {}",
+        blocklist::NOT_BLOCKED
+    ))?;
+    let due = stmt.query_map(params![now], |row| {
+        Ok(DueRepeat {
+            event_id: row.get(0)?,
+            tenant: row.get(1)?,
+            owner_id: row.get(2)?,
+            chat_id: row.get(3)?,
+            text: categories::label(row.get::<_, Option<String>>(8)?.as_deref(), &crypto::open(row.get(4)?)),
+            event_utc: row.get(5)?,
+            count: row.get::<_, u32>(6)? + 1,
+            limit: row.get(7)?,
+        })
+    })?
+    .collect::<Result<Vec<_>, _>>()?;
+
+    for repeat in &due {
+        conn.execute(
+            "UPDATE events SET repeat_count = repeat_count + 1,
+                next_repeat_utc = strftime('%Y-%m-%d %H:%M:%S', ?, '+' || repeat_minutes || ' minutes')
+             WHERE id = ?",
+            params![now, repeat.event_id],
+        )?;
+    }
+    Ok(due)
+}
+
+/// Для `reventor simulate`: повторы, которые ушли бы сейчас, — чат, бот и текст.
+pub fn dry_run(conn: &Connection, now: NaiveDateTime) -> Result<Vec<(String, i64, String)>, rusqlite::Error> {
+    take_due(conn, now)?
+        .into_iter()
+        .map(|repeat| {
+            let settings = settings::resolve(conn, repeat.owner_id, repeat.chat_id)?;
+            let text = tf(settings.lang, "repeat_reminder", &[("text", &repeat.text), ("count", &repeat.count), ("limit", &repeat.limit)]);
+            Ok((repeat.tenant, repeat.chat_id, text))
+        })
+        .collect()
+}
+
+pub async fn send_due_repeats(db: &Db, clock: &dyn Clock) {
+    let now = clock.now_utc();
+    let due = db.call(move |conn| {
+        take_due(conn, now)?
+            .into_iter()
+            .map(|repeat| {
+                let settings = settings::resolve(conn, repeat.owner_id, repeat.chat_id).unwrap_or_default();
+                let keyboard = checklist::keyboard(conn, repeat.event_id)?;
+                Ok((repeat, settings, keyboard))
+            })
+            .collect::<Result<Vec<_>, rusqlite::Error>>()
+    }).await;
+    let due = match due {
+        Ok(due) => due,
+        Err(e) => {
+            log::error!("Failed to load repeated reminders: {}", e);
+            return;
+        }
+    };
+
+    for (repeat, settings, keyboard) in due {
+        println!("Repeating reminder for event {} ({}/{})", repeat.event_id, repeat.count, repeat.limit);
+        let text = tf(settings.lang, "repeat_reminder", &[("text", &repeat.text), ("count", &repeat.count), ("limit", &repeat.limit)]);
+        let sent = async {
+            let request = chunks::send_last(tenants::bot(&repeat.tenant), ChatId(repeat.chat_id), text).await?;
+            match keyboard {
+                Some(keyboard) => request.reply_markup(keyboard).await,
+                None => request.reply_markup(followups::ack_keyboard(settings.lang, repeat.event_id)).await,
+            }
+        };
+        // Реакции на последний повтор тоже завершают событие
+        if let Ok(message) = sent.await {
+            let (event_id, event_utc) = (repeat.event_id, repeat.event_utc);
+            let _ = db.call(move |conn| notifications::confirm(conn, event_id, &event_utc, message.id.0)).await;
+        }
+    }
+}
@@ -0,0 +1,108 @@
+use std::path::{Path, PathBuf};
+
+use rusqlite::{Connection, DatabaseName, OpenFlags};
+
+use crate::{DB_PATH, SCHEMA_VERSION, init_db, storage};
+
+/// `reventor restore <файл> [--migrate]` — заменяет базу резервной копией.
+/// Бот должен быть остановлен: подмена файла под работающим процессом потеряет его записи.
+pub fn run(args: &[String]) -> Result<(), String> {
+    let migrate = args.iter().any(|arg| arg == "--migrate");
+    let Some(backup) = args.iter().find(|arg| !arg.starts_with("--")) else {
+        return Err("Usage: reventor restore <backup-file> [--migrate]".to_string());
+    };
+    let backup = Path::new(backup);
+    if !backup.is_file() {
+        return Err(format!("Backup file {:?} not found", backup));
+    }
+    ensure_stopped()?;
+
+    // Копия собирается рядом с базой, чтобы финальное переименование было атомарным
+    let staged = PathBuf::from(format!("{}.restore", DB_PATH));
+    let _ = std::fs::remove_file(&staged);
+    let result = stage(backup, &staged, migrate);
+    if let Err(e) = result {
+        let _ = std::fs::remove_file(&staged);
+        return Err(e);
+    }
+
+    if Path::new(DB_PATH).exists() {
+        let previous = format!("{}.before-restore-{}", DB_PATH, chrono::Local::now().format("%Y%m%d-%H%M%S"));
+        std::fs::copy(DB_PATH, &previous).map_err(|e| format!("Failed to keep the current database: {}", e))?;
+        println!("Current database saved to {}", previous);
+    }
+    std::fs::rename(&staged, DB_PATH).map_err(|e| format!("Failed to replace the database: {}", e))?;
+    println!("Database restored from {:?}", backup);
+    Ok(())
+}
+
+/// Пока бот работает, рядом с базой в режиме WAL лежат файлы `-wal` и `-shm`.
+/// Они же остаются после аварийной остановки и содержат ещё не перенесённые в базу записи.
+fn ensure_stopped() -> Result<(), String> {
+    let leftovers = ["-wal", "-shm"]
+        .iter()
+        .map(|suffix| format!("{}{}", DB_PATH, suffix))
+        .filter(|path| Path::new(path).exists())
+        .collect::<Vec<_>>();
+    if leftovers.is_empty() {
+        return Ok(());
+    }
+    Err(format!(
+        "Found {} - stop the bot first. If it crashed, start and stop it once so the journal is written back",
+        leftovers.join(", ")
+    ))
+}
+
+/// Копирует резервную копию через backup API и проверяет её; при `--migrate` сразу обновляет схему.
+fn stage(backup: &Path, staged: &Path, migrate: bool) -> Result<(), String> {
+    let source = Connection::open_with_flags(backup, OpenFlags::SQLITE_OPEN_READ_ONLY)
+        .map_err(|e| format!("Failed to open backup: {}", e))?;
+    source
+        .query_row("PRAGMA schema_version", [], |row| row.get::<_, i64>(0))
+        .map_err(|e| format!("Backup is not a valid database: {}", e))?;
+    source
+        .backup(DatabaseName::Main, staged, None)
+        .map_err(|e| format!("Failed to read backup: {}", e))?;
+    drop(source);
+
+    let conn = Connection::open(staged).map_err(|e| e.to_string())?;
+    let check: String = conn
+        .query_row("PRAGMA integrity_check", [], |row| row.get(0))
+        .map_err(|e| e.to_string())?;
+    if check != "ok" {
+        return Err(format!("Backup failed the integrity check: {}", check));
+    }
+    let tables: i64 = conn
+        .query_row(
+            "SELECT COUNT(*) FROM sqlite_master WHERE type = 'table' AND name IN ('users', 'events')",
+            [],
+            |row| row.get(0),
+        )
+        .map_err(|e| e.to_string())?;
+    if tables != 2 {
+        return Err("Backup does not look like a reventor database".to_string());
+    }
+
+    let version: i32 = conn
+        .pragma_query_value(None, "user_version", |row| row.get(0))
+        .map_err(|e| e.to_string())?;
+    if version > SCHEMA_VERSION {
+        return Err(format!(
+            "Backup has schema version {}, this build supports up to {}",
+            version, SCHEMA_VERSION
+        ));
+    }
+    if version < SCHEMA_VERSION {
+        if migrate {
+            init_db(&conn).map_err(|e| format!("Migration failed: {}", e))?;
+            storage::enable_foreign_keys(&conn).map_err(|e| format!("Migration failed: {}", e))?;
+            println!("Migrated backup from schema version {} to {}", version, SCHEMA_VERSION);
+        } else {
+            println!(
+                "Backup has schema version {}, the bot will migrate it to {} on start (or pass --migrate)",
+                version, SCHEMA_VERSION
+            );
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,87 @@
+use std::env;
+use std::sync::OnceLock;
+use std::time::Duration;
+
+use rusqlite::{Connection, params};
+
+use crate::{Db, in_transaction, supervisor};
+
+/// Как часто запускается очистка.
+const PURGE_INTERVAL: Duration = Duration::from_secs(6 * 3600);
+
+/// Срок хранения по умолчанию из `RETENTION_DAYS` (90, 0 — хранить всегда).
+/// Пользователь переопределяет его через `/settings retention`.
+pub fn default_days() -> Option<u32> {
+    static DAYS: OnceLock<Option<u32>> = OnceLock::new();
+    *DAYS.get_or_init(|| {
+        let days: u32 = env::var("RETENTION_DAYS").ok().and_then(|v| v.parse().ok()).unwrap_or(90);
+        (days > 0).then_some(days)
+    })
+}
+
+/// Значение настройки: число дней или `off`.
+pub fn parse_days(value: &str) -> Option<Option<u32>> {
+    match value.to_lowercase().as_str() {
+        "off" | "0" => Some(None),
+        value => value.parse().ok().filter(|days| *days > 0).map(Some),
+    }
+}
+
+/// Удаляет отправленные и выполненные события старше срока хранения их владельца.
+/// Возвращает число удалённых событий по telegram id пользователей.
+fn purge(conn: &Connection) -> Result<Vec<(i64, i64)>, rusqlite::Error> {
+    let default = default_days().map_or_else(|| "off".to_string(), |days| days.to_string());
+    // Срок пользователя хранится строкой: число дней или off
+    let expired = "FROM events e
+         JOIN users u ON e.user_id = u.id
+         WHERE e.status IN ('sent', 'done')
+           AND e.event_utc IS NOT NULL
+           AND COALESCE(u.retention_days, ?1) != 'off'
+           AND e.event_utc < datetime('now', '-' || COALESCE(u.retention_days, ?1) || ' days')";
+
+    in_transaction(conn, |tx| {
+        let counts = {
+            let mut stmt = tx.prepare(&format!("SELECT u.telegram_id, COUNT(*) {} GROUP BY u.telegram_id", expired))?;
+            let counts = stmt
+                .query_map(params![default], |row| Ok((row.get(0)?, row.get(1)?)))?
+                .collect::<Result<Vec<_>, _>>()?;
+            counts
+        };
+        if !counts.is_empty() {
+            // Удаление остаётся в истории события с прежними временем и текстом
+            tx.execute(
+                &format!(
+                    "INSERT INTO audit_log (event_id, chat_id, action, old_time, old_text)
+                     SELECT e.id, COALESCE(e.chat_id, u.telegram_id), 'delete', e.event_time, e.text {}",
+                    expired
+                ),
+                params![default],
+            )?;
+            tx.execute(&format!("DELETE FROM events WHERE id IN (SELECT e.id {})", expired), params![default])?;
+        }
+        Ok(counts)
+    })
+}
+
+/// Фоновая очистка старых событий; первый проход сразу после запуска.
+pub fn spawn_scheduler(db: Db) {
+    supervisor::spawn("retention", move || {
+        let db = db.clone();
+        async move {
+            let mut timer = tokio::time::interval(PURGE_INTERVAL);
+            loop {
+                timer.tick().await;
+                match db.call(purge).await {
+                    Ok(counts) => {
+                        for (telegram_id, count) in &counts {
+                            log::info!("Purged {} old events of user {}", count, telegram_id);
+                        }
+                        let total: i64 = counts.iter().map(|(_, count)| count).sum();
+                        log::info!("Retention purge removed {} events", total);
+                    }
+                    Err(e) => log::error!("Retention purge failed: {}", e),
+                }
+            }
+        }
+    });
+}
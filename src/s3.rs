@@ -0,0 +1,110 @@
+use std::env;
+use std::sync::OnceLock;
+
+use hmac::{Hmac, Mac};
+use reqwest::Url;
+use sha2::{Digest, Sha256};
+
+/// Хранилище для копий вне сервера: AWS S3, MinIO или любое S3-совместимое.
+#[derive(Debug)]
+pub struct S3Config {
+    endpoint: Url,
+    bucket: String,
+    region: String,
+    access_key: String,
+    secret_key: String,
+    prefix: String,
+}
+
+/// Настройки из `S3_ENDPOINT`, `S3_BUCKET`, `S3_ACCESS_KEY`, `S3_SECRET_KEY`,
+/// а также необязательных `S3_REGION` (us-east-1) и `S3_PREFIX`. Без эндпоинта и бакета выгрузка выключена.
+pub fn config() -> Option<&'static S3Config> {
+    static CONFIG: OnceLock<Option<S3Config>> = OnceLock::new();
+    CONFIG.get_or_init(|| {
+        let endpoint = env::var("S3_ENDPOINT").ok()?;
+        let Ok(endpoint) = Url::parse(&endpoint) else {
+            log::error!("Invalid S3_ENDPOINT: {}", endpoint);
+            return None;
+        };
+        Some(S3Config {
+            endpoint,
+            bucket: env::var("S3_BUCKET").ok()?,
+            region: env::var("S3_REGION").unwrap_or_else(|_| "us-east-1".to_string()),
+            access_key: env::var("S3_ACCESS_KEY").unwrap_or_default(),
+            secret_key: env::var("S3_SECRET_KEY").unwrap_or_default(),
+            prefix: env::var("S3_PREFIX").unwrap_or_default(),
+        })
+    }).as_ref()
+}
+
+fn sha256_hex(data: &[u8]) -> String {
+    hex::encode(Sha256::digest(data))
+}
+
+fn hmac_sha256(key: &[u8], data: &str) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(data.as_bytes());
+    mac.finalize().into_bytes().to_vec()
+}
+
+/// Кодирует путь объекта по правилам подписи S3: всё, кроме незарезервированных символов и `/`.
+fn encode_path(path: &str) -> String {
+    path.bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' | b'/' => (b as char).to_string(),
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+/// Загружает объект запросом PUT с подписью AWS Signature V4 (адресация path-style, как у MinIO).
+pub async fn upload(config: &S3Config, name: &str, body: Vec<u8>) -> Result<(), String> {
+    let path = encode_path(&format!("/{}/{}{}", config.bucket, config.prefix, name));
+    let url = config.endpoint.join(&path).map_err(|e| e.to_string())?;
+    let host = match url.port() {
+        Some(port) => format!("{}:{}", url.host_str().unwrap_or_default(), port),
+        None => url.host_str().unwrap_or_default().to_string(),
+    };
+
+    let now = chrono::Utc::now();
+    let amz_date = now.format("%Y%m%dT%H%M%SZ").to_string();
+    let date = now.format("%Y%m%d").to_string();
+    let payload_hash = sha256_hex(&body);
+
+    let signed_headers = "host;x-amz-content-sha256;x-amz-date";
+    let canonical_request = format!(
+        "PUT\n{}\n\nhost:{}\nx-amz-content-sha256:{}\nx-amz-date:{}\n\n{}\n{}",
+        url.path(), host, payload_hash, amz_date, signed_headers, payload_hash
+    );
+    let scope = format!("{}/{}/s3/aws4_request", date, config.region);
+    let string_to_sign = format!(
+        "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+        amz_date, scope, sha256_hex(canonical_request.as_bytes())
+    );
+
+    let key = [date.as_str(), config.region.as_str(), "s3", "aws4_request"]
+        .iter()
+        .fold(format!("AWS4{}", config.secret_key).into_bytes(), |key, part| hmac_sha256(&key, part));
+    let signature = hex::encode(hmac_sha256(&key, &string_to_sign));
+    let authorization = format!(
+        "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+        config.access_key, scope, signed_headers, signature
+    );
+
+    let response = reqwest::Client::new()
+        .put(url)
+        .header("x-amz-date", amz_date)
+        .header("x-amz-content-sha256", payload_hash)
+        .header("authorization", authorization)
+        .body(body)
+        .send()
+        .await
+        .map_err(|e| e.to_string())?;
+
+    if !response.status().is_success() {
+        let status = response.status();
+        let text = response.text().await.unwrap_or_default();
+        return Err(format!("{}: {}", status, text));
+    }
+    Ok(())
+}
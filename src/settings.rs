@@ -0,0 +1,635 @@
+use std::collections::HashMap;
+use std::sync::{Mutex, MutexGuard, OnceLock};
+
+use teloxide::prelude::*;
+use chrono::{NaiveDate, NaiveDateTime, NaiveTime};
+use chrono_tz::Tz;
+use rusqlite::{Connection, params, OptionalExtension};
+use reventor::parse::{DateOrder, Locale};
+
+use crate::chunks;
+use crate::humanize;
+use crate::i18n::{t, tf, Lang};
+use crate::holidays::{self, Country};
+use crate::{config, retention, tenants, timezone};
+use crate::{DatabaseError, Db, add_column_if_missing, ensure_user_exists, parse_time_input};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ClockFormat {
+    H24,
+    H12,
+}
+
+impl ClockFormat {
+    pub fn code(self) -> &'static str {
+        match self {
+            ClockFormat::H24 => "24h",
+            ClockFormat::H12 => "12h",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.to_lowercase().as_str() {
+            "24h" | "24" => Some(ClockFormat::H24),
+            "12h" | "12" => Some(ClockFormat::H12),
+            _ => None,
+        }
+    }
+
+    pub fn describe(self, lang: Lang) -> String {
+        match self {
+            ClockFormat::H24 => t(lang, "clock_24h"),
+            ClockFormat::H12 => t(lang, "clock_12h"),
+        }
+    }
+}
+
+/// Порядок дня и месяца в датах: и при вводе (`03.04`), и при выводе.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DateFormat {
+    Dmy,
+    Mdy,
+    Iso,
+}
+
+impl DateFormat {
+    pub fn code(self) -> &'static str {
+        match self {
+            DateFormat::Dmy => "dmy",
+            DateFormat::Mdy => "mdy",
+            DateFormat::Iso => "iso",
+        }
+    }
+
+    /// Порядок дня и месяца для разбора коротких дат; ISO вводится как дд.мм.
+    pub fn order(self) -> DateOrder {
+        match self {
+            DateFormat::Mdy => DateOrder::MonthDay,
+            DateFormat::Dmy | DateFormat::Iso => DateOrder::DayMonth,
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.to_lowercase().as_str() {
+            "dmy" | "dd.mm" | "dd.mm.yyyy" => Some(DateFormat::Dmy),
+            "mdy" | "mm/dd" | "mm/dd/yyyy" => Some(DateFormat::Mdy),
+            "iso" | "yyyy-mm-dd" => Some(DateFormat::Iso),
+            _ => None,
+        }
+    }
+
+    /// Пример даты в этом формате, понятный на любом языке.
+    pub fn describe(self) -> &'static str {
+        match self {
+            DateFormat::Dmy => "31.12.2025",
+            DateFormat::Mdy => "12/31/2025",
+            DateFormat::Iso => "2025-12-31",
+        }
+    }
+
+    fn full(self) -> &'static str {
+        match self {
+            DateFormat::Dmy => "%d.%m.%Y",
+            DateFormat::Mdy => "%m/%d/%Y",
+            DateFormat::Iso => "%Y-%m-%d",
+        }
+    }
+
+    fn short(self) -> &'static str {
+        match self {
+            DateFormat::Dmy => "%d.%m",
+            DateFormat::Mdy => "%m/%d",
+            DateFormat::Iso => "%m-%d",
+        }
+    }
+}
+
+/// Порядок событий в `/events`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SortOrder {
+    Time,
+    Created,
+    Priority,
+}
+
+impl SortOrder {
+    pub fn code(self) -> &'static str {
+        match self {
+            SortOrder::Time => "time",
+            SortOrder::Created => "created",
+            SortOrder::Priority => "priority",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.to_lowercase().as_str() {
+            "time" | "время" => Some(SortOrder::Time),
+            "created" | "создание" => Some(SortOrder::Created),
+            "priority" | "приоритет" => Some(SortOrder::Priority),
+            _ => None,
+        }
+    }
+
+    pub fn describe(self, lang: Lang) -> String {
+        t(lang, match self {
+            SortOrder::Time => "sort_time",
+            SortOrder::Created => "sort_created",
+            SortOrder::Priority => "sort_priority",
+        })
+    }
+}
+
+/// Интервал, в который напоминания не отправляются. Может переходить через полночь.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct QuietHours {
+    start: NaiveTime,
+    end: NaiveTime,
+}
+
+impl QuietHours {
+    pub fn parse(value: &str) -> Option<Self> {
+        let (start, end) = value.split_once('-')?;
+        let start = parse_time_input(start)?;
+        let end = parse_time_input(end)?;
+        if start == end {
+            return None;
+        }
+        Some(QuietHours { start, end })
+    }
+
+    pub fn contains(&self, time: NaiveTime) -> bool {
+        if self.start < self.end {
+            self.start <= time && time < self.end
+        } else {
+            time >= self.start || time < self.end
+        }
+    }
+
+    fn code(&self) -> String {
+        format!("{}-{}", self.start.format("%H:%M"), self.end.format("%H:%M"))
+    }
+}
+
+/// Хранимое значение тихих часов: `"23:00-08:00"` или `"off"`.
+fn parse_quiet_value(value: &str) -> Option<Option<QuietHours>> {
+    if value == "off" {
+        Some(None)
+    } else {
+        QuietHours::parse(value).map(Some)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Settings {
+    pub lang: Lang,
+    pub clock: ClockFormat,
+    pub quiet_hours: Option<QuietHours>,
+    /// Время ежедневной сводки; только личная настройка, группы её не переопределяют.
+    pub digest_time: Option<NaiveTime>,
+    /// Порядок по умолчанию для `/events`; тоже только личная настройка.
+    pub sort: SortOrder,
+    /// Часовой пояс пользователя; `None` — время сервера.
+    pub timezone: Option<Tz>,
+    pub date_format: DateFormat,
+    /// Через сколько дней удалять отправленные события; `None` — хранить всегда.
+    pub retention_days: Option<u32>,
+    /// Чьи праздники упоминать в сводке.
+    pub country: Option<Country>,
+    /// Простой вывод без эмодзи для экранных дикторов, см. `accessibility`.
+    pub plain: bool,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            lang: Lang::Ru,
+            clock: ClockFormat::H24,
+            quiet_hours: config::get().quiet_hours,
+            digest_time: None,
+            sort: SortOrder::Time,
+            timezone: None,
+            date_format: DateFormat::Dmy,
+            retention_days: retention::default_days(),
+            country: None,
+            plain: false,
+        }
+    }
+}
+
+impl Settings {
+    /// Привычки пользователя для `reventor::parse`.
+    pub fn locale(&self) -> Locale {
+        Locale { date_order: self.date_format.order() }
+    }
+
+    pub fn format_time(&self, time: NaiveTime) -> String {
+        match self.clock {
+            ClockFormat::H24 => time.format("%H:%M").to_string(),
+            ClockFormat::H12 => time.format("%-I:%M %p").to_string(),
+        }
+    }
+
+    pub fn format_date(&self, date: NaiveDate) -> String {
+        date.format(self.date_format.full()).to_string()
+    }
+
+    /// Дата без года, например для заголовков дней.
+    pub fn format_short_date(&self, date: NaiveDate) -> String {
+        date.format(self.date_format.short()).to_string()
+    }
+
+    pub fn format_datetime(&self, datetime: NaiveDateTime) -> String {
+        format!("{} {}", self.format_date(datetime.date()), self.format_time(datetime.time()))
+    }
+
+    pub fn is_quiet(&self, time: NaiveTime) -> bool {
+        self.quiet_hours.is_some_and(|q| q.contains(time))
+    }
+
+    fn describe_quiet(&self) -> String {
+        match &self.quiet_hours {
+            Some(quiet) => quiet.code(),
+            None => t(self.lang, "quiet_off"),
+        }
+    }
+
+    fn describe_timezone(&self) -> String {
+        match self.timezone {
+            Some(tz) => tz.name().to_string(),
+            None => t(self.lang, "timezone_server"),
+        }
+    }
+
+    fn describe_retention(&self) -> String {
+        match self.retention_days {
+            Some(days) => tf(self.lang, "retention_days", &[("days", &humanize::days(self.lang, days.into()))]),
+            None => t(self.lang, "retention_forever"),
+        }
+    }
+
+    fn describe_digest(&self) -> String {
+        match self.digest_time {
+            Some(time) => self.format_time(time),
+            None => t(self.lang, "quiet_off"),
+        }
+    }
+}
+
+/// Переопределения на уровне чата; `None` значит «как у пользователя».
+#[derive(Debug, Default, Clone)]
+pub struct ChatOverrides {
+    pub lang: Option<Lang>,
+    pub clock: Option<ClockFormat>,
+    pub quiet_hours: Option<Option<QuietHours>>,
+}
+
+impl ChatOverrides {
+    fn apply(&self, settings: &mut Settings) {
+        if let Some(lang) = self.lang {
+            settings.lang = lang;
+        }
+        if let Some(clock) = self.clock {
+            settings.clock = clock;
+        }
+        if let Some(quiet_hours) = self.quiet_hours {
+            settings.quiet_hours = quiet_hours;
+        }
+    }
+
+    pub fn describe(&self, lang: Lang) -> (String, String, String) {
+        let default = || t(lang, "group_default");
+        (
+            self.lang.map_or_else(default, |l| t(l, "lang_name")),
+            self.clock.map_or_else(default, |c| c.describe(lang)),
+            match self.quiet_hours {
+                None => default(),
+                Some(None) => t(lang, "quiet_off"),
+                Some(Some(quiet)) => quiet.code(),
+            },
+        )
+    }
+}
+
+pub fn init_tables(conn: &Connection) -> Result<(), rusqlite::Error> {
+    for table in ["users", "chat_settings"] {
+        add_column_if_missing(conn, table, "language", "TEXT")?;
+        add_column_if_missing(conn, table, "time_format", "TEXT")?;
+        add_column_if_missing(conn, table, "quiet_hours", "TEXT")?;
+    }
+    add_column_if_missing(conn, "users", "digest_time", "TEXT")?;
+    add_column_if_missing(conn, "users", "event_sort", "TEXT")?;
+    add_column_if_missing(conn, "users", "date_format", "TEXT")?;
+    add_column_if_missing(conn, "users", "retention_days", "TEXT")?;
+    add_column_if_missing(conn, "users", "country", "TEXT")?;
+    add_column_if_missing(conn, "users", "plain_output", "TEXT")?;
+    Ok(())
+}
+
+/// Сколько записей держит кэш; при переполнении он очищается целиком.
+const CACHE_LIMIT: usize = 10_000;
+
+/// Разобранные настройки: их читает каждое сообщение и каждое напоминание, а меняют редко.
+/// Ключ включает соединение, потому что в тестах баз несколько.
+#[derive(Default)]
+struct Cache {
+    users: HashMap<(usize, i64), Settings>,
+    chats: HashMap<(usize, i64), ChatOverrides>,
+}
+
+fn cache() -> MutexGuard<'static, Cache> {
+    static CACHE: OnceLock<Mutex<Cache>> = OnceLock::new();
+    CACHE.get_or_init(Default::default).lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+fn connection_key(conn: &Connection) -> usize {
+    conn as *const Connection as usize
+}
+
+/// Сбрасывает настройки пользователя после записи в его колонки.
+pub fn invalidate_user(conn: &Connection, telegram_id: i64) {
+    cache().users.remove(&(connection_key(conn), telegram_id));
+}
+
+/// Сбрасывает переопределения чата после записи в `chat_settings`.
+pub fn invalidate_chat(conn: &Connection, chat_id: i64) {
+    cache().chats.remove(&(connection_key(conn), chat_id));
+}
+
+/// Забывает всё о соединении: новое может оказаться по адресу закрытого.
+pub fn forget(conn: &Connection) {
+    let key = connection_key(conn);
+    let mut cache = cache();
+    cache.users.retain(|(conn, _), _| *conn != key);
+    cache.chats.retain(|(conn, _), _| *conn != key);
+}
+
+/// Сбрасывает кэш целиком: значения по умолчанию берутся из перечитанных настроек.
+pub fn clear_cache() {
+    *cache() = Cache::default();
+}
+
+/// Сырые значения колонок настроек пользователя: язык, формат времени, тихие часы, сводка,
+/// сортировка, часовой пояс, формат даты, срок хранения, страна.
+type UserSettingsRow = (
+    Option<String>,
+    Option<String>,
+    Option<String>,
+    Option<String>,
+    Option<String>,
+    Option<String>,
+    Option<String>,
+    Option<String>,
+    Option<String>,
+    Option<String>,
+);
+
+fn user_settings(conn: &Connection, telegram_id: i64) -> Result<Settings, rusqlite::Error> {
+    let key = (connection_key(conn), telegram_id);
+    if let Some(settings) = cache().users.get(&key) {
+        return Ok(*settings);
+    }
+    let settings = load_user_settings(conn, telegram_id)?;
+    let mut cache = cache();
+    if cache.users.len() >= CACHE_LIMIT {
+        cache.users.clear();
+    }
+    cache.users.insert(key, settings);
+    Ok(settings)
+}
+
+fn load_user_settings(conn: &Connection, telegram_id: i64) -> Result<Settings, rusqlite::Error> {
+    let row: Option<UserSettingsRow> = conn.query_row(
+        "SELECT language, time_format, quiet_hours, digest_time, event_sort, timezone, date_format, retention_days, country, plain_output
+         FROM users WHERE telegram_id = ?",
+        params![telegram_id],
+        |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?, row.get(4)?, row.get(5)?, row.get(6)?, row.get(7)?, row.get(8)?, row.get(9)?)),
+    ).optional()?;
+
+    let mut settings = Settings::default();
+    if let Some((language, time_format, quiet_hours, digest_time, sort, timezone, date_format, retention_days, country, plain)) = row {
+        if let Some(lang) = language.as_deref().and_then(Lang::parse) {
+            settings.lang = lang;
+        }
+        if let Some(clock) = time_format.as_deref().and_then(ClockFormat::parse) {
+            settings.clock = clock;
+        }
+        if let Some(quiet_hours) = quiet_hours.as_deref().and_then(parse_quiet_value) {
+            settings.quiet_hours = quiet_hours;
+        }
+        settings.digest_time = digest_time.and_then(|time| NaiveTime::parse_from_str(&time, "%H:%M").ok());
+        if let Some(sort) = sort.as_deref().and_then(SortOrder::parse) {
+            settings.sort = sort;
+        }
+        settings.timezone = timezone.as_deref().and_then(timezone::parse_zone);
+        if let Some(date_format) = date_format.as_deref().and_then(DateFormat::parse) {
+            settings.date_format = date_format;
+        }
+        if let Some(retention_days) = retention_days.as_deref().and_then(retention::parse_days) {
+            settings.retention_days = retention_days;
+        }
+        settings.country = country.as_deref().and_then(Country::parse);
+        settings.plain = plain.as_deref() == Some("on");
+    }
+    Ok(settings)
+}
+
+pub fn chat_overrides(conn: &Connection, chat_id: i64) -> Result<ChatOverrides, rusqlite::Error> {
+    let key = (connection_key(conn), chat_id);
+    if let Some(overrides) = cache().chats.get(&key) {
+        return Ok(overrides.clone());
+    }
+    let overrides = load_chat_overrides(conn, chat_id)?;
+    let mut cache = cache();
+    if cache.chats.len() >= CACHE_LIMIT {
+        cache.chats.clear();
+    }
+    cache.chats.insert(key, overrides.clone());
+    Ok(overrides)
+}
+
+fn load_chat_overrides(conn: &Connection, chat_id: i64) -> Result<ChatOverrides, rusqlite::Error> {
+    let row: Option<(Option<String>, Option<String>, Option<String>)> = conn.query_row(
+        "SELECT language, time_format, quiet_hours FROM chat_settings WHERE chat_id = ?",
+        params![chat_id],
+        |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+    ).optional()?;
+
+    Ok(match row {
+        Some((language, time_format, quiet_hours)) => ChatOverrides {
+            lang: language.as_deref().and_then(Lang::parse),
+            clock: time_format.as_deref().and_then(ClockFormat::parse),
+            quiet_hours: quiet_hours.as_deref().and_then(parse_quiet_value),
+        },
+        None => ChatOverrides::default(),
+    })
+}
+
+/// Настройки для сообщений пользователя в конкретный чат: личные настройки,
+/// поверх которых применяются настройки группы.
+pub fn resolve(conn: &Connection, telegram_id: i64, chat_id: i64) -> Result<Settings, rusqlite::Error> {
+    let mut settings = user_settings(conn, telegram_id)?;
+    if chat_id != telegram_id {
+        chat_overrides(conn, chat_id)?.apply(&mut settings);
+    }
+    Ok(settings)
+}
+
+pub async fn for_message(db: &Db, msg: &Message) -> Result<Settings, rusqlite::Error> {
+    let (telegram_id, chat_id) = (msg.from().map(|user| user.id.0 as i64), msg.chat.id.0);
+    db.call(move |conn| match telegram_id {
+        Some(telegram_id) => resolve(conn, telegram_id, chat_id),
+        None => {
+            let mut settings = Settings::default();
+            chat_overrides(conn, chat_id)?.apply(&mut settings);
+            Ok(settings)
+        }
+    }).await
+}
+
+pub fn set_user_setting(conn: &Connection, telegram_id: i64, column: &str, value: &str) -> Result<(), rusqlite::Error> {
+    conn.execute(
+        &format!("UPDATE users SET {} = ? WHERE telegram_id = ?", column),
+        params![value, telegram_id],
+    )?;
+    invalidate_user(conn, telegram_id);
+    Ok(())
+}
+
+pub fn set_chat_setting(conn: &Connection, chat_id: i64, column: &str, value: Option<&str>) -> Result<(), rusqlite::Error> {
+    conn.execute("INSERT OR IGNORE INTO chat_settings (chat_id) VALUES (?)", params![chat_id])?;
+    conn.execute(
+        &format!("UPDATE chat_settings SET {} = ? WHERE chat_id = ?", column),
+        params![value, chat_id],
+    )?;
+    invalidate_chat(conn, chat_id);
+    Ok(())
+}
+
+pub fn setting_column(name: &str) -> Option<&'static str> {
+    match name {
+        "lang" => Some("language"),
+        "time" => Some("time_format"),
+        "quiet" => Some("quiet_hours"),
+        "digest" => Some("digest_time"),
+        "sort" => Some("event_sort"),
+        "date" => Some("date_format"),
+        "retention" => Some("retention_days"),
+        "country" => Some("country"),
+        "plain" => Some("plain_output"),
+        _ => None,
+    }
+}
+
+/// Проверяет значение настройки и возвращает колонку и нормализованное значение для хранения.
+pub fn normalize_setting(name: &str, value: &str) -> Option<(&'static str, String)> {
+    let value = match name {
+        "lang" => Lang::parse(value).map(|l| l.code().to_string()),
+        "time" => ClockFormat::parse(value).map(|c| c.code().to_string()),
+        "quiet" => parse_quiet_value(value).map(|q| q.map_or_else(|| "off".to_string(), |q| q.code())),
+        "digest" if value == "off" => Some(value.to_string()),
+        "digest" => parse_time_input(value).map(|time| time.format("%H:%M").to_string()),
+        "sort" => SortOrder::parse(value).map(|s| s.code().to_string()),
+        "date" => DateFormat::parse(value).map(|d| d.code().to_string()),
+        "retention" => retention::parse_days(value).map(|days| days.map_or_else(|| "off".to_string(), |d| d.to_string())),
+        "country" if value == "off" => Some(value.to_string()),
+        "country" => Country::parse(value).map(Country::code),
+        "plain" if matches!(value, "on" | "off") => Some(value.to_string()),
+        _ => None,
+    }?;
+    Some((setting_column(name)?, value))
+}
+
+pub async fn handle_settings(bot: &Bot, msg: &Message, db: &Db, args: &str) -> ResponseResult<()> {
+    let Some(user) = msg.from() else {
+        return Ok(());
+    };
+    let telegram_id = user.id.0 as i64;
+    let mut parts = args.split_whitespace();
+
+    let (username, tenant) = (user.username.clone(), tenants::name_of(bot));
+    let (name, value) = (parts.next().map(str::to_string), parts.next().map(str::to_string));
+
+    let response = db.call(move |conn| {
+        ensure_user_exists(conn, tenant, telegram_id, username)?;
+        let response = match (name.as_deref(), value.as_deref()) {
+            (None, _) => {
+                let settings = user_settings(conn, telegram_id)?;
+                tf(settings.lang, "settings_current", &[
+                    ("lang", &t(settings.lang, "lang_name")),
+                    ("clock", &settings.clock.describe(settings.lang)),
+                    ("quiet", &settings.describe_quiet()),
+                    ("digest", &settings.describe_digest()),
+                    ("sort", &settings.sort.describe(settings.lang)),
+                    ("zone", &settings.describe_timezone()),
+                    ("date", &settings.date_format.describe()),
+                    ("retention", &settings.describe_retention()),
+                    ("country", &holidays::describe_country(settings.country, settings.lang)),
+                    ("plain", &t(settings.lang, if settings.plain { "plain_on" } else { "plain_off" })),
+                ])
+            }
+            (Some(name @ ("lang" | "time" | "quiet" | "digest" | "sort" | "date" | "retention" | "country" | "plain")), Some(value)) => match normalize_setting(name, value) {
+                Some((column, value)) => {
+                    set_user_setting(conn, telegram_id, column, &value)?;
+                    let settings = user_settings(conn, telegram_id)?;
+                    t(settings.lang, "settings_saved")
+                }
+                None => {
+                    let settings = user_settings(conn, telegram_id)?;
+                    tf(settings.lang, "settings_invalid_value", &[("value", &value)])
+                }
+            },
+            _ => {
+                let settings = user_settings(conn, telegram_id)?;
+                t(settings.lang, "settings_unknown")
+            }
+        };
+        Ok(response)
+    }).await.map_err(DatabaseError)?;
+
+    chunks::send(bot, msg.chat.id, response).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{NaiveDate, Utc};
+    use proptest::prelude::*;
+
+    use super::*;
+    use crate::storage::tests::memory_db;
+
+    proptest! {
+        /// То, что бот показал в подтверждении, можно отправить обратно и получить тот же момент.
+        #[test]
+        fn displayed_datetime_round_trips(
+            ordinal in 1u32..=365,
+            minutes in 0u32..24 * 60,
+            date_format in prop_oneof![Just(DateFormat::Dmy), Just(DateFormat::Mdy), Just(DateFormat::Iso)],
+            clock in prop_oneof![Just(ClockFormat::H24), Just(ClockFormat::H12)],
+        ) {
+            let datetime = NaiveDate::from_yo_opt(2030, ordinal).unwrap().and_hms_opt(minutes / 60, minutes % 60, 0).unwrap();
+            let settings = Settings { date_format, clock, ..Settings::default() };
+            let text = format!("Событие @{}", settings.format_datetime(datetime));
+            let parsed = reventor::parse::parse(&text, settings.locale(), Utc::now(), settings.timezone).unwrap();
+            prop_assert_eq!(parsed.datetime, datetime);
+            prop_assert_eq!(crate::parse_event_time(&reventor::parse::store(parsed.datetime)), Some(datetime));
+        }
+    }
+
+    #[tokio::test]
+    async fn cached_settings_follow_updates() {
+        let db = memory_db();
+        let langs = db.call(|conn| {
+            ensure_user_exists(conn, tenants::DEFAULT, 42, None)?;
+            let before = resolve(conn, 42, 42)?.lang;
+            // Запись в обход настроек кэш не видит, запись через них — сбрасывает
+            conn.execute("UPDATE users SET language = 'en' WHERE telegram_id = 42", [])?;
+            let stale = resolve(conn, 42, 42)?.lang;
+            set_user_setting(conn, 42, "language", "en")?;
+            let fresh = resolve(conn, 42, 42)?.lang;
+            set_chat_setting(conn, -100, "language", Some("ru"))?;
+            Ok((before, stale, fresh, resolve(conn, 42, -100)?.lang))
+        }).await.unwrap();
+        assert_eq!(langs, (Lang::Ru, Lang::Ru, Lang::En, Lang::Ru));
+    }
+}
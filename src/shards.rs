@@ -0,0 +1,208 @@
+//! Деление планировщика напоминаний на шарды по Telegram id владельца: каждый шард
+//! просматривает только своих пользователей в отдельной задаче. Несколько процессов с общей
+//! базой делят шарды через аренду: шард обслуживает тот, кто первым её взял и продлевает.
+//! Так же делятся фоновые задачи, которым хватает одного процесса, см. `holds_task`.
+
+use std::env;
+use std::sync::OnceLock;
+use std::time::Duration;
+
+use chrono::NaiveDateTime;
+use rusqlite::{params, Connection};
+
+use crate::clock::{self, Clock};
+use crate::{Db, UTC_FORMAT};
+
+/// Аренда истекает, если держатель столько не продлевал её — например, процесс упал.
+const LEASE_MIN: Duration = Duration::from_secs(30);
+
+/// Часть пользователей: те, у кого `telegram_id mod count == index`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Shard {
+    pub index: u32,
+    pub count: u32,
+}
+
+impl Shard {
+    /// Все пользователи сразу — для тестов и единственного шарда.
+    pub const ALL: Shard = Shard { index: 0, count: 1 };
+
+    /// Условие на пользователей шарда в запросе, где таблица `users` названа `u`.
+    pub fn condition(&self) -> String {
+        if self.count <= 1 {
+            return "1 = 1".to_string();
+        }
+        format!("((u.telegram_id % {count}) + {count}) % {count} = {index}", count = self.count, index = self.index)
+    }
+
+    /// Имя задачи планировщика для супервизора и проверки здоровья.
+    pub fn task_name(&self) -> &'static str {
+        if self.count <= 1 {
+            return "scheduler";
+        }
+        // Шарды запускаются один раз при старте, имена живут до выхода
+        Box::leak(format!("scheduler-{}", self.index).into_boxed_str())
+    }
+}
+
+/// Число шардов из `SCHEDULER_SHARDS`, по умолчанию один. Меняется только перезапуском.
+pub fn count() -> u32 {
+    env::var("SCHEDULER_SHARDS").ok().and_then(|count| count.trim().parse().ok()).filter(|count| *count > 0).unwrap_or(1)
+}
+
+pub fn all() -> Vec<Shard> {
+    match count() {
+        1 => vec![Shard::ALL],
+        count => (0..count).map(|index| Shard { index, count }).collect(),
+    }
+}
+
+/// Имя этого процесса в таблице аренды.
+fn holder() -> &'static str {
+    static HOLDER: OnceLock<String> = OnceLock::new();
+    HOLDER.get_or_init(|| {
+        let host = env::var("HOSTNAME").unwrap_or_else(|_| "local".to_string());
+        format!("{}:{}", host, std::process::id())
+    })
+}
+
+pub fn init_tables(conn: &Connection) -> Result<(), rusqlite::Error> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS scheduler_leases (
+            shard INTEGER NOT NULL,
+            shards INTEGER NOT NULL,
+            holder TEXT NOT NULL,
+            expires_utc TEXT NOT NULL,
+            PRIMARY KEY (shard, shards)
+        )",
+        [],
+    )?;
+    // Фоновые задачи, которым достаточно одного процесса на базу: сводки, зеркала, подписки
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS task_leases (
+            task TEXT PRIMARY KEY,
+            holder TEXT NOT NULL,
+            expires_utc TEXT NOT NULL
+        )",
+        [],
+    )?;
+    Ok(())
+}
+
+/// Берёт или продлевает аренду фоновой задачи; `false`, если её выполняет другой живой процесс.
+pub fn acquire_task(conn: &Connection, task: &str, now: NaiveDateTime, ttl: Duration) -> Result<bool, rusqlite::Error> {
+    acquire_task_as(conn, holder(), task, now, ttl)
+}
+
+fn acquire_task_as(conn: &Connection, holder: &str, task: &str, now: NaiveDateTime, ttl: Duration) -> Result<bool, rusqlite::Error> {
+    let ttl = chrono::Duration::from_std(ttl.max(LEASE_MIN)).unwrap_or_default();
+    let changed = conn.execute(
+        "INSERT INTO task_leases (task, holder, expires_utc) VALUES (?1, ?2, ?3)
+         ON CONFLICT(task) DO UPDATE SET holder = excluded.holder, expires_utc = excluded.expires_utc
+         WHERE task_leases.holder = excluded.holder OR task_leases.expires_utc <= ?4",
+        params![task, holder, (now + ttl).format(UTC_FORMAT).to_string(), now.format(UTC_FORMAT).to_string()],
+    )?;
+    Ok(changed > 0)
+}
+
+/// Выполнять ли очередной проход задачи в этом процессе. `interval` — пауза между проходами:
+/// аренда живёт три паузы, как у шардов. Ошибка базы пропускает проход.
+pub async fn holds_task(db: &Db, task: &'static str, interval: Duration) -> bool {
+    let now = clock::SYSTEM.now_utc();
+    match db.call(move |conn| acquire_task(conn, task, now, interval * 3)).await {
+        Ok(held) => held,
+        Err(e) => {
+            log::error!("Failed to lease task {}: {}", task, e);
+            false
+        }
+    }
+}
+
+/// Берёт или продлевает аренду шарда; `false`, если шард держит другой живой процесс.
+pub fn acquire(conn: &Connection, shard: Shard, now: NaiveDateTime, ttl: Duration) -> Result<bool, rusqlite::Error> {
+    acquire_as(conn, holder(), shard, now, ttl)
+}
+
+/// Шарды текущего деления, аренду которых последним брал этот процесс. Истёкшая аренда,
+/// которую никто не перехватил, тоже считается: её шард по-прежнему никто не обслуживает.
+pub fn held(conn: &Connection) -> Result<Vec<Shard>, rusqlite::Error> {
+    let mut stmt = conn.prepare("SELECT shard, shards FROM scheduler_leases WHERE holder = ? AND shards = ? ORDER BY shard")?;
+    let shards = stmt.query_map(params![holder(), count()], |row| Ok(Shard { index: row.get(0)?, count: row.get(1)? }))?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(shards)
+}
+
+fn acquire_as(conn: &Connection, holder: &str, shard: Shard, now: NaiveDateTime, ttl: Duration) -> Result<bool, rusqlite::Error> {
+    let ttl = chrono::Duration::from_std(ttl.max(LEASE_MIN)).unwrap_or_default();
+    let changed = conn.execute(
+        "INSERT INTO scheduler_leases (shard, shards, holder, expires_utc) VALUES (?1, ?2, ?3, ?4)
+         ON CONFLICT(shard, shards) DO UPDATE SET holder = excluded.holder, expires_utc = excluded.expires_utc
+         WHERE scheduler_leases.holder = excluded.holder OR scheduler_leases.expires_utc <= ?5",
+        params![
+            shard.index,
+            shard.count,
+            holder,
+            (now + ttl).format(UTC_FORMAT).to_string(),
+            now.format(UTC_FORMAT).to_string(),
+        ],
+    )?;
+    Ok(changed > 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::tests::memory_db;
+    use crate::{ensure_user_exists, tenants};
+
+    #[tokio::test]
+    async fn lease_belongs_to_one_process_until_it_expires() {
+        let db = memory_db();
+        let now = clock::SYSTEM.now_utc();
+        let ttl = Duration::from_secs(60);
+        let shard = Shard { index: 1, count: 4 };
+        let taken = db.call(move |conn| {
+            Ok((
+                acquire_as(conn, "a", shard, now, ttl)?,
+                acquire_as(conn, "b", shard, now, ttl)?,
+                acquire_as(conn, "a", shard, now, ttl)?,
+                acquire_as(conn, "b", Shard { index: 2, count: 4 }, now, ttl)?,
+                acquire_as(conn, "b", shard, now + chrono::Duration::seconds(61), ttl)?,
+            ))
+        }).await.unwrap();
+        assert_eq!(taken, (true, false, true, true, true));
+    }
+
+    #[tokio::test]
+    async fn singleton_tasks_run_in_one_process() {
+        let db = memory_db();
+        let now = clock::SYSTEM.now_utc();
+        let ttl = Duration::from_secs(60);
+        let taken = db.call(move |conn| {
+            Ok((
+                acquire_task_as(conn, "a", "notifications", now, ttl)?,
+                acquire_task_as(conn, "b", "notifications", now, ttl)?,
+                acquire_task_as(conn, "b", "mirrors", now, ttl)?,
+                acquire_task_as(conn, "b", "notifications", now + chrono::Duration::seconds(61), ttl)?,
+            ))
+        }).await.unwrap();
+        assert_eq!(taken, (true, false, true, true));
+    }
+
+    #[tokio::test]
+    async fn shards_cover_every_user_once() {
+        let db = memory_db();
+        let counts = db.call(|conn| {
+            for telegram_id in [-7_i64, 1, 5, 6, 1_000_000_007] {
+                ensure_user_exists(conn, tenants::DEFAULT, telegram_id, None)?;
+            }
+            (0..3)
+                .map(|index| Shard { index, count: 3 })
+                .chain([Shard::ALL])
+                .map(|shard| conn.query_row(&format!("SELECT COUNT(*) FROM users u WHERE {}", shard.condition()), [], |row| row.get::<_, i64>(0)))
+                .collect::<Result<Vec<_>, _>>()
+        }).await.unwrap();
+        // -7, 5 и 1_000_000_007 дают остаток 2, 1 — 1, 6 — 0
+        assert_eq!(counts, vec![1, 1, 3, 5]);
+    }
+}
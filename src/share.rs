@@ -0,0 +1,175 @@
+use std::io::Cursor;
+
+use teloxide::prelude::*;
+use teloxide::types::InputFile;
+use aes_gcm::aead::OsRng;
+use aes_gcm::aead::rand_core::RngCore;
+use chrono::{NaiveDateTime, Utc};
+use image::{GrayImage, ImageFormat, Luma};
+use qrcode::{Color, QrCode};
+use rusqlite::{Connection, params, OptionalExtension};
+
+use crate::chunks;
+use crate::i18n::{t, tf};
+use crate::settings::Settings;
+use crate::timezone;
+use crate::{
+    DatabaseError, Db, EVENT_TIME_FORMAT, UTC_FORMAT, ensure_user_exists, event_confirmation, get_event, ics,
+    in_transaction, insert_event, link_event_message, tenants,
+};
+
+/// Параметр `/start`, по которому бот узнаёт ссылку на чужое событие.
+const START_PREFIX: &str = "share_";
+/// Размер модуля QR-кода в пикселях и ширина белой рамки в модулях.
+const QR_SCALE: u32 = 8;
+const QR_QUIET_ZONE: u32 = 4;
+
+/// Событие, которое получатель ссылки копирует себе.
+#[derive(Debug)]
+struct SharedEvent {
+    text: String,
+    utc: NaiveDateTime,
+}
+
+pub fn init_tables(conn: &Connection) -> Result<(), rusqlite::Error> {
+    // Код в ссылке вместо id, чтобы чужие события нельзя было перебрать
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS event_shares (
+            code TEXT PRIMARY KEY,
+            event_id INTEGER NOT NULL UNIQUE,
+            created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            FOREIGN KEY(event_id) REFERENCES events(id) ON DELETE CASCADE
+        )",
+        [],
+    )?;
+    Ok(())
+}
+
+/// Код ссылки для события; повторный `/share` отдаёт тот же код.
+fn share_code(conn: &Connection, event_id: i64) -> Result<String, rusqlite::Error> {
+    let existing = conn.query_row(
+        "SELECT code FROM event_shares WHERE event_id = ?",
+        params![event_id],
+        |row| row.get(0),
+    ).optional()?;
+    if let Some(code) = existing {
+        return Ok(code);
+    }
+
+    let mut bytes = [0u8; 12];
+    OsRng.fill_bytes(&mut bytes);
+    let code = hex::encode(bytes);
+    conn.execute("INSERT INTO event_shares (code, event_id) VALUES (?, ?)", params![code, event_id])?;
+    Ok(code)
+}
+
+/// Событие по коду, если оно ещё не наступило.
+fn shared_event(conn: &Connection, code: &str) -> Result<Option<SharedEvent>, rusqlite::Error> {
+    let Some(event_id) = conn.query_row(
+        "SELECT s.event_id FROM event_shares s JOIN events e ON s.event_id = e.id
+         WHERE s.code = ? AND e.status = 'pending' AND e.event_utc > ?",
+        params![code, Utc::now().naive_utc().format(UTC_FORMAT).to_string()],
+        |row| row.get::<_, i64>(0),
+    ).optional()? else {
+        return Ok(None);
+    };
+    let utc: String = conn.query_row("SELECT event_utc FROM events WHERE id = ?", params![event_id], |row| row.get(0))?;
+    let Ok(utc) = NaiveDateTime::parse_from_str(&utc, UTC_FORMAT) else {
+        return Ok(None);
+    };
+    Ok(get_event(conn, event_id)?.map(|event| SharedEvent { text: event.text, utc }))
+}
+
+/// PNG с QR-кодом, построенный в процессе без внешних сервисов.
+fn render_qr(data: &str) -> Option<Vec<u8>> {
+    let code = QrCode::new(data.as_bytes()).ok()?;
+    let width = code.width() as u32;
+    let colors = code.to_colors();
+    let side = (width + QR_QUIET_ZONE * 2) * QR_SCALE;
+
+    let image = GrayImage::from_fn(side, side, |x, y| {
+        let (x, y) = (x / QR_SCALE, y / QR_SCALE);
+        let inside = (QR_QUIET_ZONE..QR_QUIET_ZONE + width).contains(&x) && (QR_QUIET_ZONE..QR_QUIET_ZONE + width).contains(&y);
+        let dark = inside && colors[((y - QR_QUIET_ZONE) * width + (x - QR_QUIET_ZONE)) as usize] == Color::Dark;
+        if dark { Luma([0]) } else { Luma([255]) }
+    });
+
+    let mut png = Vec::new();
+    image.write_to(&mut Cursor::new(&mut png), ImageFormat::Png).ok()?;
+    Some(png)
+}
+
+/// `/share #id` — QR-код со ссылкой, по которой другой человек добавит себе то же событие.
+pub async fn handle_share_command(bot: &Bot, msg: &Message, db: &Db, args: &str, settings: &Settings) -> ResponseResult<()> {
+    let lang = settings.lang;
+    let Ok(event_id) = args.trim_start_matches('#').parse::<i64>() else {
+        bot.send_message(msg.chat.id, t(lang, "share_usage")).await?;
+        return Ok(());
+    };
+
+    // Поделиться можно событием из того же чата, как и в остальных командах с #id
+    let chat_id = msg.chat.id.0;
+    let code = db.call(move |conn| {
+        match get_event(conn, event_id)?.filter(|event| event.chat_id == chat_id) {
+            Some(_) => share_code(conn, event_id).map(Some),
+            None => Ok(None),
+        }
+    }).await.map_err(DatabaseError)?;
+    let Some(code) = code else {
+        bot.send_message(msg.chat.id, t(lang, "event_not_found")).await?;
+        return Ok(());
+    };
+
+    let me = bot.get_me().await?;
+    let link = format!("https://t.me/{}?start={}{}", me.username(), START_PREFIX, code);
+    let Some(png) = render_qr(&link) else {
+        log::error!("Failed to render QR code for event {}", event_id);
+        chunks::send(bot, msg.chat.id, tf(lang, "share_link", &[("id", &event_id), ("link", &link)])).await?;
+        return Ok(());
+    };
+
+    log::info!("Shared event {} with code {}", event_id, code);
+    bot.send_photo(msg.chat.id, InputFile::memory(png).file_name(format!("event-{}.png", event_id)))
+        .caption(tf(lang, "share_link", &[("id", &event_id), ("link", &link)]))
+        .await?;
+    Ok(())
+}
+
+/// Код из `/start share_<код>`, если бот открыт по ссылке из `/share`.
+pub fn start_code(args: &str) -> Option<&str> {
+    args.strip_prefix(START_PREFIX).filter(|code| !code.is_empty())
+}
+
+/// Копирует событие по ссылке в личный чат получателя, на время по его часовому поясу.
+pub async fn handle_start(bot: &Bot, msg: &Message, db: &Db, code: &str, settings: &Settings) -> ResponseResult<()> {
+    let lang = settings.lang;
+    let Some(user) = msg.from() else {
+        return Ok(());
+    };
+
+    let (telegram_id, username, tenant, chat_id) = (user.id.0 as i64, user.username.clone(), tenants::name_of(bot), msg.chat.id.0);
+    let code = code.to_string();
+    let created = db.call(move |conn| in_transaction(conn, |tx| {
+        let Some(shared) = shared_event(tx, &code)? else {
+            return Ok(None);
+        };
+        let user_id = ensure_user_exists(tx, tenant, telegram_id, username)?;
+        let local = timezone::from_utc(shared.utc, timezone::user_zone(tx, user_id)?);
+        let event_id = insert_event(tx, tenant, user_id, chat_id, &shared.text, &local.format(EVENT_TIME_FORMAT).to_string())?;
+        Ok(Some((event_id, shared.text, local)))
+    })).await.map_err(DatabaseError)?;
+
+    let Some((event_id, text, event_time)) = created else {
+        bot.send_message(msg.chat.id, t(lang, "share_not_found")).await?;
+        return Ok(());
+    };
+
+    log::info!("User {} added shared event as {}", telegram_id, event_id);
+    let confirmation = chunks::send_last(bot, msg.chat.id, event_confirmation(settings, &text, event_time)).await?
+        .reply_markup(ics::keyboard(lang, event_id))
+        .await?;
+    db.call(move |conn| link_event_message(conn, chat_id, confirmation.id.0, event_id, "confirmation"))
+        .await
+        .map_err(DatabaseError)?;
+    Ok(())
+}
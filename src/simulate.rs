@@ -0,0 +1,92 @@
+use std::time::Duration;
+
+use chrono::{NaiveDate, NaiveDateTime, TimeZone, Utc};
+use rusqlite::backup::Backup;
+use rusqlite::{Connection, OpenFlags};
+
+use crate::clock::{Clock, FixedClock};
+use crate::shards::Shard;
+use crate::{DB_PATH, Db, UTC_FORMAT, config, deadlines, init_db, outbox, repeats, send_due_events, storage};
+
+/// Сколько страниц копируется за шаг; база копируется целиком, паузы не нужны.
+const COPY_PAGES: std::os::raw::c_int = 1000;
+const USAGE: &str = "Usage: reventor simulate --from <YYYY-MM-DDTHH:MM> --to <YYYY-MM-DDTHH:MM> [--step <seconds>]";
+
+/// Момент в UTC: `2030-01-01T09:00`, `2030-01-01 09:00:30` или просто дата.
+fn parse_moment(value: &str) -> Option<NaiveDateTime> {
+    ["%Y-%m-%dT%H:%M", "%Y-%m-%d %H:%M", "%Y-%m-%dT%H:%M:%S", UTC_FORMAT]
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(value, format).ok())
+        .or_else(|| NaiveDate::parse_from_str(value, "%Y-%m-%d").ok()?.and_hms_opt(0, 0, 0))
+}
+
+fn option<'a>(args: &'a [String], name: &str) -> Option<&'a str> {
+    let position = args.iter().position(|arg| arg == name)?;
+    args.get(position + 1).map(String::as_str)
+}
+
+/// `reventor simulate --from <момент> --to <момент> [--step <секунды>]` — прогоняет планировщик
+/// напоминаний по копии базы в памяти с часами, идущими от `--from` до `--to` (UTC), и печатает,
+/// что и когда ушло бы. Сама база и Telegram не затрагиваются; шаг по умолчанию — `POLL_INTERVAL_SECS`.
+pub async fn run(args: &[String]) -> Result<(), String> {
+    let from = option(args, "--from").and_then(parse_moment).ok_or(USAGE)?;
+    let to = option(args, "--to").and_then(parse_moment).ok_or(USAGE)?;
+    if to < from {
+        return Err("--to must not be earlier than --from".to_string());
+    }
+    let step = match option(args, "--step") {
+        Some(step) => Duration::from_secs(step.parse().ok().filter(|step| *step > 0).ok_or(USAGE)?),
+        None => config::get().poll_interval,
+    };
+    let step = chrono::Duration::from_std(step).map_err(|e| e.to_string())?;
+
+    let db = Db::new(copy_database()?);
+    let clock = FixedClock::new(Utc.from_utc_datetime(&from));
+    let mut sent = Vec::new();
+    while clock.now_utc() <= to {
+        let now = clock.now_utc();
+        send_due_events(&db, &clock, Shard::ALL).await;
+        let (reminders, repeated) = db
+            .call(move |conn| {
+                deadlines::queue_due(conn, now)?;
+                Ok((outbox::dry_run(conn, now)?, repeats::dry_run(conn, now)?))
+            })
+            .await
+            .map_err(|e| format!("Simulation failed at {}: {}", now, e))?;
+        sent.extend(reminders.into_iter().map(|message| (now, message.tenant, message.chat_id, message.text)));
+        sent.extend(repeated.into_iter().map(|(tenant, chat_id, text)| (now, tenant, chat_id, text)));
+        clock.advance(step);
+    }
+
+    println!("Simulated {} .. {} UTC, step {} s: {} messages", from, to, step.num_seconds(), sent.len());
+    for (at, tenant, chat_id, text) in sent {
+        println!("{} [{}] chat {}: {}", at.format(UTC_FORMAT), tenant, chat_id, text.replace('\n', " / "));
+    }
+    Ok(())
+}
+
+/// Копия базы в памяти со всеми миграциями: прогон меняет статусы событий и очередь.
+fn copy_database() -> Result<Connection, String> {
+    let source = Connection::open_with_flags(DB_PATH, OpenFlags::SQLITE_OPEN_READ_ONLY)
+        .map_err(|e| format!("Failed to open database: {}", e))?;
+    let mut copy = storage::open_in_memory().map_err(|e| format!("Failed to open in-memory database: {}", e))?;
+    Backup::new(&source, &mut copy)
+        .and_then(|backup| backup.run_to_completion(COPY_PAGES, Duration::ZERO, None))
+        .map_err(|e| format!("Failed to copy database: {}", e))?;
+    init_db(&copy).map_err(|e| format!("Failed to initialize database: {}", e))?;
+    Ok(copy)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn moments_accept_common_forms() {
+        let nine = NaiveDate::from_ymd_opt(2030, 1, 1).unwrap().and_hms_opt(9, 0, 0).unwrap();
+        assert_eq!(parse_moment("2030-01-01T09:00"), Some(nine));
+        assert_eq!(parse_moment("2030-01-01 09:00:00"), Some(nine));
+        assert_eq!(parse_moment("2030-01-01"), Some(nine.date().and_hms_opt(0, 0, 0).unwrap()));
+        assert_eq!(parse_moment("01.01.2030"), None);
+    }
+}
@@ -0,0 +1,120 @@
+use teloxide::prelude::*;
+use teloxide::types::MessageId;
+use chrono::NaiveDateTime;
+use rusqlite::{Connection, params, OptionalExtension};
+
+use crate::categories;
+use crate::chunks;
+use crate::i18n::{t, tf};
+use crate::settings::Settings;
+use crate::{DatabaseError, Db, add_column_if_missing, crypto, get_event, parse_event_time};
+
+pub fn init_tables(conn: &Connection) -> Result<(), rusqlite::Error> {
+    add_column_if_missing(conn, "events", "starred", "INTEGER NOT NULL DEFAULT 0")?;
+    // Закреплённое напоминание избранного события; открепляется, когда событие выполнено
+    add_column_if_missing(conn, "events", "pinned_message_id", "INTEGER")?;
+    Ok(())
+}
+
+/// Переключает отметку и возвращает новое состояние.
+fn toggle_star(conn: &Connection, event_id: i64) -> Result<bool, rusqlite::Error> {
+    conn.execute("UPDATE events SET starred = 1 - starred WHERE id = ?", params![event_id])?;
+    conn.query_row("SELECT starred FROM events WHERE id = ?", params![event_id], |row| row.get(0))
+}
+
+/// Избранные события, которые ещё не выполнены: и предстоящие, и уже напомненные.
+pub fn open_starred(conn: &Connection, telegram_id: i64) -> Result<Vec<(NaiveDateTime, String)>, rusqlite::Error> {
+    let mut stmt = conn.prepare(
+        "SELECT e.event_time, e.text, e.icon FROM events e
+         JOIN users u ON e.user_id = u.id
+         WHERE u.telegram_id = ? AND e.starred = 1 AND e.status IN ('pending', 'sent')",
+    )?;
+    let mut events = stmt.query_map(params![telegram_id], |row| {
+        Ok((row.get::<_, String>(0)?, categories::label(row.get::<_, Option<String>>(2)?.as_deref(), &crypto::open(row.get(1)?))))
+    })?
+    .collect::<Result<Vec<_>, _>>()?
+    .into_iter()
+    .filter_map(|(time, text)| parse_event_time(&time).map(|time| (time, text)))
+    .collect::<Vec<_>>();
+
+    events.sort_by_key(|(time, _)| *time);
+    Ok(events)
+}
+
+/// Раздел сводки с избранным: дата указывается, потому что событие может быть не сегодня.
+pub fn digest_section(settings: &Settings, starred: &[(NaiveDateTime, String)]) -> Option<String> {
+    if starred.is_empty() {
+        return None;
+    }
+    let list = starred
+        .iter()
+        .map(|(time, text)| format!("{} - {}", settings.format_datetime(*time), text))
+        .collect::<Vec<_>>()
+        .join("\n");
+    Some(tf(settings.lang, "digest_starred", &[("events", &list)]))
+}
+
+/// `/star #id` — отметить событие избранным или снять отметку.
+pub async fn handle_star_command(bot: &Bot, msg: &Message, db: &Db, args: &str, settings: &Settings) -> ResponseResult<()> {
+    let lang = settings.lang;
+    let Ok(event_id) = args.trim_start_matches('#').parse::<i64>() else {
+        bot.send_message(msg.chat.id, t(lang, "star_usage")).await?;
+        return Ok(());
+    };
+    let Some(user) = msg.from() else {
+        return Ok(());
+    };
+
+    let (telegram_id, chat_id) = (user.id.0 as i64, msg.chat.id.0);
+    let starred = db.call(move |conn| {
+        match get_event(conn, event_id)?.filter(|event| event.chat_id == chat_id && event.owner_id == telegram_id) {
+            Some(_) => toggle_star(conn, event_id).map(Some),
+            None => Ok(None),
+        }
+    }).await.map_err(DatabaseError)?;
+
+    let response = match starred {
+        Some(true) => tf(lang, "star_added", &[("id", &event_id)]),
+        Some(false) => tf(lang, "star_removed", &[("id", &event_id)]),
+        None => t(lang, "event_not_found"),
+    };
+    chunks::send(bot, msg.chat.id, response).await?;
+    Ok(())
+}
+
+/// Закрепляет отправленное напоминание избранного события. Без прав на закрепление
+/// в группе напоминание остаётся обычным сообщением.
+pub async fn pin(bot: &Bot, db: &Db, event_id: i64, message: &Message) {
+    if let Err(e) = bot.pin_chat_message(message.chat.id, message.id).disable_notification(true).await {
+        log::error!("Failed to pin reminder for event {}: {}", event_id, e);
+        return;
+    }
+    let message_id = message.id.0;
+    if let Err(e) = db.call(move |conn| {
+        conn.execute("UPDATE events SET pinned_message_id = ? WHERE id = ?", params![message_id, event_id])
+    }).await {
+        log::error!("Failed to save pinned message for event {}: {}", event_id, e);
+    }
+}
+
+/// Открепляет напоминание выполненного события, если оно было закреплено.
+pub async fn unpin(bot: &Bot, db: &Db, event_id: i64) -> ResponseResult<()> {
+    let pinned = db.call(move |conn| {
+        let pinned = conn.query_row(
+            "SELECT e.pinned_message_id, COALESCE(e.chat_id, u.telegram_id) FROM events e
+             JOIN users u ON e.user_id = u.id
+             WHERE e.id = ? AND e.pinned_message_id IS NOT NULL",
+            params![event_id],
+            |row| Ok((row.get::<_, i32>(0)?, row.get::<_, i64>(1)?)),
+        ).optional()?;
+        conn.execute("UPDATE events SET pinned_message_id = NULL WHERE id = ?", params![event_id])?;
+        Ok(pinned)
+    }).await.map_err(DatabaseError)?;
+
+    if let Some((message_id, chat_id)) = pinned {
+        if let Err(e) = bot.unpin_chat_message(ChatId(chat_id)).message_id(MessageId(message_id)).await {
+            log::error!("Failed to unpin reminder for event {}: {}", event_id, e);
+        }
+    }
+    Ok(())
+}
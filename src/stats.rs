@@ -0,0 +1,181 @@
+use teloxide::prelude::*;
+use teloxide::types::InputFile;
+use chrono::{Datelike, Duration, NaiveDate, NaiveDateTime};
+use rusqlite::{Connection, params};
+
+use crate::accessibility;
+use crate::chunks;
+use crate::habits;
+use crate::humanize;
+use crate::i18n::{t, tf};
+use crate::render;
+use crate::settings::Settings;
+use crate::timezone;
+use crate::{DatabaseError, Db, UTC_FORMAT, add_column_if_missing, parse_event_time};
+
+/// Сколько последних недель показывает `/stats`.
+const WEEKS: i64 = 6;
+/// Длина самой длинной полосы в символах.
+const BAR_WIDTH: usize = 10;
+/// Доли символа для конца полосы: от ⅛ до ⅞.
+const PARTIAL_BLOCKS: [char; 7] = ['▏', '▎', '▍', '▌', '▋', '▊', '▉'];
+
+pub fn init_tables(conn: &Connection) -> Result<(), rusqlite::Error> {
+    // Сколько раз событие откладывали и когда его выполнили — для `/stats`
+    add_column_if_missing(conn, "events", "snoozes", "INTEGER NOT NULL DEFAULT 0")?;
+    add_column_if_missing(conn, "events", "done_at", "DATETIME")?;
+    Ok(())
+}
+
+/// Отмечает, что событие отложили: реакцией 😴 или кнопкой у пропущенного события.
+pub fn record_snooze(conn: &Connection, event_id: i64) -> Result<(), rusqlite::Error> {
+    conn.execute("UPDATE events SET snoozes = snoozes + 1 WHERE id = ?", params![event_id])?;
+    Ok(())
+}
+
+/// Полоса из блоков длиной `value / max` от `BAR_WIDTH` с точностью до восьмой символа.
+fn bar(value: usize, max: usize) -> String {
+    if max == 0 {
+        return String::new();
+    }
+    let eighths = value * BAR_WIDTH * 8 / max;
+    let mut bar = "█".repeat(eighths / 8);
+    if let Some(partial) = (eighths % 8).checked_sub(1) {
+        bar.push(PARTIAL_BLOCKS[partial]);
+    }
+    bar
+}
+
+/// Событие пользователя: когда создано и выполнено (по его часам), на когда назначено, сколько раз отложено.
+struct Row {
+    created: Option<NaiveDateTime>,
+    done: Option<NaiveDateTime>,
+    time: Option<NaiveDateTime>,
+    snoozes: u32,
+}
+
+fn user_rows(conn: &Connection, telegram_id: i64) -> Result<Vec<Row>, rusqlite::Error> {
+    let mut stmt = conn.prepare(
+        "SELECT e.created_at, e.done_at, e.event_time, e.snoozes, u.timezone
+         FROM events e
+         JOIN users u ON e.user_id = u.id
+         WHERE u.telegram_id = ?",
+    )?;
+    let rows = stmt.query_map(params![telegram_id], |row| {
+        let tz = row.get::<_, Option<String>>(4)?.as_deref().and_then(timezone::parse_zone);
+        // created_at и done_at пишет SQLite в UTC
+        let local = |value: Option<String>| {
+            value.and_then(|value| NaiveDateTime::parse_from_str(&value, UTC_FORMAT).ok()).map(|utc| timezone::from_utc(utc, tz))
+        };
+        Ok(Row {
+            created: local(row.get(0)?),
+            done: local(row.get(1)?),
+            time: parse_event_time(&row.get::<_, String>(2)?),
+            snoozes: row.get(3)?,
+        })
+    })?;
+    rows.collect()
+}
+
+/// Понедельник недели, в которую попадает день.
+fn week_start(day: NaiveDate) -> NaiveDate {
+    day - Duration::days(day.weekday().num_days_from_monday().into())
+}
+
+/// `/stats`: создано и выполнено по неделям, загруженные дни недели, откладывания и серии привычек.
+pub async fn handle_stats_command(bot: &Bot, msg: &Message, db: &Db, settings: &Settings) -> ResponseResult<()> {
+    let lang = settings.lang;
+    let Some(user) = msg.from() else {
+        return Ok(());
+    };
+    let telegram_id = user.id.0 as i64;
+    let today = timezone::now_in(settings.timezone).date();
+    let (rows, streaks) = db.call(move |conn| Ok((user_rows(conn, telegram_id)?, habits::streaks(conn, telegram_id, today)?)))
+        .await
+        .map_err(DatabaseError)?;
+    if rows.is_empty() && streaks.is_empty() {
+        bot.send_message(msg.chat.id, t(lang, "stats_empty")).await?;
+        return Ok(());
+    }
+
+    let this_week = week_start(today);
+    let weeks = (0..WEEKS).rev().map(|ago| this_week - Duration::weeks(ago)).collect::<Vec<_>>();
+    let per_week = |at: fn(&Row) -> Option<NaiveDateTime>| {
+        weeks
+            .iter()
+            .map(|week| rows.iter().filter(|row| at(row).is_some_and(|at| week_start(at.date()) == *week)).count())
+            .collect::<Vec<_>>()
+    };
+    let (created, done) = (per_week(|row| row.created), per_week(|row| row.done));
+    let most = created.iter().chain(&done).copied().max().unwrap_or_default();
+    let week_lines = weeks
+        .iter()
+        .zip(created.iter().zip(&done))
+        .map(|(week, (created, done))| {
+            tf(lang, "stats_week", &[
+                ("week", &settings.format_short_date(*week)),
+                ("created_bar", &bar(*created, most)),
+                ("created", created),
+                ("done_bar", &bar(*done, most)),
+                ("done", done),
+            ])
+        })
+        .collect::<Vec<_>>();
+
+    let week_names = weeks.iter().map(|week| settings.format_short_date(*week)).collect::<Vec<_>>();
+    let week_chart = render::bar_chart(&t(lang, "stats_chart_weeks"), &week_names, &[
+        (&t(lang, "stats_created"), created.iter().map(|count| *count as u64).collect()),
+        (&t(lang, "stats_done"), done.iter().map(|count| *count as u64).collect()),
+    ]);
+
+    let mut weekdays = [0; 7];
+    for time in rows.iter().filter_map(|row| row.time) {
+        weekdays[time.weekday().num_days_from_monday() as usize] += 1;
+    }
+    let busiest = weekdays.iter().copied().max().unwrap_or_default();
+    let weekday_names = t(lang, "calendar_weekdays").split_whitespace().map(str::to_string).collect::<Vec<_>>();
+    let weekday_lines = weekday_names
+        .iter()
+        .zip(weekdays)
+        .map(|(name, count)| match accessibility::current() {
+            Some(_) => format!("{}: {}", name, count),
+            None => format!("{} {} {}", name, bar(count, busiest), count),
+        })
+        .collect::<Vec<_>>();
+    let weekday_chart = render::bar_chart(&t(lang, "stats_chart_weekdays"), &weekday_names, &[
+        (&t(lang, "stats_events"), weekdays.iter().map(|count| *count as u64).collect()),
+    ]);
+
+    let snoozes = rows.iter().map(|row| row.snoozes).sum::<u32>() as f64 / rows.len().max(1) as f64;
+    let habit_lines = streaks
+        .iter()
+        .map(|(name, streak)| tf(lang, "stats_habit", &[("name", name), ("streak", &humanize::days(lang, *streak))]))
+        .collect::<Vec<_>>();
+
+    chunks::send(bot, msg.chat.id, tf(lang, "stats_report", &[
+        ("weeks", &week_lines.join("\n")),
+        ("weekdays", &weekday_lines.join("\n")),
+        ("snoozes", &format!("{:.1}", snoozes)),
+        ("habits", &if habit_lines.is_empty() { t(lang, "stats_no_habits") } else { habit_lines.join("\n") }),
+    ])).await?;
+    for (name, png) in [("stats-weeks.png", week_chart), ("stats-weekdays.png", weekday_chart)] {
+        if let Some(png) = png {
+            bot.send_photo(msg.chat.id, InputFile::memory(png).file_name(name)).await?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bars_scale_to_the_longest() {
+        assert_eq!(bar(0, 4), "");
+        assert_eq!(bar(4, 4), "██████████");
+        assert_eq!(bar(2, 4), "█████");
+        assert_eq!(bar(1, 16), "▋");
+        assert_eq!(bar(3, 0), "");
+    }
+}
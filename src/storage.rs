@@ -0,0 +1,180 @@
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use regex::Regex;
+use rusqlite::{Connection, params};
+
+use crate::{accessibility, settings};
+
+/// Сколько запрос ждёт, пока другой писатель отпустит базу, прежде чем вернуть SQLITE_BUSY.
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Открывает базу: WAL позволяет читать, пока идёт запись,
+/// а при занятой базе запрос ждёт вместо немедленной ошибки.
+pub fn open(path: &str) -> Result<Connection, rusqlite::Error> {
+    let conn = Connection::open(path)?;
+    let mode: String = conn.pragma_update_and_check(None, "journal_mode", "WAL", |row| row.get(0))?;
+    if !mode.eq_ignore_ascii_case("wal") {
+        log::error!("SQLite refused WAL journal, using {}", mode);
+    }
+    conn.busy_timeout(BUSY_TIMEOUT)?;
+    Ok(conn)
+}
+
+/// База в памяти для тестов и `--ephemeral`: схема та же, но всё пропадает при выходе.
+/// WAL для неё не нужен — соединение всё равно одно.
+pub fn open_in_memory() -> Result<Connection, rusqlite::Error> {
+    let conn = Connection::open_in_memory()?;
+    conn.busy_timeout(BUSY_TIMEOUT)?;
+    Ok(conn)
+}
+
+/// Включает проверку внешних ключей. Вызывается после миграций: старые таблицы
+/// пересоздаются с `ON DELETE CASCADE`, а это возможно только при выключенной проверке.
+pub fn enable_foreign_keys(conn: &Connection) -> Result<(), rusqlite::Error> {
+    conn.pragma_update(None, "foreign_keys", false)?;
+    add_cascade_deletes(conn)?;
+    conn.pragma_update(None, "foreign_keys", true)?;
+
+    // Строки, ссылающиеся на удалённые записи, остаются, но о них стоит знать
+    let mut stmt = conn.prepare("PRAGMA foreign_key_check")?;
+    let orphans = stmt
+        .query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, Option<i64>>(1)?)))?
+        .collect::<Result<Vec<_>, _>>()?;
+    for (table, rowid) in orphans {
+        log::error!("Row {:?} in {} references a missing record", rowid, table);
+    }
+    Ok(())
+}
+
+/// Пересоздаёт таблицы, созданные до появления каскадного удаления.
+/// SQLite не умеет менять внешний ключ, поэтому таблица копируется целиком.
+fn add_cascade_deletes(conn: &Connection) -> Result<(), rusqlite::Error> {
+    let reference = Regex::new(r"REFERENCES \w+\(\w+\)").unwrap();
+
+    let mut stmt = conn.prepare(
+        "SELECT name, sql FROM sqlite_master WHERE type = 'table' AND sql LIKE '%REFERENCES%'"
+    )?;
+    let tables = stmt
+        .query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    for (table, sql) in tables {
+        let cascaded = reference.replace_all(&sql.replace(" ON DELETE CASCADE", ""), "$0 ON DELETE CASCADE").to_string();
+        if cascaded == sql {
+            continue;
+        }
+        let Some(columns) = cascaded.split_once('(').map(|(_, rest)| rest) else {
+            continue;
+        };
+
+        let mut stmt = conn.prepare("SELECT sql FROM sqlite_master WHERE type = 'index' AND tbl_name = ? AND sql IS NOT NULL")?;
+        let indexes = stmt.query_map(params![table], |row| row.get::<_, String>(0))?.collect::<Result<Vec<_>, _>>()?;
+
+        let tx = conn.unchecked_transaction()?;
+        tx.execute_batch(&format!(
+            "CREATE TABLE {table}_cascade ({columns};
+             INSERT INTO {table}_cascade SELECT * FROM {table};
+             DROP TABLE {table};
+             ALTER TABLE {table}_cascade RENAME TO {table};"
+        ))?;
+        for index in indexes {
+            tx.execute_batch(&index)?;
+        }
+        tx.commit()?;
+        log::info!("Enabled cascade deletes for {}", table);
+    }
+    Ok(())
+}
+
+/// Общее соединение с базой. rusqlite блокирует поток, поэтому запросы выполняются
+/// в пуле блокирующих потоков tokio, а не на потоках, обрабатывающих апдейты.
+#[derive(Clone)]
+pub struct Db {
+    conn: Arc<Mutex<Connection>>,
+}
+
+impl Db {
+    pub fn new(conn: Connection) -> Self {
+        let conn = Arc::new(Mutex::new(conn));
+        settings::forget(&conn.lock().unwrap_or_else(|poisoned| poisoned.into_inner()));
+        Db { conn }
+    }
+
+    /// Выполняет `f` с соединением вне асинхронного рантайма.
+    /// Всё, что должно выполниться без чужих запросов между шагами, пишется в одном вызове.
+    pub async fn call<T, F>(&self, f: F) -> Result<T, rusqlite::Error>
+    where
+        T: Send + 'static,
+        F: FnOnce(&Connection) -> Result<T, rusqlite::Error> + Send + 'static,
+    {
+        let conn = self.conn.clone();
+        // Тексты собираются и внутри запросов, поэтому режим вывода переходит в их поток
+        let plain = accessibility::current();
+        let task = tokio::task::spawn_blocking(move || {
+            // Паника в другом запросе не портит соединение, поэтому отравленный мьютекс не страшен
+            let conn = conn.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+            accessibility::with(plain, || f(&conn))
+        });
+        match task.await {
+            Ok(result) => result,
+            Err(e) => std::panic::resume_unwind(e.into_panic()),
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::clock::{self, Clock};
+    use crate::shards::Shard;
+    use crate::{ensure_user_exists, get_due_events, get_event, init_db, insert_event, tenants};
+
+    /// Готовая база в памяти со всеми таблицами, как при запуске бота.
+    pub fn memory_db() -> Db {
+        let conn = open_in_memory().unwrap();
+        init_db(&conn).unwrap();
+        enable_foreign_keys(&conn).unwrap();
+        Db::new(conn)
+    }
+
+    #[tokio::test]
+    async fn stores_and_reads_events() {
+        let db = memory_db();
+        let (event_id, event) = db.call(|conn| {
+            let user_id = ensure_user_exists(conn, tenants::DEFAULT, 42, Some("alice".to_string()))?;
+            let event_id = insert_event(conn, tenants::DEFAULT, user_id, 42, "Позвонить маме", "01.01.2020 10:00")?;
+            Ok((event_id, get_event(conn, event_id)?))
+        }).await.unwrap();
+
+        let event = event.unwrap();
+        assert_eq!(event.id, event_id);
+        assert_eq!(event.owner_id, 42);
+        assert_eq!(event.text, "Позвонить маме");
+    }
+
+    #[tokio::test]
+    async fn past_events_are_due() {
+        let db = memory_db();
+        let due = db.call(|conn| {
+            let user_id = ensure_user_exists(conn, tenants::DEFAULT, 42, None)?;
+            insert_event(conn, tenants::DEFAULT, user_id, 42, "В прошлом", "01.01.2020 10:00")?;
+            insert_event(conn, tenants::DEFAULT, user_id, 42, "В будущем", "01.01.2999 10:00")?;
+            get_due_events(conn, clock::SYSTEM.now_utc(), Shard::ALL)
+        }).await.unwrap();
+
+        assert_eq!(due.iter().map(|event| event.text.as_str()).collect::<Vec<_>>(), ["В прошлом"]);
+    }
+
+    #[tokio::test]
+    async fn separate_databases_do_not_share_data() {
+        let (first, second) = (memory_db(), memory_db());
+        first.call(|conn| ensure_user_exists(conn, tenants::DEFAULT, 42, None)).await.unwrap();
+
+        let count = |db: Db| async move {
+            db.call(|conn| conn.query_row("SELECT COUNT(*) FROM users", [], |row| row.get::<_, i64>(0))).await.unwrap()
+        };
+        assert_eq!(count(first).await, 1);
+        assert_eq!(count(second).await, 0);
+    }
+}
@@ -0,0 +1,655 @@
+//! Подписки на чужие календари по ссылке `.ics` (расписание команды, пар в университете).
+//! Календарь периодически перечитывается: новые события появляются, перенесённые переезжают,
+//! отменённые удаляются, а напоминания о них приходят как о своих.
+
+use std::collections::BTreeMap;
+use std::env;
+use std::net::IpAddr;
+use std::time::Duration;
+
+use teloxide::prelude::*;
+use chrono::{NaiveDateTime, Utc};
+use chrono_tz::Tz;
+use rusqlite::{Connection, params, OptionalExtension};
+
+use crate::audit;
+use crate::chunks;
+use crate::groups;
+use crate::i18n::{t, tf};
+use crate::ics::{self, RemoteEvent};
+use crate::outbox::{self, Outgoing};
+use crate::settings::{self, Settings};
+use crate::shards;
+use crate::sync::{self, Policy, Remote, Resolution, State};
+use crate::timezone;
+use crate::{
+    DatabaseError, Db, EVENT_TIME_FORMAT, UTC_FORMAT, add_column_if_missing, crypto, ensure_user_exists, in_transaction,
+    insert_event, set_event_end, tenants, update_event,
+};
+
+/// Насколько вперёд из календаря создаются события.
+const WINDOW_DAYS: i64 = 60;
+/// Пауза между обновлениями по умолчанию, см. `ICS_REFRESH_MINUTES`.
+const DEFAULT_REFRESH: Duration = Duration::from_secs(60 * 60);
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+/// Календари больше этого размера не читаются.
+const MAX_CALENDAR_SIZE: usize = 5 * 1024 * 1024;
+/// Сколько подписок может быть у одного чата.
+const MAX_SUBSCRIPTIONS: i64 = 10;
+
+/// Подписка чата на календарь.
+#[derive(Debug)]
+pub struct Subscription {
+    pub id: i64,
+    user_id: i64,
+    telegram_id: i64,
+    tenant: String,
+    chat_id: i64,
+    url: String,
+    pub name: String,
+    /// Как поступать, если событие изменили и в боте, и в календаре.
+    pub policy: Policy,
+}
+
+impl Subscription {
+    /// Источник в состоянии синхронизации.
+    fn source(&self) -> String {
+        source(self.id)
+    }
+}
+
+fn source(subscription_id: i64) -> String {
+    format!("ics:{}", subscription_id)
+}
+
+/// Экземпляр события из календаря: для повторяющихся — отдельный повтор.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Instance {
+    pub text: String,
+    /// Начало и конец на часах подписчика в формате БД.
+    pub event_time: String,
+    pub end_time: Option<String>,
+    pub sequence: i64,
+    /// Когда событие меняли в календаре, UTC.
+    pub modified: Option<NaiveDateTime>,
+}
+
+impl Instance {
+    /// Отпечаток того, что пришло из календаря: по нему видно, изменилось ли событие с прошлого раза.
+    pub fn fingerprint(&self) -> String {
+        sync::fingerprint(&self.event_time, self.end_time.as_deref(), &self.text)
+    }
+
+    fn remote(&self) -> Remote {
+        Remote { text: self.text.clone(), event_time: self.event_time.clone(), end_time: self.end_time.clone() }
+    }
+}
+
+/// Что изменило обновление.
+#[derive(Debug, Default, PartialEq)]
+pub struct Changes {
+    pub added: usize,
+    pub updated: usize,
+    pub cancelled: usize,
+    /// Изменения календаря, не принятые из-за правок в боте.
+    pub kept: usize,
+    /// Новые конфликты, о которых нужно спросить.
+    pub conflicts: Vec<i64>,
+}
+
+pub fn init_tables(conn: &Connection) -> Result<(), rusqlite::Error> {
+    // Адрес хранится зашифрованным: в приватных ссылках календарей есть секретный ключ
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS ics_subscriptions (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            user_id INTEGER NOT NULL,
+            chat_id INTEGER NOT NULL,
+            url TEXT NOT NULL,
+            name TEXT NOT NULL,
+            refreshed_at DATETIME,
+            last_error TEXT,
+            created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            FOREIGN KEY(user_id) REFERENCES users(id) ON DELETE CASCADE
+        )",
+        [],
+    )?;
+    add_column_if_missing(conn, "ics_subscriptions", "conflict_policy", "TEXT NOT NULL DEFAULT 'latest'")?;
+    Ok(())
+}
+
+/// Пауза между обновлениями из `ICS_REFRESH_MINUTES`. Меняется только перезапуском.
+fn refresh_interval() -> Duration {
+    env::var("ICS_REFRESH_MINUTES")
+        .ok()
+        .and_then(|minutes| minutes.trim().parse().ok())
+        .filter(|minutes| *minutes > 0)
+        .map_or(DEFAULT_REFRESH, |minutes: u64| Duration::from_secs(minutes * 60))
+}
+
+/// `webcal://` — тот же HTTPS; другие схемы не принимаются.
+fn normalize_url(url: &str) -> Option<reqwest::Url> {
+    let url = match url.strip_prefix("webcal://") {
+        Some(rest) => format!("https://{}", rest),
+        None => url.to_string(),
+    };
+    reqwest::Url::parse(&url).ok().filter(|url| matches!(url.scheme(), "http" | "https") && url.host_str().is_some())
+}
+
+/// Календарь скачивается сервером бота, поэтому адреса внутренней сети и самого сервера запрещены.
+pub async fn is_public(url: &reqwest::Url) -> bool {
+    let (Some(host), Some(port)) = (url.host_str(), url.port_or_known_default()) else {
+        return false;
+    };
+    let Ok(addresses) = tokio::net::lookup_host((host, port)).await else {
+        return false;
+    };
+    let addresses = addresses.collect::<Vec<_>>();
+    !addresses.is_empty()
+        && addresses.iter().all(|address| match address.ip() {
+            IpAddr::V4(ip) => !(ip.is_private() || ip.is_loopback() || ip.is_link_local() || ip.is_unspecified() || ip.is_broadcast()),
+            IpAddr::V6(ip) => !(ip.is_loopback() || ip.is_unspecified() || (ip.segments()[0] & 0xfe00) == 0xfc00 || (ip.segments()[0] & 0xffc0) == 0xfe80),
+        })
+}
+
+async fn fetch(url: &str) -> Result<ics::Calendar, String> {
+    let url = normalize_url(url).ok_or("invalid url")?;
+    if !is_public(&url).await {
+        return Err("address is not public".to_string());
+    }
+    let response = reqwest::Client::new()
+        .get(url)
+        .timeout(REQUEST_TIMEOUT)
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(|e| e.to_string())?;
+    if response.content_length().is_some_and(|length| length > MAX_CALENDAR_SIZE as u64) {
+        return Err("calendar is too large".to_string());
+    }
+    let data = response.bytes().await.map_err(|e| e.to_string())?;
+    if data.len() > MAX_CALENDAR_SIZE {
+        return Err("calendar is too large".to_string());
+    }
+    ics::parse_calendar(&String::from_utf8_lossy(&data)).ok_or_else(|| "not an iCalendar file".to_string())
+}
+
+/// Текст события: название и место, если оно указано.
+fn event_text(event: &RemoteEvent) -> String {
+    let summary = if event.summary.is_empty() { "📅" } else { event.summary.as_str() };
+    if event.location.is_empty() {
+        summary.to_string()
+    } else {
+        format!("{}\n📍 {}", summary, event.location)
+    }
+}
+
+/// Экземпляры событий календаря, начинающиеся в ближайшие `WINDOW_DAYS` дней, по ключам.
+/// Изменённые повторы (`RECURRENCE-ID`) заменяют свои исходные, отменённые — убирают их.
+pub fn instances(events: &[RemoteEvent], now: NaiveDateTime, tz: Option<Tz>) -> BTreeMap<String, Instance> {
+    let until = now + chrono::Duration::days(WINDOW_DAYS);
+    let local = |utc: NaiveDateTime| timezone::from_utc(utc, tz).format(EVENT_TIME_FORMAT).to_string();
+    let instance = |event: &RemoteEvent, start: ics::Moment| {
+        let start_utc = start.to_utc(tz);
+        // Конец повтора сдвигается вместе с началом
+        let end = event.start.zip(event.end).map(|(first, end)| start_utc + (end.to_utc(tz) - first.to_utc(tz)));
+        Instance {
+            text: event_text(event),
+            event_time: local(start_utc),
+            end_time: end.filter(|end| *end > start_utc).map(local),
+            sequence: event.sequence,
+            modified: event.modified,
+        }
+    };
+
+    let mut found = BTreeMap::new();
+    for event in events.iter().filter(|event| event.recurrence_id.is_none() && !event.cancelled && !event.uid.is_empty()) {
+        for start in ics::occurrences(event, now, until, tz) {
+            let key = if event.rule.is_some() { format!("{}/{}", event.uid, start.stamp()) } else { event.uid.clone() };
+            found.insert(key, instance(event, start));
+        }
+    }
+    for event in events.iter().filter(|event| !event.uid.is_empty()) {
+        let Some(replaced) = event.recurrence_id else {
+            continue;
+        };
+        let key = format!("{}/{}", event.uid, replaced.stamp());
+        found.remove(&key);
+        let start = event.start.filter(|start| (now..=until).contains(&start.to_utc(tz)));
+        if let (false, Some(start)) = (event.cancelled, start) {
+            found.insert(key, instance(event, start));
+        }
+    }
+    found
+}
+
+fn subscription(conn: &Connection, id: i64) -> Result<Option<Subscription>, rusqlite::Error> {
+    conn.query_row(
+        "SELECT s.id, s.user_id, u.telegram_id, u.tenant, s.chat_id, s.url, s.name, s.conflict_policy
+         FROM ics_subscriptions s JOIN users u ON s.user_id = u.id
+         WHERE s.id = ?",
+        params![id],
+        |row| {
+            Ok(Subscription {
+                id: row.get(0)?,
+                user_id: row.get(1)?,
+                telegram_id: row.get(2)?,
+                tenant: row.get(3)?,
+                chat_id: row.get(4)?,
+                url: crypto::open(row.get(5)?),
+                name: row.get(6)?,
+                policy: Policy::parse(&row.get::<_, String>(7)?).unwrap_or(Policy::Latest),
+            })
+        },
+    ).optional()
+}
+
+fn subscription_ids(conn: &Connection, chat_id: Option<i64>) -> Result<Vec<i64>, rusqlite::Error> {
+    let mut stmt = conn.prepare("SELECT id FROM ics_subscriptions WHERE ?1 IS NULL OR chat_id = ?1 ORDER BY id")?;
+    let ids = stmt.query_map(params![chat_id], |row| row.get(0))?;
+    ids.collect()
+}
+
+/// Приводит события подписки к календарю: создаёт новые, обновляет изменившиеся и удаляет
+/// отменённые. Прошедшие события остаются как есть. Если предстоящее событие правили и в боте,
+/// спор решает политика подписки.
+pub fn apply(conn: &Connection, subscription: &Subscription, desired: BTreeMap<String, Instance>, now: NaiveDateTime) -> Result<Changes, rusqlite::Error> {
+    in_transaction(conn, |tx| {
+        let source = subscription.source();
+        let mut desired = desired;
+        let mut changes = Changes::default();
+        let stored = {
+            let mut stmt = tx.prepare(
+                "SELECT s.remote_id, s.event_id, s.sequence, s.etag, s.local_hash,
+                        e.status, e.event_utc, e.event_time, e.end_time, e.text, e.updated_at
+                 FROM sync_state s JOIN events e ON s.event_id = e.id
+                 WHERE s.source = ?",
+            )?;
+            let stored = stmt
+                .query_map(params![source], |row| {
+                    let state = State { event_id: row.get(1)?, sequence: row.get(2)?, etag: row.get(3)?, local_hash: row.get(4)? };
+                    let updated_at = row.get::<_, Option<String>>(10)?.and_then(|at| NaiveDateTime::parse_from_str(&at, UTC_FORMAT).ok());
+                    Ok((
+                        row.get::<_, String>(0)?,
+                        state,
+                        row.get::<_, String>(5)?,
+                        row.get::<_, Option<String>>(6)?,
+                        (row.get::<_, String>(7)?, row.get::<_, Option<String>>(8)?, crypto::open(row.get(9)?)),
+                        updated_at,
+                    ))
+                })?
+                .collect::<Result<Vec<_>, _>>()?;
+            stored
+        };
+
+        let now_utc = now.format(UTC_FORMAT).to_string();
+        for (key, mut state, status, event_utc, (event_time, end_time, text), updated_at) in stored {
+            let upcoming = status == "pending" && event_utc.is_some_and(|utc| utc > now_utc);
+            let local = sync::fingerprint(&event_time, end_time.as_deref(), &text);
+            let edited_here = local != state.local_hash;
+            match desired.remove(&key) {
+                // Календарь не менялся или пришла устаревшая копия
+                Some(instance) if !upcoming || instance.fingerprint() == state.etag || instance.sequence < state.sequence => {}
+                Some(instance) => {
+                    // Если в боте сделали ту же правку, спорить не о чем
+                    let conflicting = edited_here && local != instance.fingerprint();
+                    let resolution = if conflicting { subscription.policy.resolve(instance.modified, updated_at) } else { Resolution::Remote };
+                    (state.etag, state.sequence) = (instance.fingerprint(), instance.sequence);
+                    match resolution {
+                        Resolution::Remote => {
+                            update_event(tx, state.event_id, &instance.text, &instance.event_time)?;
+                            set_event_end(tx, state.event_id, instance.end_time.as_deref())?;
+                            state.local_hash = instance.fingerprint();
+                            changes.updated += 1;
+                        }
+                        Resolution::Local => changes.kept += 1,
+                        Resolution::Ask => changes.conflicts.extend(sync::record_conflict(tx, &source, &key, state.event_id, Some(&instance.remote()))?),
+                    }
+                    sync::save(tx, &source, &key, &state)?;
+                }
+                None if upcoming => {
+                    // Время отмены календарь не сообщает: при «побеждает последнее» она побеждает
+                    let resolution = if edited_here { subscription.policy.resolve(None, updated_at) } else { Resolution::Remote };
+                    match resolution {
+                        Resolution::Remote => {
+                            audit::record(tx, state.event_id, None, "delete", Some((&event_time, &text)), None)?;
+                            tx.execute("DELETE FROM events WHERE id = ?", params![state.event_id])?;
+                            changes.cancelled += 1;
+                        }
+                        // Событие остаётся, но больше не связано с календарём
+                        Resolution::Local => {
+                            sync::forget(tx, &source, &key)?;
+                            changes.kept += 1;
+                        }
+                        Resolution::Ask => changes.conflicts.extend(sync::record_conflict(tx, &source, &key, state.event_id, None)?),
+                    }
+                }
+                // Прошедшее событие выпало из окна: связь больше не нужна, само событие остаётся
+                None => sync::forget(tx, &source, &key)?,
+            }
+        }
+
+        for (key, instance) in desired {
+            let event_id = insert_event(tx, &subscription.tenant, subscription.user_id, subscription.chat_id, &instance.text, &instance.event_time)?;
+            set_event_end(tx, event_id, instance.end_time.as_deref())?;
+            let state = State { event_id, sequence: instance.sequence, etag: instance.fingerprint(), local_hash: instance.fingerprint() };
+            sync::save(tx, &source, &key, &state)?;
+            changes.added += 1;
+        }
+        tx.execute(
+            "UPDATE ics_subscriptions SET refreshed_at = CURRENT_TIMESTAMP, last_error = NULL WHERE id = ?",
+            params![subscription.id],
+        )?;
+        Ok(changes)
+    })
+}
+
+/// Перечитывает календарь подписки. `notify` — сообщить в чат о переносах и отменах.
+async fn refresh(db: &Db, subscription_id: i64, notify: bool) -> Result<Changes, String> {
+    let subscription = db.call(move |conn| subscription(conn, subscription_id))
+        .await
+        .map_err(|e| e.to_string())?
+        .ok_or("subscription not found")?;
+    let calendar = match fetch(&subscription.url).await {
+        Ok(calendar) => calendar,
+        Err(e) => {
+            let error = e.clone();
+            db.call(move |conn| {
+                conn.execute("UPDATE ics_subscriptions SET last_error = ? WHERE id = ?", params![error, subscription_id])
+            }).await.map_err(|e| e.to_string())?;
+            return Err(e);
+        }
+    };
+
+    db.call(move |conn| {
+        let now = Utc::now().naive_utc();
+        let tz = timezone::user_zone(conn, subscription.user_id)?;
+        let changes = apply(conn, &subscription, instances(&calendar.events, now, tz), now)?;
+        let settings = settings::resolve(conn, subscription.telegram_id, subscription.chat_id)?;
+        if notify && changes.updated + changes.cancelled + changes.kept > 0 {
+            let text = tf(settings.lang, "subscription_changed", &[
+                ("name", &subscription.name),
+                ("updated", &changes.updated),
+                ("cancelled", &changes.cancelled),
+                ("added", &changes.added),
+                ("kept", &changes.kept),
+            ]);
+            let message = Outgoing { tenant: subscription.tenant.clone(), chat_id: subscription.chat_id, text, reply_markup: None, reminder: None };
+            outbox::push(conn, &message, now)?;
+        }
+        for conflict_id in &changes.conflicts {
+            if let Some((text, keyboard)) = sync::prompt(conn, *conflict_id, &subscription.name, &settings)? {
+                let message = Outgoing { tenant: subscription.tenant.clone(), chat_id: subscription.chat_id, text, reply_markup: Some(keyboard), reminder: None };
+                outbox::push(conn, &message, now)?;
+            }
+        }
+        Ok(changes)
+    }).await.map_err(|e| e.to_string())
+}
+
+/// Фоновое обновление всех подписок раз в `ICS_REFRESH_MINUTES`.
+pub async fn run(db: Db) {
+    loop {
+        if !shards::holds_task(&db, "subscriptions", refresh_interval()).await {
+            tokio::time::sleep(refresh_interval()).await;
+            continue;
+        }
+        match db.call(|conn| subscription_ids(conn, None)).await {
+            Ok(ids) => {
+                for id in ids {
+                    match refresh(&db, id, true).await {
+                        Ok(changes) if changes != Changes::default() => log::info!("Subscription {} refreshed: {:?}", id, changes),
+                        Ok(_) => {}
+                        Err(e) => log::error!("Failed to refresh subscription {}: {}", id, e),
+                    }
+                }
+            }
+            Err(e) => log::error!("Failed to list subscriptions: {}", e),
+        }
+        tokio::time::sleep(refresh_interval()).await;
+    }
+}
+
+/// Убирает подписку вместе с её ещё не наступившими событиями.
+fn remove(conn: &Connection, chat_id: i64, subscription_id: i64) -> Result<bool, rusqlite::Error> {
+    in_transaction(conn, |tx| {
+        let exists = tx.query_row(
+            "SELECT 1 FROM ics_subscriptions WHERE id = ? AND chat_id = ?",
+            params![subscription_id, chat_id],
+            |_| Ok(()),
+        ).optional()?.is_some();
+        if !exists {
+            return Ok(false);
+        }
+        tx.execute(
+            "DELETE FROM events WHERE status = 'pending' AND id IN (SELECT event_id FROM sync_state WHERE source = ?)",
+            params![source(subscription_id)],
+        )?;
+        sync::forget_source(tx, &source(subscription_id))?;
+        tx.execute("DELETE FROM ics_subscriptions WHERE id = ?", params![subscription_id])?;
+        Ok(true)
+    })
+}
+
+fn describe(conn: &Connection, chat_id: i64, settings: &Settings) -> Result<String, rusqlite::Error> {
+    let lang = settings.lang;
+    let mut stmt = conn.prepare("SELECT id, name, refreshed_at, last_error, conflict_policy FROM ics_subscriptions WHERE chat_id = ? ORDER BY id")?;
+    let lines = stmt
+        .query_map(params![chat_id], |row| {
+            let (id, name, refreshed, error) =
+                (row.get::<_, i64>(0)?, row.get::<_, String>(1)?, row.get::<_, Option<String>>(2)?, row.get::<_, Option<String>>(3)?);
+            let refreshed = refreshed.unwrap_or_else(|| t(lang, "token_never_used"));
+            let policy = Policy::parse(&row.get::<_, String>(4)?).unwrap_or(Policy::Latest).describe(lang);
+            let mut line = tf(lang, "subscription_line", &[("id", &id), ("name", &name), ("refreshed", &refreshed), ("policy", &policy)]);
+            if let Some(error) = error {
+                line.push_str(&tf(lang, "subscription_error", &[("error", &error)]));
+            }
+            Ok(line)
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(if lines.is_empty() { t(lang, "subscription_list_empty") } else { lines.join("\n") })
+}
+
+/// `/subscribe <ссылка .ics> [название]`, `/subscribe list`, `/subscribe remove <id>`,
+/// `/subscribe conflicts <id> latest|local|ask` — события из чужого календаря в этом чате.
+pub async fn handle_subscribe_command(bot: &Bot, msg: &Message, db: &Db, args: &str, settings: &Settings) -> ResponseResult<()> {
+    let lang = settings.lang;
+    let Some(user) = msg.from() else {
+        return Ok(());
+    };
+    let chat_id = msg.chat.id.0;
+    let (action, rest) = args.split_once(char::is_whitespace).unwrap_or((args, ""));
+    match action {
+        "" => {
+            bot.send_message(msg.chat.id, t(lang, "subscription_usage")).await?;
+            return Ok(());
+        }
+        "list" => {
+            let settings = *settings;
+            let text = db.call(move |conn| describe(conn, chat_id, &settings)).await.map_err(DatabaseError)?;
+            chunks::send(bot, msg.chat.id, text).await?;
+            return Ok(());
+        }
+        _ => {}
+    }
+    if !groups::can_manage_events(bot, msg, db).await? {
+        return groups::reply_not_allowed(bot, msg, db).await;
+    }
+
+    if action == "remove" {
+        let response = match rest.trim().trim_start_matches('#').parse::<i64>() {
+            Ok(id) => {
+                let removed = db.call(move |conn| remove(conn, chat_id, id)).await.map_err(DatabaseError)?;
+                tf(lang, if removed { "subscription_removed" } else { "subscription_not_found" }, &[("id", &id)])
+            }
+            Err(_) => t(lang, "subscription_usage"),
+        };
+        bot.send_message(msg.chat.id, response).await?;
+        return Ok(());
+    }
+    if action == "conflicts" {
+        let mut parts = rest.split_whitespace();
+        let id = parts.next().and_then(|id| id.trim_start_matches('#').parse::<i64>().ok());
+        let response = match (id, parts.next().and_then(Policy::parse)) {
+            (Some(id), Some(policy)) => {
+                let changed = db.call(move |conn| {
+                    conn.execute(
+                        "UPDATE ics_subscriptions SET conflict_policy = ? WHERE id = ? AND chat_id = ?",
+                        params![policy.code(), id, chat_id],
+                    )
+                }).await.map_err(DatabaseError)?;
+                if changed > 0 {
+                    tf(lang, "subscription_policy_set", &[("id", &id), ("policy", &policy.describe(lang))])
+                } else {
+                    tf(lang, "subscription_not_found", &[("id", &id)])
+                }
+            }
+            _ => t(lang, "subscription_usage"),
+        };
+        bot.send_message(msg.chat.id, response).await?;
+        return Ok(());
+    }
+
+    let Some(url) = normalize_url(action) else {
+        bot.send_message(msg.chat.id, t(lang, "subscription_usage")).await?;
+        return Ok(());
+    };
+    let count = db.call(move |conn| {
+        conn.query_row("SELECT COUNT(*) FROM ics_subscriptions WHERE chat_id = ?", params![chat_id], |row| row.get::<_, i64>(0))
+    }).await.map_err(DatabaseError)?;
+    if count >= MAX_SUBSCRIPTIONS {
+        bot.send_message(msg.chat.id, tf(lang, "subscription_limit", &[("limit", &MAX_SUBSCRIPTIONS)])).await?;
+        return Ok(());
+    }
+    let calendar = match fetch(url.as_str()).await {
+        Ok(calendar) => calendar,
+        Err(e) => {
+            log::info!("Calendar subscription rejected: {}", e);
+            bot.send_message(msg.chat.id, tf(lang, "subscription_failed", &[("error", &e)])).await?;
+            return Ok(());
+        }
+    };
+
+    let name = Some(rest.trim().to_string())
+        .filter(|name| !name.is_empty())
+        .or(calendar.name)
+        .unwrap_or_else(|| url.host_str().unwrap_or_default().to_string());
+    let (telegram_id, username, tenant) = (user.id.0 as i64, user.username.clone(), tenants::name_of(bot));
+    let (stored_url, stored_name) = (crypto::seal(url.as_str()), name.clone());
+    let subscription_id = db.call(move |conn| {
+        let user_id = ensure_user_exists(conn, tenant, telegram_id, username)?;
+        conn.execute(
+            "INSERT INTO ics_subscriptions (user_id, chat_id, url, name) VALUES (?, ?, ?, ?)",
+            params![user_id, chat_id, stored_url, stored_name],
+        )?;
+        Ok(conn.last_insert_rowid())
+    }).await.map_err(DatabaseError)?;
+    log::info!("User {} subscribed chat {} to calendar {}", telegram_id, chat_id, subscription_id);
+
+    let response = match refresh(db, subscription_id, false).await {
+        Ok(changes) => tf(lang, "subscription_added", &[("id", &subscription_id), ("name", &name), ("count", &changes.added)]),
+        Err(e) => tf(lang, "subscription_failed", &[("error", &e)]),
+    };
+    chunks::send(bot, msg.chat.id, response).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+    use crate::storage::tests::memory_db;
+
+    const CALENDAR: &str = "BEGIN:VCALENDAR\r\nVERSION:2.0\r\nX-WR-CALNAME:Пары\r\n\
+        BEGIN:VEVENT\r\nUID:lecture\r\nSUMMARY:Матанализ\r\nLOCATION:Ауд. 101\r\n\
+        DTSTART:20300107T070000Z\r\nDTEND:20300107T083000Z\r\n\
+        RRULE:FREQ=WEEKLY;BYDAY=MO,WE;COUNT=6\r\nEXDATE:20300109T070000Z\r\n\
+        BEGIN:VALARM\r\nTRIGGER:-PT10M\r\nDESCRIPTION:не то\r\nEND:VALARM\r\nEND:VEVENT\r\n\
+        BEGIN:VEVENT\r\nUID:lecture\r\nRECURRENCE-ID:20300114T070000Z\r\nSUMMARY:Матанализ\\, перенос\r\n\
+        DTSTART:20300114T090000Z\r\nDTEND:20300114T103000Z\r\nSEQUENCE:1\r\nEND:VEVENT\r\n\
+        BEGIN:VEVENT\r\nUID:exam\r\nSUMMARY:Экзамен\r\nDTSTART;VALUE=DATE:20300120\r\nSTATUS:CANCELLED\r\nEND:VEVENT\r\n\
+        BEGIN:VEVENT\r\nUID:party\r\nSUMMARY:Посвящение в студенты с очень длинным названием, кот\r\n \
+        орое перенесено на вторую строку\r\nDTSTART;TZID=Europe/Moscow:20300110T180000\r\nEND:VEVENT\r\nEND:VCALENDAR\r\n";
+
+    fn now() -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2030, 1, 6).unwrap().and_hms_opt(0, 0, 0).unwrap()
+    }
+
+    #[test]
+    fn calendar_expands_into_instances() {
+        let calendar = ics::parse_calendar(CALENDAR).unwrap();
+        assert_eq!(calendar.name.as_deref(), Some("Пары"));
+        let found = instances(&calendar.events, now(), Some(chrono_tz::UTC));
+        let summary = found
+            .iter()
+            .map(|(key, instance)| (key.as_str(), instance.event_time.as_str(), instance.end_time.as_deref(), instance.text.lines().next().unwrap()))
+            .collect::<Vec<_>>();
+        assert_eq!(summary, vec![
+            ("lecture/20300107T070000", "07.01.2030 07:00", Some("07.01.2030 08:30"), "Матанализ"),
+            ("lecture/20300114T070000", "14.01.2030 09:00", Some("14.01.2030 10:30"), "Матанализ, перенос"),
+            ("lecture/20300116T070000", "16.01.2030 07:00", Some("16.01.2030 08:30"), "Матанализ"),
+            ("lecture/20300121T070000", "21.01.2030 07:00", Some("21.01.2030 08:30"), "Матанализ"),
+            ("lecture/20300123T070000", "23.01.2030 07:00", Some("23.01.2030 08:30"), "Матанализ"),
+            ("party", "10.01.2030 15:00", None, "Посвящение в студенты с очень длинным названием, которое перенесено на вторую строку"),
+        ]);
+        assert_eq!(found["lecture/20300107T070000"].text, "Матанализ\n📍 Ауд. 101");
+    }
+
+    #[tokio::test]
+    async fn refresh_adds_moves_and_cancels_events() {
+        let db = memory_db();
+        let (first, second, third, times) = db.call(|conn| {
+            let user_id = ensure_user_exists(conn, tenants::DEFAULT, 42, None)?;
+            conn.execute("UPDATE users SET timezone = 'UTC' WHERE id = ?", params![user_id])?;
+            conn.execute("INSERT INTO ics_subscriptions (user_id, chat_id, url, name) VALUES (?, 42, 'https://example.com/a.ics', 'Пары')", params![user_id])?;
+            let subscription = subscription(conn, conn.last_insert_rowid())?.unwrap();
+            let instance = |time: &str| Instance { text: "Пара".to_string(), event_time: time.to_string(), end_time: None, sequence: 0, modified: None };
+            let calendar = |entries: &[(&str, &str)]| entries.iter().map(|(key, time)| (key.to_string(), instance(time))).collect::<BTreeMap<_, _>>();
+
+            let first = apply(conn, &subscription, calendar(&[("a", "07.01.2030 10:00"), ("b", "08.01.2030 10:00")]), now())?;
+            let second = apply(conn, &subscription, calendar(&[("a", "07.01.2030 12:00"), ("b", "08.01.2030 10:00")]), now())?;
+            let third = apply(conn, &subscription, calendar(&[("a", "07.01.2030 12:00"), ("c", "09.01.2030 10:00")]), now())?;
+            let mut stmt = conn.prepare("SELECT event_time FROM events ORDER BY event_time")?;
+            let times = stmt.query_map([], |row| row.get::<_, String>(0))?.collect::<Result<Vec<_>, _>>()?;
+            Ok((first, second, third, times))
+        }).await.unwrap();
+        assert_eq!(first, Changes { added: 2, ..Changes::default() });
+        assert_eq!(second, Changes { updated: 1, ..Changes::default() });
+        assert_eq!(third, Changes { added: 1, cancelled: 1, ..Changes::default() });
+        assert_eq!(times, vec!["07.01.2030 12:00", "09.01.2030 10:00"]);
+    }
+
+    #[tokio::test]
+    async fn local_edits_follow_the_conflict_policy() {
+        let db = memory_db();
+        let (latest, local, asked, asked_again, times) = db.call(|conn| {
+            let user_id = ensure_user_exists(conn, tenants::DEFAULT, 42, None)?;
+            conn.execute("UPDATE users SET timezone = 'UTC' WHERE id = ?", params![user_id])?;
+            conn.execute("INSERT INTO ics_subscriptions (user_id, chat_id, url, name) VALUES (?, 42, 'https://example.com/a.ics', 'Пары')", params![user_id])?;
+            let id = conn.last_insert_rowid();
+            let instance = |time: &str| Instance { text: "Пара".to_string(), event_time: time.to_string(), end_time: None, sequence: 0, modified: None };
+            let calendar = |time: &str| BTreeMap::from([("a".to_string(), instance(time))]);
+            let event_id = || conn.query_row("SELECT id FROM events", [], |row| row.get::<_, i64>(0));
+
+            apply(conn, &subscription(conn, id)?.unwrap(), calendar("07.01.2030 10:00"), now())?;
+            update_event(conn, event_id()?, "Пара", "07.01.2030 11:00")?;
+            // Время правки в календаре неизвестно — он новее
+            let latest = apply(conn, &subscription(conn, id)?.unwrap(), calendar("07.01.2030 12:00"), now())?;
+
+            update_event(conn, event_id()?, "Пара", "07.01.2030 11:00")?;
+            conn.execute("UPDATE ics_subscriptions SET conflict_policy = 'local' WHERE id = ?", params![id])?;
+            let local = apply(conn, &subscription(conn, id)?.unwrap(), calendar("07.01.2030 13:00"), now())?;
+
+            conn.execute("UPDATE ics_subscriptions SET conflict_policy = 'ask' WHERE id = ?", params![id])?;
+            let asked = apply(conn, &subscription(conn, id)?.unwrap(), calendar("07.01.2030 14:00"), now())?;
+            let asked_again = apply(conn, &subscription(conn, id)?.unwrap(), calendar("07.01.2030 15:00"), now())?;
+            let mut stmt = conn.prepare("SELECT event_time FROM events UNION ALL SELECT remote_time FROM sync_conflicts")?;
+            let times = stmt.query_map([], |row| row.get::<_, String>(0))?.collect::<Result<Vec<_>, _>>()?;
+            Ok((latest, local, asked, asked_again, times))
+        }).await.unwrap();
+        assert_eq!(latest, Changes { updated: 1, ..Changes::default() });
+        assert_eq!(local, Changes { kept: 1, ..Changes::default() });
+        assert_eq!(asked.conflicts.len(), 1);
+        assert_eq!(asked_again, Changes::default());
+        assert_eq!(times, vec!["07.01.2030 11:00", "07.01.2030 15:00"]);
+    }
+}
@@ -0,0 +1,92 @@
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+use serde_json::{Value, json};
+
+/// Пауза перед первым перезапуском; после каждого падения подряд удваивается.
+const INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_BACKOFF: Duration = Duration::from_secs(300);
+/// Задача, проработавшая столько без падений, снова перезапускается без задержки.
+const STABLE_AFTER: Duration = Duration::from_secs(60);
+
+/// Состояние фоновой задачи для проверки здоровья.
+#[derive(Debug, Clone, Default)]
+struct TaskState {
+    running: bool,
+    restarts: u32,
+    last_error: Option<String>,
+}
+
+fn registry() -> &'static Mutex<BTreeMap<&'static str, TaskState>> {
+    static TASKS: OnceLock<Mutex<BTreeMap<&'static str, TaskState>>> = OnceLock::new();
+    TASKS.get_or_init(Default::default)
+}
+
+fn update(name: &'static str, apply: impl FnOnce(&mut TaskState)) {
+    let mut tasks = registry().lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    apply(tasks.entry(name).or_default());
+}
+
+/// Текст паники из `JoinError`: `panic!` передаёт `&str` или `String`.
+fn panic_message(error: tokio::task::JoinError) -> String {
+    if error.is_cancelled() {
+        return "cancelled".to_string();
+    }
+    let payload = error.into_panic();
+    payload
+        .downcast_ref::<&str>()
+        .map(|message| message.to_string())
+        .or_else(|| payload.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "unknown panic".to_string())
+}
+
+/// Запускает фоновую задачу и перезапускает её после паники или выхода.
+/// Паузы между перезапусками растут, чтобы падающая сразу задача не забивала лог.
+pub fn spawn<F, Fut>(name: &'static str, task: F)
+where
+    F: Fn() -> Fut + Send + 'static,
+    Fut: Future<Output = ()> + Send + 'static,
+{
+    tokio::spawn(async move {
+        let mut backoff = INITIAL_BACKOFF;
+        loop {
+            update(name, |state| state.running = true);
+            let started = Instant::now();
+            let error = match tokio::spawn(task()).await {
+                Ok(()) => "stopped".to_string(),
+                Err(e) => panic_message(e),
+            };
+            if started.elapsed() >= STABLE_AFTER {
+                backoff = INITIAL_BACKOFF;
+            }
+            log::error!("Background task {} failed: {}; restarting in {:?}", name, error, backoff);
+            update(name, |state| {
+                state.running = false;
+                state.restarts += 1;
+                state.last_error = Some(error);
+            });
+            tokio::time::sleep(backoff).await;
+            backoff = (backoff * 2).min(MAX_BACKOFF);
+        }
+    });
+}
+
+/// Работают ли все фоновые задачи, и их состояние для ответа проверки здоровья.
+pub fn health() -> (bool, Value) {
+    let tasks = registry().lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    let healthy = tasks.values().all(|state| state.running);
+    let report = tasks
+        .iter()
+        .map(|(name, state)| {
+            let report = json!({
+                "running": state.running,
+                "restarts": state.restarts,
+                "last_error": state.last_error,
+            });
+            (name.to_string(), report)
+        })
+        .collect::<serde_json::Map<_, _>>();
+    (healthy, Value::Object(report))
+}
@@ -0,0 +1,346 @@
+//! Состояние синхронизации с внешними календарями и конфликты: событие изменили и в боте,
+//! и в источнике. Источник — строка вида `ics:<id подписки>`, событие в нём — его ключ.
+
+use teloxide::prelude::*;
+use teloxide::types::{InlineKeyboardButton, InlineKeyboardMarkup};
+use chrono::NaiveDateTime;
+use rusqlite::{Connection, params, OptionalExtension};
+use sha2::{Digest, Sha256};
+
+use crate::audit;
+use crate::i18n::{Lang, t, tf};
+use crate::settings::{self, Settings};
+use crate::{DatabaseError, Db, crypto, in_transaction, parse_event_time, set_event_end, update_event};
+
+/// Что делать, если событие изменили с обеих сторон.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Policy {
+    /// Побеждает более позднее изменение.
+    Latest,
+    /// Правки в боте важнее источника.
+    Local,
+    /// Спросить кнопками.
+    Ask,
+}
+
+impl Policy {
+    pub fn code(self) -> &'static str {
+        match self {
+            Policy::Latest => "latest",
+            Policy::Local => "local",
+            Policy::Ask => "ask",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.to_lowercase().as_str() {
+            "latest" | "последнее" => Some(Policy::Latest),
+            "local" | "моё" | "мое" => Some(Policy::Local),
+            "ask" | "спросить" => Some(Policy::Ask),
+            _ => None,
+        }
+    }
+
+    pub fn describe(self, lang: Lang) -> String {
+        t(lang, match self {
+            Policy::Latest => "sync_policy_latest",
+            Policy::Local => "sync_policy_local",
+            Policy::Ask => "sync_policy_ask",
+        })
+    }
+
+    /// Решение по конфликту. Время правок — в UTC; если в источнике оно не указано, источник
+    /// считается свежее: об изменении мы узнали только сейчас.
+    pub fn resolve(self, remote_modified: Option<NaiveDateTime>, local_modified: Option<NaiveDateTime>) -> Resolution {
+        match self {
+            Policy::Local => Resolution::Local,
+            Policy::Ask => Resolution::Ask,
+            Policy::Latest => match (remote_modified, local_modified) {
+                (Some(remote), Some(local)) if local > remote => Resolution::Local,
+                _ => Resolution::Remote,
+            },
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Resolution {
+    Remote,
+    Local,
+    Ask,
+}
+
+/// Отпечаток версии события: по нему видно, менялось ли оно с прошлой синхронизации.
+pub fn fingerprint(event_time: &str, end_time: Option<&str>, text: &str) -> String {
+    let data = format!("{}\n{}\n{}", event_time, end_time.unwrap_or_default(), text);
+    hex::encode(Sha256::digest(data.as_bytes()))
+}
+
+/// Что известно о событии источника после последней синхронизации.
+#[derive(Debug, Clone, PartialEq)]
+pub struct State {
+    pub event_id: i64,
+    /// `SEQUENCE` последней увиденной версии: более старые копии источника не применяются.
+    pub sequence: i64,
+    /// Отпечаток последней увиденной версии в источнике; у CalDAV сюда ляжет ETag сервера.
+    pub etag: String,
+    /// Отпечаток события в боте после синхронизации: если он другой, событие правили здесь.
+    pub local_hash: String,
+}
+
+/// Версия события в источнике, ждущая решения.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Remote {
+    pub text: String,
+    pub event_time: String,
+    pub end_time: Option<String>,
+}
+
+pub fn init_tables(conn: &Connection) -> Result<(), rusqlite::Error> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS sync_state (
+            source TEXT NOT NULL,
+            remote_id TEXT NOT NULL,
+            event_id INTEGER NOT NULL,
+            sequence INTEGER NOT NULL DEFAULT 0,
+            etag TEXT NOT NULL,
+            local_hash TEXT NOT NULL,
+            synced_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            PRIMARY KEY (source, remote_id),
+            FOREIGN KEY(event_id) REFERENCES events(id) ON DELETE CASCADE
+        )",
+        [],
+    )?;
+    // remote_time NULL — событие в источнике отменили
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS sync_conflicts (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            source TEXT NOT NULL,
+            remote_id TEXT NOT NULL,
+            event_id INTEGER NOT NULL,
+            remote_time TEXT,
+            remote_end TEXT,
+            remote_text TEXT,
+            created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            UNIQUE (source, remote_id),
+            FOREIGN KEY(event_id) REFERENCES events(id) ON DELETE CASCADE
+        )",
+        [],
+    )?;
+
+    // Связи подписок с событиями раньше жили в своей таблице
+    let legacy = conn.query_row(
+        "SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = 'ics_subscription_events'",
+        [],
+        |_| Ok(()),
+    ).optional()?.is_some();
+    if legacy {
+        conn.execute(
+            "INSERT OR IGNORE INTO sync_state (source, remote_id, event_id, sequence, etag, local_hash)
+             SELECT 'ics:' || subscription_id, instance, event_id, sequence, fingerprint, fingerprint FROM ics_subscription_events",
+            [],
+        )?;
+        conn.execute("DROP TABLE ics_subscription_events", [])?;
+    }
+    Ok(())
+}
+
+pub fn save(conn: &Connection, source: &str, remote_id: &str, state: &State) -> Result<(), rusqlite::Error> {
+    conn.execute(
+        "INSERT INTO sync_state (source, remote_id, event_id, sequence, etag, local_hash) VALUES (?, ?, ?, ?, ?, ?)
+         ON CONFLICT(source, remote_id) DO UPDATE SET
+            event_id = excluded.event_id,
+            sequence = excluded.sequence,
+            etag = excluded.etag,
+            local_hash = excluded.local_hash,
+            synced_at = CURRENT_TIMESTAMP",
+        params![source, remote_id, state.event_id, state.sequence, state.etag, state.local_hash],
+    )?;
+    Ok(())
+}
+
+/// Событие больше не связано с источником; само оно остаётся.
+pub fn forget(conn: &Connection, source: &str, remote_id: &str) -> Result<(), rusqlite::Error> {
+    conn.execute("DELETE FROM sync_state WHERE source = ? AND remote_id = ?", params![source, remote_id])?;
+    conn.execute("DELETE FROM sync_conflicts WHERE source = ? AND remote_id = ?", params![source, remote_id])?;
+    Ok(())
+}
+
+pub fn forget_source(conn: &Connection, source: &str) -> Result<(), rusqlite::Error> {
+    conn.execute("DELETE FROM sync_state WHERE source = ?", params![source])?;
+    conn.execute("DELETE FROM sync_conflicts WHERE source = ?", params![source])?;
+    Ok(())
+}
+
+/// Запоминает конфликт; `remote` `None` — событие в источнике отменили. Возвращает id нового
+/// конфликта, о котором нужно спросить; если вопрос уже задан, в нём просто обновляется версия источника.
+pub fn record_conflict(conn: &Connection, source: &str, remote_id: &str, event_id: i64, remote: Option<&Remote>) -> Result<Option<i64>, rusqlite::Error> {
+    let pending = conn.query_row(
+        "SELECT id FROM sync_conflicts WHERE source = ? AND remote_id = ?",
+        params![source, remote_id],
+        |row| row.get::<_, i64>(0),
+    ).optional()?;
+    let (time, end, text) = (remote.map(|r| r.event_time.as_str()), remote.and_then(|r| r.end_time.as_deref()), remote.map(|r| crypto::seal(&r.text)));
+    match pending {
+        Some(id) => {
+            conn.execute(
+                "UPDATE sync_conflicts SET event_id = ?, remote_time = ?, remote_end = ?, remote_text = ? WHERE id = ?",
+                params![event_id, time, end, text, id],
+            )?;
+            Ok(None)
+        }
+        None => {
+            conn.execute(
+                "INSERT INTO sync_conflicts (source, remote_id, event_id, remote_time, remote_end, remote_text) VALUES (?, ?, ?, ?, ?, ?)",
+                params![source, remote_id, event_id, time, end, text],
+            )?;
+            Ok(Some(conn.last_insert_rowid()))
+        }
+    }
+}
+
+struct Conflict {
+    source: String,
+    remote_id: String,
+    event_id: i64,
+    owner: i64,
+    local: (String, String),
+    remote: Option<Remote>,
+}
+
+fn conflict(conn: &Connection, conflict_id: i64) -> Result<Option<Conflict>, rusqlite::Error> {
+    conn.query_row(
+        "SELECT c.source, c.remote_id, c.event_id, u.telegram_id, e.event_time, e.text, c.remote_time, c.remote_end, c.remote_text
+         FROM sync_conflicts c
+         JOIN events e ON c.event_id = e.id
+         JOIN users u ON e.user_id = u.id
+         WHERE c.id = ?",
+        params![conflict_id],
+        |row| {
+            let remote_time = row.get::<_, Option<String>>(6)?;
+            let remote_text = row.get::<_, Option<String>>(8)?;
+            Ok(Conflict {
+                source: row.get(0)?,
+                remote_id: row.get(1)?,
+                event_id: row.get(2)?,
+                owner: row.get(3)?,
+                local: (row.get(4)?, crypto::open(row.get(5)?)),
+                remote: remote_time.map(|event_time| Remote {
+                    text: remote_text.map(crypto::open).unwrap_or_default(),
+                    event_time,
+                    end_time: row.get(7).ok().flatten(),
+                }),
+            })
+        },
+    ).optional()
+}
+
+/// Вопрос о конфликте с кнопками выбора. `origin` — как источник называется для пользователя.
+pub fn prompt(conn: &Connection, conflict_id: i64, origin: &str, settings: &Settings) -> Result<Option<(String, InlineKeyboardMarkup)>, rusqlite::Error> {
+    let lang = settings.lang;
+    let Some(conflict) = conflict(conn, conflict_id)? else {
+        return Ok(None);
+    };
+    let version = |time: &str, text: &str| {
+        let time = parse_event_time(time).map_or_else(|| time.to_string(), |time| settings.format_datetime(time));
+        format!("{} {}", time, text.lines().next().unwrap_or_default())
+    };
+    let remote = conflict.remote.as_ref().map_or_else(|| t(lang, "sync_remote_cancelled"), |remote| version(&remote.event_time, &remote.text));
+    let text = tf(lang, "sync_conflict", &[
+        ("origin", &origin),
+        ("local", &version(&conflict.local.0, &conflict.local.1)),
+        ("remote", &remote),
+    ]);
+    let keyboard = InlineKeyboardMarkup::new(vec![vec![
+        InlineKeyboardButton::callback(t(lang, "sync_keep_local"), format!("sync:local:{}", conflict_id)),
+        InlineKeyboardButton::callback(t(lang, "sync_take_remote"), format!("sync:remote:{}", conflict_id)),
+    ]]);
+    Ok(Some((text, keyboard)))
+}
+
+/// Решает конфликт в пользу бота или источника. `Err` — ключ сообщения, почему не получилось.
+fn settle(conn: &Connection, conflict_id: i64, telegram_id: i64, keep_local: bool) -> Result<Result<&'static str, &'static str>, rusqlite::Error> {
+    in_transaction(conn, |tx| {
+        let Some(conflict) = conflict(tx, conflict_id)? else {
+            return Ok(Err("sync_conflict_expired"));
+        };
+        if conflict.owner != telegram_id {
+            return Ok(Err("event_not_yours"));
+        }
+        tx.execute("DELETE FROM sync_conflicts WHERE id = ?", params![conflict_id])?;
+        let outcome = match (keep_local, conflict.remote) {
+            // Правка в боте остаётся; следующее изменение в источнике снова будет конфликтом
+            (true, Some(_)) => "sync_kept_local",
+            // Отменённое в источнике событие остаётся, но больше с ним не связано
+            (true, None) => {
+                forget(tx, &conflict.source, &conflict.remote_id)?;
+                "sync_kept_local"
+            }
+            (false, Some(remote)) => {
+                update_event(tx, conflict.event_id, &remote.text, &remote.event_time)?;
+                set_event_end(tx, conflict.event_id, remote.end_time.as_deref())?;
+                let hash = fingerprint(&remote.event_time, remote.end_time.as_deref(), &remote.text);
+                tx.execute(
+                    "UPDATE sync_state SET local_hash = ?, synced_at = CURRENT_TIMESTAMP WHERE source = ? AND remote_id = ?",
+                    params![hash, conflict.source, conflict.remote_id],
+                )?;
+                "sync_took_remote"
+            }
+            (false, None) => {
+                let (time, text) = &conflict.local;
+                audit::record(tx, conflict.event_id, None, "delete", Some((time, text)), None)?;
+                tx.execute("DELETE FROM events WHERE id = ?", params![conflict.event_id])?;
+                "sync_took_remote"
+            }
+        };
+        Ok(Ok(outcome))
+    })
+}
+
+/// Кнопки `sync:local:<id>` и `sync:remote:<id>` под вопросом о конфликте.
+pub async fn handle_callback(bot: &Bot, q: &CallbackQuery, db: &Db, args: &str) -> ResponseResult<()> {
+    let Some((action, conflict_id)) = args.split_once(':') else {
+        bot.answer_callback_query(q.id.clone()).await?;
+        return Ok(());
+    };
+    let conflict_id: i64 = conflict_id.parse().unwrap_or_default();
+    let Some(message) = q.message.as_ref() else {
+        bot.answer_callback_query(q.id.clone()).await?;
+        return Ok(());
+    };
+
+    let (telegram_id, chat_id, keep_local) = (q.from.id.0 as i64, message.chat.id.0, action == "local");
+    let (lang, outcome) = db.call(move |conn| {
+        let lang = settings::resolve(conn, telegram_id, chat_id)?.lang;
+        Ok((lang, settle(conn, conflict_id, telegram_id, keep_local)?))
+    }).await.map_err(DatabaseError)?;
+
+    match outcome {
+        Ok(key) => {
+            log::info!("User {} settled sync conflict {} (keep local: {})", telegram_id, conflict_id, keep_local);
+            bot.answer_callback_query(q.id.clone()).await?;
+            bot.edit_message_text(message.chat.id, message.id, t(lang, key)).await?;
+        }
+        Err(key) => {
+            bot.answer_callback_query(q.id.clone()).text(t(lang, key)).await?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    #[test]
+    fn latest_change_wins() {
+        let at = |hour| NaiveDate::from_ymd_opt(2030, 1, 1).unwrap().and_hms_opt(hour, 0, 0);
+        assert_eq!(Policy::Latest.resolve(at(10), at(12)), Resolution::Local);
+        assert_eq!(Policy::Latest.resolve(at(12), at(10)), Resolution::Remote);
+        assert_eq!(Policy::Latest.resolve(None, at(10)), Resolution::Remote);
+        assert_eq!(Policy::Latest.resolve(at(10), None), Resolution::Remote);
+        assert_eq!(Policy::Local.resolve(at(12), at(10)), Resolution::Local);
+        assert_eq!(Policy::Ask.resolve(None, None), Resolution::Ask);
+    }
+}
@@ -0,0 +1,141 @@
+use teloxide::prelude::*;
+use teloxide::types::{InlineKeyboardButton, InlineKeyboardMarkup};
+use rusqlite::{Connection, params, OptionalExtension};
+
+use crate::chunks;
+use crate::i18n::{t, tf, Lang};
+use crate::settings;
+use crate::{DatabaseError, Db, ensure_user_exists, in_transaction, tenants};
+
+#[derive(Debug)]
+pub struct Task {
+    pub id: i64,
+    pub text: String,
+}
+
+pub fn init_tables(conn: &Connection) -> Result<(), rusqlite::Error> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS tasks (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            user_id INTEGER NOT NULL,
+            text TEXT NOT NULL,
+            done INTEGER NOT NULL DEFAULT 0,
+            created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            done_at DATETIME,
+            FOREIGN KEY(user_id) REFERENCES users(id) ON DELETE CASCADE
+        )",
+        [],
+    )?;
+    Ok(())
+}
+
+fn save_task(conn: &Connection, user_id: i64, text: &str) -> Result<i64, rusqlite::Error> {
+    conn.execute("INSERT INTO tasks (user_id, text) VALUES (?, ?)", params![user_id, text])?;
+    Ok(conn.last_insert_rowid())
+}
+
+pub fn get_open_tasks(conn: &Connection, telegram_id: i64) -> Result<Vec<Task>, rusqlite::Error> {
+    let mut stmt = conn.prepare(
+        "SELECT t.id, t.text FROM tasks t
+         JOIN users u ON t.user_id = u.id
+         WHERE u.telegram_id = ? AND t.done = 0
+         ORDER BY t.created_at"
+    )?;
+    let tasks = stmt.query_map(params![telegram_id], |row| {
+        Ok(Task { id: row.get(0)?, text: row.get(1)? })
+    })?
+    .collect::<Result<Vec<_>, _>>()?;
+    Ok(tasks)
+}
+
+/// Отмечает задачу выполненной, если она принадлежит пользователю.
+fn complete_task(conn: &Connection, telegram_id: i64, task_id: i64) -> Result<bool, rusqlite::Error> {
+    let owned: Option<i64> = conn.query_row(
+        "SELECT t.id FROM tasks t JOIN users u ON t.user_id = u.id WHERE t.id = ? AND u.telegram_id = ?",
+        params![task_id, telegram_id],
+        |row| row.get(0),
+    ).optional()?;
+    if owned.is_none() {
+        return Ok(false);
+    }
+
+    conn.execute(
+        "UPDATE tasks SET done = 1, done_at = CURRENT_TIMESTAMP WHERE id = ?",
+        params![task_id],
+    )?;
+    Ok(true)
+}
+
+pub fn format_tasks(tasks: &[Task]) -> String {
+    tasks
+        .iter()
+        .map(|task| format!("#{} {}", task.id, task.text))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn render_list(lang: Lang, tasks: &[Task]) -> (String, InlineKeyboardMarkup) {
+    if tasks.is_empty() {
+        return (t(lang, "todos_empty"), InlineKeyboardMarkup::default());
+    }
+
+    let keyboard = InlineKeyboardMarkup::new(tasks.chunks(4).map(|row| {
+        row.iter()
+            .map(|task| InlineKeyboardButton::callback(format!("✅ #{}", task.id), format!("todo:{}", task.id)))
+            .collect::<Vec<_>>()
+    }));
+    (tf(lang, "todos_list", &[("tasks", &format_tasks(tasks))]), keyboard)
+}
+
+pub async fn handle_todo_command(bot: &Bot, msg: &Message, db: &Db, args: &str, lang: Lang) -> ResponseResult<()> {
+    if args.is_empty() {
+        bot.send_message(msg.chat.id, t(lang, "todo_usage")).await?;
+        return Ok(());
+    }
+    let user = msg.from().unwrap();
+
+    let (telegram_id, username, text) = (user.id.0 as i64, user.username.clone(), args.to_string());
+    let tenant = tenants::name_of(bot);
+    let task_id = db.call(move |conn| in_transaction(conn, |tx| {
+        let user_id = ensure_user_exists(tx, tenant, telegram_id, username)?;
+        save_task(tx, user_id, &text)
+    })).await.map_err(DatabaseError)?;
+
+    chunks::send(bot, msg.chat.id, tf(lang, "todo_saved", &[("id", &task_id), ("text", &args)])).await?;
+    Ok(())
+}
+
+pub async fn handle_todos_list(bot: &Bot, msg: &Message, db: &Db, lang: Lang) -> ResponseResult<()> {
+    let telegram_id = msg.from().unwrap().id.0 as i64;
+    let tasks = db.call(move |conn| get_open_tasks(conn, telegram_id)).await.map_err(DatabaseError)?;
+
+    let (text, keyboard) = render_list(lang, &tasks);
+    chunks::send_last(bot, msg.chat.id, text).await?.reply_markup(keyboard).await?;
+    Ok(())
+}
+
+pub async fn handle_callback(bot: &Bot, q: &CallbackQuery, db: &Db, args: &str) -> ResponseResult<()> {
+    let task_id: i64 = args.parse().unwrap_or_default();
+    let telegram_id = q.from.id.0 as i64;
+    let Some(message) = q.message.as_ref() else {
+        bot.answer_callback_query(q.id.clone()).await?;
+        return Ok(());
+    };
+
+    let chat_id = message.chat.id.0;
+    let (lang, completed, tasks) = db.call(move |conn| {
+        let lang = settings::resolve(conn, telegram_id, chat_id)?.lang;
+        let completed = complete_task(conn, telegram_id, task_id)?;
+        Ok((lang, completed, get_open_tasks(conn, telegram_id)?))
+    }).await.map_err(DatabaseError)?;
+
+    if !completed {
+        bot.answer_callback_query(q.id.clone()).text(t(lang, "todo_not_yours")).await?;
+        return Ok(());
+    }
+
+    bot.answer_callback_query(q.id.clone()).text(t(lang, "todo_done")).await?;
+    let (text, keyboard) = render_list(lang, &tasks);
+    bot.edit_message_text(message.chat.id, message.id, text).reply_markup(keyboard).await?;
+    Ok(())
+}
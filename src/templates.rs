@@ -0,0 +1,226 @@
+use teloxide::prelude::*;
+use chrono::{Datelike, Duration, NaiveDateTime, Weekday};
+use regex::Regex;
+use rusqlite::{Connection, params, OptionalExtension};
+
+use crate::chunks;
+use crate::clock;
+use crate::i18n::{t, tf};
+use crate::settings::Settings;
+use crate::timezone;
+use crate::{
+    DATE_PATTERN, DatabaseError, Db, EVENT_TIME_FORMAT, TIME_PATTERN, ensure_user_exists, event_confirmation, groups,
+    ics, in_transaction, insert_event, link_event_message, parse_time_input, resolve_event_time, tenants,
+};
+
+#[derive(Debug)]
+struct Template {
+    name: String,
+    pattern: String,
+}
+
+/// День в шаблоне: конкретная дата или относительный день, вычисляемый при использовании.
+#[derive(Debug)]
+enum TemplateDay {
+    Date(String),
+    Today,
+    Tomorrow,
+    Weekday(Weekday),
+}
+
+pub fn init_tables(conn: &Connection) -> Result<(), rusqlite::Error> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS templates (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            user_id INTEGER NOT NULL,
+            name TEXT NOT NULL,
+            pattern TEXT NOT NULL,
+            created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            UNIQUE(user_id, name),
+            FOREIGN KEY(user_id) REFERENCES users(id) ON DELETE CASCADE
+        )",
+        [],
+    )?;
+    Ok(())
+}
+
+fn parse_day(value: &str) -> Option<TemplateDay> {
+    let weekday = match value.to_lowercase().as_str() {
+        "today" | "сегодня" => return Some(TemplateDay::Today),
+        "tomorrow" | "завтра" => return Some(TemplateDay::Tomorrow),
+        "mon" | "monday" | "пн" | "понедельник" => Weekday::Mon,
+        "tue" | "tuesday" | "вт" | "вторник" => Weekday::Tue,
+        "wed" | "wednesday" | "ср" | "среда" => Weekday::Wed,
+        "thu" | "thursday" | "чт" | "четверг" => Weekday::Thu,
+        "fri" | "friday" | "пт" | "пятница" => Weekday::Fri,
+        "sat" | "saturday" | "сб" | "суббота" => Weekday::Sat,
+        "sun" | "sunday" | "вс" | "воскресенье" => Weekday::Sun,
+        _ => {
+            let date_re = Regex::new(&format!("^{}$", DATE_PATTERN)).unwrap();
+            return date_re.is_match(value).then(|| TemplateDay::Date(value.to_string()));
+        }
+    };
+    Some(TemplateDay::Weekday(weekday))
+}
+
+/// Разбирает шаблон `@[день] ЧЧ:ММ текст` и вычисляет ближайшее подходящее время от `now`.
+fn resolve_pattern(pattern: &str, settings: &Settings) -> Option<(NaiveDateTime, String)> {
+    let now = timezone::now_in(settings.timezone);
+    let re = Regex::new(&format!(r"^@(?:(\S+)\s+)?({})\s+(.+)$", TIME_PATTERN)).unwrap();
+    let captures = re.captures(pattern.trim())?;
+    let time = parse_time_input(captures.get(2)?.as_str())?;
+    let time_str = time.format("%H:%M").to_string();
+    let text = captures.get(3)?.as_str().trim().to_string();
+
+    let day = match captures.get(1) {
+        Some(day) => parse_day(day.as_str())?,
+        None => TemplateDay::Today,
+    };
+
+    let datetime = match day {
+        TemplateDay::Date(date) => resolve_event_time(&clock::SYSTEM, Some(&date), &time_str, settings)?,
+        TemplateDay::Today => {
+            // Если время сегодня уже прошло, берём завтра
+            let today = now.date().and_time(time);
+            if today > now { today } else { today + Duration::days(1) }
+        }
+        TemplateDay::Tomorrow => (now.date() + Duration::days(1)).and_time(time),
+        TemplateDay::Weekday(weekday) => {
+            let days_ahead = (weekday.num_days_from_monday() + 7 - now.weekday().num_days_from_monday()) % 7;
+            let candidate = (now.date() + Duration::days(days_ahead as i64)).and_time(time);
+            if candidate > now { candidate } else { candidate + Duration::days(7) }
+        }
+    };
+
+    Some((datetime, text))
+}
+
+fn save_template(conn: &Connection, user_id: i64, name: &str, pattern: &str) -> Result<(), rusqlite::Error> {
+    conn.execute(
+        "INSERT INTO templates (user_id, name, pattern) VALUES (?, ?, ?)
+         ON CONFLICT(user_id, name) DO UPDATE SET pattern = excluded.pattern",
+        params![user_id, name, pattern],
+    )?;
+    Ok(())
+}
+
+fn get_template(conn: &Connection, user_id: i64, name: &str) -> Result<Option<Template>, rusqlite::Error> {
+    conn.query_row(
+        "SELECT name, pattern FROM templates WHERE user_id = ? AND name = ?",
+        params![user_id, name],
+        |row| Ok(Template { name: row.get(0)?, pattern: row.get(1)? }),
+    ).optional()
+}
+
+fn get_templates(conn: &Connection, user_id: i64) -> Result<Vec<Template>, rusqlite::Error> {
+    let mut stmt = conn.prepare("SELECT name, pattern FROM templates WHERE user_id = ? ORDER BY name")?;
+    let templates = stmt.query_map(params![user_id], |row| {
+        Ok(Template { name: row.get(0)?, pattern: row.get(1)? })
+    })?
+    .collect::<Result<Vec<_>, _>>()?;
+    Ok(templates)
+}
+
+fn delete_template(conn: &Connection, user_id: i64, name: &str) -> Result<bool, rusqlite::Error> {
+    let deleted = conn.execute(
+        "DELETE FROM templates WHERE user_id = ? AND name = ?",
+        params![user_id, name],
+    )?;
+    Ok(deleted > 0)
+}
+
+pub async fn handle_templates_list(bot: &Bot, msg: &Message, db: &Db, settings: &Settings) -> ResponseResult<()> {
+    let lang = settings.lang;
+    let user = msg.from().unwrap();
+
+    let (telegram_id, username, tenant) = (user.id.0 as i64, user.username.clone(), tenants::name_of(bot));
+    let templates = db.call(move |conn| get_templates(conn, ensure_user_exists(conn, tenant, telegram_id, username)?))
+        .await
+        .map_err(DatabaseError)?;
+
+    if templates.is_empty() {
+        bot.send_message(msg.chat.id, t(lang, "templates_empty")).await?;
+        return Ok(());
+    }
+
+    let list = templates
+        .iter()
+        .map(|tpl| format!("{} - {}", tpl.name, tpl.pattern))
+        .collect::<Vec<_>>()
+        .join("\n");
+    chunks::send(bot, msg.chat.id, tf(lang, "templates_list", &[("templates", &list)])).await?;
+    Ok(())
+}
+
+pub async fn handle_template_command(bot: &Bot, msg: &Message, db: &Db, args: &str, settings: &Settings) -> ResponseResult<()> {
+    let lang = settings.lang;
+    let user = msg.from().unwrap();
+    let mut parts = args.splitn(3, char::is_whitespace);
+    let action = parts.next().unwrap_or_default();
+    let name = parts.next().unwrap_or_default().trim();
+    let pattern = parts.next().unwrap_or_default().trim();
+
+    if name.is_empty() {
+        bot.send_message(msg.chat.id, t(lang, "template_usage")).await?;
+        return Ok(());
+    }
+
+    let (telegram_id, username, tenant) = (user.id.0 as i64, user.username.clone(), tenants::name_of(bot));
+    let user_id = db.call(move |conn| ensure_user_exists(conn, tenant, telegram_id, username)).await.map_err(DatabaseError)?;
+    let (name, pattern) = (name.to_string(), pattern.to_string());
+
+    match action {
+        "save" => {
+            if resolve_pattern(&pattern, settings).is_none() {
+                bot.send_message(msg.chat.id, t(lang, "template_usage")).await?;
+                return Ok(());
+            }
+            let (template_name, template_pattern) = (name.clone(), pattern.clone());
+            db.call(move |conn| save_template(conn, user_id, &template_name, &template_pattern))
+                .await
+                .map_err(DatabaseError)?;
+            chunks::send(bot, msg.chat.id, tf(lang, "template_saved", &[("name", &name)])).await?;
+        }
+        "delete" => {
+            let template_name = name.clone();
+            let deleted = db.call(move |conn| delete_template(conn, user_id, &template_name)).await.map_err(DatabaseError)?;
+            let key = if deleted { "template_deleted" } else { "template_not_found" };
+            chunks::send(bot, msg.chat.id, tf(lang, key, &[("name", &name)])).await?;
+        }
+        "use" => {
+            if !groups::can_manage_events(bot, msg, db).await? {
+                return groups::reply_not_allowed(bot, msg, db).await;
+            }
+
+            let template_name = name.clone();
+            let template = db.call(move |conn| get_template(conn, user_id, &template_name)).await.map_err(DatabaseError)?;
+
+            let Some(template) = template else {
+                chunks::send(bot, msg.chat.id, tf(lang, "template_not_found", &[("name", &name)])).await?;
+                return Ok(());
+            };
+            let Some((event_time, text)) = resolve_pattern(&template.pattern, settings) else {
+                chunks::send(bot, msg.chat.id, tf(lang, "event_invalid_time", &[("value", &template.pattern)])).await?;
+                return Ok(());
+            };
+
+            let (chat_id, event_text) = (msg.chat.id.0, text.clone());
+            let event_id = db.call(move |conn| in_transaction(conn, |tx| {
+                insert_event(tx, tenant, user_id, chat_id, &event_text, &event_time.format(EVENT_TIME_FORMAT).to_string())
+            })).await.map_err(DatabaseError)?;
+
+            log::info!("Created event {} from template {}", event_id, template.name);
+            let confirmation = chunks::send_last(bot, msg.chat.id, event_confirmation(settings, &text, event_time)).await?
+                .reply_markup(ics::keyboard(lang, event_id))
+                .await?;
+
+            db.call(move |conn| link_event_message(conn, chat_id, confirmation.id.0, event_id, "confirmation"))
+                .await
+                .map_err(DatabaseError)?;
+        }
+        _ => {
+            bot.send_message(msg.chat.id, t(lang, "template_usage")).await?;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,82 @@
+use std::env;
+use std::sync::OnceLock;
+
+use teloxide::prelude::*;
+use rusqlite::Connection;
+
+use crate::add_column_if_missing;
+
+/// Имя бота, если задан только `TELOXIDE_TOKEN`. Им же помечены строки, созданные до появления ботов-арендаторов.
+pub const DEFAULT: &str = "default";
+
+/// Один из ботов, обслуживаемых процессом. Данные всех ботов лежат в одной базе,
+/// колонка `tenant` отмечает, через какого бота событие создано и должно быть доставлено.
+pub struct Tenant {
+    pub name: String,
+    pub bot: Bot,
+}
+
+pub fn init_tables(conn: &Connection) -> Result<(), rusqlite::Error> {
+    // Бот, через которого пользователь писал последним: туда приходит ежедневная сводка
+    add_column_if_missing(conn, "users", "tenant", "TEXT NOT NULL DEFAULT 'default'")?;
+    // Бот, который доставляет напоминания, закрывает опросы и спрашивает о привычках
+    for table in ["events", "polls", "habits"] {
+        add_column_if_missing(conn, table, "tenant", "TEXT NOT NULL DEFAULT 'default'")?;
+    }
+    Ok(())
+}
+
+static TENANTS: OnceLock<Vec<Tenant>> = OnceLock::new();
+
+/// Боты из `BOT_TOKENS` вида `ru=<токен>,en=<токен>`; без неё — единственный бот из `TELOXIDE_TOKEN`.
+pub fn all() -> &'static [Tenant] {
+    TENANTS.get_or_init(|| {
+        let tenants = match env::var("BOT_TOKENS") {
+            Ok(tokens) => tokens
+                .split(',')
+                .filter_map(|pair| {
+                    let (name, token) = pair.trim().split_once('=')?;
+                    Some(Tenant { name: name.trim().to_string(), bot: Bot::new(token.trim()) })
+                })
+                .collect(),
+            Err(_) => {
+                let token = env::var("TELOXIDE_TOKEN").expect("TELOXIDE_TOKEN не найден в .env файле");
+                vec![Tenant { name: DEFAULT.to_string(), bot: Bot::new(token) }]
+            }
+        };
+        assert!(!tenants.is_empty(), "BOT_TOKENS не содержит ни одного бота");
+        tenants
+    })
+}
+
+/// Подменяет ботов из окружения одним ботом, например направленным на поддельный Bot API в тестах.
+/// Действует, только если боты ещё не загружены.
+#[cfg(test)]
+pub fn install(bot: Bot) {
+    TENANTS.get_or_init(|| vec![Tenant { name: DEFAULT.to_string(), bot }]);
+}
+
+/// Имя бота, через которого пришло обновление.
+pub fn name_of(bot: &Bot) -> &'static str {
+    let tenants = all();
+    tenants
+        .iter()
+        .find(|tenant| tenant.bot.token() == bot.token())
+        .unwrap_or(&tenants[0])
+        .name
+        .as_str()
+}
+
+/// Бот для доставки сообщений арендатора. Строки `default` и неизвестные имена (бот убран
+/// из настроек) отправляются через первого бота, чтобы напоминания не терялись.
+pub fn bot(name: &str) -> &'static Bot {
+    let tenants = all();
+    match tenants.iter().find(|tenant| tenant.name == name) {
+        Some(tenant) => &tenant.bot,
+        None if name == DEFAULT => &tenants[0].bot,
+        None => {
+            log::error!("Unknown tenant {}, sending through {}", name, tenants[0].name);
+            &tenants[0].bot
+        }
+    }
+}
@@ -0,0 +1,366 @@
+//! Прогон обработчиков от обновления до запросов к Telegram без сети: обновления
+//! собираются в формате Bot API и идут через `schema()`, а бот ходит в поддельный
+//! Bot API, который запоминает запросы и отвечает как настоящий.
+
+use std::net::TcpListener;
+use std::sync::atomic::{AtomicI32, AtomicI64, Ordering};
+use std::sync::{Mutex, OnceLock};
+
+use axum::body::Bytes;
+use axum::http::Uri;
+use axum::{Json, Router};
+use chrono::{DateTime, Utc};
+use serde_json::{Value, json};
+use teloxide::prelude::*;
+
+use crate::clock::FixedClock;
+use crate::shards::Shard;
+use crate::storage::tests::memory_db;
+use crate::{outbox, pomodoro, reactions, schema, send_due_events, tenants, Db};
+
+const TOKEN: &str = "12345:test-token";
+const BOT_ID: i64 = 12345;
+
+/// Запрос бота к Bot API.
+#[derive(Debug, Clone)]
+pub struct Request {
+    /// Метод в нижнем регистре: `sendmessage`, `answercallbackquery`.
+    pub method: String,
+    pub body: Value,
+    /// Id сообщения, которое поддельный API вернул на отправку или правку.
+    pub message_id: Option<i32>,
+}
+
+impl Request {
+    pub fn text(&self) -> &str {
+        self.body["text"].as_str().unwrap_or_default()
+    }
+
+    /// Данные кнопок под сообщением.
+    pub fn buttons(&self) -> Vec<&str> {
+        self.body["reply_markup"]["inline_keyboard"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .flat_map(|row| row.as_array().into_iter().flatten())
+            .filter_map(|button| button["callback_data"].as_str())
+            .collect()
+    }
+
+    fn chat_id(&self) -> Option<i64> {
+        self.body["chat_id"].as_i64().or_else(|| self.body["chat_id"].as_str()?.parse().ok())
+    }
+}
+
+fn requests() -> &'static Mutex<Vec<Request>> {
+    static REQUESTS: OnceLock<Mutex<Vec<Request>>> = OnceLock::new();
+    REQUESTS.get_or_init(Default::default)
+}
+
+fn next_message_id() -> i32 {
+    static NEXT: AtomicI32 = AtomicI32::new(1000);
+    NEXT.fetch_add(1, Ordering::SeqCst)
+}
+
+fn chat(id: i64) -> Value {
+    if id < 0 {
+        json!({ "id": id, "type": "group", "title": "Test group" })
+    } else {
+        json!({ "id": id, "type": "private", "first_name": "Test" })
+    }
+}
+
+fn bot_user() -> Value {
+    json!({ "id": BOT_ID, "is_bot": true, "first_name": "Reventor", "username": "reventor_test_bot" })
+}
+
+/// Ответ поддельного Bot API: отправка и правка возвращают сообщение, остальное — `true`.
+async fn handle_api(uri: Uri, body: Bytes) -> Json<Value> {
+    let method = uri.path().rsplit('/').next().unwrap_or_default().to_lowercase();
+    let body = serde_json::from_slice::<Value>(&body).unwrap_or(Value::Null);
+    let mut request = Request { method, body, message_id: None };
+
+    let result = match request.method.as_str() {
+        "getme" => bot_user(),
+        "sendmessage" | "editmessagetext" | "editmessagereplymarkup" => {
+            let message_id = request.body["message_id"].as_i64().map_or_else(next_message_id, |id| id as i32);
+            request.message_id = Some(message_id);
+            json!({
+                "message_id": message_id,
+                "date": Utc::now().timestamp(),
+                "chat": chat(request.chat_id().unwrap_or_default()),
+                "from": bot_user(),
+                "text": request.text(),
+            })
+        }
+        _ => json!(true),
+    };
+    requests().lock().unwrap().push(request);
+    Json(json!({ "ok": true, "result": result }))
+}
+
+/// Адрес поддельного Bot API. Сервер живёт на своём потоке: у каждого теста свой рантайм.
+fn api_url() -> reqwest::Url {
+    static URL: OnceLock<reqwest::Url> = OnceLock::new();
+    URL.get_or_init(|| {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        listener.set_nonblocking(true).unwrap();
+        let url = reqwest::Url::parse(&format!("http://{}/", listener.local_addr().unwrap())).unwrap();
+        std::thread::spawn(move || {
+            let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap();
+            runtime.block_on(async move {
+                let app = Router::new().fallback(handle_api);
+                axum::Server::from_tcp(listener).unwrap().serve(app.into_make_service()).await.unwrap();
+            });
+        });
+        url
+    })
+    .clone()
+}
+
+/// Пользователь в личном чате с ботом и своя база в памяти. Тесты идут параллельно
+/// через один поддельный API, поэтому у каждого пользователя свой id.
+pub struct Harness {
+    pub db: Db,
+    pub user_id: i64,
+    sessions: pomodoro::Sessions,
+}
+
+impl Harness {
+    pub fn new() -> Self {
+        static NEXT_USER: AtomicI64 = AtomicI64::new(100);
+        tenants::install(Bot::new(TOKEN).set_api_url(api_url()));
+        Harness {
+            db: memory_db(),
+            user_id: NEXT_USER.fetch_add(1, Ordering::SeqCst),
+            sessions: pomodoro::new_sessions(),
+        }
+    }
+
+    fn bot(&self) -> Bot {
+        tenants::bot(tenants::DEFAULT).clone()
+    }
+
+    fn user(&self) -> Value {
+        json!({ "id": self.user_id, "is_bot": false, "first_name": "Test", "language_code": "ru" })
+    }
+
+    fn message(&self, message_id: i32, text: &str) -> Value {
+        json!({
+            "message_id": message_id,
+            "date": Utc::now().timestamp(),
+            "chat": chat(self.user_id),
+            "from": self.user(),
+            "text": text,
+        })
+    }
+
+    async fn dispatch(&self, update: Value) {
+        // Update разбирается только из текста: из `Value` teloxide теряет содержимое обновления
+        let update: Update = serde_json::from_str(&update.to_string()).expect("invalid synthetic update");
+        let deps = dptree::deps![update, self.bot(), self.db.clone(), self.sessions.clone()];
+        match schema().dispatch(deps).await {
+            std::ops::ControlFlow::Break(result) => result.expect("handler failed"),
+            std::ops::ControlFlow::Continue(_) => panic!("update was not handled"),
+        }
+    }
+
+    /// Пользователь пишет боту.
+    pub async fn send(&self, text: &str) {
+        let update = json!({ "update_id": next_message_id(), "message": self.message(next_message_id(), text) });
+        self.dispatch(update).await;
+    }
+
+    /// Пользователь нажимает кнопку под сообщением бота.
+    pub async fn press(&self, message_id: i32, data: &str) {
+        let mut message = self.message(message_id, "");
+        message["from"] = bot_user();
+        let update = json!({
+            "update_id": next_message_id(),
+            "callback_query": {
+                "id": next_message_id().to_string(),
+                "from": self.user(),
+                "message": message,
+                "chat_instance": "test",
+                "data": data,
+            },
+        });
+        self.dispatch(update).await;
+    }
+
+    /// Пользователь ставит реакцию на сообщение бота. Реакции идут мимо диспетчера, как в `reactions::listener`.
+    pub async fn react(&self, message_id: i32, emoji: &str) {
+        let update = json!({
+            "chat": chat(self.user_id),
+            "message_id": message_id,
+            "user": self.user(),
+            "date": Utc::now().timestamp(),
+            "old_reaction": [],
+            "new_reaction": [{ "type": "emoji", "emoji": emoji }],
+        });
+        reactions::handle_reaction_update(self.bot(), self.db.clone(), json!({ "message_reaction": update }), next_message_id())
+            .await
+            .expect("reaction handler failed");
+    }
+
+    /// Один проход планировщика и отправителя очереди, как будто сейчас `now`.
+    pub async fn tick_at(&self, now: DateTime<Utc>) {
+        let clock = FixedClock::new(now);
+        send_due_events(&self.db, &clock, Shard::ALL).await;
+        outbox::drain(&self.db, &clock).await;
+    }
+
+    /// Запросы бота в чат этого пользователя по порядку.
+    pub fn requests(&self) -> Vec<Request> {
+        let requests = requests().lock().unwrap();
+        requests.iter().filter(|request| request.chat_id() == Some(self.user_id)).cloned().collect()
+    }
+
+    /// Отправленные пользователю сообщения.
+    pub fn sent(&self) -> Vec<Request> {
+        self.requests().into_iter().filter(|request| request.method == "sendmessage").collect()
+    }
+
+    /// Забывает запросы, чтобы проверять только следующие.
+    pub fn clear(&self) {
+        requests().lock().unwrap().retain(|request| request.chat_id() != Some(self.user_id));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, TimeZone};
+
+    use super::*;
+
+    async fn status(harness: &Harness, event_id: i64) -> String {
+        harness.db.call(move |conn| conn.query_row("SELECT status FROM events WHERE id = ?", [event_id], |row| row.get(0)))
+            .await
+            .unwrap()
+    }
+
+    /// Создаёт событие на 1 января 2099 года 10:00 по Москве и возвращает его id.
+    async fn create_event(harness: &Harness, text: &str) -> i64 {
+        harness.send("/timezone Europe/Moscow").await;
+        harness.send(&format!("{} @01.01.2099 10:00", text)).await;
+        let confirmation = harness.sent().last().cloned().expect("no confirmation");
+        assert!(confirmation.text().contains(text), "unexpected confirmation: {}", confirmation.text());
+        harness.db.call(|conn| conn.query_row("SELECT MAX(id) FROM events", [], |row| row.get(0))).await.unwrap()
+    }
+
+    fn event_start() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2099, 1, 1, 7, 0, 0).unwrap()
+    }
+
+    #[tokio::test]
+    async fn create_notify_snooze() {
+        let harness = Harness::new();
+        let event_id = create_event(&harness, "Позвонить маме").await;
+        harness.clear();
+
+        harness.tick_at(event_start() - Duration::minutes(1)).await;
+        assert!(harness.sent().is_empty(), "reminder sent too early");
+
+        harness.tick_at(event_start()).await;
+        let reminders = harness.sent();
+        assert_eq!(reminders.len(), 1);
+        assert!(reminders[0].text().contains("Позвонить маме"));
+        assert_eq!(reminders[0].buttons(), [format!("ack:{}", event_id)]);
+        assert_eq!(status(&harness, event_id).await, "sent");
+
+        // Повторный проход не шлёт напоминание ещё раз
+        harness.tick_at(event_start()).await;
+        assert_eq!(harness.sent().len(), 1);
+        let lag: Option<i64> = harness.db
+            .call(move |conn| conn.query_row("SELECT lag_secs FROM sent_notifications WHERE event_id = ?", [event_id], |row| row.get(0)))
+            .await
+            .unwrap();
+        assert_eq!(lag, Some(0));
+
+        harness.clear();
+        let reminder_id = reminders[0].message_id.unwrap();
+        harness.react(reminder_id, "😴").await;
+        assert_eq!(harness.sent().len(), 1, "no snooze confirmation");
+        assert_eq!(status(&harness, event_id).await, "pending");
+        let actions: Vec<String> = harness.db.call(move |conn| {
+            let mut stmt = conn.prepare("SELECT action FROM audit_log WHERE event_id = ? ORDER BY id")?;
+            let actions = stmt.query_map([event_id], |row| row.get(0))?.collect::<Result<Vec<_>, _>>()?;
+            Ok(actions)
+        }).await.unwrap();
+        assert_eq!(actions, ["create", "snooze"]);
+
+        harness.clear();
+        harness.tick_at(Utc::now() + Duration::minutes(61)).await;
+        let reminders = harness.sent();
+        assert_eq!(reminders.len(), 1, "snoozed reminder not sent");
+        assert!(reminders[0].text().contains("Позвонить маме"));
+    }
+
+    #[tokio::test]
+    async fn done_button_completes_event() {
+        let harness = Harness::new();
+        let event_id = create_event(&harness, "Оплатить счёт").await;
+        harness.tick_at(event_start()).await;
+        let reminder_id = harness.sent().last().and_then(|reminder| reminder.message_id).unwrap();
+
+        harness.press(reminder_id, &format!("ack:{}", event_id)).await;
+        assert_eq!(status(&harness, event_id).await, "done");
+        // Кнопка «Готово» убирается из напоминания
+        assert!(harness.requests().iter().any(|request| request.method == "editmessagereplymarkup"));
+    }
+
+    #[tokio::test]
+    async fn cron_event_schedules_next_occurrence() {
+        let harness = Harness::new();
+        harness.send("/timezone Europe/Moscow").await;
+        harness.send("Планёрка @cron 0 10 * * *").await;
+        let pending = || harness.db.call(|conn| {
+            conn.query_row(
+                "SELECT id, event_utc, cron FROM events WHERE status = 'pending' ORDER BY id DESC LIMIT 1",
+                [],
+                |row| Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?, row.get::<_, Option<String>>(2)?)),
+            )
+        });
+        let (first_id, first_utc, cron) = pending().await.unwrap();
+        assert_eq!(cron.as_deref(), Some("0 10 * * *"));
+        let at = |utc: &str| chrono::NaiveDateTime::parse_from_str(utc, crate::UTC_FORMAT).unwrap().and_utc();
+        harness.clear();
+
+        harness.tick_at(at(&first_utc)).await;
+        assert_eq!(harness.sent().len(), 1);
+        assert_eq!(status(&harness, first_id).await, "sent");
+        // Следующее событие серии ставится на тот же час назавтра и забирает расписание себе
+        let (second_id, second_utc, cron) = pending().await.unwrap();
+        assert_ne!(second_id, first_id);
+        assert_eq!(at(&second_utc) - at(&first_utc), Duration::days(1));
+        assert_eq!(cron.as_deref(), Some("0 10 * * *"));
+
+        harness.tick_at(at(&second_utc)).await;
+        let reminders = harness.sent();
+        assert_eq!(reminders.len(), 2);
+        assert!(reminders[1].text().contains("Планёрка"));
+    }
+
+    #[tokio::test]
+    async fn redelivered_message_creates_one_event() {
+        let harness = Harness::new();
+        let update = json!({ "update_id": next_message_id(), "message": harness.message(next_message_id(), "Купить хлеб @01.01.2099 10:00") });
+        harness.dispatch(update.clone()).await;
+        harness.dispatch(update).await;
+        let events: i64 = harness.db.call(|conn| conn.query_row("SELECT COUNT(*) FROM events", [], |row| row.get(0))).await.unwrap();
+        assert_eq!(events, 1);
+        // Повтор получает обычное подтверждение, а не вопрос о дубликате
+        let sent = harness.sent();
+        assert_eq!(sent.len(), 2);
+        assert!(sent[1].buttons().iter().all(|data| !data.starts_with("dup:")), "unexpected reply: {}", sent[1].text());
+    }
+
+    #[tokio::test]
+    async fn unknown_text_gets_help() {
+        let harness = Harness::new();
+        harness.send("просто текст").await;
+        let sent = harness.sent();
+        assert_eq!(sent.len(), 1);
+        assert!(sent[0].text().contains("/help"), "unexpected reply: {}", sent[0].text());
+    }
+}
@@ -0,0 +1,183 @@
+//! Короткие таймеры `/timer 15m чай`: не попадают в список событий и не пишутся в базу.
+//! Каждый таймер — отдельная задача, которая спит до срока, поэтому срабатывает с точностью
+//! до секунды, а не раз в проход планировщика. После перезапуска бота таймеры не восстанавливаются.
+
+use std::collections::HashMap;
+use std::sync::{Mutex, MutexGuard, OnceLock};
+
+use teloxide::prelude::*;
+use chrono::{DateTime, Duration, Utc};
+use regex::Regex;
+use tokio::task::AbortHandle;
+
+use crate::accessibility;
+use crate::chunks;
+use crate::humanize;
+use crate::i18n::{t, tf, Lang};
+use crate::tenants;
+
+/// Дольше суток — уже не таймер, а событие.
+const MAX_SECONDS: i64 = 24 * 60 * 60;
+const MAX_PER_CHAT: usize = 10;
+
+#[derive(Debug)]
+struct Timer {
+    id: u64,
+    label: String,
+    ends: DateTime<Utc>,
+    handle: AbortHandle,
+}
+
+/// Ключ — бот и чат, как у сессий помодоро.
+type Registry = HashMap<(&'static str, i64), Vec<Timer>>;
+
+fn registry() -> MutexGuard<'static, Registry> {
+    static REGISTRY: OnceLock<Mutex<Registry>> = OnceLock::new();
+    REGISTRY.get_or_init(|| Mutex::new(HashMap::new())).lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+fn next_id() -> u64 {
+    static NEXT: std::sync::atomic::AtomicU64 = std::sync::atomic::AtomicU64::new(1);
+    NEXT.fetch_add(1, std::sync::atomic::Ordering::Relaxed)
+}
+
+/// Длительность `15m`, `90s`, `1h30m`, `1ч`, `30мин`, `45с`.
+fn parse_duration(value: &str) -> Option<Duration> {
+    let re = Regex::new(r"(?i)(\d+)(h|ч|min|мин|m|м|sec|сек|s|с)").unwrap();
+    let value = value.trim().to_lowercase();
+    let mut seconds = 0i64;
+    let mut matched = 0;
+    for captures in re.captures_iter(&value) {
+        let n = captures[1].parse::<i64>().ok()?;
+        let unit = match &captures[2] {
+            "h" | "ч" => 3600,
+            "min" | "мин" | "m" | "м" => 60,
+            _ => 1,
+        };
+        seconds = seconds.checked_add(n.checked_mul(unit)?)?;
+        matched += captures[0].len();
+    }
+    (matched == value.len() && (1..=MAX_SECONDS).contains(&seconds)).then(|| Duration::seconds(seconds))
+}
+
+fn remove(key: (&'static str, i64), id: Option<u64>) -> usize {
+    let mut registry = registry();
+    let Some(timers) = registry.get_mut(&key) else {
+        return 0;
+    };
+    let before = timers.len();
+    timers.retain(|timer| {
+        let keep = id.is_some_and(|id| timer.id != id);
+        if !keep {
+            timer.handle.abort();
+        }
+        keep
+    });
+    let removed = before - timers.len();
+    if timers.is_empty() {
+        registry.remove(&key);
+    }
+    removed
+}
+
+/// `/timer 15m [подпись]` — таймер; `/timer stop [#номер]` — остановить; без аргументов — список.
+pub async fn handle_timer_command(bot: &Bot, msg: &Message, args: &str, lang: Lang) -> ResponseResult<()> {
+    let key = (tenants::name_of(bot), msg.chat.id.0);
+    let args = args.trim();
+
+    if args.is_empty() {
+        let lines = registry().get(&key).map_or_else(Vec::new, |timers| {
+            timers
+                .iter()
+                .map(|timer| tf(lang, "timer_line", &[
+                    ("id", &timer.id),
+                    ("label", &timer.label),
+                    ("left", &humanize::duration(lang, timer.ends - Utc::now())),
+                ]))
+                .collect()
+        });
+        let text = if lines.is_empty() { t(lang, "timer_none") } else { tf(lang, "timer_list", &[("timers", &lines.join("\n"))]) };
+        chunks::send(bot, msg.chat.id, text).await?;
+        return Ok(());
+    }
+
+    if let Some(id) = args.strip_prefix("stop") {
+        let id = id.trim().trim_start_matches('#');
+        let stopped = match id {
+            "" => remove(key, None),
+            id => match id.parse() {
+                Ok(id) => remove(key, Some(id)),
+                Err(_) => 0,
+            },
+        };
+        chunks::send(bot, msg.chat.id, if stopped > 0 { t(lang, "timer_stopped") } else { t(lang, "timer_none") }).await?;
+        return Ok(());
+    }
+
+    let (value, label) = args.split_once(char::is_whitespace).unwrap_or((args, ""));
+    let Some(duration) = parse_duration(value) else {
+        bot.send_message(msg.chat.id, t(lang, "timer_usage")).await?;
+        return Ok(());
+    };
+    let (id, label, plain) = (next_id(), label.trim().to_string(), accessibility::current());
+    let length = humanize::duration(lang, duration);
+    let (bot_for_timer, chat_id, text_label, fired_length) = (tenants::bot(key.0).clone(), msg.chat.id, label.clone(), length.clone());
+    // Проверка лимита и запись таймера под одной блокировкой, иначе два одновременных
+    // `/timer` оба прошли бы проверку. Задача ждёт ту же блокировку, так что запись появится раньше
+    let started = {
+        let mut all = registry();
+        let timers = all.entry(key).or_default();
+        let free = timers.len() < MAX_PER_CHAT;
+        if free {
+            let task = tokio::spawn(async move {
+                tokio::time::sleep(duration.to_std().unwrap_or_default()).await;
+                // Запись снимается до отправки: `stop`, пришедший во время отправки, не найдёт таймер
+                {
+                    let mut registry = registry();
+                    if let Some(timers) = registry.get_mut(&key) {
+                        timers.retain(|timer| timer.id != id);
+                        if timers.is_empty() {
+                            registry.remove(&key);
+                        }
+                    }
+                }
+                let text = accessibility::with(plain, || {
+                    if text_label.is_empty() {
+                        tf(lang, "timer_done", &[("duration", &fired_length)])
+                    } else {
+                        tf(lang, "timer_done_label", &[("duration", &fired_length), ("label", &text_label)])
+                    }
+                });
+                if let Err(e) = bot_for_timer.send_message(chat_id, text).await {
+                    log::error!("Failed to deliver timer {} in chat {}: {}", id, chat_id, e);
+                }
+            });
+            timers.push(Timer { id, label, ends: Utc::now() + duration, handle: task.abort_handle() });
+        }
+        free
+    };
+    if !started {
+        bot.send_message(msg.chat.id, tf(lang, "timer_too_many", &[("limit", &MAX_PER_CHAT)])).await?;
+        return Ok(());
+    }
+
+    log::info!("Started timer {} for {}s in chat {}", id, duration.num_seconds(), key.1);
+    chunks::send(bot, msg.chat.id, tf(lang, "timer_started", &[("id", &id), ("duration", &length)])).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn durations_accept_compound_units() {
+        assert_eq!(parse_duration("15m"), Some(Duration::minutes(15)));
+        assert_eq!(parse_duration("1h30m"), Some(Duration::minutes(90)));
+        assert_eq!(parse_duration("90с"), Some(Duration::seconds(90)));
+        assert_eq!(parse_duration("10мин"), Some(Duration::minutes(10)));
+        assert_eq!(parse_duration("15"), None);
+        assert_eq!(parse_duration("15m tea"), None);
+        assert_eq!(parse_duration("25h"), None);
+    }
+}
@@ -0,0 +1,222 @@
+use std::sync::OnceLock;
+
+use teloxide::prelude::*;
+use teloxide::types::{KeyboardButton, KeyboardMarkup, KeyboardRemove, Location};
+use chrono::{DateTime, Duration, LocalResult, NaiveDateTime, TimeZone, Utc};
+use chrono_tz::Tz;
+use rusqlite::{Connection, params, OptionalExtension};
+use tzf_rs::DefaultFinder;
+
+use crate::chunks;
+use crate::clock::{self, Clock};
+use crate::i18n::{t, tf, Lang};
+use crate::{geofence, settings, weather};
+use crate::{DatabaseError, Db, UTC_FORMAT, add_column_if_missing, ensure_user_exists, in_transaction, parse_event_time, tenants};
+
+pub fn init_tables(conn: &Connection) -> Result<(), rusqlite::Error> {
+    add_column_if_missing(conn, "users", "timezone", "TEXT")?;
+    // Чего бот ждёт от следующей геопозиции пользователя, например 'timezone'
+    add_column_if_missing(conn, "users", "awaiting_location", "TEXT")?;
+    backfill_event_instants(conn)?;
+    Ok(())
+}
+
+/// Заполняет момент в UTC для событий, созданных до появления колонки `event_utc`.
+fn backfill_event_instants(conn: &Connection) -> Result<(), rusqlite::Error> {
+    let mut stmt = conn.prepare(
+        "SELECT e.id, e.event_time, u.timezone FROM events e
+         JOIN users u ON e.user_id = u.id
+         WHERE e.event_utc IS NULL"
+    )?;
+    let rows = stmt.query_map([], |row| {
+        Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?, row.get::<_, Option<String>>(2)?))
+    })?
+    .collect::<Result<Vec<_>, _>>()?;
+
+    for (event_id, event_time, zone) in rows {
+        let Some(local) = parse_event_time(&event_time) else {
+            continue;
+        };
+        let tz = zone.as_deref().and_then(parse_zone);
+        conn.execute(
+            "UPDATE events SET event_utc = ? WHERE id = ?",
+            params![to_utc(local, tz).format(UTC_FORMAT).to_string(), event_id],
+        )?;
+    }
+    Ok(())
+}
+
+/// Часовой пояс владельца события по `users.id`.
+pub fn user_zone(conn: &Connection, user_id: i64) -> Result<Option<Tz>, rusqlite::Error> {
+    let zone: Option<String> = conn.query_row(
+        "SELECT timezone FROM users WHERE id = ?",
+        params![user_id],
+        |row| row.get(0),
+    ).optional()?.flatten();
+    Ok(zone.as_deref().and_then(parse_zone))
+}
+
+/// Текущее время на часах пользователя; без пояса — время сервера.
+pub fn now_in(tz: Option<Tz>) -> NaiveDateTime {
+    clock::SYSTEM.now_in(tz)
+}
+
+/// Момент `now` на часах пользователя; без пояса — на часах сервера.
+pub fn local_at(now: DateTime<Utc>, tz: Option<Tz>) -> NaiveDateTime {
+    match tz {
+        Some(tz) => now.with_timezone(&tz).naive_local(),
+        None => now.with_timezone(&chrono::Local).naive_local(),
+    }
+}
+
+fn local_to_utc<T: TimeZone>(tz: &T, local: NaiveDateTime) -> NaiveDateTime {
+    match tz.from_local_datetime(&local) {
+        // При переводе часов назад время встречается дважды — берём первое
+        LocalResult::Single(dt) | LocalResult::Ambiguous(dt, _) => dt.naive_utc(),
+        // При переводе вперёд такого времени нет — напоминаем на час позже
+        LocalResult::None => tz
+            .from_local_datetime(&(local + Duration::hours(1)))
+            .earliest()
+            .map_or(local, |dt| dt.naive_utc()),
+    }
+}
+
+/// Момент в UTC для времени на часах пользователя.
+pub fn to_utc(local: NaiveDateTime, tz: Option<Tz>) -> NaiveDateTime {
+    match tz {
+        Some(tz) => local_to_utc(&tz, local),
+        None => local_to_utc(&chrono::Local, local),
+    }
+}
+
+/// Время на часах пользователя для момента в UTC.
+pub fn from_utc(utc: NaiveDateTime, tz: Option<Tz>) -> NaiveDateTime {
+    match tz {
+        Some(tz) => tz.from_utc_datetime(&utc).naive_local(),
+        None => chrono::Local.from_utc_datetime(&utc).naive_local(),
+    }
+}
+
+/// Поиск зоны по координатам по встроенным границам часовых поясов; данные грузятся один раз.
+fn zone_at(location: &Location) -> Option<Tz> {
+    static FINDER: OnceLock<DefaultFinder> = OnceLock::new();
+    let finder = FINDER.get_or_init(DefaultFinder::new);
+    finder.get_tz_name(location.longitude, location.latitude).parse().ok()
+}
+
+pub fn parse_zone(value: &str) -> Option<Tz> {
+    value.parse().ok().or_else(|| {
+        // Позволяем писать без учёта регистра: europe/moscow
+        chrono_tz::TZ_VARIANTS.iter().copied().find(|tz| tz.name().eq_ignore_ascii_case(value))
+    })
+}
+
+/// Сохраняет пояс и пересчитывает моменты неотправленных событий:
+/// время на часах пользователя остаётся прежним.
+pub fn set_timezone(conn: &Connection, telegram_id: i64, tz: Tz) -> Result<(), rusqlite::Error> {
+    conn.execute(
+        "UPDATE users SET timezone = ?, awaiting_location = NULL WHERE telegram_id = ?",
+        params![tz.name(), telegram_id],
+    )?;
+    settings::invalidate_user(conn, telegram_id);
+
+    let mut stmt = conn.prepare(
+        "SELECT e.id, e.event_time FROM events e
+         JOIN users u ON e.user_id = u.id
+         WHERE u.telegram_id = ? AND e.status = 'pending'"
+    )?;
+    let events = stmt.query_map(params![telegram_id], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?)))?
+        .collect::<Result<Vec<_>, _>>()?;
+    for (event_id, event_time) in events {
+        if let Some(local) = parse_event_time(&event_time) {
+            conn.execute(
+                "UPDATE events SET event_utc = ? WHERE id = ?",
+                params![to_utc(local, Some(tz)).format(UTC_FORMAT).to_string(), event_id],
+            )?;
+        }
+    }
+    Ok(())
+}
+
+pub fn set_awaiting_location(conn: &Connection, telegram_id: i64, purpose: Option<&str>) -> Result<(), rusqlite::Error> {
+    conn.execute(
+        "UPDATE users SET awaiting_location = ? WHERE telegram_id = ?",
+        params![purpose, telegram_id],
+    )?;
+    Ok(())
+}
+
+fn awaiting_location(conn: &Connection, telegram_id: i64) -> Result<Option<String>, rusqlite::Error> {
+    conn.query_row(
+        "SELECT awaiting_location FROM users WHERE telegram_id = ?",
+        params![telegram_id],
+        |row| row.get(0),
+    ).optional().map(Option::flatten)
+}
+
+/// `/timezone Europe/Moscow` задаёт зону явно, `/timezone` без аргументов просит геопозицию.
+pub async fn handle_timezone_command(bot: &Bot, msg: &Message, db: &Db, args: &str, lang: Lang) -> ResponseResult<()> {
+    let user = msg.from().unwrap();
+    let telegram_id = user.id.0 as i64;
+
+    let (username, tenant) = (user.username.clone(), tenants::name_of(bot));
+    db.call(move |conn| ensure_user_exists(conn, tenant, telegram_id, username)).await.map_err(DatabaseError)?;
+
+    if !args.is_empty() {
+        let Some(tz) = parse_zone(args) else {
+            chunks::send(bot, msg.chat.id, tf(lang, "timezone_unknown", &[("value", &args)])).await?;
+            return Ok(());
+        };
+        db.call(move |conn| in_transaction(conn, |tx| set_timezone(tx, telegram_id, tz))).await.map_err(DatabaseError)?;
+        chunks::send(bot, msg.chat.id, tf(lang, "timezone_saved", &[("zone", &tz.name())])).await?;
+        return Ok(());
+    }
+
+    // Кнопка запроса геопозиции работает только в личном чате
+    if !msg.chat.is_private() {
+        bot.send_message(msg.chat.id, t(lang, "timezone_usage")).await?;
+        return Ok(());
+    }
+
+    db.call(move |conn| set_awaiting_location(conn, telegram_id, Some("timezone"))).await.map_err(DatabaseError)?;
+
+    let keyboard = KeyboardMarkup::new(vec![vec![KeyboardButton::new(t(lang, "timezone_share")).request(
+        teloxide::types::ButtonRequest::Location,
+    )]])
+    .resize_keyboard(true)
+    .one_time_keyboard(true);
+    bot.send_message(msg.chat.id, t(lang, "timezone_prompt")).reply_markup(keyboard).await?;
+    Ok(())
+}
+
+/// Геопозиция, присланная после `/timezone`, `/weather` или `/place`; остальные геопозиции
+/// (в том числе трансляция) сверяются с местами напоминаний.
+pub async fn handle_location(bot: &Bot, msg: &Message, db: &Db, location: &Location, lang: Lang) -> ResponseResult<()> {
+    let Some(user) = msg.from() else {
+        return Ok(());
+    };
+    let telegram_id = user.id.0 as i64;
+
+    let awaiting = db.call(move |conn| awaiting_location(conn, telegram_id)).await.map_err(DatabaseError)?;
+    match awaiting.as_deref() {
+        Some("timezone") => {}
+        Some("weather") => return weather::save_location(bot, msg, db, location, lang).await,
+        Some("place") => return geofence::save_location(bot, msg, db, location, lang).await,
+        _ => return geofence::check_location(msg, db, location).await,
+    }
+
+    let Some(tz) = zone_at(location) else {
+        db.call(move |conn| set_awaiting_location(conn, telegram_id, None)).await.map_err(DatabaseError)?;
+        bot.send_message(msg.chat.id, t(lang, "timezone_not_found"))
+            .reply_markup(KeyboardRemove::new())
+            .await?;
+        return Ok(());
+    };
+    db.call(move |conn| in_transaction(conn, |tx| set_timezone(tx, telegram_id, tz))).await.map_err(DatabaseError)?;
+
+    log::info!("Detected timezone {} for user {}", tz.name(), telegram_id);
+    chunks::send_last(bot, msg.chat.id, tf(lang, "timezone_saved", &[("zone", &tz.name())])).await?
+        .reply_markup(KeyboardRemove::new())
+        .await?;
+    Ok(())
+}
@@ -0,0 +1,218 @@
+use teloxide::prelude::*;
+use aes_gcm::aead::OsRng;
+use aes_gcm::aead::rand_core::RngCore;
+use rusqlite::{Connection, params, OptionalExtension};
+use sha2::{Digest, Sha256};
+
+use crate::chunks;
+use crate::i18n::{t, tf, Lang};
+use crate::{DatabaseError, Db, ensure_user_exists, tenants};
+
+/// Начало каждого токена: по нему токен легко узнать в логах и конфигурации.
+const TOKEN_PREFIX: &str = "rvt_";
+
+/// Права токена: `read` — чтение событий (лента, API), `write` — создание напоминаний (входящие вебхуки).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Scope {
+    Read,
+    Write,
+}
+
+impl Scope {
+    pub fn code(self) -> &'static str {
+        match self {
+            Scope::Read => "read",
+            Scope::Write => "write",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.to_lowercase().as_str() {
+            "read" => Some(Scope::Read),
+            "write" => Some(Scope::Write),
+            _ => None,
+        }
+    }
+}
+
+/// Владелец токена, прошедшего проверку.
+#[derive(Debug)]
+pub struct TokenOwner {
+    pub user_id: i64,
+    pub telegram_id: i64,
+    pub tenant: String,
+}
+
+/// Почему токен не принят.
+#[derive(Debug, PartialEq)]
+pub enum AuthError {
+    Unknown,
+    MissingScope,
+}
+
+#[derive(Debug)]
+struct TokenInfo {
+    id: i64,
+    name: String,
+    scopes: String,
+    hint: String,
+    created_at: String,
+    last_used_at: Option<String>,
+}
+
+pub fn init_tables(conn: &Connection) -> Result<(), rusqlite::Error> {
+    // Сам токен не хранится: только SHA-256 и первые символы, чтобы пользователь узнал его в списке
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS api_tokens (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            user_id INTEGER NOT NULL,
+            name TEXT NOT NULL,
+            scopes TEXT NOT NULL,
+            token_hash TEXT NOT NULL UNIQUE,
+            hint TEXT NOT NULL,
+            created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            last_used_at DATETIME,
+            FOREIGN KEY(user_id) REFERENCES users(id) ON DELETE CASCADE
+        )",
+        [],
+    )?;
+    Ok(())
+}
+
+fn hash_token(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
+}
+
+fn generate_token() -> String {
+    let mut bytes = [0u8; 24];
+    OsRng.fill_bytes(&mut bytes);
+    format!("{}{}", TOKEN_PREFIX, hex::encode(bytes))
+}
+
+fn create_token(conn: &Connection, user_id: i64, name: &str, scopes: &[Scope]) -> Result<(i64, String), rusqlite::Error> {
+    let token = generate_token();
+    let scopes = scopes.iter().map(|scope| scope.code()).collect::<Vec<_>>().join(",");
+    conn.execute(
+        "INSERT INTO api_tokens (user_id, name, scopes, token_hash, hint) VALUES (?, ?, ?, ?, ?)",
+        params![user_id, name, scopes, hash_token(&token), &token[..TOKEN_PREFIX.len() + 6]],
+    )?;
+    Ok((conn.last_insert_rowid(), token))
+}
+
+fn list_tokens(conn: &Connection, user_id: i64) -> Result<Vec<TokenInfo>, rusqlite::Error> {
+    let mut stmt = conn.prepare(
+        "SELECT id, name, scopes, hint, created_at, last_used_at FROM api_tokens WHERE user_id = ? ORDER BY id"
+    )?;
+    let tokens = stmt.query_map(params![user_id], |row| {
+        Ok(TokenInfo {
+            id: row.get(0)?,
+            name: row.get(1)?,
+            scopes: row.get(2)?,
+            hint: row.get(3)?,
+            created_at: row.get(4)?,
+            last_used_at: row.get(5)?,
+        })
+    })?
+    .collect::<Result<Vec<_>, _>>()?;
+    Ok(tokens)
+}
+
+/// Проверяет токен и нужное право; принятый токен помечается использованным.
+pub fn authenticate(conn: &Connection, token: &str, scope: Scope) -> Result<Result<TokenOwner, AuthError>, rusqlite::Error> {
+    let found: Option<(i64, String, TokenOwner)> = conn.query_row(
+        "SELECT t.id, t.scopes, u.id, u.telegram_id, u.tenant
+         FROM api_tokens t
+         JOIN users u ON t.user_id = u.id
+         WHERE t.token_hash = ?",
+        params![hash_token(token)],
+        |row| Ok((row.get(0)?, row.get(1)?, TokenOwner { user_id: row.get(2)?, telegram_id: row.get(3)?, tenant: row.get(4)? })),
+    ).optional()?;
+
+    let Some((token_id, scopes, owner)) = found else {
+        return Ok(Err(AuthError::Unknown));
+    };
+    if !scopes.split(',').any(|granted| Scope::parse(granted) == Some(scope)) {
+        return Ok(Err(AuthError::MissingScope));
+    }
+    conn.execute("UPDATE api_tokens SET last_used_at = CURRENT_TIMESTAMP WHERE id = ?", params![token_id])?;
+    Ok(Ok(owner))
+}
+
+fn revoke_token(conn: &Connection, user_id: i64, token_id: i64) -> Result<bool, rusqlite::Error> {
+    let deleted = conn.execute("DELETE FROM api_tokens WHERE id = ? AND user_id = ?", params![token_id, user_id])?;
+    Ok(deleted > 0)
+}
+
+/// Разбирает `[read,write] [название]`; без прав токен получает только чтение.
+fn parse_create_args(args: &str) -> (Vec<Scope>, String) {
+    let (first, rest) = args.split_once(char::is_whitespace).unwrap_or((args, ""));
+    let (scopes, name) = match first.split(',').map(Scope::parse).collect::<Option<Vec<_>>>() {
+        Some(scopes) => (scopes, rest.trim()),
+        None => (vec![Scope::Read], args),
+    };
+    let name = if name.is_empty() { "token" } else { name };
+    (scopes, name.to_string())
+}
+
+fn describe_tokens(lang: Lang, tokens: &[TokenInfo]) -> String {
+    tokens
+        .iter()
+        .map(|token| {
+            let used = token.last_used_at.clone().unwrap_or_else(|| t(lang, "token_never_used"));
+            tf(lang, "token_line", &[
+                ("id", &token.id),
+                ("name", &token.name),
+                ("hint", &token.hint),
+                ("scopes", &token.scopes),
+                ("created", &token.created_at),
+                ("used", &used),
+            ])
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// `/token create [read,write] [название]`, `/token list`, `/token revoke <id>` — токены для HTTP API.
+/// Токен показывается один раз, поэтому команда работает только в личном чате.
+pub async fn handle_token_command(bot: &Bot, msg: &Message, db: &Db, args: &str, lang: Lang) -> ResponseResult<()> {
+    if !msg.chat.is_private() {
+        bot.send_message(msg.chat.id, t(lang, "token_private_only")).await?;
+        return Ok(());
+    }
+    let Some(user) = msg.from() else {
+        return Ok(());
+    };
+    let (telegram_id, username, tenant) = (user.id.0 as i64, user.username.clone(), tenants::name_of(bot));
+    let user_id = db.call(move |conn| ensure_user_exists(conn, tenant, telegram_id, username)).await.map_err(DatabaseError)?;
+
+    let (action, rest) = args.split_once(char::is_whitespace).unwrap_or((args, ""));
+    let response = match action {
+        "create" => {
+            let (scopes, name) = parse_create_args(rest.trim());
+            let (token_id, token) = db.call(move |conn| create_token(conn, user_id, &name, &scopes))
+                .await
+                .map_err(DatabaseError)?;
+            log::info!("User {} created API token {}", telegram_id, token_id);
+            tf(lang, "token_created", &[("id", &token_id), ("token", &token)])
+        }
+        "list" => {
+            let tokens = db.call(move |conn| list_tokens(conn, user_id)).await.map_err(DatabaseError)?;
+            if tokens.is_empty() {
+                t(lang, "token_list_empty")
+            } else {
+                tf(lang, "token_list", &[("tokens", &describe_tokens(lang, &tokens))])
+            }
+        }
+        "revoke" => match rest.trim().trim_start_matches('#').parse::<i64>() {
+            Ok(token_id) => {
+                let revoked = db.call(move |conn| revoke_token(conn, user_id, token_id)).await.map_err(DatabaseError)?;
+                let key = if revoked { "token_revoked" } else { "token_not_found" };
+                tf(lang, key, &[("id", &token_id)])
+            }
+            Err(_) => t(lang, "token_usage"),
+        },
+        _ => t(lang, "token_usage"),
+    };
+    chunks::send(bot, msg.chat.id, response).await?;
+    Ok(())
+}
@@ -0,0 +1,267 @@
+use std::env;
+use std::time::Duration;
+
+use teloxide::prelude::*;
+use chrono::NaiveDateTime;
+use rusqlite::{Connection, params, OptionalExtension};
+use serde::Deserialize;
+
+use crate::blocklist;
+use crate::chunks;
+use crate::humanize;
+use crate::clock::Clock;
+use crate::i18n::{t, tf};
+use crate::settings::{self, Settings};
+use crate::{DatabaseError, Db, UTC_FORMAT, crypto, get_event, parse_event_time, tenants, weather};
+
+/// Время в пути, если его не указали и маршрут не построен.
+const DEFAULT_TRAVEL_MINUTES: u32 = 30;
+const MAX_TRAVEL_MINUTES: u32 = 24 * 60;
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+const DEFAULT_ROUTING_URL: &str = "https://router.project-osrm.org";
+
+#[derive(Debug, Deserialize)]
+struct RouteResponse {
+    routes: Vec<Route>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Route {
+    /// Секунды в пути.
+    duration: f64,
+}
+
+/// Широта и долгота.
+type Place = (f64, f64);
+
+/// Напоминание «пора выходить», которое пора отправить.
+#[derive(Debug)]
+struct DueLeave {
+    event_id: i64,
+    tenant: String,
+    owner_id: i64,
+    chat_id: i64,
+    text: String,
+    event_time: String,
+    travel_minutes: u32,
+}
+
+pub fn init_tables(conn: &Connection) -> Result<(), rusqlite::Error> {
+    // Момент выхода считается от event_utc, поэтому перенос события сдвигает и его
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS leave_reminders (
+            event_id INTEGER PRIMARY KEY,
+            travel_minutes INTEGER NOT NULL,
+            status TEXT NOT NULL DEFAULT 'pending',
+            FOREIGN KEY(event_id) REFERENCES events(id) ON DELETE CASCADE
+        )",
+        [],
+    )?;
+    Ok(())
+}
+
+/// Минуты в пути по умолчанию из `TRAVEL_MINUTES`.
+fn default_minutes() -> u32 {
+    env::var("TRAVEL_MINUTES")
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .filter(|minutes| (1..=MAX_TRAVEL_MINUTES).contains(minutes))
+        .unwrap_or(DEFAULT_TRAVEL_MINUTES)
+}
+
+/// Время в пути по маршрутизатору OSRM (`ROUTING_URL`). Запрос уходит только в сборке с фичей `routing`.
+async fn route_minutes((from_lat, from_lon): Place, (to_lat, to_lon): Place) -> Option<u32> {
+    if !cfg!(feature = "routing") {
+        return None;
+    }
+    let base = env::var("ROUTING_URL").unwrap_or_else(|_| DEFAULT_ROUTING_URL.to_string());
+    let url = format!("{}/route/v1/driving/{},{};{},{}", base.trim_end_matches('/'), from_lon, from_lat, to_lon, to_lat);
+    let response = async {
+        reqwest::Client::new()
+            .get(url)
+            .query(&[("overview", "false")])
+            .timeout(REQUEST_TIMEOUT)
+            .send()
+            .await?
+            .error_for_status()?
+            .json::<RouteResponse>()
+            .await
+    };
+    match response.await {
+        Ok(response) => response.routes.first().map(|route| (route.duration / 60.0).ceil().max(1.0) as u32),
+        Err(e) => {
+            log::error!("Failed to fetch route: {}", e);
+            None
+        }
+    }
+}
+
+/// Место события и место пользователя по умолчанию; у события без геопозиции — `None`.
+fn event_route(conn: &Connection, event_id: i64, telegram_id: i64) -> Result<Option<(Place, Option<Place>)>, rusqlite::Error> {
+    let destination = conn.query_row(
+        "SELECT location_lat, location_lon FROM events WHERE id = ?",
+        params![event_id],
+        |row| Ok(row.get::<_, Option<f64>>(0)?.zip(row.get::<_, Option<f64>>(1)?)),
+    ).optional()?.flatten();
+    let Some(destination) = destination else {
+        return Ok(None);
+    };
+    Ok(Some((destination, weather::user_place(conn, telegram_id)?)))
+}
+
+fn save_reminder(conn: &Connection, event_id: i64, travel_minutes: u32) -> Result<(), rusqlite::Error> {
+    conn.execute(
+        "INSERT OR REPLACE INTO leave_reminders (event_id, travel_minutes, status) VALUES (?, ?, 'pending')",
+        params![event_id, travel_minutes],
+    )?;
+    Ok(())
+}
+
+fn delete_reminder(conn: &Connection, event_id: i64) -> Result<bool, rusqlite::Error> {
+    Ok(conn.execute("DELETE FROM leave_reminders WHERE event_id = ?", params![event_id])? > 0)
+}
+
+/// Разбирает `#id [минуты]`.
+fn parse_args(args: &str) -> Option<(i64, Option<u32>)> {
+    let mut parts = args.split_whitespace();
+    let event_id = parts.next()?.trim_start_matches('#').parse().ok()?;
+    let minutes = match parts.next() {
+        Some(value) => Some(value.trim_end_matches("мин").trim_end_matches("min").parse().ok()
+            .filter(|minutes| (1..=MAX_TRAVEL_MINUTES).contains(minutes))?),
+        None => None,
+    };
+    parts.next().is_none().then_some((event_id, minutes))
+}
+
+/// `/leave #id [минуты]` — напомнить, когда пора выходить на событие с геопозицией;
+/// `/leave off #id` — отменить. Без минут время в пути строится по маршруту от места
+/// по умолчанию (`/weather`), если маршруты включены, иначе берётся `TRAVEL_MINUTES`.
+pub async fn handle_leave_command(bot: &Bot, msg: &Message, db: &Db, args: &str, settings: &Settings) -> ResponseResult<()> {
+    let lang = settings.lang;
+    let Some(user) = msg.from() else {
+        return Ok(());
+    };
+    let (telegram_id, chat_id) = (user.id.0 as i64, msg.chat.id.0);
+
+    if let Some(id) = args.strip_prefix("off") {
+        let response = match id.trim().trim_start_matches('#').parse::<i64>() {
+            Ok(event_id) => {
+                let deleted = db.call(move |conn| {
+                    match get_event(conn, event_id)?.filter(|event| event.chat_id == chat_id && event.owner_id == telegram_id) {
+                        Some(_) => delete_reminder(conn, event_id),
+                        None => Ok(false),
+                    }
+                }).await.map_err(DatabaseError)?;
+                let key = if deleted { "leave_cancelled" } else { "leave_not_found" };
+                tf(lang, key, &[("id", &event_id)])
+            }
+            Err(_) => t(lang, "leave_usage"),
+        };
+        chunks::send(bot, msg.chat.id, response).await?;
+        return Ok(());
+    }
+
+    let Some((event_id, minutes)) = parse_args(args) else {
+        bot.send_message(msg.chat.id, t(lang, "leave_usage")).await?;
+        return Ok(());
+    };
+    let found = db.call(move |conn| {
+        match get_event(conn, event_id)?.filter(|event| event.chat_id == chat_id) {
+            Some(event) => Ok(Some((event, event_route(conn, event_id, telegram_id)?))),
+            None => Ok(None),
+        }
+    }).await.map_err(DatabaseError)?;
+
+    let Some((event, route)) = found else {
+        bot.send_message(msg.chat.id, t(lang, "event_not_found")).await?;
+        return Ok(());
+    };
+    if event.owner_id != telegram_id {
+        bot.send_message(msg.chat.id, t(lang, "event_not_yours")).await?;
+        return Ok(());
+    }
+    let Some((destination, origin)) = route else {
+        bot.send_message(msg.chat.id, t(lang, "leave_no_location")).await?;
+        return Ok(());
+    };
+
+    let routed = match (minutes, origin) {
+        (None, Some(origin)) => route_minutes(origin, destination).await,
+        _ => None,
+    };
+    let travel_minutes = minutes.or(routed).unwrap_or_else(default_minutes);
+    db.call(move |conn| save_reminder(conn, event_id, travel_minutes)).await.map_err(DatabaseError)?;
+
+    let leave_at = parse_event_time(&event.event_time)
+        .map(|time| settings.format_datetime(time - chrono::Duration::minutes(travel_minutes as i64)))
+        .unwrap_or(event.event_time);
+    let key = if routed.is_some() { "leave_saved_route" } else { "leave_saved" };
+    let response = tf(lang, key, &[("id", &event_id), ("duration", &humanize::minutes(lang, travel_minutes.into())), ("time", &leave_at)]);
+    chunks::send(bot, msg.chat.id, response).await?;
+    Ok(())
+}
+
+/// Забирает напоминания, время выхода для которых наступило, и помечает их отправленными.
+fn take_due(conn: &Connection, now: NaiveDateTime) -> Result<Vec<DueLeave>, rusqlite::Error> {
+    let now = now.format(UTC_FORMAT).to_string();
+    let mut stmt = conn.prepare(&format!(
+        "SELECT e.id, e.tenant, u.telegram_id, COALESCE(e.chat_id, u.telegram_id), e.text, e.event_time, l.travel_minutes
+         FROM leave_reminders l
+         JOIN events e ON l.event_id = e.id
+         JOIN users u ON e.user_id = u.id
+         WHERE l.status = 'pending' AND e.status = 'pending'
+           AND datetime(e.event_utc, '-' || l.travel_minutes || ' minutes') <= ? AND {}",
+        blocklist::NOT_BLOCKED
+    ))?;
+    let due = stmt.query_map(params![now], |row| {
+        Ok(DueLeave {
+            event_id: row.get(0)?,
+            tenant: row.get(1)?,
+            owner_id: row.get(2)?,
+            chat_id: row.get(3)?,
+            text: crypto::open(row.get(4)?),
+            event_time: row.get(5)?,
+            travel_minutes: row.get(6)?,
+        })
+    })?
+    .collect::<Result<Vec<_>, _>>()?;
+    for leave in &due {
+        conn.execute("UPDATE leave_reminders SET status = 'sent' WHERE event_id = ?", params![leave.event_id])?;
+    }
+    Ok(due)
+}
+
+fn leave_message(settings: &Settings, leave: &DueLeave) -> String {
+    let time = parse_event_time(&leave.event_time).map_or_else(|| leave.event_time.clone(), |time| settings.format_time(time.time()));
+    tf(settings.lang, "leave_reminder", &[
+        ("text", &leave.text.lines().next().unwrap_or_default()),
+        ("time", &time),
+        ("duration", &humanize::minutes(settings.lang, leave.travel_minutes.into())),
+    ])
+}
+
+pub async fn send_due_leave_reminders(db: &Db, clock: &dyn Clock) {
+    let now = clock.now_utc();
+    let due = db.call(move |conn| {
+        take_due(conn, now)?
+            .into_iter()
+            .map(|leave| {
+                let settings = settings::resolve(conn, leave.owner_id, leave.chat_id).unwrap_or_default();
+                Ok((leave, settings))
+            })
+            .collect::<Result<Vec<_>, rusqlite::Error>>()
+    }).await;
+    let due = match due {
+        Ok(due) => due,
+        Err(e) => {
+            log::error!("Failed to load leave reminders: {}", e);
+            return;
+        }
+    };
+
+    // Тихие часы не откладывают напоминание: позже выходить уже поздно
+    for (leave, settings) in due {
+        println!("Sending leave reminder for event {}", leave.event_id);
+        let _ = chunks::send(tenants::bot(&leave.tenant), ChatId(leave.chat_id), leave_message(&settings, &leave)).await;
+    }
+}
@@ -0,0 +1,65 @@
+use rusqlite::{Connection, params};
+
+use crate::Db;
+
+/// Сколько часов помнить обработанные обновления. Telegram хранит неподтверждённые
+/// обновления сутки, так что повторная доставка старше этого срока невозможна.
+const KEEP_HOURS: u32 = 48;
+
+pub fn init_tables(conn: &Connection) -> Result<(), rusqlite::Error> {
+    // Обработанные обновления по ботам: запись появляется после обработки, поэтому обновление,
+    // на котором бот упал, обработается заново, а уже обработанное повторно не выполнится
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS processed_updates (
+            tenant TEXT NOT NULL,
+            update_id INTEGER NOT NULL,
+            created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            PRIMARY KEY(tenant, update_id)
+        )",
+        [],
+    )?;
+    conn.execute("CREATE INDEX IF NOT EXISTS idx_processed_updates_created ON processed_updates(created_at)", [])?;
+    Ok(())
+}
+
+/// Отмечает обновление обработанным. Возвращает `false`, если оно уже встречалось.
+fn mark(conn: &Connection, tenant: &str, update_id: i32) -> Result<bool, rusqlite::Error> {
+    conn.execute(
+        "DELETE FROM processed_updates WHERE created_at < datetime('now', ?)",
+        params![format!("-{} hours", KEEP_HOURS)],
+    )?;
+    let inserted = conn.execute(
+        "INSERT OR IGNORE INTO processed_updates (tenant, update_id) VALUES (?, ?)",
+        params![tenant, update_id],
+    )?;
+    Ok(inserted > 0)
+}
+
+fn seen(conn: &Connection, tenant: &str, update_id: i32) -> Result<bool, rusqlite::Error> {
+    conn.query_row(
+        "SELECT EXISTS(SELECT 1 FROM processed_updates WHERE tenant = ? AND update_id = ?)",
+        params![tenant, update_id],
+        |row| row.get(0),
+    )
+}
+
+/// Пропускать ли обновление как повторную доставку. При ошибке базы обновление
+/// обрабатывается: лучше изредка получить дубль, чем потерять сообщение.
+pub async fn is_redelivered(db: &Db, tenant: &'static str, update_id: i32) -> bool {
+    match db.call(move |conn| seen(conn, tenant, update_id)).await {
+        Ok(seen) => seen,
+        Err(e) => {
+            log::error!("Failed to check update {}: {}", update_id, e);
+            false
+        }
+    }
+}
+
+/// Отмечает обновление обработанным, когда обработчик завершился без ошибки. Если бот упадёт
+/// раньше, Telegram пришлёт обновление снова и оно будет обработано: повторное событие
+/// из того же сообщения не даст создать `handle_new_event`.
+pub async fn mark_processed(db: &Db, tenant: &'static str, update_id: i32) {
+    if let Err(e) = db.call(move |conn| mark(conn, tenant, update_id)).await {
+        log::error!("Failed to record update {}: {}", update_id, e);
+    }
+}
@@ -0,0 +1,243 @@
+//! Напоминания голосом: вслед за текстом приходит голосовое сообщение «Напоминание: взять документы»,
+//! синтезированное внешним движком речи. Включается пользователем для всех своих событий
+//! или для отдельного события.
+
+use std::env;
+use std::process::Stdio;
+use std::sync::OnceLock;
+use std::time::Duration;
+
+use teloxide::prelude::*;
+use teloxide::types::InputFile;
+use rusqlite::{Connection, params, OptionalExtension};
+use tokio::io::AsyncWriteExt;
+
+use crate::i18n::{t, tf, Lang};
+use crate::settings::{self, Settings};
+use crate::{DatabaseError, Db, add_column_if_missing, crypto, ensure_user_exists, get_event, tenants};
+
+/// Сколько ждать синтеза одного напоминания.
+const SYNTHESIS_TIMEOUT: Duration = Duration::from_secs(30);
+/// Длинные тексты не озвучиваются целиком: голосовое в несколько минут никто не дослушает.
+const MAX_SPOKEN_CHARS: usize = 500;
+
+/// Движок речи. Оба варианта должны вернуть OGG с Opus — только такой файл Telegram
+/// показывает как голосовое сообщение.
+#[derive(Debug)]
+enum Backend {
+    /// `TTS_URL`: POST с JSON `{"text": "...", "lang": "ru"}`, в ответ — аудио.
+    Http(String),
+    /// `TTS_COMMAND`: команда оболочки, текст приходит на stdin, аудио ожидается в stdout,
+    /// язык — в переменной `TTS_LANG`. Например, `espeak-ng -v $TTS_LANG --stdout | opusenc - -`.
+    Command(String),
+}
+
+fn backend() -> Option<&'static Backend> {
+    static BACKEND: OnceLock<Option<Backend>> = OnceLock::new();
+    BACKEND.get_or_init(|| {
+        let non_empty = |key: &str| env::var(key).ok().filter(|value| !value.trim().is_empty());
+        non_empty("TTS_URL").map(Backend::Http).or_else(|| non_empty("TTS_COMMAND").map(Backend::Command))
+    }).as_ref()
+}
+
+pub fn init_tables(conn: &Connection) -> Result<(), rusqlite::Error> {
+    // Голосом по умолчанию для всех событий пользователя
+    add_column_if_missing(conn, "users", "voice_reminders", "INTEGER NOT NULL DEFAULT 0")?;
+    // У события: NULL — как у владельца, 1 — голосом, 0 — только текстом
+    add_column_if_missing(conn, "events", "voice", "INTEGER")?;
+    Ok(())
+}
+
+/// Текст для озвучивания, если напоминание о событии нужно и голосом.
+fn spoken_text(conn: &Connection, event_id: i64, lang: Lang) -> Result<Option<String>, rusqlite::Error> {
+    let found = conn.query_row(
+        "SELECT e.text, COALESCE(e.voice, u.voice_reminders) FROM events e JOIN users u ON e.user_id = u.id WHERE e.id = ?",
+        params![event_id],
+        |row| Ok((crypto::open(row.get(0)?), row.get::<_, bool>(1)?)),
+    ).optional()?;
+    Ok(found.filter(|(_, voice)| *voice).map(|(text, _)| {
+        // Пункты чек-листа читаются как перечисление, без дефисов
+        let lines = text
+            .lines()
+            .map(str::trim)
+            .map(|line| line.strip_prefix("- ").unwrap_or(line).trim())
+            .filter(|line| !line.is_empty())
+            .collect::<Vec<_>>();
+        let text = tf(lang, "voice_reminder", &[("text", &lines.join(". "))]);
+        text.chars().take(MAX_SPOKEN_CHARS).collect()
+    }))
+}
+
+async fn synthesize(backend: &Backend, text: &str, lang: Lang) -> Result<Vec<u8>, String> {
+    let work = async {
+        match backend {
+            Backend::Http(url) => {
+                let response = reqwest::Client::new()
+                    .post(url)
+                    .json(&serde_json::json!({ "text": text, "lang": lang.code() }))
+                    .send()
+                    .await
+                    .and_then(|response| response.error_for_status())
+                    .map_err(|e| e.without_url().to_string())?;
+                response.bytes().await.map(|bytes| bytes.to_vec()).map_err(|e| e.to_string())
+            }
+            Backend::Command(command) => {
+                let mut child = tokio::process::Command::new("sh")
+                    .arg("-c")
+                    .arg(command)
+                    .env("TTS_LANG", lang.code())
+                    .stdin(Stdio::piped())
+                    .stdout(Stdio::piped())
+                    .stderr(Stdio::piped())
+                    .kill_on_drop(true)
+                    .spawn()
+                    .map_err(|e| e.to_string())?;
+                if let Some(mut stdin) = child.stdin.take() {
+                    stdin.write_all(text.as_bytes()).await.map_err(|e| e.to_string())?;
+                }
+                let output = child.wait_with_output().await.map_err(|e| e.to_string())?;
+                if !output.status.success() {
+                    return Err(format!("{}: {}", output.status, String::from_utf8_lossy(&output.stderr).trim()));
+                }
+                Ok(output.stdout)
+            }
+        }
+    };
+    let audio = tokio::time::timeout(SYNTHESIS_TIMEOUT, work).await.map_err(|_| "synthesis timed out".to_string())??;
+    if audio.is_empty() {
+        return Err("empty audio".to_string());
+    }
+    Ok(audio)
+}
+
+/// Досылает голосовое к доставленному напоминанию, если оно нужно. Выполняется в фоне:
+/// текст уже доставлен, поэтому ошибки синтеза только пишутся в лог.
+pub fn send(tenant: &str, db: &Db, chat_id: i64, event_id: i64, owner_id: i64) {
+    let Some(backend) = backend() else {
+        return;
+    };
+    let (bot, db) = (tenants::bot(tenant).clone(), db.clone());
+    tokio::spawn(async move {
+        let found = db.call(move |conn| {
+            let lang = settings::resolve(conn, owner_id, chat_id)?.lang;
+            Ok(spoken_text(conn, event_id, lang)?.map(|text| (text, lang)))
+        }).await;
+        let (text, lang) = match found {
+            Ok(Some(found)) => found,
+            Ok(None) => return,
+            Err(e) => {
+                log::error!("Failed to prepare voice reminder for event {}: {}", event_id, e);
+                return;
+            }
+        };
+        let audio = match synthesize(backend, &text, lang).await {
+            Ok(audio) => audio,
+            Err(e) => {
+                log::error!("Failed to synthesize voice reminder for event {}: {}", event_id, e);
+                return;
+            }
+        };
+        if let Err(e) = bot.send_voice(ChatId(chat_id), InputFile::memory(audio).file_name("reminder.ogg")).await {
+            log::error!("Failed to send voice reminder for event {}: {}", event_id, e);
+        }
+    });
+}
+
+fn parse_switch(value: &str) -> Option<Option<bool>> {
+    match value {
+        "on" => Some(Some(true)),
+        "off" => Some(Some(false)),
+        "default" => Some(None),
+        _ => None,
+    }
+}
+
+/// `/voice on|off` — все свои напоминания голосом или только текстом; `/voice #id on|off|default` —
+/// то же для одного события, `default` возвращает настройку пользователя. Без аргументов — текущее состояние.
+pub async fn handle_voice_command(bot: &Bot, msg: &Message, db: &Db, args: &str, settings: &Settings) -> ResponseResult<()> {
+    let lang = settings.lang;
+    if backend().is_none() {
+        bot.send_message(msg.chat.id, t(lang, "voice_unavailable")).await?;
+        return Ok(());
+    }
+    let Some(user) = msg.from() else {
+        return Ok(());
+    };
+    let (telegram_id, chat_id, username, tenant) = (user.id.0 as i64, msg.chat.id.0, user.username.clone(), tenants::name_of(bot));
+    let mut parts = args.split_whitespace();
+
+    let response = match (parts.next(), parts.next()) {
+        (None, _) => {
+            let enabled = db.call(move |conn| {
+                ensure_user_exists(conn, tenant, telegram_id, username)?;
+                conn.query_row("SELECT voice_reminders FROM users WHERE telegram_id = ?", params![telegram_id], |row| row.get::<_, bool>(0))
+            }).await.map_err(DatabaseError)?;
+            t(lang, if enabled { "voice_status_on" } else { "voice_status_off" })
+        }
+        (Some(value @ ("on" | "off")), None) => {
+            let enabled = value == "on";
+            db.call(move |conn| {
+                ensure_user_exists(conn, tenant, telegram_id, username)?;
+                conn.execute("UPDATE users SET voice_reminders = ? WHERE telegram_id = ?", params![enabled, telegram_id])
+            }).await.map_err(DatabaseError)?;
+            t(lang, if enabled { "voice_enabled" } else { "voice_disabled" })
+        }
+        (Some(id), Some(value)) => match (id.trim_start_matches('#').parse::<i64>(), parse_switch(value)) {
+            (Ok(event_id), Some(voice)) => {
+                let updated = db.call(move |conn| {
+                    match get_event(conn, event_id)?.filter(|event| event.chat_id == chat_id) {
+                        Some(event) if event.owner_id == telegram_id => {
+                            conn.execute("UPDATE events SET voice = ? WHERE id = ?", params![voice, event_id])?;
+                            Ok(Ok(()))
+                        }
+                        Some(_) => Ok(Err("event_not_yours")),
+                        None => Ok(Err("event_not_found")),
+                    }
+                }).await.map_err(DatabaseError)?;
+                match updated {
+                    Ok(()) => {
+                        let key = match voice {
+                            Some(true) => "voice_event_on",
+                            Some(false) => "voice_event_off",
+                            None => "voice_event_default",
+                        };
+                        tf(lang, key, &[("id", &event_id)])
+                    }
+                    Err(key) => t(lang, key),
+                }
+            }
+            _ => t(lang, "voice_usage"),
+        },
+        _ => t(lang, "voice_usage"),
+    };
+    bot.send_message(msg.chat.id, response).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::tests::memory_db;
+    use crate::insert_event;
+
+    #[tokio::test]
+    async fn events_follow_the_owner_unless_overridden() {
+        let db = memory_db();
+        let spoken = db.call(|conn| {
+            let user_id = ensure_user_exists(conn, tenants::DEFAULT, 42, None)?;
+            let plain = insert_event(conn, tenants::DEFAULT, user_id, 42, "Взять документы", "01.01.2030 09:00")?;
+            let muted = insert_event(conn, tenants::DEFAULT, user_id, 42, "Полить цветы", "01.01.2030 10:00")?;
+            let listed = insert_event(conn, tenants::DEFAULT, user_id, 42, "Покупки\n- молоко\n- хлеб", "01.01.2030 11:00")?;
+            let before = spoken_text(conn, plain, Lang::Ru)?;
+            conn.execute("UPDATE users SET voice_reminders = 1 WHERE id = ?", params![user_id])?;
+            conn.execute("UPDATE events SET voice = 0 WHERE id = ?", params![muted])?;
+            Ok((before, spoken_text(conn, plain, Lang::Ru)?, spoken_text(conn, muted, Lang::Ru)?, spoken_text(conn, listed, Lang::Ru)?))
+        }).await.unwrap();
+        assert_eq!(spoken, (
+            None,
+            Some("Напоминание: Взять документы".to_string()),
+            None,
+            Some("Напоминание: Покупки. молоко. хлеб".to_string()),
+        ));
+    }
+}
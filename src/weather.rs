@@ -0,0 +1,387 @@
+use std::env;
+use std::time::Duration;
+
+use teloxide::prelude::*;
+use teloxide::types::{KeyboardButton, KeyboardMarkup, KeyboardRemove, Location};
+use chrono::NaiveDate;
+use rusqlite::{Connection, params, OptionalExtension};
+use serde::Deserialize;
+
+use crate::chunks;
+use crate::i18n::{t, tf, Lang};
+use crate::timezone;
+use crate::{DatabaseError, Db, add_column_if_missing, ensure_user_exists, find_event_by_message, tenants};
+
+/// Прогноз на день меняется редко, поэтому ответ провайдера переиспользуется несколько часов.
+const CACHE_HOURS: i64 = 3;
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Откуда брать прогноз: `WEATHER_PROVIDER=open-meteo` или `wttr`. Без переменной погода не показывается.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Provider {
+    OpenMeteo,
+    Wttr,
+}
+
+impl Provider {
+    fn code(self) -> &'static str {
+        match self {
+            Provider::OpenMeteo => "open-meteo",
+            Provider::Wttr => "wttr",
+        }
+    }
+
+    fn from_env() -> Option<Self> {
+        match env::var("WEATHER_PROVIDER").ok()?.to_lowercase().as_str() {
+            "open-meteo" | "openmeteo" => Some(Provider::OpenMeteo),
+            "wttr" | "wttr.in" => Some(Provider::Wttr),
+            other => {
+                log::error!("Unknown WEATHER_PROVIDER {}, weather is disabled", other);
+                None
+            }
+        }
+    }
+}
+
+/// Небо за день, сведённое из кодов погоды разных провайдеров.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Sky {
+    Clear,
+    Cloudy,
+    Fog,
+    Drizzle,
+    Rain,
+    Snow,
+    Thunder,
+}
+
+impl Sky {
+    const ALL: [Sky; 7] = [Sky::Clear, Sky::Cloudy, Sky::Fog, Sky::Drizzle, Sky::Rain, Sky::Snow, Sky::Thunder];
+
+    fn code(self) -> &'static str {
+        match self {
+            Sky::Clear => "clear",
+            Sky::Cloudy => "cloudy",
+            Sky::Fog => "fog",
+            Sky::Drizzle => "drizzle",
+            Sky::Rain => "rain",
+            Sky::Snow => "snow",
+            Sky::Thunder => "thunder",
+        }
+    }
+
+    fn parse(value: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|sky| sky.code() == value)
+    }
+
+    fn emoji(self) -> &'static str {
+        match self {
+            Sky::Clear => "☀️",
+            Sky::Cloudy => "⛅",
+            Sky::Fog => "🌫",
+            Sky::Drizzle => "🌦",
+            Sky::Rain => "🌧",
+            Sky::Snow => "❄️",
+            Sky::Thunder => "⛈",
+        }
+    }
+
+    /// Коды WMO, которые отдаёт Open-Meteo.
+    fn from_wmo(code: u32) -> Self {
+        match code {
+            0 => Sky::Clear,
+            1..=3 => Sky::Cloudy,
+            45 | 48 => Sky::Fog,
+            51..=57 => Sky::Drizzle,
+            71..=77 | 85 | 86 => Sky::Snow,
+            95..=99 => Sky::Thunder,
+            _ => Sky::Rain,
+        }
+    }
+
+    /// Коды WorldWeatherOnline, которые отдаёт wttr.in.
+    fn from_wwo(code: u32) -> Self {
+        match code {
+            113 => Sky::Clear,
+            116 | 119 | 122 => Sky::Cloudy,
+            143 | 248 | 260 => Sky::Fog,
+            176 | 263 | 266 | 281 | 284 | 293 | 296 => Sky::Drizzle,
+            179 | 227 | 230 | 317..=338 | 350 | 362..=377 | 392 | 395 => Sky::Snow,
+            200 | 386 | 389 => Sky::Thunder,
+            _ => Sky::Rain,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Forecast {
+    min: f64,
+    max: f64,
+    sky: Sky,
+}
+
+#[derive(Debug, Deserialize)]
+struct OpenMeteoResponse {
+    daily: OpenMeteoDaily,
+}
+
+#[derive(Debug, Deserialize)]
+struct OpenMeteoDaily {
+    temperature_2m_min: Vec<Option<f64>>,
+    temperature_2m_max: Vec<Option<f64>>,
+    weathercode: Vec<Option<u32>>,
+}
+
+#[derive(Debug, Deserialize)]
+struct WttrResponse {
+    weather: Vec<WttrDay>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct WttrDay {
+    date: String,
+    #[serde(rename = "mintempC")]
+    min_temp_c: String,
+    #[serde(rename = "maxtempC")]
+    max_temp_c: String,
+    hourly: Vec<WttrHour>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct WttrHour {
+    weather_code: String,
+}
+
+pub fn init_tables(conn: &Connection) -> Result<(), rusqlite::Error> {
+    // Место по умолчанию для прогноза в сводке и напоминаниях
+    add_column_if_missing(conn, "users", "weather_lat", "REAL")?;
+    add_column_if_missing(conn, "users", "weather_lon", "REAL")?;
+    // Геопозиция, присланная ответом на подтверждение события
+    add_column_if_missing(conn, "events", "location_lat", "REAL")?;
+    add_column_if_missing(conn, "events", "location_lon", "REAL")?;
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS weather_cache (
+            provider TEXT NOT NULL,
+            place TEXT NOT NULL,
+            date TEXT NOT NULL,
+            temp_min REAL NOT NULL,
+            temp_max REAL NOT NULL,
+            sky TEXT NOT NULL,
+            fetched_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            PRIMARY KEY(provider, place, date)
+        )",
+        [],
+    )?;
+    Ok(())
+}
+
+/// Место для прогноза пользователя, если он его задал через `/weather`.
+pub fn user_place(conn: &Connection, telegram_id: i64) -> Result<Option<(f64, f64)>, rusqlite::Error> {
+    conn.query_row(
+        "SELECT weather_lat, weather_lon FROM users WHERE telegram_id = ?",
+        params![telegram_id],
+        |row| Ok(row.get::<_, Option<f64>>(0)?.zip(row.get::<_, Option<f64>>(1)?)),
+    ).optional().map(Option::flatten)
+}
+
+/// Место события: прикреплённая геопозиция, иначе место владельца по умолчанию.
+pub fn event_place(conn: &Connection, event_id: i64) -> Result<Option<(f64, f64)>, rusqlite::Error> {
+    let place = conn.query_row(
+        "SELECT COALESCE(e.location_lat, u.weather_lat), COALESCE(e.location_lon, u.weather_lon)
+         FROM events e JOIN users u ON e.user_id = u.id WHERE e.id = ?",
+        params![event_id],
+        |row| Ok(row.get::<_, Option<f64>>(0)?.zip(row.get::<_, Option<f64>>(1)?)),
+    ).optional()?;
+    Ok(place.flatten())
+}
+
+fn set_user_place(conn: &Connection, telegram_id: i64, place: Option<(f64, f64)>) -> Result<(), rusqlite::Error> {
+    conn.execute(
+        "UPDATE users SET weather_lat = ?, weather_lon = ?, awaiting_location = NULL WHERE telegram_id = ?",
+        params![place.map(|(lat, _)| lat), place.map(|(_, lon)| lon), telegram_id],
+    )?;
+    Ok(())
+}
+
+/// Ключ кэша: координаты с точностью около километра, чтобы соседние места делили прогноз.
+fn place_key((lat, lon): (f64, f64)) -> String {
+    format!("{:.2},{:.2}", lat, lon)
+}
+
+fn cached_forecast(conn: &Connection, provider: Provider, place: &str, date: NaiveDate) -> Result<Option<Forecast>, rusqlite::Error> {
+    let cached: Option<(f64, f64, String)> = conn.query_row(
+        "SELECT temp_min, temp_max, sky FROM weather_cache
+         WHERE provider = ? AND place = ? AND date = ? AND fetched_at > datetime('now', ?)",
+        params![provider.code(), place, date.to_string(), format!("-{} hours", CACHE_HOURS)],
+        |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+    ).optional()?;
+    Ok(cached.and_then(|(min, max, sky)| Some(Forecast { min, max, sky: Sky::parse(&sky)? })))
+}
+
+fn save_forecast(conn: &Connection, provider: Provider, place: &str, date: NaiveDate, forecast: Forecast) -> Result<(), rusqlite::Error> {
+    conn.execute(
+        "INSERT OR REPLACE INTO weather_cache (provider, place, date, temp_min, temp_max, sky) VALUES (?, ?, ?, ?, ?, ?)",
+        params![provider.code(), place, date.to_string(), forecast.min, forecast.max, forecast.sky.code()],
+    )?;
+    // Прошедшие дни больше не понадобятся
+    conn.execute("DELETE FROM weather_cache WHERE date < date('now', '-1 day')", [])?;
+    Ok(())
+}
+
+async fn fetch_forecast(provider: Provider, (lat, lon): (f64, f64), date: NaiveDate) -> Result<Option<Forecast>, reqwest::Error> {
+    let client = reqwest::Client::new();
+    match provider {
+        Provider::OpenMeteo => {
+            let response: OpenMeteoResponse = client
+                .get("https://api.open-meteo.com/v1/forecast")
+                .query(&[
+                    ("latitude", lat.to_string()),
+                    ("longitude", lon.to_string()),
+                    ("daily", "temperature_2m_min,temperature_2m_max,weathercode".to_string()),
+                    ("timezone", "auto".to_string()),
+                    ("start_date", date.to_string()),
+                    ("end_date", date.to_string()),
+                ])
+                .timeout(REQUEST_TIMEOUT)
+                .send()
+                .await?
+                .error_for_status()?
+                .json()
+                .await?;
+            let daily = response.daily;
+            Ok(daily.temperature_2m_min.first().copied().flatten()
+                .zip(daily.temperature_2m_max.first().copied().flatten())
+                .zip(daily.weathercode.first().copied().flatten())
+                .map(|((min, max), code)| Forecast { min, max, sky: Sky::from_wmo(code) }))
+        }
+        Provider::Wttr => {
+            let response: WttrResponse = client
+                .get(format!("https://wttr.in/{},{}", lat, lon))
+                .query(&[("format", "j1")])
+                .timeout(REQUEST_TIMEOUT)
+                .send()
+                .await?
+                .error_for_status()?
+                .json()
+                .await?;
+            // Погода дня — по полуденному интервалу из восьми трёхчасовых
+            Ok(response.weather.into_iter().find(|day| day.date == date.to_string()).and_then(|day| {
+                let code = day.hourly.get(4).or(day.hourly.first())?.weather_code.parse().ok()?;
+                Some(Forecast { min: day.min_temp_c.parse().ok()?, max: day.max_temp_c.parse().ok()?, sky: Sky::from_wwo(code) })
+            }))
+        }
+    }
+}
+
+fn describe(lang: Lang, forecast: Forecast) -> String {
+    tf(lang, "weather_line", &[
+        ("emoji", &forecast.sky.emoji()),
+        ("min", &format!("{:+.0}", forecast.min)),
+        ("max", &format!("{:+.0}", forecast.max)),
+        ("sky", &t(lang, &format!("weather_{}", forecast.sky.code()))),
+    ])
+}
+
+/// Строка прогноза на день для места; `None`, если погода выключена или провайдер не ответил.
+pub async fn forecast_line(db: &Db, place: (f64, f64), date: NaiveDate, lang: Lang) -> Option<String> {
+    let provider = Provider::from_env()?;
+    let key = place_key(place);
+
+    let cache_key = key.clone();
+    match db.call(move |conn| cached_forecast(conn, provider, &cache_key, date)).await {
+        Ok(Some(forecast)) => return Some(describe(lang, forecast)),
+        Ok(None) => {}
+        Err(e) => log::error!("Failed to read weather cache: {}", e),
+    }
+
+    let forecast = match fetch_forecast(provider, place, date).await {
+        Ok(forecast) => forecast?,
+        Err(e) => {
+            log::error!("Failed to fetch weather from {}: {}", provider.code(), e);
+            return None;
+        }
+    };
+    if let Err(e) = db.call(move |conn| save_forecast(conn, provider, &key, date, forecast)).await {
+        log::error!("Failed to cache weather: {}", e);
+    }
+    Some(describe(lang, forecast))
+}
+
+/// `/weather` просит геопозицию для прогноза, `/weather off` убирает прогноз из сводки и напоминаний.
+pub async fn handle_weather_command(bot: &Bot, msg: &Message, db: &Db, args: &str, lang: Lang) -> ResponseResult<()> {
+    let Some(user) = msg.from() else {
+        return Ok(());
+    };
+    if Provider::from_env().is_none() {
+        bot.send_message(msg.chat.id, t(lang, "weather_unavailable")).await?;
+        return Ok(());
+    }
+    let (telegram_id, username, tenant) = (user.id.0 as i64, user.username.clone(), tenants::name_of(bot));
+    db.call(move |conn| ensure_user_exists(conn, tenant, telegram_id, username)).await.map_err(DatabaseError)?;
+
+    if args == "off" {
+        db.call(move |conn| set_user_place(conn, telegram_id, None)).await.map_err(DatabaseError)?;
+        bot.send_message(msg.chat.id, t(lang, "weather_off")).await?;
+        return Ok(());
+    }
+    // Кнопка запроса геопозиции работает только в личном чате
+    if !msg.chat.is_private() {
+        bot.send_message(msg.chat.id, t(lang, "weather_usage")).await?;
+        return Ok(());
+    }
+
+    db.call(move |conn| timezone::set_awaiting_location(conn, telegram_id, Some("weather"))).await.map_err(DatabaseError)?;
+    let keyboard = KeyboardMarkup::new(vec![vec![KeyboardButton::new(t(lang, "timezone_share")).request(
+        teloxide::types::ButtonRequest::Location,
+    )]])
+    .resize_keyboard(true)
+    .one_time_keyboard(true);
+    bot.send_message(msg.chat.id, t(lang, "weather_prompt")).reply_markup(keyboard).await?;
+    Ok(())
+}
+
+/// Геопозиция, присланная после `/weather`.
+pub async fn save_location(bot: &Bot, msg: &Message, db: &Db, location: &Location, lang: Lang) -> ResponseResult<()> {
+    let Some(user) = msg.from() else {
+        return Ok(());
+    };
+    let (telegram_id, place) = (user.id.0 as i64, (location.latitude, location.longitude));
+    db.call(move |conn| set_user_place(conn, telegram_id, Some(place))).await.map_err(DatabaseError)?;
+    bot.send_message(msg.chat.id, t(lang, "weather_saved"))
+        .reply_markup(KeyboardRemove::new())
+        .await?;
+    Ok(())
+}
+
+/// Геопозиция ответом на подтверждение события прикрепляется к событию. Возвращает `false`,
+/// если сообщение не ответ на подтверждение, и геопозицию нужно обработать как обычно.
+pub async fn attach_to_event(bot: &Bot, msg: &Message, db: &Db, location: &Location, lang: Lang) -> ResponseResult<bool> {
+    let (Some(reply), Some(user)) = (msg.reply_to_message(), msg.from()) else {
+        return Ok(false);
+    };
+    let (chat_id, message_id, telegram_id) = (msg.chat.id.0, reply.id.0, user.id.0 as i64);
+    let (lat, lon) = (location.latitude, location.longitude);
+    let attached = db.call(move |conn| {
+        let Some(event_id) = find_event_by_message(conn, chat_id, message_id, "confirmation")? else {
+            return Ok(None);
+        };
+        // Место события может задать только его владелец
+        let updated = conn.execute(
+            "UPDATE events SET location_lat = ?, location_lon = ?
+             WHERE id = ? AND user_id = (SELECT id FROM users WHERE telegram_id = ?)",
+            params![lat, lon, event_id, telegram_id],
+        )?;
+        Ok(Some(updated > 0))
+    }).await.map_err(DatabaseError)?;
+
+    match attached {
+        None => Ok(false),
+        Some(attached) => {
+            let key = if attached { "weather_event_location_saved" } else { "event_not_yours" };
+            chunks::send(bot, msg.chat.id, t(lang, key)).await?;
+            Ok(true)
+        }
+    }
+}